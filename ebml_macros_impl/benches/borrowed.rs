@@ -0,0 +1,59 @@
+//! Compares the owned and borrowed readers on a document of large binary payloads. Run with
+//! `cargo bench -p ebml_macros_impl --bench borrowed`.
+
+extern crate chrono;
+#[macro_use]
+extern crate criterion;
+extern crate ebml;
+extern crate ebml_macros;
+#[macro_use]
+extern crate ebml_macros_impl;
+
+use criterion::{black_box, Criterion, Throughput};
+
+ebml_dtd!(#[ebml(borrowed)] media, r#"
+    define elements {
+        Media := 4001 container [ card:1; ] {
+            Frame := 4102 container [ card:*; ] {
+                Timecode := 4201 uint [ card:1; ]
+                Data := 4202 binary [ card:1; ]
+            }
+        }
+    }
+"#);
+
+const FRAMES: usize = 256;
+const FRAME_LEN: usize = 64 * 1024;
+
+// The body of a `Media` element holding `FRAMES` frames of `FRAME_LEN` bytes each.
+fn fixture() -> Vec<u8> {
+    let mut frame = vec![0x42, 0x01, 0x81, 0x00, 0x42, 0x02, 0x10];
+    frame.extend_from_slice(&(FRAME_LEN as u32).to_be_bytes()[1..]);
+    frame.resize(frame.len() + FRAME_LEN, 0xAB);
+
+    let mut body = Vec::new();
+    for _ in 0..FRAMES {
+        body.extend_from_slice(&[0x41, 0x02, 0x10]);
+        body.extend_from_slice(&(frame.len() as u32).to_be_bytes()[1..]);
+        body.extend_from_slice(&frame);
+    }
+    body
+}
+
+fn borrowed(c: &mut Criterion) {
+    let body = fixture();
+    let mut group = c.benchmark_group("borrowed");
+    group.throughput(Throughput::Bytes(body.len() as u64));
+    group.bench_function("owned", |b| b.iter(|| {
+        let media = media::Media::read_from(&mut black_box(&body[..]), body.len() as u64);
+        assert_eq!(media.unwrap().frame.len(), FRAMES);
+    }));
+    group.bench_function("borrowed", |b| b.iter(|| {
+        let media = media::borrowed::Media::read_from(black_box(&body));
+        assert_eq!(media.unwrap().frame.len(), FRAMES);
+    }));
+    group.finish();
+}
+
+criterion_group!(benches, borrowed);
+criterion_main!(benches);
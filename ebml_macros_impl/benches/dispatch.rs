@@ -0,0 +1,75 @@
+//! Measures how elements are told apart by their ids in a schema with hundreds of elements, on a
+//! log of many small entries. Run with `cargo bench -p ebml_macros_impl --bench dispatch`.
+
+extern crate chrono;
+#[macro_use]
+extern crate criterion;
+extern crate ebml;
+extern crate ebml_macros;
+#[macro_use]
+extern crate ebml_macros_impl;
+
+use criterion::{black_box, Criterion, Throughput};
+use ebml::Id;
+
+use wide::ElementId;
+
+include_ebml_dtd!(wide, "tests/schemas/wide.edtd");
+
+const ENTRIES: usize = 10_000;
+
+// The body of a `Log` holding `ENTRIES` entries, each a few one byte elements.
+fn fixture() -> Vec<u8> {
+    let entry = [0x81, 0x81, 0x02, 0x82, 0x81, 0xFF, 0x84, 0x81, 0x01, 0x84, 0x81, 0x04];
+    let mut body = Vec::new();
+    for _ in 0..ENTRIES {
+        body.extend_from_slice(&[0x1F, 0x43, 0xB6, 0x75, 0x80 | entry.len() as u8]);
+        body.extend_from_slice(&entry);
+    }
+    body
+}
+
+// Every element of the schema by its encoded id, in the order it declares them.
+fn elements() -> Vec<(u32, ElementId)> {
+    let tables = (0..60).flat_map(|table| {
+        let columns = (0..4).map(move |column| 0x4100 + 4 * table + column);
+        Some(0x1B00_0000 + table).into_iter().chain(columns)
+    });
+    tables.chain(vec![0x1A45_DFA3, 0x1F43_B675, 0x81, 0x82, 0x83, 0x84])
+        .map(|encoded| (encoded, ElementId::from_encoded(encoded).unwrap()))
+        .collect()
+}
+
+fn dispatch(c: &mut Criterion) {
+    let body = fixture();
+    let mut group = c.benchmark_group("read");
+    group.throughput(Throughput::Bytes(body.len() as u64));
+    group.bench_function("log", |b| b.iter(|| {
+        wide::Log::read_from(&mut &body[..], body.len() as u64).unwrap().entry.len()
+    }));
+    group.finish();
+
+    // Looking up the children of an entry, which come after every table in the schema. `chain`
+    // compares each id with those of the elements in turn.
+    let children = [wide::ids::LEVEL, wide::ids::CODE, wide::ids::SOURCE, wide::ids::FLAGS];
+    let ids = children.map(|encoded| Id::from_encoded(encoded).unwrap());
+    let elements = elements();
+    let mut group = c.benchmark_group("lookup");
+    group.bench_function("chain", |b| b.iter(|| {
+        ids.map(|id| {
+            elements.iter()
+                .find(|&&(encoded, _)| Id::from_encoded(encoded) == Some(black_box(id)))
+                .map_or(ElementId::Unknown(id), |&(_, element)| element)
+        })
+    }));
+    group.bench_function("from_id", |b| b.iter(|| {
+        ids.map(|id| ElementId::from_id(black_box(id)))
+    }));
+    group.bench_function("from_encoded", |b| b.iter(|| {
+        children.map(|encoded| ElementId::from_encoded(black_box(encoded)))
+    }));
+    group.finish();
+}
+
+criterion_group!(benches, dispatch);
+criterion_main!(benches);
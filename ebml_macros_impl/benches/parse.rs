@@ -0,0 +1,34 @@
+//! Measures parsing EDTDs of different sizes, and how much comments cost. Run with
+//! `cargo bench -p ebml_macros_impl --bench parse`.
+
+#[macro_use]
+extern crate criterion;
+extern crate ebml_macros;
+
+use criterion::{black_box, Criterion, Throughput};
+use ebml_macros::parse_dtd;
+
+const SMALL: &str = include_str!("../../tests/dtd0");
+const MATROSKA: &str = include_str!("../tests/schemas/matroska_full.edtd");
+
+// The Matroska schema with a line comment and a block comment before every line of it.
+fn commented() -> String {
+    MATROSKA.lines()
+        .map(|line| format!("// {}\n/* {} */\n{}\n", line.trim(), line.len(), line))
+        .collect()
+}
+
+fn parse(c: &mut Criterion) {
+    let commented = commented();
+    let mut group = c.benchmark_group("parse");
+    for &(name, text) in &[("small", SMALL), ("matroska", MATROSKA), ("comments", &commented)] {
+        group.throughput(Throughput::Bytes(text.len() as u64));
+        group.bench_function(name, |b| b.iter(|| {
+            parse_dtd(black_box(text), &Default::default()).unwrap()
+        }));
+    }
+    group.finish();
+}
+
+criterion_group!(benches, parse);
+criterion_main!(benches);
@@ -0,0 +1,90 @@
+// Generates code for the `generated` test with `ebml_macros::codegen::generate_to_file`, the way a
+// crate using it from its own build script would, and the values the `tokens` test rebuilds.
+
+extern crate ebml_macros;
+#[macro_use]
+extern crate quote;
+
+use std::env;
+use std::fmt::Debug;
+use std::fs;
+use std::path::Path;
+
+use ebml_macros::codegen::{generate_to_file, CodegenOptions};
+use ebml_macros::runtime::{date_from_raw, DefaultValue, ElementType, LevelRange, RawId};
+use ebml_macros::{parse_dtd, BinaryRangeItem, Cardinality, DateRangeItem, FloatRangeItem,
+                  IntRangeItem, ParseOptions, Severity, SizeList, StringRangeItem, UintRangeItem};
+use quote::{ToTokens, Tokens};
+
+fn main() {
+    let schema = "tests/schemas/basic.edtd";
+    println!("cargo:rerun-if-changed={}", schema);
+
+    let text = fs::read(schema).unwrap();
+    let options = ParseOptions { validate: Some(Severity::Error), ..Default::default() };
+    let dtd = parse_dtd(&text, &options).unwrap();
+
+    let out = env::var_os("OUT_DIR").unwrap();
+    let out = Path::new(&out);
+    generate_to_file(&dtd, "basic", &CodegenOptions::default(), out.join("basic.rs")).unwrap();
+    fs::write(out.join("tokens.rs"), quoted_values().as_str()).unwrap();
+}
+
+// A test checking that values quoted with `quote::ToTokens` are rebuilt as they were, by
+// comparing their `Debug` output. Those which can be are also rebuilt in constants.
+fn quoted_values() -> Tokens {
+    let (values, printed): (Vec<_>, Vec<_>) = vec![
+        quoted(Cardinality::ZeroOrOne),
+        quoted(Cardinality::OneOrMany),
+        quoted(LevelRange { start: 1, end: Some(2) }),
+        quoted(LevelRange { start: 0, end: None }),
+        quoted(IntRangeItem::To { end: i64::MIN }),
+        quoted(IntRangeItem::Bounded { start: -3, end: 7 }),
+        quoted(UintRangeItem::Single(u64::MAX)),
+        quoted(UintRangeItem::From { start: 4 }),
+        quoted(FloatRangeItem::Bounded {
+            start: -0.5,
+            include_start: false,
+            end: 1e300,
+            include_end: true,
+        }),
+        quoted(DateRangeItem::From { start: date_from_raw(-1_000_000_123) }),
+        quoted(StringRangeItem::Bounded { start: 0x20, end: 0x10_FFFF }),
+        quoted(BinaryRangeItem::Single(0xFF)),
+        quoted(DefaultValue::Float(0.1)),
+        quoted(DefaultValue::String("a \"quoted\" string")),
+        quoted(ElementType::Container),
+    ].into_iter().unzip();
+
+    let (cardinality, cardinality_printed) = quoted(Cardinality::ExactlyOne);
+    let (level, level_printed) = quoted(LevelRange { start: 1, end: None });
+    let (sizes, sizes_printed) = quoted(SizeList::from_static(&[
+        UintRangeItem::Single(4),
+        UintRangeItem::Bounded { start: 8, end: 16 },
+    ]));
+    let (default, default_printed) = quoted(DefaultValue::Binary(&[0, 1, 0xFF]));
+    let (id, id_printed) = quoted(RawId(0x1853_8067));
+
+    quote! {
+        const CARDINALITY: ::ebml_macros::Cardinality = #cardinality;
+        const LEVEL: ::ebml_macros::runtime::LevelRange = #level;
+        const SIZES: ::ebml_macros::SizeList = #sizes;
+        const DEFAULT: ::ebml_macros::runtime::DefaultValue<'static> = #default;
+        const ID: ::ebml_macros::runtime::RawId = #id;
+
+        #[test]
+        fn test_quoted_values() {
+            #(assert_eq!(format!("{:?}", #values), #printed);)*
+            assert_eq!(format!("{:?}", CARDINALITY), #cardinality_printed);
+            assert_eq!(format!("{:?}", LEVEL), #level_printed);
+            assert_eq!(format!("{:?}", SIZES), #sizes_printed);
+            assert_eq!(format!("{:?}", DEFAULT), #default_printed);
+            assert_eq!(format!("{:?}", ID), #id_printed);
+        }
+    }
+}
+
+// An expression rebuilding `value`, and its `Debug` output.
+fn quoted<T: Debug + ToTokens>(value: T) -> (Tokens, String) {
+    (quote!(#value), format!("{:?}", value))
+}
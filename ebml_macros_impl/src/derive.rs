@@ -0,0 +1,328 @@
+//! Expands `#[derive(EbmlRead)]` and `#[derive(EbmlWrite)]`, parsing the struct and its
+//! `#[ebml(...)]` attributes by hand.
+
+use ebml_macros::{Dtd, ParseOptions, Severity, SourceMap};
+use ebml_macros::codegen::{self, DeriveError, DeriveField, DeriveInput, Wrapper};
+use proc_macro::{Delimiter, Group, Spacing, Span, TokenStream, TokenTree};
+
+use {describe_error, literal, read_schema};
+
+/// Which trait is being derived.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Derive {
+    Read,
+    Write,
+}
+
+// A field, with the tokens of its type and where to report problems with it.
+struct Field {
+    input: DeriveField,
+    type_: Vec<TokenTree>,
+    // The tokens of the type inside an `Option` or `Vec`.
+    value_type: Vec<TokenTree>,
+    element_span: Span,
+}
+
+pub fn expand(input: TokenStream, derive: Derive) -> Result<TokenStream, (String, Span)> {
+    let mut tokens = input.into_iter().peekable();
+
+    // Outer attributes, then the visibility.
+    let mut settings = Vec::new();
+    while let Some(TokenTree::Punct(punct)) = tokens.peek() {
+        if punct.as_char() != '#' {
+            break;
+        }
+        tokens.next();
+        if let Some(TokenTree::Group(group)) = tokens.next() {
+            settings.extend(parse_attribute(&group)?);
+        }
+    }
+    skip_visibility(&mut tokens);
+
+    let usage = "only structs with named fields can derive `EbmlRead` and `EbmlWrite`";
+    match tokens.next() {
+        Some(TokenTree::Ident(ref ident)) if ident.to_string() == "struct" => {}
+        Some(other) => return Err((usage.to_string(), other.span())),
+        None => return Err((usage.to_string(), Span::call_site())),
+    }
+    let name = match tokens.next() {
+        Some(TokenTree::Ident(ident)) => ident,
+        Some(other) => return Err((usage.to_string(), other.span())),
+        None => return Err((usage.to_string(), Span::call_site())),
+    };
+    let body = match tokens.next() {
+        Some(TokenTree::Group(ref group)) if group.delimiter() == Delimiter::Brace => {
+            group.stream()
+        }
+        Some(TokenTree::Punct(ref punct)) if punct.as_char() == '<' => {
+            return Err(("generic structs can't derive `EbmlRead` or `EbmlWrite`".to_string(),
+                        punct.span()));
+        }
+        Some(other) => return Err((usage.to_string(), other.span())),
+        None => return Err((usage.to_string(), name.span())),
+    };
+
+    let (mut schema, mut element) = (None, None);
+    for (setting, span, value, value_span) in settings {
+        match setting.as_str() {
+            "schema" => schema = Some((value, value_span)),
+            "element" => element = Some(value),
+            _ => return Err((format!("unknown setting `{}`", setting), span)),
+        }
+    }
+    let (schema, element) = match (schema, element) {
+        (Some(schema), Some(element)) => (schema, element),
+        _ => {
+            let message = "expected `#[ebml(schema = \"...\", element = \"...\")]` on the struct";
+            return Err((message.to_string(), name.span()));
+        }
+    };
+
+    let fields = parse_fields(body)?;
+    let input = DeriveInput {
+        name: name.to_string(),
+        element,
+        fields: fields.iter().map(|field| field.input.clone()).collect(),
+    };
+
+    let (path, path_span) = schema;
+    let (text, tracker) = read_schema(&path, path_span)?;
+    let dtd = parse(&text, &path).map_err(|message| (message, path_span))?;
+    let code = match derive {
+        Derive::Read => codegen::derive_read(&dtd, &input),
+        Derive::Write => codegen::derive_write(&dtd, &input),
+    };
+    let code = code.map_err(|err| {
+        let span = match (err.field().map(|index| &fields[index]), &err) {
+            (Some(field), &DeriveError::WrongWrapper { .. }) => field.type_[0].span(),
+            (Some(field), _) => field.element_span,
+            (None, &DeriveError::UnknownContainer(_)) => path_span,
+            (None, _) => name.span(),
+        };
+        (err.to_string(), span)
+    })?;
+
+    let tokens = code.parse::<TokenStream>()
+        .map_err(|_| (format!("generated invalid code: {}", code), name.span()))?;
+    let mut tokens = with_field_types(tokens, &fields);
+    tokens.extend(tracker.parse::<TokenStream>().unwrap());
+    Ok(tokens)
+}
+
+fn parse<'t>(text: &'t str, path: &str) -> Result<Dtd<'t>, String> {
+    let options = ParseOptions { validate: Some(Severity::Error), ..Default::default() };
+    ebml_macros::parse_dtd(text, &options).map_err(|err| {
+        describe_error(path, err, |offset| {
+            let (line, column) = SourceMap::new(text).line_column(offset);
+            format!("line {}, column {}", line, column)
+        })
+    })
+}
+
+// Skips `pub`, `pub(crate)` and the like.
+fn skip_visibility<I: Iterator<Item = TokenTree>>(tokens: &mut ::std::iter::Peekable<I>) {
+    if let Some(TokenTree::Ident(ident)) = tokens.peek() {
+        if ident.to_string() != "pub" {
+            return;
+        }
+    } else {
+        return;
+    }
+    tokens.next();
+    if let Some(TokenTree::Group(group)) = tokens.peek() {
+        if group.delimiter() == Delimiter::Parenthesis {
+            tokens.next();
+        }
+    }
+}
+
+// A setting's name and value, each with its span.
+type Setting = (String, Span, String, Span);
+
+// The settings of an `#[ebml(...)]` attribute, given the group after the `#`. Other attributes
+// have none.
+fn parse_attribute(group: &Group) -> Result<Vec<Setting>, (String, Span)> {
+    let mut tokens = group.stream().into_iter();
+    match tokens.next() {
+        Some(TokenTree::Ident(ref ident)) if ident.to_string() == "ebml" => {}
+        _ => return Ok(Vec::new()),
+    }
+    let settings = match tokens.next() {
+        Some(TokenTree::Group(ref group)) if group.delimiter() == Delimiter::Parenthesis => {
+            group.stream()
+        }
+        Some(other) => return Err(("expected `(` after `ebml`".to_string(), other.span())),
+        None => return Err(("expected `(` after `ebml`".to_string(), group.span())),
+    };
+
+    let usage = "expected a setting like `element = \"Title\"`";
+    let mut tokens = settings.into_iter();
+    let mut out = Vec::new();
+    loop {
+        let name = match tokens.next() {
+            Some(TokenTree::Ident(ident)) => ident,
+            Some(other) => return Err((usage.to_string(), other.span())),
+            None => return Ok(out),
+        };
+        match tokens.next() {
+            Some(TokenTree::Punct(ref punct)) if punct.as_char() == '=' => {}
+            _ => return Err((usage.to_string(), name.span())),
+        }
+        let (value, value_span) = match tokens.next() {
+            Some(TokenTree::Literal(lit)) => match literal::string_value(&lit.to_string()) {
+                Some(value) => (value, lit.span()),
+                None => return Err((usage.to_string(), lit.span())),
+            },
+            _ => return Err((usage.to_string(), name.span())),
+        };
+        out.push((name.to_string(), name.span(), value, value_span));
+        match tokens.next() {
+            Some(TokenTree::Punct(ref punct)) if punct.as_char() == ',' => {}
+            Some(other) => return Err(("expected `,`".to_string(), other.span())),
+            None => return Ok(out),
+        }
+    }
+}
+
+// Fields are attributes, a visibility, a name, `:` and a type, separated by commas which aren't
+// inside the type's angle brackets.
+fn parse_fields(body: TokenStream) -> Result<Vec<Field>, (String, Span)> {
+    let mut tokens = body.into_iter().peekable();
+    let mut fields = Vec::new();
+    loop {
+        let mut element = None;
+        while let Some(TokenTree::Punct(punct)) = tokens.peek() {
+            if punct.as_char() != '#' {
+                break;
+            }
+            tokens.next();
+            if let Some(TokenTree::Group(group)) = tokens.next() {
+                for (setting, span, value, value_span) in parse_attribute(&group)? {
+                    if setting != "element" {
+                        return Err((format!("unknown setting `{}`", setting), span));
+                    }
+                    element = Some((value, value_span));
+                }
+            }
+        }
+        skip_visibility(&mut tokens);
+
+        let name = match tokens.next() {
+            Some(TokenTree::Ident(ident)) => ident,
+            Some(other) => return Err(("expected a field".to_string(), other.span())),
+            None => return Ok(fields),
+        };
+        match tokens.next() {
+            Some(TokenTree::Punct(ref punct)) if punct.as_char() == ':' => {}
+            _ => return Err(("expected `:` after the field's name".to_string(), name.span())),
+        }
+        let mut type_ = Vec::new();
+        let mut depth = 0;
+        for token in tokens.by_ref() {
+            if let TokenTree::Punct(ref punct) = token {
+                match punct.as_char() {
+                    ',' if depth == 0 => break,
+                    '<' => depth += 1,
+                    '>' => depth -= 1,
+                    _ => {}
+                }
+            }
+            type_.push(token);
+        }
+        if type_.is_empty() {
+            return Err(("expected the field's type".to_string(), name.span()));
+        }
+
+        let (element, element_span) = element.ok_or_else(|| {
+            ("expected `#[ebml(element = \"...\")]` on the field".to_string(), name.span())
+        })?;
+        let wrapped = wrapped(&type_);
+        let index = fields.len();
+        let input = DeriveField {
+            name: name.to_string(),
+            element,
+            type_: placeholder("type", index),
+            wrapped: wrapped.as_ref().map(|&(wrapper, _)| (wrapper, placeholder("value", index))),
+        };
+        let value_type = wrapped.map_or_else(Vec::new, |(_, value_type)| value_type);
+        fields.push(Field { input, type_, value_type, element_span });
+    }
+}
+
+// If `type_` is an `Option` or a `Vec`, which one it is and the type inside it.
+fn wrapped(type_: &[TokenTree]) -> Option<(Wrapper, Vec<TokenTree>)> {
+    let open = type_.iter().position(|token| match *token {
+        TokenTree::Punct(ref punct) => punct.as_char() == '<',
+        _ => false,
+    })?;
+    let wrapper = match type_[..open].last() {
+        Some(TokenTree::Ident(ident)) if ident.to_string() == "Option" => Wrapper::Option,
+        Some(TokenTree::Ident(ident)) if ident.to_string() == "Vec" => Wrapper::Vec,
+        _ => return None,
+    };
+    match type_.last() {
+        Some(TokenTree::Punct(punct)) if punct.as_char() == '>' => {}
+        _ => return None,
+    }
+    Some((wrapper, type_[open + 1..type_.len() - 1].to_vec()))
+}
+
+// The generated code refers to field types by these placeholders, which are replaced with the
+// tokens from the struct afterwards. That way problems with a type are reported where it was
+// written.
+fn placeholder(kind: &str, index: usize) -> String {
+    format!("__ebml_{}_{}", kind, index)
+}
+
+// Bounds on a placeholder, from the `:` after it up to the next comma, are given the span of the
+// type too, since that's where problems with them are reported.
+fn with_field_types(tokens: TokenStream, fields: &[Field]) -> TokenStream {
+    let mut out = Vec::new();
+    // The span of the latest type, and whether its bound has started.
+    let mut bound: Option<(Span, bool)> = None;
+    for mut token in tokens {
+        bound = match (bound, &token) {
+            (Some((span, false)), TokenTree::Punct(punct))
+                if punct.as_char() == ':' && punct.spacing() == Spacing::Alone =>
+            {
+                Some((span, true))
+            }
+            (Some((_, true)), TokenTree::Punct(punct)) if punct.as_char() == ',' => None,
+            (Some((span, true)), _) => Some((span, true)),
+            _ => None,
+        };
+        if let Some((span, true)) = bound {
+            token.set_span(span);
+        }
+
+        match token {
+            TokenTree::Ident(ref ident) if ident.to_string().starts_with("__ebml_") => {
+                let name = ident.to_string();
+                let type_ = fields.iter().enumerate().find_map(|(index, field)| {
+                    if name == placeholder("type", index) {
+                        Some(&field.type_)
+                    } else if name == placeholder("value", index) {
+                        Some(&field.value_type)
+                    } else {
+                        None
+                    }
+                });
+                match type_ {
+                    Some(type_) => {
+                        out.extend(type_.iter().cloned());
+                        bound = type_.first().map(|token| (token.span(), false));
+                    }
+                    None => out.push(token.clone()),
+                }
+            }
+            TokenTree::Group(group) => {
+                let stream = with_field_types(group.stream(), fields);
+                let mut replaced = Group::new(group.delimiter(), stream);
+                replaced.set_span(group.span());
+                out.push(TokenTree::Group(replaced));
+            }
+            other => out.push(other),
+        }
+    }
+    out.into_iter().collect()
+}
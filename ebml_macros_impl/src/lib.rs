@@ -0,0 +1,488 @@
+#![deny(missing_docs, missing_debug_implementations,
+        trivial_casts, unsafe_code)]
+
+//! Procedural macros which generate code from an EDTD at compile time, using `ebml_macros`.
+//!
+//! ```ignore
+//! #[macro_use]
+//! extern crate ebml_macros_impl;
+//!
+//! ebml_dtd!(matroska, r#"
+//!     declare header {
+//!         DocType := "matroska";
+//!     }
+//!     define elements {
+//!         Segment := 18538067 container;
+//!     }
+//! "#);
+//!
+//! assert_eq!(matroska::ids::SEGMENT, 0x18538067);
+//!
+//! // Paths are relative to the directory containing your Cargo.toml.
+//! include_ebml_dtd!(webm, "schemas/webm.edtd");
+//!
+//! // A schema may be split across several files, which are merged before generating code.
+//! include_ebml_dtd!(webm_split, "schemas/ebml_header.edtd", "schemas/webm_body.edtd");
+//!
+//! // Attributes before the module name configure the generated code. Here, `date` elements are
+//! // kept as nanoseconds since 2001 rather than converted to `chrono::NaiveDateTime`.
+//! include_ebml_dtd!(#[ebml(date = "i64")] raw_webm, "schemas/webm.edtd");
+//!
+//! // An overlay derives a schema from another, removing elements and refining their properties.
+//! include_ebml_dtd!(#[ebml(overlay = "schemas/webm.overlay")] webm_only, "schemas/matroska.edtd");
+//!
+//! // Builders check that mandatory elements without defaults have been set.
+//! include_ebml_dtd!(#[ebml(builders)] built_webm, "schemas/webm.edtd");
+//! let info = built_webm::Info::builder()
+//!     .title("Big Buck Bunny")
+//!     .muxing_app("libebml")
+//!     .writing_app("mkvmerge")
+//!     .build()?;
+//!
+//! // Structs laid out by hand can be read and written as a container in a schema too.
+//! #[derive(EbmlRead, EbmlWrite)]
+//! #[ebml(schema = "schemas/webm.edtd", element = "Info")]
+//! struct Info {
+//!     #[ebml(element = "Title")]
+//!     title: Option<String>,
+//! }
+//! ```
+
+extern crate ebml_macros;
+extern crate proc_macro;
+
+mod derive;
+mod literal;
+mod options;
+
+use std::env;
+use std::fs::File;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+use ebml_macros::{DtdError, DtdFiles, IncludeError, ParseOptions, Severity, SourceMap};
+use ebml_macros::codegen::CodegenOptions;
+use proc_macro::{Group, Span, TokenStream, TokenTree};
+
+/// Generates a module from an EDTD given inline as a string literal.
+///
+/// The first argument is the name of the module to generate, and the second is the text of the
+/// EDTD. The name may be left out if the EDTD declares a DocType, in which case the module is
+/// named after it in `snake_case`. The EDTD is parsed and validated at compile time; any
+/// problems are reported as compile errors.
+///
+/// Names are converted deterministically, as described by `ebml_macros::codegen::generate`:
+/// keywords become raw identifiers like `r#type`, or get an underscore appended if they can't be
+/// raw, like `Self_`. Two EDTD names which would convert to the same identifier are an error, and
+/// `FIELD_NAME_MAP` maps the names of fields back to the elements they hold.
+///
+/// The module name may be preceded by `#[ebml(...)]` attributes choosing the Rust type used for
+/// each primitive EDTD type, like `#[ebml(date = "i64", string = "MyString")]`. The settings are
+/// `int`, `uint`, `float`, `string`, `binary` and `date`; each names a type implementing
+/// `ebml_macros::runtime::EbmlPrimitive` for the primitive's raw type. `float_encoding` may be
+/// `"single"` or `"double"` (the default), choosing how many bytes floats are written with.
+/// `binary` elements with a fixed size of at most `fixed_binary_limit` bytes (by default
+/// `"64"`) are held in arrays like `[u8; 16]` instead.
+/// `unknown_elements` may be `"error"`, `"skip"` (the default) or `"collect"`, choosing what
+/// readers do with elements the schema doesn't describe. `write_ranges` may be `"error"` (the
+/// default), `"clamp"` or `"ignore"`, choosing what writers do with values outside their range.
+/// `derive(...)` and `skip_derive(...)` add to and remove from the traits generated structs
+/// derive (by default `Debug`, `Clone` and `PartialEq`), and with the `serde` feature, `serde`
+/// makes everything generated derive `Serialize` and `Deserialize` too. With the `arbitrary`
+/// feature, `arbitrary` makes container structs implement proptest's `Arbitrary` and generates a
+/// `check_round_trips` function, so `check_round_trips(100)` in a test checks that values the
+/// schema allows survive being written and read back, and `any::<Segment>()` can be used in
+/// proptest's own tests. With the `async` feature, `async` adds `read_from_async` and
+/// `read_with_async` functions (and `stream_async` with `streaming`) reading from a
+/// `tokio::io::AsyncRead`, which need the 2018 edition. `crc32(...)` names
+/// containers which are written with a `CRC-32` element, and `unknown_size(...)` names containers
+/// which are written with an unknown size. `streaming` generates a streaming reader as well, and
+/// `streamed(...)` names the containers it reads child by child. `push` generates a `Parser` which
+/// is fed bytes in chunks of any size and returns `Event`s. Each `find = r"\Segment\Info"`
+/// generates a `find_*` function for the element at that path, instead of the ones generated for
+/// containers near the top of the document by default. `dump` generates a `dump` function, printing
+/// a document as a tree for debugging. `enums` makes `uint` elements whose range lists their values
+/// hold an enum, `omit_defaults` makes writers leave out mandatory elements equal to their
+/// default, `builders` gives every container struct a `builder` function, and `borrowed`
+/// generates a `borrowed` module of structs which borrow their strings and binaries from the
+/// input. `overlay = "schemas/webm.overlay"` applies an overlay read from that path, relative to
+/// the directory containing the invoking crate's `Cargo.toml`, to the EDTD before generating
+/// code, as by `ebml_macros::Dtd::apply_overlay`. `doc_type = "webm"` generates code for the
+/// elements of blocks labeled `define elements "webm" { ... }`, rather than the EDTD's own, and
+/// names the module after it.
+/// `mode` may be `"full"` (the default), `"constants"`, generating only the header's constants,
+/// the `element_name` and `element_id` functions, `FIELD_NAME_MAP`, and the `ids` and `defaults`
+/// modules, or `"fallback"`, generating only those if the rest can't be (with a note saying why).
+#[proc_macro]
+pub fn ebml_dtd(input: TokenStream) -> TokenStream {
+    match expand(input) {
+        Ok(tokens) => tokens,
+        Err((message, span)) => compile_error(&message, span),
+    }
+}
+
+/// Generates a module from an EDTD stored in a file.
+///
+/// The first argument is the (optional) name of the module to generate, and the second is the
+/// path to the EDTD, relative to the directory containing the invoking crate's `Cargo.toml`. The
+/// crate is rebuilt whenever the file changes. Attributes are accepted just like `ebml_dtd!`.
+///
+/// More paths may follow, for an EDTD split across several files. They're merged into one
+/// before code is generated, as by `ebml_macros::parse_dtds`: definitions repeated exactly are
+/// only used once, but names or ids defined differently in two files are a compile error naming
+/// both. Files may start with `include "path";` statements, naming files relative to their own
+/// directory, which are read as by `ebml_macros::DtdFiles`; the crate is rebuilt when they change
+/// too.
+#[proc_macro]
+pub fn include_ebml_dtd(input: TokenStream) -> TokenStream {
+    match expand_include(input) {
+        Ok(tokens) => tokens,
+        Err((message, span)) => compile_error(&message, span),
+    }
+}
+
+/// Implements `ebml_macros::runtime::EbmlRead` for a struct laid out by hand, which holds the body
+/// of a container element in a schema.
+///
+/// The struct names the schema, relative to the directory containing the invoking crate's
+/// `Cargo.toml`, and the container with `#[ebml(schema = "...", element = "...")]`. Each field
+/// names the child element it holds with `#[ebml(element = "...")]`. Fields are `Option`s for
+/// elements which occur at most once, `Vec`s for those which may occur many times, and hold the
+/// value directly for those which occur exactly once. Each value's type must implement
+/// `ebml_macros::runtime::EbmlPrimitive` for the raw type of its element, or be a generated or
+/// derived struct for containers.
+///
+/// The struct gets `read_from` and `read_with` functions like a generated one's. Children it has
+/// no field for are skipped. Unknown elements and fields which don't fit the schema are reported
+/// as compile errors at the field.
+///
+/// ```ignore
+/// #[derive(EbmlRead, EbmlWrite)]
+/// #[ebml(schema = "schemas/matroska.edtd", element = "Info")]
+/// struct Info {
+///     #[ebml(element = "Title")]
+///     title: Option<String>,
+///     #[ebml(element = "TimecodeScale")]
+///     timecode_scale: u32,
+/// }
+/// ```
+#[proc_macro_derive(EbmlRead, attributes(ebml))]
+pub fn derive_ebml_read(input: TokenStream) -> TokenStream {
+    match derive::expand(input, derive::Derive::Read) {
+        Ok(tokens) => tokens,
+        Err((message, span)) => compile_error(&message, span),
+    }
+}
+
+/// Implements `ebml_macros::runtime::EbmlWrite` for a struct laid out by hand, giving it
+/// `write_to` and `encoded_len` functions like a generated struct's. It's configured like
+/// `EbmlRead`, and only writes the children it has fields for.
+#[proc_macro_derive(EbmlWrite, attributes(ebml))]
+pub fn derive_ebml_write(input: TokenStream) -> TokenStream {
+    match derive::expand(input, derive::Derive::Write) {
+        Ok(tokens) => tokens,
+        Err((message, span)) => compile_error(&message, span),
+    }
+}
+
+fn expand(input: TokenStream) -> Result<TokenStream, (String, Span)> {
+    let usage = "expected a module name and a string literal, like `ebml_dtd!(name, \"...\")`";
+    let args = parse_arguments(input, usage)?;
+    if args.literals.len() > 1 {
+        return Err((usage.to_string(), args.span));
+    }
+    generate(&args, &[&args.literals[0]], None)
+}
+
+fn expand_include(input: TokenStream) -> Result<TokenStream, (String, Span)> {
+    let usage = "expected a module name and a path, like `include_ebml_dtd!(name, \"a.edtd\")`";
+    let args = parse_arguments(input, usage)?;
+    let span = args.span;
+
+    let manifest_dir = manifest_dir();
+    let paths: Vec<_> = args.literals.iter().map(|path| manifest_dir.join(path)).collect();
+    let files = DtdFiles::read(&paths).map_err(|err| match err {
+        IncludeError::Io(ref path, ref err) => {
+            (format!("couldn't read {}: {}", relative(path, &manifest_dir), err), span)
+        }
+        err => (err.to_string(), span),
+    })?;
+
+    let mut sources = Vec::new();
+    let mut trackers = String::new();
+    for index in 0..files.len() {
+        sources.push(relative(files.path(index), &manifest_dir));
+        trackers.push_str(&tracker(files.path(index), &sources[index], span)?);
+    }
+
+    let texts: Vec<&str> = (0..files.len()).map(|index| files.text(index)).collect();
+    let mut tokens = generate(&args, &texts, Some(&sources))?;
+    tokens.extend(trackers.parse::<TokenStream>().unwrap());
+    Ok(tokens)
+}
+
+// The directory containing the invoking crate's `Cargo.toml`, which paths are relative to.
+fn manifest_dir() -> PathBuf {
+    env::var_os("CARGO_MANIFEST_DIR").map_or_else(PathBuf::new, PathBuf::from)
+}
+
+// `path` as it's shown in errors: relative to `manifest_dir` if it's in it.
+fn relative(path: &Path, manifest_dir: &Path) -> String {
+    path.strip_prefix(manifest_dir).unwrap_or(path).display().to_string()
+}
+
+// Reads the file at `path`, relative to the directory containing the invoking crate's
+// `Cargo.toml`. Returns its text, and an item referencing it, as `tracker` makes.
+fn read_schema(path: &str, span: Span) -> Result<(String, String), (String, Span)> {
+    let full_path = manifest_dir().join(path);
+    let mut text = String::new();
+    File::open(&full_path)
+        .and_then(|mut file| file.read_to_string(&mut text))
+        .map_err(|err| (format!("couldn't read {}: {}", path, err), span))?;
+    Ok((text, tracker(&full_path, path, span)?))
+}
+
+// An item referencing the file at `full_path` with `include_bytes!`, which is what makes cargo
+// rebuild when it changes. `path` is how errors name it.
+fn tracker(full_path: &Path, path: &str, span: Span) -> Result<String, (String, Span)> {
+    let full_path = full_path.to_str()
+        .ok_or_else(|| (format!("{} is not a valid UTF-8 path", path), span))?;
+    Ok(format!("const _: &'static [u8] = include_bytes!({:?});", full_path))
+}
+
+// The arguments both macros take.
+struct Arguments {
+    options: CodegenOptions,
+    // The path to an overlay to apply to the EDTD, if one was given.
+    overlay: Option<String>,
+    // The module name, if one was given.
+    module: Option<String>,
+    // The values of the string literals, and the span of the first.
+    literals: Vec<String>,
+    span: Span,
+    // How many bytes come before the value in the first literal's source, like `r#"`.
+    prefix_len: usize,
+}
+
+// Both macros take any number of `#[ebml(...)]` attributes, an optional module name, then
+// comma-separated string literals (and an optional trailing comma).
+fn parse_arguments(input: TokenStream, usage: &str) -> Result<Arguments, (String, Span)> {
+    let mut tokens = input.into_iter().peekable();
+
+    let (mut options, mut overlay) = (CodegenOptions::default(), None);
+    options::parse_attributes(&mut tokens, &mut options, &mut overlay)?;
+
+    let module = match tokens.peek() {
+        Some(&TokenTree::Ident(ref ident)) => Some(ident.to_string()),
+        _ => None,
+    };
+    if module.is_some() {
+        tokens.next();
+        match tokens.next() {
+            Some(TokenTree::Punct(ref punct)) if punct.as_char() == ',' => {}
+            Some(other) => return Err((usage.to_string(), other.span())),
+            None => return Err((usage.to_string(), Span::call_site())),
+        }
+    }
+    let mut literals = Vec::new();
+    let (mut span, mut prefix_len) = (Span::call_site(), 0);
+    loop {
+        match tokens.next() {
+            Some(TokenTree::Literal(lit)) => {
+                let source = lit.to_string();
+                match literal::string_value(&source) {
+                    Some(text) => {
+                        if literals.is_empty() {
+                            span = lit.span();
+                            prefix_len = source.find('"').map_or(0, |idx| idx + 1);
+                        }
+                        literals.push(text);
+                    }
+                    None => return Err((usage.to_string(), lit.span())),
+                }
+            }
+            None if !literals.is_empty() => break,
+            Some(other) => return Err((usage.to_string(), other.span())),
+            None => return Err((usage.to_string(), Span::call_site())),
+        }
+        match tokens.next() {
+            None => break,
+            Some(TokenTree::Punct(ref punct)) if punct.as_char() == ',' => {}
+            Some(other) => return Err((usage.to_string(), other.span())),
+        }
+    }
+
+    Ok(Arguments { options, overlay, module, literals, span, prefix_len })
+}
+
+// `paths` are the files the EDTD was read from, one for each of `texts`, if it wasn't given
+// inline.
+fn generate(args: &Arguments, texts: &[&str], paths: Option<&[String]>)
+    -> Result<TokenStream, (String, Span)>
+{
+    let path = |index: usize| paths.map(|paths| paths[index].as_str());
+    let source = paths.map_or("EDTD".to_string(), |paths| paths.join(", "));
+    let overlay = match args.overlay {
+        Some(ref path) => Some((path, read_schema(path, args.span)?)),
+        None => None,
+    };
+    let options = ParseOptions { validate: Some(Severity::Error), ..Default::default() };
+    let dtd = ebml_macros::parse_dtds(texts, &options)
+        .map_err(|(index, err)| {
+            let (text, source) = (texts[index], path(index).unwrap_or("EDTD"));
+            let message = match err {
+                DtdError::Conflict(ref conflict) => {
+                    let first = path(conflict.first).unwrap_or("EDTD");
+                    conflict.kind.describe(&conflict.name, &conflict.existing, first, source)
+                }
+                err => {
+                    let locate = |offset| locate(args, text, path(index), offset);
+                    describe_error(source, err, locate)
+                }
+            };
+            (message, args.span)
+        })?;
+    let dtd = match overlay {
+        Some((path, (ref text, _))) => {
+            let describe = |err| {
+                let locate = |offset| locate(args, text, Some(path), offset);
+                (describe_error(path, err, locate), args.span)
+            };
+            let overlay = ebml_macros::parse_overlay(text).map_err(&describe)?;
+            dtd.apply_overlay(overlay, &options).map_err(&describe)?
+        }
+        None => dtd,
+    };
+    let dtd = match args.options.doc_type {
+        Some(ref doc_type) => dtd.select_doc_type(doc_type).ok_or_else(|| {
+            (format!("{} has no elements for DocType `{}`", source, doc_type), args.span)
+        })?,
+        None => dtd,
+    };
+
+    let module = match args.module {
+        Some(ref module) => module.clone(),
+        None => ebml_macros::codegen::module_name(&dtd).ok_or_else(|| {
+            let message = format!("{} doesn't declare a DocType to name the module after, so it \
+                                   needs a name, like `ebml_dtd!(name, \"...\")`", source);
+            (message, args.span)
+        })?,
+    };
+    let code = ebml_macros::codegen::generate(&dtd, &module, &args.options)
+        .map_err(|err| (format!("can't generate code for {}: {}", source, err), args.span))?;
+    if let Some(reason) = ebml_macros::codegen::fallback_reason(&dtd, &args.options) {
+        eprintln!("note: only constants were generated for {}: {}", source, reason);
+    }
+    let tokens = code.parse()
+        .map_err(|_| (format!("generated invalid code: {}", code), args.span))?;
+    let mut tokens = with_call_site_edition(tokens, args.span);
+    if let Some((_, (_, ref tracker))) = overlay {
+        tokens.extend(tracker.parse::<TokenStream>().unwrap());
+    }
+    Ok(tokens)
+}
+
+// Async readers use `async` and `.await`, which are only keywords from the 2018 edition on.
+// Tokens get the edition of wherever they're resolved, which is this 2015 crate for generated
+// code, so those two are resolved where the EDTD was written instead, in the including crate.
+fn with_call_site_edition(tokens: TokenStream, span: Span) -> TokenStream {
+    tokens.into_iter().map(|token| match token {
+        TokenTree::Ident(mut ident) => {
+            let name = ident.to_string();
+            if name == "async" || name == "await" {
+                ident.set_span(ident.span().resolved_at(span));
+            }
+            TokenTree::Ident(ident)
+        }
+        TokenTree::Group(group) => {
+            let stream = with_call_site_edition(group.stream(), span);
+            let mut respanned = Group::new(group.delimiter(), stream);
+            respanned.set_span(group.span());
+            TokenTree::Group(respanned)
+        }
+        other => other,
+    }).collect()
+}
+
+// `locate` describes where an offset into the EDTD is.
+fn describe_error<F>(source: &str, err: DtdError, locate: F) -> String
+    where F: Fn(usize) -> String
+{
+    match err {
+        DtdError::Syntax(err) => {
+            format!("syntax error[{}] in {} at {}: {}", err.code(), source, locate(err.byte_offset),
+                    err.message())
+        }
+        DtdError::Conflict(ref conflict) => {
+            conflict.kind.describe(&conflict.name, &conflict.existing, source, source)
+        }
+        DtdError::Invalid(diagnostics) => {
+            let mut message = format!("invalid {}:", source);
+            for diagnostic in diagnostics {
+                let (severity, code) = (diagnostic.severity(), diagnostic.code());
+                message.push_str(&format!("\n    {}[{}]", severity, code));
+                if let Some(span) = diagnostic.span() {
+                    message.push_str(&format!(" at {}", locate(span.start)));
+                }
+                message.push_str(&format!(": {}: {}", diagnostic.subject(), diagnostic.message()));
+            }
+            message
+        }
+        err => format!("{} in {}", err, source),
+    }
+}
+
+// Describes where `offset` is in `text`. For an inline EDTD, that's followed by where it is in
+// the Rust source, worked out from where the literal starts. Lines after the first start at the
+// beginning of a line in the source too, so their columns already include the literal's
+// indentation; the first line starts after the literal's opening quote.
+fn locate(args: &Arguments, text: &str, path: Option<&str>, offset: usize) -> String {
+    let (line, column) = SourceMap::new(text).line_column(offset);
+    if path.is_some() {
+        return format!("line {}, column {}", line, column);
+    }
+
+    let (source_line, source_column) = if line == 1 {
+        (args.span.line(), args.span.column() + args.prefix_len + column - 1)
+    } else {
+        (args.span.line() + line - 1, column)
+    };
+    format!(
+        "line {}, column {} ({}:{}:{})",
+        line, column, args.span.file(), source_line, source_column
+    )
+}
+
+fn compile_error(message: &str, span: Span) -> TokenStream {
+    let tokens: TokenStream = format!("compile_error!({:?});", message).parse().unwrap();
+    tokens.into_iter().map(|mut token| {
+        token.set_span(span);
+        token
+    }).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_describe_error() {
+        let locate = |offset| format!("offset {}", offset);
+        let text = "define elements {\n    Foo := 81 uint [ bogus; ]\n}\n";
+        let err = ebml_macros::parse_dtd(text, &Default::default()).unwrap_err();
+        assert_eq!(
+            describe_error("schemas/foo.edtd", err, locate),
+            "syntax error[P0005] in schemas/foo.edtd at offset 39: in element definition `Foo`: \
+             expected a keyword or punctuation, found `bogus;`"
+        );
+
+        let text = "define elements {\n    Foo := 81 uint;\n    Bar := 81 uint;\n}\n";
+        let options = ParseOptions { validate: Some(Severity::Error), ..Default::default() };
+        let err = ebml_macros::parse_dtd(text, &options).unwrap_err();
+        assert_eq!(
+            describe_error("EDTD", err, locate),
+            "invalid EDTD:\n    error[V0011] at offset 42: Bar: id 0x81 is already \
+             used by Foo"
+        );
+    }
+}
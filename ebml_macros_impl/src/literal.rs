@@ -0,0 +1,78 @@
+// Recovers the value of a string literal from its source representation. Returns None if the text
+// isn't a (non-byte) string literal.
+pub fn string_value(source: &str) -> Option<String> {
+    if source.starts_with('r') {
+        raw_string_value(&source[1..])
+    } else if source.starts_with('"') && source.ends_with('"') && source.len() >= 2 {
+        unescape(&source[1..source.len() - 1])
+    } else {
+        None
+    }
+}
+
+fn raw_string_value(source: &str) -> Option<String> {
+    let hashes = source.chars().take_while(|&c| c == '#').count();
+    let body = &source[hashes..];
+    let closing = format!("\"{}", "#".repeat(hashes));
+
+    if body.starts_with('"') && body.len() >= closing.len() + 1 && body.ends_with(&closing) {
+        Some(body[1..body.len() - closing.len()].to_string())
+    } else {
+        None
+    }
+}
+
+fn unescape(body: &str) -> Option<String> {
+    let mut out = String::with_capacity(body.len());
+    let mut chars = body.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            out.push(c);
+            continue;
+        }
+
+        match chars.next()? {
+            'n' => out.push('\n'),
+            'r' => out.push('\r'),
+            't' => out.push('\t'),
+            '0' => out.push('\0'),
+            '\\' => out.push('\\'),
+            '\'' => out.push('\''),
+            '"' => out.push('"'),
+            'x' => {
+                let hex: String = chars.by_ref().take(2).collect();
+                out.push(u8::from_str_radix(&hex, 16).ok()? as char);
+            }
+            'u' => {
+                if chars.next()? != '{' {
+                    return None;
+                }
+                let hex: String = chars.by_ref().take_while(|&c| c != '}').collect();
+                out.push(::std::char::from_u32(u32::from_str_radix(&hex, 16).ok()?)?);
+            }
+            // A line continuation skips the newline and any leading whitespace after it.
+            '\n' => while chars.peek().map_or(false, |c| c.is_whitespace()) {
+                chars.next();
+            },
+            _ => return None,
+        }
+    }
+
+    Some(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_string_value() {
+        assert_eq!(string_value(r#""plain""#), Some("plain".to_string()));
+        assert_eq!(string_value(r#""a\n\"b\" \u{9686}""#), Some("a\n\"b\" 隆".to_string()));
+        assert_eq!(string_value(r##"r#"raw "quoted" \n"#"##), Some(r#"raw "quoted" \n"#.to_string()));
+        assert_eq!(string_value(r#"r"raw""#), Some("raw".to_string()));
+        assert_eq!(string_value(r#"b"bytes""#), None);
+        assert_eq!(string_value("42"), None);
+    }
+}
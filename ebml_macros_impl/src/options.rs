@@ -0,0 +1,293 @@
+//! Parses the `#[ebml(...)]` attributes which may precede a macro's arguments into the options
+//! used to generate code.
+
+use std::iter::Peekable;
+
+use ebml_macros::codegen::{CodegenMode, CodegenOptions, FloatEncoding};
+use ebml_macros::runtime::{RangePolicy, UnknownElements};
+use proc_macro::{token_stream, Delimiter, Span, TokenStream, TokenTree};
+
+use literal;
+
+type Tokens = Peekable<token_stream::IntoIter>;
+
+/// Consumes any attributes at the start of `tokens`, applying their settings to `options`. The
+/// path given by an `overlay` setting, which changes the EDTD rather than the code, is put in
+/// `overlay`.
+pub fn parse_attributes(tokens: &mut Tokens, options: &mut CodegenOptions,
+                        overlay: &mut Option<String>)
+    -> Result<(), (String, Span)>
+{
+    loop {
+        match tokens.peek() {
+            Some(&TokenTree::Punct(ref punct)) if punct.as_char() == '#' => {}
+            _ => return Ok(()),
+        }
+        let pound = tokens.next().unwrap();
+
+        let attribute = match tokens.next() {
+            Some(TokenTree::Group(ref group)) if group.delimiter() == Delimiter::Bracket => {
+                group.stream()
+            }
+            Some(other) => return error("expected `[` after `#`", other.span()),
+            None => return error("expected `[` after `#`", pound.span()),
+        };
+        let mut attribute = attribute.into_iter();
+
+        let name = match attribute.next() {
+            Some(TokenTree::Ident(ident)) => ident,
+            Some(other) => return error("expected `ebml`", other.span()),
+            None => return error("expected `ebml`", pound.span()),
+        };
+        if name.to_string() != "ebml" {
+            return error("only `#[ebml(...)]` attributes are supported", name.span());
+        }
+        match (attribute.next(), attribute.next()) {
+            (Some(TokenTree::Group(ref group)), None)
+                if group.delimiter() == Delimiter::Parenthesis =>
+            {
+                parse_settings(group.stream(), options, overlay)?
+            }
+            (Some(other), _) => return error("expected `(` after `ebml`", other.span()),
+            (None, _) => return error("expected `(` after `ebml`", name.span()),
+        }
+    }
+}
+
+// Settings are a comma-separated list of `name = "value"`, `name(Item, ...)` or just `name`.
+fn parse_settings(settings: TokenStream, options: &mut CodegenOptions,
+                  overlay: &mut Option<String>)
+    -> Result<(), (String, Span)>
+{
+    let usage = "expected a setting like `date = \"i64\"`";
+    let mut tokens = settings.into_iter().peekable();
+
+    loop {
+        let name = match tokens.next() {
+            Some(TokenTree::Ident(ident)) => ident,
+            Some(other) => return error(usage, other.span()),
+            None => return Ok(()),
+        };
+        match tokens.next() {
+            Some(TokenTree::Punct(ref punct)) if punct.as_char() == '=' => {
+                let value = match tokens.next() {
+                    Some(TokenTree::Literal(lit)) => match literal::string_value(&lit.to_string()) {
+                        Some(value) => (value, lit.span()),
+                        None => return error(usage, lit.span()),
+                    },
+                    Some(other) => return error(usage, other.span()),
+                    None => return error(usage, name.span()),
+                };
+                if name.to_string() == "overlay" {
+                    *overlay = Some(value.0);
+                } else {
+                    apply_setting(&name.to_string(), value, name.span(), options)?;
+                }
+            }
+            Some(TokenTree::Group(ref group)) if group.delimiter() == Delimiter::Parenthesis => {
+                apply_list(&name.to_string(), group.stream(), name.span(), options)?;
+            }
+            Some(TokenTree::Punct(ref punct)) if punct.as_char() == ',' => {
+                apply_flag(&name.to_string(), name.span(), options)?;
+                continue;
+            }
+            Some(other) => return error(usage, other.span()),
+            None => return apply_flag(&name.to_string(), name.span(), options),
+        }
+
+        match tokens.next() {
+            Some(TokenTree::Punct(ref punct)) if punct.as_char() == ',' => {}
+            Some(other) => return error("expected `,`", other.span()),
+            None => return Ok(()),
+        }
+    }
+}
+
+// Settings which are lists: `derive(...)` adds derives, `skip_derive(...)` removes them,
+// `crc32(...)` names the containers written with a CRC-32, `unknown_size(...)` names those
+// written with an unknown size, and `streamed(...)` names the containers the streaming reader
+// reads child by child (which turns it on).
+fn apply_list(name: &str, items: TokenStream, span: Span, options: &mut CodegenOptions)
+    -> Result<(), (String, Span)>
+{
+    let mut paths = Vec::new();
+    let mut path = String::new();
+    for token in items {
+        match token {
+            TokenTree::Punct(ref punct) if punct.as_char() == ',' => {
+                paths.push(::std::mem::take(&mut path));
+            }
+            TokenTree::Ident(_) | TokenTree::Punct(_) => path.push_str(&token.to_string()),
+            other => return error("expected a name", other.span()),
+        }
+    }
+    if !path.is_empty() {
+        paths.push(path);
+    }
+
+    match name {
+        "derive" => for path in paths {
+            if !options.derives.contains(&path) {
+                options.derives.push(path);
+            }
+        },
+        "skip_derive" => options.derives.retain(|derive| !paths.contains(derive)),
+        "crc32" => options.crc32.extend(paths),
+        "unknown_size" => options.unknown_size.extend(paths),
+        "streamed" => {
+            options.streaming = true;
+            options.streamed.extend(paths);
+        }
+        _ => return error(&format!("unknown setting `{}`", name), span),
+    }
+    Ok(())
+}
+
+// Settings which are on if they're present.
+fn apply_flag(name: &str, span: Span, options: &mut CodegenOptions)
+    -> Result<(), (String, Span)>
+{
+    match name {
+        "serde" => enable_serde(span, options),
+        "arbitrary" => enable_arbitrary(span, options),
+        "async" => enable_async(span, options),
+        "streaming" => {
+            options.streaming = true;
+            Ok(())
+        }
+        "dump" => {
+            options.dump = true;
+            Ok(())
+        }
+        "push" => {
+            options.push = true;
+            Ok(())
+        }
+        "enums" => {
+            options.enums = true;
+            Ok(())
+        }
+        "omit_defaults" => {
+            options.omit_defaults = true;
+            Ok(())
+        }
+        "borrowed" => {
+            options.borrowed = true;
+            Ok(())
+        }
+        "builders" => {
+            options.builders = true;
+            Ok(())
+        }
+        _ => error(&format!("unknown setting `{}`", name), span),
+    }
+}
+
+#[cfg(feature = "serde")]
+fn enable_serde(_: Span, options: &mut CodegenOptions) -> Result<(), (String, Span)> {
+    options.serde = true;
+    Ok(())
+}
+
+#[cfg(not(feature = "serde"))]
+fn enable_serde(span: Span, _: &mut CodegenOptions) -> Result<(), (String, Span)> {
+    error("the `serde` setting needs the `serde` feature of `ebml_macros_impl`", span)
+}
+
+#[cfg(feature = "arbitrary")]
+fn enable_arbitrary(_: Span, options: &mut CodegenOptions) -> Result<(), (String, Span)> {
+    options.arbitrary = true;
+    Ok(())
+}
+
+#[cfg(not(feature = "arbitrary"))]
+fn enable_arbitrary(span: Span, _: &mut CodegenOptions) -> Result<(), (String, Span)> {
+    error("the `arbitrary` setting needs the `arbitrary` feature of `ebml_macros_impl`", span)
+}
+
+#[cfg(feature = "async")]
+fn enable_async(_: Span, options: &mut CodegenOptions) -> Result<(), (String, Span)> {
+    options.asynchronous = true;
+    Ok(())
+}
+
+#[cfg(not(feature = "async"))]
+fn enable_async(span: Span, _: &mut CodegenOptions) -> Result<(), (String, Span)> {
+    error("the `async` setting needs the `async` feature of `ebml_macros_impl`", span)
+}
+
+fn apply_setting(name: &str, (value, value_span): (String, Span), span: Span,
+                 options: &mut CodegenOptions)
+    -> Result<(), (String, Span)>
+{
+    if name == "float_encoding" {
+        options.float_encoding = match value.as_str() {
+            "single" => FloatEncoding::Single,
+            "double" => FloatEncoding::Double,
+            _ => return error("expected `\"single\"` or `\"double\"`", value_span),
+        };
+        return Ok(());
+    }
+    if name == "unknown_elements" {
+        options.unknown_elements = match value.as_str() {
+            "error" => UnknownElements::Error,
+            "skip" => UnknownElements::Skip,
+            "collect" => UnknownElements::Collect,
+            _ => return error("expected `\"error\"`, `\"skip\"` or `\"collect\"`", value_span),
+        };
+        return Ok(());
+    }
+    if name == "write_ranges" {
+        options.write_ranges = match value.as_str() {
+            "error" => RangePolicy::Error,
+            "clamp" => RangePolicy::Clamp,
+            "ignore" => RangePolicy::Ignore,
+            _ => return error("expected `\"error\"`, `\"clamp\"` or `\"ignore\"`", value_span),
+        };
+        return Ok(());
+    }
+    if name == "mode" {
+        options.mode = match value.as_str() {
+            "full" => CodegenMode::Full,
+            "constants" => CodegenMode::Constants,
+            "fallback" => CodegenMode::Fallback,
+            _ => return error("expected `\"full\"`, `\"constants\"` or `\"fallback\"`", value_span),
+        };
+        return Ok(());
+    }
+    if name == "fixed_binary_limit" {
+        options.fixed_binary_limit = match value.parse() {
+            Ok(limit) => limit,
+            Err(_) => return error("expected a number of bytes, like `\"64\"`", value_span),
+        };
+        return Ok(());
+    }
+    if name == "doc_type" {
+        options.doc_type = Some(value);
+        return Ok(());
+    }
+    if name == "find" {
+        options.find_paths.get_or_insert_with(Vec::new).push(value);
+        return Ok(());
+    }
+
+    let field = match name {
+        "int" => &mut options.int_type,
+        "uint" => &mut options.uint_type,
+        "float" => &mut options.float_type,
+        "string" => &mut options.string_type,
+        "binary" => &mut options.binary_type,
+        "date" => &mut options.date_type,
+        _ => return error(&format!("unknown setting `{}`", name), span),
+    };
+
+    if value.parse::<TokenStream>().is_err() {
+        return error(&format!("`{}` is not a valid type", value), value_span);
+    }
+    *field = value;
+    Ok(())
+}
+
+fn error<T>(message: &str, span: Span) -> Result<T, (String, Span)> {
+    Err((message.to_string(), span))
+}
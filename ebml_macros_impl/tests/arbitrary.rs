@@ -0,0 +1,89 @@
+extern crate chrono;
+extern crate ebml;
+extern crate ebml_macros;
+#[macro_use]
+extern crate ebml_macros_impl;
+#[macro_use]
+extern crate proptest;
+
+use proptest::prelude::*;
+use proptest::test_runner::{Config, TestError, TestRunner};
+
+macro_rules! schema {
+    ($($module:tt)*) => {
+        ebml_dtd!($($module)*, r#"
+            define types {
+                track_number := uint [ range:1..254; ]
+            }
+            define elements {
+                Segment := 18538067 container [ card:*; ] {
+                    Info := 1549a966 container [ card:1; ] {
+                        SegmentUID := 73a4 binary [ card:?; size:16; ]
+                        Title := 7ba9 string [ card:?; range:32..126; ]
+                        Duration := 4489 float [ card:?; range:>0.0; ]
+                        DateUTC := 4461 date [ card:?; range:20010101T00:00:00..20380119T03:14:07; ]
+                    }
+                    Tracks := 1654ae6b container [ card:?; ] {
+                        TrackEntry := ae container [ card:+; ] {
+                            TrackNumber := d7 track_number [ card:1; ]
+                            FlagLacing := 9c uint [ card:?; range:0..1; ]
+                            Delay := 55ee int [ card:?; range:-1000..1000; ]
+                        }
+                    }
+                    Chapters := 1043a770 container [ card:?; ];
+                }
+                ChapterAtom := b6 container [ card:*; parent:Chapters, ChapterAtom; ] {
+                    ChapterUID := 73c4 uint [ card:1; range:1..; ]
+                    ChapterString := 85 string [ card:*; size:1..8; ]
+                }
+            }
+        "#);
+    };
+}
+
+schema!(#[ebml(arbitrary)] matroska);
+schema!(#[ebml(arbitrary, float_encoding = "single", unknown_elements = "collect")] single);
+
+#[test]
+fn test_round_trips() {
+    matroska::check_round_trips(200);
+    single::check_round_trips(200);
+}
+
+proptest! {
+    #[test]
+    fn test_values_are_valid(
+        info in any::<matroska::Info>(),
+        entry in any::<matroska::TrackEntry>(),
+        tracks in any::<matroska::Tracks>()
+    ) {
+        prop_assert!(info.segment_uid.is_none_or(|uid| uid.len() == 16));
+        prop_assert!(info.title.is_none_or(|title| {
+            title.chars().all(|c| (' '..='~').contains(&c))
+        }));
+        prop_assert!(info.duration.is_none_or(|duration| duration > 0.0));
+
+        prop_assert!((1..=254).contains(&*entry.track_number));
+        prop_assert!(entry.flag_lacing.is_none_or(|flag| flag <= 1));
+        prop_assert!(entry.delay.is_none_or(|delay| (-1000..=1000).contains(&delay)));
+
+        prop_assert!(!tracks.track_entry.is_empty());
+    }
+}
+
+#[test]
+fn test_failures_shrink() {
+    let config = Config { failure_persistence: None, ..Config::default() };
+    let result = TestRunner::new(config).run(&any::<matroska::Tracks>(), |tracks| {
+        prop_assert!(tracks.track_entry.iter().all(|entry| *entry.track_number < 100));
+        Ok(())
+    });
+    let tracks = match result {
+        Err(TestError::Fail(_, tracks)) => tracks,
+        result => panic!("expected a failure, got {:?}", result),
+    };
+    assert_eq!(tracks.track_entry.len(), 1);
+    let entry = &tracks.track_entry[0];
+    assert_eq!(*entry.track_number, 100);
+    assert_eq!((entry.flag_lacing, entry.delay), (None, None));
+}
@@ -0,0 +1,170 @@
+#[macro_use]
+extern crate ebml_macros_impl;
+
+use ebml_macros::runtime::{self, ReadContext, ReadErrorKind};
+use tokio::io::{AsyncWriteExt, DuplexStream};
+
+ebml_dtd!(#[ebml(async, crc32(Frame), unknown_size(Info))] media, r#"
+    define elements {
+        Media := 4001 container [ card:1; ] {
+            Title := 4101 string [ card:?; def:"untitled"; ]
+            Frame := 4102 container [ card:*; ] {
+                Timecode := 4201 uint [ card:1; ]
+                Offset := 4202 int [ card:?; ]
+                Rate := 4203 float [ card:?; ]
+                Data := 4204 binary [ card:1; ]
+            }
+            Info := 4103 container [ card:?; unknownsizeallowed:yes; ] {
+                Count := 4301 uint [ card:1; ]
+            }
+            Chapter := 4104 container [ card:*; ] {
+                Name := 4401 string [ card:1; ]
+            }
+        }
+        Nested := 4105 container [ parent:Chapter, Nested; card:?; ]
+    }
+"#);
+
+ebml_dtd!(#[ebml(async, streamed(Media))] streaming, r#"
+    define elements {
+        Media := 4001 container [ card:*; ] {
+            Title := 4101 string [ card:?; ]
+            Frame := 4102 container [ card:*; ] {
+                Timecode := 4201 uint [ card:1; ]
+                Data := 4202 binary [ card:1; ]
+            }
+        }
+    }
+"#);
+
+// A reader which gets `bytes` a few at a time, through a pipe too small to hold them all, and
+// the future writing them. The input ends when it's finished.
+fn trickle(bytes: Vec<u8>) -> (DuplexStream, impl std::future::Future<Output = ()>) {
+    let (r, mut w) = tokio::io::duplex(5);
+    let write = async move {
+        for chunk in bytes.chunks(3) {
+            // The reader stops early if it fails.
+            if w.write_all(chunk).await.is_err() {
+                break;
+            }
+        }
+    };
+    (r, write)
+}
+
+fn run<F: std::future::Future>(future: F) -> F::Output {
+    tokio::runtime::Builder::new_current_thread().build().unwrap().block_on(future)
+}
+
+fn media() -> media::Media {
+    let frame = |timecode, data: &[u8]| media::Frame {
+        timecode,
+        offset: Some(-2),
+        rate: Some(0.5),
+        data: data.to_vec(),
+    };
+    let nested = media::Nested { nested: Some(Box::new(media::Nested { nested: None })) };
+    media::Media {
+        title: Some("clip".to_string()),
+        frame: vec![frame(1, &[0xAA; 100]), frame(2, &[])],
+        info: Some(media::Info { count: 2 }),
+        chapter: vec![media::Chapter { name: "one".to_string(), nested: Some(nested) }],
+    }
+}
+
+#[test]
+fn test_async() {
+    let mut bytes = Vec::new();
+    media().write_to(&mut bytes).unwrap();
+    let sync = {
+        let mut r = &bytes[..];
+        let (_, size, _) = runtime::read_header(&mut r).unwrap();
+        media::Media::read_from(&mut r, size).unwrap()
+    };
+    assert_eq!(sync, media());
+
+    let (mut r, write) = trickle(bytes);
+    let read = async move {
+        let (_, size, _) = runtime::read_header_async(&mut r).await?;
+        media::Media::read_from_async(&mut r, size).await
+    };
+    let (read, ()) = run(async { tokio::join!(read, write) });
+    assert_eq!(read.unwrap(), sync);
+}
+
+#[test]
+fn test_async_errors() {
+    let mut bytes = Vec::new();
+    media().write_to(&mut bytes).unwrap();
+
+    // The CRC-32 of the first frame is checked.
+    let mut corrupt = bytes.clone();
+    let last = corrupt.iter().rposition(|&byte| byte == 0xAA).unwrap();
+    corrupt[last] = 0xAB;
+    let (mut r, write) = trickle(corrupt);
+    let read = async move {
+        let (_, size, _) = runtime::read_header_async(&mut r).await?;
+        media::Media::read_from_async(&mut r, size).await
+    };
+    let (read, ()) = run(async { tokio::join!(read, write) });
+    let err = read.unwrap_err();
+    assert!(matches!(*err.kind(), ReadErrorKind::CrcMismatch { .. }));
+    assert_eq!(err.path(), &["Media", "Frame"]);
+
+    // Input which ends early.
+    bytes.truncate(bytes.len() - 1);
+    let (mut r, write) = trickle(bytes);
+    let read = async move {
+        let (_, size, _) = runtime::read_header_async(&mut r).await?;
+        let mut ctx = ReadContext::default();
+        media::Media::read_with_async(&mut r, size, &mut ctx).await
+    };
+    let (read, ()) = run(async { tokio::join!(read, write) });
+    assert!(matches!(*read.unwrap_err().kind(), ReadErrorKind::UnexpectedEof));
+}
+
+#[derive(Default)]
+struct Timecodes {
+    media: usize,
+    titles: Vec<String>,
+    timecodes: Vec<u64>,
+}
+
+impl streaming::Handler for Timecodes {
+    fn on_enter_media(&mut self) {
+        self.media += 1;
+    }
+
+    fn on_title(&mut self, title: String) {
+        self.titles.push(title);
+    }
+
+    fn on_frame(&mut self, frame: streaming::Frame) {
+        self.timecodes.push(frame.timecode);
+    }
+}
+
+#[test]
+fn test_async_stream() {
+    let media = streaming::Media {
+        title: Some("clip".to_string()),
+        frame: (0..20).map(|timecode| streaming::Frame { timecode, data: vec![0; 10] }).collect(),
+    };
+    let mut bytes = Vec::new();
+    media.write_to(&mut bytes).unwrap();
+    media.write_to(&mut bytes).unwrap();
+
+    let mut sync = Timecodes::default();
+    streaming::stream(&mut &bytes[..], &mut sync).unwrap();
+
+    let (mut r, write) = trickle(bytes);
+    let mut timecodes = Timecodes::default();
+    let read = streaming::stream_async(&mut r, &mut timecodes);
+    // The input only ends once the writer is dropped, after writing everything.
+    let (read, ()) = run(async { tokio::join!(read, write) });
+    read.unwrap();
+    assert_eq!(timecodes.media, 2);
+    assert_eq!(timecodes.titles, sync.titles);
+    assert_eq!(timecodes.timecodes, sync.timecodes);
+    assert_eq!(timecodes.timecodes.len(), 40);
+}
@@ -0,0 +1,136 @@
+extern crate chrono;
+extern crate ebml;
+extern crate ebml_macros;
+#[macro_use]
+extern crate ebml_macros_impl;
+
+mod common;
+
+use ebml_macros::runtime::{self, ReadErrorKind};
+
+use common::element;
+
+// Every feature of a real schema has to compile in borrowed structs too.
+include_ebml_dtd!(#[ebml(borrowed)] matroska, "tests/schemas/matroska.edtd");
+
+ebml_dtd!(#[ebml(borrowed)] media, r#"
+    define elements {
+        Media := 4001 container [ card:1; ] {
+            Title := 4101 string [ card:?; def:"untitled"; ]
+            Frame := 4102 container [ card:*; ] {
+                Timecode := 4201 uint [ card:1; ]
+                Data := 4202 binary [ card:1; ]
+                Key := 4203 binary [ card:?; size:4; ]
+            }
+            Info := 4103 container [ card:?; ] {
+                Count := 4301 uint [ card:1; ]
+            }
+        }
+    }
+"#);
+
+ebml_dtd!(#[ebml(borrowed, unknown_elements = "collect")] collecting, r#"
+    define elements {
+        Known := 4001 container [ card:1; ] {
+            Value := 4101 uint [ card:1; ]
+        }
+    }
+"#);
+
+fn frame(timecode: u8, data: &[u8]) -> Vec<u8> {
+    let body = [element(&[0x42, 0x01], &[timecode]), element(&[0x42, 0x02], data)].concat();
+    element(&[0x41, 0x02], &body)
+}
+
+#[test]
+fn test_borrowed() {
+    let body = [
+        element(&[0x41, 0x01], b"clip\0\0"),
+        frame(1, &[0xAA; 10]),
+        frame(2, &[0xBB; 20]),
+        element(&[0x41, 0x03], &element(&[0x43, 0x01], &[2])),
+    ].concat();
+
+    let media = media::borrowed::Media::read_from(&body).unwrap();
+    assert_eq!(media.title, Some("clip"));
+    assert_eq!(media.frame.len(), 2);
+    assert_eq!(media.frame[1].timecode, 2);
+    assert_eq!(media.frame[1].data, &[0xBB; 20][..]);
+    // Nothing is copied: the fields point into the input.
+    assert!(body.as_ptr_range().contains(&media.frame[0].data.as_ptr()));
+    assert!(body.as_ptr_range().contains(&media.title.unwrap().as_ptr()));
+
+    // `Info` holds nothing which could be borrowed, so it's the owned struct.
+    let info: Option<media::Info> = media.info;
+    assert_eq!(info.unwrap().count, 2);
+
+    // The owned reader reads the same thing.
+    let owned = media::Media::read_from(&mut &body[..], body.len() as u64).unwrap();
+    assert_eq!(owned.title.as_deref(), media.title);
+    assert_eq!(owned.frame[0].data, media.frame[0].data);
+}
+
+#[test]
+fn test_defaults() {
+    let media = media::borrowed::Media::read_from(&[]).unwrap();
+    assert_eq!(media.title, None);
+    assert_eq!(media.title_or_default(), "untitled");
+}
+
+#[test]
+fn test_errors() {
+    // A `Data` element which claims to be longer than its parent.
+    let body = [0x41, 0x02, 0x83, 0x42, 0x02, 0x85, 0x00];
+    let err = media::borrowed::Media::read_from(&body).unwrap_err();
+    match *err.kind() {
+        ReadErrorKind::Overrun => {}
+        ref other => panic!("expected an overrun, got {:?}", other),
+    }
+    assert_eq!(err.path(), &["Media", "Frame"]);
+
+    // Sizes are still checked.
+    let frame = [
+        element(&[0x42, 0x01], &[1]),
+        element(&[0x42, 0x02], &[]),
+        element(&[0x42, 0x03], &[1, 2, 3]),
+    ].concat();
+    let body = element(&[0x41, 0x02], &frame);
+    let err = media::borrowed::Media::read_from(&body).unwrap_err();
+    match *err.kind() {
+        ReadErrorKind::SizeNotAllowed { size: 3, .. } => {}
+        ref other => panic!("expected a size error, got {:?}", other),
+    }
+
+    // So are CRCs.
+    let children = element(&[0x41, 0x01], b"clip");
+    let mut body = element(&[0xBF], &runtime::crc32(&children).to_le_bytes());
+    body.extend_from_slice(&children);
+    assert!(media::borrowed::Media::read_from(&body).is_ok());
+    body[3] ^= 1;
+    match *media::borrowed::Media::read_from(&body).unwrap_err().kind() {
+        ReadErrorKind::CrcMismatch { .. } => {}
+        ref other => panic!("expected a CRC mismatch, got {:?}", other),
+    }
+}
+
+#[test]
+fn test_matroska() {
+    let info = [
+        element(&[0x2A, 0xD7, 0xB1], &[0x0F, 0x42, 0x40]),
+        element(&[0x4D, 0x80], b"libebml"),
+        element(&[0x57, 0x41], b"mkvmerge"),
+    ].concat();
+    let info = matroska::borrowed::Info::read_from(&info).unwrap();
+    assert_eq!(info.muxing_app, "libebml");
+    assert_eq!(info.writing_app, "mkvmerge");
+    assert_eq!(info.timecode_scale, 1_000_000);
+}
+
+#[test]
+fn test_unknown_children() {
+    let body = [element(&[0x41, 0x01], &[5]), element(&[0x4F, 0x01], &[0xAA, 0xBB])].concat();
+    let known = collecting::borrowed::Known::read_from(&body).unwrap();
+    assert_eq!(known.value, 5);
+    let id = ebml::Id::from_encoded(0x4F01).unwrap();
+    assert_eq!(known.unknown_children, vec![(id, &[0xAA, 0xBB][..])]);
+}
@@ -0,0 +1,47 @@
+extern crate chrono;
+extern crate ebml;
+extern crate ebml_macros;
+#[macro_use]
+extern crate ebml_macros_impl;
+
+use ebml_macros::runtime::BuildError;
+use matroska::{Info, Segment};
+
+include_ebml_dtd!(#[ebml(builders)] matroska, "tests/schemas/matroska.edtd");
+
+#[test]
+fn test_build() {
+    let info = Info::builder()
+        .title("Big Buck Bunny")
+        .duration(123.0)
+        .segment_uid([7; 16])
+        .muxing_app("libebml")
+        .writing_app("mkvmerge".to_string())
+        .build()
+        .unwrap();
+    assert_eq!(info, Info {
+        segment_uid: Some([7; 16]),
+        segment_filename: None,
+        // Absent mandatory elements take their default.
+        timecode_scale: 1_000_000,
+        duration: Some(123.0),
+        date_utc: None,
+        title: Some("Big Buck Bunny".to_string()),
+        muxing_app: "libebml".to_string(),
+        writing_app: "mkvmerge".to_string(),
+    });
+
+    // Fields holding many values are added to.
+    let segment = Segment::builder().info(info.clone()).info(info).build().unwrap();
+    assert_eq!(segment.info.len(), 2);
+}
+
+#[test]
+fn test_missing() {
+    let err = Info::builder().title("Big Buck Bunny").build().unwrap_err();
+    assert_eq!(err, BuildError { container: "Info", missing: vec!["MuxingApp", "WritingApp"] });
+    assert_eq!(err.to_string(), "Info is missing MuxingApp, WritingApp");
+
+    let header = matroska::Ebml::builder().build().unwrap_err();
+    assert_eq!(header.missing, ["DocType"]);
+}
@@ -0,0 +1,75 @@
+extern crate chrono;
+extern crate ebml;
+extern crate ebml_macros;
+#[macro_use]
+extern crate ebml_macros_impl;
+
+use ebml_macros::runtime::{self, ReadContext, ReadError, ReadErrorKind, ReadOptions};
+
+ebml_dtd!(cardinality, r#"
+    define elements {
+        Parent := 4001 container [ card:1; ] {
+            One := 4101 uint [ card:1; ]
+            AtLeastOne := 4102 uint [ card:+; ]
+            Maybe := 4103 uint [ card:?; ]
+        }
+    }
+"#);
+
+fn read_with(body: &[u8], options: ReadOptions)
+    -> (Result<cardinality::Parent, ReadError>, ReadContext)
+{
+    let mut bytes = vec![0x40, 0x01, 0x80 | body.len() as u8];
+    bytes.extend_from_slice(body);
+    let mut r = &bytes[..];
+    let (_, size, _) = runtime::read_header(&mut r).unwrap();
+    let mut ctx = ReadContext::new(options);
+    (cardinality::Parent::read_with(&mut r, size, &mut ctx), ctx)
+}
+
+fn read(body: &[u8]) -> Result<cardinality::Parent, ReadError> {
+    read_with(body, ReadOptions::default()).0
+}
+
+#[test]
+fn test_missing() {
+    let err = read(&[0x41, 0x02, 0x81, 0x01]).unwrap_err();
+    assert_eq!(err.to_string(), "missing `One` element in Parent");
+
+    let err = read(&[0x41, 0x01, 0x81, 0x01]).unwrap_err();
+    assert_eq!(err.to_string(), "missing `AtLeastOne` element in Parent");
+
+    let parent = read(&[0x41, 0x01, 0x81, 0x01, 0x41, 0x02, 0x81, 0x02, 0x41, 0x02, 0x81, 0x03])
+        .unwrap();
+    assert_eq!(parent, cardinality::Parent { one: 1, at_least_one: vec![2, 3], maybe: None });
+}
+
+#[test]
+fn test_duplicate_strict() {
+    let err = read(&[
+        0x41, 0x01, 0x81, 0x01,
+        0x41, 0x02, 0x81, 0x02,
+        0x41, 0x03, 0x81, 0x03,
+        0x41, 0x03, 0x81, 0x04,
+    ]).unwrap_err();
+    match *err.kind() {
+        ReadErrorKind::DuplicateElement("Maybe") => {}
+        ref other => panic!("expected a duplicate element, got {:?}", other),
+    }
+    assert_eq!(err.to_string(), "more than one `Maybe` element in Parent");
+}
+
+#[test]
+fn test_duplicate_lenient() {
+    let options = ReadOptions { lenient_cardinality: true, ..ReadOptions::default() };
+    let (parent, ctx) = read_with(&[
+        0x41, 0x01, 0x81, 0x01,
+        0x41, 0x01, 0x81, 0x05,
+        0x41, 0x02, 0x81, 0x02,
+    ], options);
+
+    // The last occurrence wins.
+    assert_eq!(parent.unwrap().one, 5);
+    assert_eq!(ctx.warnings().len(), 1);
+    assert_eq!(ctx.warnings()[0].to_string(), "more than one `One` element in Parent");
+}
@@ -0,0 +1,10 @@
+//! Helpers shared by the integration tests.
+
+// Builds an element with a one byte size.
+pub fn element(id: &[u8], body: &[u8]) -> Vec<u8> {
+    assert!(body.len() < 0x7F);
+    let mut out = id.to_vec();
+    out.push(0x80 | body.len() as u8);
+    out.extend_from_slice(body);
+    out
+}
@@ -0,0 +1,17 @@
+extern crate trybuild;
+
+#[test]
+fn ui() {
+    let cases = trybuild::TestCases::new();
+    cases.pass("tests/ui/inline_dtd.rs");
+    cases.pass("tests/ui/derives.rs");
+    cases.compile_fail("tests/ui/inline_dtd_syntax_error.rs");
+    cases.compile_fail("tests/ui/inline_dtd_invalid.rs");
+    cases.compile_fail("tests/ui/include_missing_file.rs");
+    cases.compile_fail("tests/ui/unknown_setting.rs");
+    cases.compile_fail("tests/ui/skip_derive.rs");
+    cases.compile_fail("tests/ui/derive_unknown_element.rs");
+    cases.compile_fail("tests/ui/derive_incompatible_type.rs");
+    cases.compile_fail("tests/ui/derive_wrong_wrapper.rs");
+    cases.compile_fail("tests/ui/private_parsers.rs");
+}
@@ -0,0 +1,105 @@
+extern crate ebml_macros;
+#[macro_use]
+extern crate ebml_macros_impl;
+
+use ebml_macros::codegen::{self, CodegenMode, CodegenOptions};
+
+// Nothing but the macros is needed for constants.
+ebml_dtd!(#[ebml(mode = "constants")] r#"
+    declare header {
+        DocType := "constant-test";
+    }
+    define elements {
+        Segment := 18538067 container {
+            Volume := 4281 int [ def:-3; ]
+            Count := 4282 uint [ def:7; ]
+            Rate := 4283 float [ def:1.5; ]
+            Created := 4284 date [ def:1; ]
+            Title := 4285 string [ def:"untitled"; ]
+            Key := 4286 binary [ def:0x0102; ]
+        }
+    }
+"#);
+
+include_ebml_dtd!(#[ebml(mode = "constants")] matroska, "tests/schemas/matroska.edtd");
+
+// Header constants can be used wherever constants can.
+const HEADER: (&str, u64, u64) =
+    (matroska::DOC_TYPE, matroska::EBML_VERSION, matroska::DOC_TYPE_VERSION);
+
+// `Unknown` can't be an `ElementId` variant, so only constants are generated.
+ebml_dtd!(#[ebml(mode = "fallback")] fallback, r#"
+    define elements {
+        Unknown := 4281 uint [ def:2; ]
+    }
+"#);
+
+#[test]
+fn test_constants() {
+    assert_eq!(constant_test::DOC_TYPE, "constant-test");
+    assert_eq!(constant_test::ids::SEGMENT, 0x1853_8067);
+    assert_eq!(constant_test::ids::KEY, 0x4286);
+    assert_eq!(constant_test::defaults::VOLUME, -3);
+    assert_eq!(constant_test::defaults::COUNT, 7);
+    assert_eq!(constant_test::defaults::RATE, 1.5);
+    assert_eq!(constant_test::defaults::CREATED, 1);
+    assert_eq!(constant_test::defaults::TITLE, "untitled");
+    assert_eq!(constant_test::defaults::KEY, &[1, 2]);
+}
+
+#[test]
+fn test_fallback() {
+    assert_eq!(fallback::ids::UNKNOWN, 0x4281);
+    assert_eq!(fallback::defaults::UNKNOWN, 2);
+}
+
+// The lookups can be used in constants too.
+const SEGMENT: Option<&str> = matroska::element_name(0x1853_8067);
+
+#[test]
+fn test_element_names() {
+    assert_eq!(SEGMENT, Some("Segment"));
+    assert_eq!(matroska::element_name(matroska::ids::TIMECODE_SCALE), Some("TimecodeScale"));
+    assert_eq!(matroska::element_id("DateUTC"), Some(matroska::ids::DATE_UTC));
+    // Built-in elements the schema doesn't declare.
+    assert_eq!(matroska::element_name(0xBF), Some("CRC-32"));
+    assert_eq!(matroska::element_id("CRC-32"), Some(0xBF));
+    assert_eq!(constant_test::element_name(0x1A45_DFA3), Some("EBML"));
+    assert_eq!(constant_test::element_id("DocTypeVersion"), Some(0x4287));
+    // Unless it declares them differently.
+    assert_eq!(constant_test::element_name(0x4282), Some("Count"));
+    assert_eq!(constant_test::element_id("DocType"), None);
+
+    assert_eq!(matroska::element_name(0x4281), None);
+    assert_eq!(matroska::element_id("Cluster"), None);
+    assert_eq!(matroska::element_id("segment"), None);
+}
+
+#[test]
+fn test_header_constants() {
+    assert_eq!(HEADER, ("matroska", 1, 4));
+
+    // They're the values the schema's header declares when it's parsed at runtime.
+    let text = include_bytes!("schemas/matroska.edtd");
+    let dtd = ebml_macros::parse_dtd(text, &Default::default()).unwrap();
+    let options = CodegenOptions { mode: CodegenMode::Constants, ..Default::default() };
+    let code = codegen::generate(&dtd, "matroska", &options).unwrap();
+    for (constant, value) in &[
+        ("DOC_TYPE : & 'static str", format!("{:?}", matroska::DOC_TYPE)),
+        ("EBML_VERSION : u64", format!("{}u64", matroska::EBML_VERSION)),
+        ("DOC_TYPE_VERSION : u64", format!("{}u64", matroska::DOC_TYPE_VERSION)),
+    ] {
+        assert!(code.contains(&format!("pub const {} = {} ;", constant, value)), "{}", constant);
+    }
+}
+
+#[test]
+fn test_field_name_map() {
+    let element = |field| {
+        matroska::FIELD_NAME_MAP.iter().find(|&&(name, _)| name == field).map(|&(_, name)| name)
+    };
+    assert_eq!(element("ebml_max_id_length"), Some("EBMLMaxIDLength"));
+    assert_eq!(element("segment_uid"), Some("SegmentUID"));
+    assert_eq!(element("date_utc"), Some("DateUTC"));
+    assert_eq!(element("segment_id"), None);
+}
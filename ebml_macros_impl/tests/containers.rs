@@ -0,0 +1,36 @@
+extern crate chrono;
+extern crate ebml;
+extern crate ebml_macros;
+#[macro_use]
+extern crate ebml_macros_impl;
+
+use chrono::NaiveDate;
+
+include_ebml_dtd!(matroska, "tests/schemas/matroska.edtd");
+
+#[test]
+fn test_container_structs() {
+    let header = matroska::Ebml {
+        ebml_version: 1,
+        ebml_read_version: 1,
+        ebml_max_id_length: 4,
+        ebml_max_size_length: 8,
+        doc_type: "matroska".to_string(),
+        doc_type_version: 4,
+        doc_type_read_version: 2,
+    };
+    let info = matroska::Info {
+        segment_uid: Some([0; 16]),
+        segment_filename: None,
+        timecode_scale: 1_000_000,
+        duration: Some(1234.5),
+        date_utc: NaiveDate::from_ymd_opt(2017, 1, 1).and_then(|d| d.and_hms_opt(0, 0, 0)),
+        title: None,
+        muxing_app: "libebml".to_string(),
+        writing_app: "mkvmerge".to_string(),
+    };
+    let segment = matroska::Segment { info: vec![info.clone()] };
+
+    assert_eq!(header.doc_type, matroska::DOC_TYPE);
+    assert_eq!(segment.info, vec![info]);
+}
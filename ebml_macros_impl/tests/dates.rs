@@ -0,0 +1,76 @@
+extern crate chrono;
+extern crate ebml;
+extern crate ebml_macros;
+#[macro_use]
+extern crate ebml_macros_impl;
+
+use chrono::NaiveDate;
+use ebml_macros::runtime::{self, ReadErrorKind};
+
+macro_rules! schema {
+    ($($module:tt)*) => {
+        ebml_dtd!($($module)*, r#"
+            define elements {
+                Info := 1549a966 container [ card:1; ] {
+                    DateUTC := 4461 date [ card:?; def:20010102T00:00:00; ]
+                    Expires := 4462 date [ card:?; range:20010101T00:00:00..; ]
+                }
+            }
+        "#);
+    };
+}
+
+schema!(dates);
+schema!(#[ebml(date = "i64")] nanoseconds);
+
+// Info with a DateUTC of one second after the epoch.
+const INFO: &[u8] = &[
+    0x15, 0x49, 0xA9, 0x66, 0x8B,
+    0x44, 0x61, 0x88, 0x00, 0x00, 0x00, 0x00, 0x3B, 0x9A, 0xCA, 0x00,
+];
+
+fn read<T, F>(bytes: &[u8], read: F) -> Result<T, runtime::ReadError>
+    where F: FnOnce(&mut &[u8], u64) -> Result<T, runtime::ReadError>
+{
+    let mut r = bytes;
+    let (_, size, _) = runtime::read_header(&mut r).unwrap();
+    read(&mut r, size)
+}
+
+#[test]
+fn test_chrono_dates() {
+    let info = read(INFO, |r, size| dates::Info::read_from(r, size)).unwrap();
+    let epoch = NaiveDate::from_ymd_opt(2001, 1, 1).unwrap().and_hms_opt(0, 0, 0).unwrap();
+    assert_eq!(info.date_utc, Some(epoch + chrono::Duration::seconds(1)));
+    assert_eq!(info.expires, None);
+    let info = dates::Info { date_utc: None, expires: None };
+    assert_eq!(info.date_utc_or_default(), epoch + chrono::Duration::days(1));
+}
+
+#[test]
+fn test_nanosecond_dates() {
+    let info = read(INFO, |r, size| nanoseconds::Info::read_from(r, size)).unwrap();
+    assert_eq!(info.date_utc, Some(1_000_000_000));
+    let empty = nanoseconds::Info { date_utc: None, expires: None };
+    assert_eq!(empty.date_utc_or_default(), 86_400_000_000_000);
+    assert_eq!(nanoseconds::defaults::DATE_UTC, 86_400_000_000_000);
+
+    // Written exactly as the chrono variant writes the same date.
+    let mut bytes = Vec::new();
+    info.write_to(&mut bytes).unwrap();
+    assert_eq!(bytes, INFO);
+}
+
+#[test]
+fn test_nanosecond_ranges() {
+    // An Expires of one second before the epoch, which is out of its range.
+    let bytes = [
+        0x15, 0x49, 0xA9, 0x66, 0x8B,
+        0x44, 0x62, 0x88, 0xFF, 0xFF, 0xFF, 0xFF, 0xC4, 0x65, 0x36, 0x00,
+    ];
+    let err = read(&bytes, |r, size| nanoseconds::Info::read_from(r, size)).unwrap_err();
+    match *err.kind() {
+        ReadErrorKind::OutOfRange { .. } => {}
+        ref other => panic!("expected a range error, got {:?}", other),
+    }
+}
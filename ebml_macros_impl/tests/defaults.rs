@@ -0,0 +1,105 @@
+extern crate chrono;
+extern crate ebml;
+extern crate ebml_macros;
+#[macro_use]
+extern crate ebml_macros_impl;
+
+use chrono::NaiveDate;
+use ebml_macros::runtime;
+
+ebml_dtd!(defaults, r#"
+    define elements {
+        Defaults := 4001 container [ card:1; ] {
+            Int := 4101 int [ def:-5; card:1; ]
+            Uint := 4102 uint [ def:7; card:?; ]
+            Float := 4103 float [ def:1.5; card:1; ]
+            Date := 4104 date [ def:20010101T00:00:01; card:1; ]
+            Text := 4105 string [ def:"hello"; card:1; ]
+            Data := 4106 binary [ def:0x0102; card:?; ]
+            Repeated := 4107 uint [ def:3; card:+; ]
+            NoDefault := 4108 uint [ card:?; ]
+        }
+    }
+"#);
+
+ebml_dtd!(#[ebml(omit_defaults)] omitted, r#"
+    define elements {
+        Omitted := 4001 container [ card:1; ] {
+            Int := 4101 int [ def:-5; card:1; ]
+            Float := 4103 float [ def:0.0; card:1; ]
+            Text := 4105 string [ def:"hello"; card:1; ]
+            Uint := 4102 uint [ def:7; card:?; ]
+        }
+    }
+"#);
+
+fn read(body: &[u8]) -> defaults::Defaults {
+    let mut bytes = vec![0x40, 0x01, 0x80 | body.len() as u8];
+    bytes.extend_from_slice(body);
+    let mut r = &bytes[..];
+    let (_, size, _) = runtime::read_header(&mut r).unwrap();
+    defaults::Defaults::read_from(&mut r, size).unwrap()
+}
+
+#[test]
+fn test_defaults() {
+    let all_defaults = read(&[]);
+    assert_eq!(all_defaults, defaults::Defaults {
+        int: -5,
+        uint: None,
+        float: 1.5,
+        date: NaiveDate::from_ymd_opt(2001, 1, 1).and_then(|d| d.and_hms_opt(0, 0, 1)).unwrap(),
+        text: "hello".to_string(),
+        data: None,
+        repeated: vec![3],
+        no_default: None,
+    });
+    assert_eq!(all_defaults.uint_or_default(), 7);
+    assert_eq!(all_defaults.data_or_default(), vec![1, 2]);
+
+    // Elements which are present override their defaults.
+    let present = read(&[
+        0x41, 0x01, 0x81, 0x02,
+        0x41, 0x02, 0x81, 0x09,
+        0x41, 0x05, 0x82, b'h', b'i',
+        0x41, 0x07, 0x81, 0x01,
+        0x41, 0x07, 0x81, 0x02,
+    ]);
+    assert_eq!(present.int, 2);
+    assert_eq!(present.uint, Some(9));
+    assert_eq!(present.uint_or_default(), 9);
+    assert_eq!(present.text, "hi");
+    assert_eq!(present.repeated, vec![1, 2]);
+}
+
+fn round_trip(value: &omitted::Omitted) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    value.write_to(&mut bytes).unwrap();
+    assert_eq!(value.encoded_len().unwrap(), bytes.len() as u64);
+    let mut r = &bytes[..];
+    let (_, size, _) = runtime::read_header(&mut r).unwrap();
+    assert_eq!(&omitted::Omitted::read_from(&mut r, size).unwrap(), value);
+    bytes
+}
+
+#[test]
+fn test_omit_defaults() {
+    let mut value = omitted::Omitted {
+        int: -5,
+        float: 0.0,
+        text: "hello".to_string(),
+        uint: Some(7),
+    };
+    // Mandatory elements equal to their defaults are left out and read back as the default, but
+    // optional ones are kept.
+    assert_eq!(round_trip(&value), [0x40, 0x01, 0x84, 0x41, 0x02, 0x81, 0x07]);
+
+    // Floats are compared bit for bit.
+    value.float = -0.0;
+    value.uint = None;
+    let bytes = round_trip(&value);
+    assert_eq!(&bytes[3..6], [0x41, 0x03, 0x88]);
+
+    value.text = "hello!".to_string();
+    assert_eq!(round_trip(&value).len(), 3 + 11 + 9);
+}
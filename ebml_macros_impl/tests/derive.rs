@@ -0,0 +1,113 @@
+extern crate chrono;
+extern crate ebml;
+extern crate ebml_macros;
+#[macro_use]
+extern crate ebml_macros_impl;
+
+use ebml_macros::runtime::{EbmlRead, EbmlWrite};
+
+include_ebml_dtd!(matroska, "tests/schemas/matroska.edtd");
+
+// Only some of the children, in types of its own choosing.
+#[derive(Debug, PartialEq, EbmlRead, EbmlWrite)]
+#[ebml(schema = "tests/schemas/matroska.edtd", element = "Info")]
+pub struct Info {
+    #[ebml(element = "Title")]
+    pub title: Option<String>,
+    #[ebml(element = "SegmentUID")]
+    uid: Option<Vec<u8>>,
+    /// Kept in a narrower type than the generated struct's.
+    #[ebml(element = "TimecodeScale")]
+    timecode_scale: u32,
+    #[ebml(element = "MuxingApp")]
+    muxing_app: String,
+    #[ebml(element = "WritingApp")]
+    writing_app: String,
+}
+
+#[derive(Debug, PartialEq, EbmlRead, EbmlWrite)]
+#[ebml(schema = "tests/schemas/matroska.edtd", element = "Segment")]
+struct Segment {
+    #[ebml(element = "Info")]
+    info: Vec<Info>,
+}
+
+// Generated structs can be held too.
+#[derive(Debug, PartialEq, EbmlRead, EbmlWrite)]
+#[ebml(schema = "tests/schemas/matroska.edtd", element = "Segment")]
+struct GeneratedSegment {
+    #[ebml(element = "Info")]
+    info: Vec<matroska::Info>,
+}
+
+fn generated_info() -> matroska::Info {
+    matroska::Info {
+        segment_uid: Some([7; 16]),
+        segment_filename: Some("bunny.mkv".to_string()),
+        timecode_scale: 1_000_000,
+        duration: Some(1234.5),
+        date_utc: None,
+        title: Some("Big Buck Bunny".to_string()),
+        muxing_app: "libebml".to_string(),
+        writing_app: "mkvmerge".to_string(),
+    }
+}
+
+fn body<T: EbmlWrite>(value: &T) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    value.write_to(&mut bytes).unwrap();
+    assert_eq!(value.encoded_len().unwrap(), bytes.len() as u64);
+    // Everything after the id and the one byte size.
+    bytes.split_off(5)
+}
+
+#[test]
+fn test_read_derived() {
+    let segment = matroska::Segment { info: vec![generated_info()] };
+    let body = body(&segment);
+    let derived = Segment::read_from(&mut &body[..], body.len() as u64).unwrap();
+    assert_eq!(derived, Segment {
+        info: vec![Info {
+            title: Some("Big Buck Bunny".to_string()),
+            uid: Some(vec![7; 16]),
+            timecode_scale: 1_000_000,
+            muxing_app: "libebml".to_string(),
+            writing_app: "mkvmerge".to_string(),
+        }],
+    });
+
+    let generated = <GeneratedSegment as EbmlRead>::read_from(&mut &body[..], body.len() as u64);
+    assert_eq!(generated.unwrap().info, segment.info);
+}
+
+#[test]
+fn test_write_derived() {
+    let info = Info {
+        title: None,
+        uid: None,
+        timecode_scale: 1000,
+        muxing_app: "ebml_macros".to_string(),
+        writing_app: "ebml_macros".to_string(),
+    };
+    let segment = Segment { info: vec![info] };
+    let body = body(&segment);
+    let generated = matroska::Segment::read_from(&mut &body[..], body.len() as u64).unwrap();
+    assert_eq!(generated.info[0].timecode_scale, 1000);
+    assert_eq!(generated.info[0].muxing_app, "ebml_macros");
+    assert_eq!(Segment::read_from(&mut &body[..], body.len() as u64).unwrap(), segment);
+}
+
+#[test]
+fn test_derived_checks_schema() {
+    // The timecode scale doesn't fit in a u32.
+    let mut info = generated_info();
+    info.timecode_scale = 1 << 40;
+    let body = body(&info);
+    assert!(Info::read_from(&mut &body[..], body.len() as u64).is_err());
+
+    // Missing mandatory elements take their defaults, or else fail.
+    let body = [0x4D, 0x80, 0x81, b'x', 0x57, 0x41, 0x81, b'y'];
+    let info = Info::read_from(&mut &body[..], body.len() as u64).unwrap();
+    assert_eq!((info.timecode_scale, &*info.muxing_app), (1_000_000, "x"));
+    assert!(Info::read_from(&mut &[][..], 0).is_err());
+}
@@ -0,0 +1,128 @@
+extern crate chrono;
+extern crate ebml;
+extern crate ebml_macros;
+#[macro_use]
+extern crate ebml_macros_impl;
+
+mod common;
+
+use ebml::Id;
+use ebml_macros::runtime;
+
+use common::element;
+use wide::ElementId;
+
+// Over three hundred elements, of which the log only uses a handful.
+include_ebml_dtd!(
+    #[ebml(borrowed, streamed(Log), unknown_elements = "collect")] wide,
+    "tests/schemas/wide.edtd"
+);
+
+// Every encoded id near those in the schema, whether it's valid or not.
+fn encoded_ids() -> Vec<u32> {
+    (0..0x4400)
+        .chain(0x1A45_DF00..0x1A45_E000)
+        .chain(0x1B00_0000..0x1B00_0100)
+        .chain(0x1F43_B600..0x1F43_B700)
+        .chain(vec![0x7FFF, 0x3F_FFFF, u32::MAX])
+        .collect()
+}
+
+#[test]
+fn test_lookups_agree() {
+    let mut known = 0;
+    for encoded in encoded_ids() {
+        let id = Id::from_encoded(encoded);
+        match ElementId::from_encoded(encoded) {
+            None => assert_eq!(id, None, "{:#x}", encoded),
+            Some(ElementId::Unknown(unknown)) => {
+                assert_eq!(Some(unknown), id);
+                assert_eq!(ElementId::from_id(unknown), ElementId::Unknown(unknown));
+            }
+            Some(element) => {
+                known += 1;
+                assert_eq!(element.id(), id, "{}", element.name());
+                assert_eq!(ElementId::from_id(id.unwrap()), element);
+            }
+        }
+    }
+    // Every element of the schema was found.
+    assert_eq!(known, 306);
+    assert_eq!(ElementId::from_encoded(wide::ids::LEVEL), Some(ElementId::Level));
+    assert_eq!(ElementId::from_encoded(wide::ids::TABLE42_COLUMN3),
+               Some(ElementId::Table42Column3));
+}
+
+// Raw ids are constants, so they work where `ebml::Id`s can't: in patterns, `const fn`s and
+// other constants.
+const fn is_level(id: runtime::RawId) -> bool {
+    matches!(id, wide::raw_ids::LEVEL)
+}
+
+const LEVEL: Option<runtime::RawId> = ElementId::Level.raw_id();
+const LEVEL_IS_LEVEL: bool = is_level(runtime::RawId(wide::ids::LEVEL));
+const LEVEL_BYTES: usize = 4 - (wide::raw_ids::LEVEL.encoded().leading_zeros() / 8) as usize;
+
+#[test]
+fn test_raw_ids() {
+    assert_eq!(LEVEL, Some(wide::raw_ids::LEVEL));
+    assert!(LEVEL_IS_LEVEL && !is_level(wide::raw_ids::TABLE42_COLUMN3));
+    assert_eq!(LEVEL_BYTES, 1);
+    assert_eq!(wide::raw_ids::LEVEL.to_id(), ElementId::Level.id());
+    assert_eq!(ElementId::Unknown(Id::from_encoded(0x4FFF).unwrap()).raw_id(), None);
+}
+
+#[derive(Default)]
+struct Entries(Vec<wide::Entry>);
+
+impl wide::Handler for Entries {
+    fn on_entry(&mut self, entry: wide::Entry) {
+        self.0.push(entry);
+    }
+}
+
+#[test]
+fn test_readers_agree() {
+    // Besides its own children, the entry holds padding, a column which belongs to a table
+    // rather than to it, and an element the schema doesn't describe.
+    let children = [
+        element(&[0x81], &[3]),
+        element(&[0xEC], &[0; 4]),
+        element(&[0x41, 0x0D], &[7]),
+        element(&[0x84], &[1]),
+        element(&[0x82], &[0xFF]),
+        element(&[0x84], &[2]),
+        element(&[0x4F, 0xFF], &[9, 9]),
+    ].concat();
+    let mut body = element(&[0xBF], &runtime::crc32(&children).to_le_bytes());
+    body.extend_from_slice(&children);
+
+    let entry = wide::Entry::read_from(&mut &body[..], body.len() as u64).unwrap();
+    assert_eq!(entry.level, 3);
+    assert_eq!(entry.code, Some(-1));
+    assert_eq!(entry.source, None);
+    assert_eq!(entry.flags, vec![1, 2]);
+    let unknown: Vec<_> = entry.unknown_children.iter()
+        .map(|&(id, ref body)| (ElementId::from_id(id), body.clone()))
+        .collect();
+    assert_eq!(unknown, vec![
+        (ElementId::Table03Column1, vec![7]),
+        (ElementId::Unknown(Id::from_encoded(0x4FFF).unwrap()), vec![9, 9]),
+    ]);
+
+    let borrowed = wide::borrowed::Entry::read_from(&body).unwrap();
+    assert_eq!((borrowed.level, borrowed.code, &borrowed.flags), (3, Some(-1), &entry.flags));
+    assert_eq!(borrowed.unknown_children.len(), 2);
+    assert_eq!(borrowed.unknown_children[0].0, entry.unknown_children[0].0);
+
+    let log = element(&[0x1A, 0x45, 0xDF, 0xA3], &element(&[0x1F, 0x43, 0xB6, 0x75], &body));
+    let mut entries = Entries::default();
+    wide::stream(&mut &log[..], &mut entries).unwrap();
+    assert_eq!(entries.0, vec![entry]);
+
+    // The CRC-32 covers everything after it.
+    let last = body.len() - 1;
+    body[last] = 8;
+    let err = wide::Entry::read_from(&mut &body[..], body.len() as u64).unwrap_err();
+    assert!(matches!(*err.kind(), runtime::ReadErrorKind::CrcMismatch { .. }));
+}
@@ -0,0 +1,75 @@
+extern crate chrono;
+extern crate ebml;
+extern crate ebml_macros;
+#[macro_use]
+extern crate ebml_macros_impl;
+
+ebml_dtd!(#[ebml(dump)] dumping, r#"
+    define elements {
+        Segment := 18538067 container [ card:*; unknownsizeallowed:yes; ] {
+            Info := 1549a966 container {
+                Title := 7ba9 string
+                Duration := 4489 float
+                DateUTC := 4461 date
+                Offset := 4462 int
+            }
+            Cluster := 1f43b675 container [ card:*; ] {
+                Timecode := e7 uint
+                SimpleBlock := a3 binary [ card:*; ]
+            }
+        }
+    }
+"#);
+
+const DOCUMENT: &[u8] = &[
+    // EBML
+    0x1A, 0x45, 0xDF, 0xA3, 0x8B,
+        0x42, 0x86, 0x81, 0x01,
+        0x42, 0x82, 0x84, b't', b'e', b's', b't',
+    // Segment, of unknown size
+    0x18, 0x53, 0x80, 0x67, 0xFF,
+        0x15, 0x49, 0xA9, 0x66, 0x9B,
+            0x7B, 0xA9, 0x82, b'h', b'i',
+            0x44, 0x89, 0x84, 0x3F, 0xC0, 0x00, 0x00,
+            0x44, 0x61, 0x88, 0x00, 0x00, 0x00, 0x00, 0x3B, 0x9A, 0xCA, 0x00,
+            0x44, 0x62, 0x81, 0xFE,
+        0xEC, 0x82, 0x00, 0x00,
+        0x1F, 0x43, 0xB6, 0x75, 0x9E,
+            0xE7, 0x81, 0x05,
+            0xA3, 0x94,
+                0x00, 0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07, 0x08, 0x09,
+                0x0A, 0x0B, 0x0C, 0x0D, 0x0E, 0x0F, 0x10, 0x11, 0x12, 0x13,
+            0x4D, 0xBB, 0x82, 0xAA, 0xBB,
+];
+
+const EXPECTED: &str = "\
+EBML (0x1A45DFA3) size 11
+  EBMLVersion (0x4286) size 1: 1
+  DocType (0x4282) size 4: \"test\"
+Segment (0x18538067) size unknown
+  Info (0x1549A966) size 27
+    Title (0x7BA9) size 2: \"hi\"
+    Duration (0x4489) size 4: 1.5
+    DateUTC (0x4461) size 8: 2001-01-01T00:00:01
+    Offset (0x4462) size 1: -2
+  Void (0xEC) size 2
+  Cluster (0x1F43B675) size 30
+    Timecode (0xE7) size 1: 5
+    SimpleBlock (0xA3) size 20: 00 01 02 03 04 05 06 07 08 09 0A 0B 0C 0D 0E 0F ...
+    Unknown (0x4DBB) size 2
+";
+
+#[test]
+fn test_dump() {
+    let mut out = String::new();
+    dumping::dump(&mut &DOCUMENT[..], &mut out).unwrap();
+    assert_eq!(out, EXPECTED);
+}
+
+#[test]
+fn test_dump_truncated() {
+    let mut out = String::new();
+    let err = dumping::dump(&mut &DOCUMENT[..45], &mut out).unwrap_err();
+    assert_eq!(err.path(), &["Segment", "Info", "DateUTC"]);
+    assert!(out.ends_with("    Duration (0x4489) size 4: 1.5\n    DateUTC (0x4461) size 8"));
+}
@@ -0,0 +1,71 @@
+extern crate chrono;
+extern crate ebml;
+extern crate ebml_macros;
+#[macro_use]
+extern crate ebml_macros_impl;
+
+use std::convert::TryFrom;
+
+use ebml_macros::runtime::{self, RangePolicy, ReadContext, ReadOptions, WriteOptions};
+use tracks::{Track, TrackType};
+
+ebml_dtd!(#[ebml(enums, arbitrary)] tracks, r#"
+    define elements {
+        Track := ae container [ card:*; ] {
+            TrackType := 83 uint [ card:1; range:1,2,3,16,17,18,32,33; variants:Video=1,Audio=2; ]
+            Channels := 9f uint [ card:?; range:1..8,16; ]
+        }
+    }
+"#);
+
+#[test]
+fn test_discrete_range() {
+    assert_eq!(TrackType::from_value(1), TrackType::Video);
+    assert_eq!(TrackType::from_value(17), TrackType::V17);
+    assert_eq!(TrackType::from_value(4), TrackType::Unknown(4));
+    assert_eq!(TrackType::V33.value(), 33);
+    assert_eq!(u64::from(TrackType::Audio), 2);
+    assert_eq!(TrackType::Unknown(256).encoded_len(0x83).unwrap(), 4);
+
+    assert_eq!(TrackType::try_from(16).unwrap(), TrackType::V16);
+    let err = TrackType::try_from(4).unwrap_err();
+    assert_eq!(err.to_string(), "value 4 is outside the range 1,2,3,16,17,18,32,33");
+}
+
+#[test]
+fn test_mixed_range() {
+    let track = Track { track_type: TrackType::Audio, channels: Some(2u64) };
+
+    let mut bytes = Vec::new();
+    track.write_to(&mut bytes).unwrap();
+    assert_eq!(bytes, [0xAE, 0x86, 0x83, 0x81, 0x02, 0x9F, 0x81, 0x02]);
+    let mut r = &bytes[..];
+    let (_, size, _) = runtime::read_header(&mut r).unwrap();
+    assert_eq!(Track::read_from(&mut r, size).unwrap(), track);
+}
+
+#[test]
+fn test_out_of_range() {
+    // Values outside the range are kept as `Unknown` by lenient readers, and rejected by others.
+    let track = Track { track_type: TrackType::Unknown(4), channels: None };
+    let mut bytes = Vec::new();
+    assert!(track.write_to(&mut bytes).is_err());
+    let write_options = WriteOptions { ranges: Some(RangePolicy::Ignore) };
+    track.write_with(&mut bytes, &write_options).unwrap();
+
+    let mut r = &bytes[..];
+    let (_, size, _) = runtime::read_header(&mut r).unwrap();
+    let options = ReadOptions { lenient_ranges: true, ..ReadOptions::default() };
+    let mut ctx = ReadContext::new(options);
+    assert_eq!(Track::read_with(&mut r, size, &mut ctx).unwrap(), track);
+    assert_eq!(ctx.take_warnings().len(), 1);
+
+    let mut r = &bytes[..];
+    let (_, size, _) = runtime::read_header(&mut r).unwrap();
+    assert!(Track::read_from(&mut r, size).is_err());
+}
+
+#[test]
+fn test_round_trips() {
+    tracks::check_round_trips(100);
+}
@@ -0,0 +1,50 @@
+extern crate ebml_macros;
+
+use std::error::Error;
+
+use ebml_macros::codegen::{self, CodegenError, DeriveError, GenerateError};
+use ebml_macros::runtime::{self, BuildError, HeaderError, RangeError, ReadError, ReadErrorKind,
+                           WriteRangeError};
+use ebml_macros::{DtdError, MergeConflict, ParseError, UnknownErrorCode, ValidationDiagnostic};
+
+// Fails to compile unless `E` can be boxed into the error types of applications.
+fn assert_err<E: Error + Send + Sync + 'static>() {}
+
+#[test]
+fn test_bounds() {
+    assert_err::<DtdError>();
+    assert_err::<ParseError>();
+    assert_err::<ValidationDiagnostic>();
+    assert_err::<MergeConflict>();
+    assert_err::<UnknownErrorCode>();
+    assert_err::<CodegenError>();
+    assert_err::<DeriveError>();
+    assert_err::<GenerateError>();
+    assert_err::<ReadError>();
+    assert_err::<HeaderError>();
+    assert_err::<RangeError>();
+    assert_err::<WriteRangeError>();
+    assert_err::<BuildError>();
+}
+
+#[test]
+fn test_sources() {
+    let err = ebml_macros::parse_dtd(b"define elements { A := 81 }", &Default::default())
+        .unwrap_err();
+    let source = err.source().unwrap();
+    assert!(source.is::<ParseError>());
+    assert_eq!(source.to_string(), err.to_string());
+
+    let err = runtime::decode_string(vec![b'a', 0xFF]).unwrap_err();
+    match *err.kind() {
+        ReadErrorKind::InvalidUtf8(_) => {}
+        ref other => panic!("{:?}", other),
+    }
+    assert!(err.source().unwrap().is::<std::str::Utf8Error>());
+
+    let dtd = ebml_macros::parse_dtd(b"define elements { A := 81 mystery; }", &Default::default())
+        .unwrap();
+    let path = std::env::temp_dir().join("ebml_macros_errors_test.rs");
+    let err = codegen::generate_to_file(&dtd, "a", &Default::default(), path).unwrap_err();
+    assert!(err.source().unwrap().is::<CodegenError>());
+}
@@ -0,0 +1,112 @@
+extern crate chrono;
+extern crate ebml;
+extern crate ebml_macros;
+#[macro_use]
+extern crate ebml_macros_impl;
+
+use std::io::{self, Cursor, Read, Seek, SeekFrom};
+
+use seeking::{Cluster, Info, Segment};
+
+ebml_dtd!(seeking, r#"
+    define elements {
+        Segment := 18538067 container [ card:*; ] {
+            Cluster := 1f43b675 container [ card:*; ] {
+                Timecode := e7 uint [ card:1; ]
+                SimpleBlock := a3 binary [ card:*; ]
+            }
+            Info := 1549a966 container [ card:?; ] {
+                Title := 7ba9 string [ card:1; ]
+            }
+        }
+    }
+"#);
+
+ebml_dtd!(#[ebml(find = r"\Segment\Info\Title")] titles, r#"
+    define elements {
+        Segment := 18538067 container [ card:*; ] {
+            Cluster := 1f43b675 container [ card:*; ] {
+                Timecode := e7 uint [ card:1; ]
+                SimpleBlock := a3 binary [ card:*; ]
+            }
+            Info := 1549a966 container [ card:?; ] {
+                Title := 7ba9 string [ card:1; ]
+            }
+        }
+    }
+"#);
+
+// Counts the bytes read through it.
+struct Counting<R> {
+    inner: R,
+    read: usize,
+}
+
+impl<R: Read> Read for Counting<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let read = self.inner.read(buf)?;
+        self.read += read;
+        Ok(read)
+    }
+}
+
+impl<R: Seek> Seek for Counting<R> {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        self.inner.seek(pos)
+    }
+}
+
+fn document(info: Option<Info>) -> Vec<u8> {
+    let clusters = (0..100).map(|timecode| Cluster {
+        timecode,
+        simple_block: vec![vec![timecode as u8; 100]; 10],
+    }).collect();
+    let segment = Segment { cluster: clusters, info };
+    let mut bytes = Vec::new();
+    segment.write_to(&mut bytes).unwrap();
+    bytes
+}
+
+#[test]
+fn test_find() {
+    let info = Info { title: "synthetic".to_string() };
+    let bytes = document(Some(info.clone()));
+
+    let mut full = Counting { inner: Cursor::new(&bytes[..]), read: 0 };
+    let (_, size, _) = ebml_macros::runtime::read_header(&mut full).unwrap();
+    assert_eq!(Segment::read_from(&mut full, size).unwrap().info, Some(info.clone()));
+
+    let mut r = Counting { inner: Cursor::new(&bytes[..]), read: 0 };
+    assert_eq!(seeking::find_info(&mut r).unwrap(), Some(info));
+    assert!(r.read * 100 < full.read, "read {} of {} bytes", r.read, full.read);
+
+    let mut r = Cursor::new(&bytes[..]);
+    let cluster = seeking::find_cluster(&mut r).unwrap().unwrap();
+    assert_eq!(cluster.timecode, 0);
+}
+
+#[test]
+fn test_find_missing() {
+    let bytes = document(None);
+    assert_eq!(seeking::find_info(&mut Cursor::new(&bytes[..])).unwrap(), None);
+    assert_eq!(titles::find_title(&mut Cursor::new(&bytes[..])).unwrap(), None);
+}
+
+#[test]
+fn test_find_primitive() {
+    let bytes = document(Some(Info { title: "synthetic".to_string() }));
+    let title = titles::find_title(&mut Cursor::new(&bytes[..])).unwrap();
+    assert_eq!(title.as_deref(), Some("synthetic"));
+}
+
+#[test]
+fn test_find_error() {
+    // An info whose title ends early.
+    let bytes = [
+        0x18, 0x53, 0x80, 0x67, 0x8A,
+        0x15, 0x49, 0xA9, 0x66, 0x85,
+        0x7B, 0xA9, 0x85, 0x61, 0x62,
+    ];
+    let err = seeking::find_info(&mut Cursor::new(&bytes[..])).unwrap_err();
+    assert_eq!(err.path(), &["Segment", "Info"]);
+}
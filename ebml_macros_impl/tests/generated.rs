@@ -0,0 +1,19 @@
+extern crate chrono;
+extern crate ebml;
+extern crate ebml_macros;
+
+use ebml_macros::runtime;
+
+// Generated by the build script.
+include!(concat!(env!("OUT_DIR"), "/basic.rs"));
+
+#[test]
+fn test_generated() {
+    let bytes = [0x1A, 0x45, 0xDF, 0xA3, 0x87, 0x42, 0x82, 0x84, b'r', b'o', b'o', b't'];
+    let mut r = &bytes[..];
+    let (id, size, _) = runtime::read_header(&mut r).unwrap();
+    assert_eq!(basic::ElementId::from_id(id), basic::ElementId::Root);
+
+    let root = basic::Root::read_from(&mut r, size).unwrap();
+    assert_eq!(root.name, vec!["root".to_string()]);
+}
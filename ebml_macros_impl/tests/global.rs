@@ -0,0 +1,82 @@
+extern crate chrono;
+extern crate ebml;
+extern crate ebml_macros;
+#[macro_use]
+extern crate ebml_macros_impl;
+
+use ebml_macros::runtime::{self, ReadContext, ReadError, ReadErrorKind, ReadOptions};
+
+ebml_dtd!(#[ebml(crc32(Cluster))] global, r#"
+    define elements {
+        Cluster := 4001 container [ card:1; ] {
+            Timecode := 4101 uint [ card:1; ]
+            Block := 4102 container [ card:*; ] {
+                Data := 4201 binary [ card:1; ]
+            }
+        }
+    }
+"#);
+
+fn read_with(bytes: &[u8], options: ReadOptions) -> Result<global::Cluster, ReadError> {
+    let mut r = bytes;
+    let (_, size, _) = runtime::read_header(&mut r).unwrap();
+    global::Cluster::read_with(&mut r, size, &mut ReadContext::new(options))
+}
+
+fn cluster() -> global::Cluster {
+    global::Cluster {
+        timecode: 7,
+        block: vec![global::Block { data: vec![1, 2] }],
+    }
+}
+
+#[test]
+fn test_void() {
+    let bytes = [
+        0x40, 0x01, 0x95,
+        0xEC, 0x82, 0x00, 0x00,
+        0x41, 0x01, 0x81, 0x07,
+        0xEC, 0x80,
+        0x41, 0x02, 0x88,
+        0xEC, 0x81, 0x00,
+        0x42, 0x01, 0x82, 0x01, 0x02,
+    ];
+    assert_eq!(read_with(&bytes, ReadOptions::default()).unwrap(), cluster());
+}
+
+#[test]
+fn test_crc32() {
+    let mut bytes = Vec::new();
+    cluster().write_to(&mut bytes).unwrap();
+    assert_eq!(bytes[3..5], [0xBF, 0x84]);
+    assert_eq!(bytes[5..9], runtime::crc32(&bytes[9..]).to_le_bytes());
+    assert_eq!(read_with(&bytes, ReadOptions::default()).unwrap(), cluster());
+
+    // Corrupt the data the CRC covers.
+    let crc = runtime::crc32(&bytes[9..]);
+    let last = bytes.len() - 1;
+    bytes[last] ^= 0xFF;
+    let err = read_with(&bytes, ReadOptions::default()).unwrap_err();
+    match *err.kind() {
+        ReadErrorKind::CrcMismatch { stored, computed } => {
+            assert_eq!((stored, computed), (crc, runtime::crc32(&bytes[9..])));
+        }
+        ref other => panic!("expected a CRC mismatch, got {:?}", other),
+    }
+    assert_eq!(err.path(), &["Cluster"]);
+
+    let options = ReadOptions { lenient_crc: true, ..ReadOptions::default() };
+    let lenient = read_with(&bytes, options).unwrap();
+    assert_eq!(lenient.block[0].data, vec![1, 0xFD]);
+}
+
+#[test]
+fn test_crc32_not_first() {
+    // Only a CRC-32 at the start of an element covers anything, so one elsewhere is skipped.
+    let bytes = [
+        0x40, 0x01, 0x8A,
+        0x41, 0x01, 0x81, 0x07,
+        0xBF, 0x84, 0x00, 0x00, 0x00, 0x00,
+    ];
+    assert_eq!(read_with(&bytes, ReadOptions::default()).unwrap().timecode, 7);
+}
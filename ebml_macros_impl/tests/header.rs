@@ -0,0 +1,57 @@
+extern crate chrono;
+extern crate ebml;
+extern crate ebml_macros;
+#[macro_use]
+extern crate ebml_macros_impl;
+
+use ebml_macros::runtime::HeaderError;
+
+include_ebml_dtd!(matroska, "tests/schemas/matroska.edtd");
+
+// An EBML header with the given DocType and DocTypeReadVersion, plus an unknown element.
+fn header(doc_type: &[u8], doc_type_read_version: u8) -> Vec<u8> {
+    let mut body = vec![0x42, 0x82, 0x80 | doc_type.len() as u8];
+    body.extend_from_slice(doc_type);
+    body.extend_from_slice(&[0x42, 0x87, 0x81, 0x04]);
+    body.extend_from_slice(&[0x42, 0x85, 0x81, doc_type_read_version]);
+    body.extend_from_slice(&[0xEC, 0x81, 0x00]);
+
+    let mut bytes = vec![0x1A, 0x45, 0xDF, 0xA3, 0x80 | body.len() as u8];
+    bytes.extend_from_slice(&body);
+    bytes
+}
+
+#[test]
+fn test_good_header() {
+    let bytes = header(b"matroska", 2);
+    let header = matroska::read_ebml_header(&mut &bytes[..]).unwrap();
+    assert_eq!(header.doc_type, "matroska");
+    assert_eq!(header.doc_type_version, 4);
+    assert_eq!(header.doc_type_read_version, 2);
+    // Absent elements take their defaults.
+    assert_eq!(header.read_version, 1);
+    assert_eq!(header.max_size_length, 8);
+}
+
+#[test]
+fn test_bad_header() {
+    let bytes = header(b"webm", 2);
+    match matroska::read_ebml_header(&mut &bytes[..]) {
+        Err(HeaderError::WrongDocType { expected: "matroska", ref found }) if found == "webm" => {}
+        other => panic!("expected the wrong DocType, got {:?}", other),
+    }
+
+    let bytes = header(b"matroska", 5);
+    let err = matroska::read_ebml_header(&mut &bytes[..]).unwrap_err();
+    match err {
+        HeaderError::VersionTooNew { element: "DocTypeReadVersion", supported: 4, found: 5 } => {}
+        ref other => panic!("expected a version which is too new, got {:?}", other),
+    }
+    assert_eq!(err.to_string(), "DocTypeReadVersion is 5, but only versions up to 4 are supported");
+
+    let bytes = &header(b"matroska", 1)[..10];
+    match matroska::read_ebml_header(&mut &bytes[..]) {
+        Err(HeaderError::Read(_)) => {}
+        other => panic!("expected a read error, got {:?}", other),
+    }
+}
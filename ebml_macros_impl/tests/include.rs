@@ -0,0 +1,13 @@
+extern crate ebml;
+extern crate ebml_macros;
+#[macro_use]
+extern crate ebml_macros_impl;
+
+include_ebml_dtd!(basic, "tests/schemas/basic.edtd");
+
+#[test]
+fn test_include() {
+    assert_eq!(basic::DOC_TYPE, "basic");
+    assert_eq!(basic::ids::ROOT, 0x1A45_DFA3);
+    assert_eq!(basic::ids::NAME, 0x4282);
+}
@@ -0,0 +1,29 @@
+extern crate chrono;
+extern crate ebml;
+extern crate ebml_macros;
+#[macro_use]
+extern crate ebml_macros_impl;
+
+use ebml_macros::runtime;
+
+include_ebml_dtd!("tests/schemas/keywords.edtd");
+
+#[test]
+fn test_keywords() {
+    assert_eq!(keyword_test::DOC_TYPE, "keyword-test");
+    assert_eq!(keyword_test::ids::TYPE, 0x4281);
+    assert_eq!(keyword_test::ElementId::Self_.name(), "Self_");
+
+    let value = keyword_test::Loop {
+        r#type: 1,
+        self_: Some(keyword_test::Self_ { r#match: vec!["a".to_string()] }),
+        r#move: None,
+    };
+    assert_eq!(value.move_or_default(), 3);
+
+    let mut bytes = Vec::new();
+    value.write_to(&mut bytes).unwrap();
+    let mut r = &bytes[..];
+    let (_, size, _) = runtime::read_header(&mut r).unwrap();
+    assert_eq!(keyword_test::Loop::read_from(&mut r, size).unwrap(), value);
+}
@@ -0,0 +1,105 @@
+extern crate chrono;
+extern crate ebml;
+extern crate ebml_macros;
+#[macro_use]
+extern crate ebml_macros_impl;
+
+mod common;
+
+use ebml_macros::runtime::{self, ReadContext, ReadErrorKind, ReadLimits, ReadOptions};
+
+use common::element;
+
+ebml_dtd!(nesting, r#"
+    define elements {
+        Root := 4001 container [ card:1; unknownsizeallowed:yes; ] {
+            Value := 4101 uint [ card:*; ]
+            Note := 4102 string [ card:?; ]
+        }
+        Nested := 4002 container [ parent:Root, Nested; card:?; ]
+    }
+"#);
+
+// A `Root` holding `Nested` elements `depth` deep.
+fn nested(depth: usize) -> Vec<u8> {
+    let mut bytes = header(&[0x40, 0x01], depth * 10);
+    for level in 1..=depth {
+        bytes.extend(header(&[0x40, 0x02], (depth - level) * 10));
+    }
+    bytes
+}
+
+// A header with an eight byte size, so any body fits.
+fn header(id: &[u8], size: usize) -> Vec<u8> {
+    let mut bytes = id.to_vec();
+    bytes.push(0x01);
+    bytes.extend_from_slice(&(size as u64).to_be_bytes()[1..]);
+    bytes
+}
+
+fn read(bytes: &[u8], limits: ReadLimits) -> Result<nesting::Root, runtime::ReadError> {
+    let mut r = bytes;
+    let (_, size, _) = runtime::read_header(&mut r).unwrap();
+    let mut ctx = ReadContext::new(ReadOptions { limits, ..ReadOptions::default() });
+    nesting::Root::read_with(&mut r, size, &mut ctx)
+}
+
+#[test]
+fn test_depth() {
+    // `Root` and 63 `Nested` elements are just within the default limit.
+    let root = read(&nested(63), ReadLimits::default()).unwrap();
+    let mut depth = 0;
+    let mut next = root.nested.as_ref();
+    while let Some(inner) = next {
+        depth += 1;
+        next = inner.nested.as_deref();
+    }
+    assert_eq!(depth, 63);
+
+    let err = read(&nested(64), ReadLimits::default()).unwrap_err();
+    assert!(matches!(*err.kind(), ReadErrorKind::TooDeep(64)));
+    assert_eq!(err.path().len(), 65);
+
+    // Far past the limit, the reader fails instead of overflowing the stack.
+    assert!(read(&nested(100_000), ReadLimits::default()).is_err());
+
+    let limits = ReadLimits { max_depth: 3, ..ReadLimits::default() };
+    assert!(read(&nested(2), limits).is_ok());
+    let err = read(&nested(3), limits).unwrap_err();
+    assert!(matches!(*err.kind(), ReadErrorKind::TooDeep(3)));
+}
+
+#[test]
+fn test_element_size() {
+    // A `Note` which claims to be 16 TiB long, in a `Root` which doesn't say how long it is.
+    let bytes = [
+        0x40, 0x01, 0xFF,
+        0x41, 0x02, 0x01, 0x00, 0x10, 0x00, 0x00, 0x00, 0x00, 0x00,
+        b'a', b'b', b'c',
+    ];
+    let mut r = &bytes[3..];
+    let mut ctx = ReadContext::default();
+    let err = nesting::Root::read_unsized(&mut r, u64::MAX, &mut ctx).unwrap_err();
+    match *err.kind() {
+        ReadErrorKind::TooLarge { size, limit } => assert_eq!((size, limit), (1 << 44, 1 << 30)),
+        ref other => panic!("expected a size error, got {:?}", other),
+    }
+    assert_eq!(err.path(), &["Root"]);
+
+    // The same goes for the container itself.
+    let root = element(&[0x40, 0x01], &[0x41, 0x01, 0x81, 0x05]);
+    let limits = ReadLimits { max_element_size: 3, ..ReadLimits::default() };
+    let err = read(&root, limits).unwrap_err();
+    assert!(matches!(*err.kind(), ReadErrorKind::TooLarge { size: 4, limit: 3 }));
+}
+
+#[test]
+fn test_children() {
+    let values: Vec<u8> = (0..10).flat_map(|value| vec![0x41, 0x01, 0x81, value]).collect();
+    let root = element(&[0x40, 0x01], &values);
+    assert_eq!(read(&root, ReadLimits::default()).unwrap().value.len(), 10);
+
+    let limits = ReadLimits { max_children: 9, ..ReadLimits::default() };
+    let err = read(&root, limits).unwrap_err();
+    assert!(matches!(*err.kind(), ReadErrorKind::TooManyChildren(9)));
+}
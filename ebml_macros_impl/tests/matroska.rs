@@ -0,0 +1,75 @@
+extern crate chrono;
+extern crate ebml;
+extern crate ebml_macros;
+#[macro_use]
+extern crate ebml_macros_impl;
+
+use ebml_macros::codegen;
+use ebml_macros::runtime::{DefaultValue, ElementType, LevelRange};
+use ebml_macros::{Cardinality, ParseOptions, Severity};
+
+include_ebml_dtd!(matroska, "tests/schemas/matroska_full.edtd");
+
+const TEXT: &[u8] = include_bytes!("schemas/matroska_full.edtd");
+
+#[test]
+fn test_parse() {
+    let options = ParseOptions { validate: Some(Severity::Error), ..Default::default() };
+    let dtd = ebml_macros::parse_dtd(TEXT, &options).unwrap();
+    let diagnostics = dtd.validate();
+    assert_eq!(diagnostics.errors().count(), 0, "{:?}", diagnostics);
+
+    let schema = codegen::schema_descriptor(&dtd).unwrap();
+    assert_eq!(schema.doc_type, Some("matroska"));
+    assert_eq!(schema.elements.len(), 221);
+
+    let segment = schema.element_named("Segment").unwrap();
+    assert_eq!(segment.id, 0x1853_8067);
+    assert_eq!(segment.cardinality, Cardinality::ExactlyOne);
+    assert!(segment.parents.is_empty());
+
+    let simple_block = schema.element_named("SimpleBlock").unwrap();
+    assert_eq!(simple_block.type_, ElementType::Binary);
+    assert_eq!(&simple_block.parents[..], &["Cluster"]);
+
+    let track_number = schema.element(0xD7).unwrap();
+    assert_eq!(track_number.name, "TrackNumber");
+    assert_eq!(track_number.type_, ElementType::Uint);
+    assert_eq!(&track_number.parents[..], &["TrackEntry"]);
+
+    // Elements of user-defined types have their base type.
+    let flag_lacing = schema.element_named("FlagLacing").unwrap();
+    assert_eq!(flag_lacing.type_, ElementType::Uint);
+    assert_eq!(flag_lacing.default, Some(DefaultValue::Uint(1)));
+    assert_eq!(schema.element_named("Language").unwrap().default,
+               Some(DefaultValue::String("eng")));
+    assert_eq!(schema.element_named("Timestamp").unwrap().type_, ElementType::Uint);
+
+    let crc = schema.element(0xBF).unwrap();
+    assert_eq!(crc.name, "CRC32");
+    assert_eq!(crc.level, Some(LevelRange { start: 1, end: None }));
+
+    let poses = schema.element_named("ProjectionPoseYaw").unwrap();
+    assert_eq!(poses.default, Some(DefaultValue::Float(0.0)));
+    assert_eq!(&schema.element_named("LuminanceMin").unwrap().parents[..], &["MasteringMetadata"]);
+    assert_eq!(schema.element_named("DateUTC").unwrap().type_, ElementType::Date);
+
+    // Chapters and tags nest inside themselves.
+    assert_eq!(&schema.element_named("ChapterAtom").unwrap().parents[..],
+               &["EditionEntry", "ChapterAtom"]);
+    assert_eq!(&schema.element_named("SimpleTag").unwrap().parents[..], &["Tag", "SimpleTag"]);
+    assert_eq!(&schema.element_named("TagName").unwrap().parents[..], &["SimpleTag"]);
+
+    // Ranges aren't in the descriptors, so they're checked in the canonical text.
+    let text = dtd.to_dtd_string();
+    assert!(text.contains("TrackNumber := d7 uint [ card:1; range:1..; ]\n"));
+    assert!(text.contains("TrackUID := 73c5 uid [ card:1; ]\n"));
+}
+
+#[test]
+fn test_generated_schema() {
+    let dtd = ebml_macros::parse_dtd(TEXT, &Default::default()).unwrap();
+    let parsed = codegen::schema_descriptor(&dtd).unwrap();
+    // The descriptors borrow from different places, so they're compared as they're printed.
+    assert_eq!(format!("{:#?}", matroska::schema()), format!("{:#?}", parsed));
+}
@@ -0,0 +1,59 @@
+extern crate chrono;
+extern crate ebml;
+extern crate ebml_macros;
+#[macro_use]
+extern crate ebml_macros_impl;
+
+use std::convert::TryFrom;
+
+use ebml_macros::runtime;
+use newtypes::{Flag, Name, Track, TrackNumber};
+
+ebml_dtd!(newtypes, r#"
+    define types {
+        TrackNumber := uint [ range:1..; ]
+        Flag := uint [ range:0..1; ]
+        Name := string [ range:32..126; ]
+    }
+    define elements {
+        Track := 4001 container [ card:1; ] {
+            Number := 4101 TrackNumber [ card:1; ]
+            Enabled := 4102 Flag [ card:?; ]
+            Label := 4103 Name [ card:*; ]
+        }
+    }
+"#);
+
+#[test]
+fn test_new() {
+    assert_eq!(*TrackNumber::new(1).unwrap(), 1);
+    assert_eq!(u64::from(TrackNumber::new(7).unwrap()), 7);
+    assert_eq!(TrackNumber::try_from(3).unwrap().into_inner(), 3);
+    assert_eq!(Name::new("track".to_string()).unwrap().len(), 5);
+
+    let err = TrackNumber::new(0).unwrap_err();
+    assert_eq!(err.to_string(), "value 0 is outside the range 1..");
+    assert!(Flag::new(2).is_err());
+    assert!(Name::new("tab\t".to_string()).is_err());
+}
+
+#[test]
+fn test_fields() {
+    let track = Track {
+        number: TrackNumber::new(2).unwrap(),
+        enabled: Some(Flag::new(1).unwrap()),
+        label: vec![Name::new("main".to_string()).unwrap()],
+    };
+    let number: &TrackNumber = &track.number;
+    assert_eq!(**number, 2);
+
+    let mut bytes = Vec::new();
+    track.write_to(&mut bytes).unwrap();
+    assert_eq!(track.encoded_len().unwrap(), bytes.len() as u64);
+    // Two bytes of id, one of size and the one byte value.
+    assert_eq!(track.number.encoded_len(0x4101).unwrap(), 4);
+    assert_eq!(track.label[0].encoded_len(0x4103).unwrap(), 7);
+    let mut r = &bytes[..];
+    let (_, size, _) = runtime::read_header(&mut r).unwrap();
+    assert_eq!(Track::read_from(&mut r, size).unwrap(), track);
+}
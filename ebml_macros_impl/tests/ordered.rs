@@ -0,0 +1,60 @@
+extern crate chrono;
+extern crate ebml;
+extern crate ebml_macros;
+#[macro_use]
+extern crate ebml_macros_impl;
+
+use ebml_macros::runtime::{self, ReadContext, ReadError, ReadOptions};
+
+ebml_dtd!(ordered, r#"
+    define elements {
+        Ordered := 4001 container [ card:1; ordered:yes; ] {
+            First := 4101 uint [ card:1; ]
+            Second := 4102 string [ card:*; ]
+            Third := 4103 uint [ card:?; ]
+        }
+    }
+"#);
+
+fn read_with(bytes: &[u8], options: ReadOptions) -> (Result<ordered::Ordered, ReadError>, ReadContext) {
+    let mut r = bytes;
+    let (_, size, _) = runtime::read_header(&mut r).unwrap();
+    let mut ctx = ReadContext::new(options);
+    (ordered::Ordered::read_with(&mut r, size, &mut ctx), ctx)
+}
+
+#[test]
+fn test_ordered() {
+    let mut value = ordered::Ordered { first: 0, second: Vec::new(), third: None };
+    // Populated in the opposite order to the declarations.
+    value.third = Some(3);
+    value.second.push("a".to_string());
+    value.second.push("b".to_string());
+    value.first = 1;
+
+    let mut bytes = Vec::new();
+    value.write_to(&mut bytes).unwrap();
+    assert_eq!(bytes, [
+        0x40, 0x01, 0x90,
+        0x41, 0x01, 0x81, 0x01,
+        0x41, 0x02, 0x81, b'a',
+        0x41, 0x02, 0x81, b'b',
+        0x41, 0x03, 0x81, 0x03,
+    ]);
+    assert_eq!(read_with(&bytes, ReadOptions::default()).0.unwrap(), value);
+
+    let reordered = [
+        0x40, 0x01, 0x90,
+        0x41, 0x01, 0x81, 0x01,
+        0x41, 0x02, 0x81, b'a',
+        0x41, 0x03, 0x81, 0x03,
+        0x41, 0x02, 0x81, b'b',
+    ];
+    let err = read_with(&reordered, ReadOptions::default()).0.unwrap_err();
+    assert_eq!(err.to_string(), "`Second` element is out of order in Ordered");
+
+    let options = ReadOptions { lenient_order: true, ..ReadOptions::default() };
+    let (lenient, ctx) = read_with(&reordered, options);
+    assert_eq!(lenient.unwrap(), value);
+    assert_eq!(ctx.warnings().len(), 1);
+}
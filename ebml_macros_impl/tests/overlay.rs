@@ -0,0 +1,29 @@
+extern crate chrono;
+extern crate ebml;
+extern crate ebml_macros;
+#[macro_use]
+extern crate ebml_macros_impl;
+
+include_ebml_dtd!(matroska, "tests/schemas/matroska_full.edtd");
+include_ebml_dtd!(#[ebml(overlay = "tests/schemas/webm.overlay", enums)] webm,
+                  "tests/schemas/matroska_full.edtd");
+
+#[test]
+fn test_overlay() {
+    assert_eq!(matroska::DOC_TYPE, "matroska");
+    assert_eq!(webm::DOC_TYPE, "webm");
+
+    // Removed elements are gone, along with everything inside them.
+    assert_eq!(matroska::element_id("Attachments"), Some(0x1941_A469));
+    assert_eq!(webm::element_id("Attachments"), None);
+    assert_eq!(matroska::element_id("FileData"), Some(0x465C));
+    assert_eq!(webm::element_id("FileData"), None);
+    assert_eq!(webm::element_name(0x6944), None);
+    assert_eq!(webm::ids::SEGMENT, matroska::ids::SEGMENT);
+
+    // Refined elements keep what the overlay doesn't change.
+    let _: webm::TrackType = webm::TrackType::Subtitle;
+    assert_eq!(matroska::defaults::CONTENT_ENCODING_TYPE, 0);
+    assert_eq!(webm::defaults::CONTENT_ENCODING_TYPE, 1);
+    assert_eq!(webm::defaults::CONTENT_ENCODING_SCOPE, 1);
+}
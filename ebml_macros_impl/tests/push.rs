@@ -0,0 +1,158 @@
+extern crate chrono;
+extern crate ebml;
+extern crate ebml_macros;
+#[macro_use]
+extern crate ebml_macros_impl;
+
+use ebml_macros::runtime::{ReadErrorKind, ReadLimits, ReadOptions, UnknownElements, Value};
+
+use pushed::{ElementId, Event, Parser};
+
+ebml_dtd!(#[ebml(push, unknown_size(Cluster), unknown_elements = "collect")] pushed, r#"
+    define elements {
+        Segment := 18538067 container [ card:*; unknownsizeallowed:yes; ] {
+            Info := 1549a966 container [ card:1; ] {
+                Title := 7ba9 string [ card:?; ]
+                Duration := 4489 float [ card:?; ]
+            }
+            Cluster := 1f43b675 container [ card:*; unknownsizeallowed:yes; ] {
+                Timecode := e7 uint [ card:1; ]
+                Offset := fb int [ card:?; ]
+                SimpleBlock := a3 binary [ card:*; ]
+            }
+        }
+    }
+"#);
+
+fn segment() -> pushed::Segment {
+    let cluster = |timecode| pushed::Cluster {
+        timecode,
+        offset: Some(-1),
+        simple_block: vec![vec![timecode as u8; 3]; 2],
+        unknown_children: Vec::new(),
+    };
+    pushed::Segment {
+        info: pushed::Info {
+            title: Some("pushed".to_string()),
+            duration: Some(2.5),
+            unknown_children: Vec::new(),
+        },
+        cluster: vec![cluster(1), cluster(2)],
+        unknown_children: Vec::new(),
+    }
+}
+
+fn fixture() -> Vec<u8> {
+    let mut bytes = Vec::new();
+    segment().write_to(&mut bytes).unwrap();
+    bytes
+}
+
+fn feed_whole(bytes: &[u8]) -> Vec<Event> {
+    let mut parser = Parser::new();
+    let mut events = parser.feed(bytes).unwrap();
+    events.extend(parser.finish().unwrap());
+    events
+}
+
+#[test]
+fn test_events() {
+    let events = feed_whole(&fixture());
+    assert!(matches!(events[0], Event::BeginElement(ElementId::Segment, Some(_))));
+    assert_eq!(&events[1..5], &[
+        Event::BeginElement(ElementId::Info, Some(20)),
+        Event::Value(ElementId::Title, Value::String("pushed".to_string())),
+        Event::Value(ElementId::Duration, Value::Float(2.5)),
+        Event::EndElement(ElementId::Info),
+    ]);
+    // Clusters are written with an unknown size, and end where the next one starts.
+    assert_eq!(&events[5..11], &[
+        Event::BeginElement(ElementId::Cluster, None),
+        Event::Value(ElementId::Timecode, Value::Uint(1)),
+        Event::Value(ElementId::Offset, Value::Int(-1)),
+        Event::Value(ElementId::SimpleBlock, Value::Binary(vec![1; 3])),
+        Event::Value(ElementId::SimpleBlock, Value::Binary(vec![1; 3])),
+        Event::EndElement(ElementId::Cluster),
+    ]);
+    assert_eq!(events[11], Event::BeginElement(ElementId::Cluster, None));
+    assert_eq!(&events[events.len() - 2..], &[
+        Event::EndElement(ElementId::Cluster),
+        Event::EndElement(ElementId::Segment),
+    ]);
+    assert_eq!(events.len(), 18);
+}
+
+#[test]
+fn test_byte_at_a_time() {
+    let bytes = fixture();
+    let mut parser = Parser::new();
+    let mut events = Vec::new();
+    for byte in &bytes {
+        events.extend(parser.feed(&[*byte]).unwrap());
+    }
+    events.extend(parser.finish().unwrap());
+    assert_eq!(events, feed_whole(&bytes));
+
+    // Chunks of any other size give the same events too.
+    for len in 2..10 {
+        let mut parser = Parser::new();
+        let mut chunked = Vec::new();
+        for chunk in bytes.chunks(len) {
+            chunked.extend(parser.feed(chunk).unwrap());
+        }
+        chunked.extend(parser.finish().unwrap());
+        assert_eq!(chunked, events);
+    }
+}
+
+#[test]
+fn test_unknown_elements() {
+    // An `Info` holding a `Title` and an element the schema doesn't describe.
+    let bytes = [
+        0x15, 0x49, 0xA9, 0x66, 0x88,
+        0x7B, 0xA9, 0x81, b'a',
+        0x45, 0x01, 0x81, 0x07,
+    ];
+    let events = feed_whole(&bytes);
+    let id = ebml::Id::from_encoded(0x4501).unwrap();
+    assert_eq!(events[2], Event::Value(ElementId::Unknown(id), Value::Binary(vec![7])));
+
+    let options = ReadOptions {
+        unknown_elements: Some(UnknownElements::Error),
+        ..ReadOptions::default()
+    };
+    let err = Parser::with_options(options).feed(&bytes).unwrap_err();
+    assert!(matches!(*err.kind(), ReadErrorKind::UnknownElement(_)));
+    assert_eq!(err.path(), &["Info"]);
+}
+
+#[test]
+fn test_errors() {
+    // Limits are applied as elements are found.
+    let limits = ReadLimits { max_depth: 1, ..ReadLimits::default() };
+    let mut parser = Parser::with_options(ReadOptions { limits, ..ReadOptions::default() });
+    let err = parser.feed(&fixture()).unwrap_err();
+    assert!(matches!(*err.kind(), ReadErrorKind::TooDeep(1)));
+    assert_eq!(err.path(), &["Segment", "Info"]);
+
+    // A child which claims to be longer than its parent.
+    let bytes = [0x15, 0x49, 0xA9, 0x66, 0x83, 0x7B, 0xA9, 0x85];
+    let err = Parser::new().feed(&bytes).unwrap_err();
+    assert!(matches!(*err.kind(), ReadErrorKind::Overrun));
+
+    // Scalars too long for their type are rejected from their headers alone.
+    let bytes = [0x18, 0x53, 0x80, 0x67, 0xFF, 0x1F, 0x43, 0xB6, 0x75, 0xFF, 0xE7, 0x89];
+    let err = Parser::new().feed(&bytes).unwrap_err();
+    assert!(matches!(*err.kind(), ReadErrorKind::InvalidValue));
+    assert_eq!(err.path(), &["Segment", "Cluster", "Timecode"]);
+    let bytes = [0x18, 0x53, 0x80, 0x67, 0xFF, 0x15, 0x49, 0xA9, 0x66, 0x85, 0x44, 0x89, 0x82];
+    let err = Parser::new().feed(&bytes).unwrap_err();
+    assert!(matches!(*err.kind(), ReadErrorKind::InvalidValue));
+    assert_eq!(err.path(), &["Segment", "Info", "Duration"]);
+
+    // Input which ends in the middle of an element.
+    let bytes = fixture();
+    let mut parser = Parser::new();
+    parser.feed(&bytes[..bytes.len() - 1]).unwrap();
+    assert!(matches!(*parser.finish().unwrap_err().kind(), ReadErrorKind::UnexpectedEof));
+}
@@ -0,0 +1,156 @@
+extern crate chrono;
+extern crate ebml;
+extern crate ebml_macros;
+#[macro_use]
+extern crate ebml_macros_impl;
+
+use ebml_macros::runtime::{self, RangePolicy, ReadContext, ReadError, ReadErrorKind, ReadOptions,
+                           WriteOptions, WriteRangeError};
+
+ebml_dtd!(ranges, r#"
+    define types {
+        percent := float [ range:0.0<=..<=100.0; ]
+    }
+    define elements {
+        Ranges := 4001 container [ card:1; ] {
+            Int := 4101 int [ range:-2..5; card:?; ]
+            Uint := 4102 uint [ range:1,10..20; card:?; ]
+            Float := 4103 percent [ card:?; ]
+            Date := 4104 date [ range:20010101T00:00:00..; card:?; ]
+            Text := 4105 string [ range:32..126; card:?; ]
+        }
+    }
+"#);
+
+ebml_dtd!(#[ebml(write_ranges = "clamp")] clamped, r#"
+    define elements {
+        Clamped := 4001 container [ card:1; ] {
+            Uint := 4102 uint [ range:1,10..20; card:?; ]
+            Text := 4105 string [ range:32..126; card:?; ]
+        }
+    }
+"#);
+
+fn read_with(body: &[u8], options: ReadOptions) -> (Result<ranges::Ranges, ReadError>, ReadContext) {
+    let mut bytes = vec![0x40, 0x01, 0x80 | body.len() as u8];
+    bytes.extend_from_slice(body);
+    let mut r = &bytes[..];
+    let (_, size, _) = runtime::read_header(&mut r).unwrap();
+    let mut ctx = ReadContext::new(options);
+    (ranges::Ranges::read_with(&mut r, size, &mut ctx), ctx)
+}
+
+fn read(body: &[u8]) -> Result<ranges::Ranges, ReadError> {
+    read_with(body, ReadOptions::default()).0
+}
+
+fn assert_out_of_range(body: &[u8], element: &str, value: &str, range: &str) {
+    let err = read(body).unwrap_err();
+    match *err.kind() {
+        ReadErrorKind::OutOfRange { value: ref v, range: ref r } => {
+            assert_eq!((v.as_str(), r.as_str()), (value, range));
+        }
+        ref other => panic!("expected an out of range error, got {:?}", other),
+    }
+    assert_eq!(err.path(), &["Ranges", element]);
+}
+
+#[test]
+fn test_in_range() {
+    let ranges = read(&[
+        0x41, 0x01, 0x81, 0xFE,
+        0x41, 0x02, 0x81, 0x0F,
+        0x41, 0x03, 0x84, 0x42, 0xC8, 0x00, 0x00,
+        0x41, 0x04, 0x88, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+        0x41, 0x05, 0x82, b'o', b'k',
+    ]).unwrap();
+    assert_eq!(ranges.int, Some(-2));
+    assert_eq!(ranges.uint, Some(15));
+    assert_eq!(ranges.float.as_deref(), Some(&100.0));
+    assert!(ranges.date.is_some());
+    assert_eq!(ranges.text, Some("ok".to_string()));
+}
+
+#[test]
+fn test_out_of_range() {
+    assert_out_of_range(&[0x41, 0x01, 0x81, 0x06], "Int", "6", "-2..5");
+    assert_out_of_range(&[0x41, 0x02, 0x81, 0x02], "Uint", "2", "1,10..20");
+    assert_out_of_range(
+        &[0x41, 0x03, 0x84, 0xBF, 0x80, 0x00, 0x00],
+        "Float", "-1", "0.0<=..<=100.0",
+    );
+    assert_out_of_range(
+        &[0x41, 0x04, 0x88, 0xFF, 0xFF, 0xFF, 0xFF, 0xC4, 0x65, 0x36, 0x00],
+        "Date", "20001231T23:59:59", "20010101T00:00:00..",
+    );
+    assert_out_of_range(&[0x41, 0x05, 0x82, b'o', b'\n'], "Text", "\"o\\n\"", "32..126");
+}
+
+#[test]
+fn test_lenient() {
+    let options = ReadOptions { lenient_ranges: true, ..ReadOptions::default() };
+    let (ranges, mut ctx) = read_with(&[
+        0x41, 0x01, 0x81, 0x06,
+        0x41, 0x05, 0x82, b'o', b'\n',
+    ], options);
+    let ranges = ranges.unwrap();
+    assert_eq!(ranges.int, Some(6));
+    assert_eq!(ranges.text, Some("o\n".to_string()));
+
+    let warnings = ctx.take_warnings();
+    assert_eq!(warnings.len(), 2);
+    assert_eq!(warnings[0].to_string(), "value 6 is outside the range -2..5 in Ranges/Int");
+    assert_eq!(warnings[1].path(), &["Ranges", "Text"]);
+    assert!(ctx.warnings().is_empty());
+}
+
+fn ranges(uint: u64) -> ranges::Ranges {
+    ranges::Ranges { int: None, uint: Some(uint), float: None, date: None, text: None }
+}
+
+fn write_error<T: runtime::EbmlWrite>(value: &T, options: &WriteOptions) -> WriteRangeError {
+    let err = value.write_with(&mut Vec::new(), options).unwrap_err();
+    assert!(value.encoded_len_with(options).is_err());
+    err.get_ref().and_then(|err| err.downcast_ref::<WriteRangeError>()).unwrap().clone()
+}
+
+#[test]
+fn test_write_error() {
+    let err = write_error(&ranges(7), &WriteOptions::default());
+    assert_eq!((err.element, err.error.value.as_str()), ("Uint", "7"));
+    assert_eq!(err.to_string(), "can't write Uint: value 7 is outside the range 1,10..20");
+
+    // Values in the range are written as usual.
+    let mut bytes = Vec::new();
+    ranges(12).write_to(&mut bytes).unwrap();
+    assert_eq!(bytes, [0x40, 0x01, 0x84, 0x41, 0x02, 0x81, 0x0C]);
+}
+
+#[test]
+fn test_write_clamp() {
+    let mut bytes = Vec::new();
+    let value = clamped::Clamped { uint: Some(7), text: None };
+    assert_eq!(value.write_to(&mut bytes).unwrap(), value.encoded_len().unwrap());
+    assert_eq!(bytes, [0x40, 0x01, 0x84, 0x41, 0x02, 0x81, 0x0A]);
+
+    // The policy can be overridden when writing.
+    let options = WriteOptions { ranges: Some(RangePolicy::Clamp) };
+    bytes.clear();
+    ranges(1000).write_with(&mut bytes, &options).unwrap();
+    assert_eq!(bytes, [0x40, 0x01, 0x84, 0x41, 0x02, 0x81, 0x14]);
+    let options = WriteOptions { ranges: Some(RangePolicy::Error) };
+    assert_eq!(write_error(&value, &options).element, "Uint");
+
+    // Only numbers can be clamped.
+    let value = clamped::Clamped { uint: None, text: Some("tab\t".to_string()) };
+    let err = write_error(&value, &WriteOptions::default());
+    assert_eq!((err.element, err.error.value.as_str()), ("Text", "\"tab\\t\""));
+}
+
+#[test]
+fn test_write_ignore() {
+    let options = WriteOptions { ranges: Some(RangePolicy::Ignore) };
+    let mut bytes = Vec::new();
+    ranges(7).write_with(&mut bytes, &options).unwrap();
+    assert_out_of_range(&bytes[3..], "Uint", "7", "1,10..20");
+}
@@ -0,0 +1,107 @@
+extern crate chrono;
+extern crate ebml;
+extern crate ebml_macros;
+#[macro_use]
+extern crate ebml_macros_impl;
+
+mod common;
+
+use chrono::NaiveDate;
+use ebml_macros::runtime::{self, ReadErrorKind};
+
+use common::element;
+
+include_ebml_dtd!(matroska, "tests/schemas/matroska.edtd");
+
+fn header() -> Vec<u8> {
+    let body = [
+        element(&[0x42, 0x86], &[1]),
+        element(&[0x42, 0xF7], &[1]),
+        element(&[0x42, 0xF2], &[4]),
+        element(&[0x42, 0xF3], &[8]),
+        element(&[0x42, 0x82], b"matroska\0\0"),
+        // A Void element, which isn't expected here and gets skipped.
+        element(&[0xEC], &[0; 3]),
+        element(&[0x42, 0x87], &[4]),
+        element(&[0x42, 0x85], &[2]),
+    ].concat();
+    element(&[0x1A, 0x45, 0xDF, 0xA3], &body)
+}
+
+fn segment() -> Vec<u8> {
+    let info = [
+        element(&[0x2A, 0xD7, 0xB1], &[0x0F, 0x42, 0x40]),
+        element(&[0x44, 0x89], &[0x40, 0x93, 0x4A, 0x00, 0x00, 0x00, 0x00, 0x00]),
+        element(&[0x44, 0x61], &[0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x01]),
+        element(&[0x4D, 0x80], b"libebml"),
+        element(&[0x57, 0x41], b"mkvmerge"),
+        element(&[0x7B, 0xA9], "Big Buck Bunny \u{1F430}".as_bytes()),
+    ].concat();
+    element(&[0x18, 0x53, 0x80, 0x67], &element(&[0x15, 0x49, 0xA9, 0x66], &info))
+}
+
+#[test]
+fn test_read() {
+    let bytes = [header(), segment()].concat();
+    let mut r = &bytes[..];
+
+    let (id, size, _) = runtime::read_header(&mut r).unwrap();
+    assert_eq!(matroska::ElementId::from_id(id), matroska::ElementId::Ebml);
+    let header = matroska::Ebml::read_from(&mut r, size).unwrap();
+    assert_eq!(header, matroska::Ebml {
+        ebml_version: 1,
+        ebml_read_version: 1,
+        ebml_max_id_length: 4,
+        ebml_max_size_length: 8,
+        doc_type: "matroska".to_string(),
+        doc_type_version: 4,
+        doc_type_read_version: 2,
+    });
+
+    let (id, size, _) = runtime::read_header(&mut r).unwrap();
+    assert_eq!(matroska::ElementId::from_id(id), matroska::ElementId::Segment);
+    let segment = matroska::Segment::read_from(&mut r, size).unwrap();
+    assert_eq!(segment, matroska::Segment {
+        info: vec![matroska::Info {
+            segment_uid: None,
+            segment_filename: None,
+            timecode_scale: 1_000_000,
+            duration: Some(1234.5),
+            date_utc: NaiveDate::from_ymd_opt(2001, 1, 1).and_then(|d| d.and_hms_nano_opt(0, 0, 0, 1)),
+            title: Some("Big Buck Bunny \u{1F430}".to_string()),
+            muxing_app: "libebml".to_string(),
+            writing_app: "mkvmerge".to_string(),
+        }],
+    });
+    assert!(r.is_empty());
+}
+
+#[test]
+fn test_read_errors() {
+    // Cut off in the middle of the DocType.
+    let bytes = header();
+    let mut r = &bytes[..30];
+    let (_, size, _) = runtime::read_header(&mut r).unwrap();
+    let err = matroska::Ebml::read_from(&mut r, size).unwrap_err();
+    match *err.kind() {
+        ReadErrorKind::UnexpectedEof => {}
+        ref other => panic!("{:?}", other),
+    }
+    assert_eq!(err.path(), &["EBML", "DocType"]);
+    assert_eq!(err.to_string(), "unexpected end of input in EBML/DocType");
+
+    // Missing the mandatory MuxingApp and WritingApp.
+    let bytes = element(&[0x15, 0x49, 0xA9, 0x66], &element(&[0x2A, 0xD7, 0xB1], &[1]));
+    let mut r = &bytes[..];
+    let (_, size, _) = runtime::read_header(&mut r).unwrap();
+    let err = matroska::Info::read_from(&mut r, size).unwrap_err();
+    assert_eq!(err.to_string(), "missing `MuxingApp` element in Info");
+
+    // A child which claims to be bigger than its parent.
+    let mut bytes = element(&[0x15, 0x49, 0xA9, 0x66], &element(&[0x7B, 0xA9], b"title"));
+    bytes[4] -= 1;
+    let mut r = &bytes[..];
+    let (_, size, _) = runtime::read_header(&mut r).unwrap();
+    let err = matroska::Info::read_from(&mut r, size).unwrap_err();
+    assert_eq!(err.to_string(), "element extends past the end of its parent in Info");
+}
@@ -0,0 +1,41 @@
+extern crate chrono;
+extern crate ebml;
+extern crate ebml_macros;
+#[macro_use]
+extern crate ebml_macros_impl;
+
+use ebml_macros::codegen;
+use ebml_macros::runtime::{DefaultValue, ElementType, LevelRange};
+use ebml_macros::Cardinality;
+
+include_ebml_dtd!(matroska, "tests/schemas/matroska.edtd");
+
+#[test]
+fn test_schema() {
+    let schema = matroska::schema();
+    assert_eq!(schema.doc_type, Some("matroska"));
+    assert_eq!(schema.elements.len(), 19);
+
+    let scale = schema.element(0x2A_D7B1).unwrap();
+    assert_eq!(scale.name, "TimecodeScale");
+    assert_eq!(scale.type_, ElementType::Uint);
+    assert_eq!(scale.cardinality, Cardinality::ExactlyOne);
+    assert_eq!(&scale.parents[..], &["Info"]);
+    assert_eq!(scale.default, Some(DefaultValue::Uint(1_000_000)));
+
+    let void = schema.element_named("Void").unwrap();
+    assert_eq!(void.id, 0xEC);
+    assert_eq!(void.level, Some(LevelRange { start: 1, end: None }));
+    assert!(void.parents.is_empty());
+    assert_eq!(schema.element_named("Segment").unwrap().type_, ElementType::Container);
+    assert_eq!(schema.element(0x1234), None);
+}
+
+#[test]
+fn test_schema_matches_parsed_dtd() {
+    let text = include_bytes!("schemas/matroska.edtd");
+    let dtd = ebml_macros::parse_dtd(text, &Default::default()).unwrap();
+    let parsed = codegen::schema_descriptor(&dtd).unwrap();
+    // The descriptors borrow from different places, so they're compared as they're printed.
+    assert_eq!(format!("{:#?}", matroska::schema()), format!("{:#?}", parsed));
+}
@@ -0,0 +1,33 @@
+extern crate chrono;
+extern crate ebml;
+extern crate ebml_macros;
+#[macro_use]
+extern crate ebml_macros_impl;
+extern crate serde;
+extern crate serde_json;
+
+ebml_dtd!(#[ebml(serde)] info, r#"
+    define elements {
+        Info := 1549a966 container [ card:1; ] {
+            Title := 7ba9 string [ card:1; ]
+            TimecodeScale := 2ad7b1 uint [ card:1; ]
+            Chapter := 4101 container [ card:*; ] {
+                Start := 91 float [ card:1; ]
+            }
+        }
+    }
+"#);
+
+#[test]
+fn test_serde() {
+    let info = info::Info {
+        title: "Title".to_string(),
+        timecode_scale: 1000000,
+        chapter: vec![info::Chapter { start: 1.5 }],
+    };
+    let json = serde_json::to_string(&info).unwrap();
+    assert_eq!(json, r#"{"title":"Title","timecode_scale":1000000,"chapter":[{"start":1.5}]}"#);
+    assert_eq!(serde_json::from_str::<info::Info>(&json).unwrap(), info);
+
+    assert_eq!(serde_json::to_string(&info::ElementId::Title).unwrap(), r#""Title""#);
+}
@@ -0,0 +1,92 @@
+extern crate chrono;
+extern crate ebml;
+extern crate ebml_macros;
+#[macro_use]
+extern crate ebml_macros_impl;
+
+use ebml_macros::runtime::{self, ReadError, ReadErrorKind};
+
+macro_rules! schema {
+    ($($module:tt)*) => {
+        ebml_dtd!($($module)*, r#"
+            define elements {
+                Sizes := 4001 container [ card:1; ] {
+                    Uid := 4101 binary [ size:16; card:?; ]
+                    Name := 4102 string [ size:1..4; card:?; ]
+                    Payload := 4103 binary [ size:2..; card:?; ]
+                }
+            }
+        "#);
+    };
+}
+
+schema!(sizes);
+schema!(#[ebml(fixed_binary_limit = "8")] vectors);
+
+fn read(body: &[u8]) -> Result<sizes::Sizes, ReadError> {
+    let mut bytes = vec![0x40, 0x01, 0x80 | body.len() as u8];
+    bytes.extend_from_slice(body);
+    let mut r = &bytes[..];
+    let (_, size, _) = runtime::read_header(&mut r).unwrap();
+    sizes::Sizes::read_from(&mut r, size)
+}
+
+fn assert_size_not_allowed(body: &[u8], element: &str, size: u64, allowed: &str) {
+    let err = read(body).unwrap_err();
+    match *err.kind() {
+        ReadErrorKind::SizeNotAllowed { size: s, allowed: ref a } => {
+            assert_eq!((s, a.as_str()), (size, allowed));
+        }
+        ref other => panic!("expected a size error, got {:?}", other),
+    }
+    assert_eq!(err.path(), &["Sizes", element]);
+}
+
+#[test]
+fn test_fixed_size() {
+    let mut body = vec![0x41, 0x01, 0x90];
+    body.extend_from_slice(&[7; 16]);
+    assert_eq!(read(&body).unwrap().uid, Some([7; 16]));
+
+    assert_size_not_allowed(&[0x41, 0x01, 0x82, 7, 7], "Uid", 2, "16");
+    assert_size_not_allowed(&[0x41, 0x02, 0x85, b'a', b'b', b'c', b'd', b'e'], "Name", 5, "1..4");
+}
+
+#[test]
+fn test_fixed_size_round_trip() {
+    let value = sizes::Sizes { uid: Some([3; 16]), name: None, payload: None };
+    let mut bytes = Vec::new();
+    value.write_to(&mut bytes).unwrap();
+    assert_eq!(bytes[3..6], [0x41, 0x01, 0x90]);
+    let mut r = &bytes[..];
+    let (_, size, _) = runtime::read_header(&mut r).unwrap();
+    assert_eq!(sizes::Sizes::read_from(&mut r, size).unwrap(), value);
+
+    // Fixed sizes longer than the limit are left in vectors.
+    let value = vectors::Sizes { uid: Some(vec![3; 16]), name: None, payload: None };
+    let mut written = Vec::new();
+    value.write_to(&mut written).unwrap();
+    assert_eq!(written, bytes);
+}
+
+#[test]
+fn test_open_size() {
+    let sizes = read(&[0x41, 0x03, 0x83, 1, 2, 3]).unwrap();
+    assert_eq!(sizes.payload, Some(vec![1, 2, 3]));
+
+    assert_size_not_allowed(&[0x41, 0x03, 0x81, 1], "Payload", 1, "2..");
+}
+
+#[test]
+fn test_checked_before_reading() {
+    // An 8MB Uid inside a parent large enough to hold it. The size is rejected without trying to
+    // read a body which isn't there.
+    let bytes = [0x40, 0x01, 0x1F, 0xFF, 0xFF, 0xF0, 0x41, 0x01, 0x10, 0x80, 0x00, 0x00];
+    let mut r = &bytes[..];
+    let (_, size, _) = runtime::read_header(&mut r).unwrap();
+    let err = sizes::Sizes::read_from(&mut r, size).unwrap_err();
+    match *err.kind() {
+        ReadErrorKind::SizeNotAllowed { size, .. } => assert_eq!(size, 0x80_0000),
+        ref other => panic!("expected a size error, got {:?}", other),
+    }
+}
@@ -0,0 +1,128 @@
+extern crate chrono;
+extern crate ebml;
+extern crate ebml_macros;
+#[macro_use]
+extern crate ebml_macros_impl;
+
+use ebml::Id;
+use ebml_macros::runtime::{self, ReadContext};
+
+macro_rules! schema {
+    ($($module:tt)*) => {
+        ebml_dtd!($($module)*, r#"
+            define elements {
+                Segment := 18538067 container [ card:*; unknownsizeallowed:yes; ] {
+                    Cluster := 1f43b675 container [ card:*; unknownsizeallowed:yes; ] {
+                        Timecode := e7 uint [ card:1; ]
+                        SimpleBlock := a3 binary [ card:*; ]
+                    }
+                    Info := 1549a966 container [ card:1; ] {
+                        Title := 7ba9 string [ card:1; ]
+                    }
+                }
+            }
+        "#);
+    };
+}
+
+schema!(sized);
+schema!(#[ebml(unknown_size(Segment, Cluster))] streamable);
+
+fn clusters() -> Vec<sized::Cluster> {
+    (0..20).map(|timecode| sized::Cluster {
+        timecode,
+        simple_block: vec![vec![timecode as u8; 50]; 3],
+    }).collect()
+}
+
+fn segment() -> sized::Segment {
+    sized::Segment {
+        cluster: clusters(),
+        info: sized::Info { title: "sizing".to_string() },
+    }
+}
+
+fn streamable_segment() -> streamable::Segment {
+    let clusters = clusters().into_iter().map(|cluster| streamable::Cluster {
+        timecode: cluster.timecode,
+        simple_block: cluster.simple_block,
+    }).collect();
+    streamable::Segment {
+        cluster: clusters,
+        info: streamable::Info { title: "sizing".to_string() },
+    }
+}
+
+#[test]
+fn test_precomputed_sizes() {
+    let segment = segment();
+    let mut bytes = Vec::new();
+    let written = segment.write_to(&mut bytes).unwrap();
+    assert_eq!(written, bytes.len() as u64);
+    assert_eq!(segment.encoded_len().unwrap(), written);
+    assert_eq!(segment.cluster[0].encoded_len().unwrap(), 4 + 2 + 3 + 3 * 52);
+
+    let mut r = &bytes[..];
+    let (_, size, header_size) = runtime::read_header(&mut r).unwrap();
+    assert_eq!(header_size + size, written);
+    assert_eq!(sized::Segment::read_from(&mut r, size).unwrap(), segment);
+}
+
+#[test]
+fn test_unknown_sizes() {
+    let segment = streamable_segment();
+    let mut bytes = Vec::new();
+    let written = segment.write_to(&mut bytes).unwrap();
+    assert_eq!(written, bytes.len() as u64);
+    assert_eq!(segment.encoded_len().unwrap(), written);
+    assert_eq!(bytes[..12], [0x18, 0x53, 0x80, 0x67, 0x01, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF,
+                             0xFF]);
+
+    // Each cluster is ended by the next one, and the last by `Info`. The segment is ended by the
+    // end of the input.
+    let mut r = &bytes[..];
+    let (_, size, header_size) = runtime::read_child_header(&mut r).unwrap();
+    assert_eq!(size, None);
+    let (read, used, next) =
+        sized::Segment::read_unsized(&mut r, u64::MAX, &mut ReadContext::default()).unwrap();
+    assert_eq!(read, self::segment());
+    assert_eq!(header_size + used, written);
+    assert_eq!(next, None);
+}
+
+#[test]
+fn test_unknown_size_ended_by_sibling() {
+    // Two segments, where the second ends the first.
+    let mut bytes = Vec::new();
+    streamable_segment().write_to(&mut bytes).unwrap();
+    let first_len = bytes.len() as u64;
+    streamable_segment().write_to(&mut bytes).unwrap();
+
+    let mut r = &bytes[..];
+    runtime::read_child_header(&mut r).unwrap();
+    let ctx = &mut ReadContext::default();
+    let (first, used, next) = sized::Segment::read_unsized(&mut r, u64::MAX, ctx).unwrap();
+    assert_eq!(first, segment());
+    assert_eq!(12 + used, first_len);
+    assert_eq!(next, Some((Id::from_encoded(0x1853_8067).unwrap(), None, 12)));
+
+    let (second, _, next) = sized::Segment::read_unsized(&mut r, u64::MAX, ctx).unwrap();
+    assert_eq!(second, segment());
+    assert_eq!(next, None);
+}
+
+#[test]
+fn test_unknown_size_in_sized_parent() {
+    // A sized segment whose clusters have unknown sizes.
+    let mut clusters = Vec::new();
+    for cluster in streamable_segment().cluster {
+        cluster.write_to(&mut clusters).unwrap();
+    }
+    let mut info = Vec::new();
+    streamable_segment().info.write_to(&mut info).unwrap();
+    let mut body = clusters;
+    body.extend_from_slice(&info);
+
+    let segment = sized::Segment::read_from(&mut &body[..], body.len() as u64).unwrap();
+    assert_eq!(segment, self::segment());
+}
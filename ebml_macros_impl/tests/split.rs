@@ -0,0 +1,47 @@
+extern crate chrono;
+extern crate ebml;
+extern crate ebml_macros;
+#[macro_use]
+extern crate ebml_macros_impl;
+
+use ebml_macros::runtime;
+
+// The header fragment is included twice, which is harmless since the definitions are the same.
+include_ebml_dtd!(
+    tracks,
+    "tests/schemas/ebml_header.edtd",
+    "tests/schemas/tracks.edtd",
+    "tests/schemas/ebml_header.edtd",
+);
+
+include_ebml_dtd!(included_tracks, "tests/schemas/tracks_included.edtd");
+
+#[test]
+fn test_split() {
+    assert_eq!(tracks::DOC_TYPE, "tracks");
+    assert_eq!(tracks::ids::EBML, 0x1A45_DFA3);
+    assert_eq!(tracks::ids::TRACK_NUMBER, 0xD7);
+
+    let header = tracks::Ebml {
+        ebml_version: Some(1),
+        ebml_read_version: Some(1),
+        doc_type: "tracks".to_string(),
+    };
+    let segment = tracks::Segment { track: vec![tracks::Track { track_number: 1 }] };
+    let mut bytes = Vec::new();
+    header.write_to(&mut bytes).unwrap();
+    segment.write_to(&mut bytes).unwrap();
+
+    let mut r = &bytes[..];
+    let read = tracks::read_ebml_header(&mut r).unwrap();
+    assert_eq!(read.doc_type, "tracks");
+    let (_, size, _) = runtime::read_header(&mut r).unwrap();
+    assert_eq!(tracks::Segment::read_from(&mut r, size).unwrap(), segment);
+}
+
+#[test]
+fn test_included() {
+    assert_eq!(included_tracks::DOC_TYPE, "tracks");
+    assert_eq!(included_tracks::ids::EBML, tracks::ids::EBML);
+    assert_eq!(included_tracks::ids::TRACK_NUMBER, tracks::ids::TRACK_NUMBER);
+}
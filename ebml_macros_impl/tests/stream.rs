@@ -0,0 +1,102 @@
+extern crate chrono;
+extern crate ebml;
+extern crate ebml_macros;
+#[macro_use]
+extern crate ebml_macros_impl;
+
+use std::io::BufReader;
+
+use streaming::{Cluster, Handler, Info, Segment};
+
+ebml_dtd!(#[ebml(streamed(Segment, Cluster))] streaming, r#"
+    define elements {
+        Segment := 18538067 container [ card:*; ] {
+            Info := 1549a966 container [ card:1; ] {
+                Title := 7ba9 string [ card:1; ]
+            }
+            Cluster := 1f43b675 container [ card:*; ] {
+                Timecode := e7 uint [ card:1; ]
+                SimpleBlock := a3 binary [ card:*; ]
+            }
+        }
+    }
+"#);
+
+#[derive(Default)]
+struct Counter {
+    segments: usize,
+    titles: Vec<String>,
+    clusters: usize,
+    open_clusters: usize,
+    timecodes: u64,
+    blocks: usize,
+    block_bytes: usize,
+}
+
+impl Handler for Counter {
+    fn on_enter_segment(&mut self) {
+        self.segments += 1;
+    }
+
+    fn on_info(&mut self, info: Info) {
+        self.titles.push(info.title);
+    }
+
+    fn on_enter_cluster(&mut self) {
+        self.open_clusters += 1;
+    }
+
+    fn on_leave_cluster(&mut self) {
+        self.open_clusters -= 1;
+        self.clusters += 1;
+    }
+
+    fn on_timecode(&mut self, timecode: u64) {
+        self.timecodes += timecode;
+    }
+
+    fn on_simple_block(&mut self, data: Vec<u8>) {
+        assert_eq!(self.open_clusters, 1);
+        self.blocks += 1;
+        self.block_bytes += data.len();
+    }
+}
+
+#[test]
+fn test_stream() {
+    let clusters = (0..100).map(|timecode| Cluster {
+        timecode,
+        simple_block: vec![vec![timecode as u8; 100]; 10],
+    }).collect();
+    let segment = Segment {
+        info: Info { title: "synthetic".to_string() },
+        cluster: clusters,
+    };
+    let mut bytes = Vec::new();
+    segment.write_to(&mut bytes).unwrap();
+    segment.write_to(&mut bytes).unwrap();
+    assert!(bytes.len() > 200_000);
+
+    let mut counter = Counter::default();
+    let mut r = BufReader::with_capacity(64, &bytes[..]);
+    streaming::stream(&mut r, &mut counter).unwrap();
+
+    assert_eq!(counter.segments, 2);
+    assert_eq!(counter.titles, vec!["synthetic", "synthetic"]);
+    assert_eq!(counter.clusters, 200);
+    assert_eq!(counter.timecodes, 2 * (0..100).sum::<u64>());
+    assert_eq!(counter.blocks, 2000);
+    assert_eq!(counter.block_bytes, 200_000);
+}
+
+#[test]
+fn test_stream_error() {
+    // A cluster which ends in the middle of a block.
+    let bytes = [
+        0x18, 0x53, 0x80, 0x67, 0x88,
+        0x1F, 0x43, 0xB6, 0x75, 0x83,
+        0xA3, 0x85, 0x00,
+    ];
+    let err = streaming::stream(&mut &bytes[..], &mut Counter::default()).unwrap_err();
+    assert_eq!(err.path(), &["Segment", "Cluster"]);
+}
@@ -0,0 +1,5 @@
+extern crate ebml_macros;
+
+// Generated by the build script: values quoted with `quote::ToTokens`, and the test checking
+// they're rebuilt as they were.
+include!(concat!(env!("OUT_DIR"), "/tokens.rs"));
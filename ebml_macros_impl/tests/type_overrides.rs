@@ -0,0 +1,41 @@
+extern crate ebml;
+extern crate ebml_macros;
+#[macro_use]
+extern crate ebml_macros_impl;
+
+use ebml_macros::runtime::EbmlPrimitive;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Name(String);
+
+impl EbmlPrimitive<String> for Name {
+    fn from_raw(raw: String) -> Option<Self> {
+        Some(Name(raw))
+    }
+
+    fn to_raw(&self) -> Option<String> {
+        Some(self.0.clone())
+    }
+}
+
+ebml_dtd!(#[ebml(date = "i64")] #[ebml(string = "::Name", uint = "u32",)] info, r#"
+    define elements {
+        Info := 1549a966 container [ card:1; ] {
+            Title := 7ba9 string [ card:1; ]
+            DateUTC := 4461 date [ card:?; ]
+            TimecodeScale := 2ad7b1 uint [ def:1000000; card:1; ]
+        }
+    }
+"#);
+
+#[test]
+fn test_type_overrides() {
+    let info = info::Info {
+        title: Name("Big Buck Bunny".to_string()),
+        date_utc: Some(0),
+        timecode_scale: 1_000_000u32,
+    };
+
+    assert_eq!(info.title.to_raw(), Some("Big Buck Bunny".to_string()));
+    assert_eq!(info.date_utc, Some(0i64));
+}
@@ -0,0 +1,18 @@
+extern crate chrono;
+extern crate ebml;
+extern crate ebml_macros;
+#[macro_use]
+extern crate ebml_macros_impl;
+
+// trybuild builds this from target/tests/trybuild/ebml_macros_impl.
+#[derive(EbmlRead, EbmlWrite)]
+#[ebml(schema = "../../../../ebml_macros_impl/tests/schemas/matroska.edtd", element = "Info")]
+struct Info {
+    #[ebml(element = "Title")]
+    title: Option<String>,
+    // A `uint`, which can't be read into a string.
+    #[ebml(element = "TimecodeScale")]
+    timecode_scale: String,
+}
+
+fn main() {}
@@ -0,0 +1,17 @@
+extern crate chrono;
+extern crate ebml;
+extern crate ebml_macros;
+#[macro_use]
+extern crate ebml_macros_impl;
+
+// trybuild builds this from target/tests/trybuild/ebml_macros_impl.
+#[derive(EbmlRead)]
+#[ebml(schema = "../../../../ebml_macros_impl/tests/schemas/matroska.edtd", element = "Info")]
+struct Info {
+    #[ebml(element = "Title")]
+    title: Option<String>,
+    #[ebml(element = "Tilte")]
+    typo: Option<String>,
+}
+
+fn main() {}
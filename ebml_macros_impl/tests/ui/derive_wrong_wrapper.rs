@@ -0,0 +1,23 @@
+extern crate chrono;
+extern crate ebml;
+extern crate ebml_macros;
+#[macro_use]
+extern crate ebml_macros_impl;
+
+// trybuild builds this from target/tests/trybuild/ebml_macros_impl.
+#[derive(EbmlWrite)]
+#[ebml(schema = "../../../../ebml_macros_impl/tests/schemas/matroska.edtd", element = "Segment")]
+struct Segment {
+    // There may be any number of them.
+    #[ebml(element = "Info")]
+    info: Option<Info>,
+}
+
+#[derive(EbmlWrite)]
+#[ebml(schema = "../../../../ebml_macros_impl/tests/schemas/matroska.edtd", element = "Info")]
+struct Info {
+    #[ebml(element = "Title")]
+    title: Option<String>,
+}
+
+fn main() {}
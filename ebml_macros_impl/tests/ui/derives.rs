@@ -0,0 +1,22 @@
+extern crate chrono;
+extern crate ebml;
+extern crate ebml_macros;
+#[macro_use]
+extern crate ebml_macros_impl;
+
+use std::collections::HashSet;
+
+ebml_dtd!(#[ebml(derive(Default, Hash, Eq), skip_derive(Clone))] info, r#"
+    define elements {
+        Info := 1549a966 container [ card:1; ] {
+            Title := 7ba9 string [ card:?; ]
+            TimecodeScale := 2ad7b1 uint [ card:*; ]
+        }
+    }
+"#);
+
+fn main() {
+    let info = info::Info::default();
+    let mut set = HashSet::new();
+    set.insert(info);
+}
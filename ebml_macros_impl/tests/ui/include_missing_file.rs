@@ -0,0 +1,6 @@
+#[macro_use]
+extern crate ebml_macros_impl;
+
+include_ebml_dtd!(missing, "schemas/does_not_exist.edtd");
+
+fn main() {}
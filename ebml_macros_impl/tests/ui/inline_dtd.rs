@@ -0,0 +1,38 @@
+extern crate ebml;
+extern crate ebml_macros;
+#[macro_use]
+extern crate ebml_macros_impl;
+
+use ebml::Id;
+
+ebml_dtd!(matroska, r#"
+    declare header {
+        DocType := "matroska";
+        EBMLVersion := 1;
+    }
+    define elements {
+        EBML := 1a45dfa3 container [ card:+; ] {
+            EBMLVersion := 4286 uint [ def:1; ]
+            DocType := 4282 string [ range:32..126; ]
+        }
+        Segment := 18538067 container;
+    }
+"#);
+
+fn main() {
+    assert_eq!(matroska::DOC_TYPE, "matroska");
+    assert_eq!(matroska::ids::EBML, 0x1A45_DFA3);
+    assert_eq!(matroska::ids::EBML_VERSION, 0x4286);
+    assert_eq!(matroska::ids::DOC_TYPE, 0x4282);
+    assert_eq!(matroska::ids::SEGMENT, 0x1853_8067);
+
+    let segment = Id::from_encoded(0x1853_8067).unwrap();
+    assert_eq!(matroska::ElementId::from_id(segment), matroska::ElementId::Segment);
+    assert_eq!(matroska::ElementId::Segment.id(), Some(segment));
+    assert_eq!(matroska::ElementId::EbmlVersion.name(), "EBMLVersion");
+
+    let void = Id::from_encoded(0xEC).unwrap();
+    assert_eq!(matroska::ElementId::from_id(void), matroska::ElementId::Unknown(void));
+    assert_eq!(matroska::ElementId::from_id(void).id(), Some(void));
+    assert_eq!(matroska::ElementId::Unknown(void).name(), "Unknown");
+}
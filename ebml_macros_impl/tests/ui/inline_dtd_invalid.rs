@@ -0,0 +1,13 @@
+#[macro_use]
+extern crate ebml_macros_impl;
+
+ebml_dtd!(invalid, r#"
+    define elements {
+        Segment := 18538067 container {
+            Info := 1549a966 container;
+            Title := 1549a966 string;
+        }
+    }
+"#);
+
+fn main() {}
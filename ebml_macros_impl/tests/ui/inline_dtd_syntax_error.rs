@@ -0,0 +1,12 @@
+#[macro_use]
+extern crate ebml_macros_impl;
+
+ebml_dtd!(broken, "
+define elements {
+    Segment := 18538067 container {
+        Info := 1549a966 container [ card:many; ]
+    }
+}
+");
+
+fn main() {}
@@ -0,0 +1,6 @@
+extern crate ebml_macros;
+
+// The parsers, and the nom errors they fail with, aren't part of the API.
+use ebml_macros::parsers::Error;
+
+fn main() {}
@@ -0,0 +1,18 @@
+extern crate chrono;
+extern crate ebml;
+extern crate ebml_macros;
+#[macro_use]
+extern crate ebml_macros_impl;
+
+ebml_dtd!(#[ebml(skip_derive(Clone))] info, r#"
+    define elements {
+        Info := 1549a966 container [ card:1; ] {
+            Title := 7ba9 string [ card:?; ]
+        }
+    }
+"#);
+
+fn main() {
+    let info = info::Info { title: None };
+    let _ = info.clone();
+}
@@ -0,0 +1,10 @@
+#[macro_use]
+extern crate ebml_macros_impl;
+
+ebml_dtd!(#[ebml(timestamp = "i64")] info, r#"
+    define elements {
+        Title := 7ba9 string;
+    }
+"#);
+
+fn main() {}
@@ -0,0 +1,93 @@
+extern crate chrono;
+extern crate ebml;
+extern crate ebml_macros;
+#[macro_use]
+extern crate ebml_macros_impl;
+
+use ebml::Id;
+use ebml_macros::runtime::{self, ReadContext, ReadErrorKind, ReadOptions, UnknownElements};
+
+macro_rules! schema {
+    ($($module:tt)*) => {
+        ebml_dtd!($($module)*, r#"
+            define elements {
+                Known := 4001 container [ card:1; ] {
+                    Value := 4101 uint [ card:1; ]
+                }
+                Open := 4002 container [ card:1; unknownsizeallowed:yes; ] {
+                    OpenValue := 4102 uint [ card:1; ]
+                }
+            }
+        "#);
+    };
+}
+
+schema!(#[ebml(unknown_elements = "error")] strict);
+schema!(skipping);
+schema!(#[ebml(unknown_elements = "collect")] collecting);
+
+// A `Value` of 5, then an unknown element with id 4F01.
+const KNOWN: &[u8] = &[
+    0x40, 0x01, 0x89,
+    0x41, 0x01, 0x81, 0x05,
+    0x4F, 0x01, 0x82, 0xAA, 0xBB,
+];
+
+// An `OpenValue` of 1, then an unknown element with an unknown size.
+const OPEN: &[u8] = &[
+    0x40, 0x02, 0x89,
+    0x41, 0x02, 0x81, 0x01,
+    0x4F, 0x01, 0xFF, 0xAA, 0xBB,
+];
+
+fn unknown_id() -> Id {
+    Id::from_encoded(0x4F01).unwrap()
+}
+
+#[test]
+fn test_policies() {
+    let mut r = KNOWN;
+    let (_, size, _) = runtime::read_header(&mut r).unwrap();
+
+    let err = strict::Known::read_from(&mut { r }, size).unwrap_err();
+    match *err.kind() {
+        ReadErrorKind::UnknownElement(id) => assert_eq!(id, unknown_id()),
+        ref other => panic!("expected an unknown element error, got {:?}", other),
+    }
+    assert_eq!(err.path(), &["Known"]);
+
+    assert_eq!(skipping::Known::read_from(&mut { r }, size).unwrap().value, 5);
+
+    let collected = collecting::Known::read_from(&mut { r }, size).unwrap();
+    assert_eq!(collected.value, 5);
+    assert_eq!(collected.unknown_children, vec![(unknown_id(), vec![0xAA, 0xBB])]);
+
+    // The options override the policy chosen when generating.
+    let options = ReadOptions {
+        unknown_elements: Some(UnknownElements::Error),
+        ..ReadOptions::default()
+    };
+    let mut ctx = ReadContext::new(options);
+    assert!(skipping::Known::read_with(&mut { r }, size, &mut ctx).is_err());
+}
+
+#[test]
+fn test_unknown_size() {
+    let mut r = OPEN;
+    let (_, size, _) = runtime::read_header(&mut r).unwrap();
+    let open = collecting::Open::read_from(&mut r, size).unwrap();
+    assert_eq!(open.open_value, 1);
+    assert_eq!(open.unknown_children, vec![(unknown_id(), vec![0xAA, 0xBB])]);
+
+    // `Known` doesn't allow unknown sizes.
+    let mut bytes = OPEN.to_vec();
+    bytes[1] = 0x01;
+    bytes[4] = 0x01;
+    let mut r = &bytes[..];
+    let (_, size, _) = runtime::read_header(&mut r).unwrap();
+    let err = collecting::Known::read_from(&mut r, size).unwrap_err();
+    match *err.kind() {
+        ReadErrorKind::UnknownSize => {}
+        ref other => panic!("expected an unknown size error, got {:?}", other),
+    }
+}
@@ -0,0 +1,97 @@
+extern crate chrono;
+extern crate ebml;
+extern crate ebml_macros;
+#[macro_use]
+extern crate ebml_macros_impl;
+
+use chrono::NaiveDate;
+use ebml_macros::runtime;
+
+include_ebml_dtd!(matroska, "tests/schemas/matroska.edtd");
+
+ebml_dtd!(#[ebml(float_encoding = "single")] chapters, r#"
+    define elements {
+        EditionEntry := 45b9 container [ card:1; ];
+        ChapterAtom := b6 container [ card:?; parent:EditionEntry, ChapterAtom; ] {
+            ChapterTimeStart := 91 uint [ card:1; ]
+            ChapterGain := 4599 float [ card:?; ]
+            ChapterOffset := 459a int [ card:*; ]
+        }
+    }
+"#);
+
+fn round_trip_info() -> matroska::Info {
+    matroska::Info {
+        segment_uid: Some(std::array::from_fn(|i| i as u8)),
+        segment_filename: None,
+        timecode_scale: 1_000_000,
+        duration: Some(1234.5),
+        date_utc: NaiveDate::from_ymd_opt(2017, 6, 1).and_then(|d| d.and_hms_opt(12, 0, 0)),
+        title: Some("Big Buck Bunny \u{1F430}".to_string()),
+        muxing_app: "libebml".to_string(),
+        writing_app: "mkvmerge".to_string(),
+    }
+}
+
+#[test]
+fn test_write_header() {
+    let header = matroska::Ebml {
+        ebml_version: 1,
+        ebml_read_version: 1,
+        ebml_max_id_length: 4,
+        ebml_max_size_length: 8,
+        doc_type: "webm".to_string(),
+        doc_type_version: 4,
+        doc_type_read_version: 2,
+    };
+    let mut bytes = Vec::new();
+    assert_eq!(header.write_to(&mut bytes).unwrap(), 36);
+    assert_eq!(bytes, vec![
+        0x1A, 0x45, 0xDF, 0xA3, 0x9F,
+        0x42, 0x86, 0x81, 0x01,
+        0x42, 0xF7, 0x81, 0x01,
+        0x42, 0xF2, 0x81, 0x04,
+        0x42, 0xF3, 0x81, 0x08,
+        0x42, 0x82, 0x84, b'w', b'e', b'b', b'm',
+        0x42, 0x87, 0x81, 0x04,
+        0x42, 0x85, 0x81, 0x02,
+    ]);
+}
+
+#[test]
+fn test_round_trip() {
+    let segment = matroska::Segment { info: vec![round_trip_info(), round_trip_info()] };
+    let mut bytes = Vec::new();
+    let written = segment.write_to(&mut bytes).unwrap();
+    assert_eq!(written, bytes.len() as u64);
+
+    let mut r = &bytes[..];
+    let (id, size, _) = runtime::read_header(&mut r).unwrap();
+    assert_eq!(matroska::ElementId::from_id(id), matroska::ElementId::Segment);
+    assert_eq!(matroska::Segment::read_from(&mut r, size).unwrap(), segment);
+    assert!(r.is_empty());
+}
+
+#[test]
+fn test_round_trip_recursive() {
+    let atom = chapters::ChapterAtom {
+        chapter_time_start: 0,
+        chapter_gain: Some(-0.5),
+        chapter_offset: vec![-1, 128, -129, i64::min_value()],
+        chapter_atom: Some(Box::new(chapters::ChapterAtom {
+            chapter_time_start: 300,
+            chapter_gain: None,
+            chapter_offset: vec![],
+            chapter_atom: None,
+        })),
+    };
+    let mut bytes = Vec::new();
+    atom.write_to(&mut bytes).unwrap();
+
+    // Floats are written in 4 bytes.
+    assert!(bytes.windows(3).any(|w| w == [0x45, 0x99, 0x84]));
+
+    let mut r = &bytes[..];
+    let (_, size, _) = runtime::read_header(&mut r).unwrap();
+    assert_eq!(chapters::ChapterAtom::read_from(&mut r, size).unwrap(), atom);
+}
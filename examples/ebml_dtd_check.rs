@@ -0,0 +1,91 @@
+//! Checks an EDTD, printing what's wrong with it the way rustc prints errors.
+//!
+//! ```text
+//! cargo run --features json --example ebml_dtd_check -- [--strict] [--lenient] [--json]
+//!     [--dump-ast] [--markdown] schema.edtd
+//! ```
+//!
+//! `--strict` treats warnings as errors, `--lenient` tolerates the quirks of legacy EDTDs, `--json`
+//! prints the diagnostics as a JSON array instead, as `Diagnostics::to_json` writes them,
+//! `--dump-ast` prints the parsed EDTD as `Dtd::to_json` writes it, and `--markdown` prints its
+//! documentation. Exits with 1 if the EDTD has errors, and 2 if it couldn't be read.
+
+extern crate ebml_macros;
+
+use std::env;
+use std::fs;
+use std::process;
+
+use ebml_macros::{check_dtd, MarkdownOptions, ParseOptions, Severity};
+
+const USAGE: &str =
+    "usage: ebml_dtd_check [--strict] [--lenient] [--json] [--dump-ast] [--markdown] <path>";
+
+#[derive(Default)]
+struct Flags {
+    strict: bool,
+    lenient: bool,
+    json: bool,
+    dump_ast: bool,
+    markdown: bool,
+    path: Option<String>,
+}
+
+fn main() {
+    let mut flags = Flags::default();
+    for arg in env::args().skip(1) {
+        match arg.as_str() {
+            "--strict" => flags.strict = true,
+            "--lenient" => flags.lenient = true,
+            "--json" => flags.json = true,
+            "--dump-ast" => flags.dump_ast = true,
+            "--markdown" => flags.markdown = true,
+            _ if arg.starts_with("--") || flags.path.is_some() => fail(USAGE),
+            _ => flags.path = Some(arg),
+        }
+    }
+    let path = flags.path.unwrap_or_else(|| fail(USAGE));
+    let input = fs::read(&path).unwrap_or_else(|err| fail(&format!("{}: {}", path, err)));
+    let source = String::from_utf8_lossy(&input);
+
+    let options = ParseOptions {
+        validate: Some(Severity::Error),
+        warnings_as_errors: flags.strict,
+        lenient: flags.lenient,
+    };
+    let (dtd, diagnostics) = check_dtd(&input, &options);
+
+    if flags.json {
+        print!("{}", diagnostics.to_json(&source));
+    } else {
+        for diagnostic in diagnostics.iter() {
+            println!("{}\n", diagnostic.render(&source));
+        }
+        let errors = diagnostics.errors().count();
+        let warnings = diagnostics.warnings().count();
+        println!("{}: {} {}, {} {}", path, errors, plural(errors, "error"), warnings,
+                 plural(warnings, "warning"));
+    }
+    if let Some(dtd) = dtd {
+        if flags.dump_ast {
+            print!("{}", dtd.to_json());
+        }
+        if flags.markdown {
+            print!("{}", dtd.to_markdown(&MarkdownOptions::default()));
+        }
+    }
+
+    if diagnostics.has_errors() {
+        process::exit(1);
+    }
+}
+
+fn fail(message: &str) -> ! {
+    eprintln!("{}", message);
+    process::exit(2);
+}
+
+// `noun`, made plural unless there's exactly one.
+fn plural(count: usize, noun: &str) -> String {
+    if count == 1 { noun.to_string() } else { format!("{}s", noun) }
+}
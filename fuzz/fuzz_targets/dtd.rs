@@ -0,0 +1,17 @@
+#![no_main]
+#[macro_use]
+extern crate libfuzzer_sys;
+extern crate ebml_macros;
+
+use ebml_macros::{check_dtd, parse_dtd_recovering, ParseOptions, Severity};
+
+fuzz_target!(|data: &[u8]| {
+    let strict = ParseOptions { validate: Some(Severity::Error), ..Default::default() };
+    let lenient = ParseOptions { lenient: true, ..strict.clone() };
+    for options in &[strict, lenient] {
+        if let (Some(dtd), _) = check_dtd(data, options) {
+            dtd.to_dtd_string();
+        }
+    }
+    parse_dtd_recovering(data);
+});
@@ -0,0 +1,143 @@
+//! Generates impls of proptest's `Arbitrary` (re-exported as `runtime::Arbitrary`) for container
+//! structs, and a `check_round_trips` function testing the readers and writers with them.
+
+use quote::{Ident, Tokens};
+
+use {Cardinality, Dtd, Element, Type};
+use super::{CodegenError, CodegenOptions, FloatEncoding};
+use super::read::range_items;
+use super::structs::{containers, fields, Field};
+use super::write::encode_body;
+
+pub fn arbitrary_impls(dtd: &Dtd, options: &CodegenOptions) -> Result<Tokens, CodegenError> {
+    if !options.arbitrary() {
+        return Ok(Tokens::new());
+    }
+
+    let containers = containers(dtd)?;
+    let mut tokens = Tokens::new();
+    for &(container, ref name) in &containers {
+        tokens.append_all(&[arbitrary_impl(dtd, options, container, name)?]);
+    }
+
+    // Comparing values needs `PartialEq`, and containers written with an unknown size can't be
+    // read back by `read_from`.
+    if !options.derives.iter().any(|derive| derive == "PartialEq") {
+        return Ok(tokens);
+    }
+    let checked: Vec<_> = containers.iter()
+        .filter(|(container, _)| !options.unknown_size.iter().any(|n| n == container.name))
+        .map(|(_, name)| name)
+        .collect();
+    let read = checked.clone();
+    tokens.append_all(&[quote! {
+        #[doc = "Checks that `cases` arbitrary values of every container struct are read back \
+                 unchanged after being written in exactly `encoded_len` bytes, panicking with \
+                 the simplest failing value if one isn't. Containers written with an unknown \
+                 size aren't checked."]
+        pub fn check_round_trips(cases: u32) {
+            #(
+                ::ebml_macros::runtime::check_round_trips::<#checked, _, _, _>(
+                    cases,
+                    |value| value.encoded_len(),
+                    |value, w| value.write_to(w),
+                    |r, size| #read::read_from(r, size),
+                );
+            )*
+        }
+    }]);
+    Ok(tokens)
+}
+
+fn arbitrary_impl(dtd: &Dtd, options: &CodegenOptions, container: &Element, name: &Ident)
+    -> Result<Tokens, CodegenError>
+{
+    let fields = fields(dtd, options, container)?;
+    let names: Vec<_> = fields.iter().map(|field| &field.name).collect();
+
+    // The fields' strategies are nested in pairs, since proptest only implements `Strategy` for
+    // tuples of up to twelve.
+    let mut strategy = quote!(runtime::Just(()));
+    let mut pattern = quote!(());
+    for field in fields.iter().rev() {
+        let value = arbitrary_value(dtd, options, field);
+        let value = match field.element.cardinality() {
+            Cardinality::ExactlyOne => value,
+            Cardinality::ZeroOrOne => quote!(runtime::option_of(room, || #value)),
+            Cardinality::ZeroOrMany => quote!(runtime::vec_of(room, 0, || #value)),
+            Cardinality::OneOrMany => quote!(runtime::vec_of(room, 1, || #value)),
+        };
+        let name = &field.name;
+        strategy = quote!((#value, #strategy));
+        pattern = quote!((#name, #pattern));
+    }
+    let unknown_children = if options.collects_unknown() {
+        quote!(unknown_children: ::std::vec::Vec::new(),)
+    } else {
+        Tokens::new()
+    };
+
+    Ok(quote! {
+        impl ::ebml_macros::runtime::Arbitrary for #name {
+            type Parameters = ::ebml_macros::runtime::Room;
+            type Strategy = ::ebml_macros::runtime::BoxedStrategy<Self>;
+
+            #[allow(unused_variables)]
+            fn arbitrary_with(room: ::ebml_macros::runtime::Room) -> Self::Strategy {
+                use ::ebml_macros::runtime::{self, Strategy};
+
+                #strategy.prop_map(|#pattern| #name {
+                    #(#names,)*
+                    #unknown_children
+                }).boxed()
+            }
+        }
+    })
+}
+
+// An expression giving a strategy for single values of `field`, which may use the container's
+// `room`.
+fn arbitrary_value(dtd: &Dtd, options: &CodegenOptions, field: &Field) -> Tokens {
+    let body = match encode_body(dtd, options, field) {
+        Some(body) => body,
+        None => {
+            let value_type = &field.value_type;
+            let value = quote!(<#value_type as runtime::Arbitrary>::arbitrary_with(room.nested()));
+            return if field.boxed {
+                quote!(#value.prop_map(::std::boxed::Box::new))
+            } else {
+                value
+            };
+        }
+    };
+
+    let range = dtd.range_of(field.element).and_then(range_items)
+        .map_or_else(Vec::new, |(_, items)| items);
+    let range = quote!(&[#(#range),*]);
+    let sizes = match field.element.size {
+        Some(ref sizes) => quote!(::std::option::Option::Some(&#sizes)),
+        None => quote!(::std::option::Option::None),
+    };
+    let raw = match dtd.resolve_type(field.element.type_) {
+        Some(Type::Int) => quote!(runtime::int_strategy(#range)),
+        Some(Type::Uint) => quote!(runtime::uint_strategy(#range)),
+        Some(Type::Float) => {
+            let single = options.float_encoding == FloatEncoding::Single;
+            quote!(runtime::float_strategy(#range, #single))
+        }
+        Some(Type::Date) => quote!(runtime::date_strategy(#range)),
+        Some(Type::String) => quote!(runtime::string_strategy(#range, #sizes, room)),
+        _ => quote!(runtime::binary_strategy(#range, #sizes, room)),
+    };
+    let value_type = &field.value_type;
+    quote! {
+        runtime::primitive(
+            #raw,
+            |value: &#value_type| {
+                let body = #body;
+                ::std::result::Result::Ok(body)
+            },
+            #sizes,
+        )
+    }
+}
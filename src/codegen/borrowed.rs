@@ -0,0 +1,492 @@
+//! Generates the `borrowed` module: container structs whose `string` and `binary` children
+//! borrow from the input instead of being copied, with readers taking the input as a slice.
+
+use std::collections::HashSet;
+
+use quote::{Ident, Tokens};
+
+use {Cardinality, Dtd, Element, Property, Type};
+use super::{documented, reusable, screaming_snake_case, snake_case, CodegenError,
+            CodegenOptions};
+use super::read::{check_range, check_size, read_value, Mode};
+use super::structs::{containers, fields, Field};
+
+// How a field of a borrowed struct holds its values.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Borrow {
+    // A `&'a str`.
+    Str,
+    // A `&'a [u8]`.
+    Bytes,
+    // A borrowed struct, with a lifetime.
+    Container,
+    // The same type as the owned struct's field.
+    Owned,
+}
+
+pub fn borrowed_module(dtd: &Dtd, options: &CodegenOptions) -> Result<Tokens, CodegenError> {
+    if !options.borrowed {
+        return Ok(Tokens::new());
+    }
+
+    let containers = containers(dtd)?;
+    let borrowing = borrowing(dtd, options, &containers)?;
+    let mut items = Tokens::new();
+    for (container, name) in containers {
+        if borrowing.contains(name.as_ref()) {
+            items.append_all(&[borrowed_struct(dtd, options, &borrowing, container, name)?]);
+        } else {
+            // Nothing inside it could be borrowed, so the owned struct serves.
+            items.append_all(&[quote!(pub use super::#name;)]);
+        }
+    }
+
+    let doc = "Container structs which borrow their `string` and `binary` children from the \
+               input instead of copying them, for reading documents which are already in \
+               memory. Containers with nothing to borrow are the owned structs.";
+    Ok(quote! {
+        #[doc = #doc]
+        pub mod borrowed {
+            #[allow(unused_imports)]
+            use super::*;
+
+            #items
+        }
+    })
+}
+
+// The names of the containers whose borrowed structs refer to the input, directly or through
+// their children. Every container does if unknown elements are collected.
+fn borrowing(dtd: &Dtd, options: &CodegenOptions, containers: &[(&Element, Ident)])
+    -> Result<HashSet<String>, CodegenError>
+{
+    let mut children = Vec::new();
+    for &(container, _) in containers {
+        children.push(fields(dtd, options, container)?);
+    }
+
+    let mut borrowing = HashSet::new();
+    loop {
+        let before = borrowing.len();
+        for ((_, name), fields) in containers.iter().zip(&children) {
+            let borrows = options.collects_unknown() || fields.iter().any(|field| {
+                match borrow(options, field) {
+                    Borrow::Str | Borrow::Bytes => true,
+                    Borrow::Container => borrowing.contains(&field.value_type.to_string()),
+                    Borrow::Owned => false,
+                }
+            });
+            if borrows {
+                borrowing.insert(name.as_ref().to_string());
+            }
+        }
+        if borrowing.len() == before {
+            return Ok(borrowing);
+        }
+    }
+}
+
+fn borrow(options: &CodegenOptions, field: &Field) -> Borrow {
+    match field.element.type_ {
+        Type::String => Borrow::Str,
+        Type::Binary if options.fixed_binary_len(Type::Binary, field.element).is_none() => {
+            Borrow::Bytes
+        }
+        Type::Container => Borrow::Container,
+        _ => Borrow::Owned,
+    }
+}
+
+// The type of a single value of `field`.
+fn value_type(options: &CodegenOptions, borrowing: &HashSet<String>, field: &Field) -> Tokens {
+    let value = &field.value_type;
+    let value = match borrow(options, field) {
+        Borrow::Str => quote!(&'a str),
+        Borrow::Bytes => quote!(&'a [u8]),
+        Borrow::Container if borrowing.contains(&value.to_string()) => quote!(#value<'a>),
+        Borrow::Container | Borrow::Owned => quote!(#value),
+    };
+    if field.boxed {
+        quote!(::std::boxed::Box<#value>)
+    } else {
+        value
+    }
+}
+
+// An expression giving the default of `field` in its borrowed struct, if it has one. Borrowed
+// strings and binaries default to static slices; anything else is converted by `convert`.
+fn default<F>(options: &CodegenOptions, field: &Field, convert: F) -> Option<Tokens>
+    where F: Fn(Tokens) -> Tokens
+{
+    match (borrow(options, field), field.element.default.as_deref()) {
+        (Borrow::Str, Some(Property::StringDefault(value))) => {
+            let value = &value[..];
+            Some(quote!(#value))
+        }
+        (Borrow::Bytes, Some(Property::BinaryDefault(value))) => {
+            let value = &value[..];
+            Some(quote!(&#value[..]))
+        }
+        _ => field.raw_default().map(convert),
+    }
+}
+
+fn borrowed_struct(dtd: &Dtd, options: &CodegenOptions, borrowing: &HashSet<String>,
+                   container: &Element, name: Ident)
+    -> Result<Tokens, CodegenError>
+{
+    let fields = fields(dtd, options, container)?;
+
+    let names: Vec<_> = fields.iter().map(|field| &field.name).collect();
+    let value_types: Vec<_> = fields.iter()
+        .map(|field| value_type(options, borrowing, field))
+        .collect();
+    let types: Vec<_> = fields.iter().zip(&value_types)
+        .map(|(field, value)| match field.element.cardinality() {
+            Cardinality::ExactlyOne => quote!(#value),
+            Cardinality::ZeroOrOne => quote!(::std::option::Option<#value>),
+            Cardinality::ZeroOrMany | Cardinality::OneOrMany => {
+                quote!(::std::vec::Vec<#value>)
+            }
+        })
+        .collect();
+    let docs: Vec<_> = fields.iter()
+        .map(|field| {
+            documented(format!("The `{}` child element.", field.element.name), field.element)
+        })
+        .collect();
+    let doc = documented(
+        format!("The contents of a `{}` element, borrowed from the input.", container.name),
+        container,
+    );
+
+    // Optional fields with defaults get an accessor filling in the default, like the owned
+    // struct's.
+    let defaulted: Vec<_> = fields.iter().zip(&value_types)
+        .filter(|&(field, _)| field.element.cardinality() == Cardinality::ZeroOrOne)
+        .filter_map(|(field, value_type)| {
+            let default = default(options, field, |raw| {
+                quote!(::ebml_macros::runtime::default_value(#raw))
+            });
+            default.map(|default| (field, value_type, default))
+        })
+        .collect();
+    let accessors: Vec<_> = defaulted.iter()
+        .map(|&(field, _, _)| {
+            Ident::new(format!("{}_or_default", snake_case(field.element.name)))
+        })
+        .collect();
+    let accessor_docs: Vec<_> = defaulted.iter()
+        .map(|&(field, _, _)| format!(
+            "The `{}` child element, or its default if it's absent.\n\n\
+             Panics if the default can't be represented by the field's type.",
+            field.element.name
+        ))
+        .collect();
+    let defaulted_names: Vec<_> = defaulted.iter().map(|&(field, _, _)| &field.name).collect();
+    let defaulted_types: Vec<_> = defaulted.iter().map(|&(_, value, _)| value).collect();
+    let defaults: Vec<_> = defaulted.iter().map(|(_, _, default)| default).collect();
+
+    let derive = if options.derives.is_empty() {
+        Tokens::new()
+    } else {
+        let mut derive = Tokens::new();
+        derive.append(format!("#[derive({})]", options.derives.join(", ")));
+        derive
+    };
+    let unknown_children = if options.collects_unknown() {
+        let doc = "Child elements the schema doesn't describe, with their bodies, in the order \
+                   they were read.";
+        quote! {
+            #[doc = #doc]
+            pub unknown_children: ::std::vec::Vec<(::ebml::Id, &'a [u8])>,
+        }
+    } else {
+        Tokens::new()
+    };
+
+    let reader = reader(dtd, options, borrowing, container, &name, &fields)?;
+
+    let (names, types) = (reusable(&names), reusable(&types));
+
+    Ok(quote! {
+        #[doc = #doc]
+        #derive
+        pub struct #name<'a> {
+            #(
+                #[doc = #docs]
+                pub #names: #types,
+            )*
+            #unknown_children
+        }
+
+        impl<'a> #name<'a> {
+            #(
+                #[doc = #accessor_docs]
+                pub fn #accessors(&self) -> #defaulted_types {
+                    match self.#defaulted_names {
+                        ::std::option::Option::Some(ref value) => value.clone(),
+                        ::std::option::Option::None => #defaults,
+                    }
+                }
+            )*
+
+            #reader
+        }
+    })
+}
+
+fn reader(dtd: &Dtd, options: &CodegenOptions, borrowing: &HashSet<String>,
+          container: &Element, name: &Ident, fields: &[Field])
+    -> Result<Tokens, CodegenError>
+{
+    let element_name = container.name;
+    let doc = format!(
+        "Reads the body of a `{}` element, which is all of `body`, borrowing from it.",
+        container.name
+    );
+    let with_doc = format!(
+        "Reads the body of a `{}` element, which is all of `body`, borrowing from it and using \
+         the options and collecting warnings in `ctx`.",
+        container.name
+    );
+
+    let locals: Vec<_> = fields.iter()
+        .map(|field| {
+            Ident::new(format!("field_{}", field.name.as_ref().trim_start_matches("r#")))
+        })
+        .collect();
+    let local_types: Vec<_> = fields.iter().map(|field| {
+        let value = value_type(options, borrowing, field);
+        if field.is_many() {
+            quote!(::std::vec::Vec<#value>)
+        } else {
+            quote!(::std::option::Option<#value>)
+        }
+    }).collect();
+    let inits: Vec<_> = fields.iter().map(|field| if field.is_many() {
+        quote!(::std::vec::Vec::new())
+    } else {
+        quote!(::std::option::Option::None)
+    }).collect();
+    let constants: Vec<_> = fields.iter()
+        .map(|field| Ident::new(screaming_snake_case(field.element.name)))
+        .collect();
+
+    // Values are read from `child`, the body of the child element.
+    let stores: Vec<_> = fields.iter().zip(&locals).enumerate().map(|(index, (field, local))| {
+        let child_name = field.element.name;
+        let value = read_borrowed_value(dtd, options, borrowing, field);
+        let store = if field.is_many() {
+            quote!(#local.push(#value);)
+        } else {
+            let cardinality = field.element.cardinality();
+            quote! {
+                runtime::check_count(ctx, #child_name, &#cardinality, #local.iter().count() + 1)?;
+                #local = ::std::option::Option::Some(#value);
+            }
+        };
+        if container.is_ordered() {
+            quote!({
+                runtime::check_order(ctx, #child_name, &mut latest, #index)?;
+                #store
+            })
+        } else {
+            quote!({ #store })
+        }
+    }).collect();
+    let checks: Vec<_> = fields.iter().zip(&locals).filter_map(|(field, local)| {
+        let child_name = field.element.name;
+        let card = field.element.cardinality();
+        if card.allows(0) || field.raw_default().is_some() {
+            return None;
+        }
+        Some(quote! {
+            runtime::check_count(ctx, #child_name, &#card, #local.iter().count())?;
+        })
+    }).collect();
+    let names: Vec<_> = fields.iter().map(|field| &field.name).collect();
+    let finishes: Vec<_> = fields.iter().zip(&locals).map(|(field, local)| {
+        let child_name = field.element.name;
+        let default = default(options, field, |raw| {
+            quote!(runtime::convert(#raw).map_err(|err| err.within(#child_name))?)
+        });
+        match (field.element.cardinality(), default) {
+            (Cardinality::ExactlyOne, Some(default)) => quote! {
+                match #local {
+                    ::std::option::Option::Some(value) => value,
+                    ::std::option::Option::None => #default,
+                }
+            },
+            (Cardinality::ExactlyOne, None) => {
+                quote!(#local.expect("mandatory elements have been checked"))
+            }
+            (Cardinality::OneOrMany, Some(default)) => quote! {
+                if #local.is_empty() {
+                    vec![#default]
+                } else {
+                    #local
+                }
+            },
+            _ => quote!(#local),
+        }
+    }).collect();
+
+    let order_state = if container.is_ordered() {
+        quote!(let mut latest = 0;)
+    } else {
+        Tokens::new()
+    };
+    let policy = options.unknown_elements();
+    let (unknown_state, unknown, unknown_finish) = if options.collects_unknown() {
+        (
+            quote!(let mut unknown_children = ::std::vec::Vec::new();),
+            quote! {
+                let id = runtime::decode_id(encoded)?;
+                if let ::std::option::Option::Some(body) =
+                    runtime::borrow_unknown(child, ctx, #policy, id)?
+                {
+                    unknown_children.push((id, body));
+                }
+            },
+            quote!(unknown_children: unknown_children,),
+        )
+    } else {
+        (
+            Tokens::new(),
+            quote! {
+                let id = runtime::decode_id(encoded)?;
+                runtime::borrow_unknown(child, ctx, #policy, id)?;
+            },
+            Tokens::new(),
+        )
+    };
+
+    // An element of unknown size can only be delimited by the end of this one, so only
+    // elements which aren't children of it may have one, and only if it allows that.
+    let unknown_size = if container.allows_unknown_size() {
+        let constants = reusable(&constants);
+        quote!({
+            let is_child = match encoded {
+                #(ids::#constants => true,)*
+                _ => false,
+            };
+            runtime::unknown_size(!is_child, r.len() as u64 + header_size, header_size)?
+        })
+    } else {
+        quote!(runtime::unknown_size(false, r.len() as u64 + header_size, header_size)?)
+    };
+
+    let locals = reusable(&locals);
+
+    Ok(quote! {
+        #[doc = #doc]
+        pub fn read_from(body: &'a [u8])
+            -> ::std::result::Result<Self, ::ebml_macros::runtime::ReadError>
+        {
+            Self::read_with(body, &mut ::ebml_macros::runtime::ReadContext::default())
+        }
+
+        #[doc = #with_doc]
+        pub fn read_with(body: &'a [u8], ctx: &mut ::ebml_macros::runtime::ReadContext)
+            -> ::std::result::Result<Self, ::ebml_macros::runtime::ReadError>
+        {
+            fn read<'a>(body: &'a [u8], ctx: &mut ::ebml_macros::runtime::ReadContext)
+                -> ::std::result::Result<#name<'a>, ::ebml_macros::runtime::ReadError>
+            {
+                use ::ebml_macros::runtime;
+
+                #(let mut #locals: #local_types = #inits;)*
+
+                #order_state
+                #unknown_state
+                let mut children = 0;
+                let mut r = body;
+                while !r.is_empty() {
+                    let (encoded, child_size, header_size) =
+                        runtime::read_encoded_child_header(&mut r)?;
+                    let child_size = match child_size {
+                        ::std::option::Option::Some(child_size) => child_size,
+                        ::std::option::Option::None => #unknown_size,
+                    };
+                    runtime::check_child(
+                        ctx,
+                        ::std::option::Option::Some(&mut children),
+                        child_size,
+                    )?;
+                    let child = runtime::take_body(&mut r, child_size)?;
+
+                    // Void and CRC-32 are only handled here if the schema doesn't give them a
+                    // field of their own. A CRC-32 only counts as the first child.
+                    match encoded {
+                        #(ids::#constants => #stores,)*
+                        _ if encoded == runtime::VOID => {}
+                        _ if encoded == runtime::CRC_32 && children == 1 => {
+                            runtime::check_crc32(ctx, child, r)?
+                        }
+                        _ if encoded == runtime::CRC_32 => {}
+                        _ => { #unknown }
+                    }
+                }
+
+                #(#checks)*
+                Ok(#name {
+                    #(#names: #finishes,)*
+                    #unknown_finish
+                })
+            }
+
+            ctx.enter(#element_name);
+            let known = ::std::option::Option::Some(body.len() as u64);
+            let result = ::ebml_macros::runtime::check_limits(ctx, known)
+                .and_then(|()| read(body, ctx));
+            ctx.leave();
+            result.map_err(|err| err.within(#element_name))
+        }
+    })
+}
+
+// An expression reading the value of `field` from `child`, the body of the child element, which
+// is `child_size` bytes long.
+fn read_borrowed_value(dtd: &Dtd, options: &CodegenOptions, borrowing: &HashSet<String>,
+                       field: &Field)
+    -> Tokens
+{
+    let child_name = field.element.name;
+    match borrow(options, field) {
+        Borrow::Str => match check_range(dtd, field) {
+            Some(check) => check_size(field, quote! {
+                runtime::borrow_string(child)
+                    .and_then(|value| #check)
+                    .map_err(|err| err.within(#child_name))?
+            }),
+            None => check_size(field, quote! {
+                runtime::borrow_string(child).map_err(|err| err.within(#child_name))?
+            }),
+        },
+        Borrow::Bytes => match check_range(dtd, field) {
+            Some(check) => check_size(field, quote!({
+                let value = child;
+                #check.map_err(|err| err.within(#child_name))?
+            })),
+            None => check_size(field, quote!(child)),
+        },
+        Borrow::Container if borrowing.contains(&field.value_type.to_string()) => {
+            let value_type = &field.value_type;
+            let value = quote!(#value_type::read_with(child, ctx)?);
+            if field.boxed {
+                quote!(::std::boxed::Box::new(#value))
+            } else {
+                value
+            }
+        }
+        // Everything else is read from the body like the owned reader would.
+        Borrow::Container | Borrow::Owned => {
+            let value = read_value(dtd, field, Mode::Sync);
+            quote!({
+                let r = &mut &child[..];
+                #value
+            })
+        }
+    }
+}
@@ -0,0 +1,192 @@
+//! Generates a builder for every container struct.
+
+use quote::{Ident, Tokens};
+
+use {Cardinality, Dtd, Element, Type};
+use super::{camel_case, reusable, unique, CodegenError, CodegenOptions};
+use super::enums::enums;
+use super::structs::{containers, fields, Field};
+use super::types::newtypes;
+
+pub fn builders(dtd: &Dtd, options: &CodegenOptions) -> Result<Tokens, CodegenError> {
+    if !options.builders {
+        return Ok(Tokens::new());
+    }
+
+    let containers = containers(dtd)?;
+    let newtypes = newtypes(dtd)?;
+    let enums = enums(dtd, options)?;
+    let mut reserved: Vec<&str> = containers.iter().map(|(_, name)| name.as_ref()).collect();
+    reserved.extend(newtypes.iter().map(|(_, name)| name.as_ref()));
+    reserved.extend(enums.iter().map(|(_, name)| name.as_ref()));
+    reserved.push("ElementId");
+    let container_names: Vec<_> = containers.iter().map(|(element, _)| element.name).collect();
+    let names = unique(&container_names, &reserved, |name| {
+        format!("{}Builder", camel_case(name))
+    })?;
+
+    let mut tokens = Tokens::new();
+    for ((container, name), builder) in containers.iter().zip(names) {
+        let fields = fields(dtd, options, container)?;
+        tokens.append_all(&[builder_struct(dtd, options, container, name, &Ident::new(builder),
+                                           &fields)?]);
+    }
+    Ok(tokens)
+}
+
+fn builder_struct(dtd: &Dtd, options: &CodegenOptions, container: &Element, name: &Ident,
+                  builder: &Ident, fields: &[Field])
+    -> Result<Tokens, CodegenError>
+{
+    if let Some(field) = fields.iter().find(|field| field.name.as_ref() == "build") {
+        return Err(CodegenError::NameCollision {
+            generated: "build".to_string(),
+            first: "build".to_string(),
+            second: field.element.name.to_string(),
+        });
+    }
+
+    let names: Vec<_> = fields.iter().map(|field| &field.name).collect();
+    let types: Vec<_> = fields.iter().map(|field| {
+        let value = field.boxed_value_type();
+        if field.is_many() {
+            quote!(::std::vec::Vec<#value>)
+        } else {
+            quote!(::std::option::Option<#value>)
+        }
+    }).collect();
+    let setters: Vec<_> = fields.iter().map(|field| setter(dtd, field)).collect();
+
+    // Mandatory elements are missing if they haven't been set and have no default to fall back
+    // on.
+    let checks: Vec<_> = fields.iter().filter_map(|field| {
+        let (name, child_name) = (&field.name, field.element.name);
+        match (field.element.cardinality(), field.raw_default()) {
+            (Cardinality::ExactlyOne, None) => Some(quote! {
+                if self.#name.is_none() {
+                    missing.push(#child_name);
+                }
+            }),
+            (Cardinality::OneOrMany, None) => Some(quote! {
+                if self.#name.is_empty() {
+                    missing.push(#child_name);
+                }
+            }),
+            _ => None,
+        }
+    }).collect();
+    let values: Vec<_> = fields.iter().map(|field| {
+        let name = &field.name;
+        match (field.element.cardinality(), field.raw_default()) {
+            (Cardinality::ExactlyOne, Some(raw)) => quote! {
+                self.#name.unwrap_or_else(|| ::ebml_macros::runtime::default_value(#raw))
+            },
+            (Cardinality::ExactlyOne, None) => {
+                quote!(self.#name.expect("mandatory elements have been checked"))
+            }
+            (Cardinality::OneOrMany, Some(raw)) => quote! {
+                if self.#name.is_empty() {
+                    vec![::ebml_macros::runtime::default_value(#raw)]
+                } else {
+                    self.#name
+                }
+            },
+            _ => quote!(self.#name),
+        }
+    }).collect();
+    let unknown_children = if options.collects_unknown() {
+        quote!(unknown_children: ::std::vec::Vec::new(),)
+    } else {
+        Tokens::new()
+    };
+
+    let doc = format!("Builds a `{}`, with a method setting each of its fields.", name);
+    let builder_doc = format!(
+        "Starts building a `{}`, which is often easier than writing out the struct.\n\n\
+         ```ignore\nlet value = {}::builder(){}\n    .build()?;\n```",
+        name, name,
+        names.iter().take(2).map(|name| format!("\n    .{}(value)", name)).collect::<String>()
+    );
+    let build_doc = format!(
+        "Finishes building the `{}`. Absent mandatory elements take their defaults, and fails \
+         naming those without one.\n\nPanics if a default can't be represented by its field's \
+         type.",
+        name
+    );
+    let container_name = container.name;
+
+    let names = reusable(&names);
+
+    Ok(quote! {
+        #[doc = #doc]
+        #[derive(Default)]
+        pub struct #builder {
+            #(#names: #types,)*
+        }
+
+        impl #name {
+            #[doc = #builder_doc]
+            pub fn builder() -> #builder {
+                #builder::default()
+            }
+        }
+
+        impl #builder {
+            #(#setters)*
+
+            #[doc = #build_doc]
+            pub fn build(self)
+                -> ::std::result::Result<#name, ::ebml_macros::runtime::BuildError>
+            {
+                let mut missing = ::std::vec::Vec::new();
+                #(#checks)*
+                if !missing.is_empty() {
+                    return ::std::result::Result::Err(::ebml_macros::runtime::BuildError {
+                        container: #container_name,
+                        missing,
+                    });
+                }
+                ::std::result::Result::Ok(#name {
+                    #(#names: #values,)*
+                    #unknown_children
+                })
+            }
+        }
+    })
+}
+
+// A method setting `field`, or adding to it if it holds many values. Strings and binaries are
+// taken as anything which converts into them, so literals can be passed; other values are taken
+// as they are, so numeric literals are inferred.
+fn setter(dtd: &Dtd, field: &Field) -> Tokens {
+    let name = &field.name;
+    let value_type = &field.value_type;
+    let type_ = dtd.resolve_type(field.element.type_);
+    let converts = type_ == Some(Type::String) || type_ == Some(Type::Binary);
+    let (generics, param, value) = if converts {
+        (quote!(<V: ::std::convert::Into<#value_type>>), quote!(V), quote!(value.into()))
+    } else {
+        (Tokens::new(), quote!(#value_type), quote!(value))
+    };
+    let value = if field.boxed { quote!(::std::boxed::Box::new(#value)) } else { value };
+
+    if field.is_many() {
+        let doc = format!("Adds a `{}` child element.", field.element.name);
+        quote! {
+            #[doc = #doc]
+            pub fn #name #generics(mut self, value: #param) -> Self {
+                self.#name.push(#value);
+                self
+            }
+        }
+    } else {
+        let doc = format!("Sets the `{}` child element.", field.element.name);
+        quote! {
+            #[doc = #doc]
+            pub fn #name #generics(mut self, value: #param) -> Self {
+                self.#name = ::std::option::Option::Some(#value);
+                self
+            }
+        }
+    }
+}
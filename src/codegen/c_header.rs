@@ -0,0 +1,85 @@
+//! Generates a C header defining the same constants as the Rust code, for C code working with the
+//! same documents.
+
+use {Dtd, HeaderStatement};
+use runtime::DefaultValue;
+use super::{screaming_snake_case, unique, unique_names, CodegenError};
+use super::tokens::header_value;
+
+/// Generates the source of a C header with a macro for every statement in the header, like
+/// `MKV_DOC_TYPE`, and for the encoded id of every element, like `MKV_ID_SEGMENT`. Names are
+/// converted to `SCREAMING_SNAKE_CASE` like the Rust constants are, and start with `prefix` and
+/// an underscore, unless `prefix` is empty. Names which collide are an error, as they are for
+/// `generate`.
+///
+/// Unsigned integers are suffixed with `ULL` and signed ones with `LL`, except for ids, which fit
+/// in an `unsigned int`. Dates are nanoseconds since 2001, and binaries are string literals.
+pub fn c_header(dtd: &Dtd, prefix: &str) -> Result<String, CodegenError> {
+    let name = |name: &str| if prefix.is_empty() {
+        name.to_string()
+    } else {
+        format!("{}_{}", prefix, name)
+    };
+
+    let elements = dtd.all_elements();
+    let ids = unique_names(&elements, &[], |element| {
+        name(&format!("ID_{}", screaming_snake_case(element)))
+    })?;
+    // A statement like `IDSegment` would be converted to the same name as an id.
+    let reserved: Vec<&str> = ids.iter().map(String::as_str).collect();
+    let statements: Vec<_> = dtd.header.iter().map(|statement| statement.name()).collect();
+    let macros = unique(&statements, &reserved, |statement| {
+        name(&screaming_snake_case(statement))
+    })?;
+
+    let guard = name("EBML_IDS_H");
+    let mut out = format!(
+        "/* Generated by ebml_macros. Do not edit. */\n\n#ifndef {}\n#define {}\n\n",
+        guard, guard
+    );
+    for (statement, macro_name) in dtd.header.iter().zip(&macros) {
+        let resolved = dtd.resolve_header(statement).ok_or_else(|| match **statement {
+            HeaderStatement::Named { name, value } => CodegenError::UnresolvedHeader {
+                statement: name.to_string(),
+                name: value.to_string(),
+            },
+            _ => unreachable!("only names need resolving"),
+        })?;
+        // Dates too far from the epoch to encode are clamped, like in Rust.
+        let value = match header_value(resolved).expect("names have been resolved") {
+            DefaultValue::Int(value) | DefaultValue::Date(value) => format!("{}LL", value),
+            DefaultValue::Uint(value) => format!("{}ULL", value),
+            DefaultValue::Float(value) => format!("{:?}", value),
+            DefaultValue::String(value) => string_literal(value.as_bytes()),
+            DefaultValue::Binary(value) => string_literal(value),
+        };
+        out.push_str(&format!("#define {} {}\n", macro_name, value));
+    }
+    if !macros.is_empty() {
+        out.push('\n');
+    }
+
+    for (element, id) in elements.iter().zip(&ids) {
+        out.push_str(&format!("#define {} 0x{:X}u\n", id, element.id));
+    }
+    out.push_str(&format!("\n#endif /* {} */\n", guard));
+    Ok(out)
+}
+
+// A C string literal holding `bytes`, with anything but printable ASCII escaped in octal, which
+// unlike hex escapes can't run into the character after them.
+fn string_literal(bytes: &[u8]) -> String {
+    let mut out = String::from("\"");
+    for &byte in bytes {
+        match byte {
+            b'"' | b'\\' => {
+                out.push('\\');
+                out.push(byte as char);
+            }
+            0x20..=0x7E => out.push(byte as char),
+            _ => out.push_str(&format!("\\{:03o}", byte)),
+        }
+    }
+    out.push('"');
+    out
+}
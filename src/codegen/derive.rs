@@ -0,0 +1,280 @@
+//! Generates the implementations of `EbmlRead` and `EbmlWrite` for structs laid out by hand, for
+//! `ebml_macros_impl`'s derives.
+
+use std::error::Error;
+use std::fmt;
+
+use quote::{Ident, Tokens};
+
+use {Cardinality, Dtd, Element, Type};
+use super::{id_constants, CodegenError, CodegenOptions};
+use super::read::{read_impl, reader, Mode};
+use super::structs::Field;
+use super::write::{write_impl, writer};
+
+/// A struct deriving `EbmlRead` or `EbmlWrite`, with the elements its attributes map it onto.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DeriveInput {
+    /// The name of the struct.
+    pub name: String,
+    /// The name of the container element whose body the struct holds.
+    pub element: String,
+    /// The struct's fields, in the order they're declared.
+    pub fields: Vec<DeriveField>,
+}
+
+/// A field of a struct deriving `EbmlRead` or `EbmlWrite`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DeriveField {
+    /// The name of the field.
+    pub name: String,
+    /// The name of the child element the field holds.
+    pub element: String,
+    /// The source of the field's type.
+    pub type_: String,
+    /// If the field's type is an `Option` or a `Vec`, which one it is and the source of the type
+    /// inside it.
+    pub wrapped: Option<(Wrapper, String)>,
+}
+
+/// What a field holding more or less than one value wraps them in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Wrapper {
+    /// An `Option`, for at most one value.
+    Option,
+    /// A `Vec`, for any number of values.
+    Vec,
+}
+
+impl Wrapper {
+    /// What a field holding elements with `cardinality` wraps them in, like in generated
+    /// structs. `None` if it holds exactly one, which is the field's type itself.
+    pub fn of(cardinality: Cardinality) -> Option<Wrapper> {
+        match cardinality {
+            Cardinality::ExactlyOne => None,
+            Cardinality::ZeroOrOne => Some(Wrapper::Option),
+            Cardinality::ZeroOrMany | Cardinality::OneOrMany => Some(Wrapper::Vec),
+        }
+    }
+}
+
+/// The ways deriving `EbmlRead` or `EbmlWrite` can fail. Fields are given by their index in
+/// `DeriveInput::fields`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum DeriveError {
+    /// The schema has no container element with the struct's name for one. Holds the name.
+    UnknownContainer(String),
+    /// A field's element isn't a child of the container.
+    UnknownElement {
+        /// The field.
+        field: usize,
+        /// The container.
+        container: String,
+        /// The field's element.
+        element: String,
+    },
+    /// A field's element is already held by an earlier field.
+    DuplicateElement {
+        /// The later field.
+        field: usize,
+        /// The name of the earlier field.
+        first: String,
+        /// The element both hold.
+        element: String,
+    },
+    /// A field isn't wrapped in what its element's cardinality calls for.
+    WrongWrapper {
+        /// The field.
+        field: usize,
+        /// The field's element.
+        element: String,
+        /// What the field should wrap its values in.
+        expected: Wrapper,
+    },
+    /// The container's code couldn't be generated.
+    Codegen(CodegenError),
+}
+
+impl DeriveError {
+    /// The field at fault, if it's down to one.
+    pub fn field(&self) -> Option<usize> {
+        match *self {
+            DeriveError::UnknownElement { field, .. } |
+            DeriveError::DuplicateElement { field, .. } |
+            DeriveError::WrongWrapper { field, .. } => Some(field),
+            DeriveError::UnknownContainer(_) | DeriveError::Codegen(_) => None,
+        }
+    }
+}
+
+impl From<CodegenError> for DeriveError {
+    fn from(err: CodegenError) -> Self {
+        DeriveError::Codegen(err)
+    }
+}
+
+impl fmt::Display for DeriveError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            DeriveError::UnknownContainer(ref name) => {
+                write!(f, "the schema has no container element named `{}`", name)
+            }
+            DeriveError::UnknownElement { ref container, ref element, .. } => {
+                write!(f, "`{}` has no child element named `{}`", container, element)
+            }
+            DeriveError::DuplicateElement { ref first, ref element, .. } => {
+                write!(f, "`{}` is already held by `{}`", element, first)
+            }
+            DeriveError::WrongWrapper { ref element, expected, .. } => match expected {
+                Wrapper::Option => {
+                    write!(f, "`{}` occurs at most once, so the field should be an `Option`",
+                           element)
+                }
+                Wrapper::Vec => {
+                    write!(f, "`{}` may occur many times, so the field should be a `Vec`", element)
+                }
+            },
+            DeriveError::Codegen(ref err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl Error for DeriveError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match *self {
+            DeriveError::Codegen(ref err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+/// Generates the source of `read_from` and `read_with` functions for the struct described by
+/// `input`, and its implementation of `runtime::EbmlRead`. They read its container's body like a
+/// generated struct's do, leaving elements it has no field for to the default
+/// `UnknownElements` policy.
+///
+/// Each value type must implement `runtime::EbmlPrimitive` for the raw type of its element, or
+/// `runtime::EbmlRead` for containers, which is required by a `where` clause so that a type
+/// which doesn't is reported where it's written. Containers must be generated or derived structs.
+pub fn derive_read(dtd: &Dtd, input: &DeriveInput) -> Result<String, DeriveError> {
+    let options = CodegenOptions::default();
+    let (container, fields) = resolve(dtd, input)?;
+    let name = Ident::new(input.name.as_str());
+    let bounds = bounds(dtd, &fields, quote!(::ebml_macros::runtime::EbmlRead))?;
+    let functions = reader(dtd, &options, container, &name, &fields, &bounds, Mode::Sync);
+    let read_impl = read_impl(&name, &bounds);
+    implementation(dtd, quote! {
+        impl #name where #bounds {
+            #functions
+        }
+
+        #read_impl
+    })
+}
+
+/// Generates the source of `write_to` and `encoded_len` functions for the struct described by
+/// `input`, and its implementation of `runtime::EbmlWrite`, like `derive_read`. Only elements it
+/// has fields for are written.
+pub fn derive_write(dtd: &Dtd, input: &DeriveInput) -> Result<String, DeriveError> {
+    let options = CodegenOptions::default();
+    let (container, fields) = resolve(dtd, input)?;
+    let name = Ident::new(input.name.as_str());
+    let functions = writer(dtd, &options, container, &fields)?;
+    let bounds = bounds(dtd, &fields, quote!(::ebml_macros::runtime::EbmlWrite))?;
+    let write_impl = write_impl(&name, &bounds);
+    implementation(dtd, quote! {
+        impl #name where #bounds {
+            #functions
+        }
+
+        #write_impl
+    })
+}
+
+// Wraps `impls` in an anonymous constant, along with the `ids` module they refer to.
+fn implementation(dtd: &Dtd, impls: Tokens) -> Result<String, DeriveError> {
+    let ids = id_constants(dtd)?;
+    Ok(quote! {
+        const _: () = {
+            #[allow(dead_code)]
+            mod ids {
+                #ids
+            }
+
+            #impls
+        };
+    }.into_string())
+}
+
+// The container `input` is mapped onto, and a field for each of its fields.
+fn resolve<'d, 'a>(dtd: &'d Dtd<'a>, input: &DeriveInput)
+    -> Result<(&'d Element<'a>, Vec<Field<'d, 'a>>), DeriveError>
+{
+    let container = dtd.all_elements().into_iter()
+        .find(|element| element.name == input.element && element.type_ == Type::Container)
+        .ok_or_else(|| DeriveError::UnknownContainer(input.element.clone()))?;
+    let children = dtd.children_of(container);
+
+    let mut fields: Vec<Field> = Vec::new();
+    for (index, field) in input.fields.iter().enumerate() {
+        let element = *children.iter()
+            .find(|child| child.name == field.element)
+            .ok_or_else(|| DeriveError::UnknownElement {
+                field: index,
+                container: container.name.to_string(),
+                element: field.element.clone(),
+            })?;
+        if let Some(first) = fields.iter().find(|first| first.element.name == element.name) {
+            return Err(DeriveError::DuplicateElement {
+                field: index,
+                first: first.name.as_ref().to_string(),
+                element: field.element.clone(),
+            });
+        }
+        // Fields holding exactly one value can have any type, even an `Option` or a `Vec`,
+        // like a `binary` element held in a `Vec<u8>`.
+        let value_type = match (Wrapper::of(element.cardinality()), &field.wrapped) {
+            (None, _) => &field.type_,
+            (Some(expected), &Some((wrapper, ref value_type))) if wrapper == expected => value_type,
+            (Some(expected), _) => return Err(DeriveError::WrongWrapper {
+                field: index,
+                element: field.element.clone(),
+                expected,
+            }),
+        };
+
+        let mut tokens = Tokens::new();
+        tokens.append(value_type.as_str());
+        let name = Ident::new(field.name.as_str());
+        fields.push(Field { element, name, value_type: tokens, boxed: false });
+    }
+    Ok((container, fields))
+}
+
+// The predicates requiring each field's value type to implement `EbmlPrimitive` for its raw
+// type, or `container` for containers.
+fn bounds(dtd: &Dtd, fields: &[Field], container: Tokens) -> Result<Tokens, DeriveError> {
+    let mut bounds = Tokens::new();
+    for field in fields {
+        let raw = match dtd.resolve_type(field.element.type_) {
+            Some(Type::Int) | Some(Type::Date) => quote!(i64),
+            Some(Type::Uint) => quote!(u64),
+            Some(Type::Float) => quote!(f64),
+            Some(Type::String) => quote!(::std::string::String),
+            Some(Type::Binary) => quote!(::std::vec::Vec<u8>),
+            Some(Type::Container) => {
+                let value_type = &field.value_type;
+                bounds.append_all(&[quote!(#value_type: #container,)]);
+                continue;
+            }
+            _ => return Err(DeriveError::Codegen(CodegenError::UnknownType {
+                element: field.element.name.to_string(),
+                type_name: field.element.type_.name().to_string(),
+            })),
+        };
+        let value_type = &field.value_type;
+        bounds.append_all(&[quote!(#value_type: ::ebml_macros::runtime::EbmlPrimitive<#raw>,)]);
+    }
+    Ok(bounds)
+}
@@ -0,0 +1,54 @@
+//! Generates a `dump` function, which prints any document as a tree for debugging.
+
+use quote::{Ident, Tokens};
+
+use {Dtd, Type};
+use super::{screaming_snake_case, unique_names, CodegenError, CodegenOptions};
+
+pub fn dumper(dtd: &Dtd, options: &CodegenOptions) -> Result<Tokens, CodegenError> {
+    if !options.dump {
+        return Ok(Tokens::new());
+    }
+
+    let elements = dtd.all_elements();
+    let constants: Vec<_> = unique_names(&elements, &[], screaming_snake_case)?
+        .into_iter()
+        .map(Ident::new)
+        .collect();
+    let mut names = Vec::new();
+    let mut kinds = Vec::new();
+    for element in &elements {
+        let type_ = dtd.resolve_type(element.type_).ok_or_else(|| CodegenError::UnknownType {
+            element: element.name.to_string(),
+            type_name: element.type_.name().to_string(),
+        })?;
+        let kind = match type_ {
+            Type::Int => "Int",
+            Type::Uint => "Uint",
+            Type::Float => "Float",
+            Type::String => "String",
+            Type::Date => "Date",
+            Type::Binary => "Binary",
+            Type::Container => "Container",
+            Type::Name(_) => unreachable!("resolved types are built in"),
+        };
+        names.push(element.name);
+        kinds.push(Ident::new(kind));
+    }
+
+    Ok(quote! {
+        #[doc = "Prints every element in the rest of `r` to `out` as an indented tree, with its \
+                 size and value. Elements this schema doesn't describe are printed by id and \
+                 skipped. See `ebml_macros::runtime::dump` for the format."]
+        pub fn dump<R: ::std::io::Read, W: ::std::fmt::Write>(r: &mut R, out: &mut W)
+            -> ::std::result::Result<(), ::ebml_macros::runtime::ReadError>
+        {
+            use ::ebml_macros::runtime::DumpKind;
+
+            ::ebml_macros::runtime::dump(r, out, |id| match id {
+                #(ids::#constants => ::std::option::Option::Some((#names, DumpKind::#kinds)),)*
+                _ => ::std::option::Option::None,
+            })
+        }
+    })
+}
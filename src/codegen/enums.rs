@@ -0,0 +1,150 @@
+//! Generates an enum for every `uint` element whose range lists the values it may have, like
+//! `range: 1, 2, 17;`.
+
+use quote::{Ident, Tokens};
+
+use {Dtd, Element, Property, Type, UintRangeItem};
+use super::{camel_case, deprecated_attribute, documented, reusable, unique, unique_names,
+            CodegenError, CodegenOptions};
+use super::structs::containers;
+use super::types::newtypes;
+use super::write::scalar_encoded_len;
+
+pub fn enum_types(dtd: &Dtd, options: &CodegenOptions) -> Result<Tokens, CodegenError> {
+    let mut tokens = Tokens::new();
+    for (element, name) in enums(dtd, options)? {
+        tokens.append_all(&[enum_type(options, element, name)?]);
+    }
+    Ok(tokens)
+}
+
+// Every element which holds an enum, with the enum's name. There are none unless
+// `CodegenOptions::enums` is set.
+pub fn enums<'d, 'a>(dtd: &'d Dtd<'a>, options: &CodegenOptions)
+    -> Result<Vec<(&'d Element<'a>, Ident)>, CodegenError>
+{
+    if !options.enums {
+        return Ok(Vec::new());
+    }
+
+    let elements: Vec<_> = dtd.all_elements()
+        .into_iter()
+        .filter(|element| values(element).is_some())
+        .collect();
+    let containers = containers(dtd)?;
+    let newtypes = newtypes(dtd)?;
+    let mut reserved: Vec<&str> = containers.iter().map(|(_, name)| name.as_ref()).collect();
+    reserved.extend(newtypes.iter().map(|(_, name)| name.as_ref()));
+    reserved.push("ElementId");
+    let names = unique_names(&elements, &reserved, camel_case)?;
+    Ok(elements.into_iter().zip(names.into_iter().map(Ident::new)).collect())
+}
+
+// The values `element` may have, without repeats, if it's a `uint` whose range is made of
+// single values. Elements of user-defined types hold their type's newtype instead.
+fn values(element: &Element) -> Option<Vec<u64>> {
+    let range = match element.range.as_deref() {
+        Some(Property::UintRange(range)) if element.type_ == Type::Uint => range,
+        _ => return None,
+    };
+    let mut values = Vec::new();
+    for item in range.iter() {
+        match *item {
+            UintRangeItem::Single(value) if !values.contains(&value) => values.push(value),
+            UintRangeItem::Single(_) => {}
+            _ => return None,
+        }
+    }
+    Some(values)
+}
+
+fn enum_type(options: &CodegenOptions, element: &Element, name: Ident)
+    -> Result<Tokens, CodegenError>
+{
+    let values = values(element).expect("enums are only generated for discrete ranges");
+    // Values the `variants` property doesn't name are named after themselves.
+    let variant_names: Vec<_> = values.iter().map(|&value| {
+        element.variants.iter().flatten()
+            .find(|&&(_, named)| named == value)
+            .map_or_else(|| format!("V{}", value), |&(name, _)| name.to_string())
+    }).collect();
+    let variant_names: Vec<_> = variant_names.iter().map(String::as_str).collect();
+    let variants: Vec<_> = unique(&variant_names, &["Unknown"], camel_case)?
+        .into_iter()
+        .map(Ident::new)
+        .collect();
+    let docs: Vec<_> = values.iter().map(|value| format!("The value {}.", value)).collect();
+    let range: Vec<_> = values.iter().map(|&value| UintRangeItem::Single(value)).collect();
+
+    let doc = documented(format!("The values of a `{}` element.", element.name), element);
+    let deprecated = deprecated_attribute(element.deprecation());
+    let serde = options.serde_attribute();
+    let encoded_len = scalar_encoded_len(options, Type::Uint);
+
+    let (variants, values) = (reusable(&variants), reusable(&values));
+
+    Ok(quote! {
+        #[doc = #doc]
+        #deprecated
+        #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+        #serde
+        pub enum #name {
+            #(
+                #[doc = #docs]
+                #variants,
+            )*
+            #[doc = "A value outside the element's range."]
+            Unknown(u64),
+        }
+
+        impl #name {
+            #[doc = "The variant for `value`, or `Unknown` if there isn't one."]
+            pub fn from_value(value: u64) -> Self {
+                match value {
+                    #(#values => Self::#variants,)*
+                    value => Self::Unknown(value),
+                }
+            }
+
+            #[doc = "The value this stands for."]
+            pub fn value(&self) -> u64 {
+                match *self {
+                    #(Self::#variants => #values,)*
+                    Self::Unknown(value) => value,
+                }
+            }
+
+            #encoded_len
+        }
+
+        impl ::std::convert::From<#name> for u64 {
+            fn from(value: #name) -> Self {
+                value.value()
+            }
+        }
+
+        impl ::std::convert::TryFrom<u64> for #name {
+            type Error = ::ebml_macros::runtime::RangeError;
+
+            fn try_from(value: u64) -> ::std::result::Result<Self, Self::Error> {
+                ::ebml_macros::runtime::in_range(
+                    &value,
+                    &[#(#range),*]
+                )?;
+                ::std::result::Result::Ok(#name::from_value(value))
+            }
+        }
+
+        // Values outside the range are kept as `Unknown`, since readers check ranges themselves
+        // according to their options.
+        impl ::ebml_macros::runtime::EbmlPrimitive<u64> for #name {
+            fn from_raw(raw: u64) -> ::std::option::Option<Self> {
+                ::std::option::Option::Some(#name::from_value(raw))
+            }
+
+            fn to_raw(&self) -> ::std::option::Option<u64> {
+                ::std::option::Option::Some(self.value())
+            }
+        }
+    })
+}
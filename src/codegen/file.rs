@@ -0,0 +1,158 @@
+//! Writes generated code to a file, for build scripts and checked-in code.
+
+use std::env;
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+use {Dtd, DtdError, DtdFiles, IncludeError, MergeConflict, ParseOptions, Severity};
+use super::{c_header, generate, CodegenError, CodegenOptions};
+
+/// The ways `generate_to_file`, `generate_files_to_file` and `generate_c_header` can fail.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum GenerateError {
+    /// The code couldn't be generated.
+    Codegen(CodegenError),
+    /// A file couldn't be read or written.
+    Io(io::Error),
+    /// The generated code couldn't be formatted, because `rustfmt` couldn't be run or failed.
+    Format(io::Error),
+    /// An EDTD couldn't be parsed or validated. Holds its path and the error.
+    Parse(PathBuf, DtdError),
+    /// Two EDTDs define something differently. Holds the earlier one's path, the later one's,
+    /// and the conflict.
+    Conflict(PathBuf, PathBuf, MergeConflict),
+    /// An EDTD file, or one it includes, couldn't be read.
+    Include(IncludeError),
+}
+
+impl From<CodegenError> for GenerateError {
+    fn from(err: CodegenError) -> Self {
+        GenerateError::Codegen(err)
+    }
+}
+
+impl From<io::Error> for GenerateError {
+    fn from(err: io::Error) -> Self {
+        GenerateError::Io(err)
+    }
+}
+
+impl fmt::Display for GenerateError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            GenerateError::Codegen(ref err) => write!(f, "{}", err),
+            GenerateError::Io(ref err) => write!(f, "{}", err),
+            GenerateError::Format(ref err) => {
+                write!(f, "couldn't format the generated code: {}", err)
+            }
+            GenerateError::Parse(ref path, DtdError::Syntax(ref err)) => {
+                write!(f, "syntax error[{}] in {} at line {}, column {}: {}", err.code(),
+                       path.display(), err.line, err.column, err.message())
+            }
+            GenerateError::Parse(ref path, DtdError::Invalid(ref diagnostics)) => {
+                write!(f, "invalid {}", path.display())?;
+                for diagnostic in diagnostics.iter().filter(|d| d.is_error()) {
+                    write!(f, "\n    {}", diagnostic)?;
+                }
+                Ok(())
+            }
+            GenerateError::Parse(ref path, DtdError::Conflict(ref conflict)) => {
+                write!(f, "{}: {}", path.display(), conflict)
+            }
+            GenerateError::Conflict(ref first, ref second, ref conflict) => {
+                let (name, existing) = (&conflict.name, &conflict.existing);
+                let description =
+                    conflict.kind.describe(name, existing, first.display(), second.display());
+                f.write_str(&description)
+            }
+            GenerateError::Include(ref err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl Error for GenerateError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match *self {
+            GenerateError::Codegen(ref err) => Some(err),
+            GenerateError::Io(ref err) => Some(err),
+            GenerateError::Format(ref err) => Some(err),
+            GenerateError::Parse(_, ref err) => Some(err),
+            GenerateError::Conflict(_, _, ref conflict) => Some(conflict),
+            GenerateError::Include(ref err) => Some(err),
+        }
+    }
+}
+
+/// Generates the same code as `generate`, formats it with `format_source`, and writes it to
+/// `path`. This is meant to be called from a build script, with the result `include!`d. Nothing
+/// is written if the code can't be formatted.
+pub fn generate_to_file<P: AsRef<Path>>(dtd: &Dtd, module: &str, options: &CodegenOptions,
+                                        path: P)
+    -> Result<(), GenerateError>
+{
+    let code = format_source(&generate(dtd, module, options)?).map_err(GenerateError::Format)?;
+    fs::write(path, code)?;
+    Ok(())
+}
+
+/// Like `generate_to_file`, for an EDTD split across several files, which are read with what
+/// they include by `DtdFiles`, merged as by `parse_dtds` and validated. This is how a DocType's
+/// schema can leave the EBML header elements to a fragment shared with other schemas.
+pub fn generate_files_to_file<P, Q>(inputs: &[P], module: &str, options: &CodegenOptions, path: Q)
+    -> Result<(), GenerateError>
+    where P: AsRef<Path>, Q: AsRef<Path>
+{
+    let files = DtdFiles::read(inputs).map_err(GenerateError::Include)?;
+    let parse_options = ParseOptions { validate: Some(Severity::Error), ..Default::default() };
+    let dtd = files.parse(&parse_options).map_err(|(index, err)| match err {
+        DtdError::Conflict(conflict) => {
+            let first = files.path(conflict.first).to_path_buf();
+            GenerateError::Conflict(first, files.path(index).to_path_buf(), conflict)
+        }
+        err => GenerateError::Parse(files.path(index).to_path_buf(), err),
+    })?;
+    generate_to_file(&dtd, module, options, path)
+}
+
+/// Writes the C header made by `c_header` to `path`, so C code can share the element ids and
+/// header values with the generated Rust code.
+pub fn generate_c_header<P: AsRef<Path>>(dtd: &Dtd, prefix: &str, path: P)
+    -> Result<(), GenerateError>
+{
+    fs::write(path, c_header(dtd, prefix)?)?;
+    Ok(())
+}
+
+/// Formats generated code with `rustfmt`, so it can be read and reviewed. The `RUSTFMT`
+/// environment variable may name the `rustfmt` to use. Fails if it can't be run, or rejects the
+/// code, in which case the error holds what it printed.
+pub fn format_source(code: &str) -> io::Result<String> {
+    let rustfmt = env::var_os("RUSTFMT").unwrap_or_else(|| "rustfmt".into());
+    let mut child = Command::new(rustfmt)
+        .args(["--edition", "2015", "--emit", "stdout"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()?;
+
+    // Code this large can fill the pipe, so it's written from another thread while the output
+    // is read.
+    let mut stdin = child.stdin.take().expect("stdin was piped");
+    let input = code.to_string();
+    let writer = ::std::thread::spawn(move || stdin.write_all(input.as_bytes()));
+    let output = child.wait_with_output()?;
+    writer.join().expect("writing to rustfmt panicked")?;
+
+    if output.status.success() {
+        String::from_utf8(output.stdout)
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+    } else {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        Err(io::Error::other(format!("rustfmt failed: {}", stderr.trim())))
+    }
+}
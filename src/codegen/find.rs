@@ -0,0 +1,146 @@
+//! Generates `find_*` functions, which read one element of a document without reading the rest.
+
+use std::collections::HashSet;
+use std::ops::Deref;
+
+use quote::{Ident, Tokens};
+
+use suggest::suggest;
+use {Dtd, Element, Type};
+use super::{camel_case, screaming_snake_case, snake_case, unique, CodegenError, CodegenOptions};
+use super::read::{read_value, Mode};
+use super::structs::fields;
+
+pub fn finders(dtd: &Dtd, options: &CodegenOptions) -> Result<Tokens, CodegenError> {
+    let paths = match options.find_paths {
+        Some(ref paths) => paths.iter().map(|path| resolve(dtd, path)).collect::<Result<_, _>>()?,
+        None => default_paths(dtd),
+    };
+    let targets: Vec<_> = paths.iter().map(|path| path[path.len() - 1].name).collect();
+    let names = unique(&targets, &[], |name| format!("find_{}", snake_case(name)))?;
+
+    let mut tokens = Tokens::new();
+    for (path, name) in paths.iter().zip(names) {
+        tokens.append_all(&[finder(dtd, options, path, Ident::new(name))?]);
+    }
+    Ok(tokens)
+}
+
+// The elements named by a path like `\Segment\Info`, outermost first.
+fn resolve<'d, 'a>(dtd: &'d Dtd<'a>, path: &str) -> Result<Vec<&'d Element<'a>>, CodegenError> {
+    let names: Vec<_> = path.trim_start_matches('\\').split('\\').collect();
+    let mut elements: Vec<&Element> = Vec::new();
+    for (index, &name) in names.iter().enumerate() {
+        let candidates = match elements.last() {
+            Some(parent) => dtd.children_of(parent),
+            None => dtd.elements.iter().map(Deref::deref).collect(),
+        };
+        match candidates.iter().find(|element| element.name == name) {
+            Some(&element) => elements.push(element),
+            None => {
+                // The path with this name replaced by the one it's most likely a misspelling of.
+                let fixed = |fixed| -> String {
+                    let mut names: Vec<&str> = names.to_vec();
+                    names[index] = fixed;
+                    names.iter().map(|name| format!("\\{}", name)).collect()
+                };
+                let suggestion = suggest(name, candidates.iter().map(|element| element.name))
+                    .map(fixed);
+                return Err(CodegenError::UnknownPath { path: path.to_string(), suggestion });
+            }
+        }
+    }
+    Ok(elements)
+}
+
+// Paths to every container one or two levels inside a top-level container. Containers reachable
+// by more than one path only get the first.
+fn default_paths<'d, 'a>(dtd: &'d Dtd<'a>) -> Vec<Vec<&'d Element<'a>>> {
+    let containers = |parent: &'d Element<'a>| {
+        dtd.children_of(parent).into_iter().filter(|child| child.type_ == Type::Container)
+    };
+    let mut seen = HashSet::new();
+    let mut paths = Vec::new();
+    for root in dtd.elements.iter().filter(|root| root.type_ == Type::Container) {
+        for child in containers(root) {
+            if seen.insert(child.name) {
+                paths.push(vec![root, child]);
+            }
+            for grandchild in containers(child) {
+                if seen.insert(grandchild.name) {
+                    paths.push(vec![root, child, grandchild]);
+                }
+            }
+        }
+    }
+    paths
+}
+
+fn finder(dtd: &Dtd, options: &CodegenOptions, path: &[&Element], name: Ident)
+    -> Result<Tokens, CodegenError>
+{
+    let target = path[path.len() - 1];
+    let ids: Vec<_> = path.iter()
+        .map(|element| Ident::new(screaming_snake_case(element.name)))
+        .collect();
+    // Errors are already inside the target, and get its ancestors added innermost first.
+    let ancestors: Vec<_> = path[..path.len() - 1].iter().rev().map(|element| element.name)
+        .collect();
+
+    let (value_type, value) = if target.type_ == Type::Container {
+        let value_type = Ident::new(camel_case(target.name));
+        let value = quote!(#value_type::read_with(r, child_size, ctx));
+        (quote!(#value_type), value)
+    } else if path.len() > 1 {
+        let parent = path[path.len() - 2];
+        let field = fields(dtd, options, parent)?.into_iter()
+            .find(|field| field.element.name == target.name)
+            .expect("paths only hold children of the elements before them");
+        let value = read_value(dtd, &field, Mode::Sync);
+        (field.value_type.clone(), quote!({
+            use ::ebml_macros::runtime;
+            let value = #value;
+            ::std::result::Result::Ok(value)
+        }))
+    } else {
+        let path = target.name.to_string();
+        return Err(CodegenError::UnknownPath { path, suggestion: None });
+    };
+
+    let path_text: String = path.iter().map(|element| format!("\\{}", element.name)).collect();
+    let doc = format!(
+        "Finds the first `{}` element in the rest of `r`, seeking past everything which doesn't \
+         lead to it.",
+        path_text
+    );
+
+    Ok(quote! {
+        #[doc = #doc]
+        pub fn #name<R: ::std::io::Read + ::std::io::Seek>(r: &mut R)
+            -> ::std::result::Result<
+                ::std::option::Option<#value_type>,
+                ::ebml_macros::runtime::ReadError
+            >
+        {
+            fn read<R: ::std::io::Read>(r: &mut R, child_size: u64,
+                                        ctx: &mut ::ebml_macros::runtime::ReadContext)
+                -> ::std::result::Result<#value_type, ::ebml_macros::runtime::ReadError>
+            {
+                #value
+            }
+
+            let path = [#(ids::#ids),*];
+            match ::ebml_macros::runtime::find_path(r, &path, ::std::option::Option::None)? {
+                ::std::option::Option::Some(size) => {
+                    let ctx = &mut ::ebml_macros::runtime::ReadContext::default();
+                    read(r, size, ctx)
+                        .map(::std::option::Option::Some)
+                        .map_err(|err| err #(.within(#ancestors))*)
+                }
+                ::std::option::Option::None => {
+                    ::std::result::Result::Ok(::std::option::Option::None)
+                }
+            }
+        }
+    })
+}
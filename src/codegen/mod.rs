@@ -0,0 +1,1097 @@
+//! Generates Rust code from a parsed EDTD.
+
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+
+use ebml::Id;
+use quote::{ByteStr, Hex, Ident, Tokens};
+
+use deprecation::untagged_lines;
+use {Deprecation, Dtd, Element, HeaderStatement, Type};
+use runtime::{RangePolicy, RawId, UnknownElements};
+use self::tokens::{default_value, header_value, Raw};
+
+#[cfg(feature = "arbitrary")]
+mod arbitrary;
+mod borrowed;
+mod builders;
+mod c_header;
+mod derive;
+mod dump;
+mod enums;
+mod file;
+mod find;
+mod push;
+mod read;
+mod schema;
+mod stream;
+mod structs;
+mod tokens;
+mod types;
+mod write;
+
+pub use self::c_header::c_header;
+pub use self::derive::{derive_read, derive_write, DeriveError, DeriveField, DeriveInput,
+                       Wrapper};
+pub use self::file::{format_source, generate_c_header, generate_files_to_file, generate_to_file,
+                     GenerateError};
+pub use self::schema::schema_descriptor;
+
+/// The ways generating code from an EDTD can fail.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum CodegenError {
+    /// Two elements or types would be given the same name in the generated code.
+    NameCollision {
+        /// The name both would be given.
+        generated: String,
+        /// The element or type which was given the name first.
+        first: String,
+        /// The element or type which collided with it.
+        second: String,
+    },
+    /// An element's type is neither built-in nor defined in the EDTD.
+    UnknownType {
+        /// The element with the unknown type.
+        element: String,
+        /// The name of the unknown type.
+        type_name: String,
+    },
+    /// An element in `CodegenOptions::unknown_size` doesn't have `unknownsizeallowed:yes`. Holds
+    /// its name.
+    UnknownSizeNotAllowed(String),
+    /// A path in `CodegenOptions::find_paths` doesn't lead to an element.
+    UnknownPath {
+        /// The path.
+        path: String,
+        /// The path with the first name which doesn't lead anywhere replaced by the one it's
+        /// most likely a misspelling of, if any is close enough.
+        suggestion: Option<String>,
+    },
+    /// A header statement's value is a name which doesn't lead to another statement's value.
+    UnresolvedHeader {
+        /// The header statement.
+        statement: String,
+        /// The name it has as its value.
+        name: String,
+    },
+    /// An element's id isn't a valid encoded id, so no `ebml::Id` can be made from it.
+    InvalidId {
+        /// The element.
+        element: String,
+        /// Its encoded id.
+        id: u32,
+    },
+    /// `CodegenOptions::doc_type` names a DocType the EDTD has no elements for. Holds the name.
+    UnknownDocType(String),
+}
+
+impl CodegenError {
+    /// True if this error only stops structs and functions from being generated, so
+    /// `CodegenMode::Fallback` can still generate constants.
+    pub fn allows_constants(&self) -> bool {
+        match *self {
+            CodegenError::UnknownType { .. } => true,
+            // Collisions between element names fail the constants first, so these are between
+            // type names.
+            CodegenError::NameCollision { .. } => true,
+            CodegenError::UnknownSizeNotAllowed(_) | CodegenError::UnknownPath { .. } => false,
+            CodegenError::UnresolvedHeader { .. } | CodegenError::InvalidId { .. } => false,
+            CodegenError::UnknownDocType(_) => false,
+        }
+    }
+}
+
+impl fmt::Display for CodegenError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            CodegenError::NameCollision { ref generated, ref first, ref second } => write!(
+                f,
+                "`{}` and `{}` would both generate `{}`",
+                first, second, generated
+            ),
+            CodegenError::UnknownType { ref element, ref type_name } => {
+                write!(f, "element `{}` has unknown type `{}`", element, type_name)
+            }
+            CodegenError::UnknownSizeNotAllowed(ref name) => {
+                write!(f, "element `{}` can't be written with an unknown size", name)
+            }
+            CodegenError::UnknownPath { ref path, ref suggestion } => {
+                write!(f, "path `{}` doesn't lead to an element", path)?;
+                match *suggestion {
+                    Some(ref suggestion) => write!(f, "; did you mean `{}`?", suggestion),
+                    None => Ok(()),
+                }
+            }
+            CodegenError::UnresolvedHeader { ref statement, ref name } => write!(
+                f,
+                "header statement `{}` refers to `{}`, which doesn't lead to a value",
+                statement, name
+            ),
+            CodegenError::InvalidId { ref element, id } => {
+                write!(f, "element `{}` has id {:#x}, which isn't a valid encoded id", element, id)
+            }
+            CodegenError::UnknownDocType(ref doc_type) => {
+                write!(f, "there are no elements for DocType `{}`", doc_type)
+            }
+        }
+    }
+}
+
+impl Error for CodegenError {}
+
+/// Options controlling the code produced by `generate`.
+///
+/// The `*_type` fields choose the Rust type used for elements of each primitive EDTD type. They
+/// hold the source text of a type, which must implement `runtime::EbmlPrimitive` for the
+/// primitive's raw type.
+#[derive(Debug, Clone)]
+pub struct CodegenOptions {
+    /// What to generate. Defaults to `CodegenMode::Full`.
+    pub mode: CodegenMode,
+    /// The DocType to generate code for, if the EDTD has element blocks labeled with several.
+    /// If `None` (the default), it's the one the header declares. See `Dtd::select_doc_type`.
+    pub doc_type: Option<String>,
+    /// Whether `uint` elements whose range lists the values they may have, like `range: 1, 2,
+    /// 17;`, hold an enum with a variant for each value instead of `uint_type`. Defaults to false.
+    /// Variants are named by the element's `variants` property, like `variants: Video = 1;`, or
+    /// else after their values, like `V17`.
+    pub enums: bool,
+    /// The type used for `int` elements. Defaults to `i64`.
+    pub int_type: String,
+    /// The type used for `uint` elements. Defaults to `u64`.
+    pub uint_type: String,
+    /// The type used for `float` elements. Defaults to `f64`.
+    pub float_type: String,
+    /// The type used for `string` elements. Defaults to `String`.
+    pub string_type: String,
+    /// The type used for `binary` elements. Defaults to `Vec<u8>`.
+    pub binary_type: String,
+    /// The longest `binary` element of a fixed size (like `size:16;`) which is held in a
+    /// `[u8; N]` instead of `binary_type`. Defaults to 64; 0 turns arrays off. With `serde`, it's
+    /// at most 32, since longer arrays aren't serializable.
+    pub fixed_binary_limit: u64,
+    /// The type used for `date` elements. Defaults to `chrono::NaiveDateTime`, or `EbmlDate`
+    /// without the `chrono` feature. With `i64` or `EbmlDate`, dates are kept as nanoseconds since
+    /// 2001-01-01T00:00:00 UTC, and the generated code doesn't refer to `chrono` at all.
+    pub date_type: String,
+    /// How generated writers encode `float` elements. Defaults to `FloatEncoding::Double`.
+    pub float_encoding: FloatEncoding,
+    /// Whether generated writers leave out mandatory elements which are equal to their default,
+    /// since readers fill the default back in. Defaults to false. Values are compared by their
+    /// encoded bodies, so floats are compared bit for bit.
+    pub omit_defaults: bool,
+    /// What generated writers do with values outside their element's range, unless their
+    /// `WriteOptions` say otherwise. Defaults to `RangePolicy::Error`.
+    pub write_ranges: RangePolicy,
+    /// The traits derived by generated structs. Defaults to `Debug`, `Clone` and `PartialEq`.
+    pub derives: Vec<String>,
+    /// The names of container elements whose writers start them with a `CRC-32` element covering
+    /// the rest of their body. Defaults to none.
+    pub crc32: Vec<String>,
+    /// The names of container elements which are written with an unknown size, so nothing
+    /// inside them needs to be buffered or measured before they're written. They must have
+    /// `unknownsizeallowed:yes`. Defaults to none, which writes every size.
+    pub unknown_size: Vec<String>,
+    /// What generated readers do with elements the schema doesn't describe, unless their
+    /// `ReadOptions` say otherwise. Defaults to `UnknownElements::Skip`. With
+    /// `UnknownElements::Collect`, every container struct gets an `unknown_children` field to keep
+    /// them in.
+    pub unknown_elements: UnknownElements,
+    /// Whether to generate a `Handler` trait and `stream` functions, which read a document
+    /// incrementally and pass what they read to a handler. Defaults to false.
+    pub streaming: bool,
+    /// The names of container elements which `stream` reads child by child, calling the
+    /// handler's `on_enter_*` and `on_leave_*` methods around them. Other containers are read
+    /// whole and passed to the handler as structs. Defaults to none.
+    pub streamed: Vec<String>,
+    /// The paths of the elements to generate `find_*` functions for, written like
+    /// `\Segment\Info`. If `None` (the default), they're generated for every container one or
+    /// two levels inside a top-level container.
+    pub find_paths: Option<Vec<String>>,
+    /// Whether to generate a `dump` function, which prints a document as a tree. Defaults to
+    /// false.
+    pub dump: bool,
+    /// Whether to generate a `Parser`, which reads a document from bytes fed to it in chunks of
+    /// any size, and the `Event` enum it returns. Defaults to false.
+    pub push: bool,
+    /// Whether to generate a `borrowed` module, whose container structs borrow their `string`
+    /// and `binary` children from a slice holding the document instead of copying them.
+    /// Defaults to false. Borrowed fields are `&str` and `&[u8]` whatever `string_type` and
+    /// `binary_type` say.
+    pub borrowed: bool,
+    /// Whether every container struct gets a `builder` function, returning a builder with a
+    /// method setting each field (or adding to it, for fields holding many values) and a `build`
+    /// method checking that mandatory elements are present. Defaults to false.
+    pub builders: bool,
+    /// Whether generated types also derive `serde::Serialize` and `serde::Deserialize`. Defaults
+    /// to false. The including crate must depend on `serde` with its `derive` feature, and on
+    /// `chrono` with its `serde` feature if dates are used.
+    #[cfg(feature = "serde")]
+    pub serde: bool,
+    /// Whether container structs implement `runtime::Arbitrary`, proptest's `Arbitrary`, with
+    /// strategies generating values the schema allows, and a `check_round_trips` function is
+    /// generated to test them. Generated types derive `Debug` too, as proptest needs. Defaults
+    /// to false.
+    #[cfg(feature = "arbitrary")]
+    pub arbitrary: bool,
+    /// Whether container structs also get `read_from_async` and `read_with_async` functions,
+    /// reading from a `tokio::io::AsyncRead`, and `stream_async` and `stream_with_async` are
+    /// generated alongside `stream`. Defaults to false. The generated functions are `async fn`s,
+    /// so the including crate must use the 2018 edition or later.
+    #[cfg(feature = "async")]
+    pub asynchronous: bool,
+}
+
+/// How much `generate` generates.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CodegenMode {
+    /// Everything.
+    Full,
+    /// Only the constants: those from the header, `element_name`, `element_id` and
+    /// `FIELD_NAME_MAP`, and the `ids` and `defaults` modules. They don't depend on any other
+    /// crate.
+    Constants,
+    /// Everything if possible. If the EDTD uses something the structs and functions can't
+    /// handle (see `CodegenError::allows_constants`), just the constants, with the module's
+    /// documentation saying what was skipped and why.
+    Fallback,
+}
+
+/// The sizes a `float` element can be written with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FloatEncoding {
+    /// 4 bytes, as an IEEE 754 single precision number. Values lose precision.
+    Single,
+    /// 8 bytes, as an IEEE 754 double precision number.
+    Double,
+}
+
+impl Default for CodegenOptions {
+    fn default() -> Self {
+        CodegenOptions {
+            mode: CodegenMode::Full,
+            doc_type: None,
+            enums: false,
+            int_type: "i64".to_string(),
+            uint_type: "u64".to_string(),
+            float_type: "f64".to_string(),
+            string_type: "::std::string::String".to_string(),
+            binary_type: "::std::vec::Vec<u8>".to_string(),
+            fixed_binary_limit: 64,
+            date_type: if cfg!(feature = "chrono") {
+                "::chrono::NaiveDateTime".to_string()
+            } else {
+                "::ebml_macros::EbmlDate".to_string()
+            },
+            float_encoding: FloatEncoding::Double,
+            omit_defaults: false,
+            write_ranges: RangePolicy::Error,
+            derives: vec!["Debug".to_string(), "Clone".to_string(), "PartialEq".to_string()],
+            crc32: Vec::new(),
+            unknown_size: Vec::new(),
+            unknown_elements: UnknownElements::Skip,
+            streaming: false,
+            streamed: Vec::new(),
+            find_paths: None,
+            dump: false,
+            push: false,
+            borrowed: false,
+            builders: false,
+            #[cfg(feature = "serde")]
+            serde: false,
+            #[cfg(feature = "arbitrary")]
+            arbitrary: false,
+            #[cfg(feature = "async")]
+            asynchronous: false,
+        }
+    }
+}
+
+impl CodegenOptions {
+    // The Rust type used for elements of a primitive type, or None for containers.
+    fn primitive_type(&self, type_: Type) -> Option<&str> {
+        match type_ {
+            Type::Int => Some(&self.int_type),
+            Type::Uint => Some(&self.uint_type),
+            Type::Float => Some(&self.float_type),
+            Type::String => Some(&self.string_type),
+            Type::Binary => Some(&self.binary_type),
+            Type::Date => Some(&self.date_type),
+            Type::Container | Type::Name(_) => None,
+        }
+    }
+
+    // The length of the array holding `element`, if it's a binary of a fixed size short enough.
+    fn fixed_binary_len(&self, type_: Type, element: &Element) -> Option<u64> {
+        let mut limit = self.fixed_binary_limit;
+        if self.serde() {
+            limit = limit.min(32);
+        }
+        match element.size {
+            Some(ref sizes) if type_ == Type::Binary => {
+                sizes.is_fixed().filter(|&len| len > 0 && len <= limit)
+            }
+            _ => None,
+        }
+    }
+
+    // Whether container structs have an `unknown_children` field.
+    fn collects_unknown(&self) -> bool {
+        self.unknown_elements == UnknownElements::Collect
+    }
+
+    // The policy for unknown elements, as an expression.
+    fn unknown_elements(&self) -> Tokens {
+        match self.unknown_elements {
+            UnknownElements::Error => quote!(::ebml_macros::runtime::UnknownElements::Error),
+            UnknownElements::Skip => quote!(::ebml_macros::runtime::UnknownElements::Skip),
+            UnknownElements::Collect => quote!(::ebml_macros::runtime::UnknownElements::Collect),
+        }
+    }
+
+    // The policy for values outside their range when writing, as an expression.
+    fn write_ranges(&self) -> Tokens {
+        match self.write_ranges {
+            RangePolicy::Error => quote!(::ebml_macros::runtime::RangePolicy::Error),
+            RangePolicy::Clamp => quote!(::ebml_macros::runtime::RangePolicy::Clamp),
+            RangePolicy::Ignore => quote!(::ebml_macros::runtime::RangePolicy::Ignore),
+        }
+    }
+
+    // Whether generated types derive serde's traits.
+    #[cfg(feature = "serde")]
+    fn serde(&self) -> bool {
+        self.serde
+    }
+
+    #[cfg(not(feature = "serde"))]
+    fn serde(&self) -> bool {
+        false
+    }
+
+    // Whether container structs implement `runtime::Arbitrary`.
+    #[cfg(feature = "arbitrary")]
+    fn arbitrary(&self) -> bool {
+        self.arbitrary
+    }
+
+    #[cfg(not(feature = "arbitrary"))]
+    fn arbitrary(&self) -> bool {
+        false
+    }
+
+    // Whether async readers are generated too.
+    #[cfg(feature = "async")]
+    fn asynchronous(&self) -> bool {
+        self.asynchronous
+    }
+
+    #[cfg(not(feature = "async"))]
+    fn asynchronous(&self) -> bool {
+        false
+    }
+
+    // The derive attribute for generated structs.
+    fn derive_attribute(&self) -> Tokens {
+        let mut derives: Vec<&str> = self.derives.iter().map(String::as_str).collect();
+        if self.arbitrary() && !derives.contains(&"Debug") {
+            derives.push("Debug");
+        }
+        if self.serde() {
+            derives.extend(&["::serde::Serialize", "::serde::Deserialize"]);
+        }
+
+        let mut tokens = Tokens::new();
+        if !derives.is_empty() {
+            tokens.append(format!("#[derive({})]", derives.join(", ")));
+        }
+        tokens
+    }
+
+    // The attribute deriving serde's traits, if they're wanted, for types with fixed derives.
+    fn serde_attribute(&self) -> Tokens {
+        if self.serde() {
+            quote!(#[derive(::serde::Serialize, ::serde::Deserialize)])
+        } else {
+            Tokens::new()
+        }
+    }
+}
+
+/// Generates the source of a module named `module` containing the items described by `dtd`.
+///
+/// The module contains:
+///
+/// * a constant holding the value of every statement in the header, like `DOC_TYPE` and
+///   `DOC_TYPE_VERSION`, named after the statement in `SCREAMING_SNAKE_CASE`. Statements whose
+///   value is the name of another take that one's value. Dates are nanoseconds since 2001, and
+///   strings and binaries are slices,
+/// * `element_name` and `element_id` `const fn`s, mapping encoded ids to names and back for every
+///   element in the schema, the EBML header and the global elements, like `Void`,
+/// * a `FIELD_NAME_MAP` constant pairing the `snake_case` name of every element's struct fields
+///   with the element's name,
+/// * an `ids` module with a constant holding the encoded id of every element, named after the
+///   element in `SCREAMING_SNAKE_CASE`,
+/// * a `defaults` module with a constant holding the default of every element with one, named
+///   the same way. Dates are nanoseconds since 2001, and strings and binaries are slices,
+/// * a `read_ebml_header` function, which reads the EBML header at the start of a document and
+///   checks that its DocType and read versions are ones this schema describes,
+/// * an `ElementId` enum with a variant for every element, named after the element in
+///   `CamelCase`, plus `Unknown(ebml::Id)` for everything else,
+/// * a newtype for every type in the `define types` block, named after the type in `CamelCase`,
+///   whose `new` function checks the type's range. Elements of the type hold the newtype,
+/// * if `CodegenOptions::enums` is set, an enum for every `uint` element whose range lists its
+///   values, named after the element in `CamelCase`, with a variant for each value plus
+///   `Unknown(u64)` for values outside the range. The element holds the enum. Newtypes and enums
+///   have an `encoded_len` function giving the length of an element holding them,
+/// * a struct for every container element, named after the element in `CamelCase`, with a field
+///   (named in `snake_case`) for each of its children, and `read_from`, `write_to` and
+///   `encoded_len` functions decoding, encoding and measuring it, plus `write_with` and
+///   `encoded_len_with` taking `runtime::WriteOptions`, which are also the implementations of
+///   `runtime::EbmlRead` and `runtime::EbmlWrite`. Containers with
+///   `unknownsizeallowed:yes` also get `read_unsized`, for when their size is unknown,
+/// * if `CodegenOptions::builders` is set, a builder for every container struct, named after it
+///   with `Builder` appended and returned by its `builder` function,
+/// * if `CodegenOptions::borrowed` is set, a `borrowed` module with a struct for every container
+///   which has `string` or `binary` elements inside it, whose fields borrow them from the input.
+///   Its `read_from` reads from a slice holding the element's body. Other containers are
+///   re-exported from the parent module. Elements of unknown size must extend to the end of
+///   their parent,
+/// * if `CodegenOptions::streaming` is set, a `Handler` trait with a callback for each element
+///   the streaming reader can come across, and `stream` and `stream_with` functions driving it,
+/// * if `CodegenOptions::push` is set, a `Parser` which is fed a document in chunks and returns
+///   the `Event`s found in each: the beginnings and ends of containers, and other elements'
+///   values,
+/// * a `find_*` function for each of `CodegenOptions::find_paths`, named after the element at
+///   the end of the path in `snake_case`, which reads just that element from a document,
+/// * if `CodegenOptions::dump` is set, a `dump` function printing a document as a tree,
+/// * a `schema` function returning a `runtime::SchemaDescriptor` describing every element, as
+///   `schema_descriptor` does,
+/// * with the `arbitrary` feature, if `CodegenOptions::arbitrary` is set, an implementation of
+///   proptest's `Arbitrary` for every container struct, and a `check_round_trips` function,
+/// * with the `async` feature, if `CodegenOptions::asynchronous` is set, `async` versions of
+///   the readers and `stream` functions, suffixed with `_async`, which read from a
+///   `tokio::io::AsyncRead`.
+///
+/// Only the first five are generated with `CodegenMode::Constants`.
+///
+/// Names are converted deterministically. Words start at a capital following a lowercase letter,
+/// or ending a run of capitals by starting a capitalized word, so `EBMLMaxIDLength` becomes
+/// `ebml_max_id_length`. Digits belong to the word before them, so `CRC32Value` becomes
+/// `crc32_value` and `Video3DMode` becomes `video3d_mode`, and underscores are kept. Names
+/// which convert to the same identifier, like `TrackUID` and `TrackUid`, are a
+/// `CodegenError::NameCollision` naming both.
+///
+/// The comments before an element's definition are added to the documentation of everything
+/// generated for it. If they have an `@deprecated` tag, its id constant, `ElementId` variant,
+/// struct or enum, and the fields holding it are marked `#[deprecated]` with its version and
+/// reason, and the module allows their use inside it. Newtypes are marked the same way by an
+/// `@deprecated` tag in the comments before their type's definition.
+///
+/// The generated code refers to the `ebml`, `chrono` and `ebml_macros` crates, so the crate
+/// including it must depend on them, except with `CodegenMode::Constants`, whose code doesn't
+/// depend on anything. `chrono` is only needed if `CodegenOptions::date_type` uses it. This is
+/// exactly the code `ebml_macros_impl`'s macros expand to; `generate_to_file` writes it to a file
+/// instead. `generate_c_header` writes the ids and header values for C code.
+pub fn generate(dtd: &Dtd, module: &str, options: &CodegenOptions)
+    -> Result<String, CodegenError>
+{
+    generate_tokens(dtd, module, options).map(Tokens::into_string)
+}
+
+/// The name to give `dtd`'s module if there's no better one: its DocType in `snake_case`, with
+/// anything which can't be in an identifier replaced by `_`. `None` if it has no DocType.
+pub fn module_name(dtd: &Dtd) -> Option<String> {
+    let doc_type: String = dtd.doc_type()?.chars()
+        .map(|c| if c.is_alphanumeric() { c } else { '_' })
+        .collect();
+    let name = snake_case(&doc_type);
+    if name.starts_with(|c: char| c.is_numeric()) {
+        Some(format!("_{}", name))
+    } else {
+        Some(rust_ident(name))
+    }
+}
+
+fn generate_tokens(dtd: &Dtd, module: &str, options: &CodegenOptions)
+    -> Result<Tokens, CodegenError>
+{
+    let dtd = &*selected_doc_type(dtd, options)?;
+    let module = Ident::new(rust_ident(module.to_string()));
+    let constants = constants(dtd)?;
+    let (items, doc) = match options.mode {
+        CodegenMode::Full => (items(dtd, options)?, None),
+        CodegenMode::Constants => (Tokens::new(), None),
+        CodegenMode::Fallback => match items(dtd, options) {
+            Ok(items) => (items, None),
+            Err(ref err) if err.allows_constants() => {
+                let doc = format!("Only constants were generated for this schema: {}.", err);
+                (Tokens::new(), Some(doc))
+            }
+            Err(err) => return Err(err),
+        },
+    };
+    let doc = doc.map(|doc| quote!(#![doc = #doc]));
+    // Deprecated elements and types are still read and written by the module itself.
+    let deprecated = dtd.all_elements().iter().any(|element| element.deprecation().is_some()) ||
+        dtd.types.iter().any(|new_type| new_type.deprecation().is_some());
+    let allow = if deprecated {
+        quote!(#![allow(deprecated)])
+    } else {
+        Tokens::new()
+    };
+
+    Ok(quote! {
+        pub mod #module {
+            #doc
+            #allow
+
+            #constants
+
+            #items
+        }
+    })
+}
+
+/// Why `generate` only generates constants for `dtd` with `CodegenMode::Fallback`, if it does.
+/// This is the note added to the generated module's documentation.
+pub fn fallback_reason(dtd: &Dtd, options: &CodegenOptions) -> Option<CodegenError> {
+    if options.mode != CodegenMode::Fallback {
+        return None;
+    }
+    let dtd = selected_doc_type(dtd, options).ok()?;
+    items(&dtd, options).err().filter(CodegenError::allows_constants)
+}
+
+// The EDTD of the DocType `options` choose.
+fn selected_doc_type<'d, 'a>(dtd: &'d Dtd<'a>, options: &CodegenOptions)
+    -> Result<Cow<'d, Dtd<'a>>, CodegenError>
+{
+    match options.doc_type {
+        Some(ref doc_type) => dtd.select_doc_type(doc_type)
+            .map(Cow::Owned)
+            .ok_or_else(|| CodegenError::UnknownDocType(doc_type.clone())),
+        None => Ok(Cow::Borrowed(dtd)),
+    }
+}
+
+// The items which only depend on the EDTD's numbers and strings.
+fn constants(dtd: &Dtd) -> Result<Tokens, CodegenError> {
+    let header = header_constants(dtd)?;
+    let ids = id_constants(dtd)?;
+    let defaults = default_constants(dtd)?;
+    let lookups = name_lookups(dtd);
+    let field_names = field_name_map(dtd);
+
+    Ok(quote! {
+        #header
+
+        #lookups
+
+        #field_names
+
+        #[doc = "The encoded ids of every element in this schema."]
+        pub mod ids {
+            #ids
+        }
+
+        #[doc = "The defaults of every element in this schema which has one."]
+        pub mod defaults {
+            #defaults
+        }
+    })
+}
+
+#[cfg(feature = "arbitrary")]
+use self::arbitrary::arbitrary_impls;
+
+#[cfg(not(feature = "arbitrary"))]
+fn arbitrary_impls(_: &Dtd, _: &CodegenOptions) -> Result<Tokens, CodegenError> {
+    Ok(Tokens::new())
+}
+
+// Everything else, which needs the runtime.
+fn items(dtd: &Dtd, options: &CodegenOptions) -> Result<Tokens, CodegenError> {
+    let header = header_reader(dtd);
+    let element_id = element_id_enum(dtd, options)?;
+    let types = types::newtype_structs(dtd, options)?;
+    let enums = enums::enum_types(dtd, options)?;
+    let structs = structs::container_structs(dtd, options)?;
+    let builders = builders::builders(dtd, options)?;
+    let readers = read::readers(dtd, options)?;
+    let borrowed = borrowed::borrowed_module(dtd, options)?;
+    let writers = write::writers(dtd, options)?;
+    let stream = stream::stream_reader(dtd, options)?;
+    let push = push::push_parser(dtd, options)?;
+    let finders = find::finders(dtd, options)?;
+    let dumper = dump::dumper(dtd, options)?;
+    let schema = schema::schema_fn(dtd)?;
+    let arbitrary = arbitrary_impls(dtd, options)?;
+
+    Ok(quote! {
+        #header
+
+        #element_id
+
+        #types
+
+        #enums
+
+        #structs
+
+        #builders
+
+        #readers
+
+        #borrowed
+
+        #writers
+
+        #stream
+
+        #push
+
+        #finders
+
+        #dumper
+
+        #schema
+
+        #arbitrary
+    })
+}
+
+// A function reading the EBML header and checking it against the `declare header` block.
+fn header_reader(dtd: &Dtd) -> Tokens {
+    let doc_type = match dtd.doc_type() {
+        Some(_) => quote!(::std::option::Option::Some(DOC_TYPE)),
+        None => quote!(::std::option::Option::None),
+    };
+    let doc_type_version = match dtd.header_uint("DocTypeVersion") {
+        Some(version) => quote!(::std::option::Option::Some(#version)),
+        None => quote!(::std::option::Option::None),
+    };
+    // Only version 1 of EBML exists, so that's what's assumed if the header doesn't say.
+    let ebml_version = dtd.header_uint("EBMLVersion").unwrap_or(1);
+
+    quote! {
+        #[doc = "Reads the EBML header of a document, checking that this schema can read it."]
+        pub fn read_ebml_header<R: ::std::io::Read>(r: &mut R)
+            -> ::std::result::Result<
+                ::ebml_macros::runtime::EbmlHeader,
+                ::ebml_macros::runtime::HeaderError
+            >
+        {
+            let header = ::ebml_macros::runtime::read_ebml_header(r)?;
+            ::ebml_macros::runtime::check_ebml_header(
+                &header, #doc_type, #doc_type_version, #ebml_version
+            )?;
+            ::std::result::Result::Ok(header)
+        }
+    }
+}
+
+// Adds the comments before `element`'s definition to `doc`, as a paragraph of their own. Each
+// line has the whitespace and `*` or `/` characters around it removed, which cleans up block
+// comments and doc-style `///` comments. An `@deprecated` tag is left out, since
+// `deprecated_attribute` gives it.
+fn documented(mut doc: String, element: &Element) -> String {
+    let comments = untagged_lines(&element.comments).join("\n");
+    let comments = comments.trim();
+    if !comments.is_empty() {
+        doc.push_str("\n\n");
+        doc.push_str(comments);
+    }
+    doc
+}
+
+// A `#[deprecated]` attribute for what's generated for an element or type with `deprecation`.
+fn deprecated_attribute(deprecation: Option<Deprecation>) -> Tokens {
+    let deprecation = match deprecation {
+        Some(deprecation) => deprecation,
+        None => return Tokens::new(),
+    };
+    let since = deprecation.since.map(|since| since.to_string());
+    match (since, deprecation.reason) {
+        (Some(since), Some(note)) => quote!(#[deprecated(since = #since, note = #note)]),
+        (Some(since), None) => quote!(#[deprecated(since = #since)]),
+        (None, Some(note)) => quote!(#[deprecated(note = #note)]),
+        (None, None) => quote!(#[deprecated]),
+    }
+}
+
+// `items`, to interpolate in as many repetitions as needed. `quote!` turns what a repetition
+// iterates over into an iterator by value, so a `Vec` is consumed by the first repetition using
+// it, but a slice is only borrowed.
+fn reusable<T>(items: &[T]) -> &[T] {
+    items
+}
+
+// Converts the name of each element with `convert`, failing if two elements end up with the same
+// name. `reserved` names are treated as if an element had already claimed them.
+fn unique_names<F>(elements: &[&Element], reserved: &[&str], convert: F)
+    -> Result<Vec<String>, CodegenError>
+    where F: Fn(&str) -> String
+{
+    let names: Vec<_> = elements.iter().map(|element| element.name).collect();
+    unique(&names, reserved, convert)
+}
+
+// Like `unique_names`, for anything with a name.
+fn unique<F>(names: &[&str], reserved: &[&str], convert: F) -> Result<Vec<String>, CodegenError>
+    where F: Fn(&str) -> String
+{
+    let mut seen: HashMap<String, String> = reserved.iter()
+        .map(|name| (name.to_string(), name.to_string()))
+        .collect();
+    let mut generated_names = Vec::new();
+
+    for name in names {
+        let generated = convert(name);
+        if let Some(first) = seen.get(&generated) {
+            return Err(CodegenError::NameCollision {
+                generated,
+                first: first.clone(),
+                second: name.to_string(),
+            });
+        }
+        seen.insert(generated.clone(), name.to_string());
+        generated_names.push(generated);
+    }
+    Ok(generated_names)
+}
+
+fn header_constants(dtd: &Dtd) -> Result<Tokens, CodegenError> {
+    let names: Vec<_> = dtd.header.iter().map(|statement| statement.name()).collect();
+    let constants = unique(&names, &[], screaming_snake_case)?;
+    let mut tokens = Tokens::new();
+    for (statement, constant) in dtd.header.iter().zip(constants) {
+        let resolved = dtd.resolve_header(statement).ok_or_else(|| match **statement {
+            HeaderStatement::Named { name, value } => CodegenError::UnresolvedHeader {
+                statement: name.to_string(),
+                name: value.to_string(),
+            },
+            _ => unreachable!("only names need resolving"),
+        })?;
+        let value = header_value(resolved).expect("names have been resolved");
+        let value = Raw(&value);
+        let type_ = value.type_();
+        let doc = match statement.name() {
+            "DocType" => "The DocType of documents described by this schema.".to_string(),
+            name => format!("The `{}` declared in the header.", name),
+        };
+        let constant = Ident::new(constant);
+        tokens.append_all(&[quote! {
+            #[doc = #doc]
+            pub const #constant: #type_ = #value;
+        }]);
+    }
+    Ok(tokens)
+}
+
+fn id_constants(dtd: &Dtd) -> Result<Tokens, CodegenError> {
+    let elements = dtd.all_elements();
+    let names = unique_names(&elements, &[], screaming_snake_case)?;
+    let mut tokens = Tokens::new();
+    for (element, name) in elements.into_iter().zip(names) {
+        let name = Ident::new(name);
+        // Ids are checked here, so every constant can be made into an `ebml::Id`.
+        if Id::from_encoded(element.id).is_none() {
+            return Err(CodegenError::InvalidId {
+                element: element.name.to_string(),
+                id: element.id,
+            });
+        }
+        let doc = format!("The encoded id of the `{}` element.", element.name);
+        let id = Hex(element.id);
+        let deprecated = deprecated_attribute(element.deprecation());
+
+        tokens.append_all(&[quote! {
+            #[doc = #doc]
+            #deprecated
+            pub const #name: u32 = #id;
+        }]);
+    }
+    Ok(tokens)
+}
+
+// A `FIELD_NAME_MAP` constant pairing the name of the fields holding every element with the
+// element's name. Raw identifiers are given without their `r#`.
+fn field_name_map(dtd: &Dtd) -> Tokens {
+    let mut pairs: Vec<(String, &str)> = Vec::new();
+    for element in dtd.all_elements() {
+        let field = structs::field_name(element.name);
+        let field = field.trim_start_matches("r#").to_string();
+        if !pairs.iter().any(|&(ref other, name)| *other == field && name == element.name) {
+            pairs.push((field, element.name));
+        }
+    }
+    let (fields, names): (Vec<_>, Vec<_>) = pairs.iter()
+        .map(|&(ref field, name)| (field.as_str(), name))
+        .unzip();
+
+    quote! {
+        #[doc = "The name of the fields holding each element, paired with the element's name in \
+                 the schema."]
+        pub const FIELD_NAME_MAP: &[(&str, &str)] = &[#((#fields, #names)),*];
+    }
+}
+
+// The elements every EBML document may contain without its schema declaring them: those of the
+// EBML header, and the global elements.
+const BUILT_IN_ELEMENTS: &[(&str, u32)] = &[
+    ("EBML", 0x1A45_DFA3),
+    ("EBMLVersion", 0x4286),
+    ("EBMLReadVersion", 0x42F7),
+    ("EBMLMaxIDLength", 0x42F2),
+    ("EBMLMaxSizeLength", 0x42F3),
+    ("DocType", 0x4282),
+    ("DocTypeVersion", 0x4287),
+    ("DocTypeReadVersion", 0x4285),
+    ("Void", 0xEC),
+    ("CRC-32", 0xBF),
+];
+
+// `element_name` and `element_id` functions, mapping between the names and encoded ids of every
+// element in the schema and the built-in ones it doesn't redefine.
+fn name_lookups(dtd: &Dtd) -> Tokens {
+    let mut elements: Vec<(&str, u32)> = dtd.all_elements().into_iter()
+        .map(|element| (element.name, element.id))
+        .collect();
+    for &(name, id) in BUILT_IN_ELEMENTS {
+        if !elements.iter().any(|&(other, other_id)| other == name || other_id == id) {
+            elements.push((name, id));
+        }
+    }
+    let (names, ids): (Vec<_>, Vec<_>) = elements.into_iter()
+        .map(|(name, id)| (name, Hex(id)))
+        .unzip();
+    let byte_names: Vec<_> = names.iter().map(|name| ByteStr(name)).collect();
+    let ids = reusable(&ids);
+
+    quote! {
+        #[doc = "The name of the element with the encoded id `id`, which may be one of those in \
+                 the EBML header or a global one, like `Void`. `None` for any other id."]
+        #[allow(unreachable_patterns)]
+        pub const fn element_name(id: u32) -> ::std::option::Option<&'static str> {
+            match id {
+                #(#ids => ::std::option::Option::Some(#names),)*
+                _ => ::std::option::Option::None,
+            }
+        }
+
+        #[doc = "The encoded id of the element called `name`, like `element_name` in reverse."]
+        #[allow(unreachable_patterns)]
+        pub const fn element_id(name: &str) -> ::std::option::Option<u32> {
+            match name.as_bytes() {
+                #(#byte_names => ::std::option::Option::Some(#ids),)*
+                _ => ::std::option::Option::None,
+            }
+        }
+    }
+}
+
+fn default_constants(dtd: &Dtd) -> Result<Tokens, CodegenError> {
+    let elements = dtd.all_elements();
+    let names = unique_names(&elements, &[], screaming_snake_case)?;
+    let mut tokens = Tokens::new();
+    for (element, name) in elements.into_iter().zip(names) {
+        let value = match element.default.as_deref().and_then(default_value) {
+            Some(value) => value,
+            None => continue,
+        };
+        let value = Raw(&value);
+        let type_ = value.type_();
+        let name = Ident::new(name);
+        let doc = format!("The default of the `{}` element.", element.name);
+        tokens.append_all(&[quote! {
+            #[doc = #doc]
+            pub const #name: #type_ = #value;
+        }]);
+    }
+    Ok(tokens)
+}
+
+fn element_id_enum(dtd: &Dtd, options: &CodegenOptions) -> Result<Tokens, CodegenError> {
+    let elements = dtd.all_elements();
+    let variants: Vec<_> = unique_names(&elements, &["Unknown"], camel_case)?
+        .into_iter()
+        .map(Ident::new)
+        .collect();
+    let constants: Vec<_> = elements.iter()
+        .map(|element| Ident::new(screaming_snake_case(element.name)))
+        .collect();
+    let names: Vec<_> = elements.iter().map(|element| element.name).collect();
+    let docs: Vec<_> = elements.iter()
+        .map(|element| documented(format!("The `{}` element.", element.name), element))
+        .collect();
+    let id_docs: Vec<_> = elements.iter()
+        .map(|element| format!("The encoded id of the `{}` element.", element.name))
+        .collect();
+    let deprecated: Vec<_> = elements.iter()
+        .map(|element| deprecated_attribute(element.deprecation()))
+        .collect();
+    let raw_ids: Vec<_> = elements.iter().map(|element| RawId(element.id)).collect();
+
+    // Ids aren't serializable, so unknown elements are left out.
+    let serde = options.serde_attribute();
+    let unknown_serde = if options.serde() { quote!(#[serde(skip)]) } else { Tokens::new() };
+
+    let (variants, constants) = (reusable(&variants), reusable(&constants));
+
+    Ok(quote! {
+        #[doc = "Identifies an element in this schema."]
+        #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+        #serde
+        pub enum ElementId {
+            #(
+                #[doc = #docs]
+                #deprecated
+                #variants,
+            )*
+            #[doc = "An element which isn't part of this schema."]
+            #unknown_serde
+            Unknown(::ebml::Id),
+        }
+
+        impl ElementId {
+            #[doc = "Finds the element with the given id, or `Unknown` if there isn't one."]
+            pub fn from_id(id: ::ebml::Id) -> ElementId {
+                // Ids can only be compared, so they're looked up in a table built the first time.
+                static ELEMENTS: ::std::sync::OnceLock<
+                    ::std::collections::HashMap<::ebml::Id, ElementId>
+                > = ::std::sync::OnceLock::new();
+                let elements = ELEMENTS.get_or_init(|| {
+                    let mut elements = ::std::collections::HashMap::new();
+                    #(
+                        if let ::std::option::Option::Some(id) =
+                            ::ebml::Id::from_encoded(ids::#constants)
+                        {
+                            elements.entry(id).or_insert(ElementId::#variants);
+                        }
+                    )*
+                    elements
+                });
+                elements.get(&id).copied().unwrap_or(ElementId::Unknown(id))
+            }
+
+            #[doc = "Finds the element whose id is encoded as `encoded`, or `Unknown` if there \
+                     isn't one. Returns `None` if `encoded` isn't a valid id."]
+            #[allow(unreachable_patterns)]
+            pub fn from_encoded(encoded: u32) -> ::std::option::Option<ElementId> {
+                match encoded {
+                    #(ids::#constants => ::std::option::Option::Some(ElementId::#variants),)*
+                    _ => ::ebml::Id::from_encoded(encoded).map(ElementId::Unknown),
+                }
+            }
+
+            #[doc = "The id of this element."]
+            pub fn id(&self) -> Option<::ebml::Id> {
+                match *self {
+                    #(ElementId::#variants => ::ebml::Id::from_encoded(ids::#constants),)*
+                    ElementId::Unknown(id) => Some(id),
+                }
+            }
+
+            #[doc = "The encoded id of this element, or `None` if it's `Unknown`. Unlike `id`, \
+                     this can be used in constant expressions."]
+            pub const fn raw_id(&self)
+                -> ::std::option::Option<::ebml_macros::runtime::RawId>
+            {
+                match *self {
+                    #(ElementId::#variants => ::std::option::Option::Some(raw_ids::#constants),)*
+                    ElementId::Unknown(_) => ::std::option::Option::None,
+                }
+            }
+
+            #[doc = "The name of this element as written in the schema, or `\"Unknown\"`."]
+            pub fn name(&self) -> &'static str {
+                match *self {
+                    #(ElementId::#variants => #names,)*
+                    ElementId::Unknown(_) => "Unknown",
+                }
+            }
+        }
+
+        #[doc = "The encoded ids of every element in this schema, as `runtime::RawId`s, which \
+                 can be matched on and used in constant expressions."]
+        pub mod raw_ids {
+            #(
+                #[doc = #id_docs]
+                pub const #constants: ::ebml_macros::runtime::RawId = #raw_ids;
+            )*
+        }
+    })
+}
+
+// Converts a CamelCase name into SCREAMING_SNAKE_CASE. A word starts at a capital which follows
+// a lowercase letter, or which ends a run of capitals by starting a capitalized word, so "UID"
+// stays whole and "EBMLMaxIDLength" becomes "EBML_MAX_ID_LENGTH". Digits belong to the word
+// before them, and a capital after them only starts a word if it's followed by a lowercase
+// letter, so "CRC32Value" becomes "CRC32_VALUE" and "Video3DMode" becomes "VIDEO3D_MODE".
+// Underscores are kept as they are, leading ones included.
+fn screaming_snake_case(name: &str) -> String {
+    let chars: Vec<char> = name.chars().collect();
+    let mut out = String::with_capacity(name.len() + 4);
+
+    for (idx, &c) in chars.iter().enumerate() {
+        if idx > 0 && c.is_uppercase() {
+            let prev = chars[idx - 1];
+            let next_is_lower = chars.get(idx + 1).map_or(false, |n| n.is_lowercase());
+            let ends_run = (prev.is_uppercase() || prev.is_numeric()) && next_is_lower;
+            if prev.is_lowercase() || ends_run {
+                out.push('_');
+            }
+        }
+        out.extend(c.to_uppercase());
+    }
+    out
+}
+
+// Converts a name into CamelCase, treating runs of capitals as a single word like
+// `screaming_snake_case` does, so "EBMLVersion" becomes "EbmlVersion". The result is always
+// usable as an identifier, so `Self` becomes `Self_`.
+fn camel_case(name: &str) -> String {
+    let mut out = String::with_capacity(name.len());
+    for word in screaming_snake_case(name).split('_').filter(|word| !word.is_empty()) {
+        let mut chars = word.chars();
+        out.extend(chars.next());
+        out.extend(chars.flat_map(char::to_lowercase));
+    }
+    rust_ident(out)
+}
+
+// Converts a name into snake_case, in the same way as `screaming_snake_case`.
+fn snake_case(name: &str) -> String {
+    screaming_snake_case(name).to_lowercase()
+}
+
+// Names which can't be used as identifiers as-is.
+const KEYWORDS: &[&str] = &[
+    "abstract", "alignof", "as", "async", "await", "become", "box", "break", "const", "continue",
+    "do", "dyn", "else", "enum", "extern", "false", "final", "fn", "for", "gen", "if", "impl",
+    "in", "let", "loop", "macro", "match", "mod", "move", "mut", "offsetof", "override", "priv",
+    "proc", "pub", "pure", "ref", "return", "sizeof", "static", "struct", "trait", "true", "try",
+    "type", "typeof", "unsafe", "unsized", "use", "virtual", "where", "while", "yield",
+];
+
+// Keywords which can't even be raw identifiers.
+const RESERVED: &[&str] = &["_", "crate", "self", "Self", "super"];
+
+// Makes `name` usable as an identifier. Keywords become raw identifiers like `r#type`, except for
+// those which can't be, which get an underscore appended instead.
+fn rust_ident(name: String) -> String {
+    if RESERVED.contains(&name.as_str()) {
+        name + "_"
+    } else if KEYWORDS.contains(&name.as_str()) {
+        format!("r#{}", name)
+    } else {
+        name
+    }
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,143 @@
+//! Generates a `Parser`, which reads a document from bytes pushed to it in chunks of any size.
+
+use quote::{Ident, Tokens};
+
+use {Dtd, Type};
+use super::{screaming_snake_case, unique_names, CodegenError, CodegenOptions};
+use super::structs::{containers, fields};
+
+pub fn push_parser(dtd: &Dtd, options: &CodegenOptions) -> Result<Tokens, CodegenError> {
+    if !options.push {
+        return Ok(Tokens::new());
+    }
+
+    let elements = dtd.all_elements();
+    let constants: Vec<_> = unique_names(&elements, &[], screaming_snake_case)?;
+    // The containers each element may be inside, by the names of their constants.
+    let position = |name: &str| elements.iter().position(|element| element.name == name);
+    let mut parents = vec![Vec::new(); elements.len()];
+    for (container, _) in containers(dtd)? {
+        let constant = position(container.name).map(|index| constants[index].as_str());
+        for field in fields(dtd, options, container)? {
+            if let (Some(child), Some(constant)) = (position(field.element.name), constant) {
+                parents[child].push(Ident::new(constant));
+            }
+        }
+    }
+
+    let mut entries = Vec::new();
+    for ((element, constant), parents) in elements.iter().zip(&constants).zip(&parents) {
+        let type_ = dtd.resolve_type(element.type_).ok_or_else(|| CodegenError::UnknownType {
+            element: element.name.to_string(),
+            type_name: element.type_.name().to_string(),
+        })?;
+        let kind = Ident::new(match type_ {
+            Type::Int => "Int",
+            Type::Uint => "Uint",
+            Type::Float => "Float",
+            Type::String => "String",
+            Type::Date => "Date",
+            Type::Binary => "Binary",
+            Type::Container => "Container",
+            Type::Name(_) => unreachable!("resolved types are built in"),
+        });
+        let constant = Ident::new(constant.as_str());
+        let name = element.name;
+        let unknown_size = element.allows_unknown_size();
+        entries.push(quote! {
+            ids::#constant => ::std::option::Option::Some(runtime::PushElement {
+                name: #name,
+                kind: runtime::ElementKind::#kind,
+                unknown_size: #unknown_size,
+                parents: &[#(ids::#parents),*],
+            }),
+        });
+    }
+    let policy = options.unknown_elements();
+
+    Ok(quote! {
+        #[doc = "Something a `Parser` found."]
+        #[derive(Debug, Clone, PartialEq)]
+        pub enum Event {
+            #[doc = "The start of a container, with the size of its body if it's known."]
+            BeginElement(ElementId, ::std::option::Option<u64>),
+            #[doc = "An element which isn't a container, or one this schema doesn't describe if \
+                     unknown elements are collected."]
+            Value(ElementId, ::ebml_macros::runtime::Value),
+            #[doc = "The end of a container."]
+            EndElement(ElementId),
+        }
+
+        #[doc = "Reads a document from bytes which are fed to it in chunks of any size, \
+                 returning what it finds in each. See `ebml_macros::runtime::PushParser` for \
+                 what's checked."]
+        #[derive(Debug)]
+        pub struct Parser {
+            inner: ::ebml_macros::runtime::PushParser,
+        }
+
+        impl Parser {
+            #[doc = "Creates a parser with the default options."]
+            pub fn new() -> Self {
+                Parser::with_options(::std::default::Default::default())
+            }
+
+            #[doc = "Creates a parser which reads with `options`."]
+            pub fn with_options(options: ::ebml_macros::runtime::ReadOptions) -> Self {
+                Parser { inner: ::ebml_macros::runtime::PushParser::new(options, #policy) }
+            }
+
+            #[doc = "Handles the next `bytes` of the document, returning what was found in \
+                     them. Elements may be split across calls anywhere. Once this has failed, \
+                     the parser shouldn't be fed any more."]
+            pub fn feed(&mut self, bytes: &[u8])
+                -> ::std::result::Result<
+                    ::std::vec::Vec<Event>,
+                    ::ebml_macros::runtime::ReadError
+                >
+            {
+                self.inner.feed(bytes, Parser::lookup).map(Parser::events)
+            }
+
+            #[doc = "Ends the document, returning the ends of the containers of unknown size \
+                     which were still open. Fails if it ended in the middle of an element."]
+            pub fn finish(&mut self)
+                -> ::std::result::Result<
+                    ::std::vec::Vec<Event>,
+                    ::ebml_macros::runtime::ReadError
+                >
+            {
+                self.inner.finish().map(Parser::events)
+            }
+
+            fn lookup(id: u32) -> ::std::option::Option<::ebml_macros::runtime::PushElement> {
+                use ::ebml_macros::runtime;
+
+                match id {
+                    #(#entries)*
+                    _ => ::std::option::Option::None,
+                }
+            }
+
+            fn events(events: ::std::vec::Vec<::ebml_macros::runtime::PushEvent>)
+                -> ::std::vec::Vec<Event>
+            {
+                use ::ebml_macros::runtime::PushEvent;
+
+                events.into_iter().map(|event| match event {
+                    PushEvent::Begin(id, size) => {
+                        Event::BeginElement(ElementId::from_id(id), size)
+                    }
+                    PushEvent::Value(id, value) => Event::Value(ElementId::from_id(id), value),
+                    PushEvent::End(id) => Event::EndElement(ElementId::from_id(id)),
+                }).collect()
+            }
+        }
+
+        impl ::std::default::Default for Parser {
+            fn default() -> Self {
+                Parser::new()
+            }
+        }
+    })
+}
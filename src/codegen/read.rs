@@ -0,0 +1,630 @@
+//! Generates `read_from` and `read_with` functions for every container struct, and their async
+//! versions.
+
+use quote::{Ident, ToTokens, Tokens};
+
+use {Cardinality, Dtd, Element, Property, Type};
+use super::{reusable, screaming_snake_case, CodegenError, CodegenOptions};
+use super::structs::{containers, fields, Field};
+
+// Whether a reader reads from a `std::io::Read`, or is async and reads from an `AsyncRead`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Mode {
+    Sync,
+    Async,
+}
+
+impl Mode {
+    // `name`, suffixed with `_async` in async mode.
+    pub fn name(self, name: &str) -> Ident {
+        match self {
+            Mode::Sync => Ident::new(name),
+            Mode::Async => Ident::new(format!("{}_async", name)),
+        }
+    }
+
+    // A call of the runtime function `name`, awaited in async mode.
+    pub fn call(self, name: &str, args: Tokens) -> Tokens {
+        let function = self.name(&format!("runtime::{}", name));
+        let await_ = self.await_();
+        quote!(#function(#args)#await_)
+    }
+
+    // A call of a generated function, which might end up calling itself. Async ones are boxed, so
+    // their futures don't contain themselves.
+    pub fn call_boxed(self, call: Tokens) -> Tokens {
+        match self {
+            Mode::Sync => call,
+            Mode::Async => quote!(::std::boxed::Box::pin(#call).await),
+        }
+    }
+
+    // `call`, which returns a result, if the result of `check` is `Ok(())`.
+    pub fn and_then(self, check: Tokens, call: Tokens) -> Tokens {
+        match self {
+            Mode::Sync => quote!(#check.and_then(|()| #call)),
+            Mode::Async => quote! {
+                match #check {
+                    ::std::result::Result::Ok(()) => #call.await,
+                    ::std::result::Result::Err(err) => ::std::result::Result::Err(err),
+                }
+            },
+        }
+    }
+
+    pub fn await_(self) -> Tokens {
+        match self {
+            Mode::Sync => Tokens::new(),
+            Mode::Async => quote!(.await),
+        }
+    }
+
+    // What a function is declared with.
+    pub fn fn_token(self) -> Tokens {
+        match self {
+            Mode::Sync => quote!(fn),
+            Mode::Async => quote!(async fn),
+        }
+    }
+
+    // The bound on what's read from.
+    pub fn read_bound(self) -> Tokens {
+        match self {
+            Mode::Sync => quote!(::std::io::Read),
+            Mode::Async => quote!(::ebml_macros::runtime::AsyncRead + ::std::marker::Unpin),
+        }
+    }
+
+    // The documentation of a function, given that of the sync version, `name`.
+    pub fn doc(self, name: &str, doc: String) -> String {
+        match self {
+            Mode::Sync => doc,
+            Mode::Async => format!("Like `{}`, reading from an `AsyncRead`.", name),
+        }
+    }
+}
+
+pub fn readers(dtd: &Dtd, options: &CodegenOptions) -> Result<Tokens, CodegenError> {
+    let mut tokens = Tokens::new();
+    for (container, name) in containers(dtd)? {
+        let fields = fields(dtd, options, container)?;
+        let bounds = Tokens::new();
+        let mut functions = reader(dtd, options, container, &name, &fields, &bounds, Mode::Sync);
+        if options.asynchronous() {
+            let functions_async = reader(dtd, options, container, &name, &fields, &bounds,
+                                         Mode::Async);
+            functions.append_all(&[functions_async]);
+        }
+        let read_impl = read_impl(&name, &bounds);
+        tokens.append_all(&[quote! {
+            impl #name {
+                #functions
+            }
+
+            #read_impl
+        }]);
+    }
+    Ok(tokens)
+}
+
+// An implementation of `runtime::EbmlRead` for `name`, calling its sync readers. `bounds` are
+// the predicates of its `where` clause.
+pub fn read_impl(name: &Ident, bounds: &Tokens) -> Tokens {
+    quote! {
+        impl ::ebml_macros::runtime::EbmlRead for #name where #bounds {
+            fn read_with<R: ::std::io::Read>(r: &mut R, size: u64,
+                                             ctx: &mut ::ebml_macros::runtime::ReadContext)
+                -> ::std::result::Result<Self, ::ebml_macros::runtime::ReadError>
+            {
+                #name::read_with(r, size, ctx)
+            }
+        }
+    }
+}
+
+// The readers of `container`, whose struct is `name` with `fields`. `bounds` are the predicates
+// of the `where` clause of the `impl` they're in, which the function nested in them repeats.
+pub fn reader(dtd: &Dtd, options: &CodegenOptions, container: &Element, name: &Ident,
+              fields: &[Field], bounds: &Tokens, mode: Mode)
+    -> Tokens
+{
+    let element_name = container.name;
+    let doc = mode.doc("read_from", format!(
+        "Reads the body of a `{}` element, which is `size` bytes long.",
+        container.name
+    ));
+    let with_doc = mode.doc("read_with", format!(
+        "Reads the body of a `{}` element, which is `size` bytes long, using the options and \
+         collecting warnings in `ctx`.",
+        container.name
+    ));
+    let (read_from, read_with, read_unsized, read_body) = (
+        mode.name("read_from"),
+        mode.name("read_with"),
+        mode.name("read_unsized"),
+        mode.name("read_body"),
+    );
+    let (fn_token, bound, await_) = (mode.fn_token(), mode.read_bound(), mode.await_());
+
+    // Each field is accumulated in a local until the end of the element is reached. They're
+    // prefixed so they can't clash with anything else in scope.
+    let locals: Vec<_> = fields.iter()
+        .map(|field| {
+            Ident::new(format!("field_{}", field.name.as_ref().trim_start_matches("r#")))
+        })
+        .collect();
+    let local_types: Vec<_> = fields.iter().map(|field| {
+        let value = field.boxed_value_type();
+        if field.is_many() {
+            quote!(::std::vec::Vec<#value>)
+        } else {
+            quote!(::std::option::Option<#value>)
+        }
+    }).collect();
+    let inits: Vec<_> = fields.iter().map(|field| if field.is_many() {
+        quote!(::std::vec::Vec::new())
+    } else {
+        quote!(::std::option::Option::None)
+    }).collect();
+    // Children are told apart by matching their encoded ids against the constants in `ids`.
+    let constants: Vec<_> = fields.iter()
+        .map(|field| Ident::new(screaming_snake_case(field.element.name)))
+        .collect();
+    // Elements which may only occur once are checked every time they're found, in case it's the
+    // second time.
+    // Children of ordered containers are also checked against the latest child seen so far.
+    let store = |index: usize, field: &Field, local: &Ident, value: Tokens| {
+        let child_name = field.element.name;
+        let store = if field.is_many() {
+            quote!(#local.push(#value);)
+        } else {
+            let cardinality = field.element.cardinality();
+            quote! {
+                runtime::check_count(ctx, #child_name, &#cardinality, #local.iter().count() + 1)?;
+                #local = ::std::option::Option::Some(#value);
+            }
+        };
+        if container.is_ordered() {
+            quote!({
+                runtime::check_order(ctx, #child_name, &mut latest, #index)?;
+                #store
+            })
+        } else {
+            quote!({ #store })
+        }
+    };
+    let stores: Vec<_> = fields.iter().zip(&locals).enumerate()
+        .map(|(index, (field, local))| {
+            store(index, field, local, read_value(dtd, field, mode))
+        })
+        .collect();
+    // Mandatory elements without defaults are checked once everything has been read.
+    let checks: Vec<_> = fields.iter().zip(&locals).filter_map(|(field, local)| {
+        let child_name = field.element.name;
+        let card = field.element.cardinality();
+        if card.allows(0) || field.raw_default().is_some() {
+            return None;
+        }
+        Some(quote! {
+            runtime::check_count(ctx, #child_name, &#card, #local.iter().count())?;
+        })
+    }).collect();
+    let names: Vec<_> = fields.iter().map(|field| &field.name).collect();
+    // Absent mandatory elements take their default, if they have one. Those without one have
+    // already been checked for.
+    let finishes: Vec<_> = fields.iter().zip(&locals).map(|(field, local)| {
+        let child_name = field.element.name;
+        match (field.element.cardinality(), field.raw_default()) {
+            (Cardinality::ExactlyOne, Some(raw)) => quote! {
+                match #local {
+                    ::std::option::Option::Some(value) => value,
+                    ::std::option::Option::None => {
+                        runtime::convert(#raw).map_err(|err| err.within(#child_name))?
+                    }
+                }
+            },
+            (Cardinality::ExactlyOne, None) => {
+                quote!(#local.expect("mandatory elements have been checked"))
+            }
+            (Cardinality::OneOrMany, Some(raw)) => quote! {
+                if #local.is_empty() {
+                    vec![runtime::convert(#raw).map_err(|err| err.within(#child_name))?]
+                } else {
+                    #local
+                }
+            },
+            _ => quote!(#local),
+        }
+    }).collect();
+
+    let order_state = if container.is_ordered() {
+        quote!(let mut latest = 0;)
+    } else {
+        Tokens::new()
+    };
+
+    // Unknown elements are handled by the policy, and kept if the struct has somewhere to keep
+    // them.
+    let policy = options.unknown_elements();
+    let read_unknown = mode.call("read_unknown", quote!(r, ctx, #policy, id, child_size));
+    let (unknown_state, unknown, unknown_finish) = if options.collects_unknown() {
+        (
+            quote!(let mut unknown_children = ::std::vec::Vec::new();),
+            quote! {
+                let id = runtime::decode_id(encoded)?;
+                if let ::std::option::Option::Some(body) = #read_unknown? {
+                    unknown_children.push((id, body));
+                }
+            },
+            quote!(unknown_children: unknown_children,),
+        )
+    } else {
+        (
+            Tokens::new(),
+            quote! {
+                let id = runtime::decode_id(encoded)?;
+                #read_unknown?;
+            },
+            Tokens::new(),
+        )
+    };
+
+    // Only elements which aren't children of this one may have an unknown size, and only if it
+    // allows that. They extend to the end of this element. Children which allow an unknown size
+    // themselves end wherever something which can't be inside them starts, and hand back the
+    // header of that element to be read next.
+    let (open_constants, open_reads): (Vec<_>, Vec<_>) = fields.iter().zip(&locals).enumerate()
+        .filter(|&(_, (field, _))| {
+            field.element.type_ == Type::Container && field.element.allows_unknown_size()
+        })
+        .map(|(index, (field, local))| {
+            let value_type = &field.value_type;
+            let value = if field.boxed {
+                quote!(::std::boxed::Box::new(value))
+            } else {
+                quote!(value)
+            };
+            let store = store(index, field, local, value);
+            // The body is read directly, so the header which ended it stays encoded.
+            let read_body = mode.call_boxed(quote!(<#value_type>::#read_body(r, limit, ctx, true)));
+            let read = quote!({
+                let limit = runtime::unknown_size(true, remaining, header_size)?;
+                let (value, left, next) = #read_body?;
+                remaining = left;
+                pending = next;
+                #store
+                continue;
+            });
+            (Ident::new(screaming_snake_case(field.element.name)), read)
+        })
+        .unzip();
+    let has_open_children = !open_reads.is_empty();
+    let unknown_size = if container.allows_unknown_size() {
+        let constants = reusable(&constants);
+        quote!({
+            let is_child = match encoded {
+                #(ids::#constants => true,)*
+                _ => false,
+            };
+            runtime::unknown_size(!is_child, remaining, header_size)?
+        })
+    } else {
+        quote!(runtime::unknown_size(false, remaining, header_size)?)
+    };
+    let unknown_size = if !has_open_children {
+        unknown_size
+    } else {
+        quote! {
+            match encoded {
+                #(ids::#open_constants => #open_reads)*
+                _ => #unknown_size,
+            }
+        }
+    };
+
+    // Containers which may have an unknown size read until they find an element which can't be
+    // inside them, or the end of the input. That includes elements the schema doesn't describe.
+    let open = container.allows_unknown_size();
+    let read_child_header = mode.call("read_encoded_child_header", quote!(r));
+    let mut next_header = quote!(#read_child_header?);
+    let mut open_state = Tokens::new();
+    let mut open_end = Tokens::new();
+    if open {
+        let read_child_header_or_eof = mode.call("read_encoded_child_header_or_eof", quote!(r));
+        next_header = quote! {
+            if open_ended {
+                match #read_child_header_or_eof? {
+                    ::std::option::Option::Some(header) => header,
+                    ::std::option::Option::None => break,
+                }
+            } else {
+                #read_child_header?
+            }
+        };
+        open_state = quote!(let mut next = ::std::option::Option::None;);
+        let constants = reusable(&constants);
+        open_end = quote! {
+            let ends = match encoded {
+                #(ids::#constants => false,)*
+                _ => encoded != runtime::VOID && encoded != runtime::CRC_32,
+            };
+            if open_ended && ends {
+                next = ::std::option::Option::Some((encoded, child_size, header_size));
+                break;
+            }
+        };
+    }
+    if has_open_children {
+        next_header = quote! {
+            match pending.take() {
+                ::std::option::Option::Some(header) => header,
+                ::std::option::Option::None => #next_header,
+            }
+        };
+        open_state.append_all(&[quote! {
+            let mut pending: ::std::option::Option<(u32, ::std::option::Option<u64>, u64)> =
+                ::std::option::Option::None;
+        }]);
+    }
+
+    let header = quote!((::ebml::Id, ::std::option::Option<u64>, u64));
+    let (params, body_type, crc_call, value, finish) = if open {
+        (
+            quote!(open_ended: bool),
+            quote!((#name, u64, ::std::option::Option<(u32, ::std::option::Option<u64>, u64)>)),
+            quote! {
+                !open_ended && encoded == runtime::CRC_32 &&
+                    remaining + header_size + child_size == size
+            },
+            mode.call_boxed(quote!(body(&mut &rest[..], remaining, ctx, false))),
+            quote!((value, remaining, next)),
+        )
+    } else {
+        (
+            Tokens::new(),
+            quote!(#name),
+            quote!(encoded == runtime::CRC_32 && remaining + header_size + child_size == size),
+            mode.call_boxed(quote!(body(&mut &rest[..], remaining, ctx))),
+            quote!(value),
+        )
+    };
+
+    let locals = reusable(&locals);
+
+    let skip = mode.call("skip", quote!(r, child_size));
+    let read_crc32 = mode.call("read_crc32", quote!(r, ctx, child_size, remaining));
+    let body = quote! {
+        #fn_token body<R: #bound>(r: &mut R, size: u64,
+                                  ctx: &mut ::ebml_macros::runtime::ReadContext, #params)
+            -> ::std::result::Result<#body_type, ::ebml_macros::runtime::ReadError>
+            where #bounds
+        {
+            use ::ebml_macros::runtime;
+
+            #(let mut #locals: #local_types = #inits;)*
+
+            #order_state
+            #unknown_state
+            #open_state
+            let mut children = 0;
+            let mut remaining = size;
+            while remaining > 0 {
+                let (encoded, child_size, header_size) = #next_header;
+                #open_end
+                let child_size = match child_size {
+                    ::std::option::Option::Some(child_size) => child_size,
+                    ::std::option::Option::None => #unknown_size,
+                };
+                runtime::check_child(ctx, ::std::option::Option::Some(&mut children), child_size)?;
+                remaining = remaining.checked_sub(header_size)
+                    .and_then(|remaining| remaining.checked_sub(child_size))
+                    .ok_or_else(|| runtime::ReadError::new(
+                        runtime::ReadErrorKind::Overrun
+                    ))?;
+
+                // Void and CRC-32 are only handled here if the schema doesn't give them a
+                // field of their own. A CRC-32 only counts as the first child, and the rest of
+                // the body is read from what it covers.
+                match encoded {
+                    #(ids::#constants => #stores,)*
+                    _ if encoded == runtime::VOID => #skip?,
+                    _ if #crc_call => {
+                        let rest = #read_crc32?;
+                        if let ::std::option::Option::Some(rest) = rest {
+                            return #value;
+                        }
+                    }
+                    _ if encoded == runtime::CRC_32 => #skip?,
+                    _ => { #unknown }
+                }
+            }
+
+            #(#checks)*
+            let value = #name {
+                #(#names: #finishes,)*
+                #unknown_finish
+            };
+            Ok(#finish)
+        }
+    };
+
+    let functions = if open {
+        let unsized_doc = mode.doc("read_unsized", format!(
+            "Reads the body of a `{}` element whose size is unknown, using the options and \
+             collecting warnings in `ctx`. It ends at the first element which can't be inside \
+             it, after `limit` bytes, or at the end of the input. Returns it along with the \
+             length of its body, and the header of the element which ended it if there was one, \
+             which has already been read.",
+            container.name
+        ));
+        let result = mode.and_then(
+            quote!(::ebml_macros::runtime::check_limits(ctx, known)),
+            quote!(body(r, size, ctx, open_ended)),
+        );
+        quote! {
+            #[doc = #with_doc]
+            pub #fn_token #read_with<R: #bound>(r: &mut R, size: u64,
+                                                ctx: &mut ::ebml_macros::runtime::ReadContext)
+                -> ::std::result::Result<Self, ::ebml_macros::runtime::ReadError>
+            {
+                Self::#read_body(r, size, ctx, false)#await_.map(|(value, _, _)| value)
+            }
+
+            #[doc = #unsized_doc]
+            pub #fn_token #read_unsized<R: #bound>(r: &mut R, limit: u64,
+                                                   ctx: &mut ::ebml_macros::runtime::ReadContext)
+                -> ::std::result::Result<
+                    (Self, u64, ::std::option::Option<#header>),
+                    ::ebml_macros::runtime::ReadError
+                >
+            {
+                let (value, left, next) = Self::#read_body(r, limit, ctx, true)#await_?;
+                let next = match next {
+                    ::std::option::Option::Some((encoded, size, len)) => {
+                        let id = ::ebml_macros::runtime::decode_id(encoded)?;
+                        ::std::option::Option::Some((id, size, len))
+                    }
+                    ::std::option::Option::None => ::std::option::Option::None,
+                };
+                Ok((value, limit - left, next))
+            }
+
+            // Public so parents in other modules can read it with an unknown size.
+            #[doc(hidden)]
+            pub #fn_token #read_body<R: #bound>(r: &mut R, size: u64,
+                                            ctx: &mut ::ebml_macros::runtime::ReadContext,
+                                            open_ended: bool)
+                -> ::std::result::Result<#body_type, ::ebml_macros::runtime::ReadError>
+            {
+                #body
+
+                ctx.enter(#element_name);
+                let known = if open_ended {
+                    ::std::option::Option::None
+                } else {
+                    ::std::option::Option::Some(size)
+                };
+                let result = #result;
+                ctx.leave();
+                result.map_err(|err| err.within(#element_name))
+            }
+        }
+    } else {
+        let result = mode.and_then(
+            quote!(::ebml_macros::runtime::check_limits(ctx, known)),
+            quote!(body(r, size, ctx)),
+        );
+        quote! {
+            #[doc = #with_doc]
+            pub #fn_token #read_with<R: #bound>(r: &mut R, size: u64,
+                                                ctx: &mut ::ebml_macros::runtime::ReadContext)
+                -> ::std::result::Result<Self, ::ebml_macros::runtime::ReadError>
+            {
+                #body
+
+                ctx.enter(#element_name);
+                let known = ::std::option::Option::Some(size);
+                let result = #result;
+                ctx.leave();
+                result.map_err(|err| err.within(#element_name))
+            }
+        }
+    };
+
+    quote! {
+        #[doc = #doc]
+        pub #fn_token #read_from<R: #bound>(r: &mut R, size: u64)
+            -> ::std::result::Result<Self, ::ebml_macros::runtime::ReadError>
+        {
+            Self::#read_with(r, size, &mut ::ebml_macros::runtime::ReadContext::default())#await_
+        }
+
+        #functions
+    }
+}
+
+// An expression reading the value of `field`, whose size is in `child_size`, after checking the
+// size against the element's `size:`.
+pub fn read_value(dtd: &Dtd, field: &Field, mode: Mode) -> Tokens {
+    check_size(field, read_unchecked_value(dtd, field, mode))
+}
+
+// `value`, after checking `child_size` against the element's `size:`.
+pub fn check_size(field: &Field, value: Tokens) -> Tokens {
+    let child_name = field.element.name;
+    match field.element.size {
+        Some(ref sizes) => {
+            quote!({
+                const SIZES: ::ebml_macros::SizeList = #sizes;
+                runtime::check_size(child_size, &SIZES).map_err(|err| err.within(#child_name))?;
+                #value
+            })
+        }
+        None => value,
+    }
+}
+
+fn read_unchecked_value(dtd: &Dtd, field: &Field, mode: Mode) -> Tokens {
+    let child_name = field.element.name;
+    let read = match dtd.resolve_type(field.element.type_) {
+        Some(Type::Int) => "read_int",
+        Some(Type::Uint) => "read_uint",
+        Some(Type::Float) => "read_float",
+        Some(Type::String) => "read_string",
+        Some(Type::Binary) => "read_binary",
+        Some(Type::Date) => "read_date",
+        _ => {
+            let value_type = &field.value_type;
+            let read_with = mode.name("read_with");
+            let value = mode.call_boxed(quote!(<#value_type>::#read_with(r, child_size, ctx)));
+            let value = quote!(#value?);
+            return if field.boxed {
+                quote!(::std::boxed::Box::new(#value))
+            } else {
+                value
+            };
+        }
+    };
+    let read = mode.call(read, quote!(r, child_size));
+
+    match check_range(dtd, field) {
+        Some(check) => quote! {
+            #read
+                .and_then(|value| #check)
+                .and_then(runtime::convert)
+                .map_err(|err| err.within(#child_name))?
+        },
+        None => quote! {
+            #read
+                .and_then(runtime::convert)
+                .map_err(|err| err.within(#child_name))?
+        },
+    }
+}
+
+// An expression checking that the raw `value` of `field` is in its range, if it has one.
+pub fn check_range(dtd: &Dtd, field: &Field) -> Option<Tokens> {
+    let child_name = field.element.name;
+    let (kind, items) = range_items(dtd.range_of(field.element)?)?;
+    let check = Ident::new(format!("runtime::check_{}range", kind));
+    Some(quote!(#check(ctx, #child_name, value, &[#(#items),*])))
+}
+
+// The items of a range, and which kind of range it is: "date_", "string_" or "binary_" for those
+// types, or "" for numbers. The kind prefixes the names of the runtime functions checking ranges.
+pub fn range_items(range: Property) -> Option<(&'static str, Vec<Tokens>)> {
+    fn items<T: ToTokens>(range: &[T]) -> Vec<Tokens> {
+        range.iter().map(|item| quote!(#item)).collect()
+    }
+
+    let (kind, items) = match range {
+        Property::IntRange(range) => ("", items(&range)),
+        Property::UintRange(range) => ("", items(&range)),
+        Property::FloatRange(range) => ("", items(&range)),
+        Property::DateRange(range) => ("date_", items(&range)),
+        Property::StringRange(range) => ("string_", items(&range)),
+        Property::BinaryRange(range) => ("binary_", items(&range)),
+        _ => return None,
+    };
+    Some((kind, items))
+}
@@ -0,0 +1,106 @@
+//! Describes the schema itself, for tools which work with any generated module.
+
+use std::borrow::Cow;
+
+use quote::Tokens;
+
+use {Dtd, Element, Spanned, Type};
+use runtime::{ElementDescriptor, ElementType, SchemaDescriptor};
+use super::{CodegenError, Hex};
+use super::tokens::{default_value, level_range, option};
+
+/// Describes `dtd` as the `schema` function of the code generated for it does.
+pub fn schema_descriptor<'d>(dtd: &'d Dtd) -> Result<SchemaDescriptor<'d>, CodegenError> {
+    fn walk<'d>(dtd: &'d Dtd, elements: &'d [Spanned<Element>], parent: Option<&'d str>,
+                out: &mut Vec<ElementDescriptor<'d>>)
+        -> Result<(), CodegenError>
+    {
+        for element in elements {
+            out.push(element_descriptor(dtd, element, parent)?);
+            walk(dtd, &element.children, Some(element.name), out)?;
+        }
+        Ok(())
+    }
+
+    let mut elements = Vec::new();
+    walk(dtd, &dtd.elements, None, &mut elements)?;
+    Ok(SchemaDescriptor { doc_type: dtd.doc_type(), elements: Cow::Owned(elements) })
+}
+
+fn element_descriptor<'d>(dtd: &'d Dtd, element: &'d Element, parent: Option<&'d str>)
+    -> Result<ElementDescriptor<'d>, CodegenError>
+{
+    let type_ = match dtd.resolve_type(element.type_) {
+        Some(Type::Int) => ElementType::Int,
+        Some(Type::Uint) => ElementType::Uint,
+        Some(Type::Float) => ElementType::Float,
+        Some(Type::String) => ElementType::String,
+        Some(Type::Date) => ElementType::Date,
+        Some(Type::Binary) => ElementType::Binary,
+        Some(Type::Container) => ElementType::Container,
+        Some(Type::Name(_)) | None => return Err(CodegenError::UnknownType {
+            element: element.name.to_string(),
+            type_name: element.type_.name().to_string(),
+        }),
+    };
+    let level = element.level.as_ref().map(level_range);
+    let parents = match (parent, element.parent.as_ref()) {
+        (Some(parent), _) => Cow::Owned(vec![parent]),
+        (None, Some(parents)) => Cow::Borrowed(&parents[..]),
+        (None, None) => Cow::Borrowed(&[][..]),
+    };
+    let default = element.default.as_deref().and_then(default_value);
+
+    Ok(ElementDescriptor {
+        name: element.name,
+        id: element.id,
+        type_,
+        cardinality: element.cardinality(),
+        level,
+        parents,
+        default,
+    })
+}
+
+// A `schema` function returning the descriptor from a static, so nothing is parsed at runtime.
+pub fn schema_fn(dtd: &Dtd) -> Result<Tokens, CodegenError> {
+    let schema = schema_descriptor(dtd)?;
+    let doc_type = option(schema.doc_type);
+    let elements: Vec<_> = schema.elements.iter().map(element_tokens).collect();
+
+    Ok(quote! {
+        #[doc = "Describes this schema's elements, for tools which work with any schema."]
+        pub fn schema() -> &'static ::ebml_macros::runtime::SchemaDescriptor<'static> {
+            use ::ebml_macros::runtime::{ElementDescriptor, SchemaDescriptor};
+            use ::std::borrow::Cow;
+
+            static SCHEMA: SchemaDescriptor<'static> = SchemaDescriptor {
+                doc_type: #doc_type,
+                elements: Cow::Borrowed(&[#(#elements),*]),
+            };
+            &SCHEMA
+        }
+    })
+}
+
+fn element_tokens(element: &ElementDescriptor) -> Tokens {
+    let name = element.name;
+    let id = Hex(element.id);
+    let type_ = element.type_;
+    let cardinality = &element.cardinality;
+    let level = option(element.level);
+    let parents = &element.parents[..];
+    let default = option(element.default.as_ref());
+
+    quote! {
+        ElementDescriptor {
+            name: #name,
+            id: #id,
+            type_: #type_,
+            cardinality: #cardinality,
+            level: #level,
+            parents: Cow::Borrowed(&[#(#parents),*]),
+            default: #default,
+        }
+    }
+}
@@ -0,0 +1,280 @@
+//! Generates a `Handler` trait and `stream` functions, which read a document incrementally
+//! instead of building a tree of structs for it, and their async versions.
+
+use std::collections::HashSet;
+use std::ops::Deref;
+
+use quote::{Ident, Tokens};
+
+use {Dtd, Element, Type};
+use super::{camel_case, screaming_snake_case, snake_case, unique_names, CodegenError,
+            CodegenOptions};
+use super::read::{read_value, Mode};
+use super::structs::fields;
+
+// Everything the streaming reader can come across: the containers which are streamed, and the
+// elements which are handed over whole, with the types they're handed over as.
+struct Visits<'d, 'a: 'd> {
+    streamed: Vec<&'d Element<'a>>,
+    whole: Vec<(&'d Element<'a>, Tokens)>,
+}
+
+pub fn stream_reader(dtd: &Dtd, options: &CodegenOptions) -> Result<Tokens, CodegenError> {
+    if !options.streaming {
+        return Ok(Tokens::new());
+    }
+
+    let roots: Vec<_> = dtd.elements.iter()
+        .map(Deref::deref)
+        .filter(|element| element.parent.is_none() && element.type_ == Type::Container)
+        .collect();
+    let mut visits = Visits { streamed: Vec::new(), whole: Vec::new() };
+    let mut seen = HashSet::new();
+    for root in &roots {
+        visit(dtd, options, root, container_type(root), &mut visits, &mut seen)?;
+    }
+
+    let mut callbacks = Tokens::new();
+    for element in &visits.streamed {
+        let enter = Ident::new(format!("on_enter_{}", snake_case(element.name)));
+        let leave = Ident::new(format!("on_leave_{}", snake_case(element.name)));
+        let enter_doc = format!("Called when a `{}` element is entered.", element.name);
+        let leave_doc = format!(
+            "Called when a `{}` element has been left, after all of its children.",
+            element.name
+        );
+        callbacks.append_all(&[quote! {
+            #[doc = #enter_doc]
+            fn #enter(&mut self) {}
+
+            #[doc = #leave_doc]
+            fn #leave(&mut self) {}
+        }]);
+    }
+    let whole: Vec<_> = visits.whole.iter().map(|&(element, _)| element).collect();
+    let names = unique_names(&whole, &[], |name| format!("on_{}", snake_case(name)))?;
+    for (&(element, ref value_type), name) in visits.whole.iter().zip(&names) {
+        let name = Ident::new(name.as_str());
+        let doc = format!("Called with every `{}` element.", element.name);
+        callbacks.append_all(&[quote! {
+            #[doc = #doc]
+            fn #name(&mut self, _value: #value_type) {}
+        }]);
+    }
+
+    let mut tokens = quote! {
+        #[doc = "Callbacks for `stream`. Every method does nothing by default."]
+        pub trait Handler {
+            #callbacks
+        }
+    };
+    tokens.append_all(&[stream_functions(dtd, options, &roots, &visits, Mode::Sync)?]);
+    if options.asynchronous() {
+        tokens.append_all(&[stream_functions(dtd, options, &roots, &visits, Mode::Async)?]);
+    }
+    Ok(tokens)
+}
+
+// The `stream` functions, and one for each streamed container.
+fn stream_functions(dtd: &Dtd, options: &CodegenOptions, roots: &[&Element], visits: &Visits,
+                    mode: Mode)
+    -> Result<Tokens, CodegenError>
+{
+    let mut functions = Tokens::new();
+    for element in &visits.streamed {
+        functions.append_all(&[stream_container(dtd, options, element, mode)?]);
+    }
+
+    let root_variants: Vec<_> = roots.iter()
+        .map(|root| Ident::new(camel_case(root.name)))
+        .collect();
+    let root_visits: Vec<_> = roots.iter()
+        .map(|root| visit_child(options, root, &quote!(size), mode))
+        .collect();
+
+    let stream_doc = mode.doc("stream", "Reads a document from `r` element by element, passing \
+                                         what's read to `h`. Streamed containers are never held \
+                                         in memory whole.".to_string());
+    let with_doc = mode.doc(
+        "stream_with",
+        "Like `stream`, using the options and collecting warnings in `ctx`.".to_string(),
+    );
+    let (stream, stream_with) = (mode.name("stream"), mode.name("stream_with"));
+    let (fn_token, bound, await_) = (mode.fn_token(), mode.read_bound(), mode.await_());
+    let read_header_or_eof = mode.call("read_header_or_eof", quote!(r));
+    let skip = mode.call("skip", quote!(r, size));
+    Ok(quote! {
+        #[doc = #stream_doc]
+        pub #fn_token #stream<R: #bound, H: Handler>(r: &mut R, h: &mut H)
+            -> ::std::result::Result<(), ::ebml_macros::runtime::ReadError>
+        {
+            #stream_with(r, h, &mut ::ebml_macros::runtime::ReadContext::default())#await_
+        }
+
+        #[doc = #with_doc]
+        pub #fn_token #stream_with<R: #bound, H: Handler>(
+            r: &mut R,
+            h: &mut H,
+            ctx: &mut ::ebml_macros::runtime::ReadContext,
+        )
+            -> ::std::result::Result<(), ::ebml_macros::runtime::ReadError>
+        {
+            use ::ebml_macros::runtime;
+
+            while let ::std::option::Option::Some((id, size, _)) = #read_header_or_eof? {
+                match ElementId::from_id(id) {
+                    #(ElementId::#root_variants => #root_visits,)*
+                    _ => #skip?,
+                }
+            }
+            Ok(())
+        }
+
+        #functions
+    })
+}
+
+// The type a container is handed over as, when it isn't streamed.
+fn container_type(container: &Element) -> Tokens {
+    let name = Ident::new(camel_case(container.name));
+    quote!(#name)
+}
+
+fn is_streamed(options: &CodegenOptions, element: &Element) -> bool {
+    element.type_ == Type::Container && options.streamed.iter().any(|name| name == element.name)
+}
+
+// Records `element`, and everything inside it if it's streamed.
+fn visit<'d, 'a>(dtd: &'d Dtd<'a>, options: &CodegenOptions, element: &'d Element<'a>,
+                 value_type: Tokens, visits: &mut Visits<'d, 'a>,
+                 seen: &mut HashSet<&'a str>)
+    -> Result<(), CodegenError>
+{
+    if !seen.insert(element.name) {
+        return Ok(());
+    }
+    if !is_streamed(options, element) {
+        visits.whole.push((element, value_type));
+        return Ok(());
+    }
+
+    visits.streamed.push(element);
+    for field in fields(dtd, options, element)? {
+        let value_type = if field.element.type_ == Type::Container {
+            container_type(field.element)
+        } else {
+            field.value_type.clone()
+        };
+        visit(dtd, options, field.element, value_type, visits, seen)?;
+    }
+    Ok(())
+}
+
+// An expression handling an `element` whose body is `size` bytes long, which is either streamed
+// or a container handed over whole.
+fn visit_child(options: &CodegenOptions, element: &Element, size: &Tokens, mode: Mode)
+    -> Tokens
+{
+    let snake = snake_case(element.name);
+    if is_streamed(options, element) {
+        let stream = mode.name(&format!("stream_{}", snake));
+        let call = mode.call_boxed(quote!(#stream(r, #size, h, ctx)));
+        quote!(#call?)
+    } else {
+        let callback = Ident::new(format!("on_{}", snake));
+        let value_type = container_type(element);
+        let read_with = mode.name("read_with");
+        let value = mode.call_boxed(quote!(#value_type::#read_with(r, #size, ctx)));
+        quote!(h.#callback(#value?))
+    }
+}
+
+fn stream_container(dtd: &Dtd, options: &CodegenOptions, container: &Element, mode: Mode)
+    -> Result<Tokens, CodegenError>
+{
+    let fields = fields(dtd, options, container)?;
+    let element_name = container.name;
+    let snake = snake_case(container.name);
+    let function = mode.name(&format!("stream_{}", snake));
+    let enter = Ident::new(format!("on_enter_{}", snake));
+    let leave = Ident::new(format!("on_leave_{}", snake));
+    let policy = options.unknown_elements();
+
+    let constants: Vec<_> = fields.iter()
+        .map(|field| Ident::new(screaming_snake_case(field.element.name)))
+        .collect();
+    let visits: Vec<_> = fields.iter().map(|field| {
+        if field.element.type_ == Type::Container {
+            visit_child(options, field.element, &quote!(child_size), mode)
+        } else {
+            let callback = Ident::new(format!("on_{}", snake_case(field.element.name)));
+            let value = read_value(dtd, field, mode);
+            quote!(h.#callback(#value))
+        }
+    }).collect();
+    let (fn_token, bound) = (mode.fn_token(), mode.read_bound());
+    let read_header = mode.call("read_encoded_child_header", quote!(r));
+    let skip = mode.call("skip", quote!(r, child_size));
+    let read_unknown = mode.call("read_unknown", quote!(r, ctx, #policy, id, child_size));
+    let result = mode.and_then(
+        quote!(::ebml_macros::runtime::check_limits(ctx, known)),
+        quote!(body(r, size, h, ctx)),
+    );
+
+    // Streamed containers are read as they arrive, so their children's cardinality and order
+    // aren't checked, and a CRC-32 among them is skipped rather than verified.
+    Ok(quote! {
+        #fn_token #function<R: #bound, H: Handler>(
+            r: &mut R,
+            size: u64,
+            h: &mut H,
+            ctx: &mut ::ebml_macros::runtime::ReadContext,
+        )
+            -> ::std::result::Result<(), ::ebml_macros::runtime::ReadError>
+        {
+            #fn_token body<R: #bound, H: Handler>(
+                r: &mut R,
+                size: u64,
+                h: &mut H,
+                ctx: &mut ::ebml_macros::runtime::ReadContext,
+            )
+                -> ::std::result::Result<(), ::ebml_macros::runtime::ReadError>
+            {
+                use ::ebml_macros::runtime;
+
+                let mut remaining = size;
+                while remaining > 0 {
+                    let (encoded, child_size, header_size) = #read_header?;
+                    let child_size = child_size.ok_or_else(|| runtime::ReadError::new(
+                        runtime::ReadErrorKind::InvalidSize
+                    ))?;
+                    runtime::check_child(ctx, ::std::option::Option::None, child_size)?;
+                    remaining = remaining.checked_sub(header_size)
+                        .and_then(|remaining| remaining.checked_sub(child_size))
+                        .ok_or_else(|| runtime::ReadError::new(
+                            runtime::ReadErrorKind::Overrun
+                        ))?;
+
+                    match encoded {
+                        #(ids::#constants => #visits,)*
+                        _ if encoded == runtime::VOID || encoded == runtime::CRC_32 => #skip?,
+                        _ => {
+                            let id = runtime::decode_id(encoded)?;
+                            #read_unknown?;
+                        }
+                    }
+                }
+                Ok(())
+            }
+
+            h.#enter();
+            ctx.enter(#element_name);
+            let known = ::std::option::Option::Some(size);
+            let result = #result;
+            ctx.leave();
+            result.map_err(|err| err.within(#element_name))?;
+            h.#leave();
+            Ok(())
+        }
+    })
+}
@@ -0,0 +1,226 @@
+//! Generates a struct for every container element.
+
+use std::collections::HashSet;
+
+use quote::{Ident, Tokens};
+
+use {Cardinality, Dtd, Element, Type};
+use super::{camel_case, deprecated_attribute, documented, rust_ident, snake_case, unique_names,
+            CodegenError, CodegenOptions};
+use super::enums::enums;
+use super::tokens::{default_value, Raw};
+use super::types::newtypes;
+
+pub fn container_structs(dtd: &Dtd, options: &CodegenOptions) -> Result<Tokens, CodegenError> {
+    let mut tokens = Tokens::new();
+    for (container, name) in containers(dtd)? {
+        tokens.append_all(&[container_struct(dtd, options, container, name)?]);
+    }
+    Ok(tokens)
+}
+
+// Every container element, with the name of its struct.
+pub fn containers<'d, 'a>(dtd: &'d Dtd<'a>) -> Result<Vec<(&'d Element<'a>, Ident)>, CodegenError> {
+    let containers: Vec<_> = dtd.all_elements()
+        .into_iter()
+        .filter(|element| element.type_ == Type::Container)
+        .collect();
+    let names = unique_names(&containers, &["ElementId"], camel_case)?;
+    Ok(containers.into_iter().zip(names.into_iter().map(Ident::new)).collect())
+}
+
+// A field of a container struct, holding one kind of child element.
+pub struct Field<'d, 'a: 'd> {
+    pub element: &'d Element<'a>,
+    pub name: Ident,
+    // The type of a single value, before cardinality is taken into account.
+    pub value_type: Tokens,
+    // Whether each value is boxed, to keep recursive containers finite.
+    pub boxed: bool,
+}
+
+impl<'d, 'a> Field<'d, 'a> {
+    // The type of a single value, boxed if necessary.
+    pub fn boxed_value_type(&self) -> Tokens {
+        let value = &self.value_type;
+        if self.boxed {
+            quote!(::std::boxed::Box<#value>)
+        } else {
+            quote!(#value)
+        }
+    }
+
+    // The full type of the field.
+    pub fn type_(&self) -> Tokens {
+        let value = self.boxed_value_type();
+
+        match self.element.cardinality() {
+            Cardinality::ExactlyOne => value,
+            Cardinality::ZeroOrOne => quote!(::std::option::Option<#value>),
+            Cardinality::ZeroOrMany | Cardinality::OneOrMany => quote!(::std::vec::Vec<#value>),
+        }
+    }
+
+    // True if the field holds any number of values.
+    pub fn is_many(&self) -> bool {
+        match self.element.cardinality() {
+            Cardinality::ZeroOrMany | Cardinality::OneOrMany => true,
+            Cardinality::ZeroOrOne | Cardinality::ExactlyOne => false,
+        }
+    }
+
+    // An expression giving the raw value of the element's default, if it has one.
+    pub fn raw_default(&self) -> Option<Tokens> {
+        let default = self.element.default.as_deref().and_then(default_value)?;
+        Some(Raw(&default).owned())
+    }
+}
+
+// The fields of the struct for `container`.
+pub fn fields<'d, 'a>(dtd: &'d Dtd<'a>, options: &CodegenOptions, container: &'d Element<'a>)
+    -> Result<Vec<Field<'d, 'a>>, CodegenError>
+{
+    let children = dtd.children_of(container);
+    let reserved: &[&str] = if options.collects_unknown() { &["unknown_children"] } else { &[] };
+    let names = unique_names(&children, reserved, field_name)?;
+    let newtypes = newtypes(dtd)?;
+    let enums = enums(dtd, options)?;
+
+    children.into_iter().zip(names).map(|(child, name)| {
+        let type_ = dtd.resolve_type(child.type_).ok_or_else(|| CodegenError::UnknownType {
+            element: child.name.to_string(),
+            type_name: child.type_.name().to_string(),
+        })?;
+
+        let mut field = Field {
+            element: child,
+            name: Ident::new(name),
+            value_type: Tokens::new(),
+            boxed: false,
+        };
+        let newtype = newtypes.iter()
+            .find(|&&(new_type, _)| child.type_ == Type::Name(new_type.name()))
+            .map(|(_, name)| name);
+        let enum_ = enums.iter()
+            .find(|&&(element, _)| ::std::ptr::eq(element, child))
+            .map(|(_, name)| name);
+        match (newtype.or(enum_), options.primitive_type(type_)) {
+            // Elements of a user-defined type hold its newtype, and discrete `uint`s their enum.
+            (Some(newtype), _) => field.value_type = quote!(#newtype),
+            (None, Some(primitive)) => match options.fixed_binary_len(type_, child) {
+                Some(len) => {
+                    let len = len as usize;
+                    field.value_type = quote!([u8; #len]);
+                }
+                None => field.value_type.append(primitive),
+            },
+            (None, None) => {
+                let name = Ident::new(camel_case(child.name));
+                field.value_type = quote!(#name);
+                // Containers which can end up inside themselves need indirection to have a
+                // finite size. A Vec already provides it, but T and Option<T> don't.
+                field.boxed = !field.is_many() &&
+                    contains(dtd, child, container.name, &mut HashSet::new());
+            }
+        }
+        Ok(field)
+    }).collect()
+}
+
+fn container_struct(dtd: &Dtd, options: &CodegenOptions, container: &Element, name: Ident)
+    -> Result<Tokens, CodegenError>
+{
+    let fields = fields(dtd, options, container)?;
+    let names: Vec<_> = fields.iter().map(|field| &field.name).collect();
+    let types: Vec<_> = fields.iter().map(Field::type_).collect();
+    let docs: Vec<_> = fields.iter()
+        .map(|field| {
+            documented(format!("The `{}` child element.", field.element.name), field.element)
+        })
+        .collect();
+
+    let deprecated: Vec<_> = fields.iter()
+        .map(|field| deprecated_attribute(field.element.deprecation()))
+        .collect();
+
+    let doc = documented(format!("The contents of a `{}` element.", container.name), container);
+    let container_deprecated = deprecated_attribute(container.deprecation());
+
+    // Optional fields with defaults get an accessor filling in the default.
+    let defaulted: Vec<_> = fields.iter()
+        .filter(|field| field.element.cardinality() == Cardinality::ZeroOrOne)
+        .filter_map(|field| field.raw_default().map(|raw| (field, raw)))
+        .collect();
+    let accessors: Vec<_> = defaulted.iter()
+        .map(|&(field, _)| Ident::new(format!("{}_or_default", snake_case(field.element.name))))
+        .collect();
+    let accessor_docs: Vec<_> = defaulted.iter()
+        .map(|&(field, _)| format!(
+            "The `{}` child element, or its default if it's absent.\n\n\
+             Panics if the default can't be represented by the field's type.",
+            field.element.name
+        ))
+        .collect();
+    let defaulted_names: Vec<_> = defaulted.iter().map(|&(field, _)| &field.name).collect();
+    let defaulted_types: Vec<_> = defaulted.iter().map(|&(field, _)| &field.value_type).collect();
+    let raw_defaults: Vec<_> = defaulted.iter().map(|&(_, ref raw)| raw).collect();
+
+    let derive = options.derive_attribute();
+    let unknown_children = if options.collects_unknown() {
+        // Ids aren't serializable, so unknown elements are left out.
+        let serde = if options.serde() { quote!(#[serde(skip)]) } else { Tokens::new() };
+        let doc = "Child elements the schema doesn't describe, with their bodies, in the order \
+                   they were read. They aren't written by `write_to`.";
+        quote! {
+            #[doc = #doc]
+            #serde
+            pub unknown_children: ::std::vec::Vec<(::ebml::Id, ::std::vec::Vec<u8>)>,
+        }
+    } else {
+        Tokens::new()
+    };
+
+    Ok(quote! {
+        #[doc = #doc]
+        #container_deprecated
+        #derive
+        pub struct #name {
+            #(
+                #[doc = #docs]
+                #deprecated
+                pub #names: #types,
+            )*
+            #unknown_children
+        }
+
+        impl #name {
+            #(
+                #[doc = #accessor_docs]
+                pub fn #accessors(&self) -> #defaulted_types {
+                    match self.#defaulted_names {
+                        ::std::option::Option::Some(ref value) => value.clone(),
+                        ::std::option::Option::None => {
+                            ::ebml_macros::runtime::default_value(#raw_defaults)
+                        }
+                    }
+                }
+            )*
+        }
+    })
+}
+
+// True if an element named `name` can appear somewhere inside `container`.
+fn contains<'a>(dtd: &Dtd<'a>, container: &Element<'a>, name: &str, seen: &mut HashSet<&'a str>)
+    -> bool
+{
+    dtd.children_of(container).into_iter().any(|child| {
+        child.name == name ||
+            (child.type_ == Type::Container && seen.insert(child.name) &&
+             contains(dtd, child, name, seen))
+    })
+}
+
+// The name of the fields holding elements named `name`.
+pub fn field_name(name: &str) -> String {
+    rust_ident(snake_case(name))
+}
@@ -0,0 +1,645 @@
+use super::*;
+
+#[test]
+fn test_screaming_snake_case() {
+    assert_eq!(screaming_snake_case("Segment"), "SEGMENT");
+    assert_eq!(screaming_snake_case("EBMLVersion"), "EBML_VERSION");
+    assert_eq!(screaming_snake_case("DocTypeReadVersion"), "DOC_TYPE_READ_VERSION");
+    assert_eq!(screaming_snake_case("CRC32Value"), "CRC32_VALUE");
+    assert_eq!(screaming_snake_case("Void"), "VOID");
+    assert_eq!(screaming_snake_case("_private"), "_PRIVATE");
+}
+
+#[test]
+fn test_snake_case() {
+    let names = [
+        ("UID", "uid"),
+        ("TrackUID", "track_uid"),
+        ("EBMLMaxIDLength", "ebml_max_id_length"),
+        ("CueRefCodecState", "cue_ref_codec_state"),
+        ("DocTypeReadVersion", "doc_type_read_version"),
+        ("CRC32Value", "crc32_value"),
+        ("Video3DMode", "video3d_mode"),
+        ("Channel2D", "channel2d"),
+        ("Table00Column0", "table00_column0"),
+        ("ChapterTranslateEditionUID", "chapter_translate_edition_uid"),
+        ("_private", "_private"),
+        ("__Private", "__private"),
+        ("Foo_Bar", "foo_bar"),
+        ("x", "x"),
+    ];
+    for &(name, expected) in &names {
+        assert_eq!(snake_case(name), expected, "converting {}", name);
+    }
+}
+
+#[test]
+fn test_camel_case() {
+    assert_eq!(camel_case("Segment"), "Segment");
+    assert_eq!(camel_case("EBMLVersion"), "EbmlVersion");
+    assert_eq!(camel_case("DocTypeReadVersion"), "DocTypeReadVersion");
+    assert_eq!(camel_case("CRC32Value"), "Crc32Value");
+    assert_eq!(camel_case("EBML"), "Ebml");
+    assert_eq!(camel_case("_private"), "Private");
+    assert_eq!(camel_case("self"), "Self_");
+    assert_eq!(camel_case("Loop"), "Loop");
+}
+
+#[test]
+fn test_rust_ident() {
+    assert_eq!(rust_ident("type".to_string()), "r#type");
+    assert_eq!(rust_ident("self".to_string()), "self_");
+    assert_eq!(rust_ident("segment".to_string()), "segment");
+}
+
+#[test]
+fn test_generate() {
+    let dtd = ::parse_dtd(include_bytes!("../../tests/dtd0"), &Default::default()).unwrap();
+    let code = generate(&dtd, "matroska", &Default::default()).unwrap();
+
+    assert!(code.starts_with("pub mod matroska {"));
+    assert!(code.contains("pub const DOC_TYPE : & 'static str = \"matroska\" ;"));
+    assert!(code.contains("pub const EBML_VERSION : u32 = 0x4286u32 ;"));
+    assert!(code.contains("pub const SEGMENT : u32 = 0x18538067u32 ;"));
+    assert!(code.contains("0x18538067u32 => :: std :: option :: Option :: Some ( \"Segment\" ) ,"));
+    assert!(code.contains(
+        "b\"Segment\" => :: std :: option :: Option :: Some ( 0x18538067u32 ) ,"
+    ));
+    assert!(code.contains("pub const FLAG_LACING : u32 = 0x9Cu32 ;"));
+}
+
+#[test]
+fn test_generate_element_id() {
+    let dtd = ::parse_dtd(include_bytes!("../../tests/dtd0"), &Default::default()).unwrap();
+    let code = generate(&dtd, "matroska", &Default::default()).unwrap();
+
+    assert!(code.contains("pub enum ElementId {"));
+    assert!(code.contains("EbmlVersion ,"));
+    assert!(code.contains("Unknown ( :: ebml :: Id ) ,"));
+    assert!(code.contains("ElementId :: FlagLacing => \"FlagLacing\" ,"));
+    // Raw ids are constants, so they can be matched on and used in `const fn`s.
+    assert!(code.contains("pub const SEGMENT : :: ebml_macros :: runtime :: RawId = \
+                           :: ebml_macros :: runtime :: RawId ( 0x18538067u32 ) ;"));
+    assert!(code.contains("ElementId :: Segment => :: std :: option :: Option :: Some ( \
+                           raw_ids :: SEGMENT ) ,"));
+}
+
+#[test]
+fn test_invalid_id() {
+    // The parser rejects invalid ids, so this can only happen to a `Dtd` changed afterwards.
+    let mut dtd = ::parse_dtd(b"define elements { A := 81 uint; }", &Default::default()).unwrap();
+    dtd.elements[0].node.id = 0;
+    assert_eq!(generate(&dtd, "invalid", &Default::default()), Err(CodegenError::InvalidId {
+        element: "A".to_string(),
+        id: 0,
+    }));
+}
+
+#[test]
+fn test_doc_type() {
+    let dtd = ::parse_dtd(include_bytes!("../../tests/doc_types0"), &Default::default()).unwrap();
+    let matroska = generate(&dtd, "matroska", &Default::default()).unwrap();
+    assert!(matroska.contains("flag_lacing") && matroska.contains("\"matroska\""));
+
+    let options = CodegenOptions { doc_type: Some("webm".to_string()), ..Default::default() };
+    let webm = generate(&dtd, "webm", &options).unwrap();
+    assert!(webm.contains("\"webm\"") && !webm.contains("\"matroska\""));
+    assert!(webm.contains("title") && !webm.contains("flag_lacing"));
+
+    let options = CodegenOptions { doc_type: Some("mkv".to_string()), ..Default::default() };
+    assert_eq!(generate(&dtd, "mkv", &options),
+               Err(CodegenError::UnknownDocType("mkv".to_string())));
+}
+
+#[test]
+fn test_header_constants() {
+    let text = b"declare header {
+        DocType := \"matroska\";
+        EBMLMaxIDLength := 4;
+        Offset := -2;
+        Scale := 0.5;
+        Magic := 0xFADE;
+        DocTypeVersion := EBMLMaxIDLength;
+    }
+    define elements { A := 81 uint; }";
+    let dtd = ::parse_dtd(text, &Default::default()).unwrap();
+    let code = generate(&dtd, "header", &Default::default()).unwrap();
+
+    assert!(code.contains("pub const DOC_TYPE : & 'static str = \"matroska\" ;"));
+    assert!(code.contains("pub const EBML_MAX_ID_LENGTH : u64 = 4u64 ;"));
+    assert!(code.contains("pub const OFFSET : i64 = -2i64 ;"));
+    assert!(code.contains("pub const SCALE : f64 = 0.5f64 ;"));
+    assert!(code.contains("pub const MAGIC : & 'static [ u8 ] = & [ 250u8 , 222u8 , ] ;"));
+    // Names are resolved, and the header reader sees the same value.
+    assert!(code.contains("pub const DOC_TYPE_VERSION : u64 = 4u64 ;"));
+    assert!(code.contains("Some ( 4u64 )"));
+
+    let text = b"declare header { DocTypeVersion := Missing; } define elements { A := 81 uint; }";
+    let dtd = ::parse_dtd(text, &Default::default()).unwrap();
+    assert_eq!(generate(&dtd, "header", &Default::default()), Err(CodegenError::UnresolvedHeader {
+        statement: "DocTypeVersion".to_string(),
+        name: "Missing".to_string(),
+    }));
+}
+
+#[test]
+fn test_name_collision() {
+    let dtd = ::parse_dtd(include_bytes!("../../tests/dtd4"), &Default::default()).unwrap();
+    assert_eq!(generate(&dtd, "collide", &Default::default()), Err(CodegenError::NameCollision {
+        generated: "EBML_VERSION".to_string(),
+        first: "EBMLVersion".to_string(),
+        second: "EBML_Version".to_string(),
+    }));
+
+    let dtd = ::parse_dtd(br#"
+        define elements {
+            Track := ae container {
+                TrackUID := 73c5 uint [ card:?; ]
+                TrackUid := 73c6 uint [ card:?; ]
+            }
+        }
+    "#, &Default::default()).unwrap();
+    assert_eq!(generate(&dtd, "collide", &Default::default()), Err(CodegenError::NameCollision {
+        generated: "TRACK_UID".to_string(),
+        first: "TrackUID".to_string(),
+        second: "TrackUid".to_string(),
+    }));
+
+    let dtd = ::parse_dtd(include_bytes!("../../tests/dtd5"), &Default::default()).unwrap();
+    assert_eq!(generate(&dtd, "collide", &Default::default()), Err(CodegenError::NameCollision {
+        generated: "Unknown".to_string(),
+        first: "Unknown".to_string(),
+        second: "UNKNOWN".to_string(),
+    }));
+
+    let dtd = ::parse_dtd(b"define elements { Self := 81 uint; Self_ := 82 uint; }",
+                          &Default::default()).unwrap();
+    assert_eq!(generate(&dtd, "collide", &Default::default()), Err(CodegenError::NameCollision {
+        generated: "Self_".to_string(),
+        first: "Self".to_string(),
+        second: "Self_".to_string(),
+    }));
+}
+
+#[test]
+fn test_module_name() {
+    let dtd = ::parse_dtd(include_bytes!("../../tests/dtd0"), &Default::default()).unwrap();
+    assert_eq!(module_name(&dtd), Some("matroska".to_string()));
+
+    let text = b"declare header { DocType := \"Web-M2\"; } define elements { A := 81 uint; }";
+    let dtd = ::parse_dtd(text, &Default::default()).unwrap();
+    assert_eq!(module_name(&dtd), Some("web_m2".to_string()));
+    let dtd = ::parse_dtd(b"define elements { A := 81 uint; }", &Default::default()).unwrap();
+    assert_eq!(module_name(&dtd), None);
+}
+
+#[test]
+fn test_generate_structs() {
+    let dtd = ::parse_dtd(include_bytes!("../../tests/dtd0"), &Default::default()).unwrap();
+    let code = generate(&dtd, "matroska", &Default::default()).unwrap();
+
+    assert!(code.contains("pub struct Ebml {"));
+    assert!(code.contains("pub ebml_version : :: std :: vec :: Vec < u64 > ,"));
+    assert!(code.contains("pub info : :: std :: vec :: Vec < Info > ,"));
+    assert!(code.contains("pub flag_lacing : :: std :: vec :: Vec < Bool > ,"));
+    assert!(code.contains("pub void : :: std :: vec :: Vec < ::std::vec::Vec<u8> > ,"));
+
+    let dtd = ::parse_dtd(include_bytes!("../../tests/dtd6"), &Default::default()).unwrap();
+    assert!(dtd.validate().is_empty());
+    let code = generate(&dtd, "chapters", &Default::default()).unwrap();
+
+    assert!(code.contains("pub chapter_atom : :: std :: vec :: Vec < ChapterAtom > ,"));
+    assert!(code.contains("pub r#type : u64 ,"));
+    assert!(code.contains(
+        "pub chapter_display : :: std :: option :: Option < ChapterDisplay > ,"
+    ));
+    assert!(code.contains(
+        "pub nested : :: std :: option :: Option < :: std :: boxed :: Box < Nested > > ,"
+    ));
+}
+
+#[test]
+fn test_generate_newtypes() {
+    let dtd = ::parse_dtd(include_bytes!("../../tests/dtd0"), &Default::default()).unwrap();
+    let code = generate(&dtd, "matroska", &Default::default()).unwrap();
+
+    assert!(code.contains("pub struct Bool ( u64 ) ;"));
+    assert!(code.contains("pub fn new ( value : u64 )"));
+    assert!(code.contains("::ebml_macros::runtime::in_range ( & raw"));
+    assert!(code.contains("impl :: std :: ops :: Deref for Bool {"));
+}
+
+#[test]
+fn test_type_overrides() {
+    let dtd = ::parse_dtd(include_bytes!("../../tests/dtd0"), &Default::default()).unwrap();
+    let options = CodegenOptions {
+        string_type: "Title".to_string(),
+        uint_type: "u32".to_string(),
+        ..Default::default()
+    };
+    let code = generate(&dtd, "matroska", &options).unwrap();
+
+    assert!(code.contains("pub title : :: std :: vec :: Vec < Title > ,"));
+    assert!(code.contains("pub struct Bool ( u32 ) ;"));
+    assert!(code.contains("pub flag_lacing : :: std :: vec :: Vec < Bool > ,"));
+    assert!(code.contains("pub void : :: std :: vec :: Vec < ::std::vec::Vec<u8> > ,"));
+}
+
+#[test]
+fn test_chrono_free_dates() {
+    let dtd = ::parse_dtd(b"define elements { Info := 1549a966 container { \
+                              DateUTC := 4461 date [ def:20010102T00:00:00; ] } }",
+                          &Default::default()).unwrap();
+    let code = generate(&dtd, "dates", &Default::default()).unwrap();
+    assert_eq!(code.contains("chrono"), cfg!(feature = "chrono"));
+
+    let options = CodegenOptions { date_type: "i64".to_string(), ..Default::default() };
+    let code = generate(&dtd, "dates", &options).unwrap();
+    assert!(!code.contains("chrono"));
+    assert!(code.contains("pub const DATE_UTC : i64 = 86400000000000i64 ;"));
+}
+
+// The text of every doc attribute in generated code, unescaped.
+fn doc_attributes(code: &str) -> Vec<String> {
+    code.split("# [ doc = \"").skip(1).map(|attribute| {
+        let mut text = String::new();
+        let mut chars = attribute.chars();
+        while let Some(c) = chars.next() {
+            match c {
+                '"' => break,
+                '\\' => match chars.next() {
+                    Some('n') => text.push('\n'),
+                    Some(c) => text.push(c),
+                    None => break,
+                },
+                c => text.push(c),
+            }
+        }
+        text
+    }).collect()
+}
+
+#[test]
+fn test_comment_docs() {
+    let dtd = ::parse_dtd(include_bytes!("../../tests/dtd7"), &Default::default()).unwrap();
+    let docs = doc_attributes(&generate(&dtd, "info", &Default::default()).unwrap());
+
+    // The ElementId variants, then the struct and its fields.
+    let info = "Contains general information about the segment.";
+    let title = "The title of the segment,\nin UTF-8.";
+    assert!(docs.contains(&format!("The `Info` element.\n\n{}", info)));
+    assert!(docs.contains(&format!("The `Title` element.\n\n{}", title)));
+    assert!(docs.contains(&format!("The contents of a `Info` element.\n\n{}", info)));
+    assert!(docs.contains(&format!("The `Title` child element.\n\n{}", title)));
+    // Elements without comments are left as they were.
+    assert!(docs.contains(&"The `TimecodeScale` child element.".to_string()));
+}
+
+#[test]
+fn test_deprecated() {
+    let dtd = ::parse_dtd(b"
+        define elements {
+            Segment := 18538067 container {
+                /* The name.
+                 * @deprecated since 3: Use `Title`.
+                 */
+                Name := 4d80 string;
+                // @deprecated
+                Old := 4d81 container;
+            }
+        }
+    ", &Default::default()).unwrap();
+    let code = generate(&dtd, "segment", &Default::default()).unwrap();
+    assert!(code.contains("# ! [ allow ( deprecated ) ]"));
+    let attribute = "# [ deprecated ( since = \"3\" , note = \"Use `Title`.\" ) ]";
+    assert!(code.contains(&format!("{} pub const NAME : u32", attribute)));
+    assert!(code.contains(&format!("{} Name ,", attribute)));
+    assert!(code.contains(&format!("{} pub name : ", attribute)));
+    assert!(code.contains("# [ deprecated ] Old ,"));
+    // The tag is left out of the documentation.
+    let docs = doc_attributes(&code);
+    assert!(docs.contains(&"The `Name` element.\n\nThe name.".to_string()));
+    assert!(docs.contains(&"The `Old` element.".to_string()));
+
+    // Types are marked the same way.
+    let dtd = ::parse_dtd(b"
+        define types {
+            // @deprecated since 2
+            old_flag := uint;
+        }
+        define elements {
+            Flag := 81 old_flag;
+        }
+    ", &Default::default()).unwrap();
+    let code = generate(&dtd, "flag", &Default::default()).unwrap();
+    assert!(code.contains("# ! [ allow ( deprecated ) ]"));
+    assert!(code.contains("# [ deprecated ( since = \"2\" ) ] pub struct OldFlag"));
+
+    let dtd = ::parse_dtd(include_bytes!("../../tests/dtd7"), &Default::default()).unwrap();
+    let code = generate(&dtd, "info", &Default::default()).unwrap();
+    assert!(!code.contains("deprecated"));
+}
+
+#[test]
+fn test_generate_to_file() {
+    let dtd = ::parse_dtd(include_bytes!("../../tests/dtd7"), &Default::default()).unwrap();
+    let path = ::std::env::temp_dir().join(format!("ebml_macros_{}.rs", ::std::process::id()));
+    generate_to_file(&dtd, "info", &Default::default(), &path).unwrap();
+    let written = ::std::fs::read_to_string(&path).unwrap();
+    ::std::fs::remove_file(&path).unwrap();
+
+    let code = generate(&dtd, "info", &Default::default()).unwrap();
+    assert_eq!(written, format_source(&code).unwrap());
+    assert!(written.ends_with('\n'));
+
+    // Code which can't be formatted is an error, rather than being written as it is.
+    assert!(format_source("fn {").is_err());
+}
+
+#[test]
+fn test_generate_files_to_file() {
+    let dir = ::std::env::temp_dir().join(format!("ebml_macros_{}_split", ::std::process::id()));
+    ::std::fs::create_dir_all(&dir).unwrap();
+    let (header, body, other) =
+        (dir.join("header.edtd"), dir.join("body.edtd"), dir.join("other.edtd"));
+    let out = dir.join("out.rs");
+    ::std::fs::write(&header, "define elements { EBML := 1a45dfa3 container; }").unwrap();
+    ::std::fs::write(&body, "define elements { Segment := 18538067 container; }").unwrap();
+    ::std::fs::write(&other, "define elements { Segment := 1a45dfa3 container; }").unwrap();
+
+    generate_files_to_file(&[&header, &body, &header], "split", &Default::default(), &out).unwrap();
+    let written = ::std::fs::read_to_string(&out).unwrap();
+    assert!(written.contains("pub struct Ebml"));
+    assert!(written.contains("pub struct Segment"));
+
+    let err = generate_files_to_file(&[&header, &other], "split", &Default::default(), &out)
+        .unwrap_err();
+    assert_eq!(err.to_string(), format!("`Segment` in {} has the same id as `EBML` in {}",
+                                        other.display(), header.display()));
+
+    // A file may include the fragment itself, and errors in it name it.
+    let main = dir.join("main.edtd");
+    ::std::fs::write(&main, "include \"header.edtd\";\n\
+                             define elements { Segment := 18538067 container; }").unwrap();
+    generate_files_to_file(&[&main], "split", &Default::default(), &out).unwrap();
+    let written = ::std::fs::read_to_string(&out).unwrap();
+    assert!(written.contains("pub struct Ebml"));
+    ::std::fs::write(&header, "define elements { EBML := 1a45dfa3 }").unwrap();
+    let err = generate_files_to_file(&[&main], "split", &Default::default(), &out).unwrap_err();
+    assert!(err.to_string().starts_with(&format!("syntax error[P0005] in {} at line 1",
+                                                 header.display())));
+    ::std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_generate_c_header() {
+    let dtd = ::parse_dtd(include_bytes!("../../tests/dtd0"), &Default::default()).unwrap();
+    let path = ::std::env::temp_dir().join(format!("ebml_macros_{}.h", ::std::process::id()));
+    generate_c_header(&dtd, "MKV", &path).unwrap();
+    let written = ::std::fs::read_to_string(&path).unwrap();
+    ::std::fs::remove_file(&path).unwrap();
+
+    let lines: Vec<_> = written.lines().collect();
+    assert!(lines.contains(&"#ifndef MKV_EBML_IDS_H"));
+    assert!(lines.contains(&"#define MKV_DOC_TYPE \"matroska\""));
+    assert!(lines.contains(&"#define MKV_EBML_VERSION 1ULL"));
+    assert!(lines.contains(&"#define MKV_ID_SEGMENT 0x18538067u"));
+    assert!(lines.contains(&"#define MKV_ID_FLAG_LACING 0x9Cu"));
+    assert_eq!(written, c_header(&dtd, "MKV").unwrap());
+
+    let unprefixed = c_header(&dtd, "").unwrap();
+    assert!(unprefixed.contains("\n#define ID_EBML 0x1A45DFA3u\n"));
+
+    let dtd = ::parse_dtd(b"declare header { IDVoid := 1; } define elements { Void := ec binary; }",
+                          &Default::default()).unwrap();
+    assert_eq!(c_header(&dtd, "MKV"), Err(CodegenError::NameCollision {
+        generated: "MKV_ID_VOID".to_string(),
+        first: "MKV_ID_VOID".to_string(),
+        second: "IDVoid".to_string(),
+    }));
+}
+
+#[test]
+fn test_unknown_size_not_allowed() {
+    let dtd = ::parse_dtd(include_bytes!("../../tests/dtd0"), &Default::default()).unwrap();
+    let options = CodegenOptions { unknown_size: vec!["Info".to_string()], ..Default::default() };
+    assert_eq!(generate(&dtd, "matroska", &options),
+               Err(CodegenError::UnknownSizeNotAllowed("Info".to_string())));
+}
+
+#[test]
+fn test_constants_mode() {
+    let dtd = ::parse_dtd(include_bytes!("../../tests/dtd0"), &Default::default()).unwrap();
+    let options = CodegenOptions { mode: CodegenMode::Constants, ..Default::default() };
+    let code = generate(&dtd, "matroska", &options).unwrap();
+
+    assert!(code.contains("pub const DOC_TYPE : & 'static str = \"matroska\" ;"));
+    assert!(code.contains("pub const SEGMENT : u32 = 0x18538067u32 ;"));
+    assert!(code.contains("pub const EBML_VERSION : u64 = 1u64 ;"));
+    // The only functions are the lookups, which need nothing but `std`.
+    assert!(code.contains("pub const fn element_name ( id : u32 )"));
+    for dependency in &["struct", "pub fn ", "chrono", "ebml_macros", ":: ebml ::"] {
+        assert!(!code.contains(dependency), "constants mode generated `{}`", dependency);
+    }
+}
+
+#[test]
+fn test_fallback_mode() {
+    let dtd = ::parse_dtd(include_bytes!("../../tests/dtd5"), &Default::default()).unwrap();
+    let options = CodegenOptions { mode: CodegenMode::Fallback, ..Default::default() };
+    let code = generate(&dtd, "collide", &options).unwrap();
+    let reason = fallback_reason(&dtd, &options).unwrap();
+    assert_eq!(reason, CodegenError::NameCollision {
+        generated: "Unknown".to_string(),
+        first: "Unknown".to_string(),
+        second: "UNKNOWN".to_string(),
+    });
+    assert!(code.contains(&format!("Only constants were generated for this schema: {}.", reason)));
+    assert!(!code.contains("struct"));
+
+    let dtd = ::parse_dtd(include_bytes!("../../tests/dtd0"), &Default::default()).unwrap();
+    assert_eq!(fallback_reason(&dtd, &options), None);
+    assert!(generate(&dtd, "matroska", &options).unwrap().contains("pub struct Segment"));
+}
+
+#[test]
+fn test_enums() {
+    let dtd = ::parse_dtd(br#"
+        define elements {
+            Track := 4001 container {
+                TrackType := 83 uint [ card:1; range:1,2,17; variants:Video=1,Audio=2; ]
+                Channels := 9f uint [ card:1; range:1,2,4..8; ]
+            }
+        }
+    "#, &Default::default()).unwrap();
+    let code = generate(&dtd, "tracks", &Default::default()).unwrap();
+    assert!(!code.contains("pub enum TrackType"));
+
+    let options = CodegenOptions { enums: true, ..Default::default() };
+    let code = generate(&dtd, "tracks", &options).unwrap();
+    assert!(code.contains("pub enum TrackType"));
+    assert!(code.contains("Video , "));
+    assert!(code.contains("V17 , "));
+    assert!(code.contains("pub track_type : TrackType ,"));
+    assert!(!code.contains("enum Channels"));
+    assert!(code.contains("pub channels : u64 ,"));
+}
+
+#[test]
+fn test_borrowed() {
+    let dtd = ::parse_dtd(include_bytes!("../../tests/dtd6"), &Default::default()).unwrap();
+    let code = generate(&dtd, "chapters", &Default::default()).unwrap();
+    assert!(!code.contains("pub mod borrowed"));
+
+    let options = CodegenOptions { borrowed: true, ..Default::default() };
+    let code = generate(&dtd, "chapters", &options).unwrap();
+    assert!(code.contains("pub struct ChapterDisplay < 'a >"));
+    assert!(code.contains("pub chap_string : & 'a str ,"));
+    assert!(code.contains(
+        "pub chapter_display : :: std :: option :: Option < ChapterDisplay < 'a > > ,"
+    ));
+    // `Nested` has no strings or binaries inside it, so it isn't duplicated.
+    assert!(code.contains("pub use super :: Nested ;"));
+}
+
+#[test]
+fn test_builders() {
+    let dtd = ::parse_dtd(br#"
+        define elements {
+            Info := 1549a966 container {
+                Title := 7ba9 string [ card:?; ]
+                Tag := 4487 uint [ card:*; ]
+            }
+        }
+    "#, &Default::default()).unwrap();
+    let code = generate(&dtd, "info", &Default::default()).unwrap();
+    assert!(!code.contains("InfoBuilder"));
+
+    let options = CodegenOptions { builders: true, ..Default::default() };
+    let code = generate(&dtd, "info", &options).unwrap();
+    assert!(code.contains("pub struct InfoBuilder"));
+    assert!(code.contains("pub fn title < V : :: std :: convert :: Into <"));
+    assert!(code.contains("self . tag . push ( value ) ;"));
+
+    let dtd = ::parse_dtd(br#"
+        define elements {
+            Info := 1549a966 container {
+                Build := 7ba9 string [ card:?; ]
+            }
+        }
+    "#, &Default::default()).unwrap();
+    assert_eq!(generate(&dtd, "info", &options), Err(CodegenError::NameCollision {
+        generated: "build".to_string(),
+        first: "build".to_string(),
+        second: "Build".to_string(),
+    }));
+}
+
+#[test]
+fn test_derive() {
+    let dtd = ::parse_dtd(br#"
+        define elements {
+            Info := 1549a966 container {
+                Title := 7ba9 string [ card:?; ]
+                Uid := 73a4 binary [ card:1; ]
+                Tag := 4487 uint [ card:*; ]
+            }
+        }
+    "#, &Default::default()).unwrap();
+    let field = |name: &str, element: &str, type_: &str, wrapped: Option<(Wrapper, &str)>| {
+        DeriveField {
+            name: name.to_string(),
+            element: element.to_string(),
+            type_: type_.to_string(),
+            wrapped: wrapped.map(|(wrapper, inner)| (wrapper, inner.to_string())),
+        }
+    };
+    let mut input = DeriveInput {
+        name: "MyInfo".to_string(),
+        element: "Info".to_string(),
+        fields: vec![
+            field("title", "Title", "Option<String>", Some((Wrapper::Option, "String"))),
+            // Held in a `Vec`, but there's exactly one.
+            field("uid", "Uid", "Vec<u8>", Some((Wrapper::Vec, "u8"))),
+        ],
+    };
+    let code = derive_read(&dtd, &input).unwrap();
+    assert!(code.starts_with("const _ : ( ) = {"));
+    assert!(code.contains("impl MyInfo where String : :: ebml_macros :: runtime :: EbmlPrimitive \
+                           < :: std :: string :: String > , Vec<u8> : :: ebml_macros :: runtime \
+                           :: EbmlPrimitive < :: std :: vec :: Vec < u8 > > ,"));
+    assert!(derive_write(&dtd, &input).unwrap().contains("pub fn write_to"));
+
+    input.fields.push(field("tags", "Tag", "Option<u64>", Some((Wrapper::Option, "u64"))));
+    assert_eq!(derive_read(&dtd, &input), Err(DeriveError::WrongWrapper {
+        field: 2,
+        element: "Tag".to_string(),
+        expected: Wrapper::Vec,
+    }));
+    input.fields[2] = field("title2", "Title", "Option<String>", Some((Wrapper::Option, "String")));
+    assert_eq!(derive_read(&dtd, &input).unwrap_err().to_string(),
+               "`Title` is already held by `title`");
+    input.fields[2] = field("name", "Name", "String", None);
+    assert_eq!(derive_write(&dtd, &input).unwrap_err().to_string(),
+               "`Info` has no child element named `Name`");
+    input.element = "Title".to_string();
+    assert_eq!(derive_read(&dtd, &input), Err(DeriveError::UnknownContainer("Title".to_string())));
+}
+
+#[test]
+fn test_tokens() {
+    use {DateRangeItem, Level};
+
+    let level = Level::Bounded { start: 1, end: 3 };
+    assert_eq!(quote!(#level).as_str(),
+               ":: ebml_macros :: runtime :: LevelRange { start : 1u64 , end : :: std :: option \
+                :: Option :: Some ( 3u64 ) }");
+    let level = Level::Open { start: 0 };
+    assert!(quote!(#level).as_str().ends_with("end : :: std :: option :: Option :: None }"));
+
+    let date = DateRangeItem::To { end: ::runtime::date_from_raw(5) };
+    assert_eq!(quote!(#date).as_str(),
+               ":: ebml_macros :: DateRangeItem :: To { end : :: ebml_macros :: runtime :: \
+                date_from_raw ( 5i64 ) }");
+
+    // Ranges are checked against items named in full.
+    let text = "define elements { Info := 1549a966 container { Flag := 81 uint [ range:2..3; ] } }";
+    let dtd = ::parse_dtd(text, &Default::default()).unwrap();
+    let code = generate(&dtd, "test", &Default::default()).unwrap();
+    assert!(code.contains("& [ :: ebml_macros :: UintRangeItem :: Bounded { start : 2u64 , \
+                           end : 3u64 } ]"));
+}
+
+#[test]
+#[cfg(feature = "chrono")]
+fn test_far_dates() {
+    // Dates too far from the epoch to encode are clamped wherever they're quoted, as they are when
+    // read, rather than left out.
+    let text = "declare header { Launch := 25000101T00:00:00; } define elements { Info := 1549a966 \
+                container { Due := 4461 date [ def:25000101T00:00:00; ] } }";
+    let dtd = ::parse_dtd(text, &Default::default()).unwrap();
+    let code = generate(&dtd, "far", &Default::default()).unwrap();
+    assert!(code.contains("pub const LAUNCH : i64 = 9223372036854775807i64 ;"));
+    assert!(code.contains("pub const DUE : i64 = 9223372036854775807i64 ;"));
+    let header = c_header(&dtd, "FAR").unwrap();
+    assert!(header.contains("#define FAR_LAUNCH 9223372036854775807LL\n"));
+}
+
+#[test]
+fn test_unknown_find_path() {
+    let dtd = ::parse_dtd(include_bytes!("../../tests/dtd0"), &Default::default()).unwrap();
+    let find = |path: &str| {
+        let options = CodegenOptions {
+            find_paths: Some(vec![path.to_string()]),
+            ..Default::default()
+        };
+        generate(&dtd, "matroska", &options).unwrap_err().to_string()
+    };
+    assert_eq!(find("\\Segment\\info\\Title"),
+               "path `\\Segment\\info\\Title` doesn't lead to an element; did you mean \
+                `\\Segment\\Info\\Title`?");
+    assert_eq!(find("Segmnet\\Info"),
+               "path `Segmnet\\Info` doesn't lead to an element; did you mean `\\Segment\\Info`?");
+    // Nothing close enough.
+    assert_eq!(find("\\Segment\\Cluster"), "path `\\Segment\\Cluster` doesn't lead to an element");
+}
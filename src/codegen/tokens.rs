@@ -0,0 +1,267 @@
+//! Expressions rebuilding the values of the AST and the runtime in generated code.
+//!
+//! Each names what it builds in full, from `::ebml_macros`, so it can go anywhere in generated
+//! code. All of them can be used in constants, except those of dates: `runtime::date_from_raw`
+//! rebuilds them from nanoseconds, which isn't possible in constant expressions.
+//!
+//! Defaults and header values are quoted as `runtime::DefaultValue`s, whatever constant or field
+//! they end up in, so dates too far from the epoch to encode are clamped everywhere, as they are
+//! when values are read.
+
+use quote::{Hex, Ident, ToTokens, Tokens};
+
+use {date, BinaryRangeItem, Cardinality, DateRangeItem, FloatRangeItem, HeaderStatement,
+     IntRangeItem, Level, Property, SizeList, StringRangeItem, UintRangeItem};
+use runtime::{DefaultValue, ElementType, LevelRange, RawId};
+
+impl ToTokens for Cardinality {
+    fn to_tokens(&self, tokens: &mut Tokens) {
+        let variant = Ident::new(format!("{:?}", self));
+        quote!(::ebml_macros::Cardinality::#variant).to_tokens(tokens);
+    }
+}
+
+// As the `LevelRange` of the same levels, since `Level` isn't public.
+impl ToTokens for Level {
+    fn to_tokens(&self, tokens: &mut Tokens) {
+        level_range(self).to_tokens(tokens);
+    }
+}
+
+// The levels of `level`, as the runtime has them.
+pub fn level_range(level: &Level) -> LevelRange {
+    match *level {
+        Level::Bounded { start, end } => LevelRange { start, end: Some(end) },
+        Level::Open { start } => LevelRange { start, end: None },
+    }
+}
+
+impl ToTokens for LevelRange {
+    fn to_tokens(&self, tokens: &mut Tokens) {
+        let (start, end) = (self.start, option(self.end));
+        quote!(::ebml_macros::runtime::LevelRange { start: #start, end: #end }).to_tokens(tokens);
+    }
+}
+
+impl ToTokens for IntRangeItem {
+    fn to_tokens(&self, tokens: &mut Tokens) {
+        let expr = match *self {
+            IntRangeItem::Single(x) => quote!(::ebml_macros::IntRangeItem::Single(#x)),
+            IntRangeItem::From { start } => {
+                quote!(::ebml_macros::IntRangeItem::From { start: #start })
+            }
+            IntRangeItem::To { end } => quote!(::ebml_macros::IntRangeItem::To { end: #end }),
+            IntRangeItem::Bounded { start, end } => {
+                quote!(::ebml_macros::IntRangeItem::Bounded { start: #start, end: #end })
+            }
+        };
+        expr.to_tokens(tokens);
+    }
+}
+
+impl ToTokens for UintRangeItem {
+    fn to_tokens(&self, tokens: &mut Tokens) {
+        let expr = match *self {
+            UintRangeItem::Single(x) => quote!(::ebml_macros::UintRangeItem::Single(#x)),
+            UintRangeItem::From { start } => {
+                quote!(::ebml_macros::UintRangeItem::From { start: #start })
+            }
+            UintRangeItem::Bounded { start, end } => {
+                quote!(::ebml_macros::UintRangeItem::Bounded { start: #start, end: #end })
+            }
+        };
+        expr.to_tokens(tokens);
+    }
+}
+
+impl ToTokens for SizeList {
+    fn to_tokens(&self, tokens: &mut Tokens) {
+        let items = self.items();
+        quote!(::ebml_macros::SizeList::from_static(&[#(#items),*])).to_tokens(tokens);
+    }
+}
+
+impl ToTokens for FloatRangeItem {
+    fn to_tokens(&self, tokens: &mut Tokens) {
+        let expr = match *self {
+            FloatRangeItem::From { start, include_start } => quote! {
+                ::ebml_macros::FloatRangeItem::From { start: #start, include_start: #include_start }
+            },
+            FloatRangeItem::To { end, include_end } => quote! {
+                ::ebml_macros::FloatRangeItem::To { end: #end, include_end: #include_end }
+            },
+            FloatRangeItem::Bounded { start, include_start, end, include_end } => quote! {
+                ::ebml_macros::FloatRangeItem::Bounded {
+                    start: #start,
+                    include_start: #include_start,
+                    end: #end,
+                    include_end: #include_end,
+                }
+            },
+        };
+        expr.to_tokens(tokens);
+    }
+}
+
+impl ToTokens for DateRangeItem {
+    fn to_tokens(&self, tokens: &mut Tokens) {
+        // Dates too far from the epoch to encode are clamped, as they are when values are read.
+        let date = |value| {
+            let nanos = date::saturating_nanos(value);
+            quote!(::ebml_macros::runtime::date_from_raw(#nanos))
+        };
+        let expr = match *self {
+            DateRangeItem::From { ref start } => {
+                let start = date(start);
+                quote!(::ebml_macros::DateRangeItem::From { start: #start })
+            }
+            DateRangeItem::To { ref end } => {
+                let end = date(end);
+                quote!(::ebml_macros::DateRangeItem::To { end: #end })
+            }
+            DateRangeItem::Bounded { ref start, ref end } => {
+                let (start, end) = (date(start), date(end));
+                quote!(::ebml_macros::DateRangeItem::Bounded { start: #start, end: #end })
+            }
+        };
+        expr.to_tokens(tokens);
+    }
+}
+
+impl ToTokens for StringRangeItem {
+    fn to_tokens(&self, tokens: &mut Tokens) {
+        let expr = match *self {
+            StringRangeItem::Single(x) => quote!(::ebml_macros::StringRangeItem::Single(#x)),
+            StringRangeItem::From { start } => {
+                quote!(::ebml_macros::StringRangeItem::From { start: #start })
+            }
+            StringRangeItem::Bounded { start, end } => {
+                quote!(::ebml_macros::StringRangeItem::Bounded { start: #start, end: #end })
+            }
+        };
+        expr.to_tokens(tokens);
+    }
+}
+
+impl ToTokens for BinaryRangeItem {
+    fn to_tokens(&self, tokens: &mut Tokens) {
+        let expr = match *self {
+            BinaryRangeItem::Single(x) => quote!(::ebml_macros::BinaryRangeItem::Single(#x)),
+            BinaryRangeItem::From { start } => {
+                quote!(::ebml_macros::BinaryRangeItem::From { start: #start })
+            }
+            BinaryRangeItem::Bounded { start, end } => {
+                quote!(::ebml_macros::BinaryRangeItem::Bounded { start: #start, end: #end })
+            }
+        };
+        expr.to_tokens(tokens);
+    }
+}
+
+impl<'a> ToTokens for DefaultValue<'a> {
+    fn to_tokens(&self, tokens: &mut Tokens) {
+        let variant = Ident::new(match *self {
+            DefaultValue::Int(_) => "Int",
+            DefaultValue::Uint(_) => "Uint",
+            DefaultValue::Float(_) => "Float",
+            DefaultValue::Date(_) => "Date",
+            DefaultValue::String(_) => "String",
+            DefaultValue::Binary(_) => "Binary",
+        });
+        let raw = Raw(self);
+        quote!(::ebml_macros::runtime::DefaultValue::#variant(#raw)).to_tokens(tokens);
+    }
+}
+
+// A default or header value as the constants of generated code hold it, without the variant of
+// `DefaultValue` it's in: a number, the nanoseconds of a date, a `&'static str` or a
+// `&'static [u8]`.
+pub struct Raw<'v, 'a: 'v>(pub &'v DefaultValue<'a>);
+
+impl<'v, 'a> Raw<'v, 'a> {
+    // The type of a constant holding the value.
+    pub fn type_(&self) -> Tokens {
+        match *self.0 {
+            DefaultValue::Int(_) | DefaultValue::Date(_) => quote!(i64),
+            DefaultValue::Uint(_) => quote!(u64),
+            DefaultValue::Float(_) => quote!(f64),
+            DefaultValue::String(_) => quote!(&'static str),
+            DefaultValue::Binary(_) => quote!(&'static [u8]),
+        }
+    }
+
+    // An expression giving the value as the fields of generated structs hold it, which own their
+    // strings and binaries.
+    pub fn owned(&self) -> Tokens {
+        let raw = self;
+        match *self.0 {
+            DefaultValue::String(_) => quote!(::std::string::String::from(#raw)),
+            DefaultValue::Binary(_) => quote!(<[u8]>::to_vec(#raw)),
+            _ => quote!(#raw),
+        }
+    }
+}
+
+impl<'v, 'a> ToTokens for Raw<'v, 'a> {
+    fn to_tokens(&self, tokens: &mut Tokens) {
+        match *self.0 {
+            DefaultValue::Int(value) | DefaultValue::Date(value) => value.to_tokens(tokens),
+            DefaultValue::Uint(value) => value.to_tokens(tokens),
+            DefaultValue::Float(value) => value.to_tokens(tokens),
+            DefaultValue::String(value) => value.to_tokens(tokens),
+            DefaultValue::Binary(value) => quote!(&#value).to_tokens(tokens),
+        }
+    }
+}
+
+// The value of `property`, if it's a default.
+pub fn default_value<'p>(property: &'p Property) -> Option<DefaultValue<'p>> {
+    match *property {
+        Property::IntDefault(value) => Some(DefaultValue::Int(value)),
+        Property::UintDefault(value) => Some(DefaultValue::Uint(value)),
+        Property::FloatDefault(value) => Some(DefaultValue::Float(value)),
+        Property::DateDefault(ref value) => Some(DefaultValue::Date(date::saturating_nanos(value))),
+        Property::StringDefault(ref value) => Some(DefaultValue::String(value)),
+        Property::BinaryDefault(ref value) => Some(DefaultValue::Binary(value)),
+        _ => None,
+    }
+}
+
+// The value of `statement`, unless it names another statement.
+pub fn header_value<'s>(statement: &'s HeaderStatement) -> Option<DefaultValue<'s>> {
+    match *statement {
+        HeaderStatement::Int { value, .. } => Some(DefaultValue::Int(value)),
+        HeaderStatement::Uint { value, .. } => Some(DefaultValue::Uint(value)),
+        HeaderStatement::Float { value, .. } => Some(DefaultValue::Float(value)),
+        HeaderStatement::Date { ref value, .. } => {
+            Some(DefaultValue::Date(date::saturating_nanos(value)))
+        }
+        HeaderStatement::String { ref value, .. } => Some(DefaultValue::String(value)),
+        HeaderStatement::Binary { ref value, .. } => Some(DefaultValue::Binary(value)),
+        HeaderStatement::Named { .. } => None,
+    }
+}
+
+impl ToTokens for ElementType {
+    fn to_tokens(&self, tokens: &mut Tokens) {
+        let variant = Ident::new(format!("{:?}", self));
+        quote!(::ebml_macros::runtime::ElementType::#variant).to_tokens(tokens);
+    }
+}
+
+impl ToTokens for RawId {
+    fn to_tokens(&self, tokens: &mut Tokens) {
+        let id = Hex(self.0);
+        quote!(::ebml_macros::runtime::RawId(#id)).to_tokens(tokens);
+    }
+}
+
+// `Some(value)` or `None`, named in full. Interpolating an `Option` itself gives the value or
+// nothing.
+pub fn option<T: ToTokens>(value: Option<T>) -> Tokens {
+    match value {
+        Some(value) => quote!(::std::option::Option::Some(#value)),
+        None => quote!(::std::option::Option::None),
+    }
+}
+
@@ -0,0 +1,130 @@
+//! Generates a newtype for every type defined in the `define types` block.
+
+use quote::{Ident, Tokens};
+
+use {Dtd, NewType, Type};
+use super::{camel_case, deprecated_attribute, unique, CodegenError, CodegenOptions};
+use super::read::range_items;
+use super::structs::containers;
+use super::write::scalar_encoded_len;
+
+pub fn newtype_structs(dtd: &Dtd, options: &CodegenOptions) -> Result<Tokens, CodegenError> {
+    let mut tokens = Tokens::new();
+    for (new_type, name) in newtypes(dtd)? {
+        tokens.append_all(&[newtype_struct(options, new_type, name)]);
+    }
+    Ok(tokens)
+}
+
+// Every type which can be used by an element, with the name of its newtype. Types named after
+// built-in types can never be used, and only the first definition of a name counts.
+pub fn newtypes<'d, 'a>(dtd: &'d Dtd<'a>) -> Result<Vec<(&'d NewType<'a>, Ident)>, CodegenError> {
+    let mut types: Vec<&NewType> = Vec::new();
+    for new_type in &dtd.types {
+        if Type::builtin(new_type.name()).is_none() &&
+            !types.iter().any(|t| t.name() == new_type.name())
+        {
+            types.push(new_type);
+        }
+    }
+
+    let containers = containers(dtd)?;
+    let mut reserved: Vec<&str> = containers.iter().map(|(_, name)| name.as_ref()).collect();
+    reserved.push("ElementId");
+    let type_names: Vec<_> = types.iter().map(|t| t.name()).collect();
+    let names = unique(&type_names, &reserved, camel_case)?;
+    Ok(types.into_iter().zip(names.into_iter().map(Ident::new)).collect())
+}
+
+fn newtype_struct(options: &CodegenOptions, new_type: &NewType, name: Ident) -> Tokens {
+    let base_type = new_type.base_type();
+    let mut base = Tokens::new();
+    base.append(options.primitive_type(base_type).expect("types are aliases of primitives"));
+    let raw = match base_type {
+        Type::Int | Type::Date => quote!(i64),
+        Type::Uint => quote!(u64),
+        Type::Float => quote!(f64),
+        Type::String => quote!(::std::string::String),
+        _ => quote!(::std::vec::Vec<u8>),
+    };
+
+    // Values which can't be encoded at all aren't rejected here; writing them fails instead.
+    let check = new_type.range().and_then(range_items).map(|(kind, items)| {
+        let in_range = Ident::new(format!("::ebml_macros::runtime::{}in_range", kind));
+        let raw_value = if kind == "date_" { quote!(raw) } else { quote!(&raw) };
+        quote! {
+            let raw = ::ebml_macros::runtime::EbmlPrimitive::<#raw>::to_raw(&value);
+            if let ::std::option::Option::Some(raw) = raw {
+                #in_range(#raw_value, &[#(#items),*])?;
+            }
+        }
+    });
+
+    let doc = format!(
+        "Values of the `{}` type, which is a `{}`.",
+        new_type.name(), base_type.name()
+    );
+    let new_doc = format!("Wraps `value`, failing if it's outside the range of `{}`.",
+                          new_type.name());
+    let derive = options.derive_attribute();
+    let deprecated = deprecated_attribute(new_type.deprecation());
+    let encoded_len = scalar_encoded_len(options, base_type);
+
+    quote! {
+        #[doc = #doc]
+        #derive
+        #deprecated
+        pub struct #name(#base);
+
+        impl #name {
+            #[doc = #new_doc]
+            pub fn new(value: #base)
+                -> ::std::result::Result<Self, ::ebml_macros::runtime::RangeError>
+            {
+                #check
+                ::std::result::Result::Ok(#name(value))
+            }
+
+            #[doc = "Unwraps the value."]
+            pub fn into_inner(self) -> #base {
+                self.0
+            }
+
+            #encoded_len
+        }
+
+        impl ::std::ops::Deref for #name {
+            type Target = #base;
+
+            fn deref(&self) -> &#base {
+                &self.0
+            }
+        }
+
+        impl ::std::convert::From<#name> for #base {
+            fn from(value: #name) -> Self {
+                value.0
+            }
+        }
+
+        impl ::std::convert::TryFrom<#base> for #name {
+            type Error = ::ebml_macros::runtime::RangeError;
+
+            fn try_from(value: #base) -> ::std::result::Result<Self, Self::Error> {
+                #name::new(value)
+            }
+        }
+
+        // Decoded values aren't checked against the range here, since readers check ranges
+        // themselves according to their options.
+        impl ::ebml_macros::runtime::EbmlPrimitive<#raw> for #name {
+            fn from_raw(raw: #raw) -> ::std::option::Option<Self> {
+                <#base as ::ebml_macros::runtime::EbmlPrimitive<#raw>>::from_raw(raw).map(#name)
+            }
+
+            fn to_raw(&self) -> ::std::option::Option<#raw> {
+                ::ebml_macros::runtime::EbmlPrimitive::<#raw>::to_raw(&self.0)
+            }
+        }
+    }
+}
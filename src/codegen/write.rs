@@ -0,0 +1,333 @@
+//! Generates a `write_to` function for every container struct.
+
+use quote::{Ident, Tokens};
+
+use {Cardinality, Dtd, Element, Type};
+use super::{reusable, screaming_snake_case, CodegenError, CodegenOptions, FloatEncoding};
+use super::read::range_items;
+use super::structs::{containers, fields, Field};
+
+pub fn writers(dtd: &Dtd, options: &CodegenOptions) -> Result<Tokens, CodegenError> {
+    let mut tokens = Tokens::new();
+    for (container, name) in containers(dtd)? {
+        let functions = writer(dtd, options, container, &fields(dtd, options, container)?)?;
+        let write_impl = write_impl(&name, &Tokens::new());
+        tokens.append_all(&[quote! {
+            impl #name {
+                #functions
+            }
+
+            #write_impl
+        }]);
+    }
+    Ok(tokens)
+}
+
+// An implementation of `runtime::EbmlWrite` for `name`, calling its writers. `bounds` are the
+// predicates of its `where` clause.
+pub fn write_impl(name: &Ident, bounds: &Tokens) -> Tokens {
+    quote! {
+        impl ::ebml_macros::runtime::EbmlWrite for #name where #bounds {
+            fn write_with<W: ::std::io::Write>(&self, w: &mut W,
+                                               options: &::ebml_macros::runtime::WriteOptions)
+                -> ::std::io::Result<u64>
+            {
+                #name::write_with(self, w, options)
+            }
+
+            fn encoded_len_with(&self, options: &::ebml_macros::runtime::WriteOptions)
+                -> ::std::io::Result<u64>
+            {
+                #name::encoded_len_with(self, options)
+            }
+        }
+    }
+}
+
+// The writers of `container`, whose struct has `fields`. Fields are in declaration order, which
+// is the order `ordered:yes` requires children to be written in.
+pub fn writer(dtd: &Dtd, options: &CodegenOptions, container: &Element, fields: &[Field])
+    -> Result<Tokens, CodegenError>
+{
+    let id = Ident::new(screaming_snake_case(container.name));
+    let unknown_size = options.unknown_size.iter().any(|name| name == container.name);
+    if unknown_size && !container.allows_unknown_size() {
+        return Err(CodegenError::UnknownSizeNotAllowed(container.name.to_string()));
+    }
+
+    let mut doc = format!(
+        "Writes this as a `{}` element, returning the number of bytes written.",
+        container.name
+    );
+    if container.is_ordered() {
+        doc.push_str(" Children are written in the order they're declared in.");
+    }
+    if unknown_size {
+        doc.push_str(" It's written with an unknown size.");
+    }
+    let len_doc = "The number of bytes `write_to` writes. Fails if any value can't be encoded, \
+                   like `write_to`.";
+    let with_doc = "Like `write_to`, with options for what to do with values outside their \
+                    range.";
+    let len_with_doc = "The number of bytes `write_with` writes with the given options.";
+
+    let values: Vec<_> = fields.iter().map(field_values).collect();
+    let lens: Vec<_> = fields.iter().map(|field| value_len(dtd, options, field)).collect();
+    let writes: Vec<_> = fields.iter().map(|field| write_value(dtd, options, field)).collect();
+    let skips: Vec<_> = fields.iter().map(|field| skip_default(dtd, options, field)).collect();
+
+    // Containers with a CRC-32 have their children written to a buffer first, since the CRC
+    // covers them and comes before them. Everything else is written straight to `w`, and only
+    // needs the length of its body if it's written with a known size.
+    let crc32 = options.crc32.iter().any(|name| name == container.name);
+    let write = match (crc32, unknown_size) {
+        (true, true) => quote! {
+            sizes.next();
+            let mut children = ::std::vec::Vec::new();
+            self.write_children(&mut children, sizes, options)?;
+            let crc = runtime::crc32(&children).to_le_bytes();
+            let mut written = runtime::write_unknown_size_header(w, ids::#id)?;
+            written += runtime::write_element(w, runtime::CRC_32, &crc)?;
+            w.write_all(&children)?;
+            Ok(written + children.len() as u64)
+        },
+        (true, false) => quote! {
+            sizes.next();
+            let mut children = ::std::vec::Vec::new();
+            self.write_children(&mut children, sizes, options)?;
+            runtime::write_with_crc32(w, ids::#id, &children)
+        },
+        (false, true) => quote! {
+            sizes.next();
+            let written = runtime::write_unknown_size_header(w, ids::#id)?;
+            Ok(written + self.write_children(w, sizes, options)?)
+        },
+        (false, false) => quote! {
+            let body_len = sizes.next().expect("sizes are pushed in the order they're written");
+            let written = runtime::write_header(w, ids::#id, body_len)?;
+            Ok(written + self.write_children(w, sizes, options)?)
+        },
+    };
+    let crc_len = if crc32 { quote!(runtime::CRC_32_LEN) } else { quote!(0) };
+    // Only containers inside this one have sizes to take, and only they and values with ranges
+    // need the options.
+    let has_containers = fields.iter().any(|field| encode_body(dtd, options, field).is_none());
+    let sizes = if has_containers { quote!(sizes) } else { quote!(_sizes) };
+    let has_ranges = fields.iter()
+        .any(|field| dtd.range_of(field.element).and_then(range_items).is_some());
+    let write_options = if has_containers || has_ranges {
+        quote!(options)
+    } else {
+        quote!(_options)
+    };
+    let element_len = if unknown_size {
+        quote!(runtime::unknown_size_element_len(ids::#id, len))
+    } else {
+        quote!(runtime::element_len(ids::#id, len))
+    };
+
+    let (values, skips) = (reusable(&values), reusable(&skips));
+
+    // Sizes are worked out in a first pass over everything, so the second can write each
+    // element's size before its body without buffering it.
+    Ok(quote! {
+        #[doc = #doc]
+        pub fn write_to<W: ::std::io::Write>(&self, w: &mut W) -> ::std::io::Result<u64> {
+            self.write_with(w, &::ebml_macros::runtime::WriteOptions::default())
+        }
+
+        #[doc = #with_doc]
+        pub fn write_with<W: ::std::io::Write>(&self, w: &mut W,
+                                               options: &::ebml_macros::runtime::WriteOptions)
+            -> ::std::io::Result<u64>
+        {
+            let mut sizes = ::std::vec::Vec::new();
+            self.sizes(&mut sizes, options)?;
+            self.write_sized(w, &mut sizes.into_iter(), options)
+        }
+
+        #[doc = #len_doc]
+        pub fn encoded_len(&self) -> ::std::io::Result<u64> {
+            self.encoded_len_with(&::ebml_macros::runtime::WriteOptions::default())
+        }
+
+        #[doc = #len_with_doc]
+        pub fn encoded_len_with(&self, options: &::ebml_macros::runtime::WriteOptions)
+            -> ::std::io::Result<u64>
+        {
+            self.sizes(&mut ::std::vec::Vec::new(), options)
+        }
+
+        // Pushes the length of this element's body, then the lengths of the bodies of all
+        // the containers inside it in the order they're written, and returns this
+        // element's length. Every value is converted to find it, so this fails if any of
+        // them can't be encoded. Public, like `write_sized`, so parents in other modules can
+        // use it.
+        #[doc(hidden)]
+        pub fn sizes(&self, sizes: &mut ::std::vec::Vec<u64>,
+                     #write_options: &::ebml_macros::runtime::WriteOptions)
+            -> ::std::io::Result<u64>
+        {
+            use ::ebml_macros::runtime;
+
+            let index = sizes.len();
+            sizes.push(0);
+            let mut len = #crc_len;
+            #(
+                for value in #values {
+                    #skips
+                    len += #lens;
+                }
+            )*
+            sizes[index] = len;
+            Ok(#element_len)
+        }
+
+        // Writes this element, taking the lengths of its body and the bodies of the
+        // containers inside it from `sizes`.
+        #[doc(hidden)]
+        pub fn write_sized<W: ::std::io::Write>(&self, w: &mut W,
+                                                sizes: &mut ::std::vec::IntoIter<u64>,
+                                                options: &::ebml_macros::runtime::WriteOptions)
+            -> ::std::io::Result<u64>
+        {
+            use ::ebml_macros::runtime;
+
+            #write
+        }
+
+        // Writes the children of this element, returning the number of bytes written.
+        fn write_children<W: ::std::io::Write>(&self, w: &mut W,
+                                           #sizes: &mut ::std::vec::IntoIter<u64>,
+                                           #write_options: &::ebml_macros::runtime::WriteOptions)
+            -> ::std::io::Result<u64>
+        {
+            use ::ebml_macros::runtime;
+
+            let mut written = 0;
+            #(
+                for value in #values {
+                    #skips
+                    written += #writes;
+                }
+            )*
+            Ok(written)
+        }
+    })
+}
+
+// An expression iterating over references to the values of `field`.
+fn field_values(field: &Field) -> Tokens {
+    let name = &field.name;
+    match field.element.cardinality() {
+        Cardinality::ExactlyOne => quote!(::std::iter::once(&self.#name)),
+        _ => quote!(self.#name.iter()),
+    }
+}
+
+// An expression encoding the body of a primitive `value`, or None if `field` is a container.
+pub fn encode_body(dtd: &Dtd, options: &CodegenOptions, field: &Field) -> Option<Tokens> {
+    dtd.resolve_type(field.element.type_).and_then(|type_| primitive_body(options, type_))
+}
+
+// An expression encoding the body of `value`, a primitive of `type_`, or None if it isn't one.
+fn primitive_body(options: &CodegenOptions, type_: Type) -> Option<Tokens> {
+    match type_ {
+        Type::Int => Some(quote!(runtime::int_body(value)?)),
+        Type::Uint => Some(quote!(runtime::uint_body(value)?)),
+        Type::Float => {
+            let size = match options.float_encoding {
+                FloatEncoding::Single => 4u64,
+                FloatEncoding::Double => 8u64,
+            };
+            Some(quote!(runtime::float_body(value, #size)?))
+        }
+        Type::String => Some(quote!(runtime::string_body(value)?)),
+        Type::Binary => Some(quote!(runtime::binary_body(value)?)),
+        Type::Date => Some(quote!(runtime::date_body(value)?)),
+        _ => None,
+    }
+}
+
+// An expression giving the encoded length of an element with the id `id` and the body `body`.
+fn element_len(id: Tokens, body: Tokens) -> Tokens {
+    quote!(runtime::element_len(#id, #body.len() as u64))
+}
+
+// The `encoded_len` function of a newtype or enum wrapping a primitive of `type_`, which works
+// out lengths the same way as containers' writers do for their children.
+pub fn scalar_encoded_len(options: &CodegenOptions, type_: Type) -> Tokens {
+    let body = primitive_body(options, type_).expect("scalars wrap primitives");
+    let len = element_len(quote!(id), body);
+    quote! {
+        #[doc = "The encoded length of an element with the id `id` holding this value."]
+        pub fn encoded_len(&self, id: u32) -> ::std::io::Result<u64> {
+            use ::ebml_macros::runtime;
+            let value = self;
+            ::std::result::Result::Ok(#len)
+        }
+    }
+}
+
+// A statement skipping `value`, an element of `field`, if `omit_defaults` is set and it encodes
+// the same as the field's default. Only mandatory elements are skipped, since readers don't fill
+// in defaults for optional ones.
+fn skip_default(dtd: &Dtd, options: &CodegenOptions, field: &Field) -> Tokens {
+    let body = encode_body(dtd, options, field);
+    match (options.omit_defaults, field.element.cardinality(), body, field.raw_default()) {
+        (true, Cardinality::ExactlyOne, Some(body), Some(raw)) => quote! {
+            if #body == { let value = &#raw; #body } {
+                continue;
+            }
+        },
+        _ => Tokens::new(),
+    }
+}
+
+// An expression giving the encoded length of `value`, an element of `field`.
+fn value_len(dtd: &Dtd, options: &CodegenOptions, field: &Field) -> Tokens {
+    let id = Ident::new(screaming_snake_case(field.element.name));
+    match checked_body(dtd, options, field) {
+        Some(body) => element_len(quote!(ids::#id), body),
+        None => quote!(value.sizes(sizes, options)?),
+    }
+}
+
+// An expression writing `value`, an element of `field`, and giving the number of bytes written.
+fn write_value(dtd: &Dtd, options: &CodegenOptions, field: &Field) -> Tokens {
+    let id = Ident::new(screaming_snake_case(field.element.name));
+    match checked_body(dtd, options, field) {
+        Some(body) => quote!(runtime::write_element(w, ids::#id, &#body)?),
+        None => quote!(value.write_sized(w, sizes, options)?),
+    }
+}
+
+// Like `encode_body`, but checking the value against the field's range first according to the
+// writer's `options`. Both passes of a writer use this, so they agree on clamped values.
+fn checked_body(dtd: &Dtd, options: &CodegenOptions, field: &Field) -> Option<Tokens> {
+    let body = encode_body(dtd, options, field)?;
+    let (kind, items) = match dtd.range_of(field.element).and_then(range_items) {
+        Some(range) => range,
+        None => return Some(body),
+    };
+    let raw = match dtd.resolve_type(field.element.type_) {
+        Some(Type::Int) | Some(Type::Date) => quote!(i64),
+        Some(Type::Uint) => quote!(u64),
+        Some(Type::Float) => quote!(f64),
+        Some(Type::String) => quote!(::std::string::String),
+        _ => quote!(::std::vec::Vec<u8>),
+    };
+    let check = Ident::new(format!("runtime::write_{}range", kind));
+    let child_name = field.element.name;
+    let policy = options.write_ranges();
+    Some(quote!({
+        let value = &#check(
+            options,
+            #policy,
+            #child_name,
+            runtime::to_raw::<#raw, _>(value)?,
+            &[#(#items),*],
+        )?;
+        #body
+    }))
+}
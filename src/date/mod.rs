@@ -0,0 +1,299 @@
+//! The dates of `date` defaults, ranges and header values: `chrono::NaiveDateTime` with the
+//! `chrono` feature, or else `EbmlDate`, with the calendar worked out here.
+
+#[cfg(test)]
+mod tests;
+
+use std::fmt;
+
+#[cfg(feature = "chrono")]
+use chrono::{Datelike, Duration, NaiveDate, NaiveDateTime, NaiveTime, Timelike};
+
+/// The type dates in an EDTD are parsed as: `chrono::NaiveDateTime`, or `EbmlDate` without the
+/// `chrono` feature.
+#[cfg(feature = "chrono")]
+pub type Date = NaiveDateTime;
+
+/// The type dates in an EDTD are parsed as: `chrono::NaiveDateTime`, or `EbmlDate` without the
+/// `chrono` feature.
+#[cfg(not(feature = "chrono"))]
+pub type Date = EbmlDate;
+
+const NANOS_PER_SEC: i64 = 1_000_000_000;
+const SECS_PER_DAY: i64 = 86_400;
+// The days from the start of 1970, which the calendar math counts from, to the EBML epoch.
+const EPOCH_DAYS: i64 = 11_323;
+
+/// A date as EBML stores it: the nanoseconds since the start of 2001, ignoring leap seconds.
+///
+/// This is what dates are parsed as without the `chrono` feature. It covers about 292 years either
+/// side of 2001, so an EDTD with a date outside of that can only be parsed with `chrono`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct EbmlDate(pub i64);
+
+impl EbmlDate {
+    /// The date `date` is, if it's within range.
+    #[cfg(feature = "chrono")]
+    pub fn from_naive(date: NaiveDateTime) -> Option<Self> {
+        date.signed_duration_since(epoch()).num_nanoseconds().map(EbmlDate)
+    }
+
+    /// This date as a `chrono::NaiveDateTime`, which every `EbmlDate` can be.
+    #[cfg(feature = "chrono")]
+    pub fn to_naive(self) -> NaiveDateTime {
+        epoch() + Duration::nanoseconds(self.0)
+    }
+
+    fn from_fields(fields: &Fields) -> Option<Self> {
+        let days = days_from_civil(fields.year, fields.month, fields.day)? - EPOCH_DAYS;
+        let seconds = i64::from(fields.hour * 3600 + fields.minute * 60 + fields.second);
+        days.checked_mul(SECS_PER_DAY)?
+            .checked_add(seconds)?
+            .checked_mul(NANOS_PER_SEC)?
+            .checked_add(i64::from(fields.nanosecond))
+            .map(EbmlDate)
+    }
+
+    fn fields(self) -> Fields {
+        let seconds = self.0.div_euclid(NANOS_PER_SEC);
+        let (days, second) = (seconds.div_euclid(SECS_PER_DAY), seconds.rem_euclid(SECS_PER_DAY));
+        let (year, month, day) = civil_from_days(days + EPOCH_DAYS);
+        let second = second as u32;
+        Fields {
+            year,
+            month,
+            day,
+            hour: second / 3600,
+            minute: second / 60 % 60,
+            second: second % 60,
+            nanosecond: self.0.rem_euclid(NANOS_PER_SEC) as u32,
+        }
+    }
+}
+
+// Written like `20010101T00:00:00`, as in an EDTD.
+impl fmt::Display for EbmlDate {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.fields().display(""))
+    }
+}
+
+// The parts of a date as it's written.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Fields {
+    pub year: i64,
+    pub month: u32,
+    pub day: u32,
+    pub hour: u32,
+    pub minute: u32,
+    pub second: u32,
+    pub nanosecond: u32,
+}
+
+impl Fields {
+    // Reads a date like `20010101T00:00:00`, with any fraction of a second after it. The fields
+    // aren't checked to be in range. Digits of the fraction past nanoseconds are ignored.
+    fn parse(text: &str) -> Option<Fields> {
+        let field = |start: usize, end: usize| text.get(start..end)?.parse::<u32>().ok();
+        if text.get(8..9)? != "T" || text.get(11..12)? != ":" || text.get(14..15)? != ":" {
+            return None;
+        }
+        let nanosecond = match text.get(17..)? {
+            "" => 0,
+            fraction => {
+                let digits = fraction.strip_prefix('.')?;
+                if digits.is_empty() || !digits.bytes().all(|b| b.is_ascii_digit()) {
+                    return None;
+                }
+                let digits = &digits[..digits.len().min(9)];
+                digits.parse::<u32>().ok()? * 10u32.pow(9 - digits.len() as u32)
+            }
+        };
+        Some(Fields {
+            year: i64::from(field(0, 4)?),
+            month: field(4, 6)?,
+            day: field(6, 8)?,
+            hour: field(9, 11)?,
+            minute: field(12, 14)?,
+            second: field(15, 17)?,
+            nanosecond,
+        })
+    }
+
+    // Writes the date like `20010101T00:00:00`, with `separator` between the year, month and day.
+    // Any fraction of a second is written with three, six or nine digits, as few as it needs. Years
+    // outside of 0 to 9999 are signed.
+    pub fn display(self, separator: &'static str) -> impl fmt::Display {
+        struct Display(Fields, &'static str);
+        impl fmt::Display for Display {
+            fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                let Display(fields, separator) = *self;
+                if (0..10_000).contains(&fields.year) {
+                    write!(f, "{:04}", fields.year)?;
+                } else {
+                    write!(f, "{:+05}", fields.year)?;
+                }
+                write!(f, "{}{:02}{}{:02}T{:02}:{:02}:{:02}", separator, fields.month, separator,
+                       fields.day, fields.hour, fields.minute, fields.second)?;
+                match fields.nanosecond {
+                    0 => Ok(()),
+                    nanos if nanos % 1_000_000 == 0 => write!(f, ".{:03}", nanos / 1_000_000),
+                    nanos if nanos % 1_000 == 0 => write!(f, ".{:06}", nanos / 1_000),
+                    nanos => write!(f, ".{:09}", nanos),
+                }
+            }
+        }
+        Display(self, separator)
+    }
+}
+
+// The days from the start of 1970 to a date in the proleptic Gregorian calendar, if it's a real
+// date. This and `civil_from_days` are Howard Hinnant's algorithms, which count years from March
+// so that the leap day comes last.
+fn days_from_civil(year: i64, month: u32, day: u32) -> Option<i64> {
+    if !(1..=12).contains(&month) || day < 1 || day > days_in_month(year, month) {
+        return None;
+    }
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let month_from_march = i64::from((month + 9) % 12);
+    let day_of_year = (153 * month_from_march + 2) / 5 + i64::from(day) - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    Some(era * 146_097 + day_of_era - 719_468)
+}
+
+// The year, month and day of the date a number of days from the start of 1970.
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let days = days + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days - era * 146_097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_from_march = (5 * day_of_year + 2) / 153;
+    let day = (day_of_year - (153 * month_from_march + 2) / 5 + 1) as u32;
+    let month = ((month_from_march + 2) % 12 + 1) as u32;
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
+
+fn days_in_month(year: i64, month: u32) -> u32 {
+    match month {
+        2 if year % 4 == 0 && (year % 100 != 0 || year % 400 == 0) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+// A date written like `20010101T00:00:00`, with any fraction of a second after it.
+pub fn timestamp(text: &str) -> Option<Date> {
+    from_fields(&Fields::parse(text)?)
+}
+
+// A date written like `2001-01-01T00:00:00`, as in JSON.
+#[cfg(feature = "chrono")]
+pub fn dashed_timestamp(text: &str) -> Option<Date> {
+    NaiveDateTime::parse_from_str(text, "%Y-%m-%dT%H:%M:%S%.f").ok()
+}
+
+// A date written like `2001-01-01T00:00:00`, as in JSON. Without chrono, the year must have four
+// digits, as it does in an EDTD.
+#[cfg(not(feature = "chrono"))]
+pub fn dashed_timestamp(text: &str) -> Option<Date> {
+    if text.get(4..5)? != "-" || text.get(7..8)? != "-" {
+        return None;
+    }
+    timestamp(&format!("{}{}{}", &text[..4], &text[5..7], &text[8..]))
+}
+
+#[cfg(feature = "chrono")]
+fn from_fields(fields: &Fields) -> Option<Date> {
+    let year = i32::try_from(fields.year).ok()?;
+    let date = NaiveDate::from_ymd_opt(year, fields.month, fields.day)?;
+    let time = NaiveTime::from_hms_nano_opt(fields.hour, fields.minute, fields.second,
+                                            fields.nanosecond)?;
+    Some(NaiveDateTime::new(date, time))
+}
+
+#[cfg(not(feature = "chrono"))]
+fn from_fields(fields: &Fields) -> Option<Date> {
+    if fields.hour > 23 || fields.minute > 59 || fields.second > 59 {
+        return None;
+    }
+    EbmlDate::from_fields(fields)
+}
+
+// The date with the given parts, which must be one, for tests which don't care what type dates
+// are.
+#[cfg(test)]
+pub fn from_parts(year: i64, month: u32, day: u32, hour: u32, minute: u32, second: u32,
+                  nanosecond: u32)
+    -> Date
+{
+    from_fields(&Fields { year, month, day, hour, minute, second, nanosecond }).unwrap()
+}
+
+// The parts of a date, to write it with.
+#[cfg(feature = "chrono")]
+pub fn fields(date: &Date) -> Fields {
+    Fields {
+        year: i64::from(date.year()),
+        month: date.month(),
+        day: date.day(),
+        hour: date.hour(),
+        minute: date.minute(),
+        second: date.second(),
+        nanosecond: date.nanosecond(),
+    }
+}
+
+// The parts of a date, to write it with.
+#[cfg(not(feature = "chrono"))]
+pub fn fields(date: &Date) -> Fields {
+    date.fields()
+}
+
+// The start of 2001, which numerical dates count nanoseconds from.
+#[cfg(feature = "chrono")]
+pub fn epoch() -> Date {
+    let date = NaiveDate::from_ymd_opt(2001, 1, 1).expect("2001-01-01 is a date");
+    let time = NaiveTime::from_hms_opt(0, 0, 0).expect("midnight is a time");
+    NaiveDateTime::new(date, time)
+}
+
+// The start of 2001, which numerical dates count nanoseconds from.
+#[cfg(not(feature = "chrono"))]
+pub fn epoch() -> Date {
+    EbmlDate(0)
+}
+
+// The date some nanoseconds after the EBML epoch. Every `i64` is one.
+#[cfg(feature = "chrono")]
+pub fn from_nanos(nanos: i64) -> Date {
+    EbmlDate(nanos).to_naive()
+}
+
+// The date some nanoseconds after the EBML epoch. Every `i64` is one.
+#[cfg(not(feature = "chrono"))]
+pub fn from_nanos(nanos: i64) -> Date {
+    EbmlDate(nanos)
+}
+
+// The nanoseconds since the EBML epoch of a date, if there aren't too many for an `i64`.
+#[cfg(feature = "chrono")]
+pub fn nanos(date: &Date) -> Option<i64> {
+    EbmlDate::from_naive(*date).map(|date| date.0)
+}
+
+// The nanoseconds since the EBML epoch of a date, if there aren't too many for an `i64`.
+#[cfg(not(feature = "chrono"))]
+pub fn nanos(date: &Date) -> Option<i64> {
+    Some(date.0)
+}
+
+// Like `nanos`, but the closest `i64` to a date which is too far from the epoch.
+pub fn saturating_nanos(date: &Date) -> i64 {
+    nanos(date).unwrap_or(if *date < epoch() { i64::MIN } else { i64::MAX })
+}
@@ -0,0 +1,82 @@
+use super::*;
+
+// The dates written in the date fixtures, and their nanoseconds since the EBML epoch.
+const FIXTURE_DATES: &[(&str, i64)] = &[
+    ("20170101T00:00:00", 504_921_600_000_000_000),
+    ("19020102T00:00:24", -3_124_137_576_000_000_000),
+    ("19950418T04:20:00.420", -180_128_399_580_000_000),
+    ("20010101T00:00:00", 0),
+    ("20170101T19:20:45.245", 504_991_245_245_000_000),
+    ("20200101T00:00:00", 599_529_600_000_000_000),
+    ("20140203T00:12:14.5", 413_079_134_500_000_000),
+    ("20000101T00:00:00", -31_622_400_000_000_000),
+    ("17760604T09:21:55.356", -7_086_926_284_644_000_000),
+];
+
+#[test]
+fn test_fixture_dates() {
+    for &(text, nanos) in FIXTURE_DATES {
+        let date = timestamp(text).unwrap();
+        assert_eq!(super::nanos(&date), Some(nanos), "{}", text);
+        assert_eq!(EbmlDate::from_fields(&Fields::parse(text).unwrap()), Some(EbmlDate(nanos)));
+    }
+}
+
+#[test]
+fn test_invalid_timestamps() {
+    for text in &["20171301T00:13:13", "20170229T00:00:00", "20171232T00:00:00",
+                  "20171201T30:30:30", "20171201T00:60:00", "20040000T:25:61:61",
+                  "20170101T00:00:00Z", "2017010T00:00:00", "20170101T00:00:0",
+                  "20170101T00:00:00.", "20170101T00:00:00.5e3", "20170101T00:00:00.-5"] {
+        assert_eq!(timestamp(text), None, "{}", text);
+    }
+    assert!(timestamp("20000229T00:00:00").is_some());
+}
+
+#[test]
+fn test_fractions() {
+    // Fractions are read exactly, and digits past nanoseconds are ignored.
+    for &(fraction, nanosecond) in &[(".5", 500_000_000), (".000000001", 1),
+                                     (".123456789", 123_456_789),
+                                     (".99999999999999999999", 999_999_999)] {
+        let fields = Fields::parse(&format!("20170101T00:00:00{}", fraction)).unwrap();
+        assert_eq!(fields.nanosecond, nanosecond, "{}", fraction);
+    }
+}
+
+#[test]
+fn test_ebml_date() {
+    for &(text, nanos) in FIXTURE_DATES {
+        let written = EbmlDate(nanos).to_string();
+        assert_eq!(timestamp(&written).and_then(|date| super::nanos(&date)), Some(nanos));
+        assert!(text.starts_with(&written[..17]));
+    }
+    assert_eq!(EbmlDate(-1).to_string(), "20001231T23:59:59.999999999");
+    assert_eq!(EbmlDate(1_500_000).to_string(), "20010101T00:00:00.001500");
+    assert_eq!(EbmlDate(i64::MAX).to_string(), "22930411T23:47:16.854775807");
+    assert_eq!(EbmlDate(i64::MIN).to_string(), "17080922T00:12:43.145224192");
+    // Past what an `EbmlDate` covers.
+    assert_eq!(EbmlDate::from_fields(&Fields::parse("12341225T14:15:32").unwrap()), None);
+}
+
+// The calendar math agrees with chrono's, and so does how dates are written.
+#[cfg(feature = "chrono")]
+#[test]
+fn test_matches_chrono() {
+    let mut date = EbmlDate(i64::MIN);
+    while let Some(next) = date.0.checked_add(86_399_999_999_993) {
+        let naive = date.to_naive();
+        assert_eq!(date.fields(), fields(&naive));
+        assert_eq!(EbmlDate::from_naive(naive), Some(date));
+        assert_eq!(EbmlDate::from_fields(&fields(&naive)), Some(date));
+        assert_eq!(date.to_string(), naive.format("%Y%m%dT%H:%M:%S%.f").to_string());
+        date = EbmlDate(next);
+    }
+
+    for text in &["00000101T00:00:00", "99991231T23:59:59.5", "12341225T14:15:32.42"] {
+        let naive = timestamp(text).unwrap();
+        assert_eq!(fields(&naive).display("-").to_string(),
+                   naive.format("%Y-%m-%dT%H:%M:%S%.f").to_string());
+        assert_eq!(dashed_timestamp(&fields(&naive).display("-").to_string()), Some(naive));
+    }
+}
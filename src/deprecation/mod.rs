@@ -0,0 +1,135 @@
+//! Reads `@deprecated` tags in the comments before element and type definitions.
+//!
+//! A tag starts a line of a comment, after any `*` or `/` characters, and is written
+//!
+//! ```text
+//! @deprecated [since <version>][:] [reason]
+//! ```
+//!
+//! The reason runs to the end of the line, and on over the lines after it up to a blank line or
+//! another `@` tag.
+
+use std::fmt;
+use std::str::FromStr;
+
+use nom::bytes::complete::tag;
+use nom::character::complete::{char, digit1, satisfy, space0, space1};
+use nom::combinator::{map_res, not, opt, rest};
+use nom::sequence::{preceded, tuple};
+use nom::IResult;
+
+use {Dtd, Element, NewType};
+
+/// An element marked deprecated by an `@deprecated` tag in the comments before its definition.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Deprecation {
+    /// The DocTypeVersion the element was deprecated in, from `since <version>`.
+    pub since: Option<u64>,
+    /// Why the element is deprecated, or what to use instead, with each run of whitespace
+    /// replaced by a space.
+    pub reason: Option<String>,
+}
+
+impl fmt::Display for Deprecation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("deprecated")?;
+        if let Some(since) = self.since {
+            write!(f, " since version {}", since)?;
+        }
+        match self.reason {
+            Some(ref reason) => write!(f, ": {}", reason),
+            None => Ok(()),
+        }
+    }
+}
+
+impl<'a> Dtd<'a> {
+    /// The deprecation of the element named `name`, or of the type named `name` if there's no
+    /// such element, if it's defined and the comments before its definition have an
+    /// `@deprecated` tag.
+    pub fn deprecation(&self, name: &str) -> Option<Deprecation> {
+        match self.all_elements().into_iter().find(|element| element.name == name) {
+            Some(element) => element.deprecation(),
+            None => self.new_type(name)?.deprecation(),
+        }
+    }
+}
+
+impl<'a> Element<'a> {
+    // The `@deprecated` tag in the comments before the definition, if there is one.
+    pub fn deprecation(&self) -> Option<Deprecation> {
+        deprecation(&self.comments)
+    }
+}
+
+impl<'a> NewType<'a> {
+    // The `@deprecated` tag in the comments before the definition, if there is one.
+    pub fn deprecation(&self) -> Option<Deprecation> {
+        deprecation(self.comments())
+    }
+}
+
+// The first `@deprecated` tag in `comments`, if there is one.
+fn deprecation(comments: &[&str]) -> Option<Deprecation> {
+    let lines = comment_lines(comments);
+    let (start, end) = find_tag(&lines)?;
+    let (_, (since, first)) = header(lines[start]).ok()?;
+    let words: Vec<_> = Some(first).into_iter()
+        .chain(lines[start + 1..end].iter().cloned())
+        .flat_map(str::split_whitespace)
+        .collect();
+    let reason = if words.is_empty() { None } else { Some(words.join(" ")) };
+    Some(Deprecation { since, reason })
+}
+
+// The lines of `comments`, with the whitespace and `*` or `/` characters around each removed,
+// which cleans up block comments and doc-style `///` comments.
+pub fn comment_lines<'c>(comments: &[&'c str]) -> Vec<&'c str> {
+    comments.iter()
+        .flat_map(|comment| comment.lines())
+        .map(|line| line.trim().trim_start_matches(|c| c == '*' || c == '/').trim())
+        .collect()
+}
+
+// The lines of `comments` as `comment_lines` gives them, without the `@deprecated` tag.
+pub fn untagged_lines<'c>(comments: &[&'c str]) -> Vec<&'c str> {
+    let mut lines = comment_lines(comments);
+    if let Some((start, end)) = find_tag(&lines) {
+        lines.drain(start..end);
+    }
+    lines
+}
+
+// The indices of the first and one past the last of the lines taken up by the first tag.
+fn find_tag(lines: &[&str]) -> Option<(usize, usize)> {
+    let start = lines.iter().position(|line| header(line).is_ok())?;
+    let end = lines[start + 1..].iter()
+        .position(|line| line.is_empty() || line.starts_with('@'))
+        .map_or(lines.len(), |offset| start + 1 + offset);
+    Some((start, end))
+}
+
+// The first line of a tag: the version it gives, and the start of the reason.
+fn header(line: &str) -> IResult<&str, (Option<u64>, &str)> {
+    let (input, _) = tuple((tag("@deprecated"), word_end))(line)?;
+    let (input, since) = opt(preceded(space1, since))(input)?;
+    let (input, _) = opt(preceded(space0, char(':')))(input)?;
+    let (input, reason) = preceded(space0, rest)(input)?;
+    Ok((input, (since, reason.trim())))
+}
+
+// `since <version>`, where the version is a whole word.
+fn since(input: &str) -> IResult<&str, u64> {
+    let (input, _) = tuple((tag("since"), space1))(input)?;
+    let (input, version) = map_res(digit1, u64::from_str)(input)?;
+    let (input, _) = word_end(input)?;
+    Ok((input, version))
+}
+
+// Succeeds, consuming nothing, where a word ends.
+fn word_end(input: &str) -> IResult<&str, ()> {
+    not(satisfy(|c: char| c.is_alphanumeric() || c == '_'))(input)
+}
+
+#[cfg(test)]
+mod tests;
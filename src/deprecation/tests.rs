@@ -0,0 +1,87 @@
+use super::*;
+use parse_dtd;
+
+fn deprecation(comments: &[&'static str]) -> Option<Deprecation> {
+    let mut element = Element::new("Flag", 0x81, ::Type::Uint);
+    element.comments = comments.to_vec();
+    element.deprecation()
+}
+
+fn deprecated(since: Option<u64>, reason: Option<&str>) -> Option<Deprecation> {
+    Some(Deprecation { since, reason: reason.map(str::to_string) })
+}
+
+#[test]
+fn test_header() {
+    assert_eq!(header("@deprecated"), Ok(("", (None, ""))));
+    assert_eq!(header("@deprecated since 3"), Ok(("", (Some(3), ""))));
+    assert_eq!(header("@deprecated since 3: use Other"), Ok(("", (Some(3), "use Other"))));
+    assert_eq!(header("@deprecated: use Other "), Ok(("", (None, "use Other"))));
+    assert_eq!(header("@deprecated use Other"), Ok(("", (None, "use Other"))));
+    // A `since` without a version is part of the reason.
+    assert_eq!(header("@deprecated since then"), Ok(("", (None, "since then"))));
+    assert_eq!(header("@deprecated since 3rd"), Ok(("", (None, "since 3rd"))));
+    assert!(header("@deprecatedness").is_err());
+    assert!(header("deprecated").is_err());
+}
+
+#[test]
+fn test_deprecation() {
+    assert_eq!(deprecation(&[]), None);
+    assert_eq!(deprecation(&[" Not @deprecated. "]), None);
+    assert_eq!(deprecation(&[" @deprecated "]), deprecated(None, None));
+    assert_eq!(deprecation(&[" The flag.", " @deprecated since 2: use   Other."]),
+               deprecated(Some(2), Some("use Other.")));
+
+    // The reason runs on to a blank line or another tag, and leading `*`s are skipped.
+    let comment = "*\n * The flag.\n * @deprecated since 4\n *   Use Other,\n * or nothing.\n *\n \
+                   * More.\n ";
+    assert_eq!(deprecation(&[comment]), deprecated(Some(4), Some("Use Other, or nothing.")));
+    assert_eq!(untagged_lines(&[comment]), ["", "The flag.", "", "More.", ""]);
+    let comment = "/ @deprecated Old.\n/ @since 2";
+    assert_eq!(deprecation(&[comment]), deprecated(None, Some("Old.")));
+    assert_eq!(untagged_lines(&[comment]), ["@since 2"]);
+}
+
+#[test]
+fn test_dtd_deprecation() {
+    let dtd = parse_dtd(b"
+        define elements {
+            Segment := 18538067 container {
+                // @deprecated since 3: Use `Title`.
+                Name := 4d80 string;
+                Title := 7ba9 string;
+            }
+        }
+    ", &Default::default()).unwrap();
+    assert_eq!(dtd.deprecation("Name"), deprecated(Some(3), Some("Use `Title`.")));
+    assert_eq!(dtd.deprecation("Name").unwrap().to_string(),
+               "deprecated since version 3: Use `Title`.");
+    assert_eq!(dtd.deprecation("Title"), None);
+    assert_eq!(dtd.deprecation("Missing"), None);
+}
+
+#[test]
+fn test_type_deprecation() {
+    let dtd = parse_dtd(b"
+        define types {
+            /* @deprecated: Use `uint`. */
+            flag := uint [ range:0..1; ]
+            // A byte.
+            byte := uint [ range:0..255; ]
+        }
+        define elements {
+            // Not deprecated, though its type is.
+            flag := 81 flag;
+        }
+    ", &Default::default()).unwrap();
+    assert_eq!(dtd.new_type("flag").unwrap().deprecation(), deprecated(None, Some("Use `uint`.")));
+    assert_eq!(dtd.new_type("byte").unwrap().deprecation(), None);
+    // Elements are looked for before types.
+    assert_eq!(dtd.deprecation("flag"), None);
+    assert_eq!(dtd.deprecation("byte"), None);
+
+    let dtd = parse_dtd(b"define types { /* @deprecated since 2 */ flag := uint; }",
+                        &Default::default()).unwrap();
+    assert_eq!(dtd.deprecation("flag"), deprecated(Some(2), None));
+}
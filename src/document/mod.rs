@@ -0,0 +1,400 @@
+//! Checks EBML documents against a parsed EDTD at runtime, without generating any code for it.
+
+#[cfg(test)]
+mod tests;
+
+use std::fmt;
+use std::io::{self, Read};
+
+use runtime::{binary_in_range, date_in_range, decode_date, decode_float, decode_int,
+              decode_string, decode_uint, in_range, read_binary, read_encoded_id,
+              read_size_or_unknown, skip, string_in_range, RangeError, ReadError, ReadErrorKind,
+              ReadLimits, CRC_32, EBML, VOID};
+use {Cardinality, Dtd, Element, Level, Property, Severity, Type};
+
+/// What `Dtd::check_document` found wrong with a document.
+///
+/// A strict check wants no violations at all (`is_valid`). A lint can let warnings through
+/// (`has_errors`), as unknown elements are the only warnings, and readers skip them.
+#[derive(Debug, Default)]
+pub struct DocumentReport {
+    /// Every violation found, in the order they were found. If the document couldn't be read to
+    /// its end, the last is `ViolationKind::Unreadable`.
+    pub violations: Vec<DocumentViolation>,
+}
+
+impl DocumentReport {
+    /// True if nothing at all was found.
+    pub fn is_valid(&self) -> bool {
+        self.violations.is_empty()
+    }
+
+    /// True if anything worse than a warning was found.
+    pub fn has_errors(&self) -> bool {
+        self.violations.iter().any(|violation| violation.severity() == Severity::Error)
+    }
+}
+
+impl fmt::Display for DocumentReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for violation in &self.violations {
+            writeln!(f, "{}", violation)?;
+        }
+        Ok(())
+    }
+}
+
+/// One thing wrong with a document, and where it is.
+#[derive(Debug)]
+pub struct DocumentViolation {
+    /// What's wrong.
+    pub kind: ViolationKind,
+    /// The path of the element it's about, like `\Segment\Info\Title`. Unknown elements are
+    /// named by their encoded id, like `0x4DBB`, and the document itself is `\`.
+    pub path: String,
+    /// The byte offset of the start of the element's header in the document, or 0 for the
+    /// document itself.
+    pub offset: u64,
+}
+
+impl DocumentViolation {
+    /// How serious the violation is: a warning for `ViolationKind::UnknownElement`, and an error
+    /// for anything else.
+    pub fn severity(&self) -> Severity {
+        match self.kind {
+            ViolationKind::UnknownElement(_) => Severity::Warning,
+            _ => Severity::Error,
+        }
+    }
+}
+
+impl fmt::Display for DocumentViolation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: {} at offset {}: {}", self.severity(), self.path, self.offset, self.kind)
+    }
+}
+
+/// The ways a document can break the rules of its EDTD.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum ViolationKind {
+    /// An element the EDTD doesn't define. Holds its encoded id. It's skipped.
+    UnknownElement(u32),
+    /// An element the EDTD defines, somewhere it can't be. It's skipped.
+    MisplacedElement,
+    /// An element's size isn't one of those its `size:` allows.
+    SizeNotAllowed {
+        /// The size of the element's body.
+        size: u64,
+        /// The sizes it could have had, as written in an EDTD.
+        allowed: String,
+    },
+    /// An element has an unknown size without `unknownsizeallowed:yes`.
+    UnknownSizeNotAllowed,
+    /// An element's value is outside its `range:`, or its type's.
+    OutOfRange(RangeError),
+    /// An element's value can't be decoded as its type, like a string which isn't UTF-8.
+    InvalidValue(ReadError),
+    /// A container has a child a number of times its `card:` doesn't allow.
+    Cardinality {
+        /// The name of the child.
+        element: String,
+        /// How many times it occurs.
+        count: usize,
+        /// How many times it may.
+        allowed: Cardinality,
+    },
+    /// A child of an `ordered:yes` container comes after a child declared later than it.
+    OutOfOrder,
+    /// The document couldn't be read past this element, so the check stopped there.
+    Unreadable(ReadError),
+}
+
+impl fmt::Display for ViolationKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ViolationKind::UnknownElement(id) => {
+                write!(f, "element {:#X} isn't defined by the EDTD", id)
+            }
+            ViolationKind::MisplacedElement => write!(f, "element can't appear here"),
+            ViolationKind::SizeNotAllowed { size, ref allowed } => {
+                write!(f, "size {} isn't one of those allowed: {}", size, allowed)
+            }
+            ViolationKind::UnknownSizeNotAllowed => write!(f, "element can't have an unknown size"),
+            ViolationKind::OutOfRange(ref err) => write!(f, "{}", err),
+            ViolationKind::InvalidValue(ref err) => write!(f, "{}", err),
+            ViolationKind::Cardinality { ref element, count, ref allowed } => {
+                let allowed = match *allowed {
+                    Cardinality::ZeroOrMany => "any number of times",
+                    Cardinality::ZeroOrOne => "at most once",
+                    Cardinality::ExactlyOne => "exactly once",
+                    Cardinality::OneOrMany => "at least once",
+                };
+                write!(f, "`{}` occurs {} times, but must occur {}", element, count, allowed)
+            }
+            ViolationKind::OutOfOrder => {
+                write!(f, "element comes after one its ordered parent declares later")
+            }
+            ViolationKind::Unreadable(ref err) => write!(f, "can't read past here: {}", err),
+        }
+    }
+}
+
+impl<'a> Dtd<'a> {
+    /// Checks the EBML document read from `reader` against this EDTD, returning everything wrong
+    /// with it. Every element must be one this EDTD allows where it is, with a size its `size:`
+    /// allows, and a value its `range:` (or its type's) allows. Containers must have each child
+    /// as many times as its `card:` allows, and in order if they're `ordered:yes`.
+    ///
+    /// Only this EDTD's own elements are checked against; use `select_doc_type` to check a
+    /// document of another DocType. `Void` and `CRC-32` elements may be anywhere, and the EBML
+    /// header may start the document, unless this EDTD says otherwise. An element with an unknown
+    /// size is taken to end where its parent does. Nesting is bounded by `ReadLimits::default()`,
+    /// and values larger than it allows are skipped unchecked.
+    pub fn check_document<R: Read>(&self, reader: R) -> DocumentReport {
+        let mut checker = Checker {
+            dtd: self,
+            reader: Counted { inner: reader, offset: 0 },
+            limits: ReadLimits::default(),
+            path: Vec::new(),
+            report: DocumentReport::default(),
+        };
+        if let Err((err, offset)) = checker.children(None, None, 0) {
+            checker.violation(ViolationKind::Unreadable(err), offset);
+        }
+        checker.report
+    }
+}
+
+// Counts the bytes read, so violations can say where they are.
+struct Counted<R> {
+    inner: R,
+    offset: u64,
+}
+
+impl<R: Read> Read for Counted<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let len = self.inner.read(buf)?;
+        self.offset += len as u64;
+        Ok(len)
+    }
+}
+
+// A read error, and the offset of the element it stopped the check in.
+type Failure = (ReadError, u64);
+
+struct Checker<'d, 'a: 'd, R> {
+    dtd: &'d Dtd<'a>,
+    reader: Counted<R>,
+    limits: ReadLimits,
+    // The names of the elements being checked, outermost first.
+    path: Vec<String>,
+    report: DocumentReport,
+}
+
+impl<'d, 'a, R: Read> Checker<'d, 'a, R> {
+    // Checks the children of `parent`, which starts at `offset`, or the top level of the
+    // document. They end at the offset `end`, or the end of the document if it's `None`.
+    fn children(&mut self, parent: Option<&'d Element<'a>>, end: Option<u64>, offset: u64)
+        -> Result<(), Failure>
+    {
+        let allowed: Vec<&Element> = match parent {
+            Some(parent) => self.dtd.children_of(parent),
+            None => self.dtd.elements.iter()
+                .map(|element| &element.node)
+                .filter(|element| element.parent.is_none() && element.level.is_none())
+                .collect(),
+        };
+        let ordered = parent.is_some_and(Element::is_ordered);
+        let mut counts = vec![0; allowed.len()];
+        let mut latest = 0;
+        while let Some((id, size, child_offset)) = self.header(end)? {
+            let body_end = match size {
+                Some(size) => {
+                    let body_end = self.reader.offset.saturating_add(size);
+                    if end.is_some_and(|end| body_end > end) {
+                        return Err((ReadError::new(ReadErrorKind::Overrun), child_offset));
+                    }
+                    Some(body_end)
+                }
+                None => end,
+            };
+            let element = match allowed.iter().position(|child| child.id == id) {
+                Some(index) => {
+                    counts[index] += 1;
+                    if ordered && index < latest {
+                        self.path.push(allowed[index].name.to_string());
+                        self.violation(ViolationKind::OutOfOrder, child_offset);
+                        self.path.pop();
+                    }
+                    latest = latest.max(index);
+                    Some(allowed[index])
+                }
+                None => self.by_level(id),
+            };
+
+            let result = match element {
+                Some(element) => {
+                    self.path.push(element.name.to_string());
+                    self.element(element, size, body_end, child_offset)
+                }
+                None => {
+                    self.path.push(format!("{:#X}", id));
+                    self.unknown(id, size, child_offset)
+                }
+            };
+            // On failure, the path is left for `check_document` to report it at.
+            result?;
+            self.path.pop();
+        }
+
+        for (child, count) in allowed.iter().zip(counts) {
+            let cardinality = child.cardinality();
+            if !cardinality.allows(count) {
+                let element = child.name.to_string();
+                let kind = ViolationKind::Cardinality { element, count, allowed: cardinality };
+                self.violation(kind, offset);
+            }
+        }
+        Ok(())
+    }
+
+    // The next element header, as its encoded id, size, and the offset it starts at. `None` at
+    // `end`, or at the end of the document if that's `None`.
+    fn header(&mut self, end: Option<u64>) -> Result<Option<(u32, Option<u64>, u64)>, Failure> {
+        let offset = self.reader.offset;
+        if end.is_some_and(|end| offset >= end) {
+            return Ok(None);
+        }
+        let mut first = [0];
+        if self.reader.read(&mut first).map_err(|err| (err.into(), offset))? == 0 {
+            return match end {
+                Some(_) => Err((ReadError::new(ReadErrorKind::UnexpectedEof), offset)),
+                None => Ok(None),
+            };
+        }
+        let r = &mut (&first[..]).chain(&mut self.reader);
+        let (id, _) = read_encoded_id(r).map_err(|err| (err, offset))?;
+        let (size, _) = read_size_or_unknown(r).map_err(|err| (err, offset))?;
+        Ok(Some((id, size, offset)))
+    }
+
+    // An element which isn't a child its parent declares, but which may be at this depth
+    // because of its `level:`.
+    fn by_level(&self, id: u32) -> Option<&'d Element<'a>> {
+        let depth = self.path.len() as u64;
+        self.dtd.all_elements().into_iter().find(|element| {
+            element.id == id && match element.level {
+                Some(Level::Open { start }) => depth >= start,
+                Some(Level::Bounded { start, end }) => start <= depth && depth <= end,
+                None => false,
+            }
+        })
+    }
+
+    // Checks an element the EDTD allows here, whose body ends at `end`.
+    fn element(&mut self, element: &'d Element<'a>, size: Option<u64>, end: Option<u64>,
+               offset: u64)
+        -> Result<(), Failure>
+    {
+        if size.is_none() && !element.allows_unknown_size() {
+            self.violation(ViolationKind::UnknownSizeNotAllowed, offset);
+        }
+        let type_ = self.dtd.resolve_type(element.type_);
+        if type_ == Some(Type::Container) {
+            if self.path.len() > self.limits.max_depth {
+                let err = ReadError::new(ReadErrorKind::TooDeep(self.limits.max_depth));
+                return Err((err, offset));
+            }
+            return self.children(Some(element), end, offset);
+        }
+
+        let size = size.ok_or_else(|| (ReadError::new(ReadErrorKind::UnknownSize), offset))?;
+        if let Some(ref sizes) = element.size {
+            if !sizes.matches(size) {
+                let kind = ViolationKind::SizeNotAllowed { size, allowed: sizes.to_string() };
+                self.violation(kind, offset);
+            }
+        }
+        let range = self.dtd.range_of(element);
+        let unchecked = match type_ {
+            Some(Type::Binary) => range.is_none(),
+            Some(_) => false,
+            None => true,
+        };
+        if unchecked || size > self.limits.max_element_size {
+            return skip(&mut self.reader, size).map_err(|err| (err, offset));
+        }
+        let body = read_binary(&mut self.reader, size).map_err(|err| (err, offset))?;
+        if let Err(kind) = check_value(type_, body, range) {
+            self.violation(kind, offset);
+        }
+        Ok(())
+    }
+
+    // Skips an element the EDTD doesn't allow here, noting it unless it's one any document may
+    // have.
+    fn unknown(&mut self, id: u32, size: Option<u64>, offset: u64) -> Result<(), Failure> {
+        let global = id == VOID || id == CRC_32 || (id == EBML && self.path.len() == 1);
+        if !global {
+            let defined = self.dtd.all_elements().iter().any(|element| element.id == id);
+            let kind = if defined {
+                ViolationKind::MisplacedElement
+            } else {
+                ViolationKind::UnknownElement(id)
+            };
+            self.violation(kind, offset);
+        }
+        let size = size.ok_or_else(|| (ReadError::new(ReadErrorKind::UnknownSize), offset))?;
+        skip(&mut self.reader, size).map_err(|err| (err, offset))
+    }
+
+    fn violation(&mut self, kind: ViolationKind, offset: u64) {
+        let path = format!("\\{}", self.path.join("\\"));
+        self.report.violations.push(DocumentViolation { kind, path, offset });
+    }
+}
+
+// Decodes the body of a primitive element of type `type_`, and checks it's in `range`.
+fn check_value(type_: Option<Type>, body: Vec<u8>, range: Option<Property>)
+    -> Result<(), ViolationKind>
+{
+    let invalid = ViolationKind::InvalidValue;
+    match (type_, range) {
+        (Some(Type::Int), range) => {
+            let value = decode_int(&body).map_err(invalid)?;
+            if let Some(Property::IntRange(range)) = range {
+                in_range(&value, &range).map_err(ViolationKind::OutOfRange)?;
+            }
+        }
+        (Some(Type::Uint), range) => {
+            let value = decode_uint(&body).map_err(invalid)?;
+            if let Some(Property::UintRange(range)) = range {
+                in_range(&value, &range).map_err(ViolationKind::OutOfRange)?;
+            }
+        }
+        (Some(Type::Float), range) => {
+            let value = decode_float(&body).map_err(invalid)?;
+            if let Some(Property::FloatRange(range)) = range {
+                in_range(&value, &range).map_err(ViolationKind::OutOfRange)?;
+            }
+        }
+        (Some(Type::Date), range) => {
+            let value = decode_date(&body).map_err(invalid)?;
+            if let Some(Property::DateRange(range)) = range {
+                date_in_range(value, &range).map_err(ViolationKind::OutOfRange)?;
+            }
+        }
+        (Some(Type::String), range) => {
+            let value = decode_string(body).map_err(invalid)?;
+            if let Some(Property::StringRange(range)) = range {
+                string_in_range(&value, &range).map_err(ViolationKind::OutOfRange)?;
+            }
+        }
+        (Some(Type::Binary), Some(Property::BinaryRange(range))) => {
+            binary_in_range(&body, &range).map_err(ViolationKind::OutOfRange)?;
+        }
+        _ => {}
+    }
+    Ok(())
+}
@@ -0,0 +1,167 @@
+use super::*;
+use fixtures::element;
+use {parse_dtd, ParseOptions};
+
+const DTD: &str = r#"
+declare header {
+    DocType := "test";
+}
+define types {
+    bool := uint [ range:0..1; ]
+}
+define elements {
+    Segment := 18538067 container [ card:1; ] {
+        Info := 1549a966 container [ card:1; ordered:yes; ] {
+            Title := 7ba9 string [ card:1; range:32..126; ]
+            Flag := 9c bool;
+            Duration := 4489 uint [ range:1..100; size:1..2; ]
+        }
+    }
+}
+"#;
+
+// An EBML header, and a `Segment` holding an `Info` with `children`. The header starts at 0, the
+// segment at 5, the info at 10, and its first child at 15.
+fn document(children: &[Vec<u8>]) -> Vec<u8> {
+    let info = element(&[0x15, 0x49, 0xA9, 0x66], &children.concat());
+    let mut bytes = element(&[0x1A, 0x45, 0xDF, 0xA3], &[]);
+    bytes.extend(element(&[0x18, 0x53, 0x80, 0x67], &info));
+    bytes
+}
+
+fn title() -> Vec<u8> {
+    element(&[0x7B, 0xA9], b"Hi")
+}
+
+fn flag(value: u8) -> Vec<u8> {
+    element(&[0x9C], &[value])
+}
+
+fn check(bytes: &[u8]) -> DocumentReport {
+    let dtd = parse_dtd(DTD, &ParseOptions::default()).unwrap();
+    dtd.check_document(bytes)
+}
+
+// The paths, offsets and kinds of the violations in a report, with the kinds as text.
+fn violations(report: &DocumentReport) -> Vec<(&str, u64, String)> {
+    report.violations.iter()
+        .map(|violation| (&violation.path[..], violation.offset, violation.kind.to_string()))
+        .collect()
+}
+
+#[test]
+fn test_valid() {
+    let void = element(&[0xEC], &[0; 3]);
+    let report = check(&document(&[title(), flag(1), void, element(&[0x44, 0x89], &[50])]));
+    assert!(report.is_valid(), "{}", report);
+    assert!(!report.has_errors());
+}
+
+#[test]
+fn test_unknown_element() {
+    let report = check(&document(&[title(), element(&[0x4D, 0xBB], &[1, 2]), flag(0)]));
+    assert_eq!(violations(&report), vec![
+        ("\\Segment\\Info\\0x4DBB", 20, "element 0x4DBB isn't defined by the EDTD".to_string()),
+    ]);
+    match report.violations[0].kind {
+        ViolationKind::UnknownElement(0x4DBB) => {}
+        ref kind => panic!("{:?}", kind),
+    }
+    assert!(!report.is_valid());
+    assert!(!report.has_errors());
+}
+
+#[test]
+fn test_misplaced_element() {
+    let report = check(&document(&[title(), element(&[0x15, 0x49, 0xA9, 0x66], &[])]));
+    assert_eq!(violations(&report), vec![
+        ("\\Segment\\Info\\0x1549A966", 20, "element can't appear here".to_string()),
+    ]);
+    assert!(report.has_errors());
+}
+
+#[test]
+fn test_out_of_range() {
+    let report = check(&document(&[title(), flag(2)]));
+    assert_eq!(violations(&report), vec![
+        ("\\Segment\\Info\\Flag", 20, "value 2 is outside the range 0..1".to_string()),
+    ]);
+
+    let report = check(&document(&[element(&[0x7B, 0xA9], b"\tab")]));
+    match report.violations[..] {
+        [DocumentViolation { kind: ViolationKind::OutOfRange(ref err), offset: 15, .. }] => {
+            assert_eq!(err.value, "\"\\tab\"");
+        }
+        ref violations => panic!("{:?}", violations),
+    }
+}
+
+#[test]
+fn test_invalid_value() {
+    let report = check(&document(&[element(&[0x7B, 0xA9], &[0xFF])]));
+    match report.violations[..] {
+        [DocumentViolation { kind: ViolationKind::InvalidValue(_), offset: 15, .. }] => {}
+        ref violations => panic!("{:?}", violations),
+    }
+}
+
+#[test]
+fn test_size_not_allowed() {
+    let report = check(&document(&[title(), element(&[0x44, 0x89], &[0, 0, 50])]));
+    assert_eq!(violations(&report), vec![
+        ("\\Segment\\Info\\Duration", 20,
+         "size 3 isn't one of those allowed: 1..2".to_string()),
+    ]);
+}
+
+#[test]
+fn test_missing_element() {
+    let report = check(&document(&[flag(1)]));
+    assert_eq!(violations(&report), vec![
+        ("\\Segment\\Info", 10, "`Title` occurs 0 times, but must occur exactly once".to_string()),
+    ]);
+
+    let report = check(&element(&[0x1A, 0x45, 0xDF, 0xA3], &[]));
+    assert_eq!(violations(&report), vec![
+        ("\\", 0, "`Segment` occurs 0 times, but must occur exactly once".to_string()),
+    ]);
+}
+
+#[test]
+fn test_out_of_order() {
+    let report = check(&document(&[flag(1), title()]));
+    assert_eq!(violations(&report), vec![
+        ("\\Segment\\Info\\Title", 18,
+         "element comes after one its ordered parent declares later".to_string()),
+    ]);
+}
+
+#[test]
+fn test_unreadable() {
+    let bytes = document(&[title(), flag(1)]);
+    let report = check(&bytes[..bytes.len() - 1]);
+    match report.violations[..] {
+        [DocumentViolation { kind: ViolationKind::Unreadable(ref err), ref path, offset }] => {
+            match *err.kind() {
+                ReadErrorKind::UnexpectedEof => {}
+                ref kind => panic!("{:?}", kind),
+            }
+            assert_eq!((&path[..], offset), ("\\Segment\\Info\\Flag", 20));
+        }
+        ref violations => panic!("{:?}", violations),
+    }
+
+    // A child which doesn't fit in its parent.
+    let mut bytes = document(&[title()]);
+    bytes[17] = 0x83;
+    let report = check(&bytes);
+    match report.violations[..] {
+        [DocumentViolation { kind: ViolationKind::Unreadable(ref err), offset: 15, .. }] => {
+            match *err.kind() {
+                ReadErrorKind::Overrun => {}
+                ref kind => panic!("{:?}", kind),
+            }
+        }
+        ref violations => panic!("{:?}", violations),
+    }
+}
@@ -0,0 +1,107 @@
+//! Draws the element hierarchy of a `Dtd` as a Graphviz digraph.
+
+use std::collections::HashSet;
+use std::fmt::Write;
+
+use {Cardinality, Dtd, Element};
+
+/// Options controlling what `Dtd::to_dot` draws.
+#[derive(Debug, Clone, Default)]
+pub struct DotOptions {
+    /// How many levels of children are drawn below the elements at the top of the hierarchy.
+    /// `None` (the default) draws every level.
+    pub max_depth: Option<usize>,
+    /// The names of elements which are left out, along with everything only reachable through
+    /// them. Defaults to none.
+    pub exclude: Vec<String>,
+}
+
+impl<'a> Dtd<'a> {
+    /// Draws the element hierarchy as a Graphviz digraph, in the DOT language.
+    ///
+    /// Each element is a box labeled with its name, id and type. Containers have an edge to each
+    /// of their children, labeled with the child's cardinality: solid for children nested inside
+    /// the container's definition, and dashed for those which name it with `parent:`. Edges to
+    /// mandatory children are bold. The hierarchy starts from the top-level elements which don't
+    /// name a parent, and every element is drawn once, however many parents it has.
+    pub fn to_dot(&self, options: &DotOptions) -> String {
+        let mut graph = Graph { dtd: self, options, drawn: HashSet::new(), nodes: String::new(),
+                                edges: String::new() };
+        for element in &self.elements {
+            if element.parent.is_none() {
+                graph.draw(element, 0);
+            }
+        }
+
+        let name = self.doc_type().unwrap_or("EDTD");
+        format!("digraph {} {{\n    node [shape=box];\n{}{}}}\n", quote(name), graph.nodes,
+                graph.edges)
+    }
+}
+
+struct Graph<'d, 'a: 'd> {
+    dtd: &'d Dtd<'a>,
+    options: &'d DotOptions,
+    drawn: HashSet<&'a str>,
+    nodes: String,
+    edges: String,
+}
+
+impl<'d, 'a> Graph<'d, 'a> {
+    // Draws `element`, and then its children if they're within the depth limit.
+    fn draw(&mut self, element: &'d Element<'a>, depth: usize) {
+        if self.excluded(element) || !self.drawn.insert(element.name) {
+            return;
+        }
+        writeln!(self.nodes, "    {} [label=\"{}\\n0x{:X}\\n{}\"];", quote(element.name),
+                 element.name, element.id, element.type_.name()).unwrap();
+
+        if self.options.max_depth.is_some_and(|max_depth| depth >= max_depth) {
+            return;
+        }
+        let children = self.dtd.children_of(element);
+        for &child in &children {
+            if self.excluded(child) {
+                continue;
+            }
+            let nested = element.children.iter().any(|nested| nested.name == child.name);
+            let cardinality = child.cardinality();
+            let mut style = Vec::new();
+            if !nested {
+                style.push("dashed");
+            }
+            if cardinality == Cardinality::ExactlyOne || cardinality == Cardinality::OneOrMany {
+                style.push("bold");
+            }
+            write!(self.edges, "    {} -> {} [label=\"{}\"", quote(element.name),
+                   quote(child.name), symbol(&cardinality)).unwrap();
+            if !style.is_empty() {
+                write!(self.edges, ", style=\"{}\"", style.join(",")).unwrap();
+            }
+            self.edges.push_str("];\n");
+        }
+        for child in children {
+            self.draw(child, depth + 1);
+        }
+    }
+
+    fn excluded(&self, element: &Element) -> bool {
+        self.options.exclude.iter().any(|name| name == element.name)
+    }
+}
+
+fn symbol(cardinality: &Cardinality) -> &'static str {
+    match *cardinality {
+        Cardinality::ZeroOrMany => "*",
+        Cardinality::ZeroOrOne => "?",
+        Cardinality::ExactlyOne => "1",
+        Cardinality::OneOrMany => "+",
+    }
+}
+
+fn quote(text: &str) -> String {
+    format!("\"{}\"", text.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,32 @@
+use super::*;
+use parse_dtd;
+
+#[test]
+fn test_golden() {
+    let dtd = parse_dtd(include_bytes!("../../tests/dtd9"), &Default::default()).unwrap();
+    assert_eq!(dtd.to_dot(&DotOptions::default()), include_str!("../../tests/dot0"));
+}
+
+#[test]
+fn test_options() {
+    let dtd = parse_dtd(include_bytes!("../../tests/dtd8"), &Default::default()).unwrap();
+    let nodes = |options: &DotOptions| -> Vec<String> {
+        dtd.to_dot(options).lines()
+            .filter(|line| line.contains("[label=\"") && !line.contains("->"))
+            .map(|line| line.trim().split(' ').next().unwrap().trim_matches('"').to_string())
+            .collect()
+    };
+
+    let shallow = DotOptions { max_depth: Some(1), ..Default::default() };
+    assert_eq!(nodes(&shallow),
+               ["EBML", "DocType", "Segment", "SeekHead", "Info", "Tags", "Void", "CRC32"]);
+    assert!(!dtd.to_dot(&shallow).contains("\"Info\" -> "));
+
+    // SimpleTag is only reachable through Tags, though it names itself as a parent too.
+    let pruned = DotOptions { exclude: vec!["Info".to_string(), "Tags".to_string()],
+                              ..Default::default() };
+    assert_eq!(nodes(&pruned),
+               ["EBML", "DocType", "Segment", "SeekHead", "SeekID", "SeekPosition", "Void",
+                "CRC32"]);
+    assert_eq!(dtd.to_dot(&pruned).lines().next(), Some("digraph \"matroska\" {"));
+}
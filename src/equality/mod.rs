@@ -0,0 +1,256 @@
+//! Compares and hashes EDTDs by what they define. The parts of an EDTD holding floats, and
+//! elements, whose comments don't count, can't derive these, so they're written out here.
+
+use std::hash::{Hash, Hasher};
+use std::mem;
+
+use {Dtd, Element, FloatRangeItem, HeaderStatement, NewType, Property};
+
+// Floats are compared by their bits, so that equality is reflexive and agrees with hashing.
+fn bits(value: f64) -> u64 {
+    value.to_bits()
+}
+
+impl<'a> Dtd<'a> {
+    /// A 64-bit fingerprint of what this EDTD defines, for use as a cache key. Equal EDTDs have
+    /// the same fingerprint. Unlike hashing with a `HashMap`'s hasher, it's the same from run to
+    /// run of a program, though not necessarily between platforms or versions of this crate.
+    /// It's worked out once, when the EDTD is assembled, so this is free.
+    pub fn content_hash(&self) -> u64 {
+        self.content_hash
+    }
+}
+
+// The FNV-1a hash of `value`, which is the same from run to run.
+pub fn fingerprint<T: Hash>(value: &T) -> u64 {
+    let mut hasher = Fnv(FNV_OFFSET_BASIS);
+    value.hash(&mut hasher);
+    hasher.finish()
+}
+
+const FNV_OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+const FNV_PRIME: u64 = 0x0100_0000_01b3;
+
+// The 64-bit FNV-1a hash, which has no random state.
+struct Fnv(u64);
+
+impl Hasher for Fnv {
+    fn write(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            self.0 = (self.0 ^ u64::from(byte)).wrapping_mul(FNV_PRIME);
+        }
+    }
+
+    fn finish(&self) -> u64 {
+        self.0
+    }
+}
+
+impl<'a> PartialEq for HeaderStatement<'a> {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (&HeaderStatement::Int { name, value },
+             &HeaderStatement::Int { name: n, value: v }) => name == n && value == v,
+            (&HeaderStatement::Uint { name, value },
+             &HeaderStatement::Uint { name: n, value: v }) => name == n && value == v,
+            (&HeaderStatement::Float { name, value },
+             &HeaderStatement::Float { name: n, value: v }) => name == n && bits(value) == bits(v),
+            (&HeaderStatement::Date { name, value },
+             &HeaderStatement::Date { name: n, value: v }) => name == n && value == v,
+            (&HeaderStatement::String { name, ref value },
+             &HeaderStatement::String { name: n, value: ref v }) => name == n && value == v,
+            (&HeaderStatement::Binary { name, ref value },
+             &HeaderStatement::Binary { name: n, value: ref v }) => name == n && value == v,
+            (&HeaderStatement::Named { name, value },
+             &HeaderStatement::Named { name: n, value: v }) => name == n && value == v,
+            _ => false,
+        }
+    }
+}
+impl<'a> Eq for HeaderStatement<'a> {}
+impl<'a> Hash for HeaderStatement<'a> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        mem::discriminant(self).hash(state);
+        match *self {
+            HeaderStatement::Int { name, value } => (name, value).hash(state),
+            HeaderStatement::Uint { name, value } => (name, value).hash(state),
+            HeaderStatement::Float { name, value } => (name, bits(value)).hash(state),
+            HeaderStatement::Date { name, value } => (name, value).hash(state),
+            HeaderStatement::String { name, ref value } => (name, value).hash(state),
+            HeaderStatement::Binary { name, ref value } => (name, value).hash(state),
+            HeaderStatement::Named { name, value } => (name, value).hash(state),
+        }
+    }
+}
+
+impl<'a> PartialEq for NewType<'a> {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (&NewType::Int { name, ref default, ref range, .. },
+             &NewType::Int { name: n, default: ref d, range: ref r, .. }) => {
+                name == n && default == d && range == r
+            }
+            (&NewType::Uint { name, ref default, ref range, .. },
+             &NewType::Uint { name: n, default: ref d, range: ref r, .. }) => {
+                name == n && default == d && range == r
+            }
+            (&NewType::Float { name, default, ref range, .. },
+             &NewType::Float { name: n, default: d, range: ref r, .. }) => {
+                name == n && default.map(bits) == d.map(bits) && range == r
+            }
+            (&NewType::Date { name, ref default, ref range, .. },
+             &NewType::Date { name: n, default: ref d, range: ref r, .. }) => {
+                name == n && default == d && range == r
+            }
+            (&NewType::String { name, ref default, ref range, .. },
+             &NewType::String { name: n, default: ref d, range: ref r, .. }) => {
+                name == n && default == d && range == r
+            }
+            (&NewType::Binary { name, ref default, ref range, .. },
+             &NewType::Binary { name: n, default: ref d, range: ref r, .. }) => {
+                name == n && default == d && range == r
+            }
+            _ => false,
+        }
+    }
+}
+impl<'a> Eq for NewType<'a> {}
+impl<'a> Hash for NewType<'a> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        mem::discriminant(self).hash(state);
+        match *self {
+            NewType::Int { name, ref default, ref range, .. } => (name, default, range).hash(state),
+            NewType::Uint { name, ref default, ref range, .. } => (name, default, range).hash(state),
+            NewType::Float { name, default, ref range, .. } => {
+                (name, default.map(bits), range).hash(state)
+            }
+            NewType::Date { name, ref default, ref range, .. } => (name, default, range).hash(state),
+            NewType::String { name, ref default, ref range, .. } => {
+                (name, default, range).hash(state)
+            }
+            NewType::Binary { name, ref default, ref range, .. } => {
+                (name, default, range).hash(state)
+            }
+        }
+    }
+}
+
+// Everything but the comments.
+impl<'a> PartialEq for Element<'a> {
+    fn eq(&self, other: &Self) -> bool {
+        self.name == other.name && self.id == other.id && self.type_ == other.type_ &&
+            self.parent == other.parent && self.level == other.level &&
+            self.cardinality == other.cardinality && self.size == other.size &&
+            self.ordered == other.ordered &&
+            self.unknown_size_allowed == other.unknown_size_allowed &&
+            self.variants == other.variants && self.minver == other.minver &&
+            self.maxver == other.maxver && self.default == other.default &&
+            self.range == other.range && self.children == other.children
+    }
+}
+impl<'a> Eq for Element<'a> {}
+impl<'a> Hash for Element<'a> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        (self.name, self.id, self.type_, &self.parent, &self.level, &self.cardinality).hash(state);
+        (&self.size, self.ordered, self.unknown_size_allowed, &self.variants).hash(state);
+        (self.minver, self.maxver).hash(state);
+        (&self.default, &self.range, &self.children).hash(state);
+    }
+}
+
+impl<'a> PartialEq for Property<'a> {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (&Property::Parent(ref a), &Property::Parent(ref b)) => a == b,
+            (&Property::Level(ref a), &Property::Level(ref b)) => a == b,
+            (&Property::Cardinality(ref a), &Property::Cardinality(ref b)) => a == b,
+            (&Property::Size(ref a), &Property::Size(ref b)) => a == b,
+            (&Property::Ordered(a), &Property::Ordered(b)) => a == b,
+            (&Property::UnknownSizeAllowed(a), &Property::UnknownSizeAllowed(b)) => a == b,
+            (&Property::Variants(ref a), &Property::Variants(ref b)) => a == b,
+            (&Property::MinVer(a), &Property::MinVer(b)) => a == b,
+            (&Property::MaxVer(a), &Property::MaxVer(b)) => a == b,
+            (&Property::IntDefault(a), &Property::IntDefault(b)) => a == b,
+            (&Property::IntRange(ref a), &Property::IntRange(ref b)) => a == b,
+            (&Property::UintDefault(a), &Property::UintDefault(b)) => a == b,
+            (&Property::UintRange(ref a), &Property::UintRange(ref b)) => a == b,
+            (&Property::FloatDefault(a), &Property::FloatDefault(b)) => bits(a) == bits(b),
+            (&Property::FloatRange(ref a), &Property::FloatRange(ref b)) => a == b,
+            (&Property::DateDefault(a), &Property::DateDefault(b)) => a == b,
+            (&Property::DateRange(ref a), &Property::DateRange(ref b)) => a == b,
+            (&Property::StringDefault(ref a), &Property::StringDefault(ref b)) => a == b,
+            (&Property::StringRange(ref a), &Property::StringRange(ref b)) => a == b,
+            (&Property::BinaryDefault(ref a), &Property::BinaryDefault(ref b)) => a == b,
+            (&Property::BinaryRange(ref a), &Property::BinaryRange(ref b)) => a == b,
+            _ => false,
+        }
+    }
+}
+impl<'a> Eq for Property<'a> {}
+impl<'a> Hash for Property<'a> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        mem::discriminant(self).hash(state);
+        match *self {
+            Property::Parent(ref x) => x.hash(state),
+            Property::Level(ref x) => x.hash(state),
+            Property::Cardinality(ref x) => x.hash(state),
+            Property::Size(ref x) => x.hash(state),
+            Property::Ordered(x) => x.hash(state),
+            Property::UnknownSizeAllowed(x) => x.hash(state),
+            Property::Variants(ref x) => x.hash(state),
+            Property::MinVer(x) => x.hash(state),
+            Property::MaxVer(x) => x.hash(state),
+            Property::IntDefault(x) => x.hash(state),
+            Property::IntRange(ref x) => x.hash(state),
+            Property::UintDefault(x) => x.hash(state),
+            Property::UintRange(ref x) => x.hash(state),
+            Property::FloatDefault(x) => bits(x).hash(state),
+            Property::FloatRange(ref x) => x.hash(state),
+            Property::DateDefault(x) => x.hash(state),
+            Property::DateRange(ref x) => x.hash(state),
+            Property::StringDefault(ref x) => x.hash(state),
+            Property::StringRange(ref x) => x.hash(state),
+            Property::BinaryDefault(ref x) => x.hash(state),
+            Property::BinaryRange(ref x) => x.hash(state),
+        }
+    }
+}
+
+impl PartialEq for FloatRangeItem {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (&FloatRangeItem::From { start, include_start },
+             &FloatRangeItem::From { start: s, include_start: i }) => {
+                bits(start) == bits(s) && include_start == i
+            }
+            (&FloatRangeItem::To { end, include_end },
+             &FloatRangeItem::To { end: e, include_end: i }) => {
+                bits(end) == bits(e) && include_end == i
+            }
+            (&FloatRangeItem::Bounded { start, include_start, end, include_end },
+             &FloatRangeItem::Bounded { start: s, include_start: is, end: e, include_end: ie }) => {
+                bits(start) == bits(s) && include_start == is && bits(end) == bits(e) &&
+                    include_end == ie
+            }
+            _ => false,
+        }
+    }
+}
+impl Eq for FloatRangeItem {}
+impl Hash for FloatRangeItem {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        mem::discriminant(self).hash(state);
+        match *self {
+            FloatRangeItem::From { start, include_start } => {
+                (bits(start), include_start).hash(state)
+            }
+            FloatRangeItem::To { end, include_end } => (bits(end), include_end).hash(state),
+            FloatRangeItem::Bounded { start, include_start, end, include_end } => {
+                (bits(start), include_start, bits(end), include_end).hash(state)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests;
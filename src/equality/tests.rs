@@ -0,0 +1,73 @@
+use std::collections::hash_map::DefaultHasher;
+
+use super::*;
+use parse_dtd;
+
+const DTD: &str = "
+    declare header { DocType := \"doc\"; MaxSize := 1.5; }
+    define types { Flag := uint [ range:0..1; ] }
+    define elements {
+        // The root.
+        Segment := 18538067 container [ card:*; ] {
+            Enabled := 4101 Flag [ def:1; ]
+            Gain := 4102 float [ def:0.0; range:>-10.0; ]
+        }
+    }
+";
+
+fn hash(dtd: &Dtd) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    dtd.hash(&mut hasher);
+    hasher.finish()
+}
+
+#[test]
+fn test_equal() {
+    let a = parse_dtd(DTD, &Default::default()).unwrap();
+    let b = parse_dtd(DTD, &Default::default()).unwrap();
+    assert_eq!(a, b);
+    assert_eq!(hash(&a), hash(&b));
+    assert_eq!(a.content_hash(), b.content_hash());
+
+    // Comments and where things were written don't count.
+    let text = DTD.replace("// The root.", "/* The root of everything. */\n\n");
+    let c = parse_dtd(&text, &Default::default()).unwrap();
+    assert_ne!(c.all_elements()[0].comments, a.all_elements()[0].comments);
+    assert_eq!(a, c);
+    assert_eq!(hash(&a), hash(&c));
+    assert_eq!(a.content_hash(), c.content_hash());
+}
+
+#[test]
+fn test_different() {
+    let a = parse_dtd(DTD, &Default::default()).unwrap();
+    for &(from, to) in &[
+        ("4101", "4103"), ("card:*", "card:+"), ("1.5", "2.5"), ("range:0..1", "range:0..2"),
+        ("def:0.0", "def:-0.0"), (">-10.0", ">=-10.0"),
+    ] {
+        let text = DTD.replace(from, to);
+        let b = parse_dtd(&text, &Default::default()).unwrap();
+        assert_ne!(a, b, "{} -> {}", from, to);
+        assert_ne!(a.content_hash(), b.content_hash(), "{} -> {}", from, to);
+    }
+}
+
+#[test]
+fn test_float_bits() {
+    let nan = FloatRangeItem::From { start: ::std::f64::NAN, include_start: true };
+    assert_eq!(nan, nan.clone());
+    let zero = FloatRangeItem::To { end: 0.0, include_end: true };
+    assert_ne!(zero, FloatRangeItem::To { end: -0.0, include_end: true });
+}
+
+#[test]
+fn test_content_hash_of_derived() {
+    // Deriving one EDTD from another gives it the fingerprint of what it defines.
+    let a = parse_dtd(DTD, &Default::default()).unwrap();
+    let b = a.clone().restrict(|name| name != "Gain");
+    let text = DTD.replace("Gain := 4102 float [ def:0.0; range:>-10.0; ]", "");
+    let c = parse_dtd(&text, &Default::default()).unwrap();
+    assert_eq!(b, c);
+    assert_eq!(b.content_hash(), c.content_hash());
+    assert_ne!(a.content_hash(), b.content_hash());
+}
@@ -0,0 +1,226 @@
+use std::error::Error;
+use std::fmt;
+use std::str::FromStr;
+
+/// A stable identifier for every kind of problem parsing or validating an EDTD can report, for
+/// tools which suppress or promote particular ones. Parse errors have codes starting with `P`,
+/// validation diagnostics codes starting with `V`, problems importing XML schemas codes starting
+/// with `X`, problems reading JSON descriptions codes starting with `J`, problems applying
+/// overlays codes starting with `O`, and the quirks a lenient parse tolerates codes starting with
+/// `L`.
+///
+/// A code never changes meaning once it's been released, and changing one is a breaking change.
+/// New codes are added as new variants, so matching on this exhaustively will catch them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum ErrorCode {
+    /// `P0001`: the input ended in the middle of something.
+    UnexpectedEnd,
+    /// `P0002`: something other than a block was found outside of the blocks.
+    UnexpectedInput,
+    /// `P0003`: a header statement is malformed.
+    InvalidHeaderStatement,
+    /// `P0004`: a type definition is malformed.
+    InvalidTypeDefinition,
+    /// `P0005`: an element definition is malformed.
+    InvalidElementDefinition,
+    /// `P0006`: a `parent:` property is malformed.
+    InvalidParent,
+    /// `P0007`: a `level:` property is malformed.
+    InvalidLevel,
+    /// `P0008`: a `card:` property is malformed.
+    InvalidCardinality,
+    /// `P0009`: a `def:` property is malformed, or not of the element's type.
+    InvalidDefault,
+    /// `P0010`: a `range:` property is malformed.
+    InvalidRange,
+    /// `P0011`: a `size:` property is malformed.
+    InvalidSize,
+    /// `P0012`: an `ordered:` property is malformed.
+    InvalidOrdered,
+    /// `P0013`: an `unknownsizeallowed:` property is malformed.
+    InvalidUnknownSizeAllowed,
+    /// `P0014`: a `variants:` property is malformed.
+    InvalidVariants,
+    /// `P0015`: the input is bytes which aren't UTF-8.
+    InvalidUtf8,
+    /// `P0016`: an `include` statement is malformed, comes after a block, or is in an EDTD which
+    /// wasn't read from a file, so has nowhere to include from.
+    InvalidInclude,
+    /// `P0017`: a `minver:` or `maxver:` property is malformed.
+    InvalidVersion,
+    /// `V0001`: a type or element has the same name as a built-in type.
+    ShadowedBuiltinType,
+    /// `V0010`: a type is defined more than once.
+    DuplicateTypeName,
+    /// `V0011`: two elements have the same id.
+    DuplicateId,
+    /// `V0012`: an element is defined more than once.
+    DuplicateElementName,
+    /// `V0013`: an element's type is not defined.
+    UnknownType,
+    /// `V0014`: an element's `parent:` names an element which is not defined.
+    UnknownParent,
+    /// `V0015`: an element's default or range is not valid for its type.
+    MismatchedProperty,
+    /// `V0016`: an element which isn't deprecated can only appear inside one which is.
+    DeprecatedParent,
+    /// `V0017`: an element's `minver:` is greater than its `maxver:`, or either is greater than
+    /// the header's `DocTypeVersion`.
+    InvalidVersionRange,
+    /// `X0001`: an XML schema is not well-formed XML.
+    MalformedXml,
+    /// `X0002`: an element of an XML schema is missing a required attribute, or has an invalid
+    /// one.
+    InvalidSchemaElement,
+    /// `X0003`: an XML schema uses something an EDTD has no equivalent of, which was left out.
+    UnsupportedSchemaFeature,
+    /// `J0001`: a JSON description of an EDTD is not valid JSON.
+    MalformedJson,
+    /// `J0002`: a JSON description of an EDTD doesn't have the shape `Dtd::to_json` writes.
+    InvalidJsonSchema,
+    /// `J0003`: a JSON description of an EDTD has something which can't be kept, which was left
+    /// out.
+    IgnoredJsonValue,
+    /// `O0001`: an overlay refines or removes an element which isn't defined, or gives one a
+    /// different id or type.
+    InvalidOverlay,
+    /// `L0001`: a `%name;` entity reference, which was skipped.
+    EntityReference,
+    /// `L0002`: a property or header statement with no semicolon before the `]` or `}` after it.
+    MissingSemicolon,
+    /// `L0003`: a binary default of hex digits in single quotes, like `'0x1f43b675'`.
+    QuotedBinary,
+    /// `L0004`: a `#` comment, which was skipped.
+    HashComment,
+    /// `L0005`: a keyword written in a case other than lowercase, like `Card:` or `Define`.
+    KeywordCase,
+    /// `L0006`: bytes in a comment which aren't UTF-8, which were read as Latin-1.
+    Latin1Comment,
+}
+
+impl ErrorCode {
+    /// Every code, in order.
+    pub const ALL: &'static [ErrorCode] = &[
+        ErrorCode::UnexpectedEnd,
+        ErrorCode::UnexpectedInput,
+        ErrorCode::InvalidHeaderStatement,
+        ErrorCode::InvalidTypeDefinition,
+        ErrorCode::InvalidElementDefinition,
+        ErrorCode::InvalidParent,
+        ErrorCode::InvalidLevel,
+        ErrorCode::InvalidCardinality,
+        ErrorCode::InvalidDefault,
+        ErrorCode::InvalidRange,
+        ErrorCode::InvalidSize,
+        ErrorCode::InvalidOrdered,
+        ErrorCode::InvalidUnknownSizeAllowed,
+        ErrorCode::InvalidVariants,
+        ErrorCode::InvalidUtf8,
+        ErrorCode::InvalidInclude,
+        ErrorCode::InvalidVersion,
+        ErrorCode::ShadowedBuiltinType,
+        ErrorCode::DuplicateTypeName,
+        ErrorCode::DuplicateId,
+        ErrorCode::DuplicateElementName,
+        ErrorCode::UnknownType,
+        ErrorCode::UnknownParent,
+        ErrorCode::MismatchedProperty,
+        ErrorCode::DeprecatedParent,
+        ErrorCode::InvalidVersionRange,
+        ErrorCode::MalformedXml,
+        ErrorCode::InvalidSchemaElement,
+        ErrorCode::UnsupportedSchemaFeature,
+        ErrorCode::MalformedJson,
+        ErrorCode::InvalidJsonSchema,
+        ErrorCode::IgnoredJsonValue,
+        ErrorCode::InvalidOverlay,
+        ErrorCode::EntityReference,
+        ErrorCode::MissingSemicolon,
+        ErrorCode::QuotedBinary,
+        ErrorCode::HashComment,
+        ErrorCode::KeywordCase,
+        ErrorCode::Latin1Comment,
+    ];
+
+    /// The code as it's written, like `"P0008"`.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ErrorCode::UnexpectedEnd => "P0001",
+            ErrorCode::UnexpectedInput => "P0002",
+            ErrorCode::InvalidHeaderStatement => "P0003",
+            ErrorCode::InvalidTypeDefinition => "P0004",
+            ErrorCode::InvalidElementDefinition => "P0005",
+            ErrorCode::InvalidParent => "P0006",
+            ErrorCode::InvalidLevel => "P0007",
+            ErrorCode::InvalidCardinality => "P0008",
+            ErrorCode::InvalidDefault => "P0009",
+            ErrorCode::InvalidRange => "P0010",
+            ErrorCode::InvalidSize => "P0011",
+            ErrorCode::InvalidOrdered => "P0012",
+            ErrorCode::InvalidUnknownSizeAllowed => "P0013",
+            ErrorCode::InvalidVariants => "P0014",
+            ErrorCode::InvalidUtf8 => "P0015",
+            ErrorCode::InvalidInclude => "P0016",
+            ErrorCode::InvalidVersion => "P0017",
+            ErrorCode::ShadowedBuiltinType => "V0001",
+            ErrorCode::DuplicateTypeName => "V0010",
+            ErrorCode::DuplicateId => "V0011",
+            ErrorCode::DuplicateElementName => "V0012",
+            ErrorCode::UnknownType => "V0013",
+            ErrorCode::UnknownParent => "V0014",
+            ErrorCode::MismatchedProperty => "V0015",
+            ErrorCode::DeprecatedParent => "V0016",
+            ErrorCode::InvalidVersionRange => "V0017",
+            ErrorCode::MalformedXml => "X0001",
+            ErrorCode::InvalidSchemaElement => "X0002",
+            ErrorCode::UnsupportedSchemaFeature => "X0003",
+            ErrorCode::MalformedJson => "J0001",
+            ErrorCode::InvalidJsonSchema => "J0002",
+            ErrorCode::IgnoredJsonValue => "J0003",
+            ErrorCode::InvalidOverlay => "O0001",
+            ErrorCode::EntityReference => "L0001",
+            ErrorCode::MissingSemicolon => "L0002",
+            ErrorCode::QuotedBinary => "L0003",
+            ErrorCode::HashComment => "L0004",
+            ErrorCode::KeywordCase => "L0005",
+            ErrorCode::Latin1Comment => "L0006",
+        }
+    }
+
+    /// True for the codes of parse errors, and false for those of validation diagnostics.
+    pub fn is_parse_error(self) -> bool {
+        self.as_str().starts_with('P')
+    }
+}
+
+impl fmt::Display for ErrorCode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// The error parsing a string which isn't one of the codes.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnknownErrorCode(pub String);
+
+impl fmt::Display for UnknownErrorCode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "unknown error code `{}`", self.0)
+    }
+}
+
+impl Error for UnknownErrorCode {}
+
+impl FromStr for ErrorCode {
+    type Err = UnknownErrorCode;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        ErrorCode::ALL.iter()
+            .find(|code| code.as_str() == s)
+            .cloned()
+            .ok_or_else(|| UnknownErrorCode(s.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests;
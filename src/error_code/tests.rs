@@ -0,0 +1,57 @@
+use super::*;
+
+// Codes are part of the public interface: this list only ever grows.
+const GOLDEN: &[(ErrorCode, &str)] = &[
+    (ErrorCode::UnexpectedEnd, "P0001"),
+    (ErrorCode::UnexpectedInput, "P0002"),
+    (ErrorCode::InvalidHeaderStatement, "P0003"),
+    (ErrorCode::InvalidTypeDefinition, "P0004"),
+    (ErrorCode::InvalidElementDefinition, "P0005"),
+    (ErrorCode::InvalidParent, "P0006"),
+    (ErrorCode::InvalidLevel, "P0007"),
+    (ErrorCode::InvalidCardinality, "P0008"),
+    (ErrorCode::InvalidDefault, "P0009"),
+    (ErrorCode::InvalidRange, "P0010"),
+    (ErrorCode::InvalidSize, "P0011"),
+    (ErrorCode::InvalidOrdered, "P0012"),
+    (ErrorCode::InvalidUnknownSizeAllowed, "P0013"),
+    (ErrorCode::InvalidVariants, "P0014"),
+    (ErrorCode::InvalidUtf8, "P0015"),
+    (ErrorCode::InvalidInclude, "P0016"),
+    (ErrorCode::InvalidVersion, "P0017"),
+    (ErrorCode::ShadowedBuiltinType, "V0001"),
+    (ErrorCode::DuplicateTypeName, "V0010"),
+    (ErrorCode::DuplicateId, "V0011"),
+    (ErrorCode::DuplicateElementName, "V0012"),
+    (ErrorCode::UnknownType, "V0013"),
+    (ErrorCode::UnknownParent, "V0014"),
+    (ErrorCode::MismatchedProperty, "V0015"),
+    (ErrorCode::DeprecatedParent, "V0016"),
+    (ErrorCode::InvalidVersionRange, "V0017"),
+    (ErrorCode::MalformedXml, "X0001"),
+    (ErrorCode::InvalidSchemaElement, "X0002"),
+    (ErrorCode::UnsupportedSchemaFeature, "X0003"),
+    (ErrorCode::MalformedJson, "J0001"),
+    (ErrorCode::InvalidJsonSchema, "J0002"),
+    (ErrorCode::IgnoredJsonValue, "J0003"),
+    (ErrorCode::InvalidOverlay, "O0001"),
+    (ErrorCode::EntityReference, "L0001"),
+    (ErrorCode::MissingSemicolon, "L0002"),
+    (ErrorCode::QuotedBinary, "L0003"),
+    (ErrorCode::HashComment, "L0004"),
+    (ErrorCode::KeywordCase, "L0005"),
+    (ErrorCode::Latin1Comment, "L0006"),
+];
+
+#[test]
+fn test_codes() {
+    let codes: Vec<_> = ErrorCode::ALL.iter().map(|&code| (code, code.as_str())).collect();
+    assert_eq!(codes, GOLDEN);
+
+    for &(code, text) in GOLDEN {
+        assert_eq!(code.to_string(), text);
+        assert_eq!(text.parse(), Ok(code));
+        assert_eq!(code.is_parse_error(), text.starts_with('P'));
+    }
+    assert_eq!("P9999".parse::<ErrorCode>(), Err(UnknownErrorCode("P9999".to_string())));
+}
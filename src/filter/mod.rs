@@ -0,0 +1,131 @@
+//! Carves sub-schemas out of an EDTD, keeping chosen elements along with what they need.
+
+use std::collections::{HashMap, HashSet};
+
+use {flatten, Dtd, Element, Spanned, Type};
+
+impl<'a> Dtd<'a> {
+    /// A sub-schema of this EDTD, with only the elements `keep` chooses and what they need.
+    ///
+    /// `keep` is given the path to each place an element can appear: the names of the elements
+    /// from the top level down to it, ending with its own. An element is kept if `keep` is true
+    /// of any of its paths, along with the elements on that path, and those it's nested inside in
+    /// its definition. Everything else is dropped: other elements, with everything nested inside
+    /// them, the names of dropped elements in the `parent:` of those kept, and the types no kept
+    /// element has. The header is kept whole, and each DocType's elements are filtered
+    /// separately.
+    ///
+    /// No kept element refers to a dropped element or type, so if this EDTD validates without
+    /// errors, so does the result.
+    pub fn filter<F: Fn(&[&str]) -> bool>(&self, keep: F) -> Dtd<'a> {
+        let mut dtd = self.clone();
+        let main = Dtd { doc_types: Vec::new(), ..self.clone() }.assembled();
+        retain(&mut dtd.elements, &kept(&main, &keep));
+        for group in &mut dtd.doc_types {
+            let selected = self.select_doc_type(group.0)
+                .expect("labeled DocTypes can be selected");
+            retain(&mut group.1, &kept(&selected, &keep));
+        }
+        dtd.doc_types.retain(|group| !group.1.is_empty());
+
+        let groups = Some(&dtd.elements).into_iter()
+            .chain(dtd.doc_types.iter().map(|group| &group.1));
+        let used: HashSet<_> = groups
+            .flat_map(|elements| flatten(elements))
+            .filter_map(|element| match element.type_ {
+                Type::Name(name) => Some(name),
+                _ => None,
+            })
+            .collect();
+        dtd.types.retain(|new_type| used.contains(new_type.name()));
+        dtd.assembled()
+    }
+
+    /// Like `filter`, keeping the elements at `paths`, written like `\Segment\Info`, and
+    /// everything which can appear inside them.
+    pub fn filter_paths(&self, paths: &[&str]) -> Dtd<'a> {
+        let paths: Vec<Vec<&str>> = paths.iter()
+            .map(|path| path.trim_start_matches('\\').split('\\').collect())
+            .collect();
+        self.filter(|path| paths.iter().any(|prefix| {
+            prefix.len() <= path.len() && prefix.iter().zip(path).all(|(a, b)| a == b)
+        }))
+    }
+}
+
+// The names of the elements of `dtd` to keep.
+fn kept<'a, F: Fn(&[&str]) -> bool>(dtd: &Dtd<'a>, keep: &F) -> HashSet<&'a str> {
+    let mut paths = Vec::new();
+    for root in dtd.elements.iter().filter(|element| element.parent.is_none()) {
+        walk(dtd, root, &mut Vec::new(), &mut paths);
+    }
+
+    let mut kept = HashSet::new();
+    for path in paths.iter().filter(|path| keep(path)) {
+        kept.extend(path.iter().cloned());
+    }
+    // An element nested inside another's definition can't be kept without it, which needs the
+    // elements on a path to it in turn.
+    let containers = containers(&dtd.elements);
+    loop {
+        let missing: HashSet<_> = kept.iter()
+            .filter_map(|name| containers.get(name).cloned())
+            .filter(|container| !kept.contains(container))
+            .collect();
+        if missing.is_empty() {
+            return kept;
+        }
+        for container in missing {
+            if let Some(path) = paths.iter().find(|path| path.last() == Some(&container)) {
+                kept.extend(path.iter().cloned());
+            }
+            kept.insert(container);
+        }
+    }
+}
+
+// Adds the path to every place `element` and what can appear inside it can appear to `paths`,
+// given the path to its parent. Recursive elements are only followed once.
+fn walk<'d, 'a>(dtd: &'d Dtd<'a>, element: &'d Element<'a>, path: &mut Vec<&'a str>,
+                paths: &mut Vec<Vec<&'a str>>)
+{
+    if path.contains(&element.name) {
+        return;
+    }
+    path.push(element.name);
+    paths.push(path.clone());
+    for child in dtd.children_of(element) {
+        walk(dtd, child, path, paths);
+    }
+    path.pop();
+}
+
+// The name of the element each element is nested inside in its definition, by its name.
+fn containers<'a>(elements: &[Spanned<Element<'a>>]) -> HashMap<&'a str, &'a str> {
+    let mut containers = HashMap::new();
+    for container in flatten(elements) {
+        for child in &container.children {
+            containers.insert(child.name, container.name);
+        }
+    }
+    containers
+}
+
+// Keeps the elements named in `kept`, and only their names in each `parent:`. Elements left with
+// no parent can still appear inside the one they're nested in, so they lose their `parent:`.
+fn retain(elements: &mut Vec<Spanned<Element>>, kept: &HashSet<&str>) {
+    elements.retain(|element| kept.contains(element.name));
+    for element in elements {
+        let element = &mut element.node;
+        if let Some(ref mut parents) = element.parent {
+            parents.retain(|parent| kept.contains(parent));
+        }
+        if element.parent.as_ref().is_some_and(Vec::is_empty) {
+            element.parent = None;
+        }
+        retain(&mut element.children, kept);
+    }
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,91 @@
+use super::*;
+use parse_dtd;
+
+const DTD: &str = "
+    declare header { DocType := \"doc\"; }
+    define types { Flag := uint [ range:0..1; ] }
+    define elements {
+        Segment := 18538067 container {
+            Info := 1549a966 container {
+                Title := 7ba9 string;
+                Enabled := 4d80 Flag;
+            }
+            Tracks := 1654ae6b container {
+                TrackEntry := ae container {
+                    Default := 88 Flag;
+                }
+            }
+            Tags := 1254c367 container {
+                Tag := 7373 container {
+                    SimpleTag := 67c8 container [ parent:SimpleTag; ] {
+                        TagName := 45a3 string;
+                    }
+                }
+            }
+        }
+        Void := ec binary [ level:0..; ]
+        CodecPrivate := 63a2 binary [ parent:TrackEntry; ]
+        Shared := 4d81 uint [ parent:Info, TrackEntry; ]
+    }
+";
+
+fn names(dtd: &Dtd) -> Vec<&str> {
+    dtd.all_elements().iter().map(|element| element.name).collect()
+}
+
+fn find<'d, 'a>(dtd: &'d Dtd<'a>, name: &str) -> &'d Element<'a> {
+    dtd.all_elements().into_iter().find(|element| element.name == name).unwrap()
+}
+
+#[test]
+fn test_filter_leaf() {
+    let dtd = parse_dtd(DTD, &Default::default()).unwrap();
+
+    // A leaf keeps the elements it's inside, and nothing else.
+    let filtered = dtd.filter(|path| path.last() == Some(&"Default"));
+    assert_eq!(names(&filtered), vec!["Segment", "Tracks", "TrackEntry", "Default"]);
+    assert_eq!(filtered.header, dtd.header);
+    assert_eq!(filtered.types, dtd.types);
+    assert!(filtered.validate().is_empty());
+
+    // Elements declared elsewhere keep the parents which are kept.
+    let filtered = dtd.filter(|path| path == ["Segment", "Info", "Shared"]);
+    assert_eq!(names(&filtered), vec!["Segment", "Info", "Shared"]);
+    assert_eq!(find(&filtered, "Shared").parent, Some(vec!["Info"]));
+    assert!(filtered.types.is_empty());
+    assert!(filtered.validate().is_empty());
+
+    // A recursive element is kept once its path is.
+    let filtered = dtd.filter(|path| path.last() == Some(&"TagName"));
+    assert_eq!(names(&filtered), vec!["Segment", "Tags", "Tag", "SimpleTag", "TagName"]);
+    assert_eq!(find(&filtered, "SimpleTag").parent, Some(vec!["SimpleTag"]));
+}
+
+#[test]
+fn test_filter_paths() {
+    let dtd = parse_dtd(DTD, &Default::default()).unwrap();
+
+    let filtered = dtd.filter_paths(&["\\Segment\\Info", "\\Segment\\Tracks"]);
+    assert_eq!(names(&filtered), vec![
+        "Segment", "Info", "Title", "Enabled", "Tracks", "TrackEntry", "Default", "CodecPrivate",
+        "Shared",
+    ]);
+    assert_eq!(find(&filtered, "Shared").parent, Some(vec!["Info", "TrackEntry"]));
+    assert!(filtered.validate().is_empty());
+
+    // Filtering a container out drops what's inside it, and what can only appear inside it.
+    let filtered = dtd.filter_paths(&["\\Segment\\Info"]);
+    assert_eq!(names(&filtered), vec!["Segment", "Info", "Title", "Enabled", "Shared"]);
+    assert_eq!(find(&filtered, "Shared").parent, Some(vec!["Info"]));
+    assert!(filtered.validate().is_empty());
+
+    let filtered = dtd.filter_paths(&["\\Void", "\\Segment\\Tags"]);
+    assert_eq!(names(&filtered), vec![
+        "Segment", "Tags", "Tag", "SimpleTag", "TagName", "Void",
+    ]);
+    assert!(filtered.types.is_empty());
+    assert!(filtered.validate().is_empty());
+
+    assert!(dtd.filter_paths(&[]).elements.is_empty());
+    assert!(dtd.filter_paths(&["\\Missing"]).elements.is_empty());
+}
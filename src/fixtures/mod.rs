@@ -0,0 +1,10 @@
+//! Encoded elements, for the tests of what reads them.
+
+// An element with a one byte size, so its body must be shorter than 127 bytes.
+pub fn element(id: &[u8], body: &[u8]) -> Vec<u8> {
+    assert!(body.len() < 0x7F);
+    let mut bytes = id.to_vec();
+    bytes.push(0x80 | body.len() as u8);
+    bytes.extend_from_slice(body);
+    bytes
+}
@@ -0,0 +1,85 @@
+//! Decodes the hex digits binary values are written with.
+
+#[cfg(test)]
+mod tests;
+
+use std::error::Error;
+use std::fmt;
+
+/// Why text couldn't be decoded as hex.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum HexError {
+    /// A character which is neither a hex digit nor whitespace, at a byte offset into the text.
+    InvalidCharacter {
+        /// The character.
+        character: char,
+        /// The byte offset of the character.
+        offset: usize,
+    },
+    /// An odd number of hex digits, the last of which, at a byte offset into the text, is half of
+    /// a byte.
+    UnpairedDigit {
+        /// The byte offset of the last digit.
+        offset: usize,
+    },
+}
+
+impl fmt::Display for HexError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            HexError::InvalidCharacter { character, offset } => {
+                write!(f, "`{}` at offset {} is not a hex digit", character.escape_debug(), offset)
+            }
+            HexError::UnpairedDigit { offset } => {
+                write!(f, "the hex digit at offset {} is half of a byte", offset)
+            }
+        }
+    }
+}
+
+impl Error for HexError {}
+
+/// Decodes `text`, hex digits of either case, appending the bytes to `out`. Whitespace may
+/// separate the digits, even the two of a byte. If this fails, `out` is left as it was.
+///
+/// The digits are counted before any are decoded, so `out` grows at most once, by exactly the
+/// number of bytes decoded.
+pub fn from_hex_into(text: &str, out: &mut Vec<u8>) -> Result<(), HexError> {
+    let mut digits = 0;
+    let mut last = 0;
+    for (offset, character) in text.char_indices() {
+        if character.is_ascii_hexdigit() {
+            digits += 1;
+            last = offset;
+        } else if !matches!(character, ' ' | '\r' | '\n' | '\t') {
+            return Err(HexError::InvalidCharacter { character, offset });
+        }
+    }
+    if digits % 2 != 0 {
+        return Err(HexError::UnpairedDigit { offset: last });
+    }
+
+    out.reserve_exact(digits / 2);
+    let mut high = None;
+    for byte in text.bytes() {
+        let value = match byte {
+            b'0'..=b'9' => byte - b'0',
+            b'a'..=b'f' => byte - b'a' + 10,
+            b'A'..=b'F' => byte - b'A' + 10,
+            _ => continue,
+        };
+        match high.take() {
+            Some(high) => out.push(high << 4 | value),
+            None => high = Some(value),
+        }
+    }
+    Ok(())
+}
+
+/// Decodes `text` like `from_hex_into`, into a new vector.
+pub fn from_hex(text: &str) -> Result<Vec<u8>, HexError> {
+    let mut out = Vec::new();
+    from_hex_into(text, &mut out)?;
+    Ok(out)
+}
@@ -0,0 +1,109 @@
+use super::*;
+
+// How hex was decoded before from_hex_into, to check its output against.
+fn old_from_hex(s: &str) -> Option<Vec<u8>> {
+    let mut b = Vec::with_capacity(s.len() / 2);
+    let mut modulus = 0;
+    let mut buf = 0;
+
+    for byte in s.bytes() {
+        buf <<= 4;
+
+        match byte {
+            b'A'..=b'F' => buf |= byte - b'A' + 10,
+            b'a'..=b'f' => buf |= byte - b'a' + 10,
+            b'0'..=b'9' => buf |= byte - b'0',
+            b' '|b'\r'|b'\n'|b'\t' => {
+                buf >>= 4;
+                continue
+            }
+            _ => return None
+        }
+
+        modulus += 1;
+        if modulus == 2 {
+            modulus = 0;
+            b.push(buf);
+        }
+    }
+
+    if modulus == 0 {
+        Some(b)
+    } else {
+        None
+    }
+}
+
+#[test]
+fn test_from_hex() {
+    assert_eq!(from_hex(""), Ok(vec![]));
+    assert_eq!(from_hex("00ff7F"), Ok(vec![0x00, 0xFF, 0x7F]));
+    assert_eq!(from_hex("aBcD"), Ok(vec![0xAB, 0xCD]));
+}
+
+#[test]
+fn test_separators() {
+    assert_eq!(from_hex(" 01 02\t03\r\n04 "), Ok(vec![1, 2, 3, 4]));
+    // Even the digits of a byte may be separated.
+    assert_eq!(from_hex("0 1\n2\t3"), Ok(vec![0x01, 0x23]));
+    assert_eq!(from_hex(" \n "), Ok(vec![]));
+}
+
+#[test]
+fn test_unpaired_digit() {
+    assert_eq!(from_hex("1"), Err(HexError::UnpairedDigit { offset: 0 }));
+    assert_eq!(from_hex("123"), Err(HexError::UnpairedDigit { offset: 2 }));
+    assert_eq!(from_hex("12 3 "), Err(HexError::UnpairedDigit { offset: 3 }));
+}
+
+#[test]
+fn test_invalid_character() {
+    assert_eq!(from_hex("12g4"), Err(HexError::InvalidCharacter { character: 'g', offset: 2 }));
+    // Offsets are in bytes, and the character is reported whole.
+    assert_eq!(from_hex("é1"), Err(HexError::InvalidCharacter { character: 'é', offset: 0 }));
+    assert_eq!(from_hex("12\u{0}"), Err(HexError::InvalidCharacter { character: '\0', offset: 2 }));
+    // A bad character is reported even if there's an odd number of digits too.
+    assert_eq!(from_hex("1x"), Err(HexError::InvalidCharacter { character: 'x', offset: 1 }));
+    assert_eq!(
+        HexError::InvalidCharacter { character: '\n', offset: 3 }.to_string(),
+        "`\\n` at offset 3 is not a hex digit"
+    );
+}
+
+#[test]
+fn test_from_hex_into() {
+    let mut out = vec![9];
+    from_hex_into("0a0b", &mut out).unwrap();
+    assert_eq!(out, [9, 10, 11]);
+    assert_eq!(out.capacity(), 3);
+
+    // Nothing is appended if decoding fails, even if the error is at the end.
+    assert!(from_hex_into("0c0d0", &mut out).is_err());
+    assert!(from_hex_into("0c0dz", &mut out).is_err());
+    assert_eq!(out, [9, 10, 11]);
+}
+
+#[test]
+fn test_large() {
+    // Pseudo-random bytes, written with separators and mixed case every so often.
+    let mut state = 0x2545_F491_u32;
+    let mut text = String::new();
+    for i in 0..20_000 {
+        state ^= state << 13;
+        state ^= state >> 17;
+        state ^= state << 5;
+        text.push_str(&if i % 3 == 0 { format!("{:02X}", state as u8) } else {
+            format!("{:02x}", state as u8)
+        });
+        match state % 11 {
+            0 => text.push(' '),
+            1 => text.push_str("\r\n"),
+            2 => text.push('\t'),
+            _ => {}
+        }
+    }
+
+    let expected = old_from_hex(&text).unwrap();
+    assert_eq!(expected.len(), 20_000);
+    assert_eq!(from_hex(&text), Ok(expected));
+}
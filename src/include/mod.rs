@@ -0,0 +1,175 @@
+//! Reads EDTD files along with the files their `include` statements name.
+
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use {parse_dtds, parsers, utf8, Dtd, DtdError, ParseError, ParseOptions};
+
+/// How deeply includes may be nested, counting the files given to `DtdFiles::read` as the first
+/// level.
+pub const MAX_INCLUDE_DEPTH: usize = 32;
+
+/// EDTD files, and the files they include, read so they can be parsed together.
+///
+/// An EDTD read from a file may start with `include "path";` statements, before its blocks. The
+/// path is relative to the directory of the file including it. The included file's blocks are
+/// parsed as if they had been written in place of the statement, so they come before those of the
+/// file including them; includes are merged as by `parse_dtds`, so a fragment several files
+/// include is harmless. Each file is read once, however many times it's included.
+#[derive(Debug, Clone)]
+pub struct DtdFiles {
+    // The files in the order their blocks are parsed, with their `include` statements blanked out.
+    files: Vec<(PathBuf, String)>,
+}
+
+/// The ways reading EDTD files and what they include can fail.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum IncludeError {
+    /// A file couldn't be read. Holds its path and the error.
+    Io(PathBuf, io::Error),
+    /// A file isn't UTF-8, or one of its `include` statements is malformed. Holds its path and the
+    /// error.
+    Syntax(PathBuf, ParseError),
+    /// A file includes itself, directly or through others. Holds the paths of the files in the
+    /// cycle, starting and ending with that one.
+    Cycle(Vec<PathBuf>),
+    /// Includes are nested more than `MAX_INCLUDE_DEPTH` deep. Holds the path of the file which
+    /// would have been included.
+    TooDeep(PathBuf),
+}
+
+impl fmt::Display for IncludeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            IncludeError::Io(ref path, ref err) => {
+                write!(f, "couldn't read {}: {}", path.display(), err)
+            }
+            IncludeError::Syntax(ref path, ref err) => {
+                write!(f, "syntax error[{}] in {} at line {}, column {}: {}", err.code(),
+                       path.display(), err.line, err.column, err.message())
+            }
+            IncludeError::Cycle(ref paths) => {
+                let paths: Vec<_> = paths.iter().map(|path| path.display().to_string()).collect();
+                write!(f, "{} includes itself: {}", paths[0], paths.join(" -> "))
+            }
+            IncludeError::TooDeep(ref path) => {
+                write!(f, "including {} nests includes more than {} deep", path.display(),
+                       MAX_INCLUDE_DEPTH)
+            }
+        }
+    }
+}
+
+impl Error for IncludeError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match *self {
+            IncludeError::Io(_, ref err) => Some(err),
+            IncludeError::Syntax(_, ref err) => Some(err),
+            IncludeError::Cycle(_) | IncludeError::TooDeep(_) => None,
+        }
+    }
+}
+
+impl DtdFiles {
+    /// Reads the EDTD files at `paths`, in order, along with everything they include.
+    pub fn read<P: AsRef<Path>>(paths: &[P]) -> Result<Self, IncludeError> {
+        let mut reader = Reader { files: Vec::new(), read: Vec::new(), including: Vec::new() };
+        for path in paths {
+            reader.read(path.as_ref())?;
+        }
+        Ok(DtdFiles { files: reader.files })
+    }
+
+    /// How many files were read.
+    pub fn len(&self) -> usize {
+        self.files.len()
+    }
+
+    /// Whether no files were read.
+    pub fn is_empty(&self) -> bool {
+        self.files.is_empty()
+    }
+
+    /// The path of the file at `index`, in the order the files are parsed.
+    pub fn path(&self, index: usize) -> &Path {
+        &self.files[index].0
+    }
+
+    /// The text of the file at `index`, as it's parsed: with its `include` statements replaced by
+    /// spaces, so the lines and columns errors give are those of the file.
+    pub fn text(&self, index: usize) -> &str {
+        &self.files[index].1
+    }
+
+    /// Parses the files and merges them as `parse_dtds` does, optionally validating the result.
+    /// Errors come with the index of the file they're in, which `path` gives the path of.
+    pub fn parse(&self, options: &ParseOptions) -> Result<Dtd<'_>, (usize, DtdError)> {
+        let texts: Vec<&str> = self.files.iter().map(|file| &file.1[..]).collect();
+        parse_dtds(&texts, options)
+    }
+}
+
+// Reads files depth first, so each one's includes come before it.
+struct Reader {
+    files: Vec<(PathBuf, String)>,
+    // The canonical paths of the files which have been read, or are being read.
+    read: Vec<PathBuf>,
+    // The files whose includes are being read, outermost first, as given and canonically.
+    including: Vec<(PathBuf, PathBuf)>,
+}
+
+impl Reader {
+    fn read(&mut self, path: &Path) -> Result<(), IncludeError> {
+        let io_error = |err| IncludeError::Io(path.to_path_buf(), err);
+        let canonical = fs::canonicalize(path).map_err(io_error)?;
+        if let Some(start) = self.including.iter().position(|&(_, ref c)| *c == canonical) {
+            let mut cycle: Vec<_> = self.including[start..].iter()
+                .map(|&(ref path, _)| path.clone())
+                .collect();
+            cycle.push(path.to_path_buf());
+            return Err(IncludeError::Cycle(cycle));
+        }
+        if self.read.contains(&canonical) {
+            return Ok(());
+        }
+        if self.including.len() == MAX_INCLUDE_DEPTH {
+            return Err(IncludeError::TooDeep(path.to_path_buf()));
+        }
+
+        let bytes = fs::read(path).map_err(io_error)?;
+        let syntax_error = |err| IncludeError::Syntax(path.to_path_buf(), err);
+        let text = utf8(&bytes).map_err(syntax_error)?;
+        let includes = parsers::includes(text).map_err(|err| match err {
+            ::nom::Err::Error(err) | ::nom::Err::Failure(err) => {
+                syntax_error(ParseError::new(text, &err, text.len()))
+            }
+            ::nom::Err::Incomplete(_) => syntax_error(ParseError::at_end(text)),
+        })?;
+
+        let mut blanked = text.as_bytes().to_vec();
+        let directory = path.parent().unwrap_or_else(|| Path::new(""));
+        self.including.push((path.to_path_buf(), canonical.clone()));
+        for include in &includes {
+            self.read(&directory.join(include.node))?;
+            for byte in &mut blanked[include.span.start..include.span.end] {
+                if *byte != b'\n' && *byte != b'\r' {
+                    *byte = b' ';
+                }
+            }
+        }
+        self.including.pop();
+
+        // Only whole UTF-8 sequences were replaced, each byte by a space.
+        let blanked = String::from_utf8(blanked).expect("blanking kept the text UTF-8");
+        self.read.push(canonical);
+        self.files.push((path.to_path_buf(), blanked));
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,116 @@
+use std::fs;
+use std::path::PathBuf;
+
+use super::*;
+
+// A directory of EDTD files for one test, named after it, which is removed when dropped.
+struct Files(PathBuf);
+
+impl Files {
+    fn new(name: &str, files: &[(&str, &str)]) -> Self {
+        let dir = ::std::env::temp_dir()
+            .join(format!("ebml_macros_{}_include_{}", ::std::process::id(), name));
+        for &(path, text) in files {
+            let path = dir.join(path);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, text).unwrap();
+        }
+        Files(dir)
+    }
+
+    fn path(&self, path: &str) -> PathBuf {
+        self.0.join(path)
+    }
+}
+
+impl Drop for Files {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
+
+#[test]
+fn test_include() {
+    let files = Files::new("splice", &[
+        ("main.edtd", "include \"common/header.edtd\";\ninclude \"common/types.edtd\";\n\
+                       define elements { Segment := 18538067 container { Flag := 9c bool; } }"),
+        ("common/header.edtd", "include \"types.edtd\";\ndeclare header { DocType := \"x\"; }"),
+        ("common/types.edtd", "define types { bool := uint [ range:0..1; ] }"),
+    ]);
+    let dtd_files = DtdFiles::read(&[files.path("main.edtd")]).unwrap();
+
+    // Each file is read once, after what it includes.
+    let paths: Vec<_> = (0..dtd_files.len()).map(|index| dtd_files.path(index)).collect();
+    assert_eq!(paths, vec![
+        files.path("common/types.edtd"), files.path("common/header.edtd"), files.path("main.edtd"),
+    ]);
+    // Includes are blanked out, leaving the lines where they were.
+    let text = dtd_files.text(2);
+    assert_eq!(text.len(), fs::read(files.path("main.edtd")).unwrap().len());
+    assert_eq!(text.lines().map(str::trim).collect::<Vec<_>>()[..2], ["", ""]);
+
+    let options = ParseOptions { validate: Some(::Severity::Error), ..Default::default() };
+    let dtd = dtd_files.parse(&options).unwrap();
+    assert_eq!(dtd.doc_type(), Some("x"));
+    assert_eq!(dtd.all_elements().len(), 2);
+}
+
+#[test]
+fn test_include_errors() {
+    let files = Files::new("errors", &[
+        ("a.edtd", "include \"b.edtd\";"),
+        ("b.edtd", "include \"a.edtd\";"),
+        ("bad.edtd", "include \"broken.edtd\";\ndefine elements { EBML := 1a45dfa3 container; }"),
+        ("broken.edtd", "\n\ndefine elements { Segment := 18538067 }"),
+        ("malformed.edtd", "include broken.edtd;"),
+        ("missing.edtd", "include \"nowhere.edtd\";"),
+    ]);
+
+    match DtdFiles::read(&[files.path("a.edtd")]) {
+        Err(IncludeError::Cycle(ref cycle)) => {
+            assert_eq!(cycle, &[files.path("a.edtd"), files.path("b.edtd"), files.path("a.edtd")]);
+        }
+        other => panic!("{:?}", other),
+    }
+
+    // Errors in an included file are about that file.
+    let dtd_files = DtdFiles::read(&[files.path("bad.edtd")]).unwrap();
+    match dtd_files.parse(&Default::default()) {
+        Err((index, DtdError::Syntax(ref err))) => {
+            assert_eq!(dtd_files.path(index), files.path("broken.edtd"));
+            assert_eq!(err.line, 3);
+        }
+        other => panic!("{:?}", other),
+    }
+
+    match DtdFiles::read(&[files.path("malformed.edtd")]) {
+        Err(IncludeError::Syntax(ref path, ref err)) => {
+            assert_eq!(path, &files.path("malformed.edtd"));
+            assert_eq!(err.code(), ::ErrorCode::InvalidInclude);
+        }
+        other => panic!("{:?}", other),
+    }
+    match DtdFiles::read(&[files.path("missing.edtd")]) {
+        Err(IncludeError::Io(ref path, _)) => assert_eq!(path, &files.path("nowhere.edtd")),
+        other => panic!("{:?}", other),
+    }
+}
+
+#[test]
+fn test_include_depth() {
+    let mut texts = Vec::new();
+    for index in 0..MAX_INCLUDE_DEPTH {
+        texts.push((format!("{}.edtd", index), format!("include \"{}.edtd\";", index + 1)));
+    }
+    texts.push((format!("{}.edtd", MAX_INCLUDE_DEPTH), String::new()));
+    let texts: Vec<_> = texts.iter().map(|&(ref path, ref text)| (&path[..], &text[..])).collect();
+    let files = Files::new("depth", &texts);
+
+    assert_eq!(DtdFiles::read(&[files.path("1.edtd")]).unwrap().len(), MAX_INCLUDE_DEPTH);
+    match DtdFiles::read(&[files.path("0.edtd")]) {
+        Err(IncludeError::TooDeep(ref path)) => {
+            assert_eq!(path, &files.path(&format!("{}.edtd", MAX_INCLUDE_DEPTH)));
+        }
+        other => panic!("{:?}", other),
+    }
+}
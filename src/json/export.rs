@@ -0,0 +1,415 @@
+//! Writes a `Dtd` out as JSON, the inverse of `Dtd::from_json`.
+
+use std::fmt::Write;
+
+use {date, BinaryRangeItem, Cardinality, Date, DateRangeItem, Diagnostics, Dtd, Element,
+     FloatRangeItem, HeaderStatement, IntRangeItem, Level, NewType, Property, SourceMap,
+     StringRangeItem, UintRangeItem};
+
+impl<'a> Dtd<'a> {
+    /// Writes this EDTD as JSON, for tools which can't use it from Rust.
+    ///
+    /// The result is an object with three arrays, any of which may be empty:
+    ///
+    /// * `"header"` holds an object for each header statement, with its `"name"`, the `"type"` of
+    ///   its value (a built-in type, or `"reference"` for the name of another statement) and its
+    ///   `"value"`.
+    /// * `"types"` holds an object for each type, with its `"name"`, the built-in `"type"` it's
+    ///   based on, and its `"default"` and `"range"` if it has them.
+    /// * `"elements"` holds an object for each element defined at the top level, with its
+    ///   `"name"`, its `"id"` as a hex string like `"0x1A45DFA3"`, and its `"type"`. Whichever of
+    ///   `"parent"` (an array of names), `"level"` (an object with a `"start"`, and an `"end"`
+    ///   unless it's open), `"cardinality"` (`"*"`, `"?"`, `"1"` or `"+"`), `"default"`,
+    ///   `"range"`, `"size"`, `"ordered"`, `"unknownSizeAllowed"` (booleans), `"variants"` (an
+    ///   array of objects with a `"name"` and a `"value"`), `"minver"`, `"maxver"` (integers),
+    ///   `"comments"` (an array of strings) and `"children"` (an array of elements) it has
+    ///   follow, in that order.
+    ///
+    /// Integers are numbers. Floats are numbers too, apart from `"NaN"`, `"Infinity"` and
+    /// `"-Infinity"`. Dates are strings in ISO 8601 form, like `"2001-01-01T00:00:00.5"`, and
+    /// binaries base64 strings. A range or size is an array of objects, each with either a
+    /// `"value"`, or a `"start"`, an `"end"` or both; for floats, those come with
+    /// `"startInclusive"` and `"endInclusive"`. Ranges of strings give code points, and ranges of
+    /// binaries bytes.
+    pub fn to_json(&self) -> String {
+        let dtd = Json::Object(vec![
+            ("header", Json::Array(self.header.iter().map(|s| header_statement(s)).collect())),
+            ("types", Json::Array(self.types.iter().map(|t| new_type(t)).collect())),
+            ("elements", Json::Array(self.elements.iter().map(|e| element(e)).collect())),
+        ]);
+        let mut out = String::new();
+        dtd.write(&mut out, 0);
+        out.push('\n');
+        out
+    }
+}
+
+impl Diagnostics {
+    /// Writes these diagnostics as a JSON array, for tools which can't use them from Rust. Each
+    /// is an object with its `"severity"`, `"code"`, `"subject"` and `"message"`, followed by the
+    /// one-based `"line"` and `"column"` in `source` it starts at if it has a span.
+    pub fn to_json(&self, source: &str) -> String {
+        let map = SourceMap::new(source);
+        let diagnostics = self.iter().map(|diagnostic| {
+            let mut members = vec![
+                ("severity", string(&diagnostic.severity().to_string())),
+                ("code", string(diagnostic.code().as_str())),
+                ("subject", string(diagnostic.subject())),
+                ("message", string(diagnostic.message())),
+            ];
+            if let Some(span) = diagnostic.span() {
+                let (line, column) = map.line_column(span.start);
+                members.push(("line", number(line)));
+                members.push(("column", number(column)));
+            }
+            Json::Object(members)
+        }).collect();
+        let mut out = String::new();
+        Json::Array(diagnostics).write(&mut out, 0);
+        out.push('\n');
+        out
+    }
+}
+
+// A JSON value, along with the order to write an object's members in.
+enum Json {
+    Bool(bool),
+    Number(String),
+    String(String),
+    Array(Vec<Json>),
+    Object(Vec<(&'static str, Json)>),
+}
+
+impl Json {
+    // Arrays and objects are written inline if there's nothing but scalars in them, and with one
+    // item per line otherwise.
+    fn write(&self, out: &mut String, depth: usize) {
+        match *self {
+            Json::Bool(value) => out.push_str(if value { "true" } else { "false" }),
+            Json::Number(ref value) => out.push_str(value),
+            Json::String(ref value) => write_string(out, value),
+            Json::Array(ref items) if items.iter().all(Json::is_scalar) => {
+                out.push('[');
+                for (i, item) in items.iter().enumerate() {
+                    if i > 0 {
+                        out.push_str(", ");
+                    }
+                    item.write(out, depth);
+                }
+                out.push(']');
+            }
+            Json::Object(ref members) if members.iter().all(|member| member.1.is_scalar()) => {
+                out.push('{');
+                for (i, &(name, ref value)) in members.iter().enumerate() {
+                    if i > 0 {
+                        out.push_str(", ");
+                    }
+                    write!(out, "\"{}\": ", name).unwrap();
+                    value.write(out, depth);
+                }
+                out.push('}');
+            }
+            Json::Array(ref items) => {
+                out.push('[');
+                for (i, item) in items.iter().enumerate() {
+                    out.push_str(if i > 0 { ",\n" } else { "\n" });
+                    indent(out, depth + 1);
+                    item.write(out, depth + 1);
+                }
+                out.push('\n');
+                indent(out, depth);
+                out.push(']');
+            }
+            Json::Object(ref members) => {
+                out.push('{');
+                for (i, &(name, ref value)) in members.iter().enumerate() {
+                    out.push_str(if i > 0 { ",\n" } else { "\n" });
+                    indent(out, depth + 1);
+                    write!(out, "\"{}\": ", name).unwrap();
+                    value.write(out, depth + 1);
+                }
+                out.push('\n');
+                indent(out, depth);
+                out.push('}');
+            }
+        }
+    }
+
+    fn is_scalar(&self) -> bool {
+        match *self {
+            Json::Array(ref items) => items.is_empty(),
+            Json::Object(ref members) => members.is_empty(),
+            _ => true,
+        }
+    }
+}
+
+fn indent(out: &mut String, depth: usize) {
+    for _ in 0..depth {
+        out.push_str("  ");
+    }
+}
+
+fn write_string(out: &mut String, value: &str) {
+    out.push('"');
+    for c in value.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            '\u{0}'..='\u{1f}' => write!(out, "\\u{:04x}", c as u32).unwrap(),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+}
+
+fn header_statement(statement: &HeaderStatement) -> Json {
+    let (type_, value) = match *statement {
+        HeaderStatement::Int { value, .. } => ("int", number(value)),
+        HeaderStatement::Uint { value, .. } => ("uint", number(value)),
+        HeaderStatement::Float { value, .. } => ("float", float(value)),
+        HeaderStatement::Date { value, .. } => ("date", date(value)),
+        HeaderStatement::String { ref value, .. } => ("string", string(value)),
+        HeaderStatement::Binary { ref value, .. } => ("binary", binary(value)),
+        HeaderStatement::Named { value, .. } => ("reference", string(value)),
+    };
+    Json::Object(vec![
+        ("name", string(statement.name())),
+        ("type", string(type_)),
+        ("value", value),
+    ])
+}
+
+fn new_type(new_type: &NewType) -> Json {
+    let default = match *new_type {
+        NewType::Int { default, .. } => default.map(Property::IntDefault),
+        NewType::Uint { default, .. } => default.map(Property::UintDefault),
+        NewType::Float { default, .. } => default.map(Property::FloatDefault),
+        NewType::Date { default, .. } => default.map(Property::DateDefault),
+        NewType::String { ref default, .. } => default.clone().map(Property::StringDefault),
+        NewType::Binary { ref default, .. } => default.clone().map(Property::BinaryDefault),
+    };
+
+    let mut members = vec![
+        ("name", string(new_type.name())),
+        ("type", string(new_type.base_type().name())),
+    ];
+    members.extend(default.map(|default| ("default", property(&default))));
+    members.extend(new_type.range().map(|range| ("range", property(&range))));
+    Json::Object(members)
+}
+
+fn element(element: &Element) -> Json {
+    let mut members = vec![
+        ("name", string(element.name)),
+        ("id", string(&format!("0x{:X}", element.id))),
+        ("type", string(element.type_.name())),
+    ];
+    if let Some(ref parents) = element.parent {
+        members.push(("parent", Json::Array(parents.iter().map(|p| string(p)).collect())));
+    }
+    match element.level {
+        Some(Level::Bounded { start, end }) => members.push(("level", Json::Object(vec![
+            ("start", number(start)),
+            ("end", number(end)),
+        ]))),
+        Some(Level::Open { start }) => {
+            members.push(("level", Json::Object(vec![("start", number(start))])))
+        }
+        None => {}
+    }
+    if let Some(ref cardinality) = element.cardinality {
+        members.push(("cardinality", string(match *cardinality {
+            Cardinality::ZeroOrMany => "*",
+            Cardinality::ZeroOrOne => "?",
+            Cardinality::ExactlyOne => "1",
+            Cardinality::OneOrMany => "+",
+        })));
+    }
+    if let Some(ref default) = element.default {
+        members.push(("default", property(default)));
+    }
+    if let Some(ref range) = element.range {
+        members.push(("range", property(range)));
+    }
+    if let Some(ref size) = element.size {
+        members.push(("size", Json::Array(size.items().iter().map(uint_item).collect())));
+    }
+    if let Some(ordered) = element.ordered {
+        members.push(("ordered", Json::Bool(ordered)));
+    }
+    if let Some(allowed) = element.unknown_size_allowed {
+        members.push(("unknownSizeAllowed", Json::Bool(allowed)));
+    }
+    if let Some(ref variants) = element.variants {
+        let variants = variants.iter()
+            .map(|&(name, value)| {
+                Json::Object(vec![("name", string(name)), ("value", number(value))])
+            })
+            .collect();
+        members.push(("variants", Json::Array(variants)));
+    }
+    if let Some(minver) = element.minver {
+        members.push(("minver", number(minver)));
+    }
+    if let Some(maxver) = element.maxver {
+        members.push(("maxver", number(maxver)));
+    }
+    if !element.comments.is_empty() {
+        let comments = element.comments.iter().map(|c| string(c)).collect();
+        members.push(("comments", Json::Array(comments)));
+    }
+    if !element.children.is_empty() {
+        let children = element.children.iter().map(|c| self::element(c)).collect();
+        members.push(("children", Json::Array(children)));
+    }
+    Json::Object(members)
+}
+
+// The value of a default or range property.
+fn property(property: &Property) -> Json {
+    match *property {
+        Property::IntDefault(value) => number(value),
+        Property::UintDefault(value) => number(value),
+        Property::FloatDefault(value) => float(value),
+        Property::DateDefault(value) => date(value),
+        Property::StringDefault(ref value) => string(value),
+        Property::BinaryDefault(ref value) => binary(value),
+        Property::IntRange(ref items) => Json::Array(items.iter().map(int_item).collect()),
+        Property::UintRange(ref items) => Json::Array(items.iter().map(uint_item).collect()),
+        Property::FloatRange(ref items) => Json::Array(items.iter().map(float_item).collect()),
+        Property::DateRange(ref items) => Json::Array(items.iter().map(date_item).collect()),
+        Property::StringRange(ref items) => Json::Array(items.iter().map(string_item).collect()),
+        Property::BinaryRange(ref items) => Json::Array(items.iter().map(binary_item).collect()),
+        _ => unreachable!(),
+    }
+}
+
+fn number<T: ToString>(value: T) -> Json {
+    Json::Number(value.to_string())
+}
+
+fn float(value: f64) -> Json {
+    if value.is_nan() {
+        string("NaN")
+    } else if value.is_infinite() {
+        string(if value > 0.0 { "Infinity" } else { "-Infinity" })
+    } else {
+        // Debug formatting always includes a decimal point or exponent, as JSON allows.
+        Json::Number(format!("{:?}", value))
+    }
+}
+
+fn date(value: Date) -> Json {
+    string(&date::fields(&value).display("-").to_string())
+}
+
+fn string(value: &str) -> Json {
+    Json::String(value.to_owned())
+}
+
+fn binary(value: &[u8]) -> Json {
+    Json::String(base64(value))
+}
+
+pub fn base64(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+    let mut out = String::new();
+    for chunk in bytes.chunks(3) {
+        let mut buf = 0;
+        for (i, &byte) in chunk.iter().enumerate() {
+            buf |= u32::from(byte) << (16 - 8 * i);
+        }
+        for i in 0..4 {
+            if i <= chunk.len() {
+                out.push(ALPHABET[(buf >> (18 - 6 * i) & 0x3f) as usize] as char);
+            } else {
+                out.push('=');
+            }
+        }
+    }
+    out
+}
+
+// A range item, as whichever of its value, start and end it has.
+fn bounds(value: Option<Json>, start: Option<Json>, end: Option<Json>) -> Json {
+    let mut members = Vec::new();
+    members.extend(value.map(|value| ("value", value)));
+    members.extend(start.map(|start| ("start", start)));
+    members.extend(end.map(|end| ("end", end)));
+    Json::Object(members)
+}
+
+fn int_item(item: &IntRangeItem) -> Json {
+    match *item {
+        IntRangeItem::Single(value) => bounds(Some(number(value)), None, None),
+        IntRangeItem::From { start } => bounds(None, Some(number(start)), None),
+        IntRangeItem::To { end } => bounds(None, None, Some(number(end))),
+        IntRangeItem::Bounded { start, end } => {
+            bounds(None, Some(number(start)), Some(number(end)))
+        }
+    }
+}
+
+fn uint_item(item: &UintRangeItem) -> Json {
+    match *item {
+        UintRangeItem::Single(value) => bounds(Some(number(value)), None, None),
+        UintRangeItem::From { start } => bounds(None, Some(number(start)), None),
+        UintRangeItem::Bounded { start, end } => {
+            bounds(None, Some(number(start)), Some(number(end)))
+        }
+    }
+}
+
+fn float_item(item: &FloatRangeItem) -> Json {
+    let mut members = Vec::new();
+    match *item {
+        FloatRangeItem::From { start, include_start } => {
+            members.push(("start", float(start)));
+            members.push(("startInclusive", Json::Bool(include_start)));
+        }
+        FloatRangeItem::To { end, include_end } => {
+            members.push(("end", float(end)));
+            members.push(("endInclusive", Json::Bool(include_end)));
+        }
+        FloatRangeItem::Bounded { start, include_start, end, include_end } => {
+            members.push(("start", float(start)));
+            members.push(("startInclusive", Json::Bool(include_start)));
+            members.push(("end", float(end)));
+            members.push(("endInclusive", Json::Bool(include_end)));
+        }
+    }
+    Json::Object(members)
+}
+
+fn date_item(item: &DateRangeItem) -> Json {
+    match *item {
+        DateRangeItem::From { start } => bounds(None, Some(date(start)), None),
+        DateRangeItem::To { end } => bounds(None, None, Some(date(end))),
+        DateRangeItem::Bounded { start, end } => bounds(None, Some(date(start)), Some(date(end))),
+    }
+}
+
+fn string_item(item: &StringRangeItem) -> Json {
+    match *item {
+        StringRangeItem::Single(value) => bounds(Some(number(value)), None, None),
+        StringRangeItem::From { start } => bounds(None, Some(number(start)), None),
+        StringRangeItem::Bounded { start, end } => {
+            bounds(None, Some(number(start)), Some(number(end)))
+        }
+    }
+}
+
+fn binary_item(item: &BinaryRangeItem) -> Json {
+    match *item {
+        BinaryRangeItem::Single(value) => bounds(Some(number(value)), None, None),
+        BinaryRangeItem::From { start } => bounds(None, Some(number(start)), None),
+        BinaryRangeItem::Bounded { start, end } => {
+            bounds(None, Some(number(start)), Some(number(end)))
+        }
+    }
+}
@@ -0,0 +1,569 @@
+//! Converts between EDTDs and the JSON description of them written by `Dtd::to_json`, for tools
+//! outside of Rust.
+
+mod export;
+mod reader;
+#[cfg(test)]
+mod tests;
+
+use std::borrow::Cow;
+use std::str::FromStr;
+
+use ebml::Id;
+
+use self::reader::{Kind, Member, Value};
+use {date, BinaryRangeItem, Cardinality, Date, DateRangeItem, Diagnostics, Dtd, Element,
+     ErrorCode, FloatRangeItem, HeaderStatement, IntRangeItem, Level, NewType, Property, SizeList,
+     Span, Spanned, StringRangeItem, Type, UintRangeItem, ValidationDiagnostic};
+
+impl<'a> Dtd<'a> {
+    /// Reads the JSON written by `Dtd::to_json` back in, giving an equal `Dtd`.
+    ///
+    /// Names are borrowed from `input`, so they can't be written with escapes. Comments which
+    /// are can't be kept either, and are left out with a warning, as are members of objects that
+    /// aren't part of the format. Fails if the input isn't valid JSON, or doesn't have the shape
+    /// `to_json` describes. Either way, the diagnostics have spans into `input`.
+    pub fn from_json(input: &'a str) -> Result<(Dtd<'a>, Diagnostics), Diagnostics> {
+        let mut importer = Importer { diagnostics: Diagnostics::new(), types: Vec::new() };
+        let root = match reader::parse(input) {
+            Ok(root) => root,
+            Err(err) => {
+                let len = input[err.offset..].chars().next().map_or(0, char::len_utf8);
+                let span = Span { start: err.offset, end: err.offset + len };
+                importer.diagnostics.push(ValidationDiagnostic::error(
+                    ErrorCode::MalformedJson, "JSON", err.message
+                ).with_span(span));
+                return Err(importer.diagnostics);
+            }
+        };
+
+        let dtd = importer.dtd(&root);
+        match dtd {
+            Some(dtd) if !importer.diagnostics.has_errors() => Ok((dtd, importer.diagnostics)),
+            _ => Err(importer.diagnostics),
+        }
+    }
+}
+
+struct Importer<'a> {
+    diagnostics: Diagnostics,
+    // The types read so far, which the defaults and ranges of elements are read as.
+    types: Vec<Spanned<NewType<'a>>>,
+}
+
+// The members of an object.
+struct Members<'v, 'a: 'v> {
+    members: &'v [Member<'a>],
+    span: Span,
+}
+
+impl<'v, 'a> Members<'v, 'a> {
+    fn get(&self, name: &str) -> Option<&'v Value<'a>> {
+        self.members.iter().find(|member| member.name == name).map(|member| &member.value)
+    }
+}
+
+impl<'a> Importer<'a> {
+    fn invalid<M: Into<String>>(&mut self, subject: &str, message: M, span: Span) {
+        self.diagnostics.push(ValidationDiagnostic::error(
+            ErrorCode::InvalidJsonSchema, subject, message
+        ).with_span(span));
+    }
+
+    fn ignored<M: Into<String>>(&mut self, subject: &str, message: M, span: Span) {
+        self.diagnostics.push(ValidationDiagnostic::warning(
+            ErrorCode::IgnoredJsonValue, subject, message
+        ).with_span(span));
+    }
+
+    fn dtd(&mut self, root: &Value<'a>) -> Option<Dtd<'a>> {
+        let members = self.object("JSON", root, &["header", "types", "elements"])?;
+        let header = self.items("JSON", members.get("header"), Importer::header_statement);
+        for new_type in self.items("JSON", members.get("types"), Importer::new_type) {
+            self.types.push(new_type);
+        }
+        let elements = self.items("JSON", members.get("elements"), Importer::element);
+        let types = ::std::mem::take(&mut self.types);
+        Some(Dtd::new(header, types, elements))
+    }
+
+    fn header_statement(&mut self, value: &Value<'a>) -> Option<Spanned<HeaderStatement<'a>>> {
+        let members = self.object("header", value, &["name", "type", "value"])?;
+        let name = self.required_name("header", &members, "name")?;
+        let type_ = self.required(name, &members, "type")?;
+        let type_ = self.text(name, type_)?;
+        let value = self.required(name, &members, "value")?;
+        let statement = match &*type_ {
+            "int" => HeaderStatement::Int { name, value: self.number(name, value)? },
+            "uint" => HeaderStatement::Uint { name, value: self.number(name, value)? },
+            "float" => HeaderStatement::Float { name, value: self.float(name, value)? },
+            "date" => HeaderStatement::Date { name, value: self.date(name, value)? },
+            "string" => HeaderStatement::String {
+                name,
+                value: self.text(name, value)?,
+            },
+            "binary" => HeaderStatement::Binary {
+                name,
+                value: Cow::Owned(self.binary(name, value)?),
+            },
+            "reference" => HeaderStatement::Named {
+                name,
+                value: self.name(name, value)?,
+            },
+            _ => {
+                self.invalid(name, "expected a built-in type or `reference`",
+                             members.get("type").unwrap().span);
+                return None;
+            }
+        };
+        Some(Spanned::new(statement, members.span))
+    }
+
+    fn new_type(&mut self, value: &Value<'a>) -> Option<Spanned<NewType<'a>>> {
+        let members = self.object("types", value, &["name", "type", "default", "range"])?;
+        let name = self.required_name("types", &members, "name")?;
+        let type_value = self.required(name, &members, "type")?;
+        let base = match Type::builtin(&self.text(name, type_value)?) {
+            Some(Type::Container) | None => {
+                self.invalid(name, "expected a built-in type other than `container`",
+                             type_value.span);
+                return None;
+            }
+            Some(base) => base,
+        };
+
+        let comments = Vec::new();
+        let mut new_type = match base {
+            Type::Int => NewType::Int { name, default: None, range: None, comments },
+            Type::Uint => NewType::Uint { name, default: None, range: None, comments },
+            Type::Float => NewType::Float { name, default: None, range: None, comments },
+            Type::Date => NewType::Date { name, default: None, range: None, comments },
+            Type::String => NewType::String { name, default: None, range: None, comments },
+            _ => NewType::Binary { name, default: None, range: None, comments },
+        };
+        if let Some(default) = members.get("default") {
+            new_type.update(self.default(name, base, default)?);
+        }
+        if let Some(range) = members.get("range") {
+            new_type.update(self.range(name, base, range)?);
+        }
+        Some(Spanned::new(new_type, members.span))
+    }
+
+    fn element(&mut self, value: &Value<'a>) -> Option<Spanned<Element<'a>>> {
+        const MEMBERS: &[&str] = &[
+            "name", "id", "type", "parent", "level", "cardinality", "default", "range", "size",
+            "ordered", "unknownSizeAllowed", "variants", "minver", "maxver", "comments",
+            "children",
+        ];
+
+        let members = self.object("elements", value, MEMBERS)?;
+        let name = self.required_name("elements", &members, "name")?;
+        let id_value = self.required(name, &members, "id")?;
+        let id = self.text(name, id_value)?;
+        let id = match id.strip_prefix("0x").map(|id| u32::from_str_radix(id, 16)) {
+            Some(Ok(id)) if Id::from_encoded(id).is_some() => id,
+            _ => {
+                self.invalid(name, "expected an encoded id like `\"0x1A45DFA3\"`", id_value.span);
+                return None;
+            }
+        };
+        let type_ = self.required_name(name, &members, "type")?;
+        let type_ = Type::builtin(type_).unwrap_or(Type::Name(type_));
+
+        let mut element = Element::new(name, id, type_);
+        if let Some(value) = members.get("parent") {
+            let parents = self.array(name, value)?;
+            element.parent = Some(parents.iter()
+                .map(|parent| self.name(name, parent))
+                .collect::<Option<_>>()?);
+        }
+        if let Some(value) = members.get("level") {
+            let level = self.object(name, value, &["start", "end"])?;
+            let start = self.required(name, &level, "start")?;
+            let start = self.number(name, start)?;
+            element.level = Some(match level.get("end") {
+                Some(end) => Level::Bounded { start, end: self.number(name, end)? },
+                None => Level::Open { start },
+            });
+        }
+        if let Some(value) = members.get("cardinality") {
+            element.cardinality = Some(match &*self.text(name, value)? {
+                "*" => Cardinality::ZeroOrMany,
+                "?" => Cardinality::ZeroOrOne,
+                "1" => Cardinality::ExactlyOne,
+                "+" => Cardinality::OneOrMany,
+                _ => {
+                    self.invalid(name, "expected `*`, `?`, `1` or `+`", value.span);
+                    return None;
+                }
+            });
+        }
+        let base = match type_ {
+            Type::Name(type_name) => {
+                self.types.iter().find(|t| t.name() == type_name).map(|t| t.base_type())
+            }
+            builtin => Some(builtin),
+        };
+        for &(key, is_default) in &[("default", true), ("range", false)] {
+            let value = match members.get(key) {
+                Some(value) => value,
+                None => continue,
+            };
+            let base = match base {
+                Some(Type::Container) => {
+                    self.invalid(name, format!("containers can't have a {}", key), value.span);
+                    return None;
+                }
+                Some(base) => base,
+                None => {
+                    self.invalid(name, format!("the type isn't defined, so the {} can't be read",
+                                               key),
+                                 value.span);
+                    return None;
+                }
+            };
+            let property = if is_default {
+                self.default(name, base, value)?
+            } else {
+                self.range(name, base, value)?
+            };
+            element.update(Spanned::new(property, value.span));
+        }
+        if let Some(value) = members.get("size") {
+            let items = self.array(name, value)?;
+            let size = items.iter()
+                .map(|item| self.uint_item(name, item))
+                .collect::<Option<Vec<_>>>()?;
+            element.size = Some(SizeList(Cow::Owned(size)));
+        }
+        if let Some(value) = members.get("ordered") {
+            element.ordered = Some(self.boolean(name, value)?);
+        }
+        if let Some(value) = members.get("unknownSizeAllowed") {
+            element.unknown_size_allowed = Some(self.boolean(name, value)?);
+        }
+        if let Some(value) = members.get("variants") {
+            let mut variants = Vec::new();
+            for variant in self.array(name, value)? {
+                let variant = self.object(name, variant, &["name", "value"])?;
+                let variant_name = self.required_name(name, &variant, "name")?;
+                let value = self.required(name, &variant, "value")?;
+                variants.push((variant_name, self.number(name, value)?));
+            }
+            element.variants = Some(variants);
+        }
+        if let Some(value) = members.get("minver") {
+            element.minver = Some(self.number(name, value)?);
+        }
+        if let Some(value) = members.get("maxver") {
+            element.maxver = Some(self.number(name, value)?);
+        }
+        if let Some(value) = members.get("comments") {
+            for comment in self.array(name, value)? {
+                match comment.kind {
+                    Kind::String(Cow::Borrowed(comment)) => element.comments.push(comment),
+                    Kind::String(Cow::Owned(_)) => {
+                        self.ignored(name, "comments written with escapes can't be kept",
+                                     comment.span)
+                    }
+                    _ => self.invalid(name, format!("expected a string, not {}",
+                                                    comment.describe()),
+                                      comment.span),
+                }
+            }
+        }
+        element.children = self.items(name, members.get("children"), Importer::element);
+        Some(Spanned::new(element, members.span))
+    }
+
+    fn default(&mut self, subject: &str, base: Type, value: &Value<'a>) -> Option<Property<'a>> {
+        Some(match base {
+            Type::Int => Property::IntDefault(self.number(subject, value)?),
+            Type::Uint => Property::UintDefault(self.number(subject, value)?),
+            Type::Float => Property::FloatDefault(self.float(subject, value)?),
+            Type::Date => Property::DateDefault(self.date(subject, value)?),
+            Type::String => Property::StringDefault(self.text(subject, value)?),
+            _ => Property::BinaryDefault(Cow::Owned(self.binary(subject, value)?)),
+        })
+    }
+
+    fn range(&mut self, subject: &str, base: Type, value: &Value<'a>) -> Option<Property<'a>> {
+        let items = self.array(subject, value)?;
+        macro_rules! items {
+            ($item:ident) => {
+                items.iter().map(|item| self.$item(subject, item)).collect::<Option<_>>()?
+            }
+        }
+
+        Some(match base {
+            Type::Int => Property::IntRange(items!(int_item)),
+            Type::Uint => Property::UintRange(items!(uint_item)),
+            Type::Float => Property::FloatRange(items!(float_item)),
+            Type::Date => Property::DateRange(items!(date_item)),
+            Type::String => Property::StringRange(items!(string_item)),
+            _ => Property::BinaryRange(items!(binary_item)),
+        })
+    }
+
+    fn int_item(&mut self, subject: &str, value: &Value<'a>) -> Option<IntRangeItem> {
+        Some(match self.bounds(subject, value, Importer::number)? {
+            (Some(value), None, None) => IntRangeItem::Single(value),
+            (None, Some(start), None) => IntRangeItem::From { start },
+            (None, None, Some(end)) => IntRangeItem::To { end },
+            (None, Some(start), Some(end)) => IntRangeItem::Bounded { start, end },
+            _ => return self.mixed_bounds(subject, value),
+        })
+    }
+
+    fn uint_item(&mut self, subject: &str, value: &Value<'a>) -> Option<UintRangeItem> {
+        Some(match self.bounds(subject, value, Importer::number)? {
+            (Some(value), None, None) => UintRangeItem::Single(value),
+            (None, Some(start), None) => UintRangeItem::From { start },
+            (None, Some(start), Some(end)) => UintRangeItem::Bounded { start, end },
+            _ => return self.mixed_bounds(subject, value),
+        })
+    }
+
+    fn string_item(&mut self, subject: &str, value: &Value<'a>) -> Option<StringRangeItem> {
+        Some(match self.bounds(subject, value, Importer::number)? {
+            (Some(value), None, None) => StringRangeItem::Single(value),
+            (None, Some(start), None) => StringRangeItem::From { start },
+            (None, Some(start), Some(end)) => StringRangeItem::Bounded { start, end },
+            _ => return self.mixed_bounds(subject, value),
+        })
+    }
+
+    fn binary_item(&mut self, subject: &str, value: &Value<'a>) -> Option<BinaryRangeItem> {
+        Some(match self.bounds(subject, value, Importer::number)? {
+            (Some(value), None, None) => BinaryRangeItem::Single(value),
+            (None, Some(start), None) => BinaryRangeItem::From { start },
+            (None, Some(start), Some(end)) => BinaryRangeItem::Bounded { start, end },
+            _ => return self.mixed_bounds(subject, value),
+        })
+    }
+
+    fn date_item(&mut self, subject: &str, value: &Value<'a>) -> Option<DateRangeItem> {
+        Some(match self.bounds(subject, value, Importer::date)? {
+            (None, Some(start), None) => DateRangeItem::From { start },
+            (None, None, Some(end)) => DateRangeItem::To { end },
+            (None, Some(start), Some(end)) => DateRangeItem::Bounded { start, end },
+            _ => return self.mixed_bounds(subject, value),
+        })
+    }
+
+    fn float_item(&mut self, subject: &str, value: &Value<'a>) -> Option<FloatRangeItem> {
+        const MEMBERS: &[&str] = &["start", "startInclusive", "end", "endInclusive"];
+
+        let members = self.object(subject, value, MEMBERS)?;
+        let bound = |importer: &mut Self, key: &str, inclusive: &str| {
+            match (members.get(key), members.get(inclusive)) {
+                (Some(bound), Some(inclusive)) => {
+                    let bound = importer.float(subject, bound)?;
+                    Some(Some((bound, importer.boolean(subject, inclusive)?)))
+                }
+                (None, None) => Some(None),
+                _ => {
+                    importer.invalid(subject, format!("`{}` and `{}` must be given together",
+                                                      key, inclusive),
+                                     members.span);
+                    None
+                }
+            }
+        };
+        Some(match (bound(self, "start", "startInclusive")?, bound(self, "end", "endInclusive")?) {
+            (Some((start, include_start)), None) => FloatRangeItem::From { start, include_start },
+            (None, Some((end, include_end))) => FloatRangeItem::To { end, include_end },
+            (Some((start, include_start)), Some((end, include_end))) => {
+                FloatRangeItem::Bounded { start, include_start, end, include_end }
+            }
+            (None, None) => return self.mixed_bounds(subject, value),
+        })
+    }
+
+    // The value, start and end of a range item, read with `read`.
+    fn bounds<T, F>(&mut self, subject: &str, value: &Value<'a>, read: F) -> Option<Bounds<T>>
+        where F: Fn(&mut Self, &str, &Value<'a>) -> Option<T>
+    {
+        let members = self.object(subject, value, &["value", "start", "end"])?;
+        let get = |importer: &mut Self, key| match members.get(key) {
+            Some(value) => read(importer, subject, value).map(Some),
+            None => Some(None),
+        };
+        Some((get(self, "value")?, get(self, "start")?, get(self, "end")?))
+    }
+
+    fn mixed_bounds<T>(&mut self, subject: &str, value: &Value<'a>) -> Option<T> {
+        self.invalid(subject, "expected a range item with a `value`, or a `start`, an `end` or \
+                               both that its type allows",
+                     value.span);
+        None
+    }
+
+    // Reads each item of an optional array with `read`, leaving out those it fails on.
+    fn items<T, F>(&mut self, subject: &str, value: Option<&Value<'a>>, mut read: F) -> Vec<T>
+        where F: FnMut(&mut Self, &Value<'a>) -> Option<T>
+    {
+        let items = match value {
+            Some(value) => self.array(subject, value).unwrap_or(&[]),
+            None => &[],
+        };
+        items.iter().filter_map(|item| read(self, item)).collect()
+    }
+
+    fn object<'v>(&mut self, subject: &str, value: &'v Value<'a>, known: &[&str])
+        -> Option<Members<'v, 'a>>
+    {
+        match value.kind {
+            Kind::Object(ref members) => {
+                for member in members {
+                    if !known.contains(&&*member.name) {
+                        self.ignored(subject, format!("`{}` isn't part of the format",
+                                                      member.name),
+                                     member.value.span);
+                    }
+                }
+                Some(Members { members, span: value.span })
+            }
+            _ => self.expected(subject, "an object", value),
+        }
+    }
+
+    fn array<'v>(&mut self, subject: &str, value: &'v Value<'a>) -> Option<&'v [Value<'a>]> {
+        match value.kind {
+            Kind::Array(ref items) => Some(items),
+            _ => self.expected(subject, "an array", value),
+        }
+    }
+
+    fn required<'v>(&mut self, subject: &str, members: &Members<'v, 'a>, name: &str)
+        -> Option<&'v Value<'a>>
+    {
+        let value = members.get(name);
+        if value.is_none() {
+            self.invalid(subject, format!("`{}` is missing", name), members.span);
+        }
+        value
+    }
+
+    fn required_name<'v>(&mut self, subject: &str, members: &Members<'v, 'a>, name: &str)
+        -> Option<&'a str>
+    {
+        let value = self.required(subject, members, name)?;
+        self.name(subject, value)
+    }
+
+    // A name, which must be borrowed from the input.
+    fn name(&mut self, subject: &str, value: &Value<'a>) -> Option<&'a str> {
+        match value.kind {
+            Kind::String(Cow::Borrowed(name)) if is_name(name) => Some(name),
+            Kind::String(_) => {
+                self.invalid(subject, "expected a name, without escapes", value.span);
+                None
+            }
+            _ => self.expected(subject, "a string", value),
+        }
+    }
+
+    fn text(&mut self, subject: &str, value: &Value<'a>) -> Option<Cow<'a, str>> {
+        match value.kind {
+            Kind::String(ref text) => Some(text.clone()),
+            _ => self.expected(subject, "a string", value),
+        }
+    }
+
+    fn boolean(&mut self, subject: &str, value: &Value<'a>) -> Option<bool> {
+        match value.kind {
+            Kind::Bool(value) => Some(value),
+            _ => self.expected(subject, "a boolean", value),
+        }
+    }
+
+    // An integer of any of the types values are kept in.
+    fn number<T: FromStr>(&mut self, subject: &str, value: &Value<'a>) -> Option<T> {
+        match value.kind {
+            Kind::Number(number) => number.parse().ok().or_else(|| {
+                self.invalid(subject, "the number is out of range, or not an integer",
+                             value.span);
+                None
+            }),
+            _ => self.expected(subject, "a number", value),
+        }
+    }
+
+    fn float(&mut self, subject: &str, value: &Value<'a>) -> Option<f64> {
+        match value.kind {
+            Kind::Number(number) => number.parse().ok(),
+            Kind::String(ref text) if text == "NaN" => Some(f64::NAN),
+            Kind::String(ref text) if text == "Infinity" => Some(f64::INFINITY),
+            Kind::String(ref text) if text == "-Infinity" => Some(f64::NEG_INFINITY),
+            _ => self.expected(subject, "a number, `\"NaN\"` or `\"Infinity\"`", value),
+        }
+    }
+
+    fn date(&mut self, subject: &str, value: &Value<'a>) -> Option<Date> {
+        let text = self.text(subject, value)?;
+        date::dashed_timestamp(&text).or_else(|| {
+            self.invalid(subject, "expected a date like `\"2001-01-01T00:00:00\"`", value.span);
+            None
+        })
+    }
+
+    fn binary(&mut self, subject: &str, value: &Value<'a>) -> Option<Vec<u8>> {
+        let text = self.text(subject, value)?;
+        from_base64(&text).or_else(|| {
+            self.invalid(subject, "expected base64", value.span);
+            None
+        })
+    }
+
+    fn expected<T>(&mut self, subject: &str, expected: &str, value: &Value<'a>) -> Option<T> {
+        self.invalid(subject, format!("expected {}, not {}", expected, value.describe()),
+                     value.span);
+        None
+    }
+}
+
+type Bounds<T> = (Option<T>, Option<T>, Option<T>);
+
+// Whether an EDTD could refer to something by `name`.
+fn is_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars.next().is_some_and(|c| c.is_ascii_alphabetic() || c == '_') &&
+        chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+// Decodes padded base64 in the standard alphabet.
+fn from_base64(text: &str) -> Option<Vec<u8>> {
+    fn sextet(c: u8) -> Option<u32> {
+        Some(u32::from(match c {
+            b'A'..=b'Z' => c - b'A',
+            b'a'..=b'z' => c - b'a' + 26,
+            b'0'..=b'9' => c - b'0' + 52,
+            b'+' => 62,
+            b'/' => 63,
+            _ => return None,
+        }))
+    }
+
+    let text = text.as_bytes();
+    if !text.len().is_multiple_of(4) {
+        return None;
+    }
+    let mut out = Vec::with_capacity(text.len() / 4 * 3);
+    for (idx, chunk) in text.chunks(4).enumerate() {
+        let is_last = idx == text.len() / 4 - 1;
+        let padding = chunk.iter().rev().take_while(|&&c| c == b'=').count();
+        if padding > 2 || (padding > 0 && !is_last) {
+            return None;
+        }
+        let mut buf = 0;
+        for &c in &chunk[..4 - padding] {
+            buf = buf << 6 | sextet(c)?;
+        }
+        buf <<= 6 * padding;
+        let bytes = [(buf >> 16) as u8, (buf >> 8) as u8, buf as u8];
+        out.extend_from_slice(&bytes[..3 - padding]);
+    }
+    Some(out)
+}
@@ -0,0 +1,283 @@
+//! A reader for JSON, as described by RFC 8259. Numbers are kept as they were written, so
+//! integers too big for a float can still be read exactly.
+
+use std::borrow::Cow;
+use std::char;
+
+use Span;
+
+#[derive(Debug)]
+pub struct Value<'a> {
+    pub kind: Kind<'a>,
+    pub span: Span,
+}
+
+#[derive(Debug)]
+pub enum Kind<'a> {
+    Null,
+    Bool(bool),
+    Number(&'a str),
+    // With escapes replaced by the characters they stand for. Borrowed if there were none.
+    String(Cow<'a, str>),
+    Array(Vec<Value<'a>>),
+    Object(Vec<Member<'a>>),
+}
+
+#[derive(Debug)]
+pub struct Member<'a> {
+    pub name: Cow<'a, str>,
+    pub value: Value<'a>,
+}
+
+impl<'a> Value<'a> {
+    // What kind of value this is, for messages about expecting another.
+    pub fn describe(&self) -> &'static str {
+        match self.kind {
+            Kind::Null => "null",
+            Kind::Bool(_) => "a boolean",
+            Kind::Number(_) => "a number",
+            Kind::String(_) => "a string",
+            Kind::Array(_) => "an array",
+            Kind::Object(_) => "an object",
+        }
+    }
+}
+
+// Where a document stops being valid JSON, and what's wrong there.
+#[derive(Debug)]
+pub struct JsonError {
+    pub offset: usize,
+    pub message: String,
+}
+
+type Result<T> = ::std::result::Result<T, JsonError>;
+
+// Reads the value making up the whole of `input`.
+pub fn parse<'a>(input: &'a str) -> Result<Value<'a>> {
+    let mut reader = Reader { input, pos: 0 };
+    let value = reader.value(0)?;
+    reader.skip_whitespace();
+    if reader.pos < input.len() {
+        return Err(reader.error("expected nothing after the value"));
+    }
+    Ok(value)
+}
+
+// Deeper nesting than this is refused, rather than overflowing the stack.
+const MAX_DEPTH: usize = 256;
+
+struct Reader<'a> {
+    input: &'a str,
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn rest(&self) -> &'a str {
+        &self.input[self.pos..]
+    }
+
+    fn error<M: Into<String>>(&self, message: M) -> JsonError {
+        JsonError { offset: self.pos, message: message.into() }
+    }
+
+    fn eat(&mut self, prefix: &str) -> bool {
+        if self.rest().starts_with(prefix) {
+            self.pos += prefix.len();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, prefix: &str) -> Result<()> {
+        if self.eat(prefix) {
+            Ok(())
+        } else {
+            Err(self.error(format!("expected `{}`", prefix)))
+        }
+    }
+
+    fn skip_whitespace(&mut self) {
+        let rest = self.rest();
+        self.pos += rest.len() - rest.trim_start_matches([' ', '\t', '\n', '\r']).len();
+    }
+
+    fn value(&mut self, depth: usize) -> Result<Value<'a>> {
+        self.skip_whitespace();
+        if depth > MAX_DEPTH {
+            return Err(self.error("values are nested too deeply"));
+        }
+
+        let start = self.pos;
+        let kind = match self.rest().chars().next() {
+            Some('{') => self.object(depth)?,
+            Some('[') => self.array(depth)?,
+            Some('"') => Kind::String(self.string()?),
+            Some('-') | Some('0'..='9') => self.number()?,
+            _ if self.eat("true") => Kind::Bool(true),
+            _ if self.eat("false") => Kind::Bool(false),
+            _ if self.eat("null") => Kind::Null,
+            _ => return Err(self.error("expected a value")),
+        };
+        Ok(Value { kind, span: Span { start, end: self.pos } })
+    }
+
+    fn object(&mut self, depth: usize) -> Result<Kind<'a>> {
+        self.expect("{")?;
+        let mut members = Vec::new();
+        self.skip_whitespace();
+        if self.eat("}") {
+            return Ok(Kind::Object(members));
+        }
+        loop {
+            self.skip_whitespace();
+            if !self.rest().starts_with('"') {
+                return Err(self.error("expected a member name"));
+            }
+            let name = self.string()?;
+            self.skip_whitespace();
+            self.expect(":")?;
+            let value = self.value(depth + 1)?;
+            members.push(Member { name, value });
+
+            self.skip_whitespace();
+            if self.eat("}") {
+                return Ok(Kind::Object(members));
+            }
+            if !self.eat(",") {
+                return Err(self.error("expected `,` or `}`"));
+            }
+        }
+    }
+
+    fn array(&mut self, depth: usize) -> Result<Kind<'a>> {
+        self.expect("[")?;
+        let mut items = Vec::new();
+        self.skip_whitespace();
+        if self.eat("]") {
+            return Ok(Kind::Array(items));
+        }
+        loop {
+            items.push(self.value(depth + 1)?);
+            self.skip_whitespace();
+            if self.eat("]") {
+                return Ok(Kind::Array(items));
+            }
+            if !self.eat(",") {
+                return Err(self.error("expected `,` or `]`"));
+            }
+        }
+    }
+
+    fn number(&mut self) -> Result<Kind<'a>> {
+        let start = self.pos;
+        let digits = |reader: &mut Self| {
+            let rest = reader.rest();
+            let len = rest.len() - rest.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+            reader.pos += len;
+            len
+        };
+
+        self.eat("-");
+        if !self.eat("0") && digits(self) == 0 {
+            return Err(self.error("expected a digit"));
+        }
+        if self.eat(".") && digits(self) == 0 {
+            return Err(self.error("expected a digit"));
+        }
+        if self.eat("e") || self.eat("E") {
+            let _ = self.eat("+") || self.eat("-");
+            if digits(self) == 0 {
+                return Err(self.error("expected a digit"));
+            }
+        }
+        Ok(Kind::Number(&self.input[start..self.pos]))
+    }
+
+    fn string(&mut self) -> Result<Cow<'a, str>> {
+        self.expect("\"")?;
+        let start = self.pos;
+        let mut owned: Option<String> = None;
+        loop {
+            let c = match self.rest().chars().next() {
+                Some(c) => c,
+                None => return Err(self.error("expected `\"` before the end of the input")),
+            };
+            match c {
+                '"' => {
+                    let text = match owned {
+                        Some(text) => Cow::Owned(text),
+                        None => Cow::Borrowed(&self.input[start..self.pos]),
+                    };
+                    self.pos += 1;
+                    return Ok(text);
+                }
+                '\\' => {
+                    let text = owned.get_or_insert_with(|| self.input[start..self.pos].to_owned());
+                    self.pos += 1;
+                    let escaped = self.escape()?;
+                    text.push(escaped);
+                }
+                '\u{0}'..='\u{1f}' => {
+                    return Err(self.error("control characters in strings must be escaped"));
+                }
+                c => {
+                    if let Some(ref mut text) = owned {
+                        text.push(c);
+                    }
+                    self.pos += c.len_utf8();
+                }
+            }
+        }
+    }
+
+    // Reads the rest of an escape sequence, after the backslash.
+    fn escape(&mut self) -> Result<char> {
+        let c = match self.rest().chars().next() {
+            Some(c) => c,
+            None => return Err(self.error("expected an escape sequence")),
+        };
+        self.pos += c.len_utf8();
+        Ok(match c {
+            '"' => '"',
+            '\\' => '\\',
+            '/' => '/',
+            'b' => '\u{8}',
+            'f' => '\u{c}',
+            'n' => '\n',
+            'r' => '\r',
+            't' => '\t',
+            'u' => {
+                let high = self.hex4()?;
+                if !(0xD800..0xDC00).contains(&high) {
+                    return char::from_u32(high).ok_or_else(|| self.error("unpaired surrogate"));
+                }
+                // Characters outside the basic multilingual plane are written as a pair.
+                if !self.eat("\\u") {
+                    return Err(self.error("unpaired surrogate"));
+                }
+                let low = self.hex4()?;
+                if !(0xDC00..0xE000).contains(&low) {
+                    return Err(self.error("unpaired surrogate"));
+                }
+                let code = 0x1_0000 + ((high - 0xD800) << 10) + (low - 0xDC00);
+                char::from_u32(code).unwrap()
+            }
+            _ => {
+                self.pos -= c.len_utf8();
+                return Err(self.error("unknown escape sequence"));
+            }
+        })
+    }
+
+    fn hex4(&mut self) -> Result<u32> {
+        let digits = self.rest().get(..4).filter(|d| d.chars().all(|c| c.is_ascii_hexdigit()));
+        match digits {
+            Some(digits) => {
+                self.pos += 4;
+                Ok(u32::from_str_radix(digits, 16).unwrap())
+            }
+            None => Err(self.error("expected four hex digits")),
+        }
+    }
+}
@@ -0,0 +1,182 @@
+use super::*;
+
+use super::export::base64;
+use {parse_dtd, Severity};
+
+const FIXTURES: &[(&str, &[u8])] = &[
+    ("dtd0", include_bytes!("../../tests/dtd0")),
+    ("dtd1", include_bytes!("../../tests/dtd1")),
+    ("dtd3", include_bytes!("../../tests/dtd3")),
+    ("dtd4", include_bytes!("../../tests/dtd4")),
+    ("dtd5", include_bytes!("../../tests/dtd5")),
+    ("dtd6", include_bytes!("../../tests/dtd6")),
+    ("dtd7", include_bytes!("../../tests/dtd7")),
+    ("dtd8", include_bytes!("../../tests/dtd8")),
+    ("dtd9", include_bytes!("../../tests/dtd9")),
+];
+
+// Drops the comments which JSON needs escapes for, since those can't be read back.
+fn drop_escaped_comments(elements: &mut [Spanned<Element>]) {
+    for element in elements {
+        let escaped = |c| c < ' ' || c == '"' || c == '\\';
+        element.node.comments.retain(|comment| !comment.contains(escaped));
+        drop_escaped_comments(&mut element.node.children);
+    }
+}
+
+fn errors(input: &str) -> Vec<(ErrorCode, String, String)> {
+    let diagnostics = Dtd::from_json(input).unwrap_err();
+    diagnostics.errors()
+        .map(|d| (d.code(), d.subject().to_string(), d.message().to_string()))
+        .collect()
+}
+
+#[test]
+fn test_golden() {
+    let dtd = parse_dtd(include_bytes!("../../tests/dtd9"), &Default::default()).unwrap();
+    assert_eq!(dtd.to_json(), include_str!("../../tests/json0"));
+}
+
+#[test]
+fn test_round_trip() {
+    for &(name, input) in FIXTURES {
+        let mut dtd = parse_dtd(input, &Default::default()).unwrap();
+        let json = dtd.to_json();
+        let (imported, diagnostics) = Dtd::from_json(&json)
+            .unwrap_or_else(|d| panic!("{}: {:?}\n{}", name, d, json));
+        assert!(diagnostics.iter().all(|d| d.code() == ErrorCode::IgnoredJsonValue), "{}", name);
+
+        drop_escaped_comments(&mut dtd.elements);
+        assert_eq!(imported, dtd, "{}", name);
+    }
+}
+
+#[test]
+fn test_values() {
+    let input = r#"{
+        "header": [
+            {"name": "Offset", "type": "int", "value": 5},
+            {"name": "Big", "type": "uint", "value": 18446744073709551615},
+            {"name": "Magic", "type": "binary", "value": "aGk="},
+            {"name": "Scale", "type": "float", "value": "-Infinity"}
+        ],
+        "elements": [
+            {"name": "Root", "id": "0x81", "type": "string", "default": "\u00e9\ud83d\ude00\n",
+             "comments": [" kept", "dropped\n"], "extra": 1}
+        ]
+    }"#;
+    let (dtd, diagnostics) = Dtd::from_json(input).unwrap();
+
+    // Unlike in an EDTD, the types of header statements are kept.
+    assert_eq!(dtd.header[0].node, HeaderStatement::Int { name: "Offset", value: 5 });
+    assert_eq!(dtd.header_uint("Big"), Some(u64::MAX));
+    assert_eq!(dtd.header[2].node,
+               HeaderStatement::Binary { name: "Magic", value: b"hi"[..].into() });
+    assert_eq!(dtd.header[3].node,
+               HeaderStatement::Float { name: "Scale", value: f64::NEG_INFINITY });
+
+    let root = &dtd.elements[0];
+    assert_eq!(root.default.as_ref().unwrap().node,
+               Property::StringDefault("é😀\n".into()));
+    assert_eq!(root.comments, vec![" kept"]);
+
+    let warnings: Vec<_> = diagnostics.iter()
+        .map(|d| (d.severity(), d.code(), &input[d.span().unwrap().start..][..9]))
+        .collect();
+    assert_eq!(warnings, vec![
+        (Severity::Warning, ErrorCode::IgnoredJsonValue, "\"dropped\\"),
+        (Severity::Warning, ErrorCode::IgnoredJsonValue, "1}\n      "),
+    ]);
+}
+
+#[test]
+fn test_errors() {
+    let malformed = Dtd::from_json("{\"header\": [}").unwrap_err();
+    let malformed: Vec<_> = malformed.iter()
+        .map(|d| (d.code(), d.message().to_string(), d.span().unwrap().start))
+        .collect();
+    assert_eq!(malformed, vec![(ErrorCode::MalformedJson, "expected a value".to_string(), 12)]);
+
+    let error = |subject: &str, message: &str| {
+        (ErrorCode::InvalidJsonSchema, subject.to_string(), message.to_string())
+    };
+    assert_eq!(errors("[]"), vec![error("JSON", "expected an object, not an array")]);
+    assert_eq!(errors(r#"{"types": [{"name": "t", "type": "container"}]}"#),
+               vec![error("t", "expected a built-in type other than `container`")]);
+    assert_eq!(errors(r#"{"elements": [
+                           {"name": "A", "id": "0x4000", "type": "uint"},
+                           {"name": "B\u0043", "id": "0x81", "type": "uint"},
+                           {"name": "C", "id": "0x82", "type": "t", "default": 1},
+                           {"name": "D", "id": "0x83", "type": "uint", "range": [{"end": 1}]},
+                           {"name": "E", "id": "0x84", "type": "uint", "default": -1},
+                           {"name": "F", "id": "0x85", "type": "float",
+                            "range": [{"start": 1.0}]},
+                           {"name": "G", "id": "0x86"}
+                       ]}"#),
+               vec![
+                   error("A", "expected an encoded id like `\"0x1A45DFA3\"`"),
+                   error("elements", "expected a name, without escapes"),
+                   error("C", "the type isn't defined, so the default can't be read"),
+                   error("D", "expected a range item with a `value`, or a `start`, an `end` or \
+                               both that its type allows"),
+                   error("E", "the number is out of range, or not an integer"),
+                   error("F", "`start` and `startInclusive` must be given together"),
+                   error("G", "`type` is missing"),
+               ]);
+}
+
+#[test]
+fn test_diagnostics() {
+    let mut diagnostics = Diagnostics::new();
+    assert_eq!(diagnostics.to_json(""), "[]\n");
+
+    diagnostics.push(ValidationDiagnostic::warning(ErrorCode::DuplicateId, "A", "no \"span\""));
+    let span = Span { start: 19, end: 21 };
+    diagnostics.push(ValidationDiagnostic::error(ErrorCode::DuplicateId, "B", "x").with_span(span));
+    let json = diagnostics.to_json("A := 81 uint;\nB := 81 uint;");
+    assert_eq!(json.lines().collect::<Vec<_>>(), [
+        "[",
+        "  {\"severity\": \"error\", \"code\": \"V0011\", \"subject\": \"B\", \"message\": \"x\", \
+         \"line\": 2, \"column\": 6},",
+        "  {\"severity\": \"warning\", \"code\": \"V0011\", \"subject\": \"A\", \
+         \"message\": \"no \\\"span\\\"\"}",
+        "]",
+    ]);
+}
+
+#[test]
+fn test_base64() {
+    for bytes in &[&b""[..], b"f", b"fo", b"foo", b"foob", b"fooba", b"foobar", b"\xff\x00\xfe"] {
+        assert_eq!(from_base64(&base64(bytes)).as_deref(), Some(*bytes));
+    }
+    assert_eq!(base64(b"foobar"), "Zm9vYmFy");
+    assert_eq!(base64(b"fooba"), "Zm9vYmE=");
+    assert_eq!(from_base64("Zm9vYg=="), Some(b"foob".to_vec()));
+    assert_eq!(from_base64("Zm9vYg="), None);
+    assert_eq!(from_base64("Zg==Zg=="), None);
+    assert_eq!(from_base64("Zm9v!mFy"), None);
+}
+
+#[test]
+fn test_reader() {
+    use super::reader::parse;
+
+    let value = parse(r#" [1, -0.5e+3, "a\"b", "plain", true, null, {}] "#).unwrap();
+    let items = match value.kind {
+        Kind::Array(items) => items,
+        _ => panic!(),
+    };
+    let kinds: Vec<_> = items.iter().map(|item| format!("{:?}", item.kind)).collect();
+    assert_eq!(kinds, vec![
+        "Number(\"1\")", "Number(\"-0.5e+3\")", "String(\"a\\\"b\")", "String(\"plain\")",
+        "Bool(true)", "Null", "Object([])",
+    ]);
+    assert!(matches!(items[3].kind, Kind::String(Cow::Borrowed(_))));
+
+    let error = |input| parse(input).map(|_| ()).unwrap_err().message;
+    assert_eq!(error("01"), "expected nothing after the value");
+    assert_eq!(error("[1,]"), "expected a value");
+    assert_eq!(error("\"\\ud800\""), "unpaired surrogate");
+    assert_eq!(error("\"a\nb\""), "control characters in strings must be escaped");
+    assert_eq!(error(&"[".repeat(300)), "values are nested too deeply");
+}
@@ -4,11 +4,20 @@
 
 #![cfg_attr(feature = "dev", feature(plugin))]
 #![cfg_attr(feature = "dev", plugin(clippy))]
+#![recursion_limit = "256"]
 #![allow(unused)]
 
 //! Uses `nom` to parse an EDTD (an EBML Document Type Definition), and generate types for use with
 //! the `ebml` crate.
 //!
+//! ##Layout:
+//!
+//! Everything needed to parse and inspect an EDTD is at the crate root: `parse_dtd` and the
+//! functions like it, `Dtd`, the items of its ranges, and the errors parsing reports. `codegen`
+//! generates Rust from a `Dtd`, and `runtime` holds what the generated code calls. The parsers
+//! themselves, and the `nom` types they're written with, are private, so neither is part of the
+//! API. Error enums are `#[non_exhaustive]`, since new ways to fail come with new features.
+//!
 //! ##Errata:
 //!
 //! * The specification gives no upper bound on the value of integer literals. We limit them to the
@@ -30,21 +39,966 @@
 //! * There is a line in the specification which says that properties of elements and new types
 //!   must be enclosed in angle brackets, the BNF it gives specifies parentheses, and every example
 //!   uses square brackets. We accept square brackets only.
+//! * Drafts of the specification differ over whether the header block is opened with `declare` or
+//!   `define`, and EDTDs in the wild put their blocks in any order. We accept either keyword for
+//!   any block, and any number of blocks of each kind, in any order.
 
+#[cfg(feature = "chrono")]
 extern crate chrono;
 extern crate ebml;
-#[macro_use]
+#[cfg(feature = "async")]
+extern crate tokio;
 extern crate nom;
+#[cfg(any(test, feature = "arbitrary"))]
+#[cfg_attr(test, macro_use)]
+extern crate proptest;
 #[macro_use]
 extern crate quote;
 
+pub mod codegen;
+mod date;
+mod deprecation;
+mod document;
+mod dot;
+mod equality;
+mod error_code;
+mod filter;
+#[cfg(test)]
+mod fixtures;
+mod hex;
+mod include;
+#[cfg(feature = "json")]
+mod json;
+mod markdown;
+mod merge;
+mod overlay;
 mod parsers;
+mod print;
+mod range;
+mod render;
+pub mod runtime;
+mod source_map;
+#[cfg(test)]
+mod strategies;
+mod streaming;
+mod suggest;
+mod symbols;
+mod tree;
+mod validation;
+mod versions;
+#[cfg(feature = "xml")]
+mod xml;
+
+use std::borrow::Cow;
+use std::cell::RefCell;
+use std::error::Error;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::ops::Deref;
+use std::str;
+
+use nom::error::ErrorKind;
+
+pub use date::{Date, EbmlDate};
+pub use deprecation::Deprecation;
+pub use document::{DocumentReport, DocumentViolation, ViolationKind};
+pub use dot::DotOptions;
+pub use error_code::{ErrorCode, UnknownErrorCode};
+pub use hex::{from_hex, from_hex_into, HexError};
+pub use include::{DtdFiles, IncludeError, MAX_INCLUDE_DEPTH};
+pub use markdown::MarkdownOptions;
+pub use merge::{MergeConflict, MergeConflictKind, MergeConflicts};
+pub use overlay::Overlay;
+pub use print::PrintOptions;
+pub use range::{ClampRangeItem, RangeItem};
+pub use source_map::SourceMap;
+pub use streaming::{parse_dtd_from_reader, ReadDtdError};
+pub use symbols::Symbol;
+pub use tree::{EbmlNode, EbmlValueTree, ElementRef, NodeValue, WriteTreeError, WriteTreeErrorKind,
+               WriteTreeOptions};
+pub use validation::{Diagnostics, Severity, ValidationDiagnostic};
+
+#[cfg(feature = "fuzzing")]
+#[doc(hidden)]
+pub use parsers::fuzz;
+
+/// A half-open range of byte offsets into the source text of an EDTD.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Span {
+    /// The offset of the first byte covered.
+    pub start: usize,
+    /// The offset one past the last byte covered.
+    pub end: usize,
+}
+
+// A node of the AST along with where in the source it was written.
+#[derive(Debug, Clone)]
+struct Spanned<T> {
+    node: T,
+    span: Span,
+}
+impl<T> Spanned<T> {
+    fn new(node: T, span: Span) -> Self {
+        Spanned { node, span }
+    }
+
+    fn map<U, F: FnOnce(T) -> U>(self, f: F) -> Spanned<U> {
+        Spanned { node: f(self.node), span: self.span }
+    }
+}
+
+impl<T> Deref for Spanned<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.node
+    }
+}
+
+// Where something was written isn't part of what it is, so spans are left out of comparisons and
+// hashes. This lets `parse_dtds` recognize a definition repeated in another input.
+impl<T: PartialEq> PartialEq for Spanned<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.node == other.node
+    }
+}
+impl<T: Eq> Eq for Spanned<T> {}
+impl<T: Hash> Hash for Spanned<T> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.node.hash(state);
+    }
+}
+
+/// A parsed EDTD.
+///
+/// An EDTD may hold the elements of several DocTypes, in element blocks labeled with the DocType
+/// they're for, like `define elements "webm" { ... }`. Those of unlabeled blocks, and of blocks
+/// labeled with the DocType the header declares, are the EDTD's own. The others are only seen
+/// through `select_doc_type`.
+///
+/// Two EDTDs are equal, and hash the same, if they define the same things: where definitions
+/// were written and the comments before them don't count, while the order they were written in
+/// does. Floats are compared by their bits, so `0.0` and `-0.0` differ, and the same NaN is equal
+/// to itself.
+#[derive(Debug, PartialEq, Eq, Hash, Clone)]
+pub struct Dtd<'a> {
+    header: Header<'a>,
+    types: Vec<Spanned<NewType<'a>>>,
+    elements: Vec<Spanned<Element<'a>>>,
+    // The elements of blocks labeled with other DocTypes, by label, in the order the labels were
+    // first written.
+    doc_types: Vec<(&'a str, Vec<Spanned<Element<'a>>>)>,
+    // The names of the elements and types, and those referred to, each held once. Interned by
+    // `assembled`, like the fingerprint.
+    symbols: symbols::Symbols<'a>,
+    // The fingerprint of the rest, worked out by `assembled` whenever it's assembled or changed.
+    content_hash: u64,
+}
+impl<'a> Dtd<'a> {
+    fn new(header: Header<'a>, types: Vec<Spanned<NewType<'a>>>,
+           elements: Vec<Spanned<Element<'a>>>)
+        -> Self
+    {
+        Dtd::from_blocks(header, types, vec![(None, elements)])
+    }
+
+    // Like `new`, but from element blocks which may be labeled with a DocType. Blocks with the
+    // same label are joined.
+    fn from_blocks(header: Header<'a>, types: Vec<Spanned<NewType<'a>>>,
+                   blocks: Vec<(Option<&'a str>, Vec<Spanned<Element<'a>>>)>)
+        -> Self
+    {
+        let mut dtd = Dtd {
+            header,
+            types,
+            elements: Vec::new(),
+            doc_types: Vec::new(),
+            symbols: Default::default(),
+            content_hash: 0,
+        };
+        for (label, elements) in blocks {
+            let elements: Vec<_> = elements.into_iter()
+                .map(|e| e.map(|e| dtd.resolve_properties(e)))
+                .collect();
+            match label {
+                Some(label) if dtd.doc_type() != Some(label) => {
+                    match dtd.doc_types.iter_mut().find(|group| group.0 == label) {
+                        Some(group) => group.1.extend(elements),
+                        None => dtd.doc_types.push((label, elements)),
+                    }
+                }
+                _ => dtd.elements.extend(elements),
+            }
+        }
+        dtd.assembled()
+    }
+
+    // Interns the names the EDTD now holds, and works out the content hash of what it defines.
+    // Everything which assembles an EDTD, or changes what one defines, finishes with this.
+    fn assembled(mut self) -> Self {
+        symbols::intern_names(&mut self);
+        let parts = (&self.header, &self.types, &self.elements, &self.doc_types);
+        self.content_hash = equality::fingerprint(&parts);
+        self
+    }
+
+    /// The DocTypes this EDTD has elements for: the one its header declares, if it does, then
+    /// those of any element blocks labeled with another, in the order they were first written.
+    pub fn doc_types(&self) -> Vec<&str> {
+        self.doc_type().into_iter().chain(self.doc_types.iter().map(|group| group.0)).collect()
+    }
+
+    /// The EDTD of one of the DocTypes in `doc_types`: its elements, with this EDTD's header and
+    /// types, and the header's DocType changed to it. `None` if this EDTD has no elements for
+    /// it.
+    pub fn select_doc_type(&self, doc_type: &str) -> Option<Dtd<'a>> {
+        if self.doc_type() == Some(doc_type) {
+            return Some(Dtd { doc_types: Vec::new(), ..self.clone() }.assembled());
+        }
+        let &(label, ref elements) = self.doc_types.iter().find(|group| group.0 == doc_type)?;
+        let mut header = self.header.clone();
+        let statement = HeaderStatement::String { name: "DocType", value: Cow::Borrowed(label) };
+        match header.iter_mut().find(|statement| statement.name() == "DocType") {
+            Some(existing) => existing.node = statement,
+            None => header.push(Spanned::new(statement, Span { start: 0, end: 0 })),
+        }
+        let dtd = Dtd {
+            header,
+            types: self.types.clone(),
+            elements: elements.clone(),
+            doc_types: Vec::new(),
+            symbols: Default::default(),
+            content_hash: 0,
+        };
+        Some(dtd.assembled())
+    }
+
+    // Parsers only see the input left to parse, so they record where nodes start and end as how
+    // much of it was left there. This turns those into offsets into an input `len` bytes long.
+    fn locate_spans(mut self, len: usize) -> Self {
+        fn locate(span: &mut Span, len: usize) {
+            *span = Span { start: len - span.start, end: len - span.end };
+        }
+        fn locate_element(element: &mut Spanned<Element>, len: usize) {
+            locate(&mut element.span, len);
+            let element = &mut element.node;
+            for property in element.default.iter_mut().chain(element.range.iter_mut()) {
+                locate(&mut property.span, len);
+            }
+            for child in &mut element.children {
+                locate_element(child, len);
+            }
+        }
+
+        for statement in &mut self.header {
+            locate(&mut statement.span, len);
+        }
+        for new_type in &mut self.types {
+            locate(&mut new_type.span, len);
+        }
+        let groups = self.doc_types.iter_mut().flat_map(|group| group.1.iter_mut());
+        for element in self.elements.iter_mut().chain(groups) {
+            locate_element(element, len);
+        }
+        self
+    }
+
+    // Converts the default and range of elements with user-defined types to the type's base type.
+    // Anything which can't be converted is left as-is for validation to complain about.
+    fn resolve_properties(&self, mut element: Element<'a>) -> Element<'a> {
+        if let Some(base) = self.resolve_type(element.type_) {
+            let coerce = |p: Property<'a>| p.coerce(base).unwrap_or_else(|p| p);
+            element.default = element.default.map(|p| p.map(coerce));
+            element.range = element.range.map(|p| p.map(coerce));
+        }
+        element.children = element.children
+            .into_iter()
+            .map(|e| e.map(|e| self.resolve_properties(e)))
+            .collect();
+        element
+    }
+
+    // Maps a type to the built-in type it's implemented with.
+    fn resolve_type(&self, type_: Type<'a>) -> Option<Type<'a>> {
+        match type_ {
+            Type::Name(name) => self.new_type(name).map(NewType::base_type),
+            builtin => Some(builtin),
+        }
+    }
+
+    fn new_type(&self, name: &str) -> Option<&NewType<'a>> {
+        self.types.iter().find(|t| t.name() == name).map(Deref::deref)
+    }
+
+    // The range an element's values must be in: its own if it has one, or else its type's.
+    fn range_of(&self, element: &Element<'a>) -> Option<Property<'a>> {
+        element.range.as_ref().map(|range| range.node.clone()).or_else(|| match element.type_ {
+            Type::Name(name) => self.new_type(name).and_then(NewType::range),
+            _ => None,
+        })
+    }
+
+    // The value of the DocType header statement, if there is one.
+    fn doc_type(&self) -> Option<&str> {
+        match self.header_statement("DocType") {
+            Some(HeaderStatement::String { value, .. }) => Some(value),
+            _ => None,
+        }
+    }
+
+    // The value of an unsigned integer header statement, if there is one.
+    fn header_uint(&self, name: &str) -> Option<u64> {
+        match self.header_statement(name) {
+            Some(&HeaderStatement::Uint { value, .. }) => Some(value),
+            _ => None,
+        }
+    }
+
+    // The header statement called `name`, resolved as by `resolve_header`.
+    fn header_statement(&self, name: &str) -> Option<&HeaderStatement<'a>> {
+        self.header.iter()
+            .find(|statement| statement.name() == name)
+            .and_then(|statement| self.resolve_header(statement))
+    }
+
+    // The statement holding the value of `statement`: itself, unless its value is the name of
+    // another statement, in which case that one's resolved. `None` if a name leads nowhere, or
+    // round in a circle.
+    fn resolve_header<'s>(&'s self, statement: &'s HeaderStatement<'a>)
+        -> Option<&'s HeaderStatement<'a>>
+    {
+        let mut statement = statement;
+        for _ in 0..=self.header.len() {
+            match *statement {
+                HeaderStatement::Named { value, .. } => {
+                    statement = self.header.iter().find(|other| other.name() == value)?;
+                }
+                _ => return Some(statement),
+            }
+        }
+        None
+    }
+
+    // Every element in the DTD, depth-first in declaration order. Those of other DocTypes aren't
+    // included.
+    fn all_elements(&self) -> Vec<&Element<'a>> {
+        flatten(&self.elements)
+    }
+
+    // The elements which may appear directly inside `container`: those nested inside its
+    // definition, followed by any declared elsewhere which name it as a parent.
+    fn children_of<'b>(&'b self, container: &'b Element<'a>) -> Vec<&'b Element<'a>> {
+        let mut children: Vec<_> = container.children.iter().map(Deref::deref).collect();
+        for element in self.all_elements() {
+            let is_parent = element.parent.as_ref()
+                .map_or(false, |parents| parents.contains(&container.name));
+            if is_parent && !children.iter().any(|child| child.name == element.name) {
+                children.push(element);
+            }
+        }
+        children
+    }
+}
+
+// Every element in `elements`, depth-first in declaration order.
+fn flatten<'a, 'b>(elements: &'b [Spanned<Element<'a>>]) -> Vec<&'b Element<'a>> {
+    fn walk<'a, 'b>(elements: &'b [Spanned<Element<'a>>], out: &mut Vec<&'b Element<'a>>) {
+        for element in elements {
+            out.push(element);
+            walk(&element.children, out);
+        }
+    }
+
+    let mut out = Vec::new();
+    walk(elements, &mut out);
+    out
+}
+
+/// Options controlling the behavior of `parse_dtd`.
+#[derive(Debug, Clone, Default)]
+pub struct ParseOptions {
+    /// If set, `Dtd::validate` is run on the parsed EDTD, and parsing fails if any diagnostic at
+    /// or above this severity is reported. If unset, no validation is done.
+    pub validate: Option<Severity>,
+    /// If set, warnings are reported as errors, and fail validation as errors would.
+    pub warnings_as_errors: bool,
+    /// If set, quirks of legacy EDTDs like the original Matroska one are tolerated: `%name;`
+    /// entity references and `#` comments are skipped, the semicolon after a property or header
+    /// statement may be left out before a `]` or `}`, binary defaults may be hex digits in single
+    /// quotes, and keywords (those opening blocks and properties, the built-in types, `yes` and
+    /// `no`) may be written in any case, though names may not. `parse_dtd_from_reader`, which
+    /// keeps what it reads, also reads bytes in comments which aren't UTF-8 as Latin-1; they're
+    /// still an error anywhere else. Each quirk found is a warning, with an `L` code saying which
+    /// it was and a span saying where, which is reported and checked against `validate` along
+    /// with the validation diagnostics.
+    pub lenient: bool,
+}
+
+/// The ways parsing an EDTD can fail.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum DtdError {
+    /// The input is not syntactically valid, or ends in the middle of something.
+    Syntax(ParseError),
+    /// The input parsed, but validation reported problems at or above the requested severity.
+    /// Every diagnostic produced is included, not just the ones which caused the failure.
+    Invalid(Diagnostics),
+    /// Two of the inputs given to `parse_dtds` define something differently.
+    Conflict(MergeConflict),
+}
+
+impl fmt::Display for DtdError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            DtdError::Syntax(ref err) => write!(f, "{}", err),
+            DtdError::Invalid(ref diagnostics) => {
+                f.write_str("invalid EDTD")?;
+                for diagnostic in diagnostics {
+                    write!(f, "\n    {}", diagnostic)?;
+                }
+                Ok(())
+            }
+            DtdError::Conflict(ref conflict) => write!(f, "{}", conflict),
+        }
+    }
+}
+
+impl Error for DtdError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match *self {
+            DtdError::Syntax(ref err) => Some(err),
+            DtdError::Invalid(_) => None,
+            DtdError::Conflict(ref conflict) => Some(conflict),
+        }
+    }
+}
+
+/// Where an EDTD stops being syntactically valid, and what was there.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError {
+    /// The line the error was detected on, counting from 1.
+    pub line: usize,
+    /// The column the error was detected at, counting characters from 1.
+    pub column: usize,
+    /// How far into the input, in bytes, the error was detected.
+    pub byte_offset: usize,
+    /// Descriptions of what the parser would have accepted there, like `"a number"`.
+    pub expected: Vec<&'static str>,
+    /// The text found there instead, up to the next whitespace. Empty at the end of the input.
+    pub found: String,
+    /// The constructs the error is inside of, outermost first.
+    pub context: Vec<ErrorContext>,
+}
+
+/// A construct a `ParseError` was found inside of, like a definition or one of its properties.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ErrorContext {
+    /// What it is, like `"element definition"` or `"range property"`.
+    pub construct: &'static str,
+    /// For definitions and header statements, the name being defined.
+    pub name: Option<String>,
+    /// How far into the input, in bytes, it starts.
+    pub byte_offset: usize,
+    kind: parsers::Context,
+}
+
+impl ParseError {
+    // The furthest position into the input recorded in a (verbose) nom error, what the parsers
+    // which failed there expected, and what they were parsing. Only the first `len` bytes of
+    // `input` were given to `parse_dtd`; errors after them are at the end of the input.
+    fn new(input: &str, err: &parsers::Error, len: usize) -> Self {
+        let failures = error_positions(input, err);
+        let furthest = failures.iter().map(|failure| failure.offset).max().unwrap_or(0);
+        let byte_offset = furthest.min(len);
+        let furthest: Vec<_> = failures.iter().filter(|f| f.offset == furthest).collect();
+        let contexts = furthest.first().map_or(&[][..], |failure| &failure.contexts[..]);
+
+        // Where there were several options and none fit, what's expected depends on the
+        // construct.
+        let mut expected = Vec::new();
+        for failure in &furthest {
+            let description = match (failure.kind, failure.contexts.last()) {
+                (ErrorKind::Alt, Some(&(_, context))) => context.expected(),
+                (kind, _) => expected_description(kind),
+            };
+            if !expected.contains(&description) {
+                expected.push(description);
+            }
+        }
+        // Constructs which would start after the end of the input aren't in it.
+        let contexts: Vec<_> = contexts.iter().filter(|&&(offset, _)| offset < len).collect();
+        let context = contexts.iter()
+            .map(|&&(offset, context)| ErrorContext {
+                construct: context.construct(),
+                name: if context.is_definition() {
+                    parsers::definition_name(&input[offset..]).map(str::to_string)
+                } else {
+                    None
+                },
+                byte_offset: offset,
+                kind: context,
+            })
+            .collect();
+
+        let (line, column) = SourceMap::new(input).line_column(byte_offset);
+        let found = input[byte_offset..len].split_whitespace().next().unwrap_or("").to_string();
+        ParseError {
+            line,
+            column,
+            byte_offset,
+            expected,
+            found,
+            context,
+        }
+    }
+
+    // Parsers which need more than the input has either say so or give up on what they can see,
+    // so the input is parsed again followed by bytes nothing accepts. If that fails further in,
+    // the input just ended too soon, and the error is at its end, in whatever wanted more.
+    fn from_failure(input: &str, err: Option<&parsers::Error>) -> Self {
+        let len = input.len();
+        let padded = format!("{}\n\0", input);
+        let truncated = match parsers::dtd(&padded) {
+            Err(nom::Err::Error(ref err)) => Some(ParseError::new(&padded, err, len)),
+            _ => None,
+        };
+        match (err.map(|err| ParseError::new(input, err, len)), truncated) {
+            (Some(err), Some(truncated)) => {
+                if truncated.byte_offset > err.byte_offset { truncated } else { err }
+            }
+            (Some(err), None) => err,
+            (None, Some(truncated)) => truncated,
+            (None, None) => ParseError::at_end(input),
+        }
+    }
+
+    // At the end of the input, not knowing what was being parsed.
+    fn at_end(input: &str) -> Self {
+        let err = parsers::Error {
+            failures: vec![(&input[input.len()..], parsers::Kind::Parser(ErrorKind::Complete))],
+            at_end: true,
+        };
+        ParseError::new(input, &err, input.len())
+    }
+
+    // Where `input` stops being UTF-8, `offset` bytes in.
+    fn invalid_utf8(input: &[u8], offset: usize) -> Self {
+        let (line, column) = SourceMap::new(input).line_column(offset);
+        let found = String::from_utf8_lossy(&input[offset..]).split_whitespace().next()
+            .unwrap_or("")
+            .to_string();
+        ParseError {
+            line,
+            column,
+            byte_offset: offset,
+            expected: vec![UTF8_TEXT],
+            found,
+            context: Vec::new(),
+        }
+    }
+
+    /// Describes the error without saying where it is: the constructs it's inside of, what was
+    /// expected and what was found. If the input ended, it's what was being parsed when it did.
+    pub fn message(&self) -> String {
+        if self.found.is_empty() {
+            return match self.context.last() {
+                Some(context) => {
+                    format!("unexpected end of input while parsing {}", context.describe())
+                }
+                None => "unexpected end of input".to_string(),
+            };
+        }
+
+        let mut message = String::new();
+        for (index, context) in self.context.iter().enumerate() {
+            let separator = if index + 1 == self.context.len() { ": " } else { ", " };
+            message.push_str(&format!("{}{}", context, separator));
+        }
+        let expected = self.expected.join(" or ");
+        message.push_str(&format!("expected {}, found `{}`", expected, self.found));
+        message
+    }
+
+    /// The category of the error, which depends on the construct it's innermost in.
+    pub fn code(&self) -> ErrorCode {
+        match self.context.last() {
+            _ if self.found.is_empty() => ErrorCode::UnexpectedEnd,
+            _ if self.expected == [UTF8_TEXT] => ErrorCode::InvalidUtf8,
+            Some(context) => context.kind.code(),
+            None => ErrorCode::UnexpectedInput,
+        }
+    }
+
+    /// The error as a `Severity::Error` diagnostic about the innermost definition it's in, so it
+    /// can be reported alongside what validation finds.
+    pub fn to_diagnostic(&self) -> ValidationDiagnostic {
+        let subject = self.context.iter().rev()
+            .filter_map(|context| context.name.as_ref())
+            .next()
+            .map_or("EDTD", String::as_str);
+        let span = Span { start: self.byte_offset, end: self.byte_offset + self.found.len() };
+        ValidationDiagnostic::error(self.code(), subject, self.message()).with_span(span)
+    }
+
+    /// Renders the error like rustc does, with the line of `source` it's on and what was found
+    /// there underlined. `source` must be the text which was parsed.
+    pub fn render(&self, source: &str) -> String {
+        let span = Span { start: self.byte_offset, end: self.byte_offset + self.found.len() };
+        render::render(source, &format!("error[{}]: {}", self.code(), self.message()), span)
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "error[{}]: line {}, column {}: {}", self.code(), self.line, self.column,
+               self.message())
+    }
+}
+
+impl Error for ParseError {}
+
+impl ErrorContext {
+    // The construct, and its name if it has one.
+    fn describe(&self) -> String {
+        match self.name {
+            Some(ref name) => format!("{} `{}`", self.construct, name),
+            None => self.construct.to_string(),
+        }
+    }
+}
+
+impl fmt::Display for ErrorContext {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "in {}", self.describe())
+    }
+}
 
-use chrono::NaiveDateTime;
+// What an input which isn't UTF-8 is expected to be, which nothing else expects.
+const UTF8_TEXT: &str = "UTF-8 text";
 
-type Header<'a> = Vec<HeaderStatement<'a>>;
+// Reads `input` as the text the parsers work on, which must be UTF-8.
+fn utf8<S: AsRef<[u8]> + ?Sized>(input: &S) -> Result<&str, ParseError> {
+    let input = input.as_ref();
+    str::from_utf8(input).map_err(|err| ParseError::invalid_utf8(input, err.valid_up_to()))
+}
+
+// Reads `input` as text leniently: bytes which aren't UTF-8 are read as Latin-1 if they're in a
+// comment, with a warning about each run of them, spanning what they became in the text. Those
+// anywhere else, like in a string, would change what the EDTD says, so they're still an error.
+fn latin1_comments(input: &[u8]) -> Result<(Cow<str>, Diagnostics), ParseError> {
+    let mut text = String::new();
+    let mut warnings = Diagnostics::new();
+    let mut start = 0;
+    loop {
+        let err = match str::from_utf8(&input[start..]) {
+            Ok(rest) if start == 0 => return Ok((Cow::Borrowed(rest), warnings)),
+            Ok(rest) => {
+                text.push_str(rest);
+                return Ok((Cow::Owned(text), warnings));
+            }
+            Err(err) => err,
+        };
+        let valid = start + err.valid_up_to();
+        text.push_str(str::from_utf8(&input[start..valid]).unwrap());
+        if !parsers::ends_in_comment(&text, &parsers::State::new(true)) {
+            return Err(ParseError::invalid_utf8(input, valid));
+        }
+        start = valid + err.error_len().unwrap_or(input.len() - valid);
+        let decoded = text.len();
+        text.extend(input[valid..start].iter().map(|&b| b as char));
+        let span = Span { start: decoded, end: text.len() };
+        let message = "read bytes in a comment which aren't UTF-8 as Latin-1";
+        warnings.push(
+            ValidationDiagnostic::warning(ErrorCode::Latin1Comment, "EDTD", message).with_span(span)
+        );
+    }
+}
+
+// Describes what a parser which failed with `kind` was looking for.
+fn expected_description(kind: ErrorKind) -> &'static str {
+    use nom::error::ErrorKind::*;
+
+    match kind {
+        Tag | TagClosure | Char | OneOf => "a keyword or punctuation",
+        Alt | Switch | Permutation => "one of several alternatives",
+        Many1 | ManyTill | ManyMN | SeparatedList | SeparatedNonEmptyList | Count => "another item",
+        MapRes | MapOpt | Verify => "a valid value",
+        Digit => "a number",
+        HexDigit => "a hexadecimal number",
+        Alpha | AlphaNumeric => "a name",
+        Space | MultiSpace => "whitespace",
+        Eof => "the end of the input",
+        TakeUntil => "a terminator",
+        _ => "something else",
+    }
+}
+
+/// Parses an EDTD, optionally validating it too. The input may be a `str`, or bytes which must be
+/// UTF-8. It has nowhere to include other files from, so an `include` statement is a syntax error;
+/// `DtdFiles` reads EDTD files which have them.
+pub fn parse_dtd<'a, S>(input: &'a S, options: &ParseOptions) -> Result<Dtd<'a>, DtdError>
+    where S: AsRef<[u8]> + ?Sized
+{
+    let input = utf8(input).map_err(DtdError::Syntax)?;
+    parse_text(input, options, Diagnostics::new())
+}
+
+// Parses and validates `input` as `parse_dtd` does. `warnings` are about reading it, and are
+// reported along with the quirks and validation diagnostics.
+fn parse_text<'a>(input: &'a str, options: &ParseOptions, mut warnings: Diagnostics)
+    -> Result<Dtd<'a>, DtdError>
+{
+    let (result, quirks) = parse_with(input, options, |state| {
+        parsers::recovering_dtd(input, state, None)
+    });
+    let dtd = match result {
+        Ok(dtd) => dtd,
+        Err(nom::Err::Error(err)) | Err(nom::Err::Failure(err)) => {
+            return Err(DtdError::Syntax(ParseError::from_failure(input, Some(&err))))
+        }
+        Err(nom::Err::Incomplete(_)) => {
+            return Err(DtdError::Syntax(ParseError::from_failure(input, None)))
+        }
+    };
+
+    if let Some(threshold) = options.validate {
+        if options.warnings_as_errors {
+            warnings.promote_warnings();
+        }
+        let mut diagnostics = warnings;
+        diagnostics.extend(quirks);
+        diagnostics.extend(validate(input, &dtd, options));
+        if diagnostics.max_severity() >= Some(threshold) {
+            return Err(DtdError::Invalid(diagnostics));
+        }
+    }
+
+    Ok(dtd)
+}
+
+/// Parses and validates an EDTD, collecting everything found in either phase into one report
+/// rather than stopping at the first problem. Syntax errors in definitions are recovered from as
+/// `parse_dtd_recovering` does; an EDTD with any is returned without being validated, as most of
+/// what validation found in what's left would be about the missing definitions. Otherwise it's
+/// validated if `options.validate` is set, and returned unless a diagnostic reached that severity.
+pub fn check_dtd<'a, S>(input: &'a S, options: &ParseOptions) -> (Option<Dtd<'a>>, Diagnostics)
+    where S: AsRef<[u8]> + ?Sized
+{
+    let input = match utf8(input) {
+        Ok(input) => input,
+        Err(err) => return (None, Some(err.to_diagnostic()).into_iter().collect()),
+    };
+    let ((dtd, errors), mut diagnostics) = parse_with(input, options, |state| {
+        recovering(input, state)
+    });
+    if !errors.is_empty() {
+        diagnostics.extend(errors.iter().map(ParseError::to_diagnostic));
+        return (None, diagnostics);
+    }
+
+    match options.validate {
+        Some(threshold) => {
+            diagnostics.extend(validate(input, &dtd, options));
+            if diagnostics.max_severity() >= Some(threshold) {
+                (None, diagnostics)
+            } else {
+                (Some(dtd), diagnostics)
+            }
+        }
+        None => (Some(dtd), diagnostics),
+    }
+}
+
+// Runs `parse` over `input`, leniently if `options` say to, returning the quirks it tolerated as
+// warnings.
+fn parse_with<T, F>(input: &str, options: &ParseOptions, parse: F) -> (T, Diagnostics)
+    where F: FnOnce(&parsers::State) -> T
+{
+    let state = parsers::State::new(options.lenient);
+    let result = parse(&state);
+    let mut diagnostics: Diagnostics = state.into_quirks(input.len()).into_iter()
+        .map(|(code, span)| {
+            let message = match code {
+                ErrorCode::EntityReference => "skipped an entity reference",
+                ErrorCode::MissingSemicolon => "a semicolon is missing here",
+                ErrorCode::QuotedBinary => "read a binary default in single quotes as hex",
+                ErrorCode::KeywordCase => "a keyword is written in a case other than lowercase",
+                _ => "skipped a `#` comment",
+            };
+            ValidationDiagnostic::warning(code, "EDTD", message).with_span(span)
+        })
+        .collect();
+    if options.warnings_as_errors {
+        diagnostics.promote_warnings();
+    }
+    (result, diagnostics)
+}
+
+// Validates `dtd`, which was parsed from `input`, giving diagnostics about definitions their spans.
+fn validate(input: &str, dtd: &Dtd, options: &ParseOptions) -> Diagnostics {
+    let mut diagnostics: Diagnostics = dtd.validate().into_iter()
+        .map(|d| match definition_span(input.as_bytes(), dtd, d.subject()) {
+            Some(span) if d.span().is_none() => d.with_span(span),
+            _ => d,
+        })
+        .collect();
+    if options.warnings_as_errors {
+        diagnostics.promote_warnings();
+    }
+    diagnostics
+}
+
+/// Parses an EDTD like `parse_dtd`, but rather than stopping at the first syntax error in a
+/// definition, records it, skips the rest of the definition, and carries on. Returns every
+/// definition which parsed along with every error found, in order, so a linter can report them
+/// all at once. An error outside of any definition can't be skipped past; it's the last error,
+/// and the `Dtd` is then empty, as it is if the input is bytes which aren't UTF-8. No validation
+/// is done.
+pub fn parse_dtd_recovering<S: AsRef<[u8]> + ?Sized>(input: &S) -> (Dtd<'_>, Vec<ParseError>) {
+    let input = match utf8(input) {
+        Ok(input) => input,
+        Err(err) => return (Dtd::new(Vec::new(), Vec::new(), Vec::new()), vec![err]),
+    };
+    recovering(input, &parsers::State::new(false))
+}
 
-#[derive(Debug, PartialEq)]
+// Parses `input` as `parse_dtd_recovering` does, leniently if `state` is.
+fn recovering<'a>(input: &'a str, state: &parsers::State) -> (Dtd<'a>, Vec<ParseError>) {
+    let errors = RefCell::new(Vec::new());
+    let result = parsers::recovering_dtd(input, state, Some(&errors));
+    let mut errors: Vec<_> = errors.into_inner().iter()
+        .map(|err| ParseError::new(input, err, input.len()))
+        .collect();
+    let fatal = match result {
+        Ok(dtd) => return (dtd, errors),
+        Err(nom::Err::Error(err)) | Err(nom::Err::Failure(err)) => {
+            ParseError::new(input, &err, input.len())
+        }
+        Err(nom::Err::Incomplete(_)) => ParseError::at_end(input),
+    };
+    errors.push(fatal);
+    (Dtd::new(Vec::new(), Vec::new(), Vec::new()), errors)
+}
+
+/// Parses several EDTDs and merges them into one, as if their blocks had been written one after
+/// another, optionally validating the result.
+///
+/// Definitions identical to earlier ones (apart from comments) are dropped, so a fragment can be
+/// included by schemas which may already define some of it. Header statements, types or
+/// elements with the same name but different definitions, and elements with the same id, are a
+/// `DtdError::Conflict`, holding the first conflict found. Errors come with the index of the
+/// input they're in. Validation diagnostics are given spans in the input the first of them is
+/// about, and those about other inputs are left without one. `options.lenient` applies to each
+/// input, but the quirks found aren't reported.
+pub fn parse_dtds<'a, S>(inputs: &[&'a S], options: &ParseOptions)
+    -> Result<Dtd<'a>, (usize, DtdError)>
+    where S: AsRef<[u8]> + ?Sized
+{
+    let mut merger = merge::Merger::default();
+    for (index, input) in inputs.iter().enumerate() {
+        let lenient = ParseOptions { lenient: options.lenient, ..Default::default() };
+        let dtd = parse_dtd(*input, &lenient).map_err(|err| (index, err))?;
+        merger.add(dtd, index)
+            .map_err(|conflicts| (index, DtdError::Conflict(conflicts.into_vec().remove(0))))?;
+    }
+    let dtd = merger.finish();
+
+    if let Some(threshold) = options.validate {
+        let mut diagnostics = dtd.validate();
+        if options.warnings_as_errors {
+            diagnostics.promote_warnings();
+        }
+        if diagnostics.max_severity() >= Some(threshold) {
+            // Each diagnostic is about the definition in the first input which has it.
+            let locate = |d: &ValidationDiagnostic| inputs.iter().enumerate()
+                .filter_map(|(index, input)| {
+                    definition_span(input.as_ref(), &dtd, d.subject()).map(|span| (index, span))
+                })
+                .next();
+            let index = diagnostics.iter().filter_map(&locate).map(|(index, _)| index).next();
+            let diagnostics = diagnostics.into_iter()
+                .map(|d| match locate(&d) {
+                    Some((found, span)) if Some(found) == index && d.span().is_none() => {
+                        d.with_span(span)
+                    }
+                    _ => d,
+                })
+                .collect();
+            return Err((index.unwrap_or(0), DtdError::Invalid(diagnostics)));
+        }
+    }
+
+    Ok(dtd)
+}
+
+/// Parses an overlay, which `Dtd::apply_overlay` derives one EDTD from another with. Like
+/// `parse_dtd`, it takes a `str` or UTF-8 bytes.
+pub fn parse_overlay<S: AsRef<[u8]> + ?Sized>(input: &S) -> Result<Overlay<'_>, DtdError> {
+    overlay::parse(utf8(input).map_err(DtdError::Syntax)?)
+}
+
+// The span of the name in the last definition of the element or type called `name`, which is
+// the one a diagnostic about it is most likely to be about. Names are slices of the input, so
+// they can be found by where they point.
+fn definition_span(input: &[u8], dtd: &Dtd, name: &str) -> Option<Span> {
+    let groups = dtd.doc_types.iter().flat_map(|group| flatten(&group.1));
+    let elements = dtd.all_elements().into_iter().chain(groups).map(|element| element.name);
+    let types = dtd.types.iter().map(|new_type| new_type.name());
+    let found = elements.chain(types).filter(|&found| found == name).last()?;
+    let start = (found.as_ptr() as usize).checked_sub(input.as_ptr() as usize)?;
+    if start + found.len() <= input.len() {
+        Some(Span { start, end: start + found.len() })
+    } else {
+        None
+    }
+}
+
+// A parser's failure recorded in an error: where it was, the kind of parser, and the contexts it
+// was inside of, with where they start, outermost first.
+struct Failure {
+    offset: usize,
+    kind: ErrorKind,
+    contexts: Vec<(usize, parsers::Context)>,
+}
+
+// Collects every failure recorded in an error. Its chain is innermost first, so the contexts a
+// failure is inside of are those after it.
+fn error_positions(input: &str, err: &parsers::Error) -> Vec<Failure> {
+    let links: Vec<_> = err.failures.iter()
+        .map(|&(rest, kind)| (input.len() - rest.len(), kind))
+        .collect();
+    let mut failures = Vec::new();
+    for (index, &(offset, kind)) in links.iter().enumerate() {
+        let kind = match kind {
+            parsers::Kind::Parser(kind) => kind,
+            parsers::Kind::Context(_) => continue,
+        };
+        let contexts = links[index + 1..].iter().rev()
+            .filter_map(|&(offset, kind)| match kind {
+                parsers::Kind::Context(context) => Some((offset, context)),
+                parsers::Kind::Parser(_) => None,
+            })
+            .collect();
+        failures.push(Failure { offset, kind, contexts });
+    }
+    failures
+}
+
+// Binary which is UTF-8 as a string, still borrowed if it was, or else the binary back.
+fn into_string(binary: Cow<[u8]>) -> Result<Cow<str>, Cow<[u8]>> {
+    match binary {
+        Cow::Borrowed(bytes) => {
+            str::from_utf8(bytes).map(Cow::Borrowed).map_err(|_| Cow::Borrowed(bytes))
+        }
+        Cow::Owned(bytes) => {
+            String::from_utf8(bytes).map(Cow::Owned).map_err(|err| Cow::Owned(err.into_bytes()))
+        }
+    }
+}
+
+type Header<'a> = Vec<Spanned<HeaderStatement<'a>>>;
+
+#[derive(Debug, Clone)]
 enum HeaderStatement<'a> {
     Int {
         name: &'a str,
@@ -60,15 +1014,15 @@ enum HeaderStatement<'a> {
     },
     Date {
         name: &'a str,
-        value: NaiveDateTime,
+        value: Date,
     },
     String {
         name: &'a str,
-        value: String,
+        value: Cow<'a, str>,
     },
     Binary {
         name: &'a str,
-        value: Vec<u8>,
+        value: Cow<'a, [u8]>,
     },
     Named {
         name: &'a str,
@@ -76,41 +1030,63 @@ enum HeaderStatement<'a> {
     },
 }
 
-#[derive(Debug, PartialEq)]
+impl<'a> HeaderStatement<'a> {
+    fn name(&self) -> &'a str {
+        match *self {
+            HeaderStatement::Int { name, .. } |
+            HeaderStatement::Uint { name, .. } |
+            HeaderStatement::Float { name, .. } |
+            HeaderStatement::Date { name, .. } |
+            HeaderStatement::String { name, .. } |
+            HeaderStatement::Binary { name, .. } |
+            HeaderStatement::Named { name, .. } => name,
+        }
+    }
+}
+
+// Every variant's `comments` hold the text of the comments just before the definition, as
+// `Element::comments` do, and are left out of comparisons and hashes.
+#[derive(Debug, Clone)]
 enum NewType<'a> {
     Int {
         name: &'a str,
         default: Option<i64>,
         range: Option<IntRange>,
+        comments: Vec<&'a str>,
     },
     Uint {
         name: &'a str,
         default: Option<u64>,
         range: Option<UintRange>,
+        comments: Vec<&'a str>,
     },
     Float {
         name: &'a str,
         default: Option<f64>,
         range: Option<FloatRange>,
+        comments: Vec<&'a str>,
     },
     Date {
         name: &'a str,
-        default: Option<NaiveDateTime>,
+        default: Option<Date>,
         range: Option<DateRange>,
+        comments: Vec<&'a str>,
     },
     String {
         name: &'a str,
-        default: Option<String>,
+        default: Option<Cow<'a, str>>,
         range: Option<StringRange>,
+        comments: Vec<&'a str>,
     },
     Binary {
         name: &'a str,
-        default: Option<Vec<u8>>,
+        default: Option<Cow<'a, [u8]>>,
         range: Option<BinaryRange>,
+        comments: Vec<&'a str>,
     },
 }
 impl<'a> NewType<'a> {
-    fn update<'b>(&mut self, val: Property<'b>) {
+    fn update(&mut self, val: Property<'a>) {
         match val {
             Property::IntDefault(x) => match self {
                 &mut NewType::Int { ref mut default, .. } => *default = Some(x),
@@ -164,15 +1140,149 @@ impl<'a> NewType<'a> {
             _ => unreachable!(),
         }
     }
+
+    fn name(&self) -> &'a str {
+        match *self {
+            NewType::Int { name, .. } |
+            NewType::Uint { name, .. } |
+            NewType::Float { name, .. } |
+            NewType::Date { name, .. } |
+            NewType::String { name, .. } |
+            NewType::Binary { name, .. } => name,
+        }
+    }
+
+    fn comments(&self) -> &[&'a str] {
+        match *self {
+            NewType::Int { ref comments, .. } |
+            NewType::Uint { ref comments, .. } |
+            NewType::Float { ref comments, .. } |
+            NewType::Date { ref comments, .. } |
+            NewType::String { ref comments, .. } |
+            NewType::Binary { ref comments, .. } => comments,
+        }
+    }
+
+    fn range(&self) -> Option<Property<'a>> {
+        match *self {
+            NewType::Int { ref range, .. } => range.clone().map(Property::IntRange),
+            NewType::Uint { ref range, .. } => range.clone().map(Property::UintRange),
+            NewType::Float { ref range, .. } => range.clone().map(Property::FloatRange),
+            NewType::Date { ref range, .. } => range.clone().map(Property::DateRange),
+            NewType::String { ref range, .. } => range.clone().map(Property::StringRange),
+            NewType::Binary { ref range, .. } => range.clone().map(Property::BinaryRange),
+        }
+    }
+
+    // The built-in type this is an alias of.
+    fn base_type(&self) -> Type<'static> {
+        match *self {
+            NewType::Int { .. } => Type::Int,
+            NewType::Uint { .. } => Type::Uint,
+            NewType::Float { .. } => Type::Float,
+            NewType::Date { .. } => Type::Date,
+            NewType::String { .. } => Type::String,
+            NewType::Binary { .. } => Type::Binary,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Element<'a> {
+    name: &'a str,
+    // The id in its encoded form, including the length marker bits.
+    id: u32,
+    type_: Type<'a>,
+    parent: Option<Vec<&'a str>>,
+    level: Option<Level>,
+    cardinality: Option<Cardinality>,
+    size: Option<SizeList>,
+    ordered: Option<bool>,
+    unknown_size_allowed: Option<bool>,
+    // The names given to values of a `uint` by the `variants` extension property, which isn't
+    // part of the EDTD specification.
+    variants: Option<Vec<(&'a str, u64)>>,
+    // The first and last DocTypeVersions the element is part of, from the `minver` and `maxver`
+    // extension properties.
+    minver: Option<u64>,
+    maxver: Option<u64>,
+    // These always hold one of the *Default or *Range variants of Property, respectively.
+    default: Option<Spanned<Property<'a>>>,
+    range: Option<Spanned<Property<'a>>>,
+    children: Vec<Spanned<Element<'a>>>,
+    // The text of the comments just before the definition, without their delimiters. These are
+    // left out of comparisons and hashes.
+    comments: Vec<&'a str>,
+}
+impl<'a> Element<'a> {
+    fn new(name: &'a str, id: u32, type_: Type<'a>) -> Self {
+        Element {
+            name,
+            id,
+            type_,
+            parent: None,
+            level: None,
+            cardinality: None,
+            size: None,
+            ordered: None,
+            unknown_size_allowed: None,
+            variants: None,
+            minver: None,
+            maxver: None,
+            default: None,
+            range: None,
+            children: Vec::new(),
+            comments: Vec::new(),
+        }
+    }
+
+    fn update(&mut self, val: Spanned<Property<'a>>) {
+        match val.node {
+            Property::Parent(x) => self.parent = Some(x),
+            Property::Level(x) => self.level = Some(x),
+            Property::Cardinality(x) => self.cardinality = Some(x),
+            Property::Size(x) => self.size = Some(x),
+            Property::Ordered(x) => self.ordered = Some(x),
+            Property::UnknownSizeAllowed(x) => self.unknown_size_allowed = Some(x),
+            Property::Variants(x) => self.variants = Some(x),
+            Property::MinVer(x) => self.minver = Some(x),
+            Property::MaxVer(x) => self.maxver = Some(x),
+            x => if x.is_default() {
+                self.default = Some(Spanned::new(x, val.span))
+            } else {
+                self.range = Some(Spanned::new(x, val.span))
+            },
+        }
+    }
+
+    // The spec says an element with no cardinality may appear any number of times.
+    fn cardinality(&self) -> Cardinality {
+        self.cardinality.clone().unwrap_or(Cardinality::ZeroOrMany)
+    }
+
+    // Whether children must appear in the order they're declared in. Only `ordered:yes` says so.
+    fn is_ordered(&self) -> bool {
+        self.ordered == Some(true)
+    }
+
+    // Whether the element, and unknown elements inside it, may have an unknown size. Only
+    // `unknownsizeallowed:yes` says so.
+    fn allows_unknown_size(&self) -> bool {
+        self.unknown_size_allowed == Some(true)
+    }
 }
 
-#[derive(Debug, PartialEq, Clone)]
+#[derive(Debug, Clone)]
 enum Property<'a> {
     Parent(Vec<&'a str>),
     Level(Level),
     Cardinality(Cardinality),
     Size(SizeList),
     Ordered(bool),
+    UnknownSizeAllowed(bool),
+    Variants(Vec<(&'a str, u64)>),
+    MinVer(u64),
+    MaxVer(u64),
 
     IntDefault(i64),
     IntRange(IntRange),
@@ -183,17 +1293,87 @@ enum Property<'a> {
     FloatDefault(f64),
     FloatRange(FloatRange),
 
-    DateDefault(NaiveDateTime),
+    DateDefault(Date),
     DateRange(DateRange),
 
-    StringDefault(String),
+    StringDefault(Cow<'a, str>),
     StringRange(StringRange),
 
-    BinaryDefault(Vec<u8>),
+    BinaryDefault(Cow<'a, [u8]>),
     BinaryRange(BinaryRange),
 }
+impl<'a> Property<'a> {
+    fn is_default(&self) -> bool {
+        match *self {
+            Property::IntDefault(_) |
+            Property::UintDefault(_) |
+            Property::FloatDefault(_) |
+            Property::DateDefault(_) |
+            Property::StringDefault(_) |
+            Property::BinaryDefault(_) => true,
+            _ => false,
+        }
+    }
 
-#[derive(Debug, Eq, PartialEq)]
+    // The type of value a default or range property applies to.
+    fn value_type(&self) -> Option<Type<'static>> {
+        match *self {
+            Property::IntDefault(_) | Property::IntRange(_) => Some(Type::Int),
+            Property::UintDefault(_) | Property::UintRange(_) => Some(Type::Uint),
+            Property::FloatDefault(_) | Property::FloatRange(_) => Some(Type::Float),
+            Property::DateDefault(_) | Property::DateRange(_) => Some(Type::Date),
+            Property::StringDefault(_) | Property::StringRange(_) => Some(Type::String),
+            Property::BinaryDefault(_) | Property::BinaryRange(_) => Some(Type::Binary),
+            _ => None,
+        }
+    }
+
+    // Elements of user-defined types have their defaults and ranges parsed before the type is
+    // known; this converts such a property to the given built-in type, if it makes sense.
+    fn coerce(self, to: Type) -> Result<Property<'a>, Property<'a>> {
+        if self.value_type() == Some(to) {
+            return Ok(self);
+        }
+
+        match (self, to) {
+            (Property::UintDefault(x), Type::Int) if x <= i64::max_value() as u64 => {
+                Ok(Property::IntDefault(x as i64))
+            }
+            (Property::UintDefault(x), Type::Float) => Ok(Property::FloatDefault(x as f64)),
+            (Property::IntDefault(x), Type::Float) => Ok(Property::FloatDefault(x as f64)),
+            (Property::UintDefault(x), Type::Date) if x <= i64::max_value() as u64 => {
+                Ok(Property::DateDefault(date::from_nanos(x as i64)))
+            }
+            (Property::IntDefault(x), Type::Date) => {
+                Ok(Property::DateDefault(date::from_nanos(x)))
+            }
+            (Property::BinaryDefault(x), Type::String) => {
+                into_string(x).map(Property::StringDefault).map_err(Property::BinaryDefault)
+            }
+            (Property::UintRange(r), Type::Int) => {
+                match r.iter().map(UintRangeItem::to_int_range_item).collect() {
+                    Some(items) => Ok(Property::IntRange(items)),
+                    None => Err(Property::UintRange(r)),
+                }
+            }
+            (Property::UintRange(r), Type::String) => {
+                match r.iter().map(UintRangeItem::to_string_range_item).collect() {
+                    Some(items) => Ok(Property::StringRange(items)),
+                    None => Err(Property::UintRange(r)),
+                }
+            }
+            (Property::UintRange(r), Type::Binary) => {
+                match r.iter().map(UintRangeItem::to_binary_range_item).collect() {
+                    Some(items) => Ok(Property::BinaryRange(items)),
+                    None => Err(Property::UintRange(r)),
+                }
+            }
+            (prop, _) => Err(prop),
+        }
+    }
+}
+
+#[derive(Debug, Eq, PartialEq, Hash, Clone, Copy)]
 enum Type<'a> {
     Int,
     Uint,
@@ -204,8 +1384,33 @@ enum Type<'a> {
     Container,
     Name(&'a str),
 }
+impl<'a> Type<'a> {
+    fn builtin(name: &str) -> Option<Type<'static>> {
+        BUILTIN_TYPES.iter().find(|&&(keyword, _)| keyword == name).map(|&(_, t)| t)
+    }
 
-#[derive(Debug, Eq, PartialEq, Clone)]
+    // The name this type is referred to by in an EDTD.
+    fn name(&self) -> &'a str {
+        match *self {
+            Type::Name(name) => name,
+            builtin => BUILTIN_TYPES.iter().find(|&&(_, t)| t == builtin).unwrap().0,
+        }
+    }
+}
+
+// The keywords naming the built-in types. These take precedence over user-defined type names, so
+// a type defined with one of these names can never be referred to.
+const BUILTIN_TYPES: &[(&str, Type<'static>)] = &[
+    ("int", Type::Int),
+    ("uint", Type::Uint),
+    ("float", Type::Float),
+    ("string", Type::String),
+    ("date", Type::Date),
+    ("binary", Type::Binary),
+    ("container", Type::Container),
+];
+
+#[derive(Debug, Eq, PartialEq, Hash, Clone)]
 enum Level {
     Bounded {
         start: u64,
@@ -216,35 +1421,68 @@ enum Level {
     },
 }
 
-#[derive(Debug, Eq, PartialEq, Clone)]
-enum IntRangeItem {
+/// One item of the `range:` of an `int` element.
+#[derive(Debug, Eq, PartialEq, Hash, Clone)]
+pub enum IntRangeItem {
+    /// Exactly this value.
     Single(i64),
+    /// This value or greater.
     From {
+        /// The smallest value allowed.
         start: i64,
     },
+    /// This value or less.
     To {
+        /// The largest value allowed.
         end: i64,
     },
+    /// Between two values, inclusive.
     Bounded {
+        /// The smallest value allowed.
         start: i64,
+        /// The largest value allowed.
         end: i64,
     },
 }
 type IntRange = Vec<IntRangeItem>;
 
-#[derive(Debug, Eq, PartialEq, Clone)]
-enum UintRangeItem {
+/// One item of the `range:` of a `uint` element, or of a `size:` list.
+#[derive(Debug, Eq, PartialEq, Hash, Clone)]
+pub enum UintRangeItem {
+    /// Exactly this value.
     Single(u64),
+    /// This value or greater.
     From {
+        /// The smallest value allowed.
         start: u64,
     },
     // There is no To for unsigned integers
+    /// Between two values, inclusive.
     Bounded {
+        /// The smallest value allowed.
         start: u64,
+        /// The largest value allowed.
         end: u64,
     },
 }
 impl UintRangeItem {
+    fn to_int_range_item(&self) -> Option<IntRangeItem> {
+        use UintRangeItem::*;
+
+        let max = i64::max_value() as u64;
+        match *self {
+            Single(x) if x <= max => Some(IntRangeItem::Single(x as i64)),
+            From { start } if start <= max => Some(IntRangeItem::From { start: start as i64 }),
+            Bounded { start, end } if start <= max && end <= max => {
+                Some(IntRangeItem::Bounded {
+                    start: start as i64,
+                    end: end as i64
+                })
+            }
+            _ => None
+        }
+    }
+
     // binary range items must only think of a single byte
     fn to_binary_range_item(&self) -> Option<BinaryRangeItem> {
         use UintRangeItem::*;
@@ -288,73 +1526,129 @@ impl UintRangeItem {
     }
 }
 type UintRange = Vec<UintRangeItem>;
-type SizeList = Vec<UintRangeItem>;
 
-#[derive(Debug, PartialEq, Clone)]
-enum FloatRangeItem {
+/// The sizes, in bytes, which the body of an element may have: the value of its `size:`
+/// property. A size is allowed if any of the items contain it.
+#[derive(Debug, Eq, PartialEq, Hash, Clone)]
+pub struct SizeList(Cow<'static, [UintRangeItem]>);
+
+/// One item of the `range:` of a `float` element.
+///
+/// Items are compared and hashed with their bounds' bits, as `Dtd` compares floats.
+#[derive(Debug, Clone)]
+pub enum FloatRangeItem {
+    /// Above a value.
     From {
+        /// The lower bound.
         start: f64,
+        /// Whether the lower bound itself is allowed.
         include_start: bool,
     },
+    /// Below a value.
     To {
+        /// The upper bound.
         end: f64,
+        /// Whether the upper bound itself is allowed.
         include_end: bool,
     },
+    /// Between two values.
     Bounded {
+        /// The lower bound.
         start: f64,
+        /// Whether the lower bound itself is allowed.
         include_start: bool,
+        /// The upper bound.
         end: f64,
+        /// Whether the upper bound itself is allowed.
         include_end: bool,
     },
 }
 type FloatRange = Vec<FloatRangeItem>;
 
-#[derive(Debug, Eq, PartialEq, Clone)]
-enum DateRangeItem {
+/// One item of the `range:` of a `date` element.
+#[derive(Debug, Eq, PartialEq, Hash, Clone)]
+pub enum DateRangeItem {
+    /// This date or later.
     From {
-        start: NaiveDateTime,
+        /// The earliest date allowed.
+        start: Date,
     },
+    /// This date or earlier.
     To {
-        end: NaiveDateTime,
+        /// The latest date allowed.
+        end: Date,
     },
+    /// Between two dates, inclusive.
     Bounded {
-        start: NaiveDateTime,
-        end: NaiveDateTime,
+        /// The earliest date allowed.
+        start: Date,
+        /// The latest date allowed.
+        end: Date,
     },
 }
 type DateRange = Vec<DateRangeItem>;
 
 // This uses u32 since the values are Unicode code points, not bytes.
-#[derive(Debug, Eq, PartialEq, Clone)]
-enum StringRangeItem {
+/// One item of the `range:` of a `string` element, which every character must satisfy.
+#[derive(Debug, Eq, PartialEq, Hash, Clone)]
+pub enum StringRangeItem {
+    /// Exactly this code point.
     Single(u32),
+    /// This code point or greater.
     From {
+        /// The smallest code point allowed.
         start: u32,
     },
+    /// Between two code points, inclusive.
     Bounded {
+        /// The smallest code point allowed.
         start: u32,
+        /// The largest code point allowed.
         end: u32,
     },
 }
 type StringRange = Vec<StringRangeItem>;
 
-#[derive(Debug, Eq, PartialEq, Clone)]
-enum BinaryRangeItem {
+/// One item of the `range:` of a `binary` element, which every byte must satisfy.
+#[derive(Debug, Eq, PartialEq, Hash, Clone)]
+pub enum BinaryRangeItem {
+    /// Exactly this byte.
     Single(u8),
+    /// This byte or greater.
     From {
+        /// The smallest byte allowed.
         start: u8,
     },
+    /// Between two bytes, inclusive.
     Bounded {
+        /// The smallest byte allowed.
         start: u8,
+        /// The largest byte allowed.
         end: u8,
     },
 }
 type BinaryRange = Vec<BinaryRangeItem>;
 
-#[derive(Debug, Eq, PartialEq, Clone)]
-enum Cardinality {
+/// How many times an element may occur in its parent: the value of its `card:` property.
+#[derive(Debug, Eq, PartialEq, Hash, Clone)]
+pub enum Cardinality {
+    /// `*`: any number of times.
     ZeroOrMany,
+    /// `?`: at most once.
     ZeroOrOne,
+    /// `1`: exactly once.
     ExactlyOne,
+    /// `+`: at least once.
     OneOrMany,
 }
+impl Cardinality {
+    /// True if an element may occur `count` times in its parent.
+    pub fn allows(&self, count: usize) -> bool {
+        match *self {
+            Cardinality::ZeroOrMany => true,
+            Cardinality::ZeroOrOne => count <= 1,
+            Cardinality::ExactlyOne => count == 1,
+            Cardinality::OneOrMany => count >= 1,
+        }
+    }
+}
@@ -0,0 +1,177 @@
+//! Documents a `Dtd` in GitHub-flavored Markdown.
+
+use std::fmt::Write;
+
+use deprecation::untagged_lines;
+use print::{header_value, property_value, type_properties};
+use {Cardinality, Deprecation, Dtd, Element, Level, Type};
+
+/// Options controlling the layout of `Dtd::to_markdown`.
+#[derive(Debug, Clone)]
+pub struct MarkdownOptions {
+    /// The level of the document's title, so it can be included in a larger document. Sections
+    /// are one level below it, and containers two.
+    pub heading_level: usize,
+    /// Whether the comments before element definitions are included as their descriptions.
+    pub comments: bool,
+}
+
+impl Default for MarkdownOptions {
+    fn default() -> Self {
+        MarkdownOptions { heading_level: 1, comments: true }
+    }
+}
+
+impl<'a> Dtd<'a> {
+    /// Documents this EDTD in GitHub-flavored Markdown.
+    ///
+    /// The document is titled with the DocType, and has a table of the header statements, one
+    /// of the types, and then one of the elements at the top of the document, followed by a
+    /// section for each container, in the order they're defined, with a table of the elements
+    /// which may appear inside it. Each row has an element's id, type, cardinality, level, range
+    /// and default, and its comments as a description. Containers link to their section, by the
+    /// anchor GitHub gives its heading. An `@deprecated` tag in an element's comments starts its
+    /// description with a notice, or marks its name if comments are left out.
+    pub fn to_markdown(&self, options: &MarkdownOptions) -> String {
+        let heading = |level: usize| "#".repeat(options.heading_level + level);
+        let mut out = String::new();
+        writeln!(out, "{} {}", heading(0), self.doc_type().unwrap_or("EDTD")).unwrap();
+
+        if !self.header.is_empty() {
+            writeln!(out, "\n{} Header\n\n| Statement | Value |\n| --- | --- |", heading(1))
+                .unwrap();
+            for statement in &self.header {
+                writeln!(out, "| {} | {} |", statement.name(), code(&header_value(statement)))
+                    .unwrap();
+            }
+        }
+
+        if !self.types.is_empty() {
+            writeln!(out, "\n{} Types\n\n| Type | Base | Properties |\n| --- | --- | --- |",
+                     heading(1)).unwrap();
+            for new_type in &self.types {
+                let (base, properties) = type_properties(new_type);
+                let properties: Vec<_> = properties.iter().map(|p| code(p)).collect();
+                let mut name = new_type.name().to_string();
+                if new_type.deprecation().is_some() {
+                    name.push_str(" *(deprecated)*");
+                }
+                writeln!(out, "| {} | {} | {} |", name, base, properties.join(" ")).unwrap();
+            }
+        }
+
+        if self.elements.is_empty() {
+            return out;
+        }
+        writeln!(out, "\n{} Elements\n", heading(1)).unwrap();
+        let top: Vec<_> = self.elements.iter()
+            .map(|element| &element.node)
+            .filter(|element| element.parent.is_none())
+            .collect();
+        self.table(&mut out, &top, options);
+        for container in self.all_elements() {
+            if container.type_ != Type::Container {
+                continue;
+            }
+            writeln!(out, "\n{} {}\n", heading(2), container.name).unwrap();
+            if options.comments && !container.comments.is_empty() {
+                writeln!(out, "{}\n", description(container)).unwrap();
+            }
+            let children = self.children_of(container);
+            if children.is_empty() {
+                out.push_str("Nothing may appear inside it.\n");
+            } else {
+                self.table(&mut out, &children, options);
+            }
+        }
+        out
+    }
+
+    fn table(&self, out: &mut String, elements: &[&Element], options: &MarkdownOptions) {
+        out.push_str("| Element | Id | Type | Cardinality | Level | Range | Default |");
+        out.push_str(if options.comments { " Description |\n" } else { "\n" });
+        out.push_str("| --- | --- | --- | --- | --- | --- | --- |");
+        out.push_str(if options.comments { " --- |\n" } else { "\n" });
+
+        for element in elements {
+            let mut name = if element.type_ == Type::Container {
+                format!("[{}](#{})", element.name, element.name.to_lowercase())
+            } else {
+                element.name.to_string()
+            };
+            if !options.comments && element.deprecation().is_some() {
+                name.push_str(" *(deprecated)*");
+            }
+            let level = match element.level {
+                Some(Level::Bounded { start, end }) => format!("{}..{}", start, end),
+                Some(Level::Open { start }) => format!("{}..", start),
+                None => String::new(),
+            };
+            let range = self.range_of(element).map(|range| code(&property_value(&range)));
+            let default = element.default.as_ref().map(|default| code(&property_value(default)));
+            write!(out, "| {} | `0x{:X}` | {} | {} | {} | {} | {} |", name, element.id,
+                   element.type_.name(), cardinality(&element.cardinality()), level,
+                   range.unwrap_or_default(), default.unwrap_or_default()).unwrap();
+            if options.comments {
+                write!(out, " {} |", description(element)).unwrap();
+            }
+            out.push('\n');
+        }
+    }
+}
+
+fn cardinality(cardinality: &Cardinality) -> &'static str {
+    match *cardinality {
+        Cardinality::ZeroOrMany => "any number",
+        Cardinality::ZeroOrOne => "at most one",
+        Cardinality::ExactlyOne => "exactly one",
+        Cardinality::OneOrMany => "at least one",
+    }
+}
+
+// The comments before an element, as one line of text, starting with the notice of its
+// `@deprecated` tag in place of the tag.
+fn description(element: &Element) -> String {
+    let lines = untagged_lines(&element.comments);
+    let words: Vec<_> = lines.iter().flat_map(|line| line.split_whitespace()).collect();
+    match element.deprecation() {
+        Some(deprecation) if words.is_empty() => escape(&notice(&deprecation)),
+        Some(deprecation) => escape(&format!("{} {}", notice(&deprecation), words.join(" "))),
+        None => escape(&words.join(" ")),
+    }
+}
+
+// The notice of a deprecation, like `**Deprecated since version 3**: Use Title.`
+fn notice(deprecation: &Deprecation) -> String {
+    let mut notice = "**Deprecated".to_string();
+    if let Some(since) = deprecation.since {
+        write!(notice, " since version {}", since).unwrap();
+    }
+    notice.push_str("**");
+    match deprecation.reason {
+        Some(ref reason) => write!(notice, ": {}", reason).unwrap(),
+        None => notice.push('.'),
+    }
+    notice
+}
+
+// Text in a code span. Backticks in it are fenced with more backticks than it has in a row.
+fn code(text: &str) -> String {
+    let mut longest = 0;
+    let mut run = 0;
+    for c in text.chars() {
+        run = if c == '`' { run + 1 } else { 0 };
+        longest = longest.max(run);
+    }
+    let fence = "`".repeat(longest + 1);
+    let padding = if longest > 0 { " " } else { "" };
+    escape(&format!("{}{}{}{}{}", fence, padding, text, padding, fence))
+}
+
+// Pipes would end a table cell, even in a code span.
+fn escape(text: &str) -> String {
+    text.replace('|', "\\|")
+}
+
+#[cfg(test)]
+mod tests;
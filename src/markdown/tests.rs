@@ -0,0 +1,78 @@
+use super::*;
+use parse_dtd;
+
+#[test]
+fn test_snapshot() {
+    let dtd = parse_dtd(include_bytes!("../../tests/dtd9"), &Default::default()).unwrap();
+    assert_eq!(dtd.to_markdown(&MarkdownOptions::default()), include_str!("../../tests/markdown0"));
+}
+
+#[test]
+fn test_options() {
+    let input = b"
+        define elements {
+            // A | in a comment.
+            Root := 1a45dfa3 container [ card:1; ] {
+                Tag := 81 string [ def:\"a`|`b\"; ]
+            }
+            Empty := 82 container;
+        }
+    ";
+    let dtd = parse_dtd(input, &Default::default()).unwrap();
+    let options = MarkdownOptions { heading_level: 2, comments: false };
+    assert_eq!(dtd.to_markdown(&options), "\
+## EDTD
+
+### Elements
+
+| Element | Id | Type | Cardinality | Level | Range | Default |
+| --- | --- | --- | --- | --- | --- | --- |
+| [Root](#root) | `0x1A45DFA3` | container | exactly one |  |  |  |
+| [Empty](#empty) | `0x82` | container | any number |  |  |  |
+
+#### Root
+
+| Element | Id | Type | Cardinality | Level | Range | Default |
+| --- | --- | --- | --- | --- | --- | --- |
+| Tag | `0x81` | string | any number |  |  | `` \"a`\\|`b\" `` |
+
+#### Empty
+
+Nothing may appear inside it.
+");
+
+    let markdown = dtd.to_markdown(&MarkdownOptions::default());
+    assert!(markdown.starts_with("# EDTD\n"));
+    assert!(markdown.contains("\n### Root\n\nA \\| in a comment.\n"));
+}
+
+#[test]
+fn test_deprecated() {
+    let input = b"
+        define elements {
+            Root := 1a45dfa3 container {
+                // The name.
+                // @deprecated since 3: Use Title.
+                Name := 81 string;
+                /* @deprecated */
+                Old := 82 uint;
+            }
+        }
+    ";
+    let dtd = parse_dtd(input, &Default::default()).unwrap();
+    let markdown = dtd.to_markdown(&MarkdownOptions::default());
+    assert!(markdown.contains(
+        "| Name | `0x81` | string | any number |  |  |  | \
+         **Deprecated since version 3**: Use Title. The name. |\n"
+    ));
+    assert!(markdown.contains("| Old | `0x82` | uint | any number |  |  |  | **Deprecated**. |\n"));
+
+    let options = MarkdownOptions { comments: false, ..Default::default() };
+    let markdown = dtd.to_markdown(&options);
+    assert!(markdown.contains("| Name *(deprecated)* | `0x81` |"));
+    assert!(markdown.contains("| Old *(deprecated)* | `0x82` |"));
+
+    let input = b"define types { // @deprecated\n flag := uint; }";
+    let markdown = parse_dtd(input, &Default::default()).unwrap().to_markdown(&Default::default());
+    assert!(markdown.contains("| flag *(deprecated)* | uint |  |\n"));
+}
@@ -0,0 +1,251 @@
+//! Merges EDTDs split across several inputs, like a DocType's own elements and a fragment holding
+//! the EBML header elements it shares with others.
+
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+use std::ops::Deref;
+
+use {Dtd, Element, Header, NewType, Spanned};
+
+/// Two of the inputs given to `parse_dtds`, or the two EDTDs given to `Dtd::merge`, define
+/// something differently.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MergeConflict {
+    /// How the definitions differ.
+    pub kind: MergeConflictKind,
+    /// The name of the definition in the later input.
+    pub name: String,
+    /// The name of the definition it conflicts with in the earlier input. This is `name` unless
+    /// the two are elements with the same id.
+    pub existing: String,
+    /// The index of the earlier input.
+    pub first: usize,
+    /// The index of the later input.
+    pub second: usize,
+}
+
+/// The ways two definitions can conflict.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum MergeConflictKind {
+    /// Two header statements, types or elements have the same name, but different values or
+    /// properties. Elements with different children differ too.
+    DifferentDefinition,
+    /// Two elements have the same name but different ids.
+    DifferentId,
+    /// Two elements with different names have the same id.
+    SameId,
+}
+
+impl fmt::Display for MergeConflict {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let (first, second) = (format!("input {}", self.first), format!("input {}", self.second));
+        f.write_str(&self.kind.describe(&self.name, &self.existing, first, second))
+    }
+}
+
+impl MergeConflictKind {
+    /// Describes a conflict of this kind between `name`, defined in the input called `second`,
+    /// and `existing`, defined in the earlier one called `first`. The inputs may be called
+    /// anything which tells them apart, like their indexes or the paths of their files.
+    pub fn describe<F, S>(self, name: &str, existing: &str, first: F, second: S) -> String
+        where F: fmt::Display,
+              S: fmt::Display
+    {
+        match self {
+            MergeConflictKind::DifferentDefinition => {
+                format!("`{}` is defined differently in {} and {}", name, first, second)
+            }
+            MergeConflictKind::DifferentId => {
+                format!("`{}` has different ids in {} and {}", name, first, second)
+            }
+            MergeConflictKind::SameId => {
+                format!("`{}` in {} has the same id as `{}` in {}", name, second, existing, first)
+            }
+        }
+    }
+}
+
+impl Error for MergeConflict {}
+
+/// Every conflict found merging two EDTDs with `Dtd::merge`, in the order the definitions were
+/// written. Dereferences to a slice of them.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MergeConflicts(Vec<MergeConflict>);
+
+impl MergeConflicts {
+    /// The conflicts, in order.
+    pub fn into_vec(self) -> Vec<MergeConflict> {
+        self.0
+    }
+}
+
+impl Deref for MergeConflicts {
+    type Target = [MergeConflict];
+
+    fn deref(&self) -> &[MergeConflict] {
+        &self.0
+    }
+}
+
+impl fmt::Display for MergeConflicts {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("conflicting definitions")?;
+        for conflict in &self.0 {
+            write!(f, "\n    {}", conflict)?;
+        }
+        Ok(())
+    }
+}
+
+impl Error for MergeConflicts {}
+
+impl<'a> Dtd<'a> {
+    /// Merges `other` into this EDTD, as `parse_dtds` merges its inputs: definitions in `other`
+    /// identical to this EDTD's (apart from comments) are dropped, and the rest are added after
+    /// this EDTD's own. References by name are resolved again afterwards, so an element of one
+    /// may have a parent or type defined in the other. In conflicts, this EDTD is input 0 and
+    /// `other` input 1; every one found is reported. Spans are left as they were, so those of
+    /// definitions from `other` are into its input.
+    pub fn merge(self, other: Dtd<'a>) -> Result<Dtd<'a>, MergeConflicts> {
+        let mut merger = Merger::default();
+        merger.add(self, 0)?;
+        merger.add(other, 1)?;
+        Ok(merger.finish())
+    }
+}
+
+// Collects the definitions of each input in turn, remembering which input each came from.
+#[derive(Default)]
+pub struct Merger<'a> {
+    header: Header<'a>,
+    types: Vec<Spanned<NewType<'a>>>,
+    elements: Vec<Spanned<Element<'a>>>,
+    doc_types: Vec<(&'a str, Vec<Spanned<Element<'a>>>)>,
+    header_inputs: HashMap<&'a str, usize>,
+    type_inputs: HashMap<&'a str, usize>,
+    element_inputs: HashMap<&'a str, usize>,
+    ids: HashMap<u32, (&'a str, usize)>,
+}
+
+impl<'a> Merger<'a> {
+    // Adds the definitions of `dtd`, which was parsed from input `input`. Definitions identical
+    // to ones from earlier inputs are dropped; a top-level element is dropped along with its
+    // children. Repeats within one input are kept for validation to report. Definitions which
+    // conflict with earlier ones are left out, and every conflict is returned.
+    pub fn add(&mut self, dtd: Dtd<'a>, input: usize) -> Result<(), MergeConflicts> {
+        let mut conflicts = Vec::new();
+        for statement in dtd.header {
+            let name = statement.name();
+            if let Some(first) = earlier(&self.header_inputs, name, input) {
+                if !self.header.contains(&statement) {
+                    let kind = MergeConflictKind::DifferentDefinition;
+                    conflicts.push(conflict(kind, name, name, first, input));
+                }
+                continue;
+            }
+            self.header_inputs.entry(name).or_insert(input);
+            self.header.push(statement);
+        }
+
+        for new_type in dtd.types {
+            let name = new_type.name();
+            if let Some(first) = earlier(&self.type_inputs, name, input) {
+                if !self.types.contains(&new_type) {
+                    let kind = MergeConflictKind::DifferentDefinition;
+                    conflicts.push(conflict(kind, name, name, first, input));
+                }
+                continue;
+            }
+            self.type_inputs.entry(name).or_insert(input);
+            self.types.push(new_type);
+        }
+
+        for element in dtd.elements {
+            let duplicate = earlier(&self.element_inputs, element.name, input).is_some() &&
+                self.elements.contains(&element);
+            let count = conflicts.len();
+            if !duplicate {
+                self.check_element(&element, input, &mut conflicts);
+            }
+            if !duplicate && conflicts.len() == count {
+                self.record_element(&element, input);
+                self.elements.push(element);
+            }
+        }
+        self.doc_types.extend(dtd.doc_types);
+
+        if conflicts.is_empty() {
+            Ok(())
+        } else {
+            Err(MergeConflicts(conflicts))
+        }
+    }
+
+    // Finds where `element` or anything inside it shares a name or id with an element from an
+    // earlier input. An element with the name of an earlier one conflicts as a whole, so what's
+    // inside it isn't looked at.
+    fn check_element(&self, element: &Element<'a>, input: usize,
+                     conflicts: &mut Vec<MergeConflict>)
+    {
+        let name = element.name;
+        if let Some(first) = earlier(&self.element_inputs, name, input) {
+            // Ids are recorded with the first name they're given, and names with their first id.
+            let kind = match self.ids.get(&element.id) {
+                Some(&(existing, _)) if existing == name => MergeConflictKind::DifferentDefinition,
+                _ => MergeConflictKind::DifferentId,
+            };
+            conflicts.push(conflict(kind, name, name, first, input));
+            return;
+        }
+        match self.ids.get(&element.id) {
+            Some(&(existing, first)) if first != input => {
+                conflicts.push(conflict(MergeConflictKind::SameId, name, existing, first, input));
+            }
+            _ => {}
+        }
+        for child in &element.children {
+            self.check_element(child, input, conflicts);
+        }
+    }
+
+    // Records the names and ids of `element` and everything inside it.
+    fn record_element(&mut self, element: &Element<'a>, input: usize) {
+        self.element_inputs.entry(element.name).or_insert(input);
+        self.ids.entry(element.id).or_insert((element.name, input));
+        for child in &element.children {
+            self.record_element(child, input);
+        }
+    }
+
+    // The merged EDTD. Properties are resolved again, since elements may use types defined in
+    // other inputs. The elements of other DocTypes are joined by label, but not checked against
+    // each other.
+    pub fn finish(self) -> Dtd<'a> {
+        let doc_types = self.doc_types.into_iter()
+            .map(|(doc_type, elements)| (Some(doc_type), elements));
+        let blocks = Some((None, self.elements)).into_iter().chain(doc_types).collect();
+        Dtd::from_blocks(self.header, self.types, blocks)
+    }
+}
+
+// The input `name` was first defined in, if it's earlier than `input`.
+fn earlier(inputs: &HashMap<&str, usize>, name: &str, input: usize) -> Option<usize> {
+    inputs.get(name).cloned().filter(|&first| first != input)
+}
+
+fn conflict(kind: MergeConflictKind, name: &str, existing: &str, first: usize, second: usize)
+    -> MergeConflict
+{
+    MergeConflict {
+        kind,
+        name: name.to_string(),
+        existing: existing.to_string(),
+        first,
+        second,
+    }
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,188 @@
+use super::*;
+use {parse_dtd, parse_dtds, DtdError, ParseOptions, Severity};
+
+const HEADER: &[u8] = b"
+    define elements {
+        EBML := 1a45dfa3 container [ card:+; ] {
+            // The version of EBML used.
+            EBMLVersion := 4286 uint [ def:1; ]
+            DocType := 4282 string [ range:32..126; ]
+        }
+    }
+";
+
+const DOC: &[u8] = b"
+    declare header {
+        DocType := \"doc\";
+    }
+    define types {
+        Flag := uint [ range:0..1; ]
+    }
+    define elements {
+        Segment := 18538067 container [ card:*; ] {
+            Enabled := 4101 Flag [ def:1; ]
+        }
+    }
+";
+
+fn validated() -> ParseOptions {
+    ParseOptions { validate: Some(Severity::Error), ..Default::default() }
+}
+
+#[test]
+fn test_merge() {
+    let dtd = parse_dtds(&[HEADER, DOC], &validated()).unwrap();
+    assert_eq!(dtd.doc_type(), Some("doc"));
+    let names: Vec<_> = dtd.all_elements().iter().map(|element| element.name).collect();
+    assert_eq!(names, ["EBML", "EBMLVersion", "DocType", "Segment", "Enabled"]);
+    assert_eq!(dtd.types.len(), 1);
+}
+
+#[test]
+fn test_identical_definitions() {
+    // The header fragment is included twice, with different comments the second time.
+    let copy = String::from_utf8(HEADER.to_vec()).unwrap().replace("The version", "Version");
+    let dtd = parse_dtds(&[HEADER, DOC, copy.as_bytes(), DOC], &validated()).unwrap();
+    assert_eq!(dtd.elements.len(), 2);
+    assert_eq!(dtd.all_elements()[1].comments, [" The version of EBML used."]);
+}
+
+#[test]
+fn test_types_across_inputs() {
+    // The element's default is resolved once its type is known.
+    let types = b"define types { Flag := uint [ range:0..1; ] }";
+    let elements = b"define elements { Enabled := 4101 Flag [ def:1; ] }";
+    let dtd = parse_dtds(&[&types[..], &elements[..]], &validated()).unwrap();
+    assert_eq!(dtd.elements[0].default.as_deref(), Some(&::Property::UintDefault(1)));
+}
+
+#[test]
+fn test_conflicts() {
+    let conflict = |inputs: &[&[u8]]| match parse_dtds(inputs, &Default::default()) {
+        Err((index, DtdError::Conflict(conflict))) => {
+            assert_eq!(index, conflict.second);
+            conflict
+        }
+        other => panic!("expected a conflict, got {:?}", other),
+    };
+
+    let other_id = b"define elements { Segment := 18538068 container; }";
+    assert_eq!(conflict(&[DOC, &other_id[..]]), MergeConflict {
+        kind: MergeConflictKind::DifferentId,
+        name: "Segment".to_string(),
+        existing: "Segment".to_string(),
+        first: 0,
+        second: 1,
+    });
+
+    let same_id = b"define elements { Cluster := 4101 uint; }";
+    let err = conflict(&[DOC, HEADER, &same_id[..]]);
+    assert_eq!((err.first, err.second), (0, 2));
+    assert_eq!(err.to_string(), "`Cluster` in input 2 has the same id as `Enabled` in input 0");
+
+    let other_type = b"define types { Flag := uint [ range:0..2; ] }";
+    assert_eq!(conflict(&[DOC, &other_type[..]]).name, "Flag");
+
+    let other_doc_type = b"declare header { DocType := \"other\"; }";
+    let err = conflict(&[DOC, &other_doc_type[..]]);
+    assert_eq!(err.to_string(), "`DocType` is defined differently in input 0 and input 1");
+}
+
+#[test]
+fn test_describe() {
+    let describe = |kind: MergeConflictKind| kind.describe("Title", "Info", "a.edtd", "b.edtd");
+    assert_eq!(describe(MergeConflictKind::SameId),
+               "`Title` in b.edtd has the same id as `Info` in a.edtd");
+    assert_eq!(describe(MergeConflictKind::DifferentDefinition),
+               "`Title` is defined differently in a.edtd and b.edtd");
+    assert_eq!(describe(MergeConflictKind::DifferentId),
+               "`Title` has different ids in a.edtd and b.edtd");
+}
+
+#[test]
+fn test_errors_in_inputs() {
+    let broken = b"define elements { Segment := container; }";
+    match parse_dtds(&[HEADER, &broken[..]], &Default::default()) {
+        Err((1, DtdError::Syntax(_))) => {}
+        other => panic!("expected a syntax error in input 1, got {:?}", other),
+    }
+
+    // Each input is fine alone, but not together.
+    let unknown_parent = b"define elements { Cluster := 1f43b675 container [ parent:Segmnt; ] }";
+    match parse_dtds(&[DOC, &unknown_parent[..]], &validated()) {
+        Err((1, DtdError::Invalid(diagnostics))) => {
+            assert!(diagnostics[0].span().is_some());
+        }
+        other => panic!("expected validation to fail in input 1, got {:?}", other),
+    }
+}
+
+#[test]
+fn test_repeats_within_an_input() {
+    // These are left for validation to report, as they would be with `parse_dtd`.
+    let repeated = b"define elements { Segment := 18538067 container; Segment := 18538067 uint; }";
+    let dtd = parse_dtds(&[&repeated[..]], &Default::default()).unwrap();
+    assert_eq!(dtd.elements.len(), 2);
+    match parse_dtds(&[HEADER, &repeated[..]], &validated()) {
+        Err((1, DtdError::Invalid(_))) => {}
+        other => panic!("expected validation to fail in input 1, got {:?}", other),
+    }
+}
+
+fn parse(input: &[u8]) -> Dtd {
+    parse_dtd(input, &Default::default()).unwrap()
+}
+
+#[test]
+fn test_dtd_merge() {
+    let dtd = parse(HEADER).merge(parse(DOC)).unwrap();
+    assert_eq!(dtd, parse_dtds(&[HEADER, DOC], &Default::default()).unwrap());
+    assert_eq!(dtd.doc_type(), Some("doc"));
+
+    // Identical definitions are kept once.
+    let again = dtd.clone().merge(parse(DOC)).unwrap();
+    assert_eq!(again, dtd);
+
+    // References are resolved across the two: the default is converted once the type is known,
+    // and the parent is found.
+    let types = parse(b"define types { Gain := float; }");
+    let elements = b"define elements { Volume := 4101 Gain [ parent:Segment; def:1; ] }";
+    let segment = parse(b"define elements { Segment := 18538067 container; }");
+    let dtd = types.merge(parse(elements)).unwrap().merge(segment).unwrap();
+    assert_eq!(dtd.elements[0].default.as_deref(), Some(&::Property::FloatDefault(1.0)));
+    assert!(dtd.validate().iter().all(|d| d.code() != ::ErrorCode::UnknownParent));
+}
+
+#[test]
+fn test_dtd_merge_conflicts() {
+    let conflicts = |other: &[u8]| -> Vec<(MergeConflictKind, String, String)> {
+        let conflicts = parse(DOC).merge(parse(other)).unwrap_err();
+        assert!(conflicts.iter().all(|c| (c.first, c.second) == (0, 1)));
+        conflicts.iter().map(|c| (c.kind, c.name.clone(), c.existing.clone())).collect()
+    };
+    let conflict = |kind, name: &str, existing: &str| {
+        (kind, name.to_string(), existing.to_string())
+    };
+
+    assert_eq!(conflicts(b"define elements { Segment := 18538068 container; }"), [
+        conflict(MergeConflictKind::DifferentId, "Segment", "Segment"),
+    ]);
+    assert_eq!(conflicts(b"define elements { Cluster := 18538067 container; }"), [
+        conflict(MergeConflictKind::SameId, "Cluster", "Segment"),
+    ]);
+    assert_eq!(conflicts(b"define elements { Segment := 18538067 container [ card:1; ]; }"), [
+        conflict(MergeConflictKind::DifferentDefinition, "Segment", "Segment"),
+    ]);
+
+    // Every conflict is reported, of header statements and types too.
+    let other = b"declare header { DocType := \"other\"; }
+                  define types { Flag := uint [ range:0..2; ] }
+                  define elements { Cluster := 1f43b675 container { Enabled := 4101 uint; } }";
+    assert_eq!(conflicts(other), [
+        conflict(MergeConflictKind::DifferentDefinition, "DocType", "DocType"),
+        conflict(MergeConflictKind::DifferentDefinition, "Flag", "Flag"),
+        conflict(MergeConflictKind::DifferentDefinition, "Enabled", "Enabled"),
+    ]);
+    let err = parse(DOC).merge(parse(other)).unwrap_err();
+    assert_eq!(err.to_string().lines().count(), 4);
+}
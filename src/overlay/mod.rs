@@ -0,0 +1,222 @@
+//! Derives one EDTD from another, like WebM from Matroska: a subset of its elements, some with
+//! tighter properties.
+
+use std::collections::HashSet;
+use std::mem;
+
+use {parsers, Diagnostics, Dtd, DtdError, Element, ErrorCode, ParseError, ParseOptions, Span,
+     Spanned, ValidationDiagnostic};
+
+/// Changes which derive one EDTD from another, parsed by `parse_overlay` and applied by
+/// `Dtd::apply_overlay`.
+///
+/// An overlay is written like an EDTD, and may end with a block naming elements to remove:
+///
+/// ```text
+/// declare header {
+///     DocType := "webm";
+/// }
+/// define elements {
+///     TrackNumber := d7 uint [ range:1..127; ]
+/// }
+/// remove elements {
+///     Chapters;
+///     Tags;
+/// }
+/// ```
+///
+/// Header statements and types replace those with the same name, or are added. Each element
+/// refines the one with the same name, wherever it's defined, which must have the same id and
+/// type: the properties it gives replace the element's, and the comments before it replace the
+/// element's documentation. Elements nested inside it refine their own namesakes in turn.
+#[derive(Debug)]
+pub struct Overlay<'a> {
+    dtd: Dtd<'a>,
+    removed: Vec<Spanned<&'a str>>,
+}
+
+// Parses an overlay, as `parse_overlay`.
+pub fn parse(input: &str) -> Result<Overlay<'_>, DtdError> {
+    match parsers::overlay(input) {
+        Ok((dtd, removed)) => Ok(Overlay { dtd, removed }),
+        Err(nom::Err::Error(err)) | Err(nom::Err::Failure(err)) => {
+            Err(DtdError::Syntax(ParseError::from_failure(input, Some(&err))))
+        }
+        Err(nom::Err::Incomplete(_)) => {
+            Err(DtdError::Syntax(ParseError::from_failure(input, None)))
+        }
+    }
+}
+
+impl<'a> Dtd<'a> {
+    /// Removes the elements `keep` returns false for, given their names, along with everything
+    /// which may only appear inside them: the elements nested in their definitions, and those
+    /// defined elsewhere whose parents have all been removed. Removed elements are taken out of
+    /// the `parent:` properties of those left. The elements of other DocTypes are left alone.
+    pub fn restrict<F: Fn(&str) -> bool>(self, keep: F) -> Dtd<'a> {
+        let mut removed: HashSet<&'a str> = self.all_elements().iter()
+            .map(|element| element.name)
+            .filter(|name| !keep(name))
+            .collect();
+        loop {
+            let count = removed.len();
+            for element in &self.elements {
+                collect_removed(element, false, &mut removed);
+            }
+            if removed.len() == count {
+                break;
+            }
+        }
+
+        let mut dtd = self;
+        retain(&mut dtd.elements, &removed);
+        dtd.assembled()
+    }
+
+    /// Derives another EDTD from this one with `overlay`, then validates it if `options` say
+    /// to, as `parse_dtd` does. The overlay's elements refine this EDTD's own, not those of other
+    /// DocTypes. An overlay naming elements this doesn't define, or giving one a
+    /// different id or type, is a `DtdError::Invalid` holding `ErrorCode::InvalidOverlay`
+    /// diagnostics, with spans in the overlay's input.
+    pub fn apply_overlay(self, overlay: Overlay<'a>, options: &ParseOptions)
+        -> Result<Dtd<'a>, DtdError>
+    {
+        let Dtd { mut header, mut types, mut elements, doc_types, .. } = self;
+        let Overlay { dtd: overlay, removed } = overlay;
+        let mut diagnostics = Diagnostics::new();
+
+        for statement in overlay.header {
+            match header.iter_mut().find(|existing| existing.name() == statement.name()) {
+                Some(existing) => *existing = statement,
+                None => header.push(statement),
+            }
+        }
+        for new_type in overlay.types {
+            match types.iter_mut().find(|existing| existing.name() == new_type.name()) {
+                Some(existing) => *existing = new_type,
+                None => types.push(new_type),
+            }
+        }
+
+        let mut refinements = Vec::new();
+        flatten(overlay.elements, &mut refinements);
+        for refinement in refinements {
+            let (name, span) = (refinement.name, refinement.span);
+            match find_mut(&mut elements, name) {
+                Some(element) if element.id == refinement.id &&
+                    element.type_ == refinement.type_ => refine(element, refinement.node),
+                Some(_) => diagnostics.push(invalid(
+                    name, "element has a different id or type than the one it refines", span
+                )),
+                None => diagnostics.push(invalid(
+                    name, "element is not defined, so it can't be refined", span
+                )),
+            }
+        }
+        for name in &removed {
+            if find_mut(&mut elements, name.node).is_none() {
+                diagnostics.push(invalid(
+                    name.node, "element is not defined, so it can't be removed", name.span
+                ));
+            }
+        }
+        if diagnostics.has_errors() {
+            return Err(DtdError::Invalid(diagnostics));
+        }
+
+        // Properties are resolved again, as the types of refined elements may have changed.
+        let blocks = Some((None, elements)).into_iter()
+            .chain(doc_types.into_iter().map(|(doc_type, elements)| (Some(doc_type), elements)))
+            .collect();
+        let dtd = Dtd::from_blocks(header, types, blocks)
+            .restrict(|name| !removed.iter().any(|removed| removed.node == name));
+        if let Some(threshold) = options.validate {
+            let mut diagnostics = dtd.validate();
+            if options.warnings_as_errors {
+                diagnostics.promote_warnings();
+            }
+            if diagnostics.max_severity() >= Some(threshold) {
+                return Err(DtdError::Invalid(diagnostics));
+            }
+        }
+        Ok(dtd)
+    }
+}
+
+// Adds the names of elements which may only appear inside removed ones to `removed`: those nested
+// inside a removed element, and those whose parents, other than themselves, have all been.
+fn collect_removed<'a>(element: &Element<'a>, nested: bool, removed: &mut HashSet<&'a str>) {
+    let orphaned = !nested && element.parent.as_ref().is_some_and(|parents| {
+        let mut others = parents.iter().filter(|&&parent| parent != element.name).peekable();
+        others.peek().is_some() && others.all(|parent| removed.contains(parent))
+    });
+    if orphaned {
+        removed.insert(element.name);
+    }
+
+    let inside_removed = removed.contains(element.name);
+    for child in &element.children {
+        if inside_removed {
+            removed.insert(child.name);
+        }
+        collect_removed(child, true, removed);
+    }
+}
+
+fn retain<'a>(elements: &mut Vec<Spanned<Element<'a>>>, removed: &HashSet<&'a str>) {
+    elements.retain(|element| !removed.contains(element.name));
+    for element in elements {
+        let element = &mut element.node;
+        if let Some(ref mut parents) = element.parent {
+            parents.retain(|parent| !removed.contains(parent));
+        }
+        retain(&mut element.children, removed);
+    }
+}
+
+// Moves every element out of `elements` into `out`, depth-first in declaration order.
+fn flatten<'a>(elements: Vec<Spanned<Element<'a>>>, out: &mut Vec<Spanned<Element<'a>>>) {
+    for mut element in elements {
+        let children = mem::take(&mut element.node.children);
+        out.push(element);
+        flatten(children, out);
+    }
+}
+
+fn find_mut<'a, 'b>(elements: &'b mut [Spanned<Element<'a>>], name: &str)
+    -> Option<&'b mut Element<'a>>
+{
+    for element in elements {
+        if element.name == name {
+            return Some(&mut element.node);
+        }
+        if let Some(found) = find_mut(&mut element.node.children, name) {
+            return Some(found);
+        }
+    }
+    None
+}
+
+fn refine<'a>(element: &mut Element<'a>, refinement: Element<'a>) {
+    element.parent = refinement.parent.or(element.parent.take());
+    element.level = refinement.level.or(element.level.take());
+    element.cardinality = refinement.cardinality.or(element.cardinality.take());
+    element.size = refinement.size.or(element.size.take());
+    element.ordered = refinement.ordered.or(element.ordered);
+    element.unknown_size_allowed = refinement.unknown_size_allowed.or(element.unknown_size_allowed);
+    element.variants = refinement.variants.or(element.variants.take());
+    element.minver = refinement.minver.or(element.minver);
+    element.maxver = refinement.maxver.or(element.maxver);
+    element.default = refinement.default.or(element.default.take());
+    element.range = refinement.range.or(element.range.take());
+    if !refinement.comments.is_empty() {
+        element.comments = refinement.comments;
+    }
+}
+
+fn invalid(name: &str, message: &str, span: Span) -> ValidationDiagnostic {
+    ValidationDiagnostic::error(ErrorCode::InvalidOverlay, name, message).with_span(span)
+}
+
+#[cfg(test)]
+mod tests;
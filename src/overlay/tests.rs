@@ -0,0 +1,135 @@
+use super::*;
+use {parse_dtd, parse_overlay, Cardinality, Property, Severity, UintRangeItem};
+
+const BASE: &[u8] = b"
+    declare header {
+        DocType := \"matroska\";
+        DocTypeVersion := 4;
+    }
+    define elements {
+        Segment := 18538067 container [ card:1; ] {
+            Tracks := 1654ae6b container {
+                TrackEntry := ae container [ card:+; ] {
+                    // The track's number.
+                    TrackNumber := d7 uint [ card:1; range:1..; ]
+                    Video := e0 container;
+                }
+            }
+            Chapters := 1043a770 container {
+                EditionEntry := 45b9 container [ card:+; ]
+            }
+        }
+        ChapterAtom := b6 container [ parent:EditionEntry, ChapterAtom; card:+; ]
+        ChapterUID := 73c4 uint [ parent:ChapterAtom; ]
+        Void := ec binary [ parent:Tracks, EditionEntry; ]
+    }
+";
+
+fn base() -> Dtd<'static> {
+    parse_dtd(BASE, &Default::default()).unwrap()
+}
+
+fn names<'a>(dtd: &Dtd<'a>) -> Vec<&'a str> {
+    dtd.all_elements().iter().map(|element| element.name).collect()
+}
+
+#[test]
+fn test_restrict() {
+    // Removing Chapters removes what's nested in it, then what may only appear inside those. The
+    // atom's own name in its parents doesn't keep it.
+    let dtd = base().restrict(|name| name != "Chapters");
+    assert_eq!(names(&dtd), ["Segment", "Tracks", "TrackEntry", "TrackNumber", "Video", "Void"]);
+    assert_eq!(dtd.all_elements()[5].parent, Some(vec!["Tracks"]));
+
+    let dtd = base().restrict(|name| name != "ChapterAtom" && name != "Video");
+    assert_eq!(names(&dtd), [
+        "Segment", "Tracks", "TrackEntry", "TrackNumber", "Chapters", "EditionEntry", "Void",
+    ]);
+    assert!(dtd.validate().is_empty());
+}
+
+#[test]
+fn test_apply_overlay() {
+    let overlay = parse_overlay(b"
+        declare header {
+            DocType := \"webm\";
+            DocTypeReadVersion := 2;
+        }
+        define elements {
+            Segment := 18538067 container {
+                TrackNumber := d7 uint [ range:1..127; ]
+            }
+            // The track's number, which WebM keeps small.
+            TrackNumber := d7 uint [ def:1; ]
+        }
+        remove elements {
+            Chapters;
+            Video;
+        }
+    ").unwrap();
+    let dtd = base().apply_overlay(overlay, &ParseOptions {
+        validate: Some(Severity::Error),
+        ..Default::default()
+    }).unwrap();
+
+    assert_eq!(dtd.doc_type(), Some("webm"));
+    assert_eq!(dtd.header_uint("DocTypeVersion"), Some(4));
+    assert_eq!(dtd.header_uint("DocTypeReadVersion"), Some(2));
+    assert_eq!(names(&dtd), ["Segment", "Tracks", "TrackEntry", "TrackNumber", "Void"]);
+
+    // Refinements apply in order, keeping what they don't give.
+    let track_number = dtd.all_elements()[3];
+    assert_eq!(track_number.cardinality, Some(Cardinality::ExactlyOne));
+    assert_eq!(track_number.default.as_deref(), Some(&Property::UintDefault(1)));
+    assert_eq!(track_number.range.as_deref(), Some(&Property::UintRange(vec![
+        UintRangeItem::Bounded { start: 1, end: 127 },
+    ])));
+    assert_eq!(track_number.comments, [" The track's number, which WebM keeps small."]);
+    assert_eq!(dtd.all_elements()[0].cardinality, Some(Cardinality::ExactlyOne));
+}
+
+#[test]
+fn test_errors() {
+    let overlay = b"
+        define elements {
+            Tracks := 1654ae6c container;
+            TrackNumber := d7 int;
+            Audio := e1 container;
+        }
+        remove elements { Tags; }
+    ";
+    let diagnostics = match base().apply_overlay(parse_overlay(overlay).unwrap(),
+                                                 &Default::default()) {
+        Err(DtdError::Invalid(diagnostics)) => diagnostics,
+        other => panic!("expected diagnostics, got {:?}", other),
+    };
+    let found: Vec<_> = diagnostics.iter()
+        .map(|d| {
+            let span = d.span().unwrap();
+            (d.code(), d.subject(), d.message(), &overlay[span.start..span.end])
+        })
+        .collect();
+    let invalid = ErrorCode::InvalidOverlay;
+    let different = "element has a different id or type than the one it refines";
+    assert_eq!(found, vec![
+        (invalid, "Tracks", different, &b"Tracks := 1654ae6c container;"[..]),
+        (invalid, "TrackNumber", different, b"TrackNumber := d7 int;"),
+        (invalid, "Audio", "element is not defined, so it can't be refined",
+         b"Audio := e1 container;"),
+        (invalid, "Tags", "element is not defined, so it can't be removed", b"Tags;"),
+    ]);
+
+    // The result is validated.
+    let overlay = parse_overlay(b"define elements { Void := ec binary [ parent:Cues; ] }").unwrap();
+    let options = ParseOptions { validate: Some(Severity::Error), ..Default::default() };
+    match base().apply_overlay(overlay, &options) {
+        Err(DtdError::Invalid(diagnostics)) => {
+            assert_eq!(diagnostics[0].code(), ErrorCode::UnknownParent);
+        }
+        other => panic!("expected diagnostics, got {:?}", other),
+    }
+
+    // Only the blocks of an EDTD may come before the removals.
+    let err = parse_overlay(b"remove elements { Tags; } define elements {}").unwrap_err();
+    assert!(matches!(err, DtdError::Syntax(_)));
+}
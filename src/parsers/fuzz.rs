@@ -0,0 +1,22 @@
+//! The value parsers, for the targets in `fuzz`. Each parses a value from the start of `input`,
+//! returning whether there was one. They must fail, rather than panic, on anything.
+
+use super::{binary_v, date_v, float_v, lexer, State, Tokens};
+
+/// Runs `date_v`.
+pub fn date(input: &str) -> bool {
+    let state = State::new(false);
+    date_v(Tokens::new(&lexer::tokenize(input, &state), &state)).is_ok()
+}
+
+/// Runs `binary_v`.
+pub fn binary(input: &str) -> bool {
+    let state = State::new(false);
+    binary_v(Tokens::new(&lexer::tokenize(input, &state), &state)).is_ok()
+}
+
+/// Runs `float_v`.
+pub fn float(input: &str) -> bool {
+    let state = State::new(false);
+    float_v(Tokens::new(&lexer::tokenize(input, &state), &state)).is_ok()
+}
@@ -0,0 +1,215 @@
+//! Splits an EDTD into the tokens the grammar is written over. The whitespace and comments before
+//! a token are its trivia, kept with it rather than being tokens of their own, so the parsers never
+//! have to skip them. Nothing fails to lex: a character no token starts with is a token by itself,
+//! for the parsers to reject, and the last token is always an `End` which no parser consumes.
+
+use super::State;
+use ErrorCode;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TokenKind {
+    // A letter or underscore, followed by any letters, digits and underscores. Keywords are names.
+    Name,
+    // Letters, digits and underscores starting with a digit, like an id which isn't all digits.
+    Word,
+    // Digits, possibly signed, with anything else a number is written with: dots, `e`s and signs.
+    Number,
+    // Digits followed by a `T` and a time, like `20010101T00:00:00.5`.
+    Date,
+    // `0x` followed by hex digits.
+    Hex,
+    // Anything between double quotes, quotes included.
+    Str,
+    // Leniently, anything between single quotes, quotes included.
+    Quoted,
+    // `:=`, `..`, or any other character.
+    Punct,
+    End,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Token<'a> {
+    pub kind: TokenKind,
+    pub text: &'a str,
+    // The input from the start of the token, which is where errors in it are.
+    pub rest: &'a str,
+    // The input from the end of the token before, where the trivia starts.
+    pub trivia: &'a str,
+    // The text of the comments in the trivia, without their delimiters.
+    pub comments: Vec<&'a str>,
+}
+
+pub struct Lexer<'a, 's> {
+    input: &'a str,
+    pos: usize,
+    // Whether quirks are skipped, and where those which are get recorded.
+    state: &'s State,
+}
+
+// Splits all of `input` into tokens, the last being an `End`.
+pub fn tokenize<'a>(input: &'a str, state: &State) -> Vec<Token<'a>> {
+    let mut lexer = Lexer::new(input, state);
+    // With their trivia, tokens average more than four bytes, so this is usually room for all of
+    // them. Growing the vector as it fills costs more than the slack.
+    let mut tokens = Vec::with_capacity(input.len() / 4);
+    loop {
+        let token = lexer.token();
+        let end = token.kind == TokenKind::End;
+        tokens.push(token);
+        if end {
+            return tokens;
+        }
+    }
+}
+
+impl<'a, 's> Lexer<'a, 's> {
+    pub fn new(input: &'a str, state: &'s State) -> Self {
+        Lexer { input, pos: 0, state }
+    }
+
+    fn rest(&self) -> &'a str {
+        &self.input[self.pos..]
+    }
+
+    // The next token, and the trivia before it.
+    pub fn token(&mut self) -> Token<'a> {
+        let trivia = self.rest();
+        let comments = self.trivia();
+        let rest = self.rest();
+        let (kind, len) = lex(rest, self.state.lenient);
+        self.pos += len;
+        Token { kind, text: &rest[..len], rest, trivia, comments }
+    }
+
+    // Skips whitespace and comments, returning the comments. Leniently, `%name;` entity
+    // references and `#` comments are skipped too, but not kept. The first byte decides what
+    // could be there, so the byte starting most tokens is only looked at once.
+    fn trivia(&mut self) -> Vec<&'a str> {
+        let mut comments = Vec::new();
+        loop {
+            let rest = self.rest();
+            let len = match rest.as_bytes().first() {
+                Some(b' ') | Some(b'\t') | Some(b'\r') | Some(b'\n') => {
+                    rest.bytes().position(|b| !b" \t\r\n".contains(&b)).unwrap_or(rest.len())
+                }
+                Some(b'/') => match comment(rest) {
+                    Some((comment, len)) => {
+                        comments.push(comment);
+                        len
+                    }
+                    None => return comments,
+                },
+                Some(b'%') | Some(b'#') => match skipped_quirk(rest, self.state) {
+                    Some(len) => len,
+                    None => return comments,
+                },
+                _ => return comments,
+            };
+            self.pos += len;
+        }
+    }
+}
+
+// The text of the `//` or `/*` comment at the start of `input`, and its length with delimiters.
+fn comment(input: &str) -> Option<(&str, usize)> {
+    match delimited(input, "//", "\n") {
+        Some(text) => Some((text, text.len() + 3)),
+        None => delimited(input, "/*", "*/").map(|text| (text, text.len() + 4)),
+    }
+}
+
+// The text between `open` at the start of `input` and the first `close` after it.
+fn delimited<'a>(input: &'a str, open: &str, close: &str) -> Option<&'a str> {
+    if !input.starts_with(open) {
+        return None;
+    }
+    let len = input[open.len()..].find(close)?;
+    Some(&input[open.len()..open.len() + len])
+}
+
+// The length of the entity reference or `#` comment at the start of `input`, recording it as a
+// quirk, if parsing leniently.
+fn skipped_quirk(input: &str, state: &State) -> Option<usize> {
+    if !state.lenient {
+        return None;
+    }
+    let (code, len) = if let Some(reference) = input.strip_prefix('%') {
+        match lex(reference, state.lenient) {
+            (TokenKind::Name, len) if reference[len..].starts_with(';') => {
+                (ErrorCode::EntityReference, len + 2)
+            }
+            _ => return None,
+        }
+    } else if input.starts_with('#') {
+        (ErrorCode::HashComment, input.find('\n')? + 1)
+    } else {
+        return None;
+    };
+    state.quirk(code, input, &input[len..]);
+    Some(len)
+}
+
+// The kind and length of the token at the start of `input`, after its trivia. Leniently, single
+// quotes delimit a token too.
+fn lex(input: &str, lenient: bool) -> (TokenKind, usize) {
+    let first = match input.chars().next() {
+        Some(first) => first,
+        None => return (TokenKind::End, 0),
+    };
+    let digits = input.find(|c: char| !c.is_ascii_digit()).unwrap_or(input.len());
+    if first == '"' {
+        if let Some(text) = delimited(input, "\"", "\"") {
+            return (TokenKind::Str, text.len() + 2);
+        }
+    } else if first == '\'' && lenient {
+        if let Some(text) = delimited(input, "'", "'") {
+            return (TokenKind::Quoted, text.len() + 2);
+        }
+    } else if let Some(digits) = input.strip_prefix("0x") {
+        return (TokenKind::Hex, 2 + word_len(digits, |c| c.is_ascii_hexdigit()));
+    } else if first.is_ascii_alphabetic() || first == '_' {
+        return (TokenKind::Name, word_len(input, |c| c.is_ascii_alphanumeric() || c == '_'));
+    } else if digits > 0 && input[digits..].starts_with('T') {
+        let time = number_len(&input[digits + 1..], |c| c.is_ascii_digit() || c == ':');
+        return (TokenKind::Date, digits + 1 + time);
+    } else if starts_number(input) {
+        let len = number_len(input, |c| c.is_ascii_digit() || ['e', '+', '-'].contains(&c));
+        // An id like `1a45dfa3` starts like a number, but is a word.
+        let word = word_len(input, |c| c.is_ascii_alphanumeric() || c == '_');
+        if word > len {
+            return (TokenKind::Word, word);
+        }
+        return (TokenKind::Number, len);
+    } else if input.starts_with(":=") || input.starts_with("..") {
+        return (TokenKind::Punct, 2);
+    }
+    (TokenKind::Punct, first.len_utf8())
+}
+
+// Whether a number starts `input`: a digit, possibly after a sign or a dot.
+fn starts_number(input: &str) -> bool {
+    let unsigned = if input.starts_with(['-', '+']) { &input[1..] } else { input };
+    let unsigned = if unsigned.starts_with('.') && !unsigned.starts_with("..") {
+        &unsigned[1..]
+    } else {
+        unsigned
+    };
+    unsigned.starts_with(|c: char| c.is_ascii_digit())
+}
+
+// The length of the run of characters `accept` accepts at the start of `input`.
+fn word_len<F: Fn(char) -> bool>(input: &str, accept: F) -> usize {
+    input.find(|c: char| !accept(c)).unwrap_or(input.len())
+}
+
+// Like word_len, but dots are accepted too, unless they start a `..`, which ends the number.
+fn number_len<F: Fn(char) -> bool>(input: &str, accept: F) -> usize {
+    let mut len = 0;
+    for c in input.chars() {
+        if !(accept(c) || (c == '.' && !input[len..].starts_with(".."))) {
+            break;
+        }
+        len += c.len_utf8();
+    }
+    len
+}
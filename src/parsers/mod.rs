@@ -1,709 +1,1350 @@
+use std::borrow::Cow;
+use std::cell::RefCell;
+use std::collections::BTreeSet;
+use std::ops::Deref;
+use std::str::FromStr;
 
-use std::str::{self, FromStr};
-
-use chrono::{Duration, NaiveDate, NaiveDateTime, NaiveTime};
 use ebml::Id;
-use nom::{AsChar, ErrorKind, IResult, Needed, is_digit, is_hex_digit};
+use nom::branch::alt;
+use nom::combinator::{cond, map, map_opt, map_res, not, opt, value};
+use nom::error::{ErrorKind, FromExternalError, ParseError};
+use nom::sequence::{delimited, pair, preceded, separated_pair, terminated, tuple};
+
+use {date, into_string, Cardinality, Date, DateRangeItem, Dtd, Element, FloatRangeItem, Header,
+     HeaderStatement, IntRangeItem, Level, NewType, Property, SizeList, Span, Spanned, Type,
+     UintRangeItem};
+use ErrorCode;
+use hex::{from_hex, from_hex_into};
+
+use self::lexer::{Lexer, Token, TokenKind};
+
+#[cfg(feature = "fuzzing")]
+pub mod fuzz;
+mod lexer;
+
+// How far recovery looks for the end of a definition which failed to parse before giving up.
+const MAX_SKIP: usize = 64 * 1024;
+
+// The tokens left to parse, the last of which is always the `End`, along with the state of the
+// parse they're part of. It derefs to the tokens, and is as cheap to pass around.
+#[derive(Debug, Clone, Copy)]
+pub struct Tokens<'t, 'a> {
+    tokens: &'t [Token<'a>],
+    state: &'t State,
+}
+
+impl<'t, 'a> Tokens<'t, 'a> {
+    pub fn new(tokens: &'t [Token<'a>], state: &'t State) -> Self {
+        Tokens { tokens, state }
+    }
+
+    // The tokens after the first `n`.
+    fn skip(self, n: usize) -> Self {
+        Tokens { tokens: &self.tokens[n..], ..self }
+    }
 
-use {BinaryRange, BinaryRangeItem, Cardinality, DateRange, DateRangeItem, FloatRange,
-     FloatRangeItem, Header, HeaderStatement, IntRange, IntRangeItem, Level, NewType, Property,
-     SizeList, StringRange, StringRangeItem, Type, UintRange, UintRangeItem};
+    // The first token, and the tokens after it.
+    fn split_first(self) -> Option<(&'t Token<'a>, Self)> {
+        self.tokens.split_first().map(|(token, rest)| (token, Tokens { tokens: rest, ..self }))
+    }
+}
 
-const NANOS_PER_SEC: f64 = 1_000_000_000f64;
+impl<'t, 'a> Deref for Tokens<'t, 'a> {
+    type Target = [Token<'a>];
 
-fn from_hex(s: &str) -> Option<Vec<u8>> {
-    let mut b = Vec::with_capacity(s.len() / 2);
-    let mut modulus = 0;
-    let mut buf = 0;
+    fn deref(&self) -> &[Token<'a>] {
+        self.tokens
+    }
+}
 
-    for (idx, byte) in s.bytes().enumerate() {
-        buf <<= 4;
+// What a parse carries besides its tokens: whether quirks are tolerated, and the quirks which
+// have been, as the code and how much input was left at their start and end.
+#[derive(Debug)]
+pub struct State {
+    lenient: bool,
+    quirks: RefCell<Vec<(ErrorCode, usize, usize)>>,
+}
 
-        match byte {
-            b'A'...b'F' => buf |= byte - b'A' + 10,
-            b'a'...b'f' => buf |= byte - b'a' + 10,
-            b'0'...b'9' => buf |= byte - b'0',
-            b' '|b'\r'|b'\n'|b'\t' => {
-                buf >>= 4;
-                continue
+impl State {
+    pub fn new(lenient: bool) -> Self {
+        State { lenient, quirks: RefCell::new(Vec::new()) }
+    }
+
+    // Records a quirk found between `start` and `end`, if quirks are tolerated.
+    fn quirk(&self, code: ErrorCode, start: &str, end: &str) {
+        if self.lenient {
+            self.quirks.borrow_mut().push((code, start.len(), end.len()));
+        }
+    }
+
+    // The quirks found in an input `len` bytes long, in order and without repeats from
+    // backtracking.
+    pub fn into_quirks(self, len: usize) -> Vec<(ErrorCode, Span)> {
+        let found: BTreeSet<_> = self.quirks.into_inner().into_iter()
+            .map(|(code, start, end)| (len - start, len - end, code))
+            .collect();
+        found.into_iter().map(|(start, end, code)| (code, Span { start, end })).collect()
+    }
+}
+
+// What the parsers return. The input is always complete, so they never ask for more.
+pub type IResult<'t, 'a, O> = ::nom::IResult<Tokens<'t, 'a>, O, Error<'a>>;
+
+// Where parsers which recover from errors in definitions record them. Strict parsers have nowhere.
+pub type Recovered<'r, 'a> = Option<&'r RefCell<Vec<Error<'a>>>>;
+
+// The error the parsers fail with: the failures on the way out of the parsers which failed,
+// innermost first, each with the input that was left where it happened, so a `ParseError` can
+// say where the error is and what was being parsed there.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Error<'a> {
+    pub failures: Vec<(&'a str, Kind)>,
+    // Whether any parser on the way to the error failed at the `End`, including those failing
+    // alternatives or lookaheads which were given up on. Had there been more input, the parse
+    // might have gone another way.
+    pub at_end: bool,
+}
+
+// What a parser which failed was doing: running one of nom's combinators, or parsing one of the
+// constructs.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Kind {
+    Parser(ErrorKind),
+    Context(Context),
+}
+
+impl<'a> Error<'a> {
+    // Notes that the error was inside `context`, which starts at `input`.
+    fn within(mut self, input: Tokens<'_, 'a>, context: Context) -> Self {
+        self.failures.push((position(input), Kind::Context(context)));
+        self
+    }
+}
+
+impl<'t, 'a> ParseError<Tokens<'t, 'a>> for Error<'a> {
+    fn from_error_kind(input: Tokens<'t, 'a>, kind: ErrorKind) -> Self {
+        let position = position(input);
+        Error { failures: vec![(position, Kind::Parser(kind))], at_end: position.is_empty() }
+    }
+
+    // When none of several alternatives fit, how far each got says little about what was wrong,
+    // so only where they started is kept, and whether any got to the end.
+    fn append(input: Tokens<'t, 'a>, kind: ErrorKind, mut other: Self) -> Self {
+        if kind == ErrorKind::Alt {
+            let err = Error::from_error_kind(input, kind);
+            return Error { at_end: err.at_end || other.at_end, ..err };
+        }
+        other.failures.push((position(input), Kind::Parser(kind)));
+        other
+    }
+
+    fn or(self, other: Self) -> Self {
+        Error { at_end: self.at_end || other.at_end, ..other }
+    }
+}
+
+impl<'t, 'a, E> FromExternalError<Tokens<'t, 'a>, E> for Error<'a> {
+    fn from_external_error(input: Tokens<'t, 'a>, kind: ErrorKind, _: E) -> Self {
+        Error::from_error_kind(input, kind)
+    }
+}
+
+// The input from the start of the next token.
+fn position<'a>(input: Tokens<'_, 'a>) -> &'a str {
+    input.first().map_or("", |token| token.rest)
+}
+
+// The constructs parsers name in their errors, so a `ParseError` can say what was being parsed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Context {
+    HeaderStatement,
+    TypeDefinition,
+    ElementDefinition,
+    Parent,
+    Level,
+    Cardinality,
+    Default,
+    Range,
+    Size,
+    Ordered,
+    UnknownSizeAllowed,
+    Variants,
+    MinVer,
+    MaxVer,
+    Include,
+    // An `include` statement in an EDTD which wasn't read from a file.
+    IncludeOutsideFile,
+}
+
+impl Context {
+    pub fn construct(self) -> &'static str {
+        match self {
+            Context::HeaderStatement => "header statement",
+            Context::TypeDefinition => "type definition",
+            Context::ElementDefinition => "element definition",
+            Context::Parent => "parent property",
+            Context::Level => "level property",
+            Context::Cardinality => "cardinality property",
+            Context::Default => "default property",
+            Context::Range => "range property",
+            Context::Size => "size property",
+            Context::Ordered => "ordered property",
+            Context::UnknownSizeAllowed => "unknownsizeallowed property",
+            Context::Variants => "variants property",
+            Context::MinVer => "minver property",
+            Context::MaxVer => "maxver property",
+            Context::Include | Context::IncludeOutsideFile => "include statement",
+        }
+    }
+
+    // The code of errors innermost in the construct.
+    pub fn code(self) -> ErrorCode {
+        match self {
+            Context::HeaderStatement => ErrorCode::InvalidHeaderStatement,
+            Context::TypeDefinition => ErrorCode::InvalidTypeDefinition,
+            Context::ElementDefinition => ErrorCode::InvalidElementDefinition,
+            Context::Parent => ErrorCode::InvalidParent,
+            Context::Level => ErrorCode::InvalidLevel,
+            Context::Cardinality => ErrorCode::InvalidCardinality,
+            Context::Default => ErrorCode::InvalidDefault,
+            Context::Range => ErrorCode::InvalidRange,
+            Context::Size => ErrorCode::InvalidSize,
+            Context::Ordered => ErrorCode::InvalidOrdered,
+            Context::UnknownSizeAllowed => ErrorCode::InvalidUnknownSizeAllowed,
+            Context::Variants => ErrorCode::InvalidVariants,
+            Context::MinVer | Context::MaxVer => ErrorCode::InvalidVersion,
+            Context::Include | Context::IncludeOutsideFile => ErrorCode::InvalidInclude,
+        }
+    }
+
+    // What the construct's parser wanted where it had several options and none fit.
+    pub fn expected(self) -> &'static str {
+        match self {
+            Context::HeaderStatement => "a value",
+            Context::TypeDefinition | Context::ElementDefinition => "a type",
+            Context::Parent => "a name",
+            Context::Level => "a level",
+            Context::Cardinality => "`*`, `?`, `1` or `+`",
+            Context::Default => "a value of the element's type",
+            Context::Range | Context::Size => "a range item",
+            Context::Ordered | Context::UnknownSizeAllowed => "`yes` or `no`",
+            Context::Variants => "a variant",
+            Context::MinVer | Context::MaxVer => "a version",
+            Context::Include => "a path in double quotes",
+            Context::IncludeOutsideFile => {
+                "a block, since only EDTDs read from files can include others"
             }
-            _ => return None
         }
+    }
+
+    // Whether the construct has a name, which is the first thing in it.
+    pub fn is_definition(self) -> bool {
+        self == Context::HeaderStatement || self == Context::TypeDefinition ||
+            self == Context::ElementDefinition
+    }
+}
+
+// Names the construct `parser` parses in its errors.
+fn context<'t, 'a, O, F>(context: Context, mut parser: F)
+    -> impl FnMut(Tokens<'t, 'a>) -> IResult<'t, 'a, O>
+    where 'a: 't,
+          F: FnMut(Tokens<'t, 'a>) -> IResult<'t, 'a, O>
+{
+    move |input| parser(input).map_err(|err| err.map(|err| err.within(input, context)))
+}
+
+// Fails at `input` the way nom's own parsers do.
+fn fail<'t, 'a, O>(input: Tokens<'t, 'a>, kind: ErrorKind) -> IResult<'t, 'a, O> {
+    Err(::nom::Err::Error(Error::from_error_kind(input, kind)))
+}
+
+// Whether `parser` would succeed at the start of `input`. If it got to the end of the input
+// trying, that's an error: more input might have decided it.
+fn lookahead<'t, 'a, O, F>(input: Tokens<'t, 'a>, mut parser: F)
+    -> Result<bool, ::nom::Err<Error<'a>>>
+    where F: FnMut(Tokens<'t, 'a>) -> IResult<'t, 'a, O>
+{
+    match parser(input) {
+        Ok(_) => Ok(true),
+        Err(::nom::Err::Error(ref err)) if !err.at_end => Ok(false),
+        Err(err) => Err(err),
+    }
+}
+
+// The name at the start of a definition.
+pub fn definition_name(input: &str) -> Option<&str> {
+    let token = Lexer::new(input, &State::new(false)).token();
+    if token.kind == TokenKind::Name { Some(token.text) } else { None }
+}
 
-        modulus += 1;
-        if modulus == 2 {
-            modulus = 0;
-            b.push(buf);
+// How much of `input`, which more may yet be read onto the end of, is certain to lex the same
+// whatever that is. It ends before the first token which runs into the end of the input, or which
+// might only be the start of a comment or string whose end hasn't been read, like a `/` or `"`.
+pub fn settled_len(input: &str, state: &State) -> usize {
+    lexer::tokenize(input, state).iter()
+        .find(|token| {
+            token.text.len() == token.rest.len() || matches!(token.text, "/" | "\"" | "'" | "#" | "%")
+        })
+        .map_or(input.len(), |token| input.len() - token.rest.len())
+}
+
+// Whether the end of `input` is inside a comment: one which has started but not yet ended. The
+// trivia before a token stops at such a comment, so it's lexed as punctuation. An unfinished
+// string is lexed as punctuation too, and what's after it isn't in a comment.
+pub fn ends_in_comment(input: &str, state: &State) -> bool {
+    let mut lexer = Lexer::new(input, state);
+    loop {
+        let token = lexer.token();
+        match (token.kind, token.text) {
+            (TokenKind::End, _) | (TokenKind::Punct, "\"") => return false,
+            (TokenKind::Punct, "/") if token.rest.starts_with("//") => return true,
+            (TokenKind::Punct, "/") if token.rest.starts_with("/*") => return true,
+            (TokenKind::Punct, "#") if state.lenient => return true,
+            _ => {}
         }
     }
+}
 
-    if modulus == 0 {
-        Some(b)
-    } else {
-        None
+// The next token, if it's `text`, which may be a keyword or punctuation.
+fn symbol<'t, 'a: 't>(text: &'static str) -> impl Fn(Tokens<'t, 'a>) -> IResult<'t, 'a, &'a str> {
+    move |input| match input.split_first() {
+        Some((token, rest)) if token.text == text => Ok((rest, token.text)),
+        _ => fail(input, ErrorKind::Tag),
     }
 }
 
-named!(lcomment<&str>, map_res!(
-    preceded!(
-        tag!("//"),
-        take_until_and_consume!("\n")
-    ),
-    str::from_utf8
-));
+// The next token, if it's the keyword `text`. Leniently, it may be written in any case.
+fn word<'t, 'a: 't>(text: &'static str) -> impl Fn(Tokens<'t, 'a>) -> IResult<'t, 'a, &'a str> {
+    move |input| match input.split_first() {
+        Some((token, rest)) if token.text == text => Ok((rest, token.text)),
+        Some((token, rest)) if input.state.lenient && token.text.eq_ignore_ascii_case(text) => {
+            keyword_case(input, token);
+            Ok((rest, token.text))
+        }
+        _ => fail(input, ErrorKind::Tag),
+    }
+}
 
-named!(bcomment<&str>, map_res!(
-    preceded!(
-        tag!("/*"),
-        take_until_and_consume!("*/")
-    ),
-    str::from_utf8
-));
+// Records a keyword written in a case other than its own as a quirk.
+fn keyword_case(input: Tokens, token: &Token) {
+    input.state.quirk(ErrorCode::KeywordCase, token.rest, &token.rest[token.text.len()..]);
+}
 
-named!(comment<&str>, ws!(alt!(lcomment | bcomment)));
+// The next token, if it's a `kind` which `read` makes something of.
+fn read<'t, 'a, O, F>(input: Tokens<'t, 'a>, kind: TokenKind, read: F) -> IResult<'t, 'a, O>
+    where F: FnOnce(&'a str) -> Option<O>
+{
+    match peek(input, kind, read) {
+        Some(o) => Ok((input.skip(1), o)),
+        None => fail(input, ErrorKind::MapRes),
+    }
+}
 
-named!(sep<()>, ws!(value!((), many0!(comment))));
+// What read would make of the next token, without an error to throw away if it's nothing.
+fn peek<'a, O, F>(input: Tokens<'_, 'a>, kind: TokenKind, read: F) -> Option<O>
+    where F: FnOnce(&'a str) -> Option<O>
+{
+    input.first().filter(|token| token.kind == kind).and_then(|token| read(token.text))
+}
 
-// Sadly handwritten name parser.
-fn name(input: &[u8]) -> IResult<&[u8], &str> {
-    let len = input.len();
-    if len == 0 {
-        IResult::Incomplete(Needed::Size(1))
-    } else {
-        // The first character must be alpha or underscore
-        let zeroth = input[0] as char;
-        if !zeroth.is_alpha() && zeroth != '_' {
-            IResult::Error(error_position!(ErrorKind::AlphaNumeric, input))
-        } else {
-            for (idx, item) in input[1..].iter().enumerate() {
-                if !item.is_alphanum() && item.as_char() != '_' {
-                    return IResult::Done(
-                        &input[idx + 1..],
-                        str::from_utf8(&input[0..idx + 1]).unwrap()
-                    )
+// Whether the next token is `text`. Cheaper than a lookahead, which fails with an error to throw
+// away whenever it isn't.
+fn next_is(input: Tokens, text: &str) -> bool {
+    input.first().is_some_and(|token| token.text == text)
+}
+
+// Runs `parser`, which picks between forms by looking ahead rather than trying each in turn. If it
+// fails, it fails as alt would have, at the start, for none of the forms fitting.
+fn choice<'t, 'a, O, F>(input: Tokens<'t, 'a>, parser: F) -> IResult<'t, 'a, O>
+    where F: FnOnce(Tokens<'t, 'a>) -> IResult<'t, 'a, O>
+{
+    match parser(input) {
+        Err(::nom::Err::Error(err)) => {
+            Err(::nom::Err::Error(Error::append(input, ErrorKind::Alt, err)))
+        }
+        result => result,
+    }
+}
+
+// A comma separated list of what `item` parses. The commas before the end of the property are
+// counted first, so the list is allocated once at the size it will most likely be.
+fn list<'t, 'a: 't, O, F>(mut item: F) -> impl FnMut(Tokens<'t, 'a>) -> IResult<'t, 'a, Vec<O>>
+    where F: FnMut(Tokens<'t, 'a>) -> IResult<'t, 'a, O>
+{
+    move |input| {
+        let commas = input.iter()
+            .take_while(|token| !matches!(token.text, ";" | "]" | "}" | ""))
+            .filter(|token| token.text == ",")
+            .count();
+        let mut items = Vec::with_capacity(commas + 1);
+        let (mut input, first) = item(input)?;
+        items.push(first);
+        while next_is(input, ",") {
+            match item(input.skip(1)) {
+                Ok((rest, next)) => {
+                    items.push(next);
+                    input = rest;
                 }
+                // An item cut off by the end of the input might have been one.
+                Err(::nom::Err::Error(ref err)) if !err.at_end => break,
+                Err(e) => return Err(e),
             }
-            IResult::Done(&input[len..], str::from_utf8(&input[..]).unwrap())
-        }
-    }
-}
-
-named!(id<Id>, map_opt!(
-    map_res!(
-        map_res!(take_while!(is_hex_digit), str::from_utf8),
-        |str_val| u32::from_str_radix(str_val, 16)
-    ),
-    Id::from_encoded
-));
-
-named!(type_<Type>, alt_complete!(
-    value!(Type::Int, tag!("int")) |
-    value!(Type::Uint, tag!("uint")) |
-    value!(Type::Float, tag!("float")) |
-    value!(Type::String, tag!("string")) |
-    value!(Type::Date, tag!("date")) |
-    value!(Type::Binary, tag!("binary")) |
-    value!(Type::Container, tag!("container")) |
-    map!(name, |n| Type::Name(n))
-));
-
-named!(parent<Vec<&str>>, delimited!(
-    tuple!(tag!("parent"), sep, tag!(":"), sep),
-    parents,
-    pair!(sep, tag!(";"))
-));
-
-named!(parents<Vec<&str>>, separated_nonempty_list_complete!(
-    delimited!(sep, tag!(","), sep),
-    name
-));
-
-named!(level<Level>, do_parse!(
-    tag!("level") >> sep >> tag!(":") >> sep >>
-    start: map_res!(
-        map_res!(take_while!(is_digit), str::from_utf8),
-        FromStr::from_str
-    ) >>
-    tag!("..") >>
-    end: opt!(
-        map_res!(
-            map_res!(take_while!(is_digit), str::from_utf8),
-            FromStr::from_str
-        )
-    ) >>
-    sep >> tag!(";") >>
-
-    (if let Some(end) = end {
-        Level::Bounded { start, end }
-    } else {
-        Level::Open { start }
-    })
-));
-
-named!(cardinality<Cardinality>, delimited!(
-    tuple!(tag!("card"), sep, tag!(":"), sep),
-    alt_complete!(
-        value!(Cardinality::ZeroOrMany, tag!("*")) |
-        value!(Cardinality::ZeroOrOne, tag!("?")) |
-        value!(Cardinality::ExactlyOne, tag!("1")) |
-        value!(Cardinality::OneOrMany, tag!("+"))
-    ),
-    pair!(sep, tag!(";"))
-));
-
-named!(int_v<i64>, map_res!(
-    map_res!(
-        take_while!(|x| is_digit(x) || x == b'-'),
-        str::from_utf8
-    ),
-    FromStr::from_str
-));
-
-named!(float_v<f64>, map_res!(
-    map_res!(
-        take_while!(|x| is_digit(x) || x == b'-' || x == b'+' || x == b'.' || x == b'e'),
-        str::from_utf8
-    ),
-    FromStr::from_str
-));
-
-named!(date_v<NaiveDateTime>, alt_complete!(
-    do_parse!(
-        year: map_res!(
-            map_res!(take!(4), str::from_utf8),
-            FromStr::from_str
-        ) >>
-        month: map_res!(
-            map_res!(take!(2), str::from_utf8),
-            FromStr::from_str
-        ) >>
-        day: map_res!(
-            map_res!(take!(2), str::from_utf8),
-            FromStr::from_str
-        ) >>
-        tag!("T") >>
-        hour: map_res!(
-            map_res!(take!(2), str::from_utf8),
-            FromStr::from_str
-        ) >>
-        tag!(":") >>
-        minute: map_res!(
-            map_res!(take!(2), str::from_utf8),
-            FromStr::from_str
-        ) >>
-        tag!(":") >>
-        second: map_res!(
-            map_res!(take!(2), str::from_utf8),
-            FromStr::from_str
-        ) >>
-        fractional: opt!(
-            map_res!(
-                map_res!(
-                    // Use recognize here to discard the pair itself, giving the input slice
-                    // containing the dot back.
-                    recognize!(
-                        pair!(
-                            tag!("."),
-                            take_while!(is_digit)
-                        )
-                    ),
-                    str::from_utf8
-                ),
-                <f64 as FromStr>::from_str
-            )
-        ) >>
-        time: map_opt!(value!(()),
-            |_| if let Some(part) = fractional {
-                NaiveTime::from_hms_nano_opt(hour, minute, second, (part * NANOS_PER_SEC) as u32)
+        }
+        Ok((input, items))
+    }
+}
+
+// The end of the input, once everything before it has been parsed.
+fn end<'t, 'a>(input: Tokens<'t, 'a>) -> IResult<'t, 'a, ()> {
+    match input.first() {
+        Some(token) if token.kind == TokenKind::End => Ok((input, ())),
+        _ => fail(input, ErrorKind::Eof),
+    }
+}
+
+// The semicolon ending a property or header statement. Leniently, it may be left out before the
+// `]` or `}` closing the list it's in.
+fn semicolon<'t, 'a>(input: Tokens<'t, 'a>) -> IResult<'t, 'a, ()> {
+    match input.split_first() {
+        Some((token, rest)) if token.text == ";" => Ok((rest, ())),
+        Some((token, _)) if input.state.lenient && (token.text == "]" || token.text == "}") => {
+            input.state.quirk(ErrorCode::MissingSemicolon, token.trivia, token.rest);
+            Ok((input, ()))
+        }
+        _ => fail(input, ErrorKind::Tag),
+    }
+}
+
+// Leniently, a binary default may be hex digits in single quotes, with or without `0x`.
+fn quoted_binary<'t, 'a>(input: Tokens<'t, 'a>) -> IResult<'t, 'a, Cow<'a, [u8]>> {
+    if !input.state.lenient {
+        return fail(input, ErrorKind::Alt);
+    }
+    let (rest, binary) = read(input, TokenKind::Quoted, |text| {
+        let digits = &text[1..text.len() - 1];
+        let digits = digits.strip_prefix("0x").unwrap_or(digits);
+        if digits.bytes().all(|b| b.is_ascii_hexdigit()) { from_hex(digits).ok() } else { None }
+    })?;
+    let token = &input[0];
+    input.state.quirk(ErrorCode::QuotedBinary, token.rest, &token.rest[token.text.len()..]);
+    Ok((rest, Cow::Owned(binary)))
+}
+
+// Names start with a letter or underscore, followed by any letters, digits and underscores.
+fn name<'t, 'a>(input: Tokens<'t, 'a>) -> IResult<'t, 'a, &'a str> {
+    match input.split_first() {
+        Some((token, rest)) if token.kind == TokenKind::Name => Ok((rest, token.text)),
+        _ => fail(input, ErrorKind::AlphaNumeric),
+    }
+}
+
+// Digits, read as a number of whatever type is wanted.
+fn number<'t, 'a, T: FromStr>(input: Tokens<'t, 'a>) -> IResult<'t, 'a, T> {
+    read(input, TokenKind::Number, digits)
+}
+
+fn digits<T: FromStr>(text: &str) -> Option<T> {
+    if text.bytes().all(|b| b.is_ascii_digit()) { text.parse().ok() } else { None }
+}
+
+// The keyword starting a property, and the colon after it.
+fn keyword<'t, 'a: 't>(keyword: &'static str)
+    -> impl FnMut(Tokens<'t, 'a>) -> IResult<'t, 'a, ()>
+{
+    value((), pair(word(keyword), symbol(":")))
+}
+
+// Ids are kept in their encoded form, but only accepted if they're valid. They're written in hex,
+// so whatever token they lexed as, it must be all hex digits.
+fn encoded_id<'t, 'a>(input: Tokens<'t, 'a>) -> IResult<'t, 'a, u32> {
+    map_opt(
+        |input| read(input, input[0].kind, |text: &str| {
+            if text.bytes().all(|b| b.is_ascii_hexdigit()) {
+                u32::from_str_radix(text, 16).ok()
             } else {
-                NaiveTime::from_hms_opt(hour, minute, second)
+                None
             }
-        ) >>
-        date: map_opt!(value!(()), |_| NaiveDate::from_ymd_opt(year, month, day)) >>
-        (NaiveDateTime::new(date, time))
-    ) |
-    map!(int_v, |val| {
-        // Numerical values are nanoseconds since the millennium
-        let epoch = NaiveDateTime::new(
-            NaiveDate::from_ymd(2001, 1, 1),
-            NaiveTime::from_hms(0, 0, 0)
-        );
-        epoch + Duration::nanoseconds(val)
-    })
-));
+        }),
+        |raw| Id::from_encoded(raw).map(|_| raw)
+    )(input)
+}
+
+fn id<'t, 'a>(input: Tokens<'t, 'a>) -> IResult<'t, 'a, Id> {
+    map_opt(encoded_id, Id::from_encoded)(input)
+}
+
+// Built-in type keywords are ordinary names as far as the grammar is concerned, which stops a
+// user-defined type like "integer" from being read as "int" followed by garbage. Leniently, they
+// may be written in any case, but the names of user-defined types may not.
+fn type_<'t, 'a>(input: Tokens<'t, 'a>) -> IResult<'t, 'a, Type<'a>> {
+    let (rest, n) = name(input)?;
+    if let Some(ty) = Type::builtin(n) {
+        return Ok((rest, ty));
+    }
+    match Type::builtin(&n.to_ascii_lowercase()) {
+        Some(ty) if input.state.lenient => {
+            keyword_case(input, &input[0]);
+            Ok((rest, ty))
+        }
+        _ => Ok((rest, Type::Name(n))),
+    }
+}
+
+fn parent<'t, 'a>(input: Tokens<'t, 'a>) -> IResult<'t, 'a, Vec<&'a str>> {
+    context(Context::Parent, delimited(keyword("parent"), parents, semicolon))(input)
+}
+
+fn parents<'t, 'a>(input: Tokens<'t, 'a>) -> IResult<'t, 'a, Vec<&'a str>> {
+    list(name)(input)
+}
+
+fn level<'t, 'a>(input: Tokens<'t, 'a>) -> IResult<'t, 'a, Level> {
+    context(Context::Level, |input| {
+        let (input, ()) = keyword("level")(input)?;
+        let (input, start) = number(input)?;
+        let (input, _) = symbol("..")(input)?;
+        let (input, end) = opt(number)(input)?;
+        let (input, ()) = semicolon(input)?;
+
+        Ok((input, if let Some(end) = end {
+            Level::Bounded { start, end }
+        } else {
+            Level::Open { start }
+        }))
+    })(input)
+}
+
+fn cardinality<'t, 'a>(input: Tokens<'t, 'a>) -> IResult<'t, 'a, Cardinality> {
+    context(Context::Cardinality, delimited(
+        keyword("card"),
+        alt((
+            value(Cardinality::ZeroOrMany, symbol("*")),
+            value(Cardinality::ZeroOrOne, symbol("?")),
+            value(Cardinality::ExactlyOne, symbol("1")),
+            value(Cardinality::OneOrMany, symbol("+"))
+        )),
+        semicolon
+    ))(input)
+}
+
+fn int_v<'t, 'a>(input: Tokens<'t, 'a>) -> IResult<'t, 'a, i64> {
+    read(input, TokenKind::Number, signed)
+}
+
+fn signed(text: &str) -> Option<i64> {
+    if text.bytes().all(|b| b.is_ascii_digit() || b == b'-') { text.parse().ok() } else { None }
+}
+
+fn float_v<'t, 'a>(input: Tokens<'t, 'a>) -> IResult<'t, 'a, f64> {
+    read(input, TokenKind::Number, |text| text.parse().ok())
+}
+
+fn date_v<'t, 'a>(input: Tokens<'t, 'a>) -> IResult<'t, 'a, Date> {
+    match date(input) {
+        Some(date) => Ok((input.skip(1), date)),
+        None => fail(input, ErrorKind::Alt),
+    }
+}
+
+// The next token as a date, written as one or as nanoseconds since the EBML epoch.
+fn date(input: Tokens) -> Option<Date> {
+    peek(input, TokenKind::Date, date::timestamp)
+        .or_else(|| peek(input, TokenKind::Number, signed).map(date::from_nanos))
+}
 
 // Not part of the spec, but helpful for implementing the string_def and binary_def things.
-// This creates owned data (copies the input) since it must transform any input hex data.
-named!(binary_v<Vec<u8>>, alt_complete!(
-    preceded!(
-        tag!("0x"),
-        map_opt!(
-            map_res!(take_while!(is_hex_digit), str::from_utf8),
-            from_hex
-        )
-    ) |
-    map!(
-        delimited!(
-            tag!("\""),
-            recognize!(take_until!("\"")),
-            tag!("\"")
-        ),
-        |slice| slice.to_vec()
-    )
-));
-
-
-named!(int_def<Property>, delimited!(
-    tuple!(tag!("def"), sep, tag!(":"), sep),
-    map!(int_v, Property::IntDefault),
-    pair!(sep, tag!(";"))
-));
-
-named!(uint_def<Property>, delimited!(
-    tuple!(tag!("def"), sep, tag!(":"), sep),
-    map!(
-        map_res!(
-            map_res!(take_while!(is_digit), str::from_utf8),
-            FromStr::from_str
-        ),
-        Property::UintDefault
-    ),
-    pair!(sep, tag!(";"))
-));
-
-named!(float_def<Property>, delimited!(
-    tuple!(tag!("def"), sep, tag!(":"), sep),
-    map!(float_v, Property::FloatDefault),
-    pair!(sep, tag!(";"))
-));
-
-named!(date_def<Property>, delimited!(
-    tuple!(tag!("def"), sep, tag!(":"), sep),
-    map!(date_v, Property::DateDefault),
-    pair!(sep, tag!(";"))
-));
-
-named!(string_def<Property>, delimited!(
-    tuple!(tag!("def"), sep, tag!(":"), sep),
-    map!(map_res!(binary_v, String::from_utf8), Property::StringDefault),
-    pair!(sep, tag!(";"))
-));
-
-named!(binary_def<Property>, delimited!(
-    tuple!(tag!("def"), sep, tag!(":"), sep),
-    map!(binary_v, Property::BinaryDefault),
-    pair!(sep, tag!(";"))
-));
-
-named!(int_range<Property>, delimited!(
-    tuple!(tag!("range"), sep, tag!(":"), sep),
-    map!(
-        separated_nonempty_list_complete!(
-            delimited!(sep, tag!(","), sep),
-            alt_complete!(
-                do_parse!(
-                    start: int_v >>
-                    tag!("..") >>
-                    end: int_v >>
-                    (IntRangeItem::Bounded { start, end })
-                ) |
-                map!(
-                    terminated!(
-                        int_v,
-                        tag!("..")
-                    ),
-                    |start| IntRangeItem::From { start }
-                ) |
-                map!(
-                    preceded!(
-                        tag!(".."),
-                        int_v
-                    ),
-                    |end| IntRangeItem::To { end }
-                ) |
-                map!(int_v, IntRangeItem::Single)
-            )
-        ),
-        Property::IntRange
-    ),
-    pair!(sep, tag!(";"))
-));
-
-named!(uint_range<Property>, delimited!(
-    tuple!(tag!("range"), sep, tag!(":"), sep),
-    map!(
-        separated_nonempty_list_complete!(
-            delimited!(sep, tag!(","), sep),
-            alt_complete!(
-                do_parse!(
-                    start: map_res!(
-                        map_res!(take_while!(is_digit), str::from_utf8),
-                        FromStr::from_str
-                    ) >>
-                    tag!("..") >>
-                    end: map_res!(
-                        map_res!(take_while!(is_digit), str::from_utf8),
-                        FromStr::from_str
-                    ) >>
-                    (UintRangeItem::Bounded { start, end })
-                ) |
-                map!(
-                    terminated!(
-                        map_res!(
-                            map_res!(take_while!(is_digit), str::from_utf8),
-                            FromStr::from_str
-                        ),
-                        tag!("..")
-                    ),
-                    |start| UintRangeItem::From { start }
-                ) |
-                map!(
-                    map_res!(
-                        map_res!(take_while!(is_digit), str::from_utf8),
-                        FromStr::from_str
-                    ),
-                    UintRangeItem::Single
-                )
-            )
-        ),
-        Property::UintRange
-    ),
-    pair!(sep, tag!(";"))
-));
-
-named!(float_range<Property>, delimited!(
-    tuple!(tag!("range"), sep, tag!(":"), sep),
-    map!(
-        separated_nonempty_list_complete!(
-            delimited!(sep, tag!(","), sep),
-            alt_complete!(
-                do_parse!(
-                    start: float_v >>
-                    tag!("<") >>
-                    include_start: map!(opt!(tag!("=")), |x| x.is_some()) >>
-                    tag!("..") >>
-                    tag!("<") >>
-                    include_end: map!(opt!(tag!("=")), |x| x.is_some()) >>
-                    end: float_v >>
-                    (FloatRangeItem::Bounded { start, include_start, end, include_end })
-                ) |
-                do_parse!(
-                    tag!("<") >>
-                    include_end: map!(opt!(tag!("=")), |x| x.is_some()) >>
-                    end: float_v >>
-                    (FloatRangeItem::To { end, include_end })
-                ) |
-                do_parse!(
-                    tag!(">") >>
-                    include_start: map!(opt!(tag!("=")), |x| x.is_some()) >>
-                    start: float_v >>
-                    (FloatRangeItem::From { start, include_start })
-                )
-            )
-        ),
-        Property::FloatRange
-    ),
-    pair!(sep, tag!(";"))
-));
-
-named!(date_range<Property>, delimited!(
-    tuple!(tag!("range"), sep, tag!(":"), sep),
-    map!(
-        separated_nonempty_list_complete!(
-            delimited!(sep, tag!(","), sep),
-            alt_complete!(
-                do_parse!(
-                    start: date_v >>
-                    tag!("..") >>
-                    end: date_v >>
-                    (DateRangeItem::Bounded { start, end })
-                ) |
-                map!(
-                    terminated!(date_v, tag!("..")),
-                    |start| DateRangeItem::From { start }
-                ) |
-                map!(
-                    preceded!(tag!(".."), date_v),
-                    |end| DateRangeItem::To { end }
-                )
-            )
+// Hex data has to be decoded into owned data, but quoted data is borrowed from the input.
+fn binary_v<'t, 'a>(input: Tokens<'t, 'a>) -> IResult<'t, 'a, Cow<'a, [u8]>> {
+    let binary = match input.first() {
+        Some(token) if token.kind == TokenKind::Hex => {
+            let mut binary = Vec::new();
+            from_hex_into(&token.text[2..], &mut binary).ok().map(|()| Cow::Owned(binary))
+        }
+        Some(token) if token.kind == TokenKind::Str => {
+            Some(Cow::Borrowed(&token.text.as_bytes()[1..token.text.len() - 1]))
+        }
+        _ => None,
+    };
+    match binary {
+        Some(binary) => Ok((input.skip(1), binary)),
+        None => fail(input, ErrorKind::Alt),
+    }
+}
+
+// Like binary_v, but the bytes must be UTF-8, and are still borrowed if they were.
+fn string_v<'t, 'a>(input: Tokens<'t, 'a>) -> IResult<'t, 'a, Cow<'a, str>> {
+    map_res(binary_v, into_string)(input)
+}
+
+// A `def:` property holding what `value` parses.
+fn default<'t, 'a, F>(value: F) -> impl FnMut(Tokens<'t, 'a>) -> IResult<'t, 'a, Property<'a>>
+    where 'a: 't,
+          F: FnMut(Tokens<'t, 'a>) -> IResult<'t, 'a, Property<'a>>
+{
+    context(Context::Default, delimited(keyword("def"), value, semicolon))
+}
+
+fn int_def<'t, 'a>(input: Tokens<'t, 'a>) -> IResult<'t, 'a, Property<'a>> {
+    default(map(int_v, Property::IntDefault))(input)
+}
+
+fn uint_def<'t, 'a>(input: Tokens<'t, 'a>) -> IResult<'t, 'a, Property<'a>> {
+    default(map(number, Property::UintDefault))(input)
+}
+
+fn float_def<'t, 'a>(input: Tokens<'t, 'a>) -> IResult<'t, 'a, Property<'a>> {
+    default(map(float_v, Property::FloatDefault))(input)
+}
+
+fn date_def<'t, 'a>(input: Tokens<'t, 'a>) -> IResult<'t, 'a, Property<'a>> {
+    default(map(date_v, Property::DateDefault))(input)
+}
+
+fn string_def<'t, 'a>(input: Tokens<'t, 'a>) -> IResult<'t, 'a, Property<'a>> {
+    default(map(string_v, Property::StringDefault))(input)
+}
+
+fn binary_def<'t, 'a>(input: Tokens<'t, 'a>) -> IResult<'t, 'a, Property<'a>> {
+    default(map(alt((binary_v, quoted_binary)), Property::BinaryDefault))(input)
+}
+
+// A `range:` property listing what `item` parses, which `property` turns into the property.
+fn range<'t, 'a, I, F, P>(item: F, property: P)
+    -> impl FnMut(Tokens<'t, 'a>) -> IResult<'t, 'a, Property<'a>>
+    where 'a: 't,
+          F: FnMut(Tokens<'t, 'a>) -> IResult<'t, 'a, I>,
+          P: FnMut(Vec<I>) -> Property<'a>
+{
+    delimited(keyword("range"), map(list(item), property), semicolon)
+}
+
+fn int_range<'t, 'a>(input: Tokens<'t, 'a>) -> IResult<'t, 'a, Property<'a>> {
+    context(Context::Range, range(int_range_item, Property::IntRange))(input)
+}
+
+// Range items are told apart by the `..` in them and whether a value follows it, so they're
+// looked ahead at rather than each tried in turn, at the cost of an error whenever one isn't it.
+fn int_range_item<'t, 'a>(input: Tokens<'t, 'a>) -> IResult<'t, 'a, IntRangeItem> {
+    choice(input, |input| {
+        if next_is(input, "..") {
+            let (input, end) = int_v(input.skip(1))?;
+            return Ok((input, IntRangeItem::To { end }));
+        }
+        let (input, start) = int_v(input)?;
+        if !next_is(input, "..") {
+            return Ok((input, IntRangeItem::Single(start)));
+        }
+        let input = input.skip(1);
+        Ok(match peek(input, TokenKind::Number, signed) {
+            Some(end) => (input.skip(1), IntRangeItem::Bounded { start, end }),
+            None => (input, IntRangeItem::From { start }),
+        })
+    })
+}
+
+fn uint_range_item<'t, 'a>(input: Tokens<'t, 'a>) -> IResult<'t, 'a, UintRangeItem> {
+    choice(input, |input| {
+        let (input, start) = number(input)?;
+        if !next_is(input, "..") {
+            return Ok((input, UintRangeItem::Single(start)));
+        }
+        let input = input.skip(1);
+        Ok(match peek(input, TokenKind::Number, digits) {
+            Some(end) => (input.skip(1), UintRangeItem::Bounded { start, end }),
+            None => (input, UintRangeItem::From { start }),
+        })
+    })
+}
+
+// Without a context, for the ranges of strings and binaries to name as their own.
+fn uint_range_items<'t, 'a>(input: Tokens<'t, 'a>) -> IResult<'t, 'a, Property<'a>> {
+    range(uint_range_item, Property::UintRange)(input)
+}
+
+fn uint_range<'t, 'a>(input: Tokens<'t, 'a>) -> IResult<'t, 'a, Property<'a>> {
+    context(Context::Range, uint_range_items)(input)
+}
+
+// Whether a bound of a float range includes its value.
+fn inclusive<'t, 'a>(input: Tokens<'t, 'a>) -> IResult<'t, 'a, bool> {
+    if next_is(input, "=") { Ok((input.skip(1), true)) } else { Ok((input, false)) }
+}
+
+fn float_range<'t, 'a>(input: Tokens<'t, 'a>) -> IResult<'t, 'a, Property<'a>> {
+    context(Context::Range, range(float_range_item, Property::FloatRange))(input)
+}
+
+fn float_range_item<'t, 'a>(input: Tokens<'t, 'a>) -> IResult<'t, 'a, FloatRangeItem> {
+    choice(input, |input| {
+        if next_is(input, "<") {
+            let (input, (include_end, end)) = pair(inclusive, float_v)(input.skip(1))?;
+            return Ok((input, FloatRangeItem::To { end, include_end }));
+        }
+        if next_is(input, ">") {
+            let (input, (include_start, start)) = pair(inclusive, float_v)(input.skip(1))?;
+            return Ok((input, FloatRangeItem::From { start, include_start }));
+        }
+        map(
+            tuple((
+                float_v, symbol("<"), inclusive, symbol(".."), symbol("<"), inclusive, float_v
+            )),
+            |(start, _, include_start, _, _, include_end, end)| {
+                FloatRangeItem::Bounded { start, include_start, end, include_end }
+            }
+        )(input)
+    })
+}
+
+fn date_range<'t, 'a>(input: Tokens<'t, 'a>) -> IResult<'t, 'a, Property<'a>> {
+    context(Context::Range, range(date_range_item, Property::DateRange))(input)
+}
+
+fn date_range_item<'t, 'a>(input: Tokens<'t, 'a>) -> IResult<'t, 'a, DateRangeItem> {
+    choice(input, |input| {
+        if next_is(input, "..") {
+            let (input, end) = date_v(input.skip(1))?;
+            return Ok((input, DateRangeItem::To { end }));
+        }
+        let (input, start) = date_v(input)?;
+        let (input, _) = symbol("..")(input)?;
+        Ok(match date(input) {
+            Some(end) => (input.skip(1), DateRangeItem::Bounded { start, end }),
+            None => (input, DateRangeItem::From { start }),
+        })
+    })
+}
+
+fn string_range<'t, 'a>(input: Tokens<'t, 'a>) -> IResult<'t, 'a, Property<'a>> {
+    context(Context::Range, map_opt(
+        uint_range_items,
+        |prop: Property| match prop {
+            Property::UintRange(ur) => {
+                ur.iter()
+                  .map(|uri| uri.to_string_range_item())
+                  .collect::<Option<Vec<_>>>()
+                  .map(Property::StringRange)
+            }
+            _ => unreachable!(),
+        }
+    ))(input)
+}
+
+fn binary_range<'t, 'a>(input: Tokens<'t, 'a>) -> IResult<'t, 'a, Property<'a>> {
+    context(Context::Range, map_opt(
+        uint_range_items,
+        |prop: Property| match prop {
+            Property::UintRange(ur) => {
+                ur.iter()
+                  .map(|uri| uri.to_binary_range_item())
+                  .collect::<Option<Vec<_>>>()
+                  .map(Property::BinaryRange)
+            }
+            _ => unreachable!(),
+        }
+    ))(input)
+}
+
+fn size<'t, 'a>(input: Tokens<'t, 'a>) -> IResult<'t, 'a, Property<'a>> {
+    context(Context::Size, delimited(
+        keyword("size"),
+        map(
+            list(uint_range_item),
+            |items| Property::Size(SizeList::from(items))
         ),
-        Property::DateRange
-    ),
-    pair!(sep, tag!(";"))
-));
-
-named!(string_range<Property>, map_opt!(
-    uint_range,
-    |prop: Property| match prop {
-        Property::UintRange(ur) => {
-            ur.iter()
-              .map(|uri| uri.to_string_range_item())
-              .collect::<Option<Vec<_>>>()
-              .map(Property::StringRange)
-        }
-        _ => unreachable!(),
-    }
-));
-
-named!(binary_range<Property>, map_opt!(
-    uint_range,
-    |prop: Property| match prop {
-        Property::UintRange(ur) => {
-            ur.iter()
-              .map(|uri| uri.to_binary_range_item())
-              .collect::<Option<Vec<_>>>()
-              .map(Property::BinaryRange)
-        }
-        _ => unreachable!(),
-    }
-));
-
-named!(size<Property>, delimited!(
-    tuple!(tag!("size"), sep, tag!(":"), sep),
-    map!(
-        separated_nonempty_list_complete!(
-            delimited!(sep, tag!(","), sep),
-            alt_complete!(
-                do_parse!(
-                    start: map_res!(
-                        map_res!(take_while!(is_digit), str::from_utf8),
-                        FromStr::from_str
-                    ) >>
-                    tag!("..") >>
-                    end: map_res!(
-                        map_res!(take_while!(is_digit), str::from_utf8),
-                        FromStr::from_str
-                    ) >>
-                    (UintRangeItem::Bounded { start, end })
-                ) |
-                map!(
-                    terminated!(
-                        map_res!(
-                            map_res!(take_while!(is_digit), str::from_utf8),
-                            FromStr::from_str
-                        ),
-                        tag!("..")
-                    ),
-                    |start| UintRangeItem::From { start }
-                ) |
-                map!(
-                    map_res!(
-                        map_res!(take_while!(is_digit), str::from_utf8),
-                        FromStr::from_str
-                    ),
-                    UintRangeItem::Single
-                )
-            )
+        semicolon
+    ))(input)
+}
+
+// `yes` or `no`, or `1` or `0`.
+fn boolean<'t, 'a>(input: Tokens<'t, 'a>) -> IResult<'t, 'a, bool> {
+    alt((
+        value(true, alt((word("yes"), symbol("1")))),
+        value(false, alt((word("no"), symbol("0"))))
+    ))(input)
+}
+
+fn ordered<'t, 'a>(input: Tokens<'t, 'a>) -> IResult<'t, 'a, Property<'a>> {
+    context(Context::Ordered, delimited(
+        keyword("ordered"),
+        map(boolean, Property::Ordered),
+        semicolon
+    ))(input)
+}
+
+fn unknown_size_allowed<'t, 'a>(input: Tokens<'t, 'a>) -> IResult<'t, 'a, Property<'a>> {
+    context(Context::UnknownSizeAllowed, delimited(
+        keyword("unknownsizeallowed"),
+        map(boolean, Property::UnknownSizeAllowed),
+        semicolon
+    ))(input)
+}
+
+// An extension to the EDTD specification, naming the values of a `uint` element for the enum
+// generated for it: `variants: Video = 1, Audio = 2;`.
+fn variants<'t, 'a>(input: Tokens<'t, 'a>) -> IResult<'t, 'a, Property<'a>> {
+    context(Context::Variants, delimited(
+        keyword("variants"),
+        map(
+            list(separated_pair(name, symbol("="), number)),
+            Property::Variants
         ),
-        Property::Size
-    ),
-    pair!(sep, tag!(";"))
-));
-
-named!(ordered<Property>, delimited!(
-    tuple!(tag!("ordered"), sep, tag!(":"), sep),
-    alt_complete!(
-        value!(
-            Property::Ordered(true),
-            alt_complete!(tag!("yes") | tag!("1"))
-        ) |
-        value!(
-            Property::Ordered(false),
-            alt_complete!(tag!("no") | tag!("0"))
-        )
-    ),
-    pair!(sep, tag!(";"))
-));
-
-// Types impossible to distinguish:
+        semicolon
+    ))(input)
+}
+
+// Extensions to the EDTD specification, giving the first and last DocTypeVersions an element is
+// part of, as Matroska's XML schema does: `minver:2; maxver:4;`.
+fn minver<'t, 'a>(input: Tokens<'t, 'a>) -> IResult<'t, 'a, Property<'a>> {
+    context(Context::MinVer, delimited(
+        keyword("minver"),
+        map(number, Property::MinVer),
+        semicolon
+    ))(input)
+}
+
+fn maxver<'t, 'a>(input: Tokens<'t, 'a>) -> IResult<'t, 'a, Property<'a>> {
+    context(Context::MaxVer, delimited(
+        keyword("maxver"),
+        map(number, Property::MaxVer),
+        semicolon
+    ))(input)
+}
+
+// The kind of a header statement's value is told by the token it's written as. Types impossible to
+// distinguish this way are decided in favor of the narrower one:
 //      Uint vs Int, if the Int happens to be positive
 //      String vs Binary, if the Binary happens to be valid Unicode
-named!(header_statement<HeaderStatement>, do_parse!(
-    name: name >>
-    sep >>
-    tag!(":=") >>
-    sep >>
-    value: alt_complete!(
-        // By including the terminator in these parsers, we stop floats from getting interpreted as
-        // integers.
-        map!(
-            terminated!(
-                map_res!(map_res!(take_while!(is_digit), str::from_utf8), FromStr::from_str),
-                pair!(sep, tag!(";"))
-            ),
-            |value| HeaderStatement::Uint { name, value }
-        ) |
-        map!(
-            terminated!(int_v, pair!(sep, tag!(";"))),
-            |value| HeaderStatement::Int { name, value }
-        ) |
-        map!(
-            terminated!(float_v, pair!(sep, tag!(";"))),
-            |value| HeaderStatement::Float { name, value }
-        ) |
-        map!(
-            terminated!(date_v, pair!(sep, tag!(";"))),
-            |value| HeaderStatement::Date { name, value }
-        ) |
-        map!(
-            terminated!(
-                map_res!(binary_v, String::from_utf8),
-                pair!(sep, tag!(";"))
-            ),
-            |value| HeaderStatement::String { name, value }
-        ) |
-        map!(
-            terminated!(binary_v, pair!(sep, tag!(";"))),
-            |value| HeaderStatement::Binary { name, value }
-        ) |
-        map!(
-            terminated!(::parsers::name, pair!(sep, tag!(";"))),
-            |value| HeaderStatement::Named { name, value }
-        )
-    ) >>
-    (value)
-));
-
-named!(hblock<Header>, preceded!(
-    tuple!(tag!("declare"), sep, tag!("header"), sep, tag!("{"), sep),
-    separated_nonempty_list_complete!(sep, header_statement)
-));
-
-fn update_newtype_with_property<'a, 'b>(mut nt: NewType<'a>, p: Property<'b>) -> NewType<'a> {
-    nt.update(p);
+fn header_statement<'t, 'a>(input: Tokens<'t, 'a>) -> IResult<'t, 'a, HeaderStatement<'a>> {
+    context(Context::HeaderStatement, |input| {
+        let (input, name) = name(input)?;
+        let (input, _) = symbol(":=")(input)?;
+        let value = move |input: Tokens<'t, 'a>| match input[0].kind {
+            TokenKind::Str | TokenKind::Hex => {
+                map(binary_v, move |value| match into_string(value) {
+                    Ok(value) => HeaderStatement::String { name, value },
+                    Err(value) => HeaderStatement::Binary { name, value },
+                })(input)
+            }
+            TokenKind::Date => map(date_v, move |value| {
+                HeaderStatement::Date { name, value }
+            })(input),
+            TokenKind::Number if input[0].text.contains(['.', 'e', '+']) => {
+                map(float_v, move |value| HeaderStatement::Float { name, value })(input)
+            }
+            TokenKind::Number if input[0].text.starts_with('-') => map(int_v, move |value| {
+                HeaderStatement::Int { name, value }
+            })(input),
+            TokenKind::Number => map(number, move |value| {
+                HeaderStatement::Uint { name, value }
+            })(input),
+            TokenKind::Name => map(::parsers::name, move |value| {
+                HeaderStatement::Named { name, value }
+            })(input),
+            _ => fail(input, ErrorKind::Alt),
+        };
+        // Whatever's wrong with the value, it's reported as not being one.
+        match terminated(value, semicolon)(input) {
+            Err(::nom::Err::Error(err)) => {
+                Err(::nom::Err::Error(Error::append(input, ErrorKind::Alt, err)))
+            }
+            result => result,
+        }
+    })(input)
+}
+
+// Parses a node of the AST, noting where it starts and ends as how much input is left there. The
+// parsers don't know how much came before, so `Dtd::locate_spans` turns these into offsets.
+fn spanned<'t, 'a, O, F>(input: Tokens<'t, 'a>, node: F) -> IResult<'t, 'a, Spanned<O>>
+    where F: Fn(Tokens<'t, 'a>) -> IResult<'t, 'a, O>
+{
+    let (rest, o) = node(input)?;
+    let end = rest.first().map_or(0, |token| token.trivia.len());
+    Ok((rest, Spanned::new(o, Span { start: position(input).len(), end })))
+}
+
+// Parses the items of a block up to its closing brace. Unlike many0, an item which fails to parse
+// is reported as an error rather than ending the list, or recovered from if errors are.
+fn block_items<'t, 'a, 'r, O, F>(mut input: Tokens<'t, 'a>, item: F, errors: Recovered<'r, 'a>)
+    -> IResult<'t, 'a, Vec<Spanned<O>>>
+    where F: Fn(Tokens<'t, 'a>) -> IResult<'t, 'a, O>
+{
+    let mut items = Vec::new();
+    loop {
+        if next_is(input, "}") {
+            return Ok((input, items));
+        }
+
+        match spanned(input, &item) {
+            Ok((rest, o)) => {
+                items.push(o);
+                input = rest;
+            }
+            Err(::nom::Err::Error(err)) => match recover(input, err, errors) {
+                Ok(rest) => input = rest,
+                Err(err) => return Err(::nom::Err::Error(err)),
+            },
+            Err(err) => return Err(err),
+        }
+    }
+}
+
+// Records an error in the definition at the start of `input` and skips the rest of it, if errors
+// are being recovered from and the end of the definition can be found.
+fn recover<'t, 'a, 'r>(input: Tokens<'t, 'a>, err: Error<'a>, errors: Recovered<'r, 'a>)
+    -> Result<Tokens<'t, 'a>, Error<'a>>
+{
+    match (errors, skip_definition(input)) {
+        // Something must be skipped, or the same error would be found again.
+        (Some(errors), Some(rest)) if rest.len() < input.len() => {
+            errors.borrow_mut().push(err);
+            Ok(rest)
+        }
+        _ => Err(err),
+    }
+}
+
+// Skips to the end of a definition: past the semicolon ending it, or the closing bracket or brace
+// of its properties or body if nothing follows them, or up to the closing brace of the enclosing
+// block. Brackets and braces inside it are skipped whole. None if there's no such point within
+// MAX_SKIP bytes.
+fn skip_definition<'t, 'a>(input: Tokens<'t, 'a>) -> Option<Tokens<'t, 'a>> {
+    let start = position(input).len();
+    let mut depth = 0;
+    for (idx, token) in input.iter().enumerate() {
+        if token.kind == TokenKind::End || start - token.rest.len() >= MAX_SKIP {
+            return None;
+        }
+        let after = input.skip(idx + 1);
+        match token.text {
+            "[" | "{" => depth += 1,
+            ";" if depth == 0 => return Some(after),
+            "}" if depth == 0 => return Some(input.skip(idx)),
+            "]" | "}" if depth == 1 => {
+                // Properties may be followed by a body, and either by a semicolon.
+                match after.first().map(|token| token.text) {
+                    Some("{") if token.text == "]" => depth = 0,
+                    Some(";") => return Some(after.skip(1)),
+                    _ => return Some(after),
+                }
+            }
+            "]" | "}" => depth -= 1,
+            _ => {}
+        }
+    }
+    None
+}
+
+// The keywords opening a block of `kind`. Drafts of the EDTD language differ over whether the
+// header is declared and the rest defined, so either keyword opens any block.
+fn block_start<'t, 'a: 't>(kind: &'static str)
+    -> impl Fn(Tokens<'t, 'a>) -> IResult<'t, 'a, ()>
+{
+    move |input| {
+        let (input, _) = alt((word("declare"), word("define")))(input)?;
+        let (input, _) = word(kind)(input)?;
+        Ok((input, ()))
+    }
+}
+
+fn hblock<'t, 'a>(input: Tokens<'t, 'a>) -> IResult<'t, 'a, Header<'a>> {
+    recovering_hblock(input, None)
+}
+
+fn recovering_hblock<'t, 'a, 'r>(input: Tokens<'t, 'a>, errors: Recovered<'r, 'a>)
+    -> IResult<'t, 'a, Header<'a>>
+{
+    delimited(
+        pair(block_start("header"), symbol("{")),
+        |input| block_items(input, header_statement, errors),
+        symbol("}")
+    )(input)
+}
+
+fn update_newtype_with_property<'a>(mut nt: NewType<'a>, p: Spanned<Property<'a>>) -> NewType<'a> {
+    nt.update(p.node);
     nt
 }
 
-named!(dtype_param_open, dbg_dmp!(delimited!(sep, tag!("["), sep)));
-named!(dtype_param_close<()>, value!((), dbg_dmp!(tuple!(
-    sep,
-    tag!("]"),
-    sep,
-    opt!(tag!(";"))
-))));
-
-named_args!(int_properties<'a>(name: &'a str)<NewType<'a>>, delimited!(
-    dbg_dmp!(dtype_param_open),
-    dbg_dmp!(value!(NewType::Int{name:"",default:None,range:None}, tag!("x"))),
-    //dbg_dmp!(fold_many1!(
-    //    alt!(delimited!(sep, int_range, sep) | delimited!(sep, int_def, sep)),
-    //    NewType::Int { name, default: None, range: None },
-    //    update_newtype_with_property
-    //)),
-    dbg_dmp!(dtype_param_close)
-));
-
-named_args!(uint_properties<'a>(name: &'a str)<NewType<'a>>, delimited!(
-    dtype_param_open,
-    fold_many1!(
-        alt!(uint_range | uint_def),
-        NewType::Uint { name, default: None, range: None },
-        update_newtype_with_property
-    ),
-    dtype_param_close
-));
-
-named!(dtype<NewType>, do_parse!(
-    name: name >>
-    sep >>
-    tag!(":=") >>
-    sep >>
-    value: switch!(terminated!(type_, sep),
-
-        Type::Int => alt!(
-            dbg_dmp!(call!(int_properties, name)) |
-            value!(
-                NewType::Int { name, default: None, range: None },
-                not!(dtype_param_open)
-            )
-        ) |
-
-        Type::Uint => alt!(
-            call!(uint_properties, name) |
-            value!(
-                NewType::Uint { name, default: None, range: None },
-                not!(dtype_param_open)
-            )
-        ) |
-
-        Type::Float => alt_complete!(
-            // It _has_ properties
-            delimited!(
-                dtype_param_open,
-                fold_many1!(
-                    preceded!(sep, alt_complete!(float_range | float_def)),
-                    NewType::Float { name, default: None, range: None },
-                    update_newtype_with_property
-                ),
-                dtype_param_close
-            ) |
-            // It _doesn't_ have properties
-            value!(
-                NewType::Float { name, default: None, range: None },
-                not!(dtype_param_open)
-            )
-        ) |
-
-        Type::Date => alt_complete!(
-            // It _has_ properties
-            delimited!(
-                dtype_param_open,
-                fold_many1!(
-                    preceded!(sep, alt_complete!(date_range | date_def)),
-                    NewType::Date { name, default: None, range: None },
-                    update_newtype_with_property
-                ),
-                dtype_param_close
-            ) |
-            // It _doesn't_ have properties
-            value!(
-                NewType::Date { name, default: None, range: None },
-                not!(dtype_param_open)
-            )
-        ) |
-
-        Type::String => alt_complete!(
-            // It _has_ properties
-            delimited!(
-                dbg_dmp!(dtype_param_open),
-                fold_many1!(
-                    preceded!(sep, alt_complete!(string_range | string_def)),
-                    NewType::String { name, default: None, range: None },
-                    update_newtype_with_property
-                ),
-                dtype_param_close
-            ) |
-            // It _doesn't_ have properties
-            value!(
-                NewType::String { name, default: None, range: None },
-                not!(dtype_param_open)
-            )
-        ) |
-
-        Type::Binary => alt_complete!(
-            // It _has_ properties
-            delimited!(
-                dtype_param_open,
-                fold_many1!(
-                    preceded!(sep, alt_complete!(binary_range | binary_def)),
-                    NewType::Binary { name, default: None, range: None },
-                    update_newtype_with_property
-                ),
-                dtype_param_close
-            ) |
-            // It _doesn't_ have properties
-            value!(
-                NewType::Binary { name, default: None, range: None },
-                not!(dtype_param_open)
-            )
-        ) |
+fn dtype_param_close<'t, 'a>(input: Tokens<'t, 'a>) -> IResult<'t, 'a, ()> {
+    value((), pair(symbol("]"), opt(symbol(";"))))(input)
+}
 
+// Types without properties may still be followed by a semicolon.
+fn dtype_no_params<'t, 'a>(input: Tokens<'t, 'a>) -> IResult<'t, 'a, ()> {
+    value((), pair(not(symbol("[")), opt(symbol(";"))))(input)
+}
+
+// The comments before each type are kept as its documentation, as they are for elements.
+fn dtype<'t, 'a>(input: Tokens<'t, 'a>) -> IResult<'t, 'a, NewType<'a>> {
+    context(Context::TypeDefinition, |start: Tokens<'t, 'a>| {
+        let (input, name) = name(start)?;
+        let (input, _) = symbol(":=")(input)?;
+        let (input, ty) = type_(input)?;
+        dtype_body(input, name, ty, start[0].comments.clone())
+    })(input)
+}
+
+// The properties of a type, if it has any. Once the opening bracket has been read, an error in a
+// property is reported rather than the type being taken to have none.
+fn dtype_body<'t, 'a>(input: Tokens<'t, 'a>, name: &'a str, ty: Type<'a>, comments: Vec<&'a str>)
+    -> IResult<'t, 'a, NewType<'a>>
+{
+    let new_type = match ty {
+        Type::Int => NewType::Int { name, default: None, range: None, comments },
+        Type::Uint => NewType::Uint { name, default: None, range: None, comments },
+        Type::Float => NewType::Float { name, default: None, range: None, comments },
+        Type::Date => NewType::Date { name, default: None, range: None, comments },
+        Type::String => NewType::String { name, default: None, range: None, comments },
+        Type::Binary => NewType::Binary { name, default: None, range: None, comments },
         // Type::Container and Type::Name are unimplemented
-        _ => value!(NewType::Int { name, default: None, range: None })
-    ) >>
-    (value)
-));
+        _ => return Ok((input, NewType::Int { name, default: None, range: None, comments })),
+    };
+
+    if next_is(input, "[") {
+        let (input, _) = symbol("[")(input)?;
+        let (input, new_type) = property_items(
+            input,
+            new_type,
+            |input| typed_property(input, ty),
+            update_newtype_with_property
+        )?;
+        let (input, ()) = dtype_param_close(input)?;
+        Ok((input, new_type))
+    } else {
+        let (input, ()) = dtype_no_params(input)?;
+        Ok((input, new_type))
+    }
+}
+
+fn tblock<'t, 'a>(input: Tokens<'t, 'a>) -> IResult<'t, 'a, Vec<Spanned<NewType<'a>>>> {
+    recovering_tblock(input, None)
+}
+
+fn recovering_tblock<'t, 'a, 'r>(input: Tokens<'t, 'a>, errors: Recovered<'r, 'a>)
+    -> IResult<'t, 'a, Vec<Spanned<NewType<'a>>>>
+{
+    delimited(
+        pair(block_start("types"), symbol("{")),
+        |input| block_items(input, dtype, errors),
+        symbol("}")
+    )(input)
+}
+
+// The default and range properties of an element can only be parsed once its type is known. For
+// elements with a user-defined type, we don't know the type until the whole DTD has been read, so
+// we try each kind in turn and leave it to `Dtd::new` to convert the result.
+fn typed_property<'t, 'a>(input: Tokens<'t, 'a>, ty: Type<'a>) -> IResult<'t, 'a, Property<'a>> {
+    let range = property_keyword(input)? == Some("range");
+    match ty {
+        Type::Int if range => int_range(input),
+        Type::Int => int_def(input),
+        Type::Uint if range => uint_range(input),
+        Type::Uint => uint_def(input),
+        Type::Float if range => float_range(input),
+        Type::Float => float_def(input),
+        Type::Date if range => date_range(input),
+        Type::Date => date_def(input),
+        Type::String if range => string_range(input),
+        Type::String => string_def(input),
+        Type::Binary if range => binary_range(input),
+        Type::Binary => binary_def(input),
+        Type::Name(_) if range => context(Context::Range, alt((
+            uint_range, int_range, float_range, date_range
+        )))(input),
+        Type::Name(_) => context(Context::Default, alt((
+            uint_def, int_def, float_def, date_def, binary_def
+        )))(input),
+        Type::Container => fail(input, ErrorKind::Alt),
+    }
+}
+
+// The keyword starting a property, if it's one an element may have. Leniently, it may be written
+// in any case, which is recorded once the property is parsed. If the end of the input cut off
+// what might have been one, that's an error.
+fn property_keyword<'a>(input: Tokens<'_, 'a>) -> Result<Option<&'a str>, ::nom::Err<Error<'a>>> {
+    const KEYWORDS: &[&str] = &[
+        "parent", "level", "card", "def", "range", "size", "ordered", "unknownsizeallowed",
+        "variants", "minver", "maxver",
+    ];
+    match terminated(name, symbol(":"))(input) {
+        Ok((_, keyword)) => Ok(KEYWORDS.iter()
+            .find(|&&k| k == keyword || input.state.lenient && k.eq_ignore_ascii_case(keyword))
+            .cloned()),
+        Err(::nom::Err::Error(ref err)) if !err.at_end => Ok(None),
+        Err(err) => Err(err),
+    }
+}
+
+// Properties are chosen by their keyword, so once it's been read, an error in the value is
+// reported rather than another property being tried.
+fn element_property<'t, 'a>(input: Tokens<'t, 'a>, ty: Type<'a>)
+    -> IResult<'t, 'a, Property<'a>>
+{
+    match property_keyword(input)? {
+        Some("parent") => map(parent, Property::Parent)(input),
+        Some("level") => map(level, Property::Level)(input),
+        Some("card") => map(cardinality, Property::Cardinality)(input),
+        Some("size") => size(input),
+        Some("ordered") => ordered(input),
+        Some("unknownsizeallowed") => unknown_size_allowed(input),
+        Some("variants") => variants(input),
+        Some("minver") => minver(input),
+        Some("maxver") => maxver(input),
+        Some(_) => typed_property(input, ty),
+        None => fail(input, ErrorKind::Tag),
+    }
+}
+
+// Parses the properties between square brackets up to the closing bracket, folding them into
+// `init`. Like block_items, a property which fails to parse is reported rather than ending the
+// list. There must be at least one.
+fn property_items<'t, 'a, T, P, U>(mut input: Tokens<'t, 'a>, init: T, property: P, update: U)
+    -> IResult<'t, 'a, T>
+    where P: Fn(Tokens<'t, 'a>) -> IResult<'t, 'a, Property<'a>>,
+          U: Fn(T, Spanned<Property<'a>>) -> T
+{
+    let mut acc = init;
+    let mut empty = true;
+    loop {
+        if next_is(input, "]") {
+            if empty {
+                return fail(input, ErrorKind::Many1);
+            }
+            return Ok((input, acc));
+        }
+
+        let (rest, p) = spanned(input, &property)?;
+        acc = update(acc, p);
+        empty = false;
+        input = rest;
+    }
+}
+
+fn update_element_with_property<'a>(mut e: Element<'a>, p: Spanned<Property<'a>>) -> Element<'a> {
+    e.update(p);
+    e
+}
+
+// Like block_items, for the elements of a block. The comments before each element are kept as
+// its documentation.
+fn element_items<'t, 'a, 'r>(mut input: Tokens<'t, 'a>, errors: Recovered<'r, 'a>)
+    -> IResult<'t, 'a, Vec<Spanned<Element<'a>>>>
+{
+    let mut items = Vec::new();
+    loop {
+        if next_is(input, "}") {
+            return Ok((input, items));
+        }
+
+        match spanned(input, |input| recovering_element(input, errors)) {
+            Ok((rest, element)) => {
+                let comments = input[0].comments.clone();
+                items.push(element.map(|element| Element { comments, ..element }));
+                input = rest;
+            }
+            Err(::nom::Err::Error(err)) => match recover(input, err, errors) {
+                Ok(rest) => input = rest,
+                Err(err) => return Err(::nom::Err::Error(err)),
+            },
+            Err(err) => return Err(err),
+        }
+    }
+}
+
+fn element<'t, 'a>(input: Tokens<'t, 'a>) -> IResult<'t, 'a, Element<'a>> {
+    recovering_element(input, None)
+}
+
+// Once an element's property list or body has been opened, errors inside it are reported rather
+// than causing it to be skipped.
+fn recovering_element<'t, 'a, 'r>(input: Tokens<'t, 'a>, errors: Recovered<'r, 'a>)
+    -> IResult<'t, 'a, Element<'a>>
+{
+    let recovered = errors.map_or(0, |errors| errors.borrow().len());
+    let result = context(Context::ElementDefinition, |input| {
+        let (input, name) = name(input)?;
+        let (input, _) = symbol(":=")(input)?;
+        let (input, id) = encoded_id(input)?;
+        let (input, ty) = type_(input)?;
+        let has_properties = next_is(input, "[");
+        let (input, properties) = cond(has_properties, delimited(
+            symbol("["),
+            |input| property_items(
+                input,
+                Element::new(name, id, ty),
+                |input| element_property(input, ty),
+                update_element_with_property
+            ),
+            dtype_param_close
+        ))(input)?;
+        let has_children = next_is(input, "{");
+        let (input, children) = cond(has_children, delimited(
+            symbol("{"),
+            |input| element_items(input, errors),
+            symbol("}")
+        ))(input)?;
+        let (input, _) = cond(!has_children, opt(symbol(";")))(input)?;
+        Ok((input, Element {
+            children: children.unwrap_or_else(Vec::new),
+            ..properties.unwrap_or_else(|| Element::new(name, id, ty))
+        }))
+    })(input);
+
+    // Errors recovered from in its children were inside it too.
+    if let Some(errors) = errors {
+        for err in &mut errors.borrow_mut()[recovered..] {
+            *err = err.clone().within(input, Context::ElementDefinition);
+        }
+    }
+    result
+}
+
+// The DocType an element block is labeled with, or the path an include names: anything between
+// double quotes.
+fn quoted<'t, 'a>(input: Tokens<'t, 'a>) -> IResult<'t, 'a, &'a str> {
+    match input.first() {
+        Some(token) if token.kind == TokenKind::Str => {
+            Ok((input.skip(1), &token.text[1..token.text.len() - 1]))
+        }
+        _ => fail(input, ErrorKind::Tag),
+    }
+}
+
+fn eblock<'t, 'a>(input: Tokens<'t, 'a>)
+    -> IResult<'t, 'a, (Option<&'a str>, Vec<Spanned<Element<'a>>>)>
+{
+    recovering_eblock(input, None)
+}
+
+fn recovering_eblock<'t, 'a, 'r>(input: Tokens<'t, 'a>, errors: Recovered<'r, 'a>)
+    -> IResult<'t, 'a, (Option<&'a str>, Vec<Spanned<Element<'a>>>)>
+{
+    pair(
+        preceded(block_start("elements"), opt(quoted)),
+        delimited(symbol("{"), |input| element_items(input, errors), symbol("}"))
+    )(input)
+}
+
+pub fn dtd(input: &str) -> Result<Dtd<'_>, ::nom::Err<Error<'_>>> {
+    recovering_dtd(input, &State::new(false), None)
+}
+
+// The error parsing `input` fails with, if it would fail the same way whatever came after it.
+// Every parser which looks at the `End` token and doesn't accept it fails there, and errors note
+// whether one did on the way to them, so if none did, nothing about the end of the input made a
+// difference.
+pub fn prefix_error<'a>(input: &'a str, state: &State) -> Option<Error<'a>> {
+    match recovering_dtd(input, state, None) {
+        Err(::nom::Err::Error(err)) | Err(::nom::Err::Failure(err)) if !err.at_end => Some(err),
+        _ => None,
+    }
+}
+
+// Once the keywords opening a block have been seen, errors inside the block are reported rather
+// than causing the block to be skipped. Errors in definitions are recovered from if `errors` is
+// given, but errors outside of them can't be.
+pub fn recovering_dtd<'a, 'r>(input: &'a str, state: &State, errors: Recovered<'r, 'a>)
+    -> Result<Dtd<'a>, ::nom::Err<Error<'a>>>
+{
+    let tokens = lexer::tokenize(input, state);
+    let (_, dtd) = terminated(|input| blocks(input, errors), end)(Tokens::new(&tokens, state))?;
+    Ok(dtd.locate_spans(input.len()))
+}
+
+// An `include "path";` statement, giving the path.
+fn include<'t, 'a>(input: Tokens<'t, 'a>) -> IResult<'t, 'a, Spanned<&'a str>> {
+    context(Context::Include, |input| spanned(input, |input| {
+        let (input, _) = symbol("include")(input)?;
+        let (input, path) = choice(input, quoted)?;
+        let (input, _) = symbol(";")(input)?;
+        Ok((input, path))
+    }))(input)
+}
+
+// The `include` statements at the start of an EDTD, with their spans located. Parsing stops at
+// the first thing which isn't one, which is left to the parser of the blocks.
+pub fn includes(input: &str) -> Result<Vec<Spanned<&str>>, ::nom::Err<Error<'_>>> {
+    let len = input.len();
+    let state = State::new(false);
+    let tokens = lexer::tokenize(input, &state);
+    let mut rest = Tokens::new(&tokens, &state);
+    let mut includes = Vec::new();
+    while next_is(rest, "include") {
+        let (after, statement) = include(rest)?;
+        rest = after;
+        let span = Span { start: len - statement.span.start, end: len - statement.span.end };
+        includes.push(Spanned::new(statement.node, span));
+    }
+    Ok(includes)
+}
+
+// The blocks of an EDTD, leaving spans for the caller to locate. Includes are only read from
+// files, which blank them out before parsing what's left, so any found here are an error.
+fn blocks<'t, 'a, 'r>(input: Tokens<'t, 'a>, errors: Recovered<'r, 'a>)
+    -> IResult<'t, 'a, Dtd<'a>>
+{
+    if next_is(input, "include") {
+        include(input)?;
+        let position = position(input);
+        return Err(::nom::Err::Failure(Error {
+            failures: vec![
+                (position, Kind::Parser(ErrorKind::Alt)),
+                (position, Kind::Context(Context::IncludeOutsideFile)),
+            ],
+            at_end: false,
+        }));
+    }
+    // Blocks may come in any order, and there may be several of each kind. Their definitions
+    // are joined, and types and elements resolved against each other, once all have been read.
+    let mut input = input;
+    let (mut header, mut types, mut blocks) = (Vec::new(), Vec::new(), Vec::new());
+    loop {
+        if lookahead(input, block_start("header"))? {
+            let (rest, statements) = recovering_hblock(input, errors)?;
+            input = rest;
+            header.extend(statements);
+        } else if lookahead(input, block_start("types"))? {
+            let (rest, new_types) = recovering_tblock(input, errors)?;
+            input = rest;
+            types.extend(new_types);
+        } else if lookahead(input, block_start("elements"))? {
+            let (rest, block) = recovering_eblock(input, errors)?;
+            input = rest;
+            blocks.push(block);
+        } else {
+            break;
+        }
+    }
+    Ok((input, Dtd::from_blocks(header, types, blocks)))
+}
+
+fn removed_element<'t, 'a>(input: Tokens<'t, 'a>) -> IResult<'t, 'a, &'a str> {
+    terminated(name, symbol(";"))(input)
+}
+
+fn rblock<'t, 'a>(input: Tokens<'t, 'a>) -> IResult<'t, 'a, Vec<Spanned<&'a str>>> {
+    delimited(
+        tuple((symbol("remove"), symbol("elements"), symbol("{"))),
+        |input| block_items(input, removed_element, None),
+        symbol("}")
+    )(input)
+}
+
+// An overlay is the blocks of an EDTD, followed by a block naming the elements to remove.
+pub fn overlay<'a>(input: &'a str)
+    -> Result<(Dtd<'a>, Vec<Spanned<&'a str>>), ::nom::Err<Error<'a>>>
+{
+    let len = input.len();
+    let locate = |name: Spanned<&'a str>| {
+        Spanned::new(name.node, Span { start: len - name.span.start, end: len - name.span.end })
+    };
+    let state = State::new(false);
+    let tokens = lexer::tokenize(input, &state);
+    let (rest, dtd) = blocks(Tokens::new(&tokens, &state), None)?;
+    let has_removed = next_is(rest, "remove");
+    let (_, removed) = terminated(cond(has_removed, rblock), end)(rest)?;
+    Ok((dtd.locate_spans(len), removed.into_iter().flatten().map(locate).collect()))
+}
 
 #[cfg(test)]
 mod tests;
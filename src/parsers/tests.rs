@@ -1,76 +1,122 @@
 use super::*;
+use {BinaryRange, BinaryRangeItem, DateRange, FloatRange, IntRange, StringRange, StringRangeItem,
+     UintRange};
 
 // TODO instead of taking the whole file name, just assume it's got the same name as the function
 // being tested and append a number (passed in place of the name)
 macro_rules! gen_test {
     ($fn_name:ident, $test_file:expr, $expected:expr) => (
-        match ::parsers::$fn_name(include_bytes!(concat!("../../tests/", $test_file))) {
-            ::nom::IResult::Done(_, val) => assert_eq!($expected, val),
-            ::nom::IResult::Error(err) => {
+        match parse(::parsers::$fn_name, include_str!(concat!("../../tests/", $test_file))) {
+            Ok((_, val)) => assert_eq!($expected, val),
+            Err(err) => {
                 println!("Error: {:?}", err);
                 assert!(false);
             },
-            ::nom::IResult::Incomplete(amount) => {
-                println!("Incomplete: {:?}", amount);
-                assert!(false);
-            },
         }
     );
     ($fn_name:ident, $test_file:expr, $expected:expr, $left:expr) => (
-        match ::parsers::$fn_name(include_bytes!(concat!("../../tests/", $test_file))) {
-            ::nom::IResult::Done(left, val) => {
+        match parse(::parsers::$fn_name, include_str!(concat!("../../tests/", $test_file))) {
+            Ok((left, val)) => {
                 assert_eq!($expected, val);
                 assert_eq!($left, left);
             },
-            ::nom::IResult::Error(err) => {
+            Err(err) => {
                 println!("Error: {:?}", err);
                 assert!(false);
             },
-            ::nom::IResult::Incomplete(amount) => {
-                println!("Incomplete: {:?}", amount);
-                assert!(false);
-            },
         }
     );
     (fail $fn_name:ident, $test_file:expr) => (
-        match ::parsers::$fn_name(include_bytes!(concat!("../../tests/", $test_file))) {
-            ::nom::IResult::Done(_, result) => {
+        match parse(::parsers::$fn_name, include_str!(concat!("../../tests/", $test_file))) {
+            Ok((_, result)) => {
                 println!("Unexpected success: {:?}", result);
                 assert!(false);
             }
-            ::nom::IResult::Incomplete(_) => assert!(false),
-            ::nom::IResult::Error(_) => {},
+            Err(_) => {},
         }
     );
 }
 
-#[test]
-fn test_lcomment() {
-    gen_test!(lcomment, "lcomment", " comment");
+// Runs `parser` over the tokens of `input`, returning what it left as the input from the end of the
+// last token it parsed.
+fn parse<'a, O, F>(parser: F, input: &'a str) -> Result<(&'a str, O), ::nom::Err<Error<'a>>>
+    where F: for<'t> Fn(Tokens<'t, 'a>) -> IResult<'t, 'a, O>
+{
+    parse_in(&State::new(false), parser, input)
+}
+
+// Like parse, in `state`.
+fn parse_in<'a, O, F>(state: &State, parser: F, input: &'a str)
+    -> Result<(&'a str, O), ::nom::Err<Error<'a>>>
+    where F: for<'t> Fn(Tokens<'t, 'a>) -> IResult<'t, 'a, O>
+{
+    let tokens = lexer::tokenize(input, state);
+    parser(Tokens::new(&tokens, state)).map(|(rest, o)| (rest[0].trivia, o))
+}
+
+// Parses `input` leniently, returning what the parser made of it and the quirks found.
+fn leniently<'a, O, F>(parser: F, input: &'a str)
+    -> (Result<(&'a str, O), ::nom::Err<Error<'a>>>, Vec<(::ErrorCode, Span)>)
+    where F: for<'t> Fn(Tokens<'t, 'a>) -> IResult<'t, 'a, O>
+{
+    let state = State::new(true);
+    let result = parse_in(&state, parser, input);
+    (result, state.into_quirks(input.len()))
+}
+
+// The tokens of `input`, lexed strictly.
+fn tokenize(input: &str) -> Vec<lexer::Token<'_>> {
+    lexer::tokenize(input, &State::new(false))
+}
+
+// Spans are left out of comparisons, so expected nodes can be given any.
+fn unlocated<T>(node: T) -> Spanned<T> {
+    Spanned::new(node, Span { start: 0, end: 0 })
 }
 
 #[test]
-fn test_bcomment() {
-    gen_test!(bcomment, "bcomment", " comment ");
+fn test_comments() {
+    // Comments are kept with the token after them, without their delimiters.
+    let tokens = tokenize(include_str!("../../tests/lcomment"));
+    assert_eq!(tokens[0].comments, vec![" comment"]);
+    let tokens = tokenize(include_str!("../../tests/bcomment"));
+    assert_eq!(tokens[0].comments, vec![" comment "]);
 }
 
 #[test]
-fn test_comment() {
-    gen_test!(comment, "lcomment", " comment");
-    gen_test!(comment, "bcomment", " comment ");
+fn test_trivia() {
+    let tokens = tokenize(include_str!("../../tests/separator0"));
+    assert_eq!(tokens[0].rest, "test\n");
+    let tokens = tokenize(include_str!("../../tests/separator1"));
+    assert_eq!(tokens[0].rest, "t\n");
+    assert_eq!(tokens[0].comments, vec!["\n\n//\n\n"]);
 }
 
 #[test]
-fn test_separator() {
-    gen_test!(sep, "separator0", (), b"test\n");
-    gen_test!(sep, "separator1", (), b"t\n");
+fn test_tokens() {
+    use self::TokenKind::*;
+
+    let input = "A := 1a45dfa3 [ range:-1.5<..<=2e+3, 20010101T00:00:00.5..; def:\"x\" 0xff; ]";
+    let tokens: Vec<_> = tokenize(input).into_iter()
+        .map(|token| (token.kind, token.text))
+        .collect();
+    assert_eq!(tokens, vec![
+        (Name, "A"), (Punct, ":="), (Word, "1a45dfa3"), (Punct, "["), (Name, "range"),
+        (Punct, ":"), (Number, "-1.5"), (Punct, "<"), (Punct, ".."), (Punct, "<"), (Punct, "="),
+        (Number, "2e+3"), (Punct, ","), (Date, "20010101T00:00:00.5"), (Punct, ".."),
+        (Punct, ";"), (Name, "def"), (Punct, ":"), (Str, "\"x\""), (Hex, "0xff"), (Punct, ";"),
+        (Punct, "]"), (End, ""),
+    ]);
+    // Whatever can't start a token is one by itself, like an unclosed string or comment.
+    let tokens: Vec<_> = tokenize("\"x /* y").into_iter().map(|token| token.text).collect();
+    assert_eq!(tokens, vec!["\"", "x", "/", "*", "y", ""]);
 }
 
 #[test]
 fn test_name() {
     gen_test!(name, "name0", "SimpleName");
     gen_test!(name, "name1", "_complexName1");
-    gen_test!(name, "name2", "___name___", b" foo\n");
+    gen_test!(name, "name2", "___name___", " foo\n");
     gen_test!(fail name, "name3");
     gen_test!(fail name, "name4");
 }
@@ -101,7 +147,7 @@ fn test_type() {
 fn test_parents() {
     gen_test!(parents, "parents0", vec!["name1"]);
     gen_test!(parents, "parents1", vec!["name1", "name2", "name3", "name4"]);
-    gen_test!(parents, "parents2", vec!["name1"], b", 2notaname\n");
+    gen_test!(parents, "parents2", vec!["name1"], ", 2notaname\n");
 }
 
 #[test]
@@ -173,27 +219,30 @@ fn test_float_def() {
 
 #[test]
 fn test_date_def() {
-    gen_test!(date_def, "date0", Property::DateDefault(NaiveDateTime::new(
-        NaiveDate::from_ymd(2017, 1, 1),
-        NaiveTime::from_hms(0, 0, 0)
-    )));
-    gen_test!(date_def, "date1", Property::DateDefault(NaiveDateTime::new(
-        NaiveDate::from_ymd(1234, 12, 25),
-        NaiveTime::from_hms_milli(14, 15, 32, 420)
-    )));
+    gen_test!(date_def, "date0", Property::DateDefault(date::from_parts(2017, 1, 1, 0, 0, 0, 0)));
+    // Without chrono, dates are nanoseconds since 2001 in an `i64`, which can't reach 1234.
+    #[cfg(feature = "chrono")]
+    gen_test!(date_def, "date1", Property::DateDefault(
+        date::from_parts(1234, 12, 25, 14, 15, 32, 420_000_000)
+    ));
+    #[cfg(not(feature = "chrono"))]
+    gen_test!(fail date_def, "date1");
     gen_test!(fail date_def, "date2");
     gen_test!(fail date_def, "date3");
-    gen_test!(date_def, "date4", Property::DateDefault(NaiveDateTime::new(
-        NaiveDate::from_ymd(2001, 1, 1),
-        NaiveTime::from_hms_nano(0, 0, 0, 1234)
-    )));
+    gen_test!(date_def, "date4", Property::DateDefault(date::from_nanos(1234)));
+    // A time a digit short used to panic, and a fraction a float rounds up to a whole second was
+    // read as one.
+    gen_test!(fail date_def, "date5");
+    gen_test!(date_def, "date6", Property::DateDefault(
+        date::from_parts(2017, 1, 1, 0, 0, 0, 999_999_999)
+    ));
 }
 
 #[test]
 fn test_string_def() {
-    gen_test!(string_def, "string0", Property::StringDefault("hello".to_string()));
-    gen_test!(string_def, "string1", Property::StringDefault("Test".to_string()));
-    gen_test!(string_def, "string2", Property::StringDefault("Test\x04".to_string()));
+    gen_test!(string_def, "string0", Property::StringDefault("hello".into()));
+    gen_test!(string_def, "string1", Property::StringDefault("Test".into()));
+    gen_test!(string_def, "string2", Property::StringDefault("Test\x04".into()));
     // invalid unicode
     gen_test!(fail string_def, "string3");
     // unclosed quote
@@ -202,19 +251,41 @@ fn test_string_def() {
 
 #[test]
 fn test_binary_def() {
-    gen_test!(binary_def, "string0", Property::BinaryDefault(vec![0x68, 0x65, 0x6c, 0x6c, 0x6f]));
-    gen_test!(binary_def, "string1", Property::BinaryDefault(vec![0x54, 0x65, 0x73, 0x74]));
-    gen_test!(binary_def, "string2", Property::BinaryDefault(vec![0x54, 0x65, 0x73, 0x74, 0x04]));
+    gen_test!(binary_def, "string0", Property::BinaryDefault(b"hello"[..].into()));
+    gen_test!(binary_def, "string1", Property::BinaryDefault(b"Test"[..].into()));
+    gen_test!(binary_def, "string2", Property::BinaryDefault(b"Test\x04"[..].into()));
 
     // invalid unicode/ascii is fine for a binary default
     gen_test!(binary_def, "string3", Property::BinaryDefault(
-        vec![0x54, 0x65, 0x73, 0x74, 0x80, 0x81, 0x82])
+        vec![0x54, 0x65, 0x73, 0x74, 0x80, 0x81, 0x82].into())
     );
 
     // unclosed quote
     gen_test!(fail binary_def, "string4");
 }
 
+#[test]
+fn test_borrowed_literals() {
+    // Quoted defaults are borrowed from the input, while hex ones have to be decoded.
+    let input = "def:\"Test\";";
+    match parse(binary_def, input) {
+        Ok((_, Property::BinaryDefault(Cow::Borrowed(value)))) => {
+            assert_eq!(value.as_ptr(), input[5..].as_ptr());
+        }
+        other => panic!("{:?}", other),
+    }
+    match parse(string_def, input) {
+        Ok((_, Property::StringDefault(Cow::Borrowed(value)))) => {
+            assert_eq!(value.as_ptr(), input[5..].as_ptr());
+        }
+        other => panic!("{:?}", other),
+    }
+    match parse(string_def, "def:0x54657374;") {
+        Ok((_, Property::StringDefault(Cow::Owned(value)))) => assert_eq!(value, "Test"),
+        other => panic!("{:?}", other),
+    }
+}
+
 #[test]
 fn test_int_range() {
     gen_test!(int_range, "int_range0", Property::IntRange(vec![
@@ -308,36 +379,21 @@ fn test_float_range() {
 fn test_date_range() {
     gen_test!(date_range, "date_range0", Property::DateRange(vec![
         DateRangeItem::From {
-            start: NaiveDateTime::new(
-                NaiveDate::from_ymd(1902, 01, 02),
-                NaiveTime::from_hms(0, 0, 24)
-            ),
+            start: date::from_parts(1902, 1, 2, 0, 0, 24, 0),
         },
     ]));
     gen_test!(date_range, "date_range1", Property::DateRange(vec![
         DateRangeItem::To {
-            end: NaiveDateTime::new(
-                NaiveDate::from_ymd(1995, 04, 18),
-                NaiveTime::from_hms_milli(4, 20, 0, 420)
-            ),
+            end: date::from_parts(1995, 4, 18, 4, 20, 0, 420_000_000),
         },
     ]));
     gen_test!(date_range, "date_range2", Property::DateRange(vec![
         DateRangeItem::Bounded {
-            start: NaiveDateTime::new(
-                NaiveDate::from_ymd(2001, 1, 1),
-                NaiveTime::from_hms_nano(0, 0, 0, 1234)
-            ),
-            end: NaiveDateTime::new(
-                NaiveDate::from_ymd(2017, 1, 1),
-                NaiveTime::from_hms_milli(19, 20, 45, 245)
-            ),
+            start: date::from_parts(2001, 1, 1, 0, 0, 0, 1_234),
+            end: date::from_parts(2017, 1, 1, 19, 20, 45, 245_000_000),
         },
         DateRangeItem::From {
-            start: NaiveDateTime::new(
-                NaiveDate::from_ymd(2020, 01, 01),
-                NaiveTime::from_hms(0, 0, 0)
-            ),
+            start: date::from_parts(2020, 1, 1, 0, 0, 0, 0),
         },
     ]));
     gen_test!(fail date_range, "date_range3");
@@ -376,22 +432,26 @@ fn test_binary_range() {
 
 #[test]
 fn test_size() {
-    gen_test!(size, "size_range0", Property::Size(
-        vec![UintRangeItem::Bounded { start: 2, end: 5 }])
-    );
-    gen_test!(size, "size_range1", Property::Size(vec![UintRangeItem::From { start: 4 }]));
-    gen_test!(size, "size_range2", Property::Size(vec![UintRangeItem::Single(45)]));
-    gen_test!(size, "size_range3", Property::Size(vec![
+    gen_test!(size, "size_range0", Property::Size(SizeList::from(vec![
+        UintRangeItem::Bounded { start: 2, end: 5 },
+    ])));
+    gen_test!(size, "size_range1", Property::Size(SizeList::from(vec![
+        UintRangeItem::From { start: 4 },
+    ])));
+    gen_test!(size, "size_range2", Property::Size(SizeList::from(vec![
+        UintRangeItem::Single(45),
+    ])));
+    gen_test!(size, "size_range3", Property::Size(SizeList::from(vec![
         UintRangeItem::Bounded { start: 1, end: 4 },
         UintRangeItem::Single(5),
         UintRangeItem::From { start: 66 },
-    ]));
-    gen_test!(size, "size_range4", Property::Size(vec![
+    ])));
+    gen_test!(size, "size_range4", Property::Size(SizeList::from(vec![
         UintRangeItem::Bounded { start: 100, end: 200 },
         UintRangeItem::Single(44),
         UintRangeItem::Single(55),
         UintRangeItem::Bounded { start: 66, end: 70 },
-    ]));
+    ])));
     gen_test!(fail size, "size_range5");
 }
 
@@ -403,6 +463,31 @@ fn test_ordered() {
     gen_test!(ordered, "ordered3", Property::Ordered(false));
 }
 
+#[test]
+fn test_unknown_size_allowed() {
+    gen_test!(unknown_size_allowed, "unknownsizeallowed0", Property::UnknownSizeAllowed(true));
+    gen_test!(unknown_size_allowed, "unknownsizeallowed1", Property::UnknownSizeAllowed(false));
+}
+
+#[test]
+fn test_variants() {
+    gen_test!(variants, "variants0", Property::Variants(vec![
+        ("Video", 1),
+        ("Audio", 2),
+        ("Complex", 3),
+    ]));
+    gen_test!(fail variants, "variants1");
+    gen_test!(fail variants, "variants2");
+}
+
+#[test]
+fn test_versions() {
+    gen_test!(minver, "version0", Property::MinVer(2));
+    gen_test!(maxver, "version1", Property::MaxVer(4));
+    gen_test!(fail minver, "version2");
+    gen_test!(fail maxver, "version3");
+}
+
 #[test]
 fn test_header_statement() {
     gen_test!(header_statement, "header_statement0", HeaderStatement::Uint {
@@ -419,47 +504,77 @@ fn test_header_statement() {
     });
     gen_test!(header_statement, "header_statement3", HeaderStatement::Date {
         name: "FooBar",
-        value: NaiveDateTime::new(
-            NaiveDate::from_ymd(2014, 2, 3),
-            NaiveTime::from_hms_milli(0, 12, 14, 500)
-        ),
+        value: date::from_parts(2014, 2, 3, 0, 12, 14, 500_000_000),
     });
     gen_test!(header_statement, "header_statement4", HeaderStatement::String {
         name: "FooBar",
-        value: "any unicode string 隣町".to_string(),
+        value: "any unicode string 隣町".into(),
     });
     gen_test!(header_statement, "header_statement5", HeaderStatement::Binary {
         name: "FooBar",
-        value: vec![0xFA, 0xDE, 0xF0, 0x0D],
+        value: vec![0xFA, 0xDE, 0xF0, 0x0D].into(),
     });
 }
 
+#[test]
+fn test_header_statement_values() {
+    let value = |text: &'static str| match parse(header_statement, text) {
+        Ok((_, statement)) => statement,
+        other => panic!("{}: {:?}", text, other),
+    };
+
+    // Positive integers are always unsigned, and a sign or exponent makes a float.
+    assert_eq!(value("A := 20010101;"), HeaderStatement::Uint { name: "A", value: 20010101 });
+    assert_eq!(value("A := -1;"), HeaderStatement::Int { name: "A", value: -1 });
+    assert_eq!(value("A := +1;"), HeaderStatement::Float { name: "A", value: 1.0 });
+    assert_eq!(value("A := 1e3;"), HeaderStatement::Float { name: "A", value: 1000.0 });
+    // Digits with a `T` are a date, not an integer followed by garbage.
+    assert_eq!(value("A := 20010101T00:00:01;"), HeaderStatement::Date {
+        name: "A",
+        value: date::from_nanos(1_000_000_000),
+    });
+    // Hex which is UTF-8 is a string, like quoted text.
+    assert_eq!(value("A := 0x41;"), HeaderStatement::String { name: "A", value: "A".into() });
+    let binary = HeaderStatement::Binary { name: "A", value: vec![0xFF].into() };
+    assert_eq!(value("A := 0xFF;"), binary);
+    // Anything starting like a name is one, even if it could be read as a float.
+    assert_eq!(value("A := e5;"), HeaderStatement::Named { name: "A", value: "e5" });
+
+    // Once the kind of value is known, nothing else is tried: integers too big for their type are
+    // errors rather than floats. Errors are where the value starts.
+    for &text in &["A := 18446744073709551616;", "A := 1x;", "A := ;"] {
+        match parse(header_statement, text) {
+            Err(::nom::Err::Error(err)) => {
+                assert_eq!(err.failures[0], (&text[5..], Kind::Parser(ErrorKind::Alt)));
+            }
+            other => panic!("{}: {:?}", text, other),
+        }
+    }
+}
+
 #[test]
 fn test_hblock() {
     gen_test!(hblock, "hblock0", vec![
-        HeaderStatement::Uint {
+        unlocated(HeaderStatement::Uint {
             name: "FooBar",
             value: 1,
-        },
-        HeaderStatement::String {
+        }),
+        unlocated(HeaderStatement::String {
             name: "Foo1",
-            value: "test".to_string(),
-        },
-        HeaderStatement::Binary {
+            value: "test".into(),
+        }),
+        unlocated(HeaderStatement::Binary {
             name: "FooBaz",
-            value: vec![0xFA, 0xDE, 0xF0, 0x0D],
-        },
-        HeaderStatement::Date {
+            value: vec![0xFA, 0xDE, 0xF0, 0x0D].into(),
+        }),
+        unlocated(HeaderStatement::Date {
             name: "FooQux",
-            value: NaiveDateTime::new(
-                NaiveDate::from_ymd(2000, 1, 1),
-                NaiveTime::from_hms(0, 0, 0)
-            ),
-        },
-        HeaderStatement::String {
+            value: date::from_parts(2000, 1, 1, 0, 0, 0, 0),
+        }),
+        unlocated(HeaderStatement::String {
             name: "Foo",
-            value: "隣町".to_string(),
-        },
+            value: "隣町".into(),
+        }),
     ]);
 }
 
@@ -469,6 +584,7 @@ fn test_dtype() {
         name: "Foo",
         default: None,
         range: None,
+        comments: vec![],
     });
     gen_test!(dtype, "dtype1", NewType::Int {
         name: "bar123",
@@ -477,21 +593,25 @@ fn test_dtype() {
             start: -25,
             end: 100,
         }]),
+        comments: vec![],
     });
     gen_test!(dtype, "dtype2", NewType::Uint {
         name: "Foo",
         default: None,
         range: None,
+        comments: vec![],
     });
     gen_test!(dtype, "dtype3", NewType::Uint {
         name: "Foo",
         default: Some(666),
         range: None,
+        comments: vec![],
     });
     gen_test!(dtype, "dtype4", NewType::Float {
         name: "Foo",
         default: None,
         range: None,
+        comments: vec![],
     });
     gen_test!(dtype, "dtype5", NewType::Float {
         name: "Foo",
@@ -512,28 +632,29 @@ fn test_dtype() {
                 include_end: false,
             },
         ]),
+        comments: vec![],
     });
     gen_test!(dtype, "dtype6", NewType::Date {
         name: "abcdefghijklmnopqrstuvwxyz1234567890",
         default: None,
         range: None,
+        comments: vec![],
     });
     gen_test!(dtype, "dtype7", NewType::Date {
         name: "Foo",
         default: None,
         range: Some(vec![
             DateRangeItem::From {
-                start: NaiveDateTime::new(
-                    NaiveDate::from_ymd(1776, 6, 4),
-                    NaiveTime::from_hms_milli(9, 21, 55, 356)
-                ),
+                start: date::from_parts(1776, 6, 4, 9, 21, 55, 356_000_000),
             },
         ]),
+        comments: vec![],
     });
     gen_test!(dtype, "dtype8", NewType::String {
         name: "foo",
         default: None,
         range: None,
+        comments: vec![],
     });
     gen_test!(dtype, "dtype9", NewType::String {
         name: "FooA",
@@ -548,13 +669,494 @@ fn test_dtype() {
                 end: 127,
             },
         ]),
+        comments: vec![],
     });
     gen_test!(dtype, "dtype10", NewType::Binary {
         name: "foo",
         default: None,
         range: None,
+        comments: vec![],
     });
 
     // TODO fail test for every type with empty params list (ie [])
     // TODO fail test for every type with param list that doesn't parse
 }
+
+#[test]
+fn test_tblock() {
+    gen_test!(tblock, "tblock0", vec![
+        unlocated(NewType::Uint {
+            name: "bool",
+            default: None,
+            range: Some(vec![UintRangeItem::Bounded { start: 0, end: 1 }]),
+            comments: vec![],
+        }),
+        unlocated(NewType::String {
+            name: "ascii",
+            default: None,
+            range: Some(vec![StringRangeItem::Bounded { start: 32, end: 126 }]),
+            comments: vec![],
+        }),
+        unlocated(NewType::Date {
+            name: "timestamp",
+            default: None,
+            range: None,
+            comments: vec![" comments are fine here too"],
+        }),
+    ]);
+
+    // Comparisons leave out comments, but types keep the ones before them.
+    let (_, types) = parse(tblock, include_str!("../../tests/tblock0")).unwrap();
+    let comments: Vec<_> = types.iter().map(|new_type| new_type.node.comments()).collect();
+    assert_eq!(comments, [&[][..], &[][..], &[" comments are fine here too"][..]]);
+}
+
+#[test]
+fn test_element() {
+    gen_test!(element, "element0", Element {
+        default: Some(unlocated(Property::UintDefault(1))),
+        ..Element::new("EBMLVersion", 0x4286, Type::Uint)
+    });
+    gen_test!(element, "element1", Element {
+        level: Some(Level::Open { start: 1 }),
+        cardinality: Some(Cardinality::ZeroOrMany),
+        ..Element::new("Void", 0xEC, Type::Binary)
+    });
+    gen_test!(element, "element2", Element {
+        cardinality: Some(Cardinality::OneOrMany),
+        children: vec![
+            unlocated(Element {
+                default: Some(unlocated(Property::UintDefault(1))),
+                ..Element::new("EBMLVersion", 0x4286, Type::Uint)
+            }),
+            unlocated(Element {
+                range: Some(unlocated(Property::StringRange(vec![
+                    StringRangeItem::Bounded { start: 32, end: 126 },
+                ]))),
+                ..Element::new("DocType", 0x4282, Type::String)
+            }),
+        ],
+        ..Element::new("EBML", 0x1A45_DFA3, Type::Container)
+    });
+    // Properties of elements with user-defined types are resolved later.
+    gen_test!(element, "element3", Element {
+        default: Some(unlocated(Property::UintDefault(1))),
+        cardinality: Some(Cardinality::ExactlyOne),
+        ..Element::new("FlagDefault", 0x88, Type::Name("bool"))
+    });
+    gen_test!(fail element, "element4");
+}
+
+#[test]
+fn test_eblock() {
+    gen_test!(eblock, "eblock0", (None, vec![
+        unlocated(Element {
+            cardinality: Some(Cardinality::OneOrMany),
+            children: vec![
+                unlocated(Element {
+                    default: Some(unlocated(Property::UintDefault(1))),
+                    ..Element::new("EBMLVersion", 0x4286, Type::Uint)
+                }),
+            ],
+            ..Element::new("EBML", 0x1A45_DFA3, Type::Container)
+        }),
+        unlocated(Element {
+            level: Some(Level::Open { start: 1 }),
+            cardinality: Some(Cardinality::ZeroOrMany),
+            ..Element::new("Void", 0xEC, Type::Binary)
+        }),
+    ]));
+    let (_, (label, elements)) = parse(eblock, "define elements \"webm\" { Void := ec binary; }")
+        .unwrap();
+    assert_eq!(label, Some("webm"));
+    assert_eq!(elements, vec![unlocated(Element::new("Void", 0xEC, Type::Binary))]);
+}
+
+#[test]
+fn test_dtd() {
+    match dtd(include_str!("../../tests/dtd0")) {
+        Ok(dtd) => {
+            assert_eq!(dtd.header.len(), 2);
+            assert_eq!(dtd.types.len(), 1);
+            assert_eq!(dtd.elements.len(), 3);
+            assert_eq!(dtd.all_elements().len(), 8);
+
+            // The default of an element with a user-defined type takes on the base type.
+            let flag_lacing = dtd.all_elements()[6];
+            assert_eq!(flag_lacing.name, "FlagLacing");
+            assert_eq!(flag_lacing.default.as_deref(), Some(&Property::UintDefault(1)));
+        }
+        other => panic!("{:?}", other),
+    }
+    assert!(dtd(include_str!("../../tests/dtd2")).is_err());
+}
+
+#[test]
+fn test_block_order() {
+    // Defaults are resolved against types defined after the elements using them.
+    let reordered = dtd(include_str!("../../tests/dtd_order0")).unwrap();
+    assert_eq!(reordered, dtd(include_str!("../../tests/dtd0")).unwrap());
+    let flag_lacing = reordered.all_elements()[6];
+    assert_eq!(flag_lacing.default.as_deref(), Some(&Property::UintDefault(1)));
+}
+
+#[test]
+fn test_doc_types() {
+    let both = dtd(include_str!("../../tests/doc_types0")).unwrap();
+    assert_eq!(both.doc_types(), vec!["matroska", "webm"]);
+    // The unlabeled block and the one labeled with the header's DocType are joined.
+    let names: Vec<_> = both.all_elements().iter().map(|element| element.name).collect();
+    assert_eq!(names, vec!["EBML", "DocType", "Segment", "Title", "FlagLacing"]);
+    assert_eq!(both.select_doc_type("matroska").unwrap().elements, both.elements);
+    assert!(both.select_doc_type("mkv").is_none());
+
+    let webm = both.select_doc_type("webm").unwrap();
+    assert_eq!(webm.doc_types(), vec!["webm"]);
+    let names: Vec<_> = webm.all_elements().iter().map(|element| element.name).collect();
+    assert_eq!(names, vec!["EBML", "DocType", "Segment", "Title"]);
+
+    // Sharing names between DocTypes isn't a duplicate, but sharing them within one still is.
+    assert!(!both.validate().has_errors());
+    let input = "define elements \"a\" { A := 81 uint; } define elements \"a\" { A := 82 uint; }";
+    let codes: Vec<_> = dtd(input).unwrap().validate().iter().map(|d| d.code()).collect();
+    assert_eq!(codes, vec![ErrorCode::DuplicateElementName]);
+}
+
+#[test]
+fn test_spans() {
+    let source = include_str!("../../tests/dtd0");
+    let text = |span: Span| &source[span.start..span.end];
+    let dtd = match dtd(source) {
+        Ok(dtd) => dtd,
+        other => panic!("{:?}", other),
+    };
+
+    assert_eq!(text(dtd.header[1].span), "EBMLVersion := 1;");
+    assert_eq!(text(dtd.types[0].span), "bool := uint [ range:0..1; ]");
+    let ebml = &dtd.elements[0];
+    assert_eq!(text(ebml.children[0].span), "EBMLVersion := 4286 uint [ def:1; ]");
+    assert_eq!(text(ebml.children[1].range.as_ref().unwrap().span), "range:32..126;");
+    assert!(text(ebml.span).starts_with("EBML := 1a45dfa3 container [ card:+; ] {\n"));
+    assert!(text(ebml.span).ends_with("range:32..126; ]\n    }"));
+    // Properties resolved against a user-defined type keep theirs.
+    let flag_lacing = &dtd.elements[1].children[0].children[1];
+    assert_eq!(text(flag_lacing.span), "FlagLacing := 9c bool [ def:1; ]");
+    assert_eq!(text(flag_lacing.default.as_ref().unwrap().span), "def:1;");
+}
+
+#[test]
+fn test_syntax_errors() {
+    let error = |text: &[u8]| match ::parse_dtd(text, &Default::default()) {
+        Err(::DtdError::Syntax(err)) => err,
+        other => panic!("{:?}", other),
+    };
+
+    let err = error(include_bytes!("../../tests/syntax_error0"));
+    assert_eq!((err.line, err.column, err.byte_offset), (6, 18, 117));
+    assert_eq!(err.code(), ::ErrorCode::InvalidElementDefinition);
+    assert_eq!(err.to_string(), "error[P0005]: line 6, column 18: in element definition \
+                                 `Segment`, in element definition `Title`: expected a valid \
+                                 value, found `string;`");
+
+    // Errors inside a property are reported inside it, rather than where the property starts.
+    let err = error(include_bytes!("../../tests/syntax_error1"));
+    assert_eq!((err.line, err.column), (4, 40));
+    assert_eq!(err.found, ">>0.0;");
+    assert_eq!(err.expected, vec!["a range item"]);
+    let context: Vec<_> = err.context.iter()
+        .map(|context| (context.construct, context.name.as_deref()))
+        .collect();
+    assert_eq!(context, vec![
+        ("element definition", Some("Info")),
+        ("element definition", Some("Duration")),
+        ("range property", None),
+    ]);
+    assert_eq!(err.context[2].byte_offset, 112);
+
+    let err = error(include_bytes!("../../tests/syntax_error2"));
+    assert_eq!((err.line, err.column), (6, 30));
+    assert_eq!(err.to_string(), "error[P0008]: line 6, column 30: in element definition `Void`, \
+                                 in cardinality property: expected `*`, `?`, `1` or `+`, found \
+                                 `many;`");
+
+    // Columns count characters, not bytes.
+    let err = error(include_bytes!("../../tests/syntax_error3"));
+    assert_eq!((err.line, err.column, err.byte_offset), (3, 44, 96));
+    assert_eq!(err.found, "bogus;");
+    let err = error(include_bytes!("../../tests/syntax_error6"));
+    assert_eq!((err.line, err.column, err.byte_offset), (5, 55, 133));
+    assert_eq!(err.found, "maybe;");
+
+    let err = error(b"declare header { DocType := ; }");
+    assert_eq!(err.to_string(),
+               "error[P0003]: line 1, column 29: in header statement `DocType`: expected a value, \
+                found `;`");
+}
+
+
+#[test]
+fn test_invalid_utf8() {
+    let text = b"define elements {\n    Title := 7ba9 string [ def:\"caf\xe9\"; ]\n}\n";
+    let err = match ::parse_dtd(&text[..], &Default::default()) {
+        Err(::DtdError::Syntax(err)) => err,
+        other => panic!("{:?}", other),
+    };
+    assert_eq!(err.to_string(), "error[P0015]: line 2, column 36: expected UTF-8 text, found \
+                                 `\u{fffd}\";`");
+    assert_eq!(err.byte_offset, 53);
+    assert_eq!(::parse_dtd_recovering(&text[..]).1, vec![err.clone()]);
+    let (dtd, diagnostics) = ::check_dtd(&text[..], &Default::default());
+    assert!(dtd.is_none());
+    assert_eq!(diagnostics[0].code(), ::ErrorCode::InvalidUtf8);
+    assert!(::parse_overlay(&text[..]).is_err());
+
+    // Text which is UTF-8 may be given either way.
+    let text = "define elements {\n    Title := 7ba9 string [ def:\"caf\u{e9}\"; ]\n}\n";
+    assert_eq!(::parse_dtd(text, &Default::default()).unwrap(),
+               ::parse_dtd(text.as_bytes(), &Default::default()).unwrap());
+}
+
+#[test]
+fn test_truncated_input() {
+    let text = include_bytes!("../../tests/dtd0");
+    let cases: &[(usize, &str)] = &[
+        (2, "line 1, column 3: unexpected end of input"),
+        (54, "line 3, column 15: unexpected end of input while parsing header statement `DocType`"),
+        (135, "line 7, column 30: unexpected end of input while parsing range property"),
+        (200, "line 10, column 42: unexpected end of input while parsing element definition \
+               `EBML`"),
+        (400, "line 15, column 49: unexpected end of input while parsing element definition \
+               `Info`"),
+        (470, "line 17, column 36: unexpected end of input while parsing element definition \
+               `FlagLacing`"),
+        (520, "line 20, column 25: unexpected end of input while parsing element definition \
+               `Void`"),
+    ];
+    for &(end, message) in cases {
+        match ::parse_dtd(&text[..end], &Default::default()) {
+            Err(::DtdError::Syntax(err)) => {
+                assert_eq!(err.to_string(), format!("error[P0001]: {}", message));
+                assert_eq!(err.code(), ::ErrorCode::UnexpectedEnd);
+                assert_eq!(err.byte_offset, end);
+                assert_eq!(err.found, "");
+            }
+            other => panic!("{}: {:?}", end, other),
+        }
+    }
+}
+
+#[test]
+fn test_prefix_error() {
+    let state = State::new(false);
+    // More input could make each of these parse, even where alternatives and lookaheads which got
+    // to the end were given up on.
+    for &input in &[
+        "define",
+        "define header { DocType := ",
+        "define elements { A := 81 uint [ card",
+        "define elements { A := 81 uint [ parent: B,",
+    ] {
+        assert_eq!(prefix_error(input, &state), None, "{}", input);
+    }
+    // Nothing could make this one parse.
+    let err = prefix_error("define elements { A := 81 uint [ card:many;", &state);
+    assert_eq!(err.map(|err| err.failures[0].0), Some("many;"));
+}
+
+#[test]
+fn test_recovery() {
+    let text = include_bytes!("../../tests/recovery0");
+    let (dtd, errors) = ::parse_dtd_recovering(text);
+    let positions: Vec<_> = errors.iter().map(|err| (err.line, err.column)).collect();
+    assert_eq!(positions, vec![(4, 20), (9, 30), (16, 43)]);
+    let codes: Vec<_> = errors.iter().map(::ParseError::code).collect();
+    assert_eq!(codes, vec![
+        ::ErrorCode::InvalidHeaderStatement,
+        ::ErrorCode::InvalidRange,
+        ::ErrorCode::InvalidCardinality,
+    ]);
+    assert_eq!(errors[2].message(), "in element definition `Segment`, in element definition \
+                                     `Info`, in element definition `Duration`, in cardinality \
+                                     property: expected `*`, `?`, `1` or `+`, found `many;`");
+
+    // Everything but the definitions with errors is kept, even strings containing `;` and `}`.
+    let header: Vec<_> = dtd.header.iter().map(|statement| statement.name()).collect();
+    assert_eq!(header, vec!["DocType", "EBMLReadVersion"]);
+    assert_eq!(dtd.types.len(), 2);
+    let elements: Vec<_> = dtd.all_elements().iter().map(|element| element.name).collect();
+    assert_eq!(elements, vec!["Segment", "Info", "Title", "MuxingApp", "Void"]);
+
+    // Strict parsing stops at the first.
+    match ::parse_dtd(text, &Default::default()) {
+        Err(::DtdError::Syntax(err)) => assert_eq!(err, errors[0]),
+        other => panic!("{:?}", other),
+    }
+
+    // Errors outside of definitions can't be recovered from.
+    let (dtd, errors) = ::parse_dtd_recovering(b"define elements {\n    A := 81 uint\n}\n}");
+    assert!(dtd.all_elements().is_empty());
+    assert_eq!(errors.len(), 1);
+    assert_eq!(errors[0].to_string(),
+               "error[P0002]: line 4, column 1: expected the end of the input, found `}`");
+
+    // Nor can definitions whose end is too far away to find.
+    let state = State::new(false);
+    let brackets = "[".repeat(MAX_SKIP + 1);
+    let tokens = tokenize(&brackets);
+    assert!(skip_definition(Tokens::new(&tokens, &state)).is_none());
+    let tokens = tokenize("A := 81 uint [ card:many; ] { B := 82 uint; }\n}");
+    let rest = skip_definition(Tokens::new(&tokens, &state));
+    assert_eq!(rest.map(|rest| rest[0].trivia), Some("\n}"));
+}
+
+// Runs `parser` over `input` leniently, returning what's left and the codes and text of the
+// quirks found.
+fn quirks<'a, T, F>(parser: F, input: &'a str) -> (&'a str, Vec<(::ErrorCode, &'a str)>)
+    where F: for<'t> Fn(Tokens<'t, 'a>) -> IResult<'t, 'a, T>
+{
+    let (result, found) = leniently(parser, input);
+    let found = found.into_iter().map(|(code, span)| (code, &input[span.start..span.end]));
+    match result {
+        Ok((rest, _)) => (rest, found.collect()),
+        other => panic!("{:?}", other.map(|_| ())),
+    }
+}
+
+#[test]
+fn test_quirks() {
+    use ErrorCode::*;
+
+    let input = "%EBMLElements; # Legacy\n Info := 1549a966 container;";
+    assert_eq!(quirks(name, input), (" := 1549a966 container;", vec![
+        (EntityReference, "%EBMLElements;"),
+        (HashComment, "# Legacy\n"),
+    ]));
+    // They aren't kept with the comments.
+    let input = "// Kept\n# Skipped\n%Skipped; x";
+    let tokens = lexer::tokenize(input, &State::new(true));
+    assert_eq!((tokens[0].text, &tokens[0].comments[..]), ("x", &[" Kept"][..]));
+
+    assert_eq!(quirks(cardinality, "card:* ]"), (" ]", vec![(MissingSemicolon, " ")]));
+    assert_eq!(quirks(header_statement, "EBMLVersion := 1\n}").1,
+               vec![(MissingSemicolon, "\n")]);
+    // Only before the end of a list.
+    let input = "card:* def:1; ]";
+    assert!(leniently(cardinality, input).0.is_err());
+
+    for &input in &["def:'0x0102ff';", "def:'0102ff';"] {
+        let (result, found) = leniently(binary_def, input);
+        assert_eq!(result, Ok(("", Property::BinaryDefault(vec![1, 2, 0xff].into()))));
+        assert_eq!(found[0].0, QuotedBinary);
+    }
+    // Only for binary defaults.
+    let input = "def:'0102ff';";
+    assert!(leniently(string_def, input).0.is_err());
+
+    // Strict parsing rejects every one.
+    assert!(parse(name, "%EBMLElements; A").is_err());
+    assert!(parse(name, "# Legacy\n A").is_err());
+    assert!(parse(cardinality, "card:* ]").is_err());
+    assert!(parse(binary_def, "def:'0102ff';").is_err());
+}
+
+#[test]
+fn test_keyword_case() {
+    let text = include_str!("../../tests/mixed_case0");
+    let options = ::ParseOptions {
+        validate: Some(::Severity::Error),
+        lenient: true,
+        ..Default::default()
+    };
+    let (dtd, diagnostics) = ::check_dtd(text, &options);
+    let found: Vec<_> = diagnostics.iter()
+        .map(|d| (d.code(), &text[d.span().unwrap().start..d.span().unwrap().end]))
+        .collect();
+    let keywords = [
+        "Declare", "Header", "DEFINE", "Types", "Uint", "Range", "Define", "Elements", "Container",
+        "Card", "Ordered", "Yes", "String", "Def",
+    ];
+    assert_eq!(found, keywords.iter().map(|&k| (::ErrorCode::KeywordCase, k)).collect::<Vec<_>>());
+    assert!(diagnostics.iter().all(|d| !d.is_error()));
+
+    // It's read as if the keywords were in lowercase, which leaves the spans where they were.
+    let mut lowercase = text.to_string();
+    for keyword in &keywords {
+        lowercase = lowercase.replace(keyword, &keyword.to_lowercase());
+    }
+    assert_eq!(dtd.unwrap(), ::parse_dtd(&lowercase, &Default::default()).unwrap());
+
+    // Names are still case-sensitive.
+    let input = "define types { bool := uint; } define elements { Flag := 9c Bool; }";
+    match ::parse_dtd(input, &options) {
+        Err(::DtdError::Invalid(diagnostics)) => {
+            assert_eq!(diagnostics[0].code(), ::ErrorCode::UnknownType);
+        }
+        other => panic!("{:?}", other),
+    }
+
+    match ::parse_dtd(text, &Default::default()) {
+        Err(::DtdError::Syntax(err)) => assert_eq!((err.line, err.column), (1, 1)),
+        other => panic!("{:?}", other),
+    }
+}
+
+#[test]
+fn test_lenient() {
+    let text = include_bytes!("../../tests/legacy0");
+    let options = ::ParseOptions {
+        validate: Some(::Severity::Error),
+        lenient: true,
+        ..Default::default()
+    };
+    let (dtd, diagnostics) = ::check_dtd(text, &options);
+    assert_eq!(dtd.unwrap().all_elements().len(), 9);
+    let source = ::SourceMap::new(str::from_utf8(text).unwrap());
+    let found: Vec<_> = diagnostics.iter()
+        .map(|d| (d.code().as_str(), source.line_column(d.span().unwrap().start).0))
+        .collect();
+    assert_eq!(found, vec![
+        ("L0004", 1), ("L0004", 2), ("L0002", 5), ("L0001", 11), ("L0003", 21), ("L0002", 23),
+    ]);
+    assert!(diagnostics.iter().all(|d| !d.is_error()));
+
+    // They fail validation as warnings would.
+    assert!(::parse_dtd(text, &options).is_ok());
+    let options = ::ParseOptions { warnings_as_errors: true, ..options };
+    match ::parse_dtd(text, &options) {
+        Err(::DtdError::Invalid(diagnostics)) => assert_eq!(diagnostics.len(), 6),
+        other => panic!("{:?}", other),
+    }
+
+    assert!(::parse_dtd(text, &Default::default()).is_err());
+}
+
+#[test]
+fn test_includes() {
+    let input = "include \"header.edtd\";\n  include \"sub/é.edtd\" ; define elements {}";
+    let found: Vec<_> = includes(input).unwrap().into_iter()
+        .map(|include| (include.node, &input[include.span.start..include.span.end]))
+        .collect();
+    assert_eq!(found, vec![
+        ("header.edtd", "include \"header.edtd\";"),
+        ("sub/é.edtd", "include \"sub/é.edtd\" ;"),
+    ]);
+    assert!(includes("define elements {}").unwrap().is_empty());
+
+    // An EDTD which wasn't read from a file has nowhere to include from.
+    let err = match ::parse_dtd("\ninclude \"header.edtd\";", &Default::default()) {
+        Err(::DtdError::Syntax(err)) => err,
+        other => panic!("{:?}", other),
+    };
+    assert_eq!((err.line, err.column), (2, 1));
+    assert_eq!(err.code(), ::ErrorCode::InvalidInclude);
+    assert_eq!(err.message(), "in include statement: expected a block, since only EDTDs read \
+                               from files can include others, found `include`");
+
+    let err = match ::parse_dtd("include header;", &Default::default()) {
+        Err(::DtdError::Syntax(err)) => err,
+        other => panic!("{:?}", other),
+    };
+    assert_eq!(err.code(), ::ErrorCode::InvalidInclude);
+    assert_eq!(err.message(), "in include statement: expected a path in double quotes, found \
+                               `header;`");
+}
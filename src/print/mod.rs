@@ -0,0 +1,305 @@
+//! Writes a `Dtd` back out as EDTD text.
+
+use std::fmt::{self, Display, Write};
+
+use {date, Cardinality, Date, Dtd, Element, HeaderStatement, Level, NewType, Property, Spanned};
+
+/// Options controlling the layout of `Dtd::to_dtd_string_with`.
+#[derive(Debug, Clone)]
+pub struct PrintOptions {
+    /// The number of spaces each level of nesting is indented by.
+    pub indent: usize,
+    /// The longest a line may be with a definition's properties written inline. Definitions
+    /// which would be longer get one property per line.
+    pub max_width: usize,
+    /// Whether the comments before element definitions are written out.
+    pub comments: bool,
+}
+
+impl Default for PrintOptions {
+    fn default() -> Self {
+        PrintOptions { indent: 4, max_width: 100, comments: true }
+    }
+}
+
+impl<'a> Dtd<'a> {
+    /// Writes this EDTD out in a canonical form, with the default `PrintOptions`.
+    pub fn to_dtd_string(&self) -> String {
+        self.to_dtd_string_with(&PrintOptions::default())
+    }
+
+    /// Writes this EDTD out in a canonical form: the header, then the types, then the elements
+    /// with their children nested inside them, each definition's properties in the same order.
+    ///
+    /// Parsing the result gives back an equal `Dtd`, apart from comments if they're left out,
+    /// and a few values the syntax can't tell apart: a header statement with a positive `int`
+    /// value reads back as a `uint`, and one with `binary` value that's valid UTF-8 as a string.
+    pub fn to_dtd_string_with(&self, options: &PrintOptions) -> String {
+        let mut out = String::new();
+        let printer = Printer { options, out: &mut out };
+        printer.dtd(self);
+        out
+    }
+}
+
+struct Printer<'o> {
+    options: &'o PrintOptions,
+    out: &'o mut String,
+}
+
+impl<'o> Printer<'o> {
+    fn dtd(mut self, dtd: &Dtd) {
+        if !dtd.header.is_empty() {
+            self.out.push_str("declare header {\n");
+            for statement in &dtd.header {
+                self.line(1, HeaderText(statement));
+            }
+            self.out.push_str("}\n");
+        }
+        if !dtd.types.is_empty() {
+            self.out.push_str("define types {\n");
+            for new_type in &dtd.types {
+                let (base, properties) = type_properties(new_type);
+                self.definition(1, &format!("{} := {}", new_type.name(), base), &properties);
+                self.out.push_str(if properties.is_empty() { ";\n" } else { "\n" });
+            }
+            self.out.push_str("}\n");
+        }
+        if !dtd.elements.is_empty() {
+            self.out.push_str("define elements {\n");
+            for element in &dtd.elements {
+                self.element(1, element);
+            }
+            self.out.push_str("}\n");
+        }
+        for &(doc_type, ref elements) in &dtd.doc_types {
+            self.out.push_str(&format!("define elements \"{}\" {{\n", doc_type));
+            for element in elements {
+                self.element(1, element);
+            }
+            self.out.push_str("}\n");
+        }
+    }
+
+    fn element(&mut self, depth: usize, element: &Element) {
+        if self.options.comments {
+            for comment in &element.comments {
+                if comment.contains('\n') {
+                    self.line(depth, format_args!("/*{}*/", comment));
+                } else {
+                    self.line(depth, format_args!("//{}", comment));
+                }
+            }
+        }
+
+        let head = format!("{} := {:x} {}", element.name, element.id, element.type_.name());
+        let properties = element_properties(element);
+        self.definition(depth, &head, &properties);
+        if element.children.is_empty() {
+            self.out.push_str(if properties.is_empty() { ";\n" } else { "\n" });
+            return;
+        }
+
+        self.out.push_str(" {\n");
+        for child in &element.children {
+            self.element(depth + 1, child);
+        }
+        self.indent(depth);
+        self.out.push_str("}\n");
+    }
+
+    // Writes the start of a definition and its properties, leaving the line open for whatever
+    // ends it.
+    fn definition(&mut self, depth: usize, head: &str, properties: &[String]) {
+        self.indent(depth);
+        self.out.push_str(head);
+        if properties.is_empty() {
+            return;
+        }
+
+        let inline: usize = properties.iter().map(|property| property.len() + 1).sum();
+        let width = depth * self.options.indent + head.len() + " [ ".len() + inline + "] {".len();
+        if width <= self.options.max_width {
+            self.out.push_str(" [ ");
+            for property in properties {
+                self.out.push_str(property);
+                self.out.push(' ');
+            }
+            self.out.push(']');
+        } else {
+            self.out.push_str(" [\n");
+            for property in properties {
+                self.line(depth + 1, property);
+            }
+            self.indent(depth);
+            self.out.push(']');
+        }
+    }
+
+    fn line<T: Display>(&mut self, depth: usize, text: T) {
+        self.indent(depth);
+        writeln!(self.out, "{}", text).unwrap();
+    }
+
+    fn indent(&mut self, depth: usize) {
+        for _ in 0..depth * self.options.indent {
+            self.out.push(' ');
+        }
+    }
+}
+
+struct HeaderText<'s, 'a: 's>(&'s Spanned<HeaderStatement<'a>>);
+
+impl<'s, 'a> Display for HeaderText<'s, 'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} := {};", self.0.name(), header_value(self.0))
+    }
+}
+
+// The value of a header statement as written.
+pub fn header_value(statement: &HeaderStatement) -> String {
+    match *statement {
+        HeaderStatement::Int { value, .. } => value.to_string(),
+        HeaderStatement::Uint { value, .. } => value.to_string(),
+        HeaderStatement::Float { value, .. } => format!("{:?}", value),
+        HeaderStatement::Date { value, .. } => date_text(value),
+        HeaderStatement::String { ref value, .. } => string_text(value),
+        HeaderStatement::Binary { ref value, .. } => hex_text(value),
+        HeaderStatement::Named { value, .. } => value.to_string(),
+    }
+}
+
+// The base type of a new type, and its properties as written.
+pub fn type_properties(new_type: &NewType) -> (&'static str, Vec<String>) {
+    fn both<D: Display, R: Display>(default: Option<D>, range: &Option<Vec<R>>) -> Vec<String> {
+        let mut properties: Vec<_> = default.map(|d| format!("def:{};", d)).into_iter().collect();
+        properties.extend(range.as_ref().map(|range| format!("range:{};", list(range))));
+        properties
+    }
+
+    let properties = match *new_type {
+        NewType::Int { default, ref range, .. } => both(default, range),
+        NewType::Uint { default, ref range, .. } => both(default, range),
+        NewType::Float { default, ref range, .. } => {
+            both(default.map(|d| format!("{:?}", d)), range)
+        }
+        NewType::Date { default, ref range, .. } => both(default.map(date_text), range),
+        NewType::String { ref default, ref range, .. } => {
+            both(default.as_ref().map(|d| string_text(d)), range)
+        }
+        NewType::Binary { ref default, ref range, .. } => {
+            both(default.as_ref().map(|d| hex_text(d)), range)
+        }
+    };
+    (new_type.base_type().name(), properties)
+}
+
+// An element's properties as written, in the order they're listed in the specification.
+fn element_properties(element: &Element) -> Vec<String> {
+    let mut properties = Vec::new();
+    if let Some(ref parents) = element.parent {
+        properties.push(format!("parent:{};", parents.join(", ")));
+    }
+    match element.level {
+        Some(Level::Bounded { start, end }) => {
+            properties.push(format!("level:{}..{};", start, end))
+        }
+        Some(Level::Open { start }) => properties.push(format!("level:{}..;", start)),
+        None => {}
+    }
+    if let Some(ref cardinality) = element.cardinality {
+        properties.push(format!("card:{};", match *cardinality {
+            Cardinality::ZeroOrMany => "*",
+            Cardinality::ZeroOrOne => "?",
+            Cardinality::ExactlyOne => "1",
+            Cardinality::OneOrMany => "+",
+        }));
+    }
+    if let Some(ref default) = element.default {
+        properties.push(format!("def:{};", property_value(default)));
+    }
+    if let Some(ref range) = element.range {
+        properties.push(format!("range:{};", property_value(range)));
+    }
+    if let Some(ref size) = element.size {
+        properties.push(format!("size:{};", size));
+    }
+    if let Some(ordered) = element.ordered {
+        properties.push(format!("ordered:{};", yes_no(ordered)));
+    }
+    if let Some(allowed) = element.unknown_size_allowed {
+        properties.push(format!("unknownsizeallowed:{};", yes_no(allowed)));
+    }
+    if let Some(ref variants) = element.variants {
+        let variants: Vec<_> = variants.iter()
+            .map(|&(name, value)| format!("{} = {}", name, value))
+            .collect();
+        properties.push(format!("variants: {};", variants.join(", ")));
+    }
+    if let Some(minver) = element.minver {
+        properties.push(format!("minver:{};", minver));
+    }
+    if let Some(maxver) = element.maxver {
+        properties.push(format!("maxver:{};", maxver));
+    }
+    properties
+}
+
+// The value of a default or range property.
+pub fn property_value(property: &Property) -> String {
+    match *property {
+        Property::IntDefault(value) => value.to_string(),
+        Property::UintDefault(value) => value.to_string(),
+        Property::FloatDefault(value) => format!("{:?}", value),
+        Property::DateDefault(value) => date_text(value),
+        Property::StringDefault(ref value) => string_text(value),
+        Property::BinaryDefault(ref value) => hex_text(value),
+        Property::IntRange(ref items) => list(items),
+        Property::UintRange(ref items) => list(items),
+        Property::FloatRange(ref items) => list(items),
+        Property::DateRange(ref items) => list(items),
+        Property::StringRange(ref items) => list(items),
+        Property::BinaryRange(ref items) => list(items),
+        _ => unreachable!(),
+    }
+}
+
+fn list<T: Display>(items: &[T]) -> String {
+    let items: Vec<_> = items.iter().map(T::to_string).collect();
+    items.join(",")
+}
+
+fn yes_no(value: bool) -> &'static str {
+    if value { "yes" } else { "no" }
+}
+
+// Strings are quoted, unless they contain a quote: there's no way to escape one, so those are
+// written as the hex of their bytes instead, which reads back as the same string.
+fn string_text(value: &str) -> String {
+    if value.contains('"') {
+        hex_text(value.as_bytes())
+    } else {
+        format!("\"{}\"", value)
+    }
+}
+
+fn hex_text(value: &[u8]) -> String {
+    let mut text = String::from("0x");
+    for byte in value {
+        write!(text, "{:02x}", byte).unwrap();
+    }
+    text
+}
+
+// Dates are written in the structured form where it reads back, which needs the year to have
+// four digits; the nanoseconds since the EBML epoch are written for other dates.
+fn date_text(value: Date) -> String {
+    let fields = date::fields(&value);
+    match date::nanos(&value) {
+        Some(since_epoch) if !(0..10_000).contains(&fields.year) => since_epoch.to_string(),
+        _ => fields.display("").to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests;
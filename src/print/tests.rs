@@ -0,0 +1,132 @@
+use super::*;
+
+use proptest::prelude::*;
+
+use parse_dtd;
+use strategies;
+
+const FIXTURES: &[(&str, &[u8])] = &[
+    ("dtd0", include_bytes!("../../tests/dtd0")),
+    ("dtd1", include_bytes!("../../tests/dtd1")),
+    ("dtd3", include_bytes!("../../tests/dtd3")),
+    ("dtd4", include_bytes!("../../tests/dtd4")),
+    ("dtd5", include_bytes!("../../tests/dtd5")),
+    ("dtd6", include_bytes!("../../tests/dtd6")),
+    ("dtd7", include_bytes!("../../tests/dtd7")),
+    ("dtd8", include_bytes!("../../tests/dtd8")),
+    ("dtd9", include_bytes!("../../tests/dtd9")),
+    ("doc_types0", include_bytes!("../../tests/doc_types0")),
+];
+
+fn strip_comments(elements: &mut [Spanned<Element>]) {
+    for element in elements {
+        element.node.comments.clear();
+        strip_comments(&mut element.node.children);
+    }
+}
+
+#[test]
+fn test_round_trip() {
+    let narrow = PrintOptions { indent: 2, max_width: 40, ..Default::default() };
+    for &(name, input) in FIXTURES {
+        let dtd = parse_dtd(input, &Default::default()).unwrap();
+        for options in &[PrintOptions::default(), narrow.clone()] {
+            let text = dtd.to_dtd_string_with(options);
+            let printed = parse_dtd(&text, &Default::default())
+                .unwrap_or_else(|e| panic!("{}: {}\n{}", name, e, text));
+            assert_eq!(printed, dtd, "{}:\n{}", name, text);
+
+            // Printing is canonical, so doing it again changes nothing.
+            assert_eq!(printed.to_dtd_string_with(options), text, "{}", name);
+        }
+    }
+}
+
+#[test]
+fn test_without_comments() {
+    let input = include_bytes!("../../tests/dtd9");
+    let mut dtd = parse_dtd(input, &Default::default()).unwrap();
+    let options = PrintOptions { comments: false, ..Default::default() };
+    let text = dtd.to_dtd_string_with(&options);
+    assert!(!text.contains("//") && !text.contains("/*"));
+
+    strip_comments(&mut dtd.elements);
+    assert_eq!(parse_dtd(&text, &Default::default()).unwrap(), dtd);
+}
+
+#[test]
+fn test_layout() {
+    let input = b"define types { bool := uint [ range:0..1; def:0; ] }
+                  define elements {
+                      /* The root.*/
+                      Root := a1 container [ unknownsizeallowed:yes; card:1; ] {
+                          // A flag.
+                          Flag := 81 bool [ variants: Off = 0, On = 1; card:?; ]
+                          Name := 82 string;
+                      }
+                  }";
+    let dtd = parse_dtd(input, &Default::default()).unwrap();
+    assert_eq!(dtd.to_dtd_string(), "\
+define types {
+    bool := uint [ def:0; range:0..1; ]
+}
+define elements {
+    // The root.
+    Root := a1 container [ card:1; unknownsizeallowed:yes; ] {
+        // A flag.
+        Flag := 81 bool [ card:?; variants: Off = 0, On = 1; ]
+        Name := 82 string;
+    }
+}
+");
+
+    let options = PrintOptions { indent: 2, max_width: 40, comments: false };
+    assert_eq!(dtd.to_dtd_string_with(&options), "\
+define types {
+  bool := uint [ def:0; range:0..1; ]
+}
+define elements {
+  Root := a1 container [
+    card:1;
+    unknownsizeallowed:yes;
+  ] {
+    Flag := 81 bool [
+      card:?;
+      variants: Off = 0, On = 1;
+    ]
+    Name := 82 string;
+  }
+}
+");
+}
+
+#[test]
+fn test_values() {
+    let date = |nanos| date::from_parts(2020, 2, 29, 23, 59, 58, nanos);
+    assert_eq!(date_text(date(0)), "20200229T23:59:58");
+    assert_eq!(date_text(date(500_000_000)), "20200229T23:59:58.500");
+    assert_eq!(date_text(date::epoch()), "20010101T00:00:00");
+    // Fractions are read back exactly, however many digits they need.
+    assert_eq!(date_text(date(62_812)), "20200229T23:59:58.000062812");
+    assert_eq!(date::timestamp(&date_text(date(62_812))), Some(date(62_812)));
+
+    assert_eq!(string_text("Tom & Jerry"), "\"Tom & Jerry\"");
+    assert_eq!(string_text("say \"hi\""), "0x7361792022686922");
+    assert_eq!(hex_text(&[0, 0x1f, 0xff]), "0x001fff");
+}
+
+proptest! {
+    // Whatever the printer writes, the parser reads back the same, however it's laid out.
+    #[test]
+    fn test_round_trip_generated(dtd in strategies::dtd(), indent in 0..8usize,
+                                 max_width in 0..120usize) {
+        let options = PrintOptions { indent, max_width, comments: true };
+        let text = dtd.to_dtd_string_with(&options);
+        let printed = match parse_dtd(&text, &Default::default()) {
+            Ok(printed) => printed,
+            Err(err) => return Err(TestCaseError::fail(format!("{}\n{}", err, text))),
+        };
+        prop_assert_eq!(&printed, &dtd, "{}", text);
+        prop_assert_eq!(&printed.to_dtd_string_with(&options), &text);
+    }
+}
@@ -0,0 +1,280 @@
+//! What values the items of a `range:` property allow, and how they're written in an EDTD.
+
+use std::borrow::Cow;
+use std::fmt;
+
+use {date, BinaryRangeItem, Date, DateRangeItem, FloatRangeItem, IntRangeItem, SizeList, StringRangeItem,
+     UintRangeItem};
+
+/// One item of a `range:` property. A value is in a range if any of its items contain it.
+///
+/// String and binary ranges constrain each character or byte of a value, rather than the value as
+/// a whole.
+pub trait RangeItem<T: ?Sized>: fmt::Display {
+    /// True if this item allows `value`.
+    fn contains(&self, value: &T) -> bool;
+}
+
+impl RangeItem<i64> for IntRangeItem {
+    fn contains(&self, &value: &i64) -> bool {
+        match *self {
+            IntRangeItem::Single(x) => value == x,
+            IntRangeItem::From { start } => value >= start,
+            IntRangeItem::To { end } => value <= end,
+            IntRangeItem::Bounded { start, end } => start <= value && value <= end,
+        }
+    }
+}
+
+impl RangeItem<u64> for UintRangeItem {
+    fn contains(&self, &value: &u64) -> bool {
+        match *self {
+            UintRangeItem::Single(x) => value == x,
+            UintRangeItem::From { start } => value >= start,
+            UintRangeItem::Bounded { start, end } => start <= value && value <= end,
+        }
+    }
+}
+
+impl RangeItem<f64> for FloatRangeItem {
+    fn contains(&self, &value: &f64) -> bool {
+        let above = |start: f64, inclusive: bool| value > start || (inclusive && value == start);
+        let below = |end: f64, inclusive: bool| value < end || (inclusive && value == end);
+
+        match *self {
+            FloatRangeItem::From { start, include_start } => above(start, include_start),
+            FloatRangeItem::To { end, include_end } => below(end, include_end),
+            FloatRangeItem::Bounded { start, include_start, end, include_end } => {
+                above(start, include_start) && below(end, include_end)
+            }
+        }
+    }
+}
+
+impl RangeItem<Date> for DateRangeItem {
+    fn contains(&self, value: &Date) -> bool {
+        match *self {
+            DateRangeItem::From { ref start } => value >= start,
+            DateRangeItem::To { ref end } => value <= end,
+            DateRangeItem::Bounded { ref start, ref end } => start <= value && value <= end,
+        }
+    }
+}
+
+impl RangeItem<char> for StringRangeItem {
+    fn contains(&self, &value: &char) -> bool {
+        let value = value as u32;
+        match *self {
+            StringRangeItem::Single(x) => value == x,
+            StringRangeItem::From { start } => value >= start,
+            StringRangeItem::Bounded { start, end } => start <= value && value <= end,
+        }
+    }
+}
+
+impl RangeItem<u8> for BinaryRangeItem {
+    fn contains(&self, &value: &u8) -> bool {
+        match *self {
+            BinaryRangeItem::Single(x) => value == x,
+            BinaryRangeItem::From { start } => value >= start,
+            BinaryRangeItem::Bounded { start, end } => start <= value && value <= end,
+        }
+    }
+}
+
+/// A range item of numbers, which values outside a range can be clamped to.
+pub trait ClampRangeItem<T>: RangeItem<T> {
+    /// The value this item allows which is nearest to `value`, and how far apart they are.
+    fn nearest(&self, value: T) -> (T, f64);
+}
+
+impl ClampRangeItem<i64> for IntRangeItem {
+    fn nearest(&self, value: i64) -> (i64, f64) {
+        let nearest = match *self {
+            IntRangeItem::Single(x) => x,
+            IntRangeItem::From { start } => value.max(start),
+            IntRangeItem::To { end } => value.min(end),
+            IntRangeItem::Bounded { start, end } => value.max(start).min(end),
+        };
+        (nearest, (i128::from(nearest) - i128::from(value)).abs() as f64)
+    }
+}
+
+impl ClampRangeItem<u64> for UintRangeItem {
+    fn nearest(&self, value: u64) -> (u64, f64) {
+        let nearest = match *self {
+            UintRangeItem::Single(x) => x,
+            UintRangeItem::From { start } => value.max(start),
+            UintRangeItem::Bounded { start, end } => value.max(start).min(end),
+        };
+        (nearest, (i128::from(nearest) - i128::from(value)).abs() as f64)
+    }
+}
+
+impl ClampRangeItem<f64> for FloatRangeItem {
+    fn nearest(&self, value: f64) -> (f64, f64) {
+        // Excluded bounds are stepped inside by the smallest amount possible.
+        let above = |start: f64, inclusive: bool| {
+            if inclusive { value.max(start) } else { value.max(next_toward(start, true)) }
+        };
+        let below = |end: f64, inclusive: bool| {
+            if inclusive { value.min(end) } else { value.min(next_toward(end, false)) }
+        };
+        let nearest = match *self {
+            FloatRangeItem::From { start, include_start } => above(start, include_start),
+            FloatRangeItem::To { end, include_end } => below(end, include_end),
+            FloatRangeItem::Bounded { start, include_start, end, include_end } => {
+                let start = if include_start { start } else { next_toward(start, true) };
+                let end = if include_end { end } else { next_toward(end, false) };
+                value.max(start).min(end)
+            }
+        };
+        (nearest, (nearest - value).abs())
+    }
+}
+
+// The float next to `x`, above it if `up` is set and below it otherwise.
+fn next_toward(x: f64, up: bool) -> f64 {
+    if x == 0.0 {
+        let smallest = f64::from_bits(1);
+        if up { smallest } else { -smallest }
+    } else if (x > 0.0) == up {
+        f64::from_bits(x.to_bits() + 1)
+    } else {
+        f64::from_bits(x.to_bits() - 1)
+    }
+}
+
+impl SizeList {
+    /// A list which doesn't need allocating, for use in constants.
+    pub const fn from_static(items: &'static [UintRangeItem]) -> Self {
+        SizeList(Cow::Borrowed(items))
+    }
+
+    /// The items of the list.
+    pub fn items(&self) -> &[UintRangeItem] {
+        &self.0
+    }
+
+    /// True if an element body of `size` bytes is allowed.
+    pub fn matches(&self, size: u64) -> bool {
+        self.0.iter().any(|item| item.contains(&size))
+    }
+
+    /// The only size allowed, if there's just one.
+    pub fn is_fixed(&self) -> Option<u64> {
+        let mut fixed = None;
+        for item in self.0.iter() {
+            let size = match *item {
+                UintRangeItem::Single(size) => size,
+                UintRangeItem::Bounded { start, end } if start == end => start,
+                // A backwards range allows no sizes, so it doesn't matter.
+                UintRangeItem::Bounded { start, end } if start > end => continue,
+                _ => return None,
+            };
+            if fixed.map_or(false, |fixed| fixed != size) {
+                return None;
+            }
+            fixed = Some(size);
+        }
+        fixed
+    }
+}
+
+impl From<Vec<UintRangeItem>> for SizeList {
+    fn from(items: Vec<UintRangeItem>) -> Self {
+        SizeList(Cow::Owned(items))
+    }
+}
+
+// Every item is displayed the way it's written in an EDTD.
+
+impl fmt::Display for IntRangeItem {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            IntRangeItem::Single(x) => write!(f, "{}", x),
+            IntRangeItem::From { start } => write!(f, "{}..", start),
+            IntRangeItem::To { end } => write!(f, "..{}", end),
+            IntRangeItem::Bounded { start, end } => write!(f, "{}..{}", start, end),
+        }
+    }
+}
+
+impl fmt::Display for UintRangeItem {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            UintRangeItem::Single(x) => write!(f, "{}", x),
+            UintRangeItem::From { start } => write!(f, "{}..", start),
+            UintRangeItem::Bounded { start, end } => write!(f, "{}..{}", start, end),
+        }
+    }
+}
+
+impl fmt::Display for FloatRangeItem {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let equals = |inclusive| if inclusive { "=" } else { "" };
+
+        // Debug formatting always includes a decimal point, so the output reads back as a float.
+        match *self {
+            FloatRangeItem::From { start, include_start } => {
+                write!(f, ">{}{:?}", equals(include_start), start)
+            }
+            FloatRangeItem::To { end, include_end } => {
+                write!(f, "<{}{:?}", equals(include_end), end)
+            }
+            FloatRangeItem::Bounded { start, include_start, end, include_end } => write!(
+                f,
+                "{:?}<{}..<{}{:?}",
+                start, equals(include_start), equals(include_end), end
+            ),
+        }
+    }
+}
+
+impl fmt::Display for DateRangeItem {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let date = |value| date::fields(value).display("");
+        match *self {
+            DateRangeItem::From { ref start } => write!(f, "{}..", date(start)),
+            DateRangeItem::To { ref end } => write!(f, "..{}", date(end)),
+            DateRangeItem::Bounded { ref start, ref end } => {
+                write!(f, "{}..{}", date(start), date(end))
+            }
+        }
+    }
+}
+
+impl fmt::Display for StringRangeItem {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            StringRangeItem::Single(x) => write!(f, "{}", x),
+            StringRangeItem::From { start } => write!(f, "{}..", start),
+            StringRangeItem::Bounded { start, end } => write!(f, "{}..{}", start, end),
+        }
+    }
+}
+
+impl fmt::Display for BinaryRangeItem {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            BinaryRangeItem::Single(x) => write!(f, "{}", x),
+            BinaryRangeItem::From { start } => write!(f, "{}..", start),
+            BinaryRangeItem::Bounded { start, end } => write!(f, "{}..{}", start, end),
+        }
+    }
+}
+
+impl fmt::Display for SizeList {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (i, item) in self.0.iter().enumerate() {
+            if i > 0 {
+                f.write_str(",")?;
+            }
+            write!(f, "{}", item)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,131 @@
+use super::*;
+
+use proptest::prelude::*;
+
+use strategies::{any_float, float_range_item, int_range_item, size_list, uint_range_item};
+
+#[test]
+fn test_contains() {
+    let range = IntRangeItem::Bounded { start: -5, end: 5 };
+    assert!(range.contains(&-5) && range.contains(&5) && !range.contains(&6));
+    assert!(IntRangeItem::To { end: 0 }.contains(&i64::MIN));
+    assert!(UintRangeItem::From { start: 1 }.contains(&1));
+    assert!(!UintRangeItem::Single(1).contains(&2));
+
+    let range = FloatRangeItem::Bounded {
+        start: 0.0,
+        include_start: false,
+        end: 1.0,
+        include_end: true,
+    };
+    assert!(!range.contains(&0.0) && range.contains(&1.0) && range.contains(&0.5));
+    assert!(!FloatRangeItem::From { start: 0.0, include_start: true }.contains(&f64::NAN));
+
+    let date = date::epoch();
+    assert!(DateRangeItem::From { start: date }.contains(&date));
+    assert!(!DateRangeItem::To { end: date }.contains(&date::from_nanos(1_000_000_000)));
+
+    assert!(StringRangeItem::Bounded { start: 32, end: 126 }.contains(&'~'));
+    assert!(!StringRangeItem::Bounded { start: 32, end: 126 }.contains(&'\n'));
+    assert!(BinaryRangeItem::Single(0).contains(&0));
+}
+
+#[test]
+fn test_nearest() {
+    assert_eq!(IntRangeItem::Bounded { start: -5, end: 5 }.nearest(9), (5, 4.0));
+    assert_eq!(IntRangeItem::To { end: 0 }.nearest(-3), (-3, 0.0));
+    assert_eq!(UintRangeItem::From { start: 1 }.nearest(0), (1, 1.0));
+    assert_eq!(UintRangeItem::Single(8).nearest(u64::MAX).0, 8);
+
+    let range = FloatRangeItem::Bounded {
+        start: 0.0,
+        include_start: false,
+        end: 1.0,
+        include_end: true,
+    };
+    let (nearest, _) = range.nearest(-1.0);
+    assert!(nearest > 0.0 && range.contains(&nearest));
+    assert_eq!(range.nearest(2.0), (1.0, 1.0));
+    assert!(range.nearest(f64::NAN).1.is_nan());
+}
+
+#[test]
+fn test_size_list() {
+    let sizes = SizeList::from(vec![
+        UintRangeItem::Single(4),
+        UintRangeItem::Bounded { start: 8, end: 10 },
+    ]);
+    assert!(sizes.matches(4) && sizes.matches(10));
+    assert!(!sizes.matches(5) && !sizes.matches(11));
+    assert_eq!(sizes.to_string(), "4,8..10");
+
+    const OPEN: SizeList = SizeList::from_static(&[UintRangeItem::From { start: 1 }]);
+    assert!(OPEN.matches(1 << 40) && !OPEN.matches(0));
+
+    assert_eq!(sizes.is_fixed(), None);
+    assert_eq!(OPEN.is_fixed(), None);
+    assert_eq!(SizeList::from(vec![UintRangeItem::Single(16)]).is_fixed(), Some(16));
+    let same = SizeList::from(vec![
+        UintRangeItem::Bounded { start: 8, end: 8 },
+        UintRangeItem::Single(8),
+    ]);
+    assert_eq!(same.is_fixed(), Some(8));
+    let wider = SizeList::from(vec![UintRangeItem::Single(4), UintRangeItem::From { start: 0 }]);
+    assert_eq!(wider.is_fixed(), None);
+}
+
+#[test]
+fn test_display() {
+    assert_eq!(IntRangeItem::To { end: -3 }.to_string(), "..-3");
+    assert_eq!(UintRangeItem::Bounded { start: 1, end: 2 }.to_string(), "1..2");
+    assert_eq!(
+        FloatRangeItem::Bounded { start: 0.0, include_start: true, end: 1.5, include_end: false }
+            .to_string(),
+        "0.0<=..<1.5"
+    );
+    assert_eq!(FloatRangeItem::From { start: 0.0, include_start: false }.to_string(), ">0.0");
+
+    let date = date::from_nanos(1_000_000_000);
+    assert_eq!(DateRangeItem::From { start: date }.to_string(), "20010101T00:00:01..");
+}
+
+proptest! {
+    // If an item allows anything at all, the nearest value it allows to any other is one it
+    // allows, and values it allows are their own nearest.
+    #[test]
+    fn test_int_nearest_laws(item in int_range_item(), value: i64, allowed: i64) {
+        let (nearest, distance) = item.nearest(value);
+        if item.contains(&value) {
+            prop_assert_eq!((nearest, distance), (value, 0.0));
+        }
+        prop_assert!(item.contains(&nearest) || !item.contains(&allowed));
+    }
+
+    #[test]
+    fn test_uint_nearest_laws(item in uint_range_item(), value: u64, allowed: u64) {
+        let (nearest, distance) = item.nearest(value);
+        if item.contains(&value) {
+            prop_assert_eq!((nearest, distance), (value, 0.0));
+        }
+        prop_assert!(item.contains(&nearest) || !item.contains(&allowed));
+    }
+
+    #[test]
+    fn test_float_nearest_laws(item in float_range_item(), value in any_float(),
+                               allowed in any_float()) {
+        let (nearest, distance) = item.nearest(value);
+        if item.contains(&value) {
+            prop_assert_eq!((nearest, distance), (value, 0.0));
+        }
+        prop_assert!(item.contains(&nearest) || !item.contains(&allowed));
+    }
+
+    // A fixed size is the only one a list matches.
+    #[test]
+    fn test_fixed_size_laws(sizes in size_list(), size: u64) {
+        if let Some(fixed) = sizes.is_fixed() {
+            prop_assert!(sizes.matches(fixed));
+            prop_assert!(!sizes.matches(size) || size == fixed);
+        }
+    }
+}
@@ -0,0 +1,102 @@
+//! Renders diagnostics the way rustc does: the message, then the line of source it's about with a
+//! gutter of line numbers and the problem underlined.
+
+use {SourceMap, Span};
+
+// The most columns of a source line shown. Longer lines are cut down to the part around the span,
+// with ellipses where text was left out.
+const MAX_WIDTH: usize = 100;
+const TAB_WIDTH: usize = 4;
+const ELLIPSIS: &str = "...";
+
+// Renders `header`, then the line of `source` on which `span` starts with the span underlined up
+// to the end of that line. Columns are counted as a terminal shows them.
+pub fn render(source: &str, header: &str, span: Span) -> String {
+    let start = char_boundary(source, span.start);
+    let end = char_boundary(source, span.end).max(start);
+    let map = SourceMap::new(source);
+    let (line_number, column) = map.line_column(start);
+    let Span { start: line_start, end: line_end } = map.line_span(line_number);
+    let line = source[line_start..line_end].trim_end_matches('\r');
+
+    // Each character of the line as it's displayed, with the column it starts at.
+    let mut cells = Vec::new();
+    let mut width = 0;
+    let (mut underline_start, mut underline_end) = (None, None);
+    for (idx, c) in line.char_indices() {
+        if underline_start.is_none() && line_start + idx >= start {
+            underline_start = Some(width);
+        }
+        if underline_end.is_none() && line_start + idx >= end {
+            underline_end = Some(width);
+        }
+        let text = if c == '\t' { " ".repeat(TAB_WIDTH) } else { c.to_string() };
+        let cell_width = text.chars().map(char_width).sum::<usize>();
+        cells.push((width, cell_width, text));
+        width += cell_width;
+    }
+    let underline_start = underline_start.unwrap_or(width);
+    let underline_end = underline_end.unwrap_or(width).max(underline_start + 1);
+
+    // The columns shown: all of them, or as many as fit starting a little before the span.
+    let (from, to) = if width <= MAX_WIDTH {
+        (0, width)
+    } else {
+        let from = underline_start.saturating_sub(MAX_WIDTH / 4).min(width - MAX_WIDTH);
+        (from, from + MAX_WIDTH)
+    };
+    let mut text = String::new();
+    let mut shown_from = None;
+    for &(column, cell_width, ref cell) in &cells {
+        if column >= from && column + cell_width <= to {
+            shown_from = shown_from.or(Some(column));
+            text.push_str(cell);
+        }
+    }
+    let shown_from = shown_from.unwrap_or(from);
+    let mut indent = underline_start.max(shown_from) - shown_from;
+    if from > 0 {
+        text.insert_str(0, ELLIPSIS);
+        indent += ELLIPSIS.len();
+    }
+    if to < width {
+        text.push_str(ELLIPSIS);
+    }
+    let underline = underline_end.min(to).saturating_sub(underline_start.max(shown_from)).max(1);
+
+    let number = line_number.to_string();
+    let gutter = " ".repeat(number.len());
+    let mut out = format!("{}\n{}--> line {}, column {}\n{} |\n{} |", header, gutter, line_number,
+                          column, gutter, number);
+    if !text.is_empty() {
+        out.push(' ');
+        out.push_str(&text);
+    }
+    out.push_str(&format!("\n{} | {}{}", gutter, " ".repeat(indent), "^".repeat(underline)));
+    out
+}
+
+// The nearest character boundary at or before `offset`, which may be past the end of `source`.
+fn char_boundary(source: &str, offset: usize) -> usize {
+    let mut offset = offset.min(source.len());
+    while !source.is_char_boundary(offset) {
+        offset -= 1;
+    }
+    offset
+}
+
+// How many columns a terminal shows a character in: none for combining marks, two for East Asian
+// wide characters and emoji, and one for everything else.
+fn char_width(c: char) -> usize {
+    match c as u32 {
+        0x0300..=0x036F | 0x200B..=0x200F | 0xFE00..=0xFE0F => 0,
+        0x1100..=0x115F | 0x2E80..=0x303E | 0x3041..=0x33FF | 0x3400..=0x4DBF |
+        0x4E00..=0x9FFF | 0xA000..=0xA4CF | 0xAC00..=0xD7A3 | 0xF900..=0xFAFF |
+        0xFE30..=0xFE4F | 0xFF00..=0xFF60 | 0xFFE0..=0xFFE6 | 0x1F300..=0x1F64F |
+        0x1F900..=0x1F9FF | 0x20000..=0x3FFFD => 2,
+        _ => 1,
+    }
+}
+
+#[cfg(test)]
+mod tests;
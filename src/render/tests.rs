@@ -0,0 +1,107 @@
+use super::*;
+
+fn syntax_error(source: &str) -> String {
+    match ::parse_dtd(source, &Default::default()) {
+        Err(::DtdError::Syntax(err)) => err.render(source),
+        other => panic!("{:?}", other),
+    }
+}
+
+#[test]
+fn test_render_syntax_errors() {
+    assert_eq!(syntax_error(include_str!("../../tests/syntax_error0")), concat!(
+        "error[P0005]: in element definition `Segment`, in element definition `Title`: \
+         expected a valid value, found `string;`\n",
+        " --> line 6, column 18\n",
+        "  |\n",
+        "6 |         Title := string;\n",
+        "  |                  ^^^^^^^",
+    ));
+
+    // Multi-byte characters take up one column, and wide ones two.
+    assert_eq!(syntax_error(include_str!("../../tests/syntax_error3")), concat!(
+        "error[P0005]: in element definition `EBML`, in element definition `DocType`: \
+         expected a keyword or punctuation, found `bogus;`\n",
+        " --> line 3, column 44\n",
+        "  |\n",
+        "3 |         DocType := 4282 string [ def:\"éé\"; bogus; ]\n",
+        "  |                                            ^^^^^^",
+    ));
+    let source = "define elements {\n    A := 81 string [ def:\"日本語\"; bogus; ]\n}\n";
+    assert_eq!(syntax_error(source), concat!(
+        "error[P0005]: in element definition `A`: expected a keyword or punctuation, \
+         found `bogus;`\n",
+        " --> line 2, column 33\n",
+        "  |\n",
+        "2 |     A := 81 string [ def:\"日本語\"; bogus; ]\n",
+        "  |                                    ^^^^^^",
+    ));
+    assert_eq!(syntax_error(include_str!("../../tests/syntax_error6")), concat!(
+        "error[P0008]: in element definition `Title`, in cardinality property: expected `*`, `?`, \
+         `1` or `+`, found `maybe;`\n",
+        " --> line 5, column 55\n",
+        "  |\n",
+        "5 |     Title := 7ba9 string [ def:\"Ünïcödé 🎬 タイトル\"; card:maybe; ]\n",
+        "  |                                                            ^^^^^^",
+    ));
+
+    // Tabs are expanded, so the underline lines up however wide the terminal shows them.
+    assert_eq!(syntax_error(include_str!("../../tests/syntax_error4")), concat!(
+        "error[P0008]: in element definition `Segment`, in element definition `Title`, \
+         in cardinality property: expected `*`, `?`, `1` or `+`, found `lots;`\n",
+        " --> line 3, column 31\n",
+        "  |\n",
+        "3 |         Title := 7ba9 string [    card:lots; ]\n",
+        "  |                                        ^^^^^",
+    ));
+
+    assert_eq!(syntax_error(include_str!("../../tests/syntax_error5")), concat!(
+        "error[P0009]: in element definition `Tags`, in element definition `Bad`, \
+         in default property: expected a valid value, found `oops;`\n",
+        " --> line 2, column 234\n",
+        "  |\n",
+        "2 | ...nt; Bad := 90 uint [ def:oops; ] T12 := 8c uint; T13 := 8d uint; T14 := 8e uint; \
+         T15 := 8f uint; T16...\n",
+        "  |                             ^^^^^",
+    ));
+
+    // At the end of the input, the underline is just past the last character.
+    assert_eq!(syntax_error("define elements {\n    A := 81"), concat!(
+        "error[P0001]: unexpected end of input while parsing element definition `A`\n",
+        " --> line 2, column 12\n",
+        "  |\n",
+        "2 |     A := 81\n",
+        "  |            ^",
+    ));
+}
+
+#[test]
+fn test_render_validation_diagnostics() {
+    let source = "define elements {\n    First := 81 uint;\n    Second := 81 uint;\n}\n";
+    let options = ::ParseOptions { validate: Some(::Severity::Error), ..Default::default() };
+    let diagnostics = match ::parse_dtd(source, &options) {
+        Err(::DtdError::Invalid(diagnostics)) => diagnostics,
+        other => panic!("{:?}", other),
+    };
+    assert_eq!(diagnostics[0].render(source), concat!(
+        "error[V0011]: Second: id 0x81 is already used by First\n",
+        " --> line 3, column 5\n",
+        "  |\n",
+        "3 |     Second := 81 uint;\n",
+        "  |     ^^^^^^",
+    ));
+
+    let diagnostic =
+        ::ValidationDiagnostic::warning(::ErrorCode::UnknownParent, "Nowhere", "no span");
+    assert_eq!(diagnostic.render(source), "warning[V0014]: Nowhere: no span");
+}
+
+#[test]
+fn test_char_width() {
+    assert_eq!(char_width('a'), 1);
+    assert_eq!(char_width('é'), 1);
+    assert_eq!(char_width('\u{301}'), 0);
+    assert_eq!(char_width('語'), 2);
+    assert_eq!(char_boundary("é", 1), 0);
+    assert_eq!(char_boundary("é", 5), 2);
+}
@@ -0,0 +1,278 @@
+use std::fmt;
+use std::io;
+
+use proptest::collection::vec;
+use proptest::strategy::Union;
+use proptest::test_runner::{Config, TestCaseError, TestRunner};
+
+use {date, BinaryRangeItem, DateRangeItem, FloatRangeItem, IntRangeItem, RangeItem, SizeList,
+     StringRangeItem, UintRangeItem};
+use super::{read_header, EbmlPrimitive, ReadError};
+
+pub use proptest::arbitrary::Arbitrary;
+pub use proptest::strategy::{BoxedStrategy, Just, Strategy};
+
+// How far past the start of a half-open range, or how many bytes past the start of a `size:`,
+// generated values go. Larger values are generated too, but lengths stay readable.
+const REACH: u64 = 0xFF;
+
+/// How much room generated container structs have, their `Arbitrary::Parameters`. Fields
+/// holding many values hold at most `room` more than they must, and `string` and `binary` values
+/// without a `size:` are at most `room * 4` bytes long. Nested containers get half as much room
+/// as their parents, and optional containers are left out once there's none. The default is 8.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Room(pub usize);
+
+impl Room {
+    /// The room for the contents of a container.
+    pub fn nested(self) -> Room {
+        Room(self.0 / 2)
+    }
+}
+
+impl Default for Room {
+    fn default() -> Self {
+        Room(8)
+    }
+}
+
+/// `None` or a value from `strategy`, shrinking to `None`. Always `None` once there's no room
+/// left, without calling `strategy`, so recursive containers end.
+pub fn option_of<S, F>(room: Room, strategy: F) -> BoxedStrategy<Option<S::Value>>
+    where S: Strategy + 'static, F: FnOnce() -> S
+{
+    if room.0 == 0 {
+        Just(None).boxed()
+    } else {
+        ::proptest::option::of(strategy()).boxed()
+    }
+}
+
+/// At least `min` values from `strategy`, and at most `room` more, shrinking to `min`. `strategy`
+/// isn't called if there's no room left and no values are needed, so recursive containers end.
+pub fn vec_of<S, F>(room: Room, min: usize, strategy: F) -> BoxedStrategy<Vec<S::Value>>
+    where S: Strategy + 'static, F: FnOnce() -> S
+{
+    if room.0 == 0 && min == 0 {
+        Just(Vec::new()).boxed()
+    } else {
+        vec(strategy(), min..=min + room.0).boxed()
+    }
+}
+
+/// Values for a primitive element. `raw` generates raw values in the element's range, `body`
+/// encodes values the way the writer does, and values are only kept if their encoding is one of
+/// the `sizes`, if there are any.
+pub fn primitive<Raw, T, B>(raw: BoxedStrategy<Raw>, body: B, sizes: Option<&SizeList>)
+    -> BoxedStrategy<T>
+    where Raw: fmt::Debug + 'static,
+          T: EbmlPrimitive<Raw> + fmt::Debug + 'static,
+          B: Fn(&T) -> io::Result<Vec<u8>> + 'static
+{
+    let sizes = sizes.cloned();
+    raw.prop_filter_map("doesn't fit the element's type and size", move |raw| {
+        let value = T::from_raw(raw)?;
+        match body(&value) {
+            Ok(ref body) if sizes.as_ref().is_none_or(|s| s.matches(body.len() as u64)) => {
+                Some(value)
+            }
+            _ => None,
+        }
+    }).boxed()
+}
+
+// Values from any of the strategies `item` gives for `range`'s items, or from `whole` for an
+// empty range. Shrinking moves towards the first item, and towards the start of each.
+fn union<I, T, F>(range: &[I], whole: BoxedStrategy<T>, item: F) -> BoxedStrategy<T>
+    where T: fmt::Debug + 'static, F: Fn(&I) -> BoxedStrategy<T>
+{
+    if range.is_empty() {
+        whole
+    } else {
+        Union::new(range.iter().map(item)).boxed()
+    }
+}
+
+/// Raw `int`s in `range`.
+pub fn int_strategy(range: &[IntRangeItem]) -> BoxedStrategy<i64> {
+    let reach = REACH as i64;
+    union(range, ::proptest::num::i64::ANY.boxed(), |item| match *item {
+        IntRangeItem::Single(x) => Just(x).boxed(),
+        IntRangeItem::From { start } => {
+            (start..=start.saturating_add(reach)).prop_union(start..=i64::MAX).boxed()
+        }
+        IntRangeItem::To { end } => {
+            (end.saturating_sub(reach)..=end).prop_union(i64::MIN..=end).boxed()
+        }
+        IntRangeItem::Bounded { start, end } => (start..=end).boxed(),
+    })
+}
+
+/// Raw `uint`s in `range`.
+pub fn uint_strategy(range: &[UintRangeItem]) -> BoxedStrategy<u64> {
+    union(range, ::proptest::num::u64::ANY.boxed(), |item| match *item {
+        UintRangeItem::Single(x) => Just(x).boxed(),
+        UintRangeItem::From { start } => {
+            (start..=start.saturating_add(REACH)).prop_union(start..=u64::MAX).boxed()
+        }
+        UintRangeItem::Bounded { start, end } => (start..=end).boxed(),
+    })
+}
+
+/// Raw `float`s in `range`. If they're written as `single` precision, they survive that.
+pub fn float_strategy(range: &[FloatRangeItem], single: bool) -> BoxedStrategy<f64> {
+    let finite = if single {
+        use proptest::num::f32::{NEGATIVE, NORMAL, POSITIVE, SUBNORMAL, ZERO};
+        (POSITIVE | NEGATIVE | NORMAL | SUBNORMAL | ZERO).prop_map(f64::from).boxed()
+    } else {
+        use proptest::num::f64::{NEGATIVE, NORMAL, POSITIVE, SUBNORMAL, ZERO};
+        (POSITIVE | NEGATIVE | NORMAL | SUBNORMAL | ZERO).boxed()
+    };
+    let reach = REACH as f64;
+    let values = union(range, finite, |item| match *item {
+        FloatRangeItem::From { start, .. } => (0.0..reach).prop_map(move |x| start + x).boxed(),
+        FloatRangeItem::To { end, .. } => (0.0..reach).prop_map(move |x| end - x).boxed(),
+        FloatRangeItem::Bounded { start, end, .. } => {
+            (0.0..=1.0).prop_map(move |x| start + (end - start) * x).boxed()
+        }
+    });
+    let range = range.to_vec();
+    values
+        .prop_map(move |value| if single { f64::from(value as f32) } else { value })
+        .prop_filter("isn't in the element's range", move |value| {
+            value.is_finite() && (range.is_empty() || range.iter().any(|i| i.contains(value)))
+        })
+        .boxed()
+}
+
+/// Raw `date`s in `range`.
+pub fn date_strategy(range: &[DateRangeItem]) -> BoxedStrategy<i64> {
+    let nanos = date::saturating_nanos;
+    let range: Vec<_> = range.iter().map(|item| match *item {
+        DateRangeItem::From { ref start } => IntRangeItem::From { start: nanos(start) },
+        DateRangeItem::To { ref end } => IntRangeItem::To { end: nanos(end) },
+        DateRangeItem::Bounded { ref start, ref end } => {
+            IntRangeItem::Bounded { start: nanos(start), end: nanos(end) }
+        }
+    }).collect();
+    int_strategy(&range)
+}
+
+/// Raw `string`s whose characters are in `range`, with a length in `sizes` if there are any, or
+/// at most `room * 4` bytes long. They never contain NUL, since trailing NULs are padding.
+/// Characters which would overrun the length are left out, and strings which end up too short
+/// for `sizes` are rejected.
+pub fn string_strategy(range: &[StringRangeItem], sizes: Option<&SizeList>, room: Room)
+    -> BoxedStrategy<String>
+{
+    // Mostly ASCII, so lengths in bytes and characters tend to agree.
+    let any = Union::new_weighted(vec![
+        (7, (0x20..=0x7E).boxed()),
+        (1, (0..=0x10_FFFF).boxed()),
+    ]).boxed();
+    let codes = union(range, any, |item| match *item {
+        StringRangeItem::Single(x) => Just(x).boxed(),
+        StringRangeItem::From { start } => (start..=start + 0x7F).boxed(),
+        StringRangeItem::Bounded { start, end } => (start..=end).boxed(),
+    });
+    let chars = codes.prop_filter_map("isn't a character other than NUL", |code| {
+        ::std::char::from_u32(code).filter(|&c| c != '\0')
+    }).boxed();
+    let fits = sizes.cloned();
+    len_strategy(sizes, room).prop_flat_map(move |len| {
+        vec(chars.clone(), len).prop_map(move |chars| {
+            let mut value = String::new();
+            for c in chars {
+                if value.len() + c.len_utf8() <= len {
+                    value.push(c);
+                }
+            }
+            value
+        })
+    }).prop_filter("isn't one of the sizes", move |value| {
+        fits.as_ref().is_none_or(|sizes| sizes.matches(value.len() as u64))
+    }).boxed()
+}
+
+/// Raw `binary`s whose bytes are in `range`, with a length in `sizes` if there are any, or at
+/// most `room * 4` bytes long.
+pub fn binary_strategy(range: &[BinaryRangeItem], sizes: Option<&SizeList>, room: Room)
+    -> BoxedStrategy<Vec<u8>>
+{
+    let bytes = union(range, ::proptest::num::u8::ANY.boxed(), |item| match *item {
+        BinaryRangeItem::Single(x) => Just(x).boxed(),
+        BinaryRangeItem::From { start } => (start..=0xFF).boxed(),
+        BinaryRangeItem::Bounded { start, end } => (start..=end).boxed(),
+    });
+    len_strategy(sizes, room).prop_flat_map(move |len| vec(bytes.clone(), len)).boxed()
+}
+
+// Lengths in bytes from `sizes`, or up to `room * 4` if there aren't any.
+fn len_strategy(sizes: Option<&SizeList>, room: Room) -> BoxedStrategy<usize> {
+    let most = room.0 * 4;
+    let items = sizes.map_or(&[][..], SizeList::items);
+    union(items, (0..=most).boxed(), |item| match *item {
+        UintRangeItem::Single(x) => Just(x as usize).boxed(),
+        UintRangeItem::From { start } => (start as usize..=start as usize + most).boxed(),
+        UintRangeItem::Bounded { start, end } => {
+            (start as usize..=end.min(start + most as u64) as usize).boxed()
+        }
+    })
+}
+
+// Like `check_round_trip`, returning what went wrong instead of panicking.
+fn round_trip<T, L, W, R>(value: &T, len: L, write: W, read: R) -> Result<(), String>
+    where T: PartialEq + fmt::Debug,
+          L: FnOnce() -> io::Result<u64>,
+          W: FnOnce(&mut Vec<u8>) -> io::Result<u64>,
+          R: FnOnce(&mut &[u8], u64) -> Result<T, ReadError>
+{
+    let mut bytes = Vec::new();
+    let written = write(&mut bytes)
+        .map_err(|err| format!("couldn't write {:?}: {}", value, err))?;
+    match len() {
+        Ok(len) if len == written && len == bytes.len() as u64 => {}
+        Ok(len) => {
+            return Err(format!("wrote {} bytes of {:?}, but its length is {}",
+                               bytes.len(), value, len))
+        }
+        Err(err) => return Err(format!("couldn't find the length of {:?}: {}", value, err)),
+    }
+    let mut r = &bytes[..];
+    match read_header(&mut r).and_then(|(_, size, _)| read(&mut r, size)) {
+        Ok(ref read) if read == value => Ok(()),
+        Ok(read) => Err(format!("wrote {:?} but read back {:?}", value, read)),
+        Err(err) => Err(format!("couldn't read back {:?} from {:?}: {}", value, bytes, err)),
+    }
+}
+
+/// Writes `value` with `write`, reads the element back with `read`, and panics if the result
+/// isn't equal to `value`, or if the number of bytes written isn't the one `len` gives.
+pub fn check_round_trip<T, L, W, R>(value: &T, len: L, write: W, read: R)
+    where T: PartialEq + fmt::Debug,
+          L: FnOnce() -> io::Result<u64>,
+          W: FnOnce(&mut Vec<u8>) -> io::Result<u64>,
+          R: FnOnce(&mut &[u8], u64) -> Result<T, ReadError>
+{
+    if let Err(err) = round_trip(value, len, write, read) {
+        panic!("{}", err);
+    }
+}
+
+/// Runs `check_round_trip` on `cases` arbitrary values of `T`, panicking with the simplest value
+/// proptest shrinks a failure to. Generated `check_round_trips` functions call this.
+pub fn check_round_trips<T, L, W, R>(cases: u32, len: L, write: W, read: R)
+    where T: Arbitrary + PartialEq,
+          L: Fn(&T) -> io::Result<u64>,
+          W: Fn(&T, &mut Vec<u8>) -> io::Result<u64>,
+          R: Fn(&mut &[u8], u64) -> Result<T, ReadError>
+{
+    let config = Config { failure_persistence: None, ..Config::with_cases(cases) };
+    let result = TestRunner::new(config).run(&::proptest::arbitrary::any::<T>(), |value| {
+        round_trip(&value, || len(&value), |w| write(&value, w), |r, size| read(r, size))
+            .map_err(TestCaseError::fail)
+    });
+    if let Err(err) = result {
+        panic!("{}", err);
+    }
+}
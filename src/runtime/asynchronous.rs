@@ -0,0 +1,309 @@
+//! Async counterparts of the reading functions, which generated `*_async` readers call. Bytes
+//! are read asynchronously and then decoded by the same functions the synchronous readers use.
+
+use std::future::{poll_fn, ready, Future};
+use std::io;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use ebml::Id;
+pub use tokio::io::AsyncRead;
+use tokio::io::ReadBuf;
+
+use super::global::check_crc32;
+use super::read::{decode_date, decode_float, decode_id, decode_int, decode_string, decode_uint,
+                  read_encoded_child_header, ReadContext, ReadError, ReadErrorKind,
+                  UnknownElements};
+
+/// A boxed future, for the functions which can finish in several ways.
+pub type BoxFuture<'r, T> = Pin<Box<dyn Future<Output = Result<T, ReadError>> + 'r>>;
+
+// How many bytes are read at a time by `read_binary_async` and `skip_async`, so sizes don't have
+// to be trusted enough to allocate them up front.
+const CHUNK: usize = 8 * 1024;
+
+// Reads from `r` into the unfilled part of `buf`, returning how many bytes were read. Zero means
+// the input has ended.
+fn poll_fill<R>(r: &mut R, cx: &mut Context, buf: &mut [u8]) -> Poll<io::Result<usize>>
+    where R: AsyncRead + Unpin
+{
+    let mut buf = ReadBuf::new(buf);
+    match Pin::new(r).poll_read(cx, &mut buf) {
+        Poll::Ready(Ok(())) => Poll::Ready(Ok(buf.filled().len())),
+        Poll::Ready(Err(err)) => Poll::Ready(Err(err)),
+        Poll::Pending => Poll::Pending,
+    }
+}
+
+// Reads `size` bytes, or as many as there are if `r` ends first.
+fn read_up_to<'r, R>(r: &'r mut R, size: u64)
+    -> impl Future<Output = Result<Vec<u8>, ReadError>> + Unpin + 'r
+    where R: AsyncRead + Unpin
+{
+    let mut bytes = Vec::new();
+    poll_fn(move |cx| {
+        while (bytes.len() as u64) < size {
+            let len = bytes.len();
+            let wanted = (size - len as u64).min(CHUNK as u64) as usize;
+            bytes.resize(len + wanted, 0);
+            let read = poll_fill(r, cx, &mut bytes[len..]);
+            let read = match read {
+                Poll::Ready(Ok(read)) => read,
+                Poll::Ready(Err(err)) => {
+                    bytes.truncate(len);
+                    return Poll::Ready(Err(err.into()));
+                }
+                Poll::Pending => {
+                    bytes.truncate(len);
+                    return Poll::Pending;
+                }
+            };
+            bytes.truncate(len + read);
+            if read == 0 {
+                break;
+            }
+        }
+        Poll::Ready(Ok(::std::mem::take(&mut bytes)))
+    })
+}
+
+// Applies `f` to the result of `future` once it's ready.
+fn then<F, T, U, G>(mut future: F, f: G) -> impl Future<Output = Result<U, ReadError>> + Unpin
+    where F: Future<Output = Result<T, ReadError>> + Unpin,
+          G: Fn(T) -> Result<U, ReadError> + Unpin
+{
+    poll_fn(move |cx| match Pin::new(&mut future).poll(cx) {
+        Poll::Ready(result) => Poll::Ready(result.and_then(&f)),
+        Poll::Pending => Poll::Pending,
+    })
+}
+
+fn exactly(bytes: Vec<u8>, size: u64) -> Result<Vec<u8>, ReadError> {
+    if bytes.len() as u64 == size {
+        Ok(bytes)
+    } else {
+        Err(ReadError::new(ReadErrorKind::UnexpectedEof))
+    }
+}
+
+/// Like `read_binary`: reads `size` bytes from `r`.
+pub fn read_binary_async<'r, R>(r: &'r mut R, size: u64)
+    -> impl Future<Output = Result<Vec<u8>, ReadError>> + Unpin + 'r
+    where R: AsyncRead + Unpin
+{
+    then(read_up_to(r, size), move |bytes| exactly(bytes, size))
+}
+
+/// Like `skip`: skips over the body of an element.
+pub fn skip_async<'r, R>(r: &'r mut R, size: u64)
+    -> impl Future<Output = Result<(), ReadError>> + Unpin + 'r
+    where R: AsyncRead + Unpin
+{
+    let mut left = size;
+    let mut scratch = vec![0; (size.min(CHUNK as u64)) as usize];
+    poll_fn(move |cx| {
+        while left > 0 {
+            let wanted = left.min(scratch.len() as u64) as usize;
+            match poll_fill(r, cx, &mut scratch[..wanted]) {
+                Poll::Ready(Ok(0)) => {
+                    return Poll::Ready(Err(ReadError::new(ReadErrorKind::UnexpectedEof)))
+                }
+                Poll::Ready(Ok(read)) => left -= read as u64,
+                Poll::Ready(Err(err)) => return Poll::Ready(Err(err.into())),
+                Poll::Pending => return Poll::Pending,
+            }
+        }
+        Poll::Ready(Ok(()))
+    })
+}
+
+// Reads a numeric body, which can't be more than 8 bytes long.
+fn read_scalar_async<'r, R>(r: &'r mut R, size: u64)
+    -> impl Future<Output = Result<Vec<u8>, ReadError>> + Unpin + 'r
+    where R: AsyncRead + Unpin
+{
+    let size = if size > 8 { None } else { Some(size) };
+    then(read_up_to(r, size.unwrap_or(0)), move |bytes| match size {
+        Some(size) => exactly(bytes, size),
+        None => Err(ReadError::new(ReadErrorKind::InvalidValue)),
+    })
+}
+
+/// Like `read_uint`.
+pub fn read_uint_async<'r, R>(r: &'r mut R, size: u64)
+    -> impl Future<Output = Result<u64, ReadError>> + Unpin + 'r
+    where R: AsyncRead + Unpin
+{
+    then(read_scalar_async(r, size), |body| decode_uint(&body))
+}
+
+/// Like `read_int`.
+pub fn read_int_async<'r, R>(r: &'r mut R, size: u64)
+    -> impl Future<Output = Result<i64, ReadError>> + Unpin + 'r
+    where R: AsyncRead + Unpin
+{
+    then(read_scalar_async(r, size), |body| decode_int(&body))
+}
+
+/// Like `read_float`.
+pub fn read_float_async<'r, R>(r: &'r mut R, size: u64)
+    -> impl Future<Output = Result<f64, ReadError>> + Unpin + 'r
+    where R: AsyncRead + Unpin
+{
+    then(read_scalar_async(r, size), |body| decode_float(&body))
+}
+
+/// Like `read_date`.
+pub fn read_date_async<'r, R>(r: &'r mut R, size: u64)
+    -> impl Future<Output = Result<i64, ReadError>> + Unpin + 'r
+    where R: AsyncRead + Unpin
+{
+    then(read_scalar_async(r, size), |body| decode_date(&body))
+}
+
+/// Like `read_string`.
+pub fn read_string_async<'r, R>(r: &'r mut R, size: u64)
+    -> impl Future<Output = Result<String, ReadError>> + Unpin + 'r
+    where R: AsyncRead + Unpin
+{
+    then(read_binary_async(r, size), decode_string)
+}
+
+// The length of a variable length integer starting with `first`, or 1 if it's invalid, so it's
+// reported once decoded.
+fn vint_len(first: u8) -> usize {
+    match first.leading_zeros() {
+        8 => 1,
+        zeros => zeros as usize + 1,
+    }
+}
+
+// Reads the bytes of an element's header, which is decoded by `read_encoded_child_header`.
+// Resolves to `None` if `or_eof` is set and the input ends before the header starts.
+fn read_header_bytes<'r, R>(r: &'r mut R, or_eof: bool)
+    -> impl Future<Output = Result<Option<(u32, Option<u64>, u64)>, ReadError>> + Unpin + 'r
+    where R: AsyncRead + Unpin
+{
+    let mut buf = [0; 12];
+    let mut filled = 0;
+    poll_fn(move |cx| loop {
+        // The id's length is known from its first byte, and then the size's from its first.
+        let needed = match filled {
+            0 => 1,
+            _ if vint_len(buf[0]) > 4 => filled,
+            _ if filled < vint_len(buf[0]) + 1 => vint_len(buf[0]) + 1,
+            _ => vint_len(buf[0]) + vint_len(buf[vint_len(buf[0])]),
+        };
+        if filled == needed {
+            return Poll::Ready(read_encoded_child_header(&mut &buf[..filled]).map(Some));
+        }
+        match poll_fill(r, cx, &mut buf[filled..needed]) {
+            Poll::Ready(Ok(0)) if filled == 0 && or_eof => return Poll::Ready(Ok(None)),
+            Poll::Ready(Ok(0)) => {
+                return Poll::Ready(Err(ReadError::new(ReadErrorKind::UnexpectedEof)))
+            }
+            Poll::Ready(Ok(read)) => filled += read,
+            Poll::Ready(Err(err)) => return Poll::Ready(Err(err.into())),
+            Poll::Pending => return Poll::Pending,
+        }
+    })
+}
+
+/// Like `read_child_header`.
+pub fn read_child_header_async<'r, R>(r: &'r mut R)
+    -> impl Future<Output = Result<(Id, Option<u64>, u64), ReadError>> + Unpin + 'r
+    where R: AsyncRead + Unpin
+{
+    then(read_encoded_child_header_async(r), decoded)
+}
+
+/// Like `read_child_header_or_eof`.
+pub fn read_child_header_or_eof_async<'r, R>(r: &'r mut R)
+    -> impl Future<Output = Result<Option<(Id, Option<u64>, u64)>, ReadError>> + Unpin + 'r
+    where R: AsyncRead + Unpin
+{
+    then(read_header_bytes(r, true), |header| header.map(decoded).transpose())
+}
+
+/// Like `read_encoded_child_header`.
+pub fn read_encoded_child_header_async<'r, R>(r: &'r mut R)
+    -> impl Future<Output = Result<(u32, Option<u64>, u64), ReadError>> + Unpin + 'r
+    where R: AsyncRead + Unpin
+{
+    then(read_header_bytes(r, false), |header| {
+        Ok(header.expect("headers are only missing at the end of the input if that's allowed"))
+    })
+}
+
+/// Like `read_encoded_child_header_or_eof`.
+pub fn read_encoded_child_header_or_eof_async<'r, R>(r: &'r mut R)
+    -> impl Future<Output = Result<Option<(u32, Option<u64>, u64)>, ReadError>> + Unpin + 'r
+    where R: AsyncRead + Unpin
+{
+    read_header_bytes(r, true)
+}
+
+/// Like `read_header`.
+pub fn read_header_async<'r, R>(r: &'r mut R)
+    -> impl Future<Output = Result<(Id, u64, u64), ReadError>> + Unpin + 'r
+    where R: AsyncRead + Unpin
+{
+    then(read_child_header_async(r), known_size)
+}
+
+/// Like `read_header_or_eof`.
+pub fn read_header_or_eof_async<'r, R>(r: &'r mut R)
+    -> impl Future<Output = Result<Option<(Id, u64, u64)>, ReadError>> + Unpin + 'r
+    where R: AsyncRead + Unpin
+{
+    then(read_child_header_or_eof_async(r), |header| header.map(known_size).transpose())
+}
+
+fn decoded((encoded, size, len): (u32, Option<u64>, u64))
+    -> Result<(Id, Option<u64>, u64), ReadError>
+{
+    decode_id(encoded).map(|id| (id, size, len))
+}
+
+fn known_size((id, size, len): (Id, Option<u64>, u64)) -> Result<(Id, u64, u64), ReadError> {
+    match size {
+        Some(size) => Ok((id, size, len)),
+        None => Err(ReadError::new(ReadErrorKind::InvalidSize)),
+    }
+}
+
+/// Like `read_unknown`.
+pub fn read_unknown_async<'r, R>(r: &'r mut R, ctx: &ReadContext, default: UnknownElements,
+                                 id: Id, size: u64)
+    -> BoxFuture<'r, Option<Vec<u8>>>
+    where R: AsyncRead + Unpin
+{
+    match ctx.options().unknown_elements.unwrap_or(default) {
+        UnknownElements::Error => {
+            Box::pin(ready(Err(ReadError::new(ReadErrorKind::UnknownElement(id)))))
+        }
+        UnknownElements::Skip => Box::pin(then(skip_async(r, size), |()| Ok(None))),
+        UnknownElements::Collect => Box::pin(then(read_binary_async(r, size), |body| {
+            Ok(Some(body))
+        })),
+    }
+}
+
+/// Like `read_crc32`.
+pub fn read_crc32_async<'r, R>(r: &'r mut R, ctx: &'r ReadContext, size: u64, remaining: u64)
+    -> BoxFuture<'r, Option<Vec<u8>>>
+    where R: AsyncRead + Unpin
+{
+    if ctx.options().lenient_crc {
+        return Box::pin(then(skip_async(r, size), |()| Ok(None)));
+    }
+    if size != 4 {
+        let kind = ReadErrorKind::SizeNotAllowed { size, allowed: "4".to_string() };
+        return Box::pin(ready(Err(ReadError::new(kind).within("CRC-32"))));
+    }
+    // The CRC's body and what it covers are read together, and then split.
+    Box::pin(then(read_binary_async(r, size + remaining), move |mut body| {
+        let rest = body.split_off(size as usize);
+        check_crc32(ctx, &body, &rest).map(|()| Some(rest))
+    }))
+}
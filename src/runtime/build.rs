@@ -0,0 +1,19 @@
+use std::error::Error;
+use std::fmt;
+
+/// The mandatory elements a generated builder was missing when it was built.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BuildError {
+    /// The name of the container being built.
+    pub container: &'static str,
+    /// The names of the missing elements, in the order they're declared.
+    pub missing: Vec<&'static str>,
+}
+
+impl fmt::Display for BuildError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} is missing {}", self.container, self.missing.join(", "))
+    }
+}
+
+impl Error for BuildError {}
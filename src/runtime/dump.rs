@@ -0,0 +1,164 @@
+use std::fmt;
+use std::io::{self, Read};
+
+use date;
+use super::date_from_raw;
+use super::global::{CRC_32, VOID};
+use super::read::{read_binary, read_date, read_encoded_id, read_float, read_int,
+                  read_size_or_unknown, read_string, read_uint, skip, ReadError, ReadErrorKind,
+                  ReadLimits};
+
+/// How `dump` prints an element.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DumpKind {
+    /// An `int`, printed in decimal.
+    Int,
+    /// A `uint`, printed in decimal.
+    Uint,
+    /// A `float`.
+    Float,
+    /// A `string`, quoted.
+    String,
+    /// A `date`, printed in ISO 8601.
+    Date,
+    /// A `binary`, printed in hex, up to `DUMP_BINARY_LEN` bytes.
+    Binary,
+    /// A `container`, followed by its children.
+    Container,
+}
+
+/// The most bytes of a `binary` element `dump` prints.
+pub const DUMP_BINARY_LEN: u64 = 16;
+
+// The elements any EBML document may contain, as named by the EBML specification.
+const GLOBALS: &[(u32, &str, DumpKind)] = &[
+    (0x1A45_DFA3, "EBML", DumpKind::Container),
+    (0x4286, "EBMLVersion", DumpKind::Uint),
+    (0x42F7, "EBMLReadVersion", DumpKind::Uint),
+    (0x42F2, "EBMLMaxIDLength", DumpKind::Uint),
+    (0x42F3, "EBMLMaxSizeLength", DumpKind::Uint),
+    (0x4282, "DocType", DumpKind::String),
+    (0x4287, "DocTypeVersion", DumpKind::Uint),
+    (0x4285, "DocTypeReadVersion", DumpKind::Uint),
+    (CRC_32, "CRC-32", DumpKind::Binary),
+];
+
+/// Prints every element in the rest of `r` to `out`, one per line and indented by two spaces per
+/// level: its name, encoded id, size and, unless it's a container, value. This is what generated
+/// `dump` functions call.
+///
+/// `lookup` gives the name and kind of an element from its encoded id. The EBML header's
+/// elements, `Void` and `CRC-32` are named even if it doesn't know them; anything else it
+/// doesn't know is printed by id and skipped. Elements of unknown size extend to the end of
+/// their parent. Nesting and the sizes of values it prints are bounded by `ReadLimits::default()`.
+pub fn dump<R, W, F>(r: &mut R, out: &mut W, lookup: F) -> Result<(), ReadError>
+    where R: Read, W: fmt::Write, F: Fn(u32) -> Option<(&'static str, DumpKind)>
+{
+    let mut dumper = Dumper { out, lookup };
+    dumper.children(r, 0)
+}
+
+struct Dumper<'w, W: 'w, F> {
+    out: &'w mut W,
+    lookup: F,
+}
+
+impl<'w, W, F> Dumper<'w, W, F>
+    where W: fmt::Write, F: Fn(u32) -> Option<(&'static str, DumpKind)>
+{
+    // Elements are read through trait objects, since each level of nesting would otherwise be
+    // another type.
+    fn children(&mut self, r: &mut dyn Read, depth: usize) -> Result<(), ReadError> {
+        while self.element(r, depth)? {}
+        Ok(())
+    }
+
+    // Prints the next element, returning false if there isn't one.
+    fn element(&mut self, r: &mut dyn Read, depth: usize) -> Result<bool, ReadError> {
+        let mut first = [0];
+        if r.read(&mut first)? == 0 {
+            return Ok(false);
+        }
+        let r = &mut (&first[..]).chain(r);
+        let (id, _) = read_encoded_id(r)?;
+        let (size, _) = read_size_or_unknown(r)?;
+
+        let known = (self.lookup)(id).or_else(|| {
+            GLOBALS.iter().find(|global| global.0 == id).map(|&(_, name, kind)| (name, kind))
+        });
+        write!(self.out, "{:1$}", "", depth * 2).map_err(write_error)?;
+        match known {
+            Some((name, _)) => write!(self.out, "{} ({:#X})", name, id),
+            None if id == VOID => write!(self.out, "Void ({:#X})", id),
+            None => write!(self.out, "Unknown ({:#X})", id),
+        }.map_err(write_error)?;
+        match size {
+            Some(size) => write!(self.out, " size {}", size),
+            None => write!(self.out, " size unknown"),
+        }.map_err(write_error)?;
+
+        let (name, kind) = match known {
+            Some(known) => known,
+            None => {
+                writeln!(self.out).map_err(write_error)?;
+                let size = size.ok_or_else(|| ReadError::new(ReadErrorKind::UnknownSize))?;
+                skip(r, size)?;
+                return Ok(true);
+            }
+        };
+        let result = match size {
+            Some(size) => self.body(&mut r.take(size), kind, size, depth),
+            None if kind == DumpKind::Container => self.body(r, kind, 0, depth),
+            None => Err(ReadError::new(ReadErrorKind::UnknownSize)),
+        };
+        result.map_err(|err| err.within(name))?;
+        Ok(true)
+    }
+
+    // Prints the rest of an element's line, and its children. `size` is only used for
+    // primitives.
+    fn body<R: Read>(&mut self, r: &mut R, kind: DumpKind, size: u64, depth: usize)
+        -> Result<(), ReadError>
+    {
+        let limits = ReadLimits::default();
+        if kind == DumpKind::Container && depth + 1 > limits.max_depth {
+            return Err(ReadError::new(ReadErrorKind::TooDeep(limits.max_depth)));
+        }
+        // Binaries are only read as far as they're printed.
+        if kind != DumpKind::Container && kind != DumpKind::Binary &&
+            size > limits.max_element_size
+        {
+            let limit = limits.max_element_size;
+            return Err(ReadError::new(ReadErrorKind::TooLarge { size, limit }));
+        }
+        match kind {
+            DumpKind::Container => {
+                writeln!(self.out).map_err(write_error)?;
+                return self.children(r, depth + 1);
+            }
+            DumpKind::Int => write!(self.out, ": {}", read_int(r, size)?),
+            DumpKind::Uint => write!(self.out, ": {}", read_uint(r, size)?),
+            DumpKind::Float => write!(self.out, ": {}", read_float(r, size)?),
+            DumpKind::String => write!(self.out, ": {:?}", read_string(r, size)?),
+            DumpKind::Date => {
+                let date = date_from_raw(read_date(r, size)?);
+                write!(self.out, ": {}", date::fields(&date).display("-"))
+            }
+            DumpKind::Binary if size == 0 => Ok(()),
+            DumpKind::Binary => {
+                let shown = size.min(DUMP_BINARY_LEN);
+                let bytes = read_binary(r, shown)?;
+                skip(r, size - shown)?;
+                let hex: Vec<_> = bytes.iter().map(|byte| format!("{:02X}", byte)).collect();
+                let more = if shown < size { " ..." } else { "" };
+                write!(self.out, ": {}{}", hex.join(" "), more)
+            }
+        }.map_err(write_error)?;
+        writeln!(self.out).map_err(write_error)?;
+        Ok(())
+    }
+}
+
+fn write_error(_: fmt::Error) -> ReadError {
+    io::Error::other("couldn't write the dump").into()
+}
@@ -0,0 +1,52 @@
+use std::io::{Read, Seek, SeekFrom};
+
+use ebml::Id;
+
+use super::read::{read_header, read_header_or_eof, ReadError, ReadErrorKind};
+
+/// Searches for an element by `path`, the encoded ids of the elements leading to it, outermost
+/// first. Only the elements on the path are entered; everything else is skipped by seeking past
+/// it.
+///
+/// The search covers the next `size` bytes of `r`, or the rest of it if `size` is `None`. If the
+/// element is found, `r` is left at the start of its body and its size is returned.
+pub fn find_path<R: Read + Seek>(r: &mut R, path: &[u32], size: Option<u64>)
+    -> Result<Option<u64>, ReadError>
+{
+    let (&first, rest) = match path.split_first() {
+        Some(split) => split,
+        None => return Ok(None),
+    };
+
+    let mut remaining = size;
+    loop {
+        let (id, child_size, header_size) = match remaining {
+            Some(0) => return Ok(None),
+            Some(_) => read_header(r)?,
+            None => match read_header_or_eof(r)? {
+                Some(header) => header,
+                None => return Ok(None),
+            },
+        };
+        if let Some(size) = remaining {
+            remaining = Some(size.checked_sub(header_size)
+                .and_then(|size| size.checked_sub(child_size))
+                .ok_or_else(|| ReadError::new(ReadErrorKind::Overrun))?);
+        }
+
+        if Id::from_encoded(first) == Some(id) {
+            if rest.is_empty() {
+                return Ok(Some(child_size));
+            }
+            // The rest of the path might be in a later element with the same id, so this one's
+            // end is remembered in case it isn't in here.
+            let start = r.stream_position()?;
+            if let Some(size) = find_path(r, rest, Some(child_size))? {
+                return Ok(Some(size));
+            }
+            r.seek(SeekFrom::Start(start + child_size))?;
+        } else {
+            r.seek(SeekFrom::Current(child_size as i64))?;
+        }
+    }
+}
@@ -0,0 +1,97 @@
+use std::io::{self, Read, Write};
+
+use ebml::Id;
+
+use super::read::{read_binary, skip, ReadContext, ReadError, ReadErrorKind};
+use super::write::{write_element, write_header};
+
+/// The encoded id of `Void`, which pads out space in any element and is always skipped.
+pub const VOID: u32 = 0xEC;
+
+/// The encoded id of `CRC-32`, which may be the first child of any element. It holds the CRC-32
+/// of the rest of the element's body, little-endian.
+pub const CRC_32: u32 = 0xBF;
+
+/// The length of a whole `CRC-32` element.
+pub const CRC_32_LEN: u64 = 6;
+
+/// True if `id` is the id of a `Void` element.
+pub fn is_void(id: Id) -> bool {
+    Id::from_encoded(VOID) == Some(id)
+}
+
+/// True if `id` is the id of a `CRC-32` element.
+pub fn is_crc32(id: Id) -> bool {
+    Id::from_encoded(CRC_32) == Some(id)
+}
+
+/// The CRC-32 (as used by zlib, and by EBML) of `data`.
+pub fn crc32(data: &[u8]) -> u32 {
+    !data.iter().fold(!0, |crc, &byte| {
+        (0..8).fold(crc ^ u32::from(byte), |crc, _| {
+            if crc & 1 == 1 { crc >> 1 ^ 0xEDB8_8320 } else { crc >> 1 }
+        })
+    })
+}
+
+/// Reads the body of a `CRC-32` element, `size` bytes long, which was the first child of an
+/// element with `remaining` bytes of body left after it.
+///
+/// The rest of the body is read and checked against the CRC, and returned so the remaining
+/// children can be read from it. If the options make CRCs lenient, the element is just skipped and
+/// `None` is returned.
+pub fn read_crc32<R: Read>(r: &mut R, ctx: &ReadContext, size: u64, remaining: u64)
+    -> Result<Option<Vec<u8>>, ReadError>
+{
+    if ctx.options().lenient_crc {
+        skip(r, size)?;
+        return Ok(None);
+    }
+    check_crc32_size(size)?;
+
+    let mut stored = [0; 4];
+    r.read_exact(&mut stored)?;
+    let rest = read_binary(r, remaining)?;
+    check_crc32_of(stored, &rest)?;
+    Ok(Some(rest))
+}
+
+/// Like `read_crc32`, for a `CRC-32` element whose body and the `rest` of its parent's body are
+/// already in memory. Nothing is checked if the options make CRCs lenient.
+pub fn check_crc32(ctx: &ReadContext, body: &[u8], rest: &[u8]) -> Result<(), ReadError> {
+    if ctx.options().lenient_crc {
+        return Ok(());
+    }
+    check_crc32_size(body.len() as u64)?;
+    check_crc32_of([body[0], body[1], body[2], body[3]], rest)
+}
+
+fn check_crc32_size(size: u64) -> Result<(), ReadError> {
+    if size == 4 {
+        Ok(())
+    } else {
+        let kind = ReadErrorKind::SizeNotAllowed { size, allowed: "4".to_string() };
+        Err(ReadError::new(kind).within("CRC-32"))
+    }
+}
+
+// Checks the CRC `stored` in a `CRC-32` element against the data it covers.
+fn check_crc32_of(stored: [u8; 4], data: &[u8]) -> Result<(), ReadError> {
+    let stored = u32::from_le_bytes(stored);
+    let computed = crc32(data);
+    if stored == computed {
+        Ok(())
+    } else {
+        Err(ReadError::new(ReadErrorKind::CrcMismatch { stored, computed }))
+    }
+}
+
+/// Writes an element whose body is a `CRC-32` element covering `children`, followed by
+/// `children`. Returns the number of bytes written.
+pub fn write_with_crc32<W: Write>(w: &mut W, id: u32, children: &[u8]) -> io::Result<u64> {
+    let body_len = CRC_32_LEN + children.len() as u64;
+    let mut written = write_header(w, id, body_len)?;
+    written += write_element(w, CRC_32, &crc32(children).to_le_bytes())?;
+    w.write_all(children)?;
+    Ok(written + children.len() as u64)
+}
@@ -0,0 +1,185 @@
+use std::error::Error;
+use std::fmt;
+use std::io::Read;
+
+use ebml::Id;
+
+use super::read::{read_header, read_string, read_uint, skip, ReadError, ReadErrorKind};
+
+/// The encoded id of the `EBML` header element, which starts every EBML document.
+pub const EBML: u32 = 0x1A45_DFA3;
+
+const EBML_VERSION: u32 = 0x4286;
+const EBML_READ_VERSION: u32 = 0x42F7;
+const EBML_MAX_ID_LENGTH: u32 = 0x42F2;
+const EBML_MAX_SIZE_LENGTH: u32 = 0x42F3;
+const DOC_TYPE: u32 = 0x4282;
+const DOC_TYPE_VERSION: u32 = 0x4287;
+const DOC_TYPE_READ_VERSION: u32 = 0x4285;
+
+fn is(id: Id, encoded: u32) -> bool {
+    Id::from_encoded(encoded) == Some(id)
+}
+
+/// The EBML header at the start of every EBML document. Absent elements take the defaults from
+/// the EBML specification.
+#[derive(Debug, Clone, PartialEq)]
+pub struct EbmlHeader {
+    /// `EBMLVersion`: the version of EBML the document was written with.
+    pub version: u64,
+    /// `EBMLReadVersion`: the oldest version of EBML a reader must support to read the document.
+    pub read_version: u64,
+    /// `EBMLMaxIDLength`: the longest element id in the document, in bytes.
+    pub max_id_length: u64,
+    /// `EBMLMaxSizeLength`: the longest element size in the document, in bytes.
+    pub max_size_length: u64,
+    /// `DocType`: what kind of document this is.
+    pub doc_type: String,
+    /// `DocTypeVersion`: the version of the DocType the document was written with.
+    pub doc_type_version: u64,
+    /// `DocTypeReadVersion`: the oldest version of the DocType a reader must support to read the
+    /// document.
+    pub doc_type_read_version: u64,
+}
+
+/// The ways reading and checking an EBML header can fail.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum HeaderError {
+    /// The header couldn't be read.
+    Read(ReadError),
+    /// The document isn't of the DocType the schema describes.
+    WrongDocType {
+        /// The DocType of the schema.
+        expected: &'static str,
+        /// The DocType of the document.
+        found: String,
+    },
+    /// Reading the document needs a newer version of EBML or of the DocType than the schema
+    /// describes.
+    VersionTooNew {
+        /// The header element holding the version: `EBMLReadVersion` or `DocTypeReadVersion`.
+        element: &'static str,
+        /// The newest version the schema describes.
+        supported: u64,
+        /// The version needed to read the document.
+        found: u64,
+    },
+}
+
+impl From<ReadError> for HeaderError {
+    fn from(err: ReadError) -> Self {
+        HeaderError::Read(err)
+    }
+}
+
+impl fmt::Display for HeaderError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            HeaderError::Read(ref err) => write!(f, "{}", err),
+            HeaderError::WrongDocType { expected, ref found } => {
+                write!(f, "expected a `{}` document, found `{}`", expected, found)
+            }
+            HeaderError::VersionTooNew { element, supported, found } => write!(
+                f,
+                "{} is {}, but only versions up to {} are supported",
+                element, found, supported
+            ),
+        }
+    }
+}
+
+impl Error for HeaderError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match *self {
+            HeaderError::Read(ref err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+/// Reads the EBML header element at the start of a document. Unknown children are skipped, and
+/// if a child is repeated the last one wins.
+pub fn read_ebml_header<R: Read>(r: &mut R) -> Result<EbmlHeader, ReadError> {
+    let within = |err: ReadError| err.within("EBML");
+
+    let (id, size, _) = read_header(r)?;
+    if !is(id, EBML) {
+        return Err(ReadError::new(ReadErrorKind::MissingElement("EBML")));
+    }
+
+    let mut header = EbmlHeader {
+        version: 1,
+        read_version: 1,
+        max_id_length: 4,
+        max_size_length: 8,
+        doc_type: String::new(),
+        doc_type_version: 1,
+        doc_type_read_version: 1,
+    };
+    let mut doc_type = None;
+
+    let mut remaining = size;
+    while remaining > 0 {
+        let (id, child_size, header_size) = read_header(r).map_err(within)?;
+        remaining = remaining.checked_sub(header_size)
+            .and_then(|remaining| remaining.checked_sub(child_size))
+            .ok_or_else(|| within(ReadError::new(ReadErrorKind::Overrun)))?;
+
+        let field = if is(id, EBML_VERSION) {
+            &mut header.version
+        } else if is(id, EBML_READ_VERSION) {
+            &mut header.read_version
+        } else if is(id, EBML_MAX_ID_LENGTH) {
+            &mut header.max_id_length
+        } else if is(id, EBML_MAX_SIZE_LENGTH) {
+            &mut header.max_size_length
+        } else if is(id, DOC_TYPE_VERSION) {
+            &mut header.doc_type_version
+        } else if is(id, DOC_TYPE_READ_VERSION) {
+            &mut header.doc_type_read_version
+        } else if is(id, DOC_TYPE) {
+            let value = read_string(r, child_size).map_err(|err| within(err.within("DocType")))?;
+            doc_type = Some(value);
+            continue;
+        } else {
+            skip(r, child_size).map_err(within)?;
+            continue;
+        };
+        *field = read_uint(r, child_size).map_err(within)?;
+    }
+
+    header.doc_type = doc_type
+        .ok_or_else(|| within(ReadError::new(ReadErrorKind::MissingElement("DocType"))))?;
+    Ok(header)
+}
+
+/// Checks that a document with `header` can be read with a schema for `doc_type`, describing
+/// version `doc_type_version` of it (if known) and version `ebml_version` of EBML.
+pub fn check_ebml_header(header: &EbmlHeader, doc_type: Option<&'static str>,
+                         doc_type_version: Option<u64>, ebml_version: u64)
+    -> Result<(), HeaderError>
+{
+    if let Some(expected) = doc_type {
+        if header.doc_type != expected {
+            return Err(HeaderError::WrongDocType { expected, found: header.doc_type.clone() });
+        }
+    }
+    if header.read_version > ebml_version {
+        return Err(HeaderError::VersionTooNew {
+            element: "EBMLReadVersion",
+            supported: ebml_version,
+            found: header.read_version,
+        });
+    }
+    match doc_type_version {
+        Some(supported) if header.doc_type_read_version > supported => {
+            Err(HeaderError::VersionTooNew {
+                element: "DocTypeReadVersion",
+                supported,
+                found: header.doc_type_read_version,
+            })
+        }
+        _ => Ok(()),
+    }
+}
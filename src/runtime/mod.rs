@@ -0,0 +1,204 @@
+//! Support code called by generated readers and writers.
+//!
+//! Nothing in here is needed to parse an EDTD; it exists so that generated code doesn't have to
+//! repeat it in every module.
+
+use std::convert::TryFrom;
+use std::io::{self, Read, Write};
+
+#[cfg(feature = "chrono")]
+use chrono::NaiveDateTime;
+
+use {date, Date, EbmlDate};
+
+#[cfg(feature = "arbitrary")]
+mod arbitrary;
+#[cfg(feature = "async")]
+mod asynchronous;
+mod build;
+mod dump;
+mod find;
+mod global;
+mod header;
+mod push;
+mod range;
+mod raw_id;
+mod read;
+mod schema;
+mod write;
+
+#[cfg(feature = "arbitrary")]
+pub use self::arbitrary::*;
+#[cfg(feature = "async")]
+pub use self::asynchronous::*;
+pub use self::build::*;
+pub use self::dump::*;
+pub use self::find::*;
+pub use self::global::*;
+pub use self::header::*;
+pub use self::push::*;
+pub use self::range::*;
+pub use self::raw_id::*;
+pub use self::read::*;
+pub use self::schema::*;
+pub use self::write::*;
+
+/// Converts between a Rust type and the value of an EBML element of some primitive type.
+///
+/// `Raw` is the form the value takes in an EBML document:
+///
+/// | EDTD type | `Raw`     |
+/// |-----------|-----------|
+/// | `int`     | `i64`     |
+/// | `uint`    | `u64`     |
+/// | `float`   | `f64`     |
+/// | `string`  | `String`  |
+/// | `binary`  | `Vec<u8>` |
+/// | `date`    | `i64`, nanoseconds since 2001-01-01T00:00:00 |
+///
+/// Any type used in place of the default for a primitive (see `codegen::CodegenOptions`) must
+/// implement this for the corresponding `Raw` type. Besides the raw types themselves, it's
+/// implemented for the smaller integer types, `bool` (as a `uint`), `EbmlDate` and, with the
+/// `chrono` feature, `chrono::NaiveDateTime` (as a `date`), and `[u8; N]` (as a `binary` of
+/// exactly `N` bytes).
+pub trait EbmlPrimitive<Raw>: Sized {
+    /// Converts a decoded value, or returns `None` if it can't be represented by this type.
+    fn from_raw(raw: Raw) -> Option<Self>;
+
+    /// Converts this into the value to encode, or returns `None` if it can't be encoded.
+    fn to_raw(&self) -> Option<Raw>;
+}
+
+macro_rules! identity_primitive {
+    ($($t:ty),*) => {$(
+        impl EbmlPrimitive<$t> for $t {
+            fn from_raw(raw: $t) -> Option<Self> {
+                Some(raw)
+            }
+
+            fn to_raw(&self) -> Option<$t> {
+                Some(self.clone())
+            }
+        }
+    )*}
+}
+
+identity_primitive!(i64, u64, f64, String, Vec<u8>);
+
+// Smaller integers, which can't hold every value their raw type can.
+macro_rules! narrow_primitive {
+    ($raw:ty => $($t:ty),*) => {$(
+        impl EbmlPrimitive<$raw> for $t {
+            fn from_raw(raw: $raw) -> Option<Self> {
+                <$t>::try_from(raw).ok()
+            }
+
+            fn to_raw(&self) -> Option<$raw> {
+                Some(<$raw>::from(*self))
+            }
+        }
+    )*}
+}
+
+narrow_primitive!(u64 => u8, u16, u32);
+narrow_primitive!(i64 => i8, i16, i32);
+
+// Fixed-size binaries, which can't hold any other length.
+impl<const N: usize> EbmlPrimitive<Vec<u8>> for [u8; N] {
+    fn from_raw(raw: Vec<u8>) -> Option<Self> {
+        <[u8; N]>::try_from(raw).ok()
+    }
+
+    fn to_raw(&self) -> Option<Vec<u8>> {
+        Some(self.to_vec())
+    }
+}
+
+impl EbmlPrimitive<u64> for bool {
+    fn from_raw(raw: u64) -> Option<Self> {
+        match raw {
+            0 => Some(false),
+            1 => Some(true),
+            _ => None,
+        }
+    }
+
+    fn to_raw(&self) -> Option<u64> {
+        Some(u64::from(*self))
+    }
+}
+
+impl EbmlPrimitive<i64> for EbmlDate {
+    fn from_raw(raw: i64) -> Option<Self> {
+        Some(EbmlDate(raw))
+    }
+
+    fn to_raw(&self) -> Option<i64> {
+        Some(self.0)
+    }
+}
+
+#[cfg(feature = "chrono")]
+impl EbmlPrimitive<i64> for NaiveDateTime {
+    fn from_raw(raw: i64) -> Option<Self> {
+        Some(EbmlDate(raw).to_naive())
+    }
+
+    fn to_raw(&self) -> Option<i64> {
+        EbmlDate::from_naive(*self).map(|date| date.0)
+    }
+}
+
+/// A struct holding the body of a container element, which can be read from a document.
+///
+/// Every generated container struct implements this, as does every struct deriving it with
+/// `ebml_macros_impl`'s `#[derive(EbmlRead)]`.
+pub trait EbmlRead: Sized {
+    /// Reads the body of the element, which is `size` bytes long, using the options and
+    /// collecting warnings in `ctx`.
+    fn read_with<R: Read>(r: &mut R, size: u64, ctx: &mut ReadContext) -> Result<Self, ReadError>;
+
+    /// Reads the body of the element, which is `size` bytes long.
+    fn read_from<R: Read>(r: &mut R, size: u64) -> Result<Self, ReadError> {
+        Self::read_with(r, size, &mut ReadContext::default())
+    }
+}
+
+/// A struct holding the body of a container element, which can be written as one.
+///
+/// Every generated container struct implements this, as does every struct deriving it with
+/// `ebml_macros_impl`'s `#[derive(EbmlWrite)]`.
+pub trait EbmlWrite {
+    /// Writes this as an element with the given options, returning the number of bytes written.
+    fn write_with<W: Write>(&self, w: &mut W, options: &WriteOptions) -> io::Result<u64>;
+
+    /// The number of bytes `write_with` writes with the given options.
+    fn encoded_len_with(&self, options: &WriteOptions) -> io::Result<u64>;
+
+    /// Writes this as an element, returning the number of bytes written.
+    fn write_to<W: Write>(&self, w: &mut W) -> io::Result<u64> {
+        self.write_with(w, &WriteOptions::default())
+    }
+
+    /// The number of bytes `write_to` writes.
+    fn encoded_len(&self) -> io::Result<u64> {
+        self.encoded_len_with(&WriteOptions::default())
+    }
+}
+
+/// Converts the raw value of a `date` element into a date. Every raw value is representable.
+pub fn date_from_raw(raw: i64) -> Date {
+    date::from_nanos(raw)
+}
+
+/// Converts the default of an element into the type chosen for it.
+///
+/// # Panics
+///
+/// Panics if the default can't be represented by that type.
+pub fn default_value<Raw, T: EbmlPrimitive<Raw>>(raw: Raw) -> T {
+    T::from_raw(raw).expect("an element's default can't be represented by its type")
+}
+
+#[cfg(test)]
+mod tests;
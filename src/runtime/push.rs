@@ -0,0 +1,345 @@
+use ebml::Id;
+
+use super::global::{is_crc32, is_void};
+use super::read::{check_child, check_limits, decode_date, decode_float, decode_id, decode_int,
+                  decode_string, decode_uint, read_encoded_id, read_size_or_unknown,
+                  ReadContext, ReadError, ReadErrorKind, ReadOptions, UnknownElements};
+
+/// The type of an element, as far as a `PushParser` is concerned.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ElementKind {
+    /// An `int`.
+    Int,
+    /// A `uint`.
+    Uint,
+    /// A `float`.
+    Float,
+    /// A `string`.
+    String,
+    /// A `date`.
+    Date,
+    /// A `binary`.
+    Binary,
+    /// A `container`.
+    Container,
+}
+
+/// What a `PushParser` needs to know about an element of the schema.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PushElement {
+    /// The element's name.
+    pub name: &'static str,
+    /// The element's type.
+    pub kind: ElementKind,
+    /// Whether the element may have an unknown size.
+    pub unknown_size: bool,
+    /// The encoded ids of the containers the element may be inside.
+    pub parents: &'static [u32],
+}
+
+/// The value of an element which isn't a container, as its raw type (see `EbmlPrimitive`).
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    /// The value of an `int`.
+    Int(i64),
+    /// The value of a `uint`.
+    Uint(u64),
+    /// The value of a `float`.
+    Float(f64),
+    /// The value of a `string`.
+    String(String),
+    /// The value of a `date`, in nanoseconds since 2001-01-01T00:00:00 UTC.
+    Date(i64),
+    /// The value of a `binary`.
+    Binary(Vec<u8>),
+}
+
+/// Something a `PushParser` found.
+#[derive(Debug, Clone, PartialEq)]
+pub enum PushEvent {
+    /// The start of a container, with the size of its body if it's known.
+    Begin(Id, Option<u64>),
+    /// An element which isn't a container, or one the schema doesn't describe if they're
+    /// collected.
+    Value(Id, Value),
+    /// The end of a container.
+    End(Id),
+}
+
+/// Reads a document from bytes which are pushed to it, in chunks of any size, instead of pulling
+/// them from a reader. This is what generated `Parser`s wrap.
+///
+/// Limits and the policy for unknown elements apply as they do to generated readers, but
+/// cardinalities, orders and ranges aren't checked, and a `CRC-32` is skipped rather than
+/// verified. A container of unknown size ends at the first element which can't be inside it,
+/// at the end of its parent, or at the end of the input.
+#[derive(Debug)]
+pub struct PushParser {
+    ctx: ReadContext,
+    default: UnknownElements,
+    // Bytes which have been fed, but not handled yet.
+    pending: Vec<u8>,
+    // How many bytes have been handled since the start of the input.
+    offset: u64,
+    state: State,
+    // The containers which haven't ended yet, outermost first.
+    open: Vec<Open>,
+}
+
+#[derive(Debug)]
+enum State {
+    // Waiting for the next element's header.
+    Header,
+    // Waiting for the body of an element with a value. Unknown elements have no name.
+    Body { id: Id, name: Option<&'static str>, kind: ElementKind, size: u64 },
+    // Skipping the rest of an element's body.
+    Skip { left: u64 },
+}
+
+#[derive(Debug)]
+struct Open {
+    id: Id,
+    encoded: u32,
+    name: &'static str,
+    // Where the container's body ends, if its size is known.
+    end: Option<u64>,
+    children: usize,
+}
+
+impl PushParser {
+    /// Creates a parser which reads with `options`, and handles elements the schema doesn't
+    /// describe by `default` unless the options say otherwise.
+    pub fn new(options: ReadOptions, default: UnknownElements) -> Self {
+        PushParser {
+            ctx: ReadContext::new(options),
+            default,
+            pending: Vec::new(),
+            offset: 0,
+            state: State::Header,
+            open: Vec::new(),
+        }
+    }
+
+    /// Handles the next `bytes` of the input, returning what was found in them. `lookup` finds
+    /// an element of the schema by its encoded id.
+    ///
+    /// Once this has failed, the parser shouldn't be fed any more.
+    pub fn feed<F>(&mut self, bytes: &[u8], lookup: F) -> Result<Vec<PushEvent>, ReadError>
+        where F: Fn(u32) -> Option<PushElement>
+    {
+        self.pending.extend_from_slice(bytes);
+        let mut events = Vec::new();
+        let mut used = 0;
+        let result = self.handle(&mut used, &lookup, &mut events);
+        self.pending.drain(..used);
+        result.map(|()| events).map_err(|err| self.locate(err))
+    }
+
+    /// Ends the input, returning the ends of the containers of unknown size which were still
+    /// open. Fails if the input ended in the middle of an element.
+    pub fn finish(&mut self) -> Result<Vec<PushEvent>, ReadError> {
+        let ended = match self.state {
+            State::Header => self.pending.is_empty(),
+            _ => false,
+        };
+        if !ended || self.open.iter().any(|open| open.end.is_some()) {
+            return Err(self.locate(ReadError::new(ReadErrorKind::UnexpectedEof)));
+        }
+        let mut events = Vec::new();
+        while !self.open.is_empty() {
+            self.close(&mut events);
+        }
+        Ok(events)
+    }
+
+    // Handles as much of what's pending as possible, counting the bytes handled in `used`.
+    fn handle<F>(&mut self, used: &mut usize, lookup: &F, events: &mut Vec<PushEvent>)
+        -> Result<(), ReadError>
+        where F: Fn(u32) -> Option<PushElement>
+    {
+        loop {
+            if let State::Header = self.state {
+                self.close_ended(events);
+            }
+            let available = &self.pending[*used..];
+            match self.state {
+                State::Header => {
+                    let len = match header_len(available) {
+                        Some(len) => len,
+                        None => return Ok(()),
+                    };
+                    let mut header = &available[..len];
+                    let (encoded, _) = read_encoded_id(&mut header)?;
+                    let (size, _) = read_size_or_unknown(&mut header)?;
+                    *used += len;
+                    self.offset += len as u64;
+                    self.begin(encoded, size, lookup, events)?;
+                }
+                State::Body { id, name, kind, size } => {
+                    if (available.len() as u64) < size {
+                        return Ok(());
+                    }
+                    let body = &available[..size as usize];
+                    let value = decode(kind, body).map_err(|err| match name {
+                        Some(name) => err.within(name),
+                        None => err,
+                    })?;
+                    events.push(PushEvent::Value(id, value));
+                    *used += size as usize;
+                    self.offset += size;
+                    self.state = State::Header;
+                }
+                State::Skip { left } => {
+                    if left > 0 && available.is_empty() {
+                        return Ok(());
+                    }
+                    let skipped = left.min(available.len() as u64);
+                    *used += skipped as usize;
+                    self.offset += skipped;
+                    self.state = if skipped == left {
+                        State::Header
+                    } else {
+                        State::Skip { left: left - skipped }
+                    };
+                }
+            }
+        }
+    }
+
+    // Starts an element, whose header has just been handled.
+    fn begin<F>(&mut self, encoded: u32, size: Option<u64>, lookup: &F,
+                events: &mut Vec<PushEvent>)
+        -> Result<(), ReadError>
+        where F: Fn(u32) -> Option<PushElement>
+    {
+        let id = decode_id(encoded)?;
+        let element = lookup(encoded);
+
+        // Containers of unknown size end at the first element which can't be inside them.
+        while let Some(open) = self.open.last() {
+            let inside = is_void(id) || is_crc32(id) ||
+                element.is_some_and(|element| element.parents.contains(&open.encoded));
+            if open.end.is_some() || inside {
+                break;
+            }
+            self.close(events);
+        }
+
+        // Nothing may extend past the end of the innermost container with a known size.
+        let limit = self.open.iter().rev().filter_map(|open| open.end).next();
+        if let (Some(limit), Some(size)) = (limit, size) {
+            if self.offset + size > limit {
+                return Err(ReadError::new(ReadErrorKind::Overrun));
+            }
+        }
+        let count = self.open.last_mut().map(|open| &mut open.children);
+        check_child(&self.ctx, count, size.unwrap_or(0))?;
+
+        let element = match element {
+            Some(element) => element,
+            None => {
+                let size = size.ok_or_else(|| ReadError::new(ReadErrorKind::UnknownSize))?;
+                self.state = if is_void(id) || is_crc32(id) {
+                    State::Skip { left: size }
+                } else {
+                    match self.ctx.options().unknown_elements.unwrap_or(self.default) {
+                        UnknownElements::Error => {
+                            return Err(ReadError::new(ReadErrorKind::UnknownElement(id)))
+                        }
+                        UnknownElements::Skip => State::Skip { left: size },
+                        UnknownElements::Collect => {
+                            State::Body { id, name: None, kind: ElementKind::Binary, size }
+                        }
+                    }
+                };
+                return Ok(());
+            }
+        };
+
+        if size.is_none() && !element.unknown_size {
+            return Err(ReadError::new(ReadErrorKind::UnknownSize).within(element.name));
+        }
+        match (element.kind, size) {
+            (ElementKind::Container, _) => {
+                events.push(PushEvent::Begin(id, size));
+                let end = size.map(|size| self.offset + size);
+                self.open.push(Open { id, encoded, name: element.name, end, children: 0 });
+                self.ctx.enter(element.name);
+                check_limits(&self.ctx, size)
+            }
+            // Scalars are rejected here rather than after their bodies have been buffered.
+            (kind, Some(size)) if !fits(kind, size) => {
+                Err(ReadError::new(ReadErrorKind::InvalidValue).within(element.name))
+            }
+            (kind, Some(size)) => {
+                self.state = State::Body { id, name: Some(element.name), kind, size };
+                Ok(())
+            }
+            (_, None) => Err(ReadError::new(ReadErrorKind::UnknownSize).within(element.name)),
+        }
+    }
+
+    // Ends the containers whose bodies end where the input has got to, along with those of
+    // unknown size inside them.
+    fn close_ended(&mut self, events: &mut Vec<PushEvent>) {
+        while let Some(index) = self.open.iter().rposition(|open| open.end.is_some()) {
+            if self.open[index].end != Some(self.offset) {
+                break;
+            }
+            while self.open.len() > index {
+                self.close(events);
+            }
+        }
+    }
+
+    fn close(&mut self, events: &mut Vec<PushEvent>) {
+        if let Some(open) = self.open.pop() {
+            self.ctx.leave();
+            events.push(PushEvent::End(open.id));
+        }
+    }
+
+    // Adds the containers which are open to the path of `err`.
+    fn locate(&self, err: ReadError) -> ReadError {
+        self.open.iter().rev().fold(err, |err, open| err.within(open.name))
+    }
+}
+
+// The length of the header at the start of `bytes`, or `None` if it isn't all there yet. An
+// invalid header's length is as far as it's invalid, so decoding it fails.
+fn header_len(bytes: &[u8]) -> Option<usize> {
+    let vint_len = |byte: u8| byte.leading_zeros() as usize + 1;
+    let id_len = vint_len(*bytes.first()?);
+    if id_len > 4 {
+        return Some(1);
+    }
+    let size_len = vint_len(*bytes.get(id_len)?);
+    let len = if size_len > 8 { id_len + 1 } else { id_len + size_len };
+    if bytes.len() < len {
+        None
+    } else {
+        Some(len)
+    }
+}
+
+// Whether a body of `size` bytes can hold a value of `kind`, as `decode` would find.
+fn fits(kind: ElementKind, size: u64) -> bool {
+    match kind {
+        ElementKind::Int | ElementKind::Uint => size <= 8,
+        ElementKind::Float => size == 0 || size == 4 || size == 8,
+        ElementKind::Date => size == 0 || size == 8,
+        ElementKind::String | ElementKind::Binary | ElementKind::Container => true,
+    }
+}
+
+fn decode(kind: ElementKind, body: &[u8]) -> Result<Value, ReadError> {
+    Ok(match kind {
+        ElementKind::Int => Value::Int(decode_int(body)?),
+        ElementKind::Uint => Value::Uint(decode_uint(body)?),
+        ElementKind::Float => Value::Float(decode_float(body)?),
+        ElementKind::String => Value::String(decode_string(body.to_vec())?),
+        ElementKind::Date => Value::Date(decode_date(body)?),
+        ElementKind::Binary => Value::Binary(body.to_vec()),
+        ElementKind::Container => unreachable!("containers have no value"),
+    })
+}
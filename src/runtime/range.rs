@@ -0,0 +1,90 @@
+use std::error::Error;
+use std::fmt;
+
+use super::date_from_raw;
+use {date, ClampRangeItem, Date, RangeItem};
+
+/// A value outside the range its element or type allows.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RangeError {
+    /// The value, as text.
+    pub value: String,
+    /// The range it should have been in, as written in an EDTD.
+    pub range: String,
+}
+
+impl RangeError {
+    fn new<T: fmt::Display, I: fmt::Display>(value: T, range: &[I]) -> Self {
+        RangeError {
+            value: value.to_string(),
+            range: range.iter().map(I::to_string).collect::<Vec<_>>().join(","),
+        }
+    }
+}
+
+impl fmt::Display for RangeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "value {} is outside the range {}", self.value, self.range)
+    }
+}
+
+impl Error for RangeError {}
+
+/// Checks that `value` is in `range`. An empty range allows everything.
+pub fn in_range<T, I>(value: &T, range: &[I]) -> Result<(), RangeError>
+    where T: fmt::Display, I: RangeItem<T>
+{
+    if range.is_empty() || range.iter().any(|item| item.contains(value)) {
+        Ok(())
+    } else {
+        Err(RangeError::new(value, range))
+    }
+}
+
+/// Like `in_range`, for the raw value of a `date` element.
+pub fn date_in_range<I: RangeItem<Date>>(value: i64, range: &[I])
+    -> Result<(), RangeError>
+{
+    let date = date_from_raw(value);
+    if range.is_empty() || range.iter().any(|item| item.contains(&date)) {
+        Ok(())
+    } else {
+        Err(RangeError::new(date::fields(&date).display(""), range))
+    }
+}
+
+/// Like `in_range`, for a `string` element. Every character must be in the range.
+pub fn string_in_range<I: RangeItem<char>>(value: &str, range: &[I]) -> Result<(), RangeError> {
+    if value.chars().all(|c| range.is_empty() || range.iter().any(|item| item.contains(&c))) {
+        Ok(())
+    } else {
+        Err(RangeError::new(format!("{:?}", value), range))
+    }
+}
+
+/// Like `in_range`, for a `binary` element. Every byte must be in the range.
+pub fn binary_in_range<I: RangeItem<u8>>(value: &[u8], range: &[I]) -> Result<(), RangeError> {
+    if value.iter().all(|b| range.is_empty() || range.iter().any(|item| item.contains(b))) {
+        Ok(())
+    } else {
+        Err(RangeError::new(format!("{:?}", value), range))
+    }
+}
+
+/// The value in `range` nearest to `value`, or `None` if there isn't one, like for NaN. An empty
+/// range allows everything.
+pub fn clamp<T, I: ClampRangeItem<T>>(value: T, range: &[I]) -> Option<T>
+    where T: Copy
+{
+    if range.is_empty() {
+        return Some(value);
+    }
+    range.iter()
+        .map(|item| item.nearest(value))
+        .filter(|&(_, distance)| !distance.is_nan())
+        .fold(None, |nearest: Option<(T, f64)>, (value, distance)| match nearest {
+            Some((_, best)) if best <= distance => nearest,
+            _ => Some((value, distance)),
+        })
+        .map(|(value, _)| value)
+}
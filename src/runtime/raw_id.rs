@@ -0,0 +1,21 @@
+use ebml::Id;
+
+/// The encoded id of an element, like `0x1853_8067` for Matroska's `Segment`.
+///
+/// Unlike an `ebml::Id`, this can be built in constant expressions and matched on, so generated
+/// code has one for every element in its `raw_ids` module. Those are checked to be valid ids when
+/// the code is generated; one built by hand may not be.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct RawId(pub u32);
+
+impl RawId {
+    /// The id as it's encoded, marker bits included.
+    pub const fn encoded(self) -> u32 {
+        self.0
+    }
+
+    /// The `ebml::Id` this encodes, or `None` if it isn't a valid id.
+    pub fn to_id(self) -> Option<Id> {
+        Id::from_encoded(self.0)
+    }
+}
@@ -0,0 +1,715 @@
+use std::error::Error;
+use std::fmt;
+use std::io::{self, Read};
+use std::str::Utf8Error;
+
+use ebml::Id;
+
+use super::{binary_in_range, date_in_range, in_range, string_in_range, EbmlPrimitive,
+            RangeError};
+use {Cardinality, RangeItem, SizeList};
+
+/// An error encountered while reading an EBML document with generated code.
+#[derive(Debug)]
+pub struct ReadError {
+    kind: ReadErrorKind,
+    // Outermost element first.
+    path: Vec<&'static str>,
+}
+
+/// The ways reading an EBML document can fail.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum ReadErrorKind {
+    /// The underlying reader failed.
+    Io(io::Error),
+    /// The input ended in the middle of an element.
+    UnexpectedEof,
+    /// An element id was malformed. Holds the encoded id.
+    InvalidId(u32),
+    /// An element size was malformed, or was the reserved "unknown size" value.
+    InvalidSize,
+    /// A child element extends past the end of its parent.
+    Overrun,
+    /// An element's value was malformed, or couldn't be represented by the Rust type chosen for
+    /// it.
+    InvalidValue,
+    /// A `string` element's value wasn't valid UTF-8.
+    InvalidUtf8(Utf8Error),
+    /// A mandatory child element was missing. Holds its name.
+    MissingElement(&'static str),
+    /// A child element which may only occur once occurred again. Holds its name.
+    DuplicateElement(&'static str),
+    /// A child of an `ordered:yes` element came after a child declared later than it. Holds its
+    /// name.
+    OutOfOrder(&'static str),
+    /// An element's size wasn't one of those its `size:` allows.
+    SizeNotAllowed {
+        /// The size of the element's body.
+        size: u64,
+        /// The sizes it could have had, as written in an EDTD.
+        allowed: String,
+    },
+    /// An element's value was outside the element's `range:`. Holds the value and the range, both
+    /// rendered as text.
+    OutOfRange {
+        /// The value which was read.
+        value: String,
+        /// The range it should have been in, as written in an EDTD.
+        range: String,
+    },
+    /// The CRC-32 at the start of an element didn't match the rest of its body.
+    CrcMismatch {
+        /// The CRC-32 stored in the document.
+        stored: u32,
+        /// The CRC-32 of the data it covers.
+        computed: u32,
+    },
+    /// An element the schema doesn't describe was found, and unknown elements are errors. Holds
+    /// its id.
+    UnknownElement(Id),
+    /// An element had an unknown size where that isn't allowed.
+    UnknownSize,
+    /// Elements were nested deeper than `ReadLimits::max_depth`. Holds the limit.
+    TooDeep(usize),
+    /// An element's body was larger than `ReadLimits::max_element_size`.
+    TooLarge {
+        /// The size of the element's body.
+        size: u64,
+        /// The largest size allowed.
+        limit: u64,
+    },
+    /// A container had more children than `ReadLimits::max_children`. Holds the limit.
+    TooManyChildren(usize),
+}
+
+impl ReadError {
+    /// Creates an error which didn't happen inside any particular element.
+    pub fn new(kind: ReadErrorKind) -> Self {
+        ReadError { kind, path: Vec::new() }
+    }
+
+    /// What went wrong.
+    pub fn kind(&self) -> &ReadErrorKind {
+        &self.kind
+    }
+
+    /// The names of the elements being read when the error happened, outermost first.
+    pub fn path(&self) -> &[&'static str] {
+        &self.path
+    }
+
+    /// Records that this error happened inside the element named `element`.
+    pub fn within(mut self, element: &'static str) -> Self {
+        self.path.insert(0, element);
+        self
+    }
+}
+
+impl From<io::Error> for ReadError {
+    fn from(err: io::Error) -> Self {
+        if err.kind() == io::ErrorKind::UnexpectedEof {
+            ReadError::new(ReadErrorKind::UnexpectedEof)
+        } else {
+            ReadError::new(ReadErrorKind::Io(err))
+        }
+    }
+}
+
+impl fmt::Display for ReadError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.kind {
+            ReadErrorKind::Io(ref err) => write!(f, "{}", err)?,
+            ReadErrorKind::UnexpectedEof => f.write_str("unexpected end of input")?,
+            ReadErrorKind::InvalidId(id) => write!(f, "invalid element id 0x{:X}", id)?,
+            ReadErrorKind::InvalidSize => f.write_str("invalid element size")?,
+            ReadErrorKind::Overrun => f.write_str("element extends past the end of its parent")?,
+            ReadErrorKind::InvalidValue => f.write_str("invalid element value")?,
+            ReadErrorKind::InvalidUtf8(ref err) => write!(f, "invalid string value: {}", err)?,
+            ReadErrorKind::MissingElement(name) => write!(f, "missing `{}` element", name)?,
+            ReadErrorKind::DuplicateElement(name) => {
+                write!(f, "more than one `{}` element", name)?
+            }
+            ReadErrorKind::OutOfOrder(name) => write!(f, "`{}` element is out of order", name)?,
+            ReadErrorKind::SizeNotAllowed { size, ref allowed } => {
+                write!(f, "size {} isn't one of the allowed sizes {}", size, allowed)?
+            }
+            ReadErrorKind::OutOfRange { ref value, ref range } => {
+                write!(f, "value {} is outside the range {}", value, range)?
+            }
+            ReadErrorKind::CrcMismatch { stored, computed } => write!(
+                f,
+                "stored CRC-32 {:08X} doesn't match the data's CRC-32 {:08X}",
+                stored, computed
+            )?,
+            ReadErrorKind::UnknownElement(id) => write!(f, "unknown element {:?}", id)?,
+            ReadErrorKind::UnknownSize => {
+                f.write_str("element has an unknown size, which isn't allowed here")?
+            }
+            ReadErrorKind::TooDeep(limit) => {
+                write!(f, "elements are nested more than {} deep", limit)?
+            }
+            ReadErrorKind::TooLarge { size, limit } => {
+                write!(f, "element size {} is larger than the limit of {} bytes", size, limit)?
+            }
+            ReadErrorKind::TooManyChildren(limit) => {
+                write!(f, "element has more than {} children", limit)?
+            }
+        }
+        if !self.path.is_empty() {
+            write!(f, " in {}", self.path.join("/"))?;
+        }
+        Ok(())
+    }
+}
+
+impl Error for ReadError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self.kind {
+            ReadErrorKind::Io(ref err) => Some(err),
+            ReadErrorKind::InvalidUtf8(ref err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+/// Settings controlling how strictly generated readers check what they read.
+#[derive(Debug, Clone, Default)]
+pub struct ReadOptions {
+    /// If set, values outside their element's `range:` are kept, and recorded as warnings instead
+    /// of failing the read.
+    pub lenient_ranges: bool,
+    /// If set, elements which may only occur once but occur again are recorded as warnings, and
+    /// the last occurrence is kept.
+    pub lenient_cardinality: bool,
+    /// If set, children of `ordered:yes` elements which are out of order are recorded as warnings
+    /// instead of failing the read.
+    pub lenient_order: bool,
+    /// If set, `CRC-32` elements are skipped without checking the data they cover.
+    pub lenient_crc: bool,
+    /// What to do with elements the schema doesn't describe. If unset, the policy chosen when the
+    /// readers were generated is used.
+    pub unknown_elements: Option<UnknownElements>,
+    /// How much a document may make readers do, so corrupt or malicious documents fail instead of
+    /// exhausting the stack or memory.
+    pub limits: ReadLimits,
+}
+
+/// Bounds on what generated readers will read, checked as they descend into a document.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ReadLimits {
+    /// How many containers deep elements may be nested, counting the one being read. Defaults to
+    /// 64.
+    pub max_depth: usize,
+    /// The largest body any element may have, in bytes. Defaults to 1 GiB.
+    pub max_element_size: u64,
+    /// The most children a container may have. Defaults to 2^20. Containers which streaming
+    /// readers read child by child aren't held in memory, so this doesn't apply to them.
+    pub max_children: usize,
+}
+
+impl Default for ReadLimits {
+    fn default() -> Self {
+        ReadLimits {
+            max_depth: 64,
+            max_element_size: 1 << 30,
+            max_children: 1 << 20,
+        }
+    }
+}
+
+/// What generated readers do with child elements the schema doesn't describe.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UnknownElements {
+    /// Fail the read.
+    Error,
+    /// Skip over them.
+    Skip,
+    /// Keep their ids and bodies in the container's `unknown_children` field. Containers without
+    /// that field skip them instead.
+    Collect,
+}
+
+/// State shared by generated readers over a whole read.
+#[derive(Debug, Default)]
+pub struct ReadContext {
+    options: ReadOptions,
+    warnings: Vec<ReadError>,
+    // The elements currently being read, outermost first.
+    path: Vec<&'static str>,
+}
+
+impl ReadContext {
+    /// Creates a context which reads with the given options.
+    pub fn new(options: ReadOptions) -> Self {
+        ReadContext { options, warnings: Vec::new(), path: Vec::new() }
+    }
+
+    /// The options being read with.
+    pub fn options(&self) -> &ReadOptions {
+        &self.options
+    }
+
+    /// Problems which were tolerated because of the options, in the order they were found.
+    pub fn warnings(&self) -> &[ReadError] {
+        &self.warnings
+    }
+
+    /// Takes the warnings out of this context.
+    pub fn take_warnings(&mut self) -> Vec<ReadError> {
+        ::std::mem::take(&mut self.warnings)
+    }
+
+    /// Records that the element named `element` is being entered.
+    pub fn enter(&mut self, element: &'static str) {
+        self.path.push(element);
+    }
+
+    /// Records that the innermost element being read has been left.
+    pub fn leave(&mut self) {
+        self.path.pop();
+    }
+
+    // Fails with a problem in the current element, or records it as a warning if `lenient`.
+    fn tolerate(&mut self, lenient: bool, kind: ReadErrorKind) -> Result<(), ReadError> {
+        if lenient {
+            let path = self.path.clone();
+            self.warnings.push(ReadError { kind, path });
+            Ok(())
+        } else {
+            Err(ReadError::new(kind))
+        }
+    }
+
+    // Records a problem with the child of the current element named `element`.
+    fn warn(&mut self, element: &'static str, kind: ReadErrorKind) {
+        let mut path = self.path.clone();
+        path.push(element);
+        self.warnings.push(ReadError { kind, path });
+    }
+}
+
+/// Checks that the element just entered with `ReadContext::enter` isn't nested too deeply, and
+/// that its body of `size` bytes isn't too large. The size of an element whose size is unknown
+/// isn't checked.
+pub fn check_limits(ctx: &ReadContext, size: Option<u64>) -> Result<(), ReadError> {
+    let limits = &ctx.options.limits;
+    if ctx.path.len() > limits.max_depth {
+        return Err(ReadError::new(ReadErrorKind::TooDeep(limits.max_depth)));
+    }
+    match size {
+        Some(size) if size > limits.max_element_size => {
+            Err(ReadError::new(ReadErrorKind::TooLarge { size, limit: limits.max_element_size }))
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Checks that a child with a body of `size` bytes isn't too large, and, if `count` is given,
+/// that it isn't one too many. `count` is the number of children read so far, and is
+/// incremented. Generated readers call this for every child they come across.
+pub fn check_child(ctx: &ReadContext, count: Option<&mut usize>, size: u64)
+    -> Result<(), ReadError>
+{
+    let limits = &ctx.options.limits;
+    if let Some(count) = count {
+        *count += 1;
+        if *count > limits.max_children {
+            return Err(ReadError::new(ReadErrorKind::TooManyChildren(limits.max_children)));
+        }
+    }
+    if size > limits.max_element_size {
+        let limit = limits.max_element_size;
+        return Err(ReadError::new(ReadErrorKind::TooLarge { size, limit }));
+    }
+    Ok(())
+}
+
+// Fails with an OutOfRange error, or records it as a warning if ranges are lenient.
+fn tolerate_range(ctx: &mut ReadContext, element: &'static str, result: Result<(), RangeError>)
+    -> Result<(), ReadError>
+{
+    let RangeError { value, range } = match result {
+        Ok(()) => return Ok(()),
+        Err(err) => err,
+    };
+    let kind = ReadErrorKind::OutOfRange { value, range };
+    if ctx.options.lenient_ranges {
+        ctx.warn(element, kind);
+        Ok(())
+    } else {
+        Err(ReadError::new(kind))
+    }
+}
+
+/// Checks that the element named `element` may occur `count` times in the current element.
+///
+/// Generated readers call this as each element which may only occur once is found, and for each
+/// mandatory element once everything has been read.
+pub fn check_count(ctx: &mut ReadContext, element: &'static str, cardinality: &Cardinality,
+                   count: usize)
+    -> Result<(), ReadError>
+{
+    if cardinality.allows(count) {
+        Ok(())
+    } else if count == 0 {
+        Err(ReadError::new(ReadErrorKind::MissingElement(element)))
+    } else {
+        let lenient = ctx.options.lenient_cardinality;
+        ctx.tolerate(lenient, ReadErrorKind::DuplicateElement(element))
+    }
+}
+
+/// Checks that a child of an `ordered:yes` element isn't out of order. `index` is the position of
+/// the child's declaration, and `latest` the greatest index seen so far in the current element.
+pub fn check_order(ctx: &mut ReadContext, element: &'static str, latest: &mut usize, index: usize)
+    -> Result<(), ReadError>
+{
+    if index >= *latest {
+        *latest = index;
+        Ok(())
+    } else {
+        let lenient = ctx.options.lenient_order;
+        ctx.tolerate(lenient, ReadErrorKind::OutOfOrder(element))
+    }
+}
+
+/// Checks that an element's body is one of the sizes in `sizes`. This is done before reading the
+/// body, so corrupt sizes are caught before anything is allocated for them.
+pub fn check_size(size: u64, sizes: &SizeList) -> Result<(), ReadError> {
+    if sizes.matches(size) {
+        Ok(())
+    } else {
+        Err(ReadError::new(ReadErrorKind::SizeNotAllowed { size, allowed: sizes.to_string() }))
+    }
+}
+
+/// Checks that a numeric `value` of the element named `element` is in `range`, passing it
+/// through if it is. An empty range allows anything.
+pub fn check_range<T, I>(ctx: &mut ReadContext, element: &'static str, value: T, range: &[I])
+    -> Result<T, ReadError>
+    where T: fmt::Display, I: RangeItem<T>
+{
+    tolerate_range(ctx, element, in_range(&value, range))?;
+    Ok(value)
+}
+
+/// Like `check_range`, for the raw value of a `date` element.
+pub fn check_date_range<I>(ctx: &mut ReadContext, element: &'static str, value: i64, range: &[I])
+    -> Result<i64, ReadError>
+    where I: RangeItem<::Date>
+{
+    tolerate_range(ctx, element, date_in_range(value, range))?;
+    Ok(value)
+}
+
+/// Like `check_range`, for a `string` element, owned or borrowed. Every character must be in the
+/// range.
+pub fn check_string_range<S, I>(ctx: &mut ReadContext, element: &'static str, value: S,
+                                range: &[I])
+    -> Result<S, ReadError>
+    where S: AsRef<str>, I: RangeItem<char>
+{
+    tolerate_range(ctx, element, string_in_range(value.as_ref(), range))?;
+    Ok(value)
+}
+
+/// Like `check_range`, for a `binary` element, owned or borrowed. Every byte must be in the
+/// range.
+pub fn check_binary_range<B, I>(ctx: &mut ReadContext, element: &'static str, value: B,
+                                range: &[I])
+    -> Result<B, ReadError>
+    where B: AsRef<[u8]>, I: RangeItem<u8>
+{
+    tolerate_range(ctx, element, binary_in_range(value.as_ref(), range))?;
+    Ok(value)
+}
+
+// Reads a variable length integer, returning it with its marker bit still set, and its length.
+fn read_vint<R: Read>(r: &mut R, max_len: u32) -> Result<(u64, u64), ReadError> {
+    let mut first = [0];
+    r.read_exact(&mut first)?;
+
+    let len = first[0].leading_zeros() + 1;
+    if len > max_len {
+        return Err(ReadError::new(ReadErrorKind::InvalidSize));
+    }
+
+    let mut value = u64::from(first[0]);
+    let mut rest = [0; 7];
+    r.read_exact(&mut rest[..len as usize - 1])?;
+    for &byte in &rest[..len as usize - 1] {
+        value = value << 8 | u64::from(byte);
+    }
+    Ok((value, u64::from(len)))
+}
+
+/// Reads an element id, returning it and the number of bytes it took up.
+pub fn read_id<R: Read>(r: &mut R) -> Result<(Id, u64), ReadError> {
+    let (encoded, len) = read_encoded_id(r)?;
+    decode_id(encoded).map(|id| (id, len))
+}
+
+/// The id which is encoded as `encoded`, or an error if that isn't a valid id.
+pub fn decode_id(encoded: u32) -> Result<Id, ReadError> {
+    Id::from_encoded(encoded).ok_or_else(|| ReadError::new(ReadErrorKind::InvalidId(encoded)))
+}
+
+/// Like `read_id`, but returns the id as it's encoded, like the constants in generated `ids`
+/// modules. The id isn't checked beyond its length.
+pub fn read_encoded_id<R: Read>(r: &mut R) -> Result<(u32, u64), ReadError> {
+    let (encoded, len) = read_vint(r, 4)
+        .map_err(|err| match err.kind {
+            ReadErrorKind::InvalidSize => ReadError::new(ReadErrorKind::InvalidId(0)),
+            _ => err,
+        })?;
+    Ok((encoded as u32, len))
+}
+
+/// Reads an element size, returning it and the number of bytes it took up. Unknown sizes aren't
+/// supported.
+pub fn read_size<R: Read>(r: &mut R) -> Result<(u64, u64), ReadError> {
+    match read_size_or_unknown(r)? {
+        (Some(size), len) => Ok((size, len)),
+        (None, _) => Err(ReadError::new(ReadErrorKind::InvalidSize)),
+    }
+}
+
+/// Like `read_size`, but returns `None` for an unknown size.
+pub fn read_size_or_unknown<R: Read>(r: &mut R) -> Result<(Option<u64>, u64), ReadError> {
+    let (encoded, len) = read_vint(r, 8)?;
+    let value_bits = 7 * len;
+    let value = encoded & ((1 << value_bits) - 1);
+    if value == (1 << value_bits) - 1 {
+        Ok((None, len))
+    } else {
+        Ok((Some(value), len))
+    }
+}
+
+/// Reads the id and size at the start of an element, returning them and the number of bytes they
+/// took up.
+pub fn read_header<R: Read>(r: &mut R) -> Result<(Id, u64, u64), ReadError> {
+    let (id, id_len) = read_id(r)?;
+    let (size, size_len) = read_size(r)?;
+    Ok((id, size, id_len + size_len))
+}
+
+/// Like `read_header`, but returns `None` if the input ends before the element starts.
+pub fn read_header_or_eof<R: Read>(r: &mut R) -> Result<Option<(Id, u64, u64)>, ReadError> {
+    let mut first = [0];
+    if r.read(&mut first)? == 0 {
+        return Ok(None);
+    }
+    read_header(&mut (&first[..]).chain(r)).map(Some)
+}
+
+/// Like `read_header`, but returns `None` for an unknown size.
+pub fn read_child_header<R: Read>(r: &mut R) -> Result<(Id, Option<u64>, u64), ReadError> {
+    let (id, id_len) = read_id(r)?;
+    let (size, size_len) = read_size_or_unknown(r)?;
+    Ok((id, size, id_len + size_len))
+}
+
+/// Like `read_child_header`, but returns `None` if the input ends before the element starts.
+pub fn read_child_header_or_eof<R: Read>(r: &mut R)
+    -> Result<Option<(Id, Option<u64>, u64)>, ReadError>
+{
+    let mut first = [0];
+    if r.read(&mut first)? == 0 {
+        return Ok(None);
+    }
+    read_child_header(&mut (&first[..]).chain(r)).map(Some)
+}
+
+/// Like `read_child_header`, but returns the id as it's encoded, so generated readers can match it
+/// against the constants in their `ids` modules. The id is still checked.
+pub fn read_encoded_child_header<R: Read>(r: &mut R)
+    -> Result<(u32, Option<u64>, u64), ReadError>
+{
+    let (encoded, id_len) = read_encoded_id(r)?;
+    decode_id(encoded)?;
+    let (size, size_len) = read_size_or_unknown(r)?;
+    Ok((encoded, size, id_len + size_len))
+}
+
+/// Like `read_encoded_child_header`, but returns `None` if the input ends before the element
+/// starts.
+pub fn read_encoded_child_header_or_eof<R: Read>(r: &mut R)
+    -> Result<Option<(u32, Option<u64>, u64)>, ReadError>
+{
+    let mut first = [0];
+    if r.read(&mut first)? == 0 {
+        return Ok(None);
+    }
+    read_encoded_child_header(&mut (&first[..]).chain(r)).map(Some)
+}
+
+/// The size to give a child element whose size is unknown, if it's `allowed`. The child is taken
+/// to extend to the end of its parent, which has `remaining` bytes left including the child's
+/// header of `header_size` bytes.
+pub fn unknown_size(allowed: bool, remaining: u64, header_size: u64) -> Result<u64, ReadError> {
+    match remaining.checked_sub(header_size) {
+        Some(size) if allowed => Ok(size),
+        Some(_) => Err(ReadError::new(ReadErrorKind::UnknownSize)),
+        None => Err(ReadError::new(ReadErrorKind::Overrun)),
+    }
+}
+
+/// Handles a child element the schema doesn't describe, with id `id` and a body of `size` bytes,
+/// according to the options or else the `default` policy. Returns its body if it should be
+/// collected.
+pub fn read_unknown<R: Read>(r: &mut R, ctx: &ReadContext, default: UnknownElements, id: Id,
+                             size: u64)
+    -> Result<Option<Vec<u8>>, ReadError>
+{
+    match ctx.options.unknown_elements.unwrap_or(default) {
+        UnknownElements::Error => Err(ReadError::new(ReadErrorKind::UnknownElement(id))),
+        UnknownElements::Skip => skip(r, size).map(|()| None),
+        UnknownElements::Collect => read_bytes(r, size).map(Some),
+    }
+}
+
+/// Like `read_unknown`, for a child whose body is already in memory. Returns the body if it
+/// should be collected.
+pub fn borrow_unknown<'a>(body: &'a [u8], ctx: &ReadContext, default: UnknownElements, id: Id)
+    -> Result<Option<&'a [u8]>, ReadError>
+{
+    match ctx.options.unknown_elements.unwrap_or(default) {
+        UnknownElements::Error => Err(ReadError::new(ReadErrorKind::UnknownElement(id))),
+        UnknownElements::Skip => Ok(None),
+        UnknownElements::Collect => Ok(Some(body)),
+    }
+}
+
+/// Takes the body of a child element, `size` bytes long, off the front of `r`, the rest of its
+/// parent's body. Fails with `Overrun` if the parent isn't that long.
+pub fn take_body<'a>(r: &mut &'a [u8], size: u64) -> Result<&'a [u8], ReadError> {
+    if size > r.len() as u64 {
+        return Err(ReadError::new(ReadErrorKind::Overrun));
+    }
+    let (body, rest) = r.split_at(size as usize);
+    *r = rest;
+    Ok(body)
+}
+
+/// Skips over the body of an element.
+pub fn skip<R: Read>(r: &mut R, size: u64) -> Result<(), ReadError> {
+    let skipped = io::copy(&mut r.by_ref().take(size), &mut io::sink())?;
+    if skipped == size {
+        Ok(())
+    } else {
+        Err(ReadError::new(ReadErrorKind::UnexpectedEof))
+    }
+}
+
+fn read_bytes<R: Read>(r: &mut R, size: u64) -> Result<Vec<u8>, ReadError> {
+    // Don't trust the size enough to allocate it all up front.
+    let mut bytes = Vec::new();
+    r.by_ref().take(size).read_to_end(&mut bytes)?;
+    if bytes.len() as u64 == size {
+        Ok(bytes)
+    } else {
+        Err(ReadError::new(ReadErrorKind::UnexpectedEof))
+    }
+}
+
+/// Reads the body of a `uint` element.
+pub fn read_uint<R: Read>(r: &mut R, size: u64) -> Result<u64, ReadError> {
+    decode_uint(&read_scalar(r, size)?)
+}
+
+/// Reads the body of an `int` element.
+pub fn read_int<R: Read>(r: &mut R, size: u64) -> Result<i64, ReadError> {
+    decode_int(&read_scalar(r, size)?)
+}
+
+/// Reads the body of a `float` element, which must be 0, 4 or 8 bytes long.
+pub fn read_float<R: Read>(r: &mut R, size: u64) -> Result<f64, ReadError> {
+    decode_float(&read_scalar(r, size)?)
+}
+
+/// Reads the body of a `string` element. Trailing zero bytes are padding, and are removed.
+pub fn read_string<R: Read>(r: &mut R, size: u64) -> Result<String, ReadError> {
+    decode_string(read_bytes(r, size)?)
+}
+
+/// Like `read_string`, but borrows the string from the body instead of copying it.
+pub fn borrow_string(body: &[u8]) -> Result<&str, ReadError> {
+    ::std::str::from_utf8(&body[..unpadded_len(body)])
+        .map_err(|err| ReadError::new(ReadErrorKind::InvalidUtf8(err)))
+}
+
+/// Reads the body of a `binary` element.
+pub fn read_binary<R: Read>(r: &mut R, size: u64) -> Result<Vec<u8>, ReadError> {
+    read_bytes(r, size)
+}
+
+/// Reads the body of a `date` element, which must be 0 or 8 bytes long.
+pub fn read_date<R: Read>(r: &mut R, size: u64) -> Result<i64, ReadError> {
+    decode_date(&read_scalar(r, size)?)
+}
+
+// Reads the body of a numeric element, which can't be more than 8 bytes long.
+fn read_scalar<R: Read>(r: &mut R, size: u64) -> Result<Vec<u8>, ReadError> {
+    if size > 8 {
+        return Err(ReadError::new(ReadErrorKind::InvalidValue));
+    }
+    read_bytes(r, size)
+}
+
+/// Decodes the body of a `uint` element. The readers of every kind share this, and the other
+/// `decode_*` functions.
+pub fn decode_uint(body: &[u8]) -> Result<u64, ReadError> {
+    if body.len() > 8 {
+        return Err(ReadError::new(ReadErrorKind::InvalidValue));
+    }
+    Ok(body.iter().fold(0, |value, &byte| value << 8 | u64::from(byte)))
+}
+
+/// Decodes the body of an `int` element.
+pub fn decode_int(body: &[u8]) -> Result<i64, ReadError> {
+    let value = decode_uint(body)?;
+    if body.is_empty() {
+        return Ok(0);
+    }
+    // Sign-extend from however many bytes were read.
+    let unused_bits = 64 - 8 * body.len();
+    Ok((value << unused_bits) as i64 >> unused_bits)
+}
+
+/// Decodes the body of a `float` element, which must be 0, 4 or 8 bytes long.
+pub fn decode_float(body: &[u8]) -> Result<f64, ReadError> {
+    match body.len() {
+        0 => Ok(0.0),
+        4 => Ok(f64::from(f32::from_bits(decode_uint(body)? as u32))),
+        8 => Ok(f64::from_bits(decode_uint(body)?)),
+        _ => Err(ReadError::new(ReadErrorKind::InvalidValue)),
+    }
+}
+
+/// Decodes the body of a `string` element, removing its padding.
+pub fn decode_string(mut body: Vec<u8>) -> Result<String, ReadError> {
+    let len = unpadded_len(&body);
+    body.truncate(len);
+    String::from_utf8(body)
+        .map_err(|err| ReadError::new(ReadErrorKind::InvalidUtf8(err.utf8_error())))
+}
+
+/// Decodes the body of a `date` element, which must be 0 or 8 bytes long.
+pub fn decode_date(body: &[u8]) -> Result<i64, ReadError> {
+    match body.len() {
+        0 | 8 => decode_int(body),
+        _ => Err(ReadError::new(ReadErrorKind::InvalidValue)),
+    }
+}
+
+// The length of a string's body without the zero bytes padding it.
+fn unpadded_len(body: &[u8]) -> usize {
+    body.iter().rposition(|&byte| byte != 0).map_or(0, |idx| idx + 1)
+}
+
+/// Converts a value which was just read into the type chosen for it.
+pub fn convert<Raw, T: EbmlPrimitive<Raw>>(raw: Raw) -> Result<T, ReadError> {
+    T::from_raw(raw).ok_or_else(|| ReadError::new(ReadErrorKind::InvalidValue))
+}
@@ -0,0 +1,92 @@
+use std::borrow::Cow;
+
+use Cardinality;
+
+/// A schema as generated `schema` functions describe it, so tools can work with any generated
+/// module without parsing its EDTD.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SchemaDescriptor<'a> {
+    /// The value of the `DocType` header statement, if there is one.
+    pub doc_type: Option<&'a str>,
+    /// Every element, depth-first in declaration order.
+    pub elements: Cow<'a, [ElementDescriptor<'a>]>,
+}
+
+impl<'a> SchemaDescriptor<'a> {
+    /// The element with the given encoded id.
+    pub fn element(&self, id: u32) -> Option<&ElementDescriptor<'a>> {
+        self.elements.iter().find(|element| element.id == id)
+    }
+
+    /// The element with the given name.
+    pub fn element_named(&self, name: &str) -> Option<&ElementDescriptor<'a>> {
+        self.elements.iter().find(|element| element.name == name)
+    }
+}
+
+/// One element of a `SchemaDescriptor`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ElementDescriptor<'a> {
+    /// The element's name.
+    pub name: &'a str,
+    /// The element's id in its encoded form, including the length marker bits.
+    pub id: u32,
+    /// The element's type, with user-defined types resolved to the built-in type they're based
+    /// on.
+    pub type_: ElementType,
+    /// How many times the element may occur in its parent.
+    pub cardinality: Cardinality,
+    /// The element's `level:` property, if it has one.
+    pub level: Option<LevelRange>,
+    /// The elements it may appear in: the one it's defined inside of, or else those its
+    /// `parent:` property names. Empty for top-level elements.
+    pub parents: Cow<'a, [&'a str]>,
+    /// The element's default, if it has one. Dates too far from the epoch to encode are left
+    /// without one.
+    pub default: Option<DefaultValue<'a>>,
+}
+
+/// The built-in type of an element.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ElementType {
+    /// `int`.
+    Int,
+    /// `uint`.
+    Uint,
+    /// `float`.
+    Float,
+    /// `string`.
+    String,
+    /// `date`.
+    Date,
+    /// `binary`.
+    Binary,
+    /// `container`.
+    Container,
+}
+
+/// The levels an element may occur at: the value of its `level:` property.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LevelRange {
+    /// The lowest level.
+    pub start: u64,
+    /// The highest level, or `None` if it may be nested arbitrarily deep.
+    pub end: Option<u64>,
+}
+
+/// The default of an element, in the form the `defaults` module of generated code has it.
+#[derive(Debug, Clone, PartialEq)]
+pub enum DefaultValue<'a> {
+    /// The default of an `int`.
+    Int(i64),
+    /// The default of a `uint`.
+    Uint(u64),
+    /// The default of a `float`.
+    Float(f64),
+    /// The default of a `date`, in nanoseconds since 2001-01-01T00:00:00.
+    Date(i64),
+    /// The default of a `string`.
+    String(&'a str),
+    /// The default of a `binary`.
+    Binary(&'a [u8]),
+}
@@ -0,0 +1,148 @@
+use super::*;
+
+#[cfg(feature = "chrono")]
+use chrono::NaiveDate;
+use ebml::Id;
+
+#[cfg(feature = "chrono")]
+fn date(y: i32, m: u32, d: u32, h: u32, min: u32, s: u32) -> NaiveDateTime {
+    NaiveDate::from_ymd_opt(y, m, d).and_then(|date| date.and_hms_opt(h, min, s)).unwrap()
+}
+
+#[test]
+fn test_ebml_dates() {
+    assert_eq!(EbmlDate::from_raw(-1), Some(EbmlDate(-1)));
+    assert_eq!(EbmlDate(i64::MIN).to_raw(), Some(i64::MIN));
+    assert_eq!(date_from_raw(0), date::epoch());
+}
+
+#[cfg(feature = "chrono")]
+#[test]
+fn test_dates() {
+    let raw = 86_401_000_000_000;
+    assert_eq!(NaiveDateTime::from_raw(raw), Some(date(2001, 1, 2, 0, 0, 1)));
+    assert_eq!(date(2001, 1, 2, 0, 0, 1).to_raw(), Some(raw));
+
+    assert_eq!(date(2000, 12, 31, 23, 59, 59).to_raw(), Some(-1_000_000_000));
+    assert_eq!(date(2500, 1, 1, 0, 0, 0).to_raw(), None);
+}
+
+#[test]
+fn test_narrow_integers() {
+    assert_eq!(u8::from_raw(255u64), Some(255));
+    assert_eq!(u8::from_raw(256u64), None);
+    assert_eq!(i16::from_raw(-32_768i64), Some(-32_768));
+    assert_eq!(i16::from_raw(-32_769i64), None);
+    assert_eq!(bool::from_raw(1u64), Some(true));
+    assert_eq!(bool::from_raw(2u64), None);
+    assert_eq!(true.to_raw(), Some(1u64));
+}
+
+#[test]
+fn test_read_primitives() {
+    assert_eq!(read_int(&mut &[0xFF, 0x7F][..], 2).unwrap(), -129);
+    assert_eq!(read_int(&mut &[0x7F][..], 1).unwrap(), 127);
+    assert_eq!(read_int(&mut &[][..], 0).unwrap(), 0);
+    assert_eq!(read_uint(&mut &[0x01, 0x00][..], 2).unwrap(), 256);
+    assert_eq!(read_float(&mut &[0x3F, 0xC0, 0x00, 0x00][..], 4).unwrap(), 1.5);
+    assert_eq!(read_string(&mut &b"abc\0\0"[..], 5).unwrap(), "abc");
+    assert!(read_float(&mut &[0; 3][..], 3).is_err());
+
+    assert_eq!(read_size(&mut &[0x81][..]).unwrap(), (1, 1));
+    assert_eq!(read_size(&mut &[0x40, 0x02][..]).unwrap(), (2, 2));
+    assert!(read_size(&mut &[0xFF][..]).is_err());
+    assert!(read_size(&mut &[0x00][..]).is_err());
+
+    let (id, len) = read_id(&mut &[0x1A, 0x45, 0xDF, 0xA3][..]).unwrap();
+    assert_eq!((id, len), (Id::from_encoded(0x1A45_DFA3).unwrap(), 4));
+}
+
+#[test]
+fn test_write_primitives() {
+    assert_eq!(int_body(&-129i64).unwrap(), vec![0xFF, 0x7F]);
+    assert_eq!(int_body(&127i64).unwrap(), vec![0x7F]);
+    assert_eq!(int_body(&128i64).unwrap(), vec![0x00, 0x80]);
+    assert_eq!(uint_body(&0u64).unwrap(), vec![0x00]);
+    assert_eq!(uint_body(&256u64).unwrap(), vec![0x01, 0x00]);
+    assert_eq!(float_body(&1.5f64, 4).unwrap(), vec![0x3F, 0xC0, 0x00, 0x00]);
+    assert!(float_body(&1.5f64, 3).is_err());
+
+    // 127 is reserved in a one byte size, so it needs two.
+    let mut out = Vec::new();
+    assert_eq!(write_header(&mut out, 0xEC, 127).unwrap(), 3);
+    assert_eq!(out, vec![0xEC, 0x40, 0x7F]);
+    assert_eq!(element_len(0x1A45_DFA3, 126), 4 + 1 + 126);
+}
+
+#[test]
+fn test_crc32() {
+    assert_eq!(crc32(b""), 0);
+    assert_eq!(crc32(b"123456789"), 0xCBF4_3926);
+
+    let mut out = Vec::new();
+    assert_eq!(write_with_crc32(&mut out, 0x4001, &[0x81, 0x00]).unwrap(), 3 + 6 + 2);
+    assert_eq!(out[..5], [0x40, 0x01, 0x88, 0xBF, 0x84]);
+    assert_eq!(out[5..9], crc32(&[0x81, 0x00]).to_le_bytes());
+}
+
+#[test]
+fn test_find_path() {
+    use std::io::{Cursor, Seek};
+
+    // 0x4001 { 0x81 [0], 0x4002 { 0x82 [7] } } followed by 0x83 [1].
+    let bytes = [0x40, 0x01, 0x89, 0x81, 0x81, 0x00, 0x40, 0x02, 0x83, 0x82, 0x81, 0x07,
+                 0x83, 0x81, 0x01];
+    let mut r = Cursor::new(&bytes[..]);
+    assert_eq!(find_path(&mut r, &[0x4001, 0x4002, 0x82], None).unwrap(), Some(1));
+    assert_eq!(r.stream_position().unwrap(), 11);
+
+    let mut r = Cursor::new(&bytes[..]);
+    assert_eq!(find_path(&mut r, &[0x83], None).unwrap(), Some(1));
+    assert_eq!(r.stream_position().unwrap(), 14);
+
+    let mut r = Cursor::new(&bytes[..]);
+    assert_eq!(find_path(&mut r, &[0x4001, 0x83], None).unwrap(), None);
+}
+
+#[cfg(feature = "arbitrary")]
+#[test]
+fn test_arbitrary_ranges() {
+    use proptest::strategy::ValueTree;
+    use proptest::test_runner::TestRunner;
+    use {BinaryRangeItem, SizeList, UintRangeItem};
+
+    let mut runner = TestRunner::deterministic();
+    let sizes = SizeList::from_static(&[UintRangeItem::Single(3), UintRangeItem::Single(5)]);
+    let uints = uint_strategy(&[UintRangeItem::Bounded { start: 10, end: 12 }]);
+    let strings = string_strategy(&[], Some(&sizes), Room::default());
+    let binaries = binary_strategy(&[BinaryRangeItem::Single(7)], None, Room::default());
+    for _ in 0..100 {
+        let value = uints.new_tree(&mut runner).unwrap().current();
+        assert!((10..=12).contains(&value));
+
+        let value = strings.new_tree(&mut runner).unwrap().current();
+        assert!(value.len() == 3 || value.len() == 5, "{:?}", value);
+        assert!(!value.contains('\0'));
+
+        let value = binaries.new_tree(&mut runner).unwrap().current();
+        assert!(value.iter().all(|&byte| byte == 7));
+    }
+}
+
+#[cfg(feature = "arbitrary")]
+#[test]
+fn test_arbitrary_shrinking() {
+    use proptest::strategy::ValueTree;
+    use proptest::test_runner::TestRunner;
+    use IntRangeItem;
+
+    // Values shrink towards the start of their range, and lists towards their shortest.
+    let mut runner = TestRunner::deterministic();
+    let mut tree = int_strategy(&[IntRangeItem::From { start: -5 }]).new_tree(&mut runner).unwrap();
+    while tree.simplify() {}
+    assert_eq!(tree.current(), -5);
+
+    let mut tree = vec_of(Room(4), 1, || Just(0)).new_tree(&mut runner).unwrap();
+    while tree.simplify() {}
+    assert_eq!(tree.current(), vec![0]);
+}
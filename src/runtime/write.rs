@@ -0,0 +1,228 @@
+use std::error::Error;
+use std::fmt;
+use std::io::{self, Write};
+
+use {ClampRangeItem, RangeItem};
+use super::{binary_in_range, clamp, date_in_range, in_range, string_in_range, EbmlPrimitive,
+            RangeError};
+
+/// What generated writers do with values outside their element's `range:`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RangePolicy {
+    /// Refuse to write them, failing with a `WriteRangeError`.
+    Error,
+    /// Write the nearest value in the range instead. Only numbers can be clamped; other values
+    /// are refused like with `Error`.
+    Clamp,
+    /// Write them without checking.
+    Ignore,
+}
+
+/// Options for generated writers.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct WriteOptions {
+    /// What to do with values outside their range. If unset, the policy chosen when the writers
+    /// were generated is used.
+    pub ranges: Option<RangePolicy>,
+}
+
+/// A value outside its element's range, which a generated writer refused to write. Writers fail
+/// with an `io::Error` of kind `InvalidData` holding one of these.
+#[derive(Debug, Clone, PartialEq)]
+pub struct WriteRangeError {
+    /// The name of the element.
+    pub element: &'static str,
+    /// The value and the range.
+    pub error: RangeError,
+}
+
+impl fmt::Display for WriteRangeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "can't write {}: {}", self.element, self.error)
+    }
+}
+
+impl Error for WriteRangeError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        Some(&self.error)
+    }
+}
+
+/// The raw value of `value`, failing if it has none.
+pub fn to_raw<Raw, T: EbmlPrimitive<Raw>>(value: &T) -> io::Result<Raw> {
+    value.to_raw().ok_or_else(|| {
+        io::Error::new(io::ErrorKind::InvalidData, "value can't be represented in EBML")
+    })
+}
+
+/// Checks that the raw `value` of the element named `element` is in `range` before it's written,
+/// following the policy in `options`, or `default` if they don't have one. Returns the value to
+/// write.
+pub fn write_range<T, I>(options: &WriteOptions, default: RangePolicy, element: &'static str,
+                         value: T, range: &[I])
+    -> io::Result<T>
+    where T: Copy + fmt::Display, I: ClampRangeItem<T>
+{
+    let policy = options.ranges.unwrap_or(default);
+    if policy == RangePolicy::Ignore {
+        return Ok(value);
+    }
+    match in_range(&value, range) {
+        Ok(()) => Ok(value),
+        Err(err) => match policy {
+            RangePolicy::Clamp => clamp(value, range).ok_or_else(|| refuse(element, err)),
+            _ => Err(refuse(element, err)),
+        },
+    }
+}
+
+/// Like `write_range`, for the raw value of a `date` element, which can't be clamped.
+pub fn write_date_range<I>(options: &WriteOptions, default: RangePolicy, element: &'static str,
+                           value: i64, range: &[I])
+    -> io::Result<i64>
+    where I: RangeItem<::Date>
+{
+    check_unclamped(options, default, element, || date_in_range(value, range))?;
+    Ok(value)
+}
+
+/// Like `write_range`, for a `string` element, which can't be clamped.
+pub fn write_string_range<I>(options: &WriteOptions, default: RangePolicy, element: &'static str,
+                             value: String, range: &[I])
+    -> io::Result<String>
+    where I: RangeItem<char>
+{
+    check_unclamped(options, default, element, || string_in_range(&value, range))?;
+    Ok(value)
+}
+
+/// Like `write_range`, for a `binary` element, which can't be clamped.
+pub fn write_binary_range<I>(options: &WriteOptions, default: RangePolicy, element: &'static str,
+                             value: Vec<u8>, range: &[I])
+    -> io::Result<Vec<u8>>
+    where I: RangeItem<u8>
+{
+    check_unclamped(options, default, element, || binary_in_range(&value, range))?;
+    Ok(value)
+}
+
+fn check_unclamped<F>(options: &WriteOptions, default: RangePolicy, element: &'static str,
+                      check: F)
+    -> io::Result<()>
+    where F: FnOnce() -> Result<(), RangeError>
+{
+    match options.ranges.unwrap_or(default) {
+        RangePolicy::Ignore => Ok(()),
+        RangePolicy::Error | RangePolicy::Clamp => check().map_err(|err| refuse(element, err)),
+    }
+}
+
+fn refuse(element: &'static str, error: RangeError) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, WriteRangeError { element, error })
+}
+
+// The last `len` bytes of a big-endian u64.
+fn tail(value: u64, len: u64) -> Vec<u8> {
+    let bytes: [u8; 8] = value.to_be_bytes();
+    bytes[8 - len as usize..].to_vec()
+}
+
+// The number of bytes an encoded id takes up. The length is implied by the marker bit in the
+// first byte, so this is just the number of bytes left once leading zeroes are dropped.
+fn id_len(id: u32) -> u64 {
+    u64::from(4 - id.leading_zeros() / 8)
+}
+
+// The smallest number of bytes a size can be encoded in. All ones is reserved to mean "unknown",
+// so it can't be used.
+fn size_len(size: u64) -> u64 {
+    (1..8).find(|&len| size < (1 << (7 * len)) - 1).unwrap_or(8)
+}
+
+/// The total length of an element with the given encoded id and body length.
+pub fn element_len(id: u32, body_len: u64) -> u64 {
+    id_len(id) + size_len(body_len) + body_len
+}
+
+/// The total length of an element with the given encoded id and body length, when it's written
+/// with an unknown size.
+pub fn unknown_size_element_len(id: u32, body_len: u64) -> u64 {
+    id_len(id) + 8 + body_len
+}
+
+/// Writes the id at the start of an element followed by an unknown size, returning the number of
+/// bytes written. The element ends at the first element after it which can't be one of its
+/// children, or at the end of its parent.
+pub fn write_unknown_size_header<W: Write>(w: &mut W, id: u32) -> io::Result<u64> {
+    let id_len = id_len(id);
+    w.write_all(&tail(u64::from(id), id_len))?;
+    w.write_all(&[0x01, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF])?;
+    Ok(id_len + 8)
+}
+
+/// Writes the id and size at the start of an element, returning the number of bytes written.
+pub fn write_header<W: Write>(w: &mut W, id: u32, body_len: u64) -> io::Result<u64> {
+    if body_len >= (1 << 56) - 1 {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, "element is too large"));
+    }
+
+    let id_len = id_len(id);
+    w.write_all(&tail(u64::from(id), id_len))?;
+
+    let size_len = size_len(body_len);
+    w.write_all(&tail(body_len | 1 << (7 * size_len), size_len))?;
+
+    Ok(id_len + size_len)
+}
+
+/// Writes a whole element, returning the number of bytes written.
+pub fn write_element<W: Write>(w: &mut W, id: u32, body: &[u8]) -> io::Result<u64> {
+    let header_len = write_header(w, id, body.len() as u64)?;
+    w.write_all(body)?;
+    Ok(header_len + body.len() as u64)
+}
+
+/// Encodes the body of a `uint` element in as few bytes as possible (but at least one).
+pub fn uint_body<T: EbmlPrimitive<u64>>(value: &T) -> io::Result<Vec<u8>> {
+    let value = to_raw(value)?;
+    let len = (1..8).find(|&len| value >> (8 * len) == 0).unwrap_or(8);
+    Ok(tail(value, len))
+}
+
+/// Encodes the body of an `int` element in as few bytes as possible (but at least one).
+pub fn int_body<T: EbmlPrimitive<i64>>(value: &T) -> io::Result<Vec<u8>> {
+    let value = to_raw(value)?;
+    // The value fits if sign-extending its low bytes gives it back.
+    let len = (1..8)
+        .find(|&len| {
+            let unused_bits = 64 - 8 * len;
+            value << unused_bits >> unused_bits == value
+        })
+        .unwrap_or(8);
+    Ok(tail(value as u64, len))
+}
+
+/// Encodes the body of a `float` element in `size` bytes, which must be 4 or 8.
+pub fn float_body<T: EbmlPrimitive<f64>>(value: &T, size: u64) -> io::Result<Vec<u8>> {
+    let value = to_raw(value)?;
+    match size {
+        4 => Ok(tail(u64::from((value as f32).to_bits()), 4)),
+        8 => Ok(tail(value.to_bits(), 8)),
+        _ => Err(io::Error::new(io::ErrorKind::InvalidInput, "floats are 4 or 8 bytes")),
+    }
+}
+
+/// Encodes the body of a `string` element.
+pub fn string_body<T: EbmlPrimitive<String>>(value: &T) -> io::Result<Vec<u8>> {
+    to_raw(value).map(String::into_bytes)
+}
+
+/// Encodes the body of a `binary` element.
+pub fn binary_body<T: EbmlPrimitive<Vec<u8>>>(value: &T) -> io::Result<Vec<u8>> {
+    to_raw(value)
+}
+
+/// Encodes the body of a `date` element, which is always 8 bytes.
+pub fn date_body<T: EbmlPrimitive<i64>>(value: &T) -> io::Result<Vec<u8>> {
+    to_raw(value).map(|value| tail(value as u64, 8))
+}
@@ -0,0 +1,71 @@
+//! Converts byte offsets into the source text of an EDTD into the lines and columns people read.
+
+#[cfg(test)]
+mod tests;
+
+use Span;
+
+/// Where each line of a source text starts, for finding the line and column of byte offsets into
+/// it in logarithmic time.
+///
+/// Lines and columns are one-based. Columns count characters (Unicode scalar values), not bytes,
+/// grapheme clusters or the width a terminal gives them: `é` written as `e` and a combining accent
+/// is two columns. Only `\n` ends a line, so a `\r` before it is the last character of its line.
+/// The source may be invalid UTF-8, in which case each byte that can't start a character counts as
+/// part of the one before it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SourceMap<'a> {
+    source: &'a [u8],
+    line_starts: Vec<usize>,
+}
+
+impl<'a> SourceMap<'a> {
+    /// Indexes the lines of `source`.
+    pub fn new<S: AsRef<[u8]> + ?Sized>(source: &'a S) -> Self {
+        let source = source.as_ref();
+        let line_starts = Some(0).into_iter()
+            .chain(source.iter().enumerate().filter(|&(_, &b)| b == b'\n').map(|(idx, _)| idx + 1))
+            .collect();
+        SourceMap { source, line_starts }
+    }
+
+    /// The number of lines, counting an empty one after a final newline.
+    pub fn line_count(&self) -> usize {
+        self.line_starts.len()
+    }
+
+    /// The one-based line that `offset` is on. Offsets past the end are on the last line.
+    pub fn line(&self, offset: usize) -> usize {
+        let offset = offset.min(self.source.len());
+        match self.line_starts.binary_search(&offset) {
+            Ok(idx) => idx + 1,
+            Err(idx) => idx,
+        }
+    }
+
+    /// The one-based line and column of `offset`. An offset inside a character is in the column
+    /// of that character.
+    pub fn line_column(&self, offset: usize) -> (usize, usize) {
+        let mut offset = offset.min(self.source.len());
+        let line = self.line(offset);
+        let start = self.line_starts[line - 1];
+        while offset > start && offset < self.source.len() && is_continuation(self.source[offset]) {
+            offset -= 1;
+        }
+        let column = self.source[start..offset].iter().filter(|&&b| !is_continuation(b)).count();
+        (line, column + 1)
+    }
+
+    /// The bytes of the one-based `line`, without the newline ending it.
+    pub fn line_span(&self, line: usize) -> Span {
+        assert!(line >= 1 && line <= self.line_count(), "no line {}", line);
+        let start = self.line_starts[line - 1];
+        let end = self.line_starts.get(line).map_or(self.source.len(), |&next| next - 1);
+        Span { start, end }
+    }
+}
+
+// Whether `b` continues a UTF-8 sequence rather than starting a character.
+fn is_continuation(b: u8) -> bool {
+    b & 0xC0 == 0x80
+}
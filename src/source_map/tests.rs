@@ -0,0 +1,41 @@
+use super::*;
+
+#[test]
+fn test_line_column() {
+    let map = SourceMap::new("abc\ndef\n\nx");
+    assert_eq!(map.line_count(), 4);
+    assert_eq!(map.line_column(0), (1, 1));
+    assert_eq!(map.line_column(3), (1, 4));
+    assert_eq!(map.line_column(4), (2, 1));
+    assert_eq!(map.line_column(5), (2, 2));
+    assert_eq!(map.line_column(8), (3, 1));
+    assert_eq!(map.line_column(9), (4, 1));
+    assert_eq!(map.line_column(100), (4, 2));
+    assert_eq!(map.line_span(2), Span { start: 4, end: 7 });
+    assert_eq!(map.line_span(3), Span { start: 8, end: 8 });
+    assert_eq!(map.line_span(4), Span { start: 9, end: 10 });
+
+    let map = SourceMap::new("");
+    assert_eq!(map.line_count(), 1);
+    assert_eq!(map.line_column(0), (1, 1));
+    assert_eq!(map.line_span(1), Span { start: 0, end: 0 });
+}
+
+#[test]
+fn test_multibyte() {
+    // Two characters of three bytes each, then one of four, before the `x`.
+    let source = "a\n隣町😀x\n";
+    let map = SourceMap::new(source);
+    assert_eq!(map.line_column(source.find('x').unwrap()), (2, 4));
+    assert_eq!(map.line_column(source.find('町').unwrap()), (2, 2));
+    // In the middle of `町`.
+    assert_eq!(map.line_column(6), (2, 2));
+
+    // A combining accent is a character of its own.
+    let source = "e\u{301}x";
+    assert_eq!(SourceMap::new(source).line_column(source.find('x').unwrap()), (1, 3));
+
+    // Stray continuation bytes belong to the character before them.
+    let map = SourceMap::new(&b"a\x80\x80b"[..]);
+    assert_eq!(map.line_column(3), (1, 2));
+}
@@ -0,0 +1,298 @@
+//! `proptest` strategies generating the parts of an EDTD, for property tests.
+//!
+//! What's generated is what the printer can write and the parser read back the same: elements
+//! only have built-in types, and only containers have children. Header statements avoid the values
+//! the syntax can't tell apart, as `Dtd::to_dtd_string_with` describes.
+
+use std::borrow::Cow;
+
+use proptest::collection::vec;
+use proptest::num::f64::{NEGATIVE, NORMAL, POSITIVE, SUBNORMAL, ZERO};
+use proptest::option;
+use proptest::prelude::*;
+
+use {date, BinaryRangeItem, Cardinality, Date, DateRangeItem, Dtd, Element, FloatRangeItem,
+     HeaderStatement, IntRangeItem, Level, NewType, Property, SizeList, Span, Spanned,
+     StringRangeItem, Type, UintRangeItem};
+
+// The nodes of the AST borrow their names from the input, so generated names are leaked to live
+// as long as the test.
+fn leak(text: String) -> &'static str {
+    Box::leak(text.into_boxed_str())
+}
+
+fn unspanned<T>(node: T) -> Spanned<T> {
+    Spanned::new(node, Span { start: 0, end: 0 })
+}
+
+// A name, which may be any word that doesn't start with a digit.
+pub fn name() -> impl Strategy<Value = &'static str> {
+    "[A-Za-z_][A-Za-z0-9_]{0,11}".prop_map(leak)
+}
+
+// Names starting with a capital can't be built-in type names, which are all lowercase.
+fn type_name() -> impl Strategy<Value = &'static str> {
+    "[A-Z][A-Za-z0-9_]{0,11}".prop_map(leak)
+}
+
+// A valid encoded id of any class, with the length marker bits. Each class has the values which
+// aren't reserved, and which couldn't be encoded in a shorter class.
+pub fn id() -> impl Strategy<Value = u32> {
+    prop_oneof![
+        0x81u32..0xff,
+        0x4080u32..0x7fff,
+        0x20_4000u32..0x3f_ffff,
+        0x1020_0000u32..0x1fff_ffff,
+    ]
+}
+
+// Any float but an infinity or NaN, which can't be written in an EDTD.
+pub fn any_float() -> impl Strategy<Value = f64> {
+    POSITIVE | NEGATIVE | NORMAL | SUBNORMAL | ZERO
+}
+
+// Any date which is a whole number of nanoseconds from the EBML epoch that fits in an `i64`.
+pub fn any_date() -> impl Strategy<Value = Date> {
+    any::<i64>().prop_map(date::from_nanos)
+}
+
+pub fn int_range_item() -> impl Strategy<Value = IntRangeItem> {
+    prop_oneof![
+        any::<i64>().prop_map(IntRangeItem::Single),
+        any::<i64>().prop_map(|start| IntRangeItem::From { start }),
+        any::<i64>().prop_map(|end| IntRangeItem::To { end }),
+        (any::<i64>(), any::<i64>()).prop_map(|(start, end)| IntRangeItem::Bounded { start, end }),
+    ]
+}
+
+pub fn uint_range_item() -> impl Strategy<Value = UintRangeItem> {
+    prop_oneof![
+        any::<u64>().prop_map(UintRangeItem::Single),
+        any::<u64>().prop_map(|start| UintRangeItem::From { start }),
+        (any::<u64>(), any::<u64>())
+            .prop_map(|(start, end)| UintRangeItem::Bounded { start, end }),
+    ]
+}
+
+pub fn float_range_item() -> impl Strategy<Value = FloatRangeItem> {
+    prop_oneof![
+        (any_float(), any::<bool>())
+            .prop_map(|(start, include_start)| FloatRangeItem::From { start, include_start }),
+        (any_float(), any::<bool>())
+            .prop_map(|(end, include_end)| FloatRangeItem::To { end, include_end }),
+        (any_float(), any::<bool>(), any_float(), any::<bool>()).prop_map(
+            |(start, include_start, end, include_end)| {
+                FloatRangeItem::Bounded { start, include_start, end, include_end }
+            }
+        ),
+    ]
+}
+
+pub fn date_range_item() -> impl Strategy<Value = DateRangeItem> {
+    prop_oneof![
+        any_date().prop_map(|start| DateRangeItem::From { start }),
+        any_date().prop_map(|end| DateRangeItem::To { end }),
+        (any_date(), any_date()).prop_map(|(start, end)| DateRangeItem::Bounded { start, end }),
+    ]
+}
+
+pub fn string_range_item() -> impl Strategy<Value = StringRangeItem> {
+    let code_point = || 0u32..=0x10_ffff;
+    prop_oneof![
+        code_point().prop_map(StringRangeItem::Single),
+        code_point().prop_map(|start| StringRangeItem::From { start }),
+        (code_point(), code_point())
+            .prop_map(|(start, end)| StringRangeItem::Bounded { start, end }),
+    ]
+}
+
+pub fn binary_range_item() -> impl Strategy<Value = BinaryRangeItem> {
+    prop_oneof![
+        any::<u8>().prop_map(BinaryRangeItem::Single),
+        any::<u8>().prop_map(|start| BinaryRangeItem::From { start }),
+        (any::<u8>(), any::<u8>()).prop_map(|(start, end)| BinaryRangeItem::Bounded { start, end }),
+    ]
+}
+
+// A range list, which has at least one item.
+fn items<S: Strategy>(item: S) -> impl Strategy<Value = Vec<S::Value>> {
+    vec(item, 1..4)
+}
+
+// The default and range properties a value of the built-in `type_` may have.
+fn typed(type_: Type<'static>)
+    -> (BoxedStrategy<Property<'static>>, BoxedStrategy<Property<'static>>)
+{
+    match type_ {
+        Type::Int => (
+            any::<i64>().prop_map(Property::IntDefault).boxed(),
+            items(int_range_item()).prop_map(Property::IntRange).boxed(),
+        ),
+        Type::Uint => (
+            any::<u64>().prop_map(Property::UintDefault).boxed(),
+            items(uint_range_item()).prop_map(Property::UintRange).boxed(),
+        ),
+        Type::Float => (
+            any_float().prop_map(Property::FloatDefault).boxed(),
+            items(float_range_item()).prop_map(Property::FloatRange).boxed(),
+        ),
+        Type::Date => (
+            any_date().prop_map(Property::DateDefault).boxed(),
+            items(date_range_item()).prop_map(Property::DateRange).boxed(),
+        ),
+        Type::String => (
+            any::<String>().prop_map(|value| Property::StringDefault(Cow::Owned(value))).boxed(),
+            items(string_range_item()).prop_map(Property::StringRange).boxed(),
+        ),
+        Type::Binary => (
+            any::<Vec<u8>>().prop_map(|value| Property::BinaryDefault(Cow::Owned(value))).boxed(),
+            items(binary_range_item()).prop_map(Property::BinaryRange).boxed(),
+        ),
+        Type::Container | Type::Name(_) => unreachable!(),
+    }
+}
+
+fn value_type() -> impl Strategy<Value = Type<'static>> {
+    prop_oneof![
+        Just(Type::Int),
+        Just(Type::Uint),
+        Just(Type::Float),
+        Just(Type::Date),
+        Just(Type::String),
+        Just(Type::Binary),
+    ]
+}
+
+// A header statement. `int` values are negative and `binary` ones aren't UTF-8, since others
+// read back as `uint` and `string` values.
+pub fn header_statement() -> impl Strategy<Value = HeaderStatement<'static>> {
+    prop_oneof![
+        (name(), i64::min_value()..0)
+            .prop_map(|(name, value)| HeaderStatement::Int { name, value }),
+        (name(), any::<u64>()).prop_map(|(name, value)| HeaderStatement::Uint { name, value }),
+        (name(), any_float()).prop_map(|(name, value)| HeaderStatement::Float { name, value }),
+        (name(), any_date()).prop_map(|(name, value)| HeaderStatement::Date { name, value }),
+        (name(), any::<String>()).prop_map(|(name, value)| {
+            HeaderStatement::String { name, value: Cow::Owned(value) }
+        }),
+        (name(), any::<Vec<u8>>()).prop_map(|(name, mut value)| {
+            value.insert(0, 0xff);
+            HeaderStatement::Binary { name, value: Cow::Owned(value) }
+        }),
+        (name(), name()).prop_map(|(name, value)| HeaderStatement::Named { name, value }),
+    ]
+}
+
+// A type of any of the built-in types but `container`, with or without a default and range.
+pub fn new_type() -> impl Strategy<Value = NewType<'static>> {
+    value_type().prop_flat_map(|type_| {
+        let (default, range) = typed(type_);
+        (type_name(), option::of(default), option::of(range)).prop_map(
+            move |(name, default, range)| {
+                let comments = Vec::new();
+                let mut new_type = match type_ {
+                    Type::Int => NewType::Int { name, default: None, range: None, comments },
+                    Type::Uint => NewType::Uint { name, default: None, range: None, comments },
+                    Type::Float => NewType::Float { name, default: None, range: None, comments },
+                    Type::Date => NewType::Date { name, default: None, range: None, comments },
+                    Type::String => NewType::String { name, default: None, range: None, comments },
+                    Type::Binary => NewType::Binary { name, default: None, range: None, comments },
+                    Type::Container | Type::Name(_) => unreachable!(),
+                };
+                for property in default.into_iter().chain(range) {
+                    new_type.update(property);
+                }
+                new_type
+            }
+        )
+    })
+}
+
+fn level() -> impl Strategy<Value = Level> {
+    prop_oneof![
+        any::<u64>().prop_map(|start| Level::Open { start }),
+        (any::<u64>(), any::<u64>()).prop_map(|(start, end)| Level::Bounded { start, end }),
+    ]
+}
+
+fn cardinality() -> impl Strategy<Value = Cardinality> {
+    prop_oneof![
+        Just(Cardinality::ZeroOrMany),
+        Just(Cardinality::ZeroOrOne),
+        Just(Cardinality::ExactlyOne),
+        Just(Cardinality::OneOrMany),
+    ]
+}
+
+// The properties any element may have, whatever its type.
+fn structure() -> impl Strategy<Value = Vec<Property<'static>>> {
+    (
+        option::of(vec(name(), 1..3).prop_map(Property::Parent)),
+        option::of(level().prop_map(Property::Level)),
+        option::of(cardinality().prop_map(Property::Cardinality)),
+        option::of(items(uint_range_item()).prop_map(|items| Property::Size(items.into()))),
+        option::of(any::<bool>().prop_map(Property::Ordered)),
+        option::of(any::<bool>().prop_map(Property::UnknownSizeAllowed)),
+        option::of(any::<u64>().prop_map(Property::MinVer)),
+        option::of(any::<u64>().prop_map(Property::MaxVer)),
+    ).prop_map(|(parent, level, cardinality, size, ordered, unknown_size_allowed, minver, maxver)| {
+        vec![parent, level, cardinality, size, ordered, unknown_size_allowed, minver, maxver]
+            .into_iter()
+            .flatten()
+            .collect()
+    })
+}
+
+// An element which isn't a container, with a default, range and variants of its type.
+fn value_element() -> impl Strategy<Value = Element<'static>> {
+    value_type().prop_flat_map(|type_| {
+        let (default, range) = typed(type_);
+        let variants = vec((name(), any::<u64>()), 1..4).prop_map(Property::Variants);
+        let variants = match type_ {
+            Type::Uint => option::of(variants).boxed(),
+            _ => Just(None).boxed(),
+        };
+        (name(), id(), structure(), option::of(default), option::of(range), variants).prop_map(
+            move |(name, id, properties, default, range, variants)| {
+                let mut element = Element::new(name, id, type_);
+                let values = default.into_iter().chain(range).chain(variants);
+                for property in properties.into_iter().chain(values) {
+                    element.update(unspanned(property));
+                }
+                element
+            }
+        )
+    })
+}
+
+// An element, which may be a container with up to three levels of elements nested in it.
+pub fn element() -> impl Strategy<Value = Element<'static>> {
+    value_element().prop_recursive(3, 24, 4, |children| {
+        (name(), id(), structure(), vec(children, 0..4)).prop_map(
+            |(name, id, properties, children)| {
+                let mut element = Element::new(name, id, Type::Container);
+                for property in properties {
+                    element.update(unspanned(property));
+                }
+                element.children = children.into_iter().map(unspanned).collect();
+                element
+            }
+        )
+    })
+}
+
+// An EDTD with a header, types and elements, any of which may be empty.
+pub fn dtd() -> impl Strategy<Value = Dtd<'static>> {
+    (vec(header_statement(), 0..4), vec(new_type(), 0..4), vec(element(), 0..4)).prop_map(
+        |(header, types, elements)| {
+            let header = header.into_iter().map(unspanned).collect();
+            let types = types.into_iter().map(unspanned).collect();
+            Dtd::new(header, types, elements.into_iter().map(unspanned).collect())
+        }
+    )
+}
+
+// A size list.
+pub fn size_list() -> impl Strategy<Value = SizeList> {
+    items(uint_range_item()).prop_map(SizeList::from)
+}
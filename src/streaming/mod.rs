@@ -0,0 +1,131 @@
+//! Parses an EDTD as it's read from a stream, like a pipe or a socket.
+
+use std::error::Error;
+use std::fmt;
+use std::borrow::Cow;
+use std::io::{self, Read};
+use std::str;
+
+use {latin1_comments, parse_text, parse_with, parsers, utf8, Diagnostics, Dtd, DtdError, ErrorCode,
+     ParseError, ParseOptions};
+
+// How much is read before the first attempt to parse what's arrived.
+const FIRST_ATTEMPT: usize = 64;
+
+/// The ways parsing an EDTD read from a stream can fail.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum ReadDtdError {
+    /// Reading the stream failed.
+    Io(io::Error),
+    /// The stream ended in the middle of something. The error is at its end.
+    Truncated(ParseError),
+    /// The EDTD is not syntactically valid, or failed validation, as `parse_dtd` reports.
+    Dtd(DtdError),
+}
+
+impl fmt::Display for ReadDtdError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ReadDtdError::Io(ref err) => write!(f, "failed to read the EDTD: {}", err),
+            ReadDtdError::Truncated(ref err) => write!(f, "the EDTD is truncated: {}", err),
+            ReadDtdError::Dtd(ref err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl Error for ReadDtdError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match *self {
+            ReadDtdError::Io(ref err) => Some(err),
+            ReadDtdError::Truncated(ref err) => Some(err),
+            ReadDtdError::Dtd(ref err) => Some(err),
+        }
+    }
+}
+
+/// Parses an EDTD read from `reader`, like `parse_dtd`. What's read is kept in `buffer`, which is
+/// cleared first, as the `Dtd` borrows its names and strings from it.
+///
+/// What has arrived is parsed again each time it's doubled in length, so a syntax error is
+/// reported without waiting for the rest of the stream, which may be slow or never end. Only
+/// errors which no more input could change are reported early: those before any token which
+/// could still grow, like a comment or string whose end hasn't arrived. A stream may then be
+/// reported as having a syntax error where `parse_dtd` would have found invalid UTF-8 after it.
+/// Once the stream ends, what was read is parsed as `parse_dtd` would, except that if it ended
+/// in the middle of something, that's `ReadDtdError::Truncated` rather than a syntax error.
+///
+/// If `options.lenient` is set, bytes in comments which aren't UTF-8 are read as Latin-1, and
+/// `buffer` is left holding the text they became, which is what spans are offsets into. Each run
+/// of them is an `ErrorCode::Latin1Comment` warning. This is the only entry point which does so,
+/// as the others borrow what they parse rather than keeping it.
+///
+/// The stream is read several kilobytes at a time, so wrapping `reader` in a `BufReader` gains
+/// nothing.
+pub fn parse_dtd_from_reader<'a, R: Read>(mut reader: R, buffer: &'a mut Vec<u8>,
+                                          options: &ParseOptions)
+    -> Result<Dtd<'a>, ReadDtdError>
+{
+    buffer.clear();
+    let mut chunk = [0; 8192];
+    let mut next_attempt = FIRST_ATTEMPT;
+    loop {
+        let read = match reader.read(&mut chunk) {
+            Ok(0) => break,
+            Ok(read) => read,
+            Err(ref err) if err.kind() == io::ErrorKind::Interrupted => continue,
+            Err(err) => return Err(ReadDtdError::Io(err)),
+        };
+        buffer.extend_from_slice(&chunk[..read]);
+        if buffer.len() >= next_attempt {
+            next_attempt = buffer.len() * 2;
+            if let Some(err) = settled_error(buffer, options) {
+                return Err(ReadDtdError::Dtd(DtdError::Syntax(err)));
+            }
+        }
+    }
+
+    // Leniently, bytes in comments which aren't UTF-8 are read as Latin-1, which the buffer is
+    // rewritten to hold.
+    let mut warnings = Diagnostics::new();
+    if options.lenient {
+        let (text, found) = latin1_comments(buffer)
+            .map_err(|err| ReadDtdError::Dtd(DtdError::Syntax(err)))?;
+        if let Cow::Owned(text) = text {
+            *buffer = text.into_bytes();
+        }
+        warnings = found;
+    }
+
+    let buffer: &'a Vec<u8> = buffer;
+    let result = utf8(buffer)
+        .map_err(DtdError::Syntax)
+        .and_then(|input| parse_text(input, options, warnings));
+    match result {
+        Err(DtdError::Syntax(err)) if err.code() == ErrorCode::UnexpectedEnd => {
+            Err(ReadDtdError::Truncated(err))
+        }
+        result => result.map_err(ReadDtdError::Dtd),
+    }
+}
+
+// The syntax error in what's been read of a stream so far, if there is one which reading more
+// couldn't change.
+fn settled_error(input: &[u8], options: &ParseOptions) -> Option<ParseError> {
+    // A character may have been split between reads, so the input is only parsed up to it. Bytes
+    // which are invalid whatever follows are left for the final parse to report.
+    let input = match str::from_utf8(input) {
+        Ok(input) => input,
+        Err(err) => str::from_utf8(&input[..err.valid_up_to()]).unwrap(),
+    };
+    let syntax = ParseOptions { lenient: options.lenient, ..Default::default() };
+    let (settled, _) = parse_with(input, &syntax, |state| parsers::settled_len(input, state));
+    let input = &input[..settled];
+    let (err, _) = parse_with(input, &syntax, |state| parsers::prefix_error(input, state));
+    let err = ParseError::from_failure(input, Some(&err?));
+    // What was found there must be followed by something, or it might only be part of it.
+    if err.byte_offset + err.found.len() < input.len() { Some(err) } else { None }
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,171 @@
+use std::io::{self, Read};
+
+use super::*;
+
+// Gives what it's reading from at most seven bytes at a time, counting how many it's given.
+struct Trickle<R> {
+    inner: R,
+    given: usize,
+}
+
+impl<R: Read> Read for Trickle<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let len = buf.len().min(7);
+        let read = self.inner.read(&mut buf[..len])?;
+        self.given += read;
+        Ok(read)
+    }
+}
+
+fn trickle<R: Read>(inner: R) -> Trickle<R> {
+    Trickle { inner, given: 0 }
+}
+
+// Parses `input` from a stream, checking it comes out as it does in memory.
+fn check(input: &[u8], options: &ParseOptions) {
+    let mut buffer = Vec::new();
+    let streamed = parse_dtd_from_reader(trickle(input), &mut buffer, options);
+    match (streamed, parse_dtd(input, options)) {
+        (Ok(streamed), Ok(parsed)) => assert_eq!(streamed, parsed),
+        (Err(ReadDtdError::Truncated(streamed)), Err(DtdError::Syntax(parsed))) => {
+            assert_eq!(parsed.code(), ErrorCode::UnexpectedEnd);
+            assert_eq!(streamed, parsed);
+        }
+        (Err(ReadDtdError::Dtd(DtdError::Syntax(streamed))), Err(DtdError::Syntax(parsed))) => {
+            assert_ne!(parsed.code(), ErrorCode::UnexpectedEnd);
+            assert_eq!(streamed, parsed);
+        }
+        (Err(ReadDtdError::Dtd(DtdError::Invalid(_))), Err(DtdError::Invalid(_))) => {}
+        (streamed, parsed) => panic!("streamed {:?}, parsed {:?}", streamed, parsed),
+    }
+}
+
+#[test]
+fn test_matches_parse_dtd() {
+    let validate = ParseOptions { validate: Some(::Severity::Error), ..Default::default() };
+    for input in &[
+        &include_bytes!("../../tests/dtd0")[..],
+        &include_bytes!("../../tests/dtd9")[..],
+        &include_bytes!("../../tests/invalid0")[..],
+        &include_bytes!("../../tests/syntax_error5")[..],
+        &include_bytes!("../../ebml_macros_impl/tests/schemas/matroska_full.edtd")[..],
+    ] {
+        check(input, &Default::default());
+        check(input, &validate);
+    }
+    let lenient = ParseOptions { lenient: true, ..Default::default() };
+    check(include_bytes!("../../tests/legacy0"), &lenient);
+}
+
+#[test]
+fn test_truncated() {
+    // Wherever the stream ends, it's parsed as far as it got, whether that's in a comment,
+    // string, binary literal, or between tokens.
+    let input = include_bytes!("../../tests/dtd9");
+    for len in 0..input.len() {
+        check(&input[..len], &Default::default());
+    }
+
+    let mut buffer = Vec::new();
+    match parse_dtd_from_reader(trickle(&b"define elements { A := 81 uint;"[..]), &mut buffer,
+                                &Default::default()) {
+        Err(ReadDtdError::Truncated(err)) => assert_eq!(err.byte_offset, 31),
+        result => panic!("{:?}", result),
+    }
+}
+
+#[test]
+fn test_long_tokens() {
+    // Comments and literals longer than what's read at once, with what's parsed early ending
+    // inside them.
+    let comment = format!("/*{}*/", "a*/b".replace("*/", "* /").repeat(2000));
+    let binary = "0123456789abcdef".repeat(500);
+    let input = format!(
+        "{} define elements {{ A := 81 binary [ def:0x{}; ] // {}\n B := 82 string [ def:\"{}\"; ] }}",
+        comment, binary, "x".repeat(3000), "y".repeat(5000)
+    );
+    check(input.as_bytes(), &Default::default());
+    for &len in &[1000, 2000, 9000, 12000, 16000, 20000] {
+        check(&input.as_bytes()[..len], &Default::default());
+    }
+}
+
+#[test]
+fn test_early_error() {
+    // A syntax error is reported without reading the rest of a stream which never ends.
+    let input = b"define elements { A := 81 uint [ card:x; ] }";
+    let mut reader = trickle((&input[..]).chain(io::repeat(b' ')));
+    let mut buffer = Vec::new();
+    match parse_dtd_from_reader(&mut reader, &mut buffer, &Default::default()) {
+        Err(ReadDtdError::Dtd(DtdError::Syntax(err))) => assert_eq!(err.byte_offset, 38),
+        result => panic!("{:?}", result),
+    }
+    assert!(reader.given < 4096);
+}
+
+#[test]
+fn test_io_error() {
+    struct Broken;
+    impl Read for Broken {
+        fn read(&mut self, _: &mut [u8]) -> io::Result<usize> {
+            Err(io::Error::other("broken pipe"))
+        }
+    }
+
+    let mut buffer = Vec::new();
+    let reader = (&b"define elements {"[..]).chain(Broken);
+    match parse_dtd_from_reader(reader, &mut buffer, &Default::default()) {
+        Err(ReadDtdError::Io(ref err)) => assert_eq!(err.to_string(), "broken pipe"),
+        result => panic!("{:?}", result),
+    }
+}
+
+#[test]
+fn test_latin1() {
+    let text = include_bytes!("../../tests/latin1_comment0");
+    let lenient = ParseOptions { lenient: true, ..Default::default() };
+    let mut buffer = Vec::new();
+    let dtd = parse_dtd_from_reader(trickle(&text[..]), &mut buffer, &lenient).unwrap();
+    assert_eq!(dtd.all_elements().len(), 1);
+
+    // Each byte is a warning, about the character it became.
+    let mut buffer = Vec::new();
+    let warnings = ParseOptions {
+        validate: Some(::Severity::Error),
+        warnings_as_errors: true,
+        ..lenient.clone()
+    };
+    let diagnostics = match parse_dtd_from_reader(&text[..], &mut buffer, &warnings) {
+        Err(ReadDtdError::Dtd(DtdError::Invalid(diagnostics))) => diagnostics,
+        result => panic!("{:?}", result),
+    };
+    let decoded = str::from_utf8(&buffer).unwrap();
+    assert!(decoded.starts_with("// Written by René Müller"));
+    let found: Vec<_> = diagnostics.iter()
+        .map(|d| (d.code(), &decoded[d.span().unwrap().start..d.span().unwrap().end]))
+        .collect();
+    assert_eq!(found, vec![
+        (ErrorCode::Latin1Comment, "é"), (ErrorCode::Latin1Comment, "ü"),
+        (ErrorCode::Latin1Comment, "é"),
+    ]);
+
+    // Strictly, they're an error.
+    let mut buffer = Vec::new();
+    match parse_dtd_from_reader(&text[..], &mut buffer, &Default::default()) {
+        Err(ReadDtdError::Dtd(DtdError::Syntax(err))) => {
+            assert_eq!((err.code(), err.line, err.column), (ErrorCode::InvalidUtf8, 1, 18));
+        }
+        result => panic!("{:?}", result),
+    }
+    // In a string, they're an error either way.
+    let text = include_bytes!("../../tests/latin1_string0");
+    for options in &[lenient, Default::default()] {
+        let mut buffer = Vec::new();
+        match parse_dtd_from_reader(&text[..], &mut buffer, options) {
+            Err(ReadDtdError::Dtd(DtdError::Syntax(err))) => {
+                assert_eq!((err.code(), err.line), (ErrorCode::InvalidUtf8, 3));
+            }
+            result => panic!("{:?}", result),
+        }
+    }
+}
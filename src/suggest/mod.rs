@@ -0,0 +1,71 @@
+//! Suggests which defined name an unknown one is a misspelling of, for diagnostics.
+
+use std::cmp;
+
+// The name among `candidates` which `name` is most likely a misspelling of, or `None` if none
+// is close enough to be worth suggesting.
+//
+// Names are compared without regard to case, and may be a third of their length apart in edits,
+// counting a swap of neighbouring characters as one. A candidate which only differs in case is
+// always close enough, and among equally close candidates, those whose case matches more closely
+// are preferred, then the first.
+pub fn suggest<'n, I>(name: &str, candidates: I) -> Option<&'n str>
+    where I: IntoIterator<Item = &'n str>
+{
+    let chars: Vec<char> = name.chars().collect();
+    let lower: Vec<char> = name.to_lowercase().chars().collect();
+    candidates.into_iter()
+        .filter(|&candidate| candidate != name)
+        .filter_map(|candidate| {
+            let candidate_lower: Vec<char> = candidate.to_lowercase().chars().collect();
+            let edits = distance(&lower, &candidate_lower);
+            let allowed = cmp::max(lower.len(), candidate_lower.len()) / 3;
+            if edits > allowed {
+                return None;
+            }
+            let case_edits = distance(&chars, &candidate.chars().collect::<Vec<_>>());
+            Some(((edits, case_edits), candidate))
+        })
+        .min_by_key(|&(score, _)| score)
+        .map(|(_, candidate)| candidate)
+}
+
+// `"; did you mean `X`?"`, for the name `suggest` picks among `candidates`, or nothing. Meant to
+// be appended to the message saying `name` is unknown.
+pub fn did_you_mean<'n, I>(name: &str, candidates: I) -> String
+    where I: IntoIterator<Item = &'n str>
+{
+    suggest(name, candidates)
+        .map_or_else(String::new, |suggestion| format!("; did you mean `{}`?", suggestion))
+}
+
+// How many characters must be inserted, deleted, replaced or swapped with their neighbour to turn
+// `a` into `b`, without editing any character twice.
+fn distance(a: &[char], b: &[char]) -> usize {
+    // `table[i][j]` is the distance between the first `i` characters of `a` and the first `j` of
+    // `b`.
+    let mut table = vec![vec![0; b.len() + 1]; a.len() + 1];
+    for (i, row) in table.iter_mut().enumerate() {
+        row[0] = i;
+    }
+    for (j, cell) in table[0].iter_mut().enumerate() {
+        *cell = j;
+    }
+    for i in 1..=a.len() {
+        for j in 1..=b.len() {
+            let replace = if a[i - 1] == b[j - 1] { 0 } else { 1 };
+            let mut best = cmp::min(
+                cmp::min(table[i - 1][j] + 1, table[i][j - 1] + 1),
+                table[i - 1][j - 1] + replace,
+            );
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                best = cmp::min(best, table[i - 2][j - 2] + 1);
+            }
+            table[i][j] = best;
+        }
+    }
+    table[a.len()][b.len()]
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,51 @@
+use super::*;
+
+const NAMES: &[&str] = &["Segment", "SeekHead", "Info", "TrackUID", "TrackNumber", "Void"];
+
+fn chars(text: &str) -> Vec<char> {
+    text.chars().collect()
+}
+
+#[test]
+fn test_distance() {
+    assert_eq!(distance(&chars(""), &chars("")), 0);
+    assert_eq!(distance(&chars("Info"), &chars("")), 4);
+    assert_eq!(distance(&chars("Segmnt"), &chars("Segment")), 1);
+    assert_eq!(distance(&chars("Inof"), &chars("Info")), 1);
+    assert_eq!(distance(&chars("kitten"), &chars("sitting")), 3);
+    assert_eq!(distance(&chars("ca"), &chars("abc")), 3);
+    assert_eq!(distance(&chars("Trackü"), &chars("Tracku")), 1);
+}
+
+#[test]
+fn test_suggest() {
+    let known = |name| suggest(name, NAMES.iter().cloned());
+    assert_eq!(known("Segmnet"), Some("Segment"));
+    assert_eq!(known("Inof"), Some("Info"));
+    assert_eq!(known("Vid"), Some("Void"));
+    assert_eq!(known("TrackNumbers"), Some("TrackNumber"));
+    // Case is ignored when deciding what's close enough, so this is no edits away.
+    assert_eq!(known("trackuid"), Some("TrackUID"));
+    assert_eq!(known("SEGMENT"), Some("Segment"));
+
+    // Too far from anything: short names have to be closer.
+    assert_eq!(known("Cluster"), None);
+    assert_eq!(known("Id"), None);
+    // A defined name isn't a misspelling of itself.
+    assert_eq!(known("Segment"), None);
+    assert_eq!(suggest("Info", None::<&str>), None);
+}
+
+#[test]
+fn test_suggest_ties() {
+    // Equally close without regard to case, so the one whose case matches better wins.
+    assert_eq!(suggest("trackA", vec!["TrackB", "trackC"]), Some("trackC"));
+    assert_eq!(suggest("TrackA", vec!["TrackB", "TrackC"]), Some("TrackB"));
+    assert_eq!(suggest("flag", vec!["FLAG", "Flag"]), Some("Flag"));
+}
+
+#[test]
+fn test_did_you_mean() {
+    assert_eq!(did_you_mean("Segmnet", NAMES.iter().cloned()), "; did you mean `Segment`?");
+    assert_eq!(did_you_mean("Cluster", NAMES.iter().cloned()), "");
+}
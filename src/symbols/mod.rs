@@ -0,0 +1,99 @@
+//! Interns the names of an EDTD's elements and types, and those its elements refer to, so each
+//! name is held once and can be compared as a number.
+
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+
+use {flatten, Dtd, Element, Spanned, Type};
+
+/// A name interned in a `Dtd`. Symbols of the same EDTD are equal when their names are, so they
+/// can be compared, hashed and copied without looking at the names; `Dtd::resolve` gives the name
+/// back. Symbols of different EDTDs mean nothing to each other.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Symbol(u32);
+
+// The interned names of an EDTD.
+#[derive(Debug, Clone, Default)]
+pub struct Symbols<'a> {
+    // Each name, as it was first interned, indexed by its symbol.
+    names: Vec<&'a str>,
+    symbols: HashMap<&'a str, Symbol>,
+}
+
+impl<'a> Symbols<'a> {
+    // The name equal to `name` which was interned first, interning `name` if none was.
+    fn intern(&mut self, name: &'a str) -> &'a str {
+        if let Some(&Symbol(index)) = self.symbols.get(name) {
+            return self.names[index as usize];
+        }
+        self.symbols.insert(name, Symbol(self.names.len() as u32));
+        self.names.push(name);
+        name
+    }
+}
+
+// Symbols are worked out from the names the rest of an EDTD holds, so they don't count when it's
+// compared or hashed.
+impl<'a> PartialEq for Symbols<'a> {
+    fn eq(&self, _: &Self) -> bool {
+        true
+    }
+}
+impl<'a> Eq for Symbols<'a> {}
+impl<'a> Hash for Symbols<'a> {
+    fn hash<H: Hasher>(&self, _: &mut H) {}
+}
+
+impl<'a> Dtd<'a> {
+    /// The symbol of `name`, if it's the name of one of this EDTD's elements or types, or one its
+    /// elements refer to as a parent or type. Elements of other DocTypes count too.
+    pub fn symbol(&self, name: &str) -> Option<Symbol> {
+        self.symbols.symbols.get(name).cloned()
+    }
+
+    /// The name `symbol` stands for.
+    ///
+    /// # Panics
+    ///
+    /// If `symbol` isn't one of this EDTD's.
+    pub fn resolve(&self, symbol: Symbol) -> &'a str {
+        self.symbols.names[symbol.0 as usize]
+    }
+}
+
+// Interns the names of the types and elements of `dtd`, in the order they were defined, then makes
+// the names elements refer to borrow the interned ones, so references to the same definition are
+// the same `&str`. Names which aren't defined are interned where they're first referred to.
+pub fn intern_names(dtd: &mut Dtd) {
+    fn intern_references<'a>(elements: &mut [Spanned<Element<'a>>], symbols: &mut Symbols<'a>) {
+        for element in elements {
+            let element = &mut element.node;
+            if let Type::Name(ref mut name) = element.type_ {
+                *name = symbols.intern(*name);
+            }
+            for parent in element.parent.iter_mut().flatten() {
+                *parent = symbols.intern(*parent);
+            }
+            intern_references(&mut element.children, symbols);
+        }
+    }
+
+    let mut symbols = Symbols::default();
+    for new_type in &dtd.types {
+        symbols.intern(new_type.name());
+    }
+    let groups = dtd.doc_types.iter().map(|group| &group.1[..]);
+    for elements in Some(&dtd.elements[..]).into_iter().chain(groups) {
+        for element in flatten(elements) {
+            symbols.intern(element.name);
+        }
+    }
+    intern_references(&mut dtd.elements, &mut symbols);
+    for group in &mut dtd.doc_types {
+        intern_references(&mut group.1, &mut symbols);
+    }
+    dtd.symbols = symbols;
+}
+
+#[cfg(test)]
+mod tests;
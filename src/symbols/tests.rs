@@ -0,0 +1,59 @@
+use std::ptr;
+
+use parse_dtd;
+
+const DTD: &str = "
+    define types {
+        Flag := uint [ range:0..1; ]
+    }
+    define elements {
+        A := 81 container {
+            B := 82 Flag [ parent:A; ]
+        }
+        C := 83 Flag [ parent:A, X; ]
+    }
+";
+
+#[test]
+fn test_symbols() {
+    let dtd = parse_dtd(DTD, &Default::default()).unwrap();
+    let elements = dtd.all_elements();
+    let (a, b, c) = (elements[0], elements[1], elements[2]);
+
+    // References to an element or type have the symbol of its definition, and borrow its name.
+    let symbol = dtd.symbol("A").unwrap();
+    for parents in &[b.parent.as_ref().unwrap(), c.parent.as_ref().unwrap()] {
+        assert_eq!(dtd.symbol(parents[0]), Some(symbol));
+        assert!(ptr::eq(parents[0], a.name));
+    }
+    assert!(ptr::eq(dtd.resolve(symbol), a.name));
+    let flag = dtd.symbol("Flag").unwrap();
+    assert_eq!(dtd.symbol(b.type_.name()), Some(flag));
+    assert!(ptr::eq(b.type_.name(), c.type_.name()));
+    assert!(ptr::eq(b.type_.name(), dtd.types[0].name()));
+
+    // Different names have different symbols.
+    assert_ne!(dtd.symbol("B"), Some(symbol));
+    assert_ne!(dtd.symbol("B"), dtd.symbol("C"));
+    // Names which are only referred to have symbols too, but other names don't.
+    assert_eq!(dtd.resolve(dtd.symbol("X").unwrap()), "X");
+    assert_eq!(dtd.symbol("Y"), None);
+}
+
+#[test]
+fn test_symbols_of_selected_doc_type() {
+    let text = "
+        declare header { DocType := \"main\"; }
+        define elements { A := 81 uint; }
+        define elements \"other\" { O := 82 container; P := 83 uint [ parent:O; ] }
+    ";
+    let dtd = parse_dtd(text, &Default::default()).unwrap();
+    // Those of other DocTypes are interned with the rest.
+    assert!(dtd.symbol("O").is_some());
+    let other = dtd.select_doc_type("other").unwrap();
+    let elements = other.all_elements();
+    let symbol = other.symbol("O").unwrap();
+    assert_eq!(other.symbol(elements[1].parent.as_ref().unwrap()[0]), Some(symbol));
+    // An EDTD made from another only has symbols for the names it holds.
+    assert_eq!(other.symbol("A"), None);
+}
@@ -0,0 +1,387 @@
+//! Reads EBML documents into a tree of generic values, driven by a parsed EDTD, for tools which
+//! choose their schema at runtime and so can't generate code for it.
+
+#[cfg(test)]
+mod tests;
+mod write;
+
+use std::io::Read;
+
+use runtime::{check_binary_range, check_child, check_count, check_date_range, check_limits,
+              check_order, check_range, check_size, check_string_range, decode_id, read_binary,
+              read_crc32, read_date, read_encoded_child_header_or_eof, read_float, read_int,
+              read_string, read_uint, read_unknown, skip, ReadContext, ReadError, ReadErrorKind,
+              UnknownElements, Value, CRC_32, EBML, VOID};
+use {date, Dtd, Element, Property, Type};
+
+pub use self::write::{WriteTreeError, WriteTreeErrorKind, WriteTreeOptions};
+
+/// A document read by `Dtd::read_document`: its top-level elements, in the order they occur.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct EbmlValueTree {
+    /// The top-level elements.
+    pub roots: Vec<EbmlNode>,
+}
+
+impl EbmlValueTree {
+    /// The `index`th top-level element named `name`, counting from 0.
+    pub fn child(&self, name: &str, index: usize) -> Option<&EbmlNode> {
+        named(&self.roots, name).nth(index)
+    }
+
+    /// The top-level elements named `name`.
+    pub fn children_named<'t>(&'t self, name: &'t str) -> impl Iterator<Item = &'t EbmlNode> {
+        named(&self.roots, name)
+    }
+}
+
+/// One element of an `EbmlValueTree`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct EbmlNode {
+    /// Which element it is.
+    pub element: ElementRef,
+    /// What it holds.
+    pub value: NodeValue,
+}
+
+impl EbmlNode {
+    /// The children of a container, or nothing for any other element.
+    pub fn children(&self) -> &[EbmlNode] {
+        match self.value {
+            NodeValue::Container(ref children) => children,
+            _ => &[],
+        }
+    }
+
+    /// The `index`th child named `name`, counting from 0.
+    pub fn child(&self, name: &str, index: usize) -> Option<&EbmlNode> {
+        named(self.children(), name).nth(index)
+    }
+
+    /// The children named `name`.
+    pub fn children_named<'t>(&'t self, name: &'t str) -> impl Iterator<Item = &'t EbmlNode> {
+        named(self.children(), name)
+    }
+
+    /// The value of an element which isn't a container, if the EDTD defines it.
+    pub fn value(&self) -> Option<&Value> {
+        match self.value {
+            NodeValue::Value(ref value) => Some(value),
+            _ => None,
+        }
+    }
+}
+
+/// Which element an `EbmlNode` is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ElementRef {
+    /// One the EDTD defines.
+    Known {
+        /// The element's name.
+        name: &'static str,
+        /// The element's id in its encoded form, including the length marker bits.
+        id: u32,
+    },
+    /// One it doesn't. Holds its encoded id.
+    Unknown(u32),
+}
+
+impl ElementRef {
+    /// The element's id in its encoded form.
+    pub fn id(self) -> u32 {
+        match self {
+            ElementRef::Known { id, .. } | ElementRef::Unknown(id) => id,
+        }
+    }
+
+    /// The element's name, if the EDTD defines it.
+    pub fn name(self) -> Option<&'static str> {
+        match self {
+            ElementRef::Known { name, .. } => Some(name),
+            ElementRef::Unknown(_) => None,
+        }
+    }
+}
+
+/// What an `EbmlNode` holds.
+#[derive(Debug, Clone, PartialEq)]
+pub enum NodeValue {
+    /// The children of a container, in the order they occur. Absent mandatory children with a
+    /// default come last, holding it.
+    Container(Vec<EbmlNode>),
+    /// The value of any other element.
+    Value(Value),
+    /// The body of an element the EDTD doesn't define, which is kept if unknown elements are
+    /// collected.
+    Unknown(Vec<u8>),
+}
+
+fn named<'t>(nodes: &'t [EbmlNode], name: &'t str) -> impl Iterator<Item = &'t EbmlNode> {
+    nodes.iter().filter(move |node| node.element.name() == Some(name))
+}
+
+impl Dtd<'static> {
+    /// Reads the EBML document from `reader` into a tree, as generated readers would read it
+    /// into structs. The EDTD must live as long as the tree, which names elements with its
+    /// `&'static str`s, like generated code and `ReadError` do; leak the text of one which is
+    /// loaded at runtime.
+    ///
+    /// Every element is checked as generated readers check it: cardinalities, orders, sizes and
+    /// ranges, against `ReadLimits`, and any `CRC-32` first in a container. Absent mandatory
+    /// elements take their default. `Void` is skipped, as is the EBML header unless the EDTD
+    /// defines it; read it first with `runtime::read_ebml_header` to check it. Elements the EDTD
+    /// doesn't define are skipped unless `ReadOptions::unknown_elements` says otherwise. An
+    /// element with an unknown size must allow one, and ends at the first element which can't be
+    /// inside it.
+    pub fn read_document<R: Read>(&self, reader: R) -> Result<EbmlValueTree, ReadError> {
+        self.read_document_with(reader, &mut ReadContext::default())
+    }
+
+    /// The element named `name`, to build the nodes of a tree with.
+    pub fn element_ref(&self, name: &str) -> Option<ElementRef> {
+        self.all_elements().into_iter()
+            .find(|element| element.name == name)
+            .map(|element| ElementRef::Known { name: element.name, id: element.id })
+    }
+
+    /// Like `read_document`, using the options and collecting warnings in `ctx`.
+    pub fn read_document_with<R: Read>(&self, mut reader: R, ctx: &mut ReadContext)
+        -> Result<EbmlValueTree, ReadError>
+    {
+        let body = read_body(self, &mut reader, ctx, None, None, false)?;
+        Ok(EbmlValueTree { roots: body.children })
+    }
+}
+
+// An element's encoded id, its size if it's known, and the length of the two.
+type Header = (u32, Option<u64>, u64);
+
+// The children of a container, or the top level of a document.
+struct Body {
+    children: Vec<EbmlNode>,
+    // What's left of the bytes the body was given, if it was given a number.
+    remaining: Option<u64>,
+    // The header of the element which ended an open-ended body, which has already been read.
+    next: Option<Header>,
+}
+
+// Reads the children of `parent`, or of the document if it's `None`, from the next `remaining`
+// bytes, or to the end of the input if that's `None`. If `open_ended`, they also end at the first
+// element which can't be among them.
+fn read_body<R: Read>(dtd: &Dtd<'static>, r: &mut R, ctx: &mut ReadContext,
+                      parent: Option<&Element<'static>>, mut remaining: Option<u64>,
+                      open_ended: bool)
+    -> Result<Body, ReadError>
+{
+    let allowed: Vec<&Element<'static>> = match parent {
+        Some(parent) => dtd.children_of(parent),
+        None => dtd.elements.iter()
+            .map(|element| &element.node)
+            .filter(|element| element.parent.is_none())
+            .collect(),
+    };
+    let ordered = parent.is_some_and(Element::is_ordered);
+    let size = remaining;
+
+    let mut nodes = Vec::new();
+    let mut counts = vec![0; allowed.len()];
+    let mut latest = 0;
+    let mut children = 0;
+    let mut pending = None;
+    let mut next = None;
+    while remaining != Some(0) {
+        let header = match pending.take() {
+            Some(header) => header,
+            None => match read_encoded_child_header_or_eof(r)? {
+                Some(header) => header,
+                None if remaining.is_none() => break,
+                None => return Err(ReadError::new(ReadErrorKind::UnexpectedEof)),
+            },
+        };
+        let (encoded, child_size, header_size) = header;
+        let index = allowed.iter().position(|child| child.id == encoded);
+        if open_ended && index.is_none() && encoded != VOID && encoded != CRC_32 {
+            next = Some(header);
+            break;
+        }
+        let is_first = remaining == size;
+        remaining = consume(remaining, header_size)?;
+
+        if let Some(index) = index {
+            let child = allowed[index];
+            counts[index] += 1;
+            if ordered {
+                check_order(ctx, child.name, &mut latest, index)?;
+            }
+            let cardinality = child.cardinality();
+            if !cardinality.allows(2) {
+                check_count(ctx, child.name, &cardinality, counts[index])?;
+                // As in generated readers, a repeated element replaces the last one.
+                nodes.retain(|node: &EbmlNode| node.element.id() != child.id);
+            }
+
+            let node = match child_size {
+                Some(child_size) => {
+                    check_child(ctx, Some(&mut children), child_size)?;
+                    remaining = consume(remaining, child_size)?;
+                    read_element(dtd, r, ctx, child, child_size)?
+                }
+                None if child.allows_unknown_size() &&
+                        dtd.resolve_type(child.type_) == Some(Type::Container) => {
+                    check_child(ctx, Some(&mut children), 0)?;
+                    let body = read_unsized(dtd, r, ctx, child, remaining)?;
+                    let used = match (remaining, body.remaining) {
+                        (Some(remaining), Some(left)) => remaining - left,
+                        _ => 0,
+                    };
+                    remaining = consume(remaining, used)?;
+                    pending = body.next;
+                    node(child, NodeValue::Container(body.children))
+                }
+                None => {
+                    let err = ReadError::new(ReadErrorKind::UnknownSize);
+                    return Err(err.within(child.name));
+                }
+            };
+            nodes.push(node);
+            continue;
+        }
+
+        let child_size = child_size.ok_or_else(|| ReadError::new(ReadErrorKind::UnknownSize))?;
+        check_child(ctx, Some(&mut children), child_size)?;
+        remaining = consume(remaining, child_size)?;
+        // A CRC-32 only counts as the first child of an element of known size, and the rest of
+        // the body is read from what it covers.
+        if encoded == CRC_32 && is_first && !open_ended && parent.is_some() {
+            if let Some(covered) = remaining {
+                if let Some(rest) = read_crc32(r, ctx, child_size, covered)? {
+                    let len = rest.len() as u64;
+                    return read_body(dtd, &mut &rest[..], ctx, parent, Some(len), false);
+                }
+                continue;
+            }
+        }
+        if encoded == VOID || encoded == CRC_32 || (encoded == EBML && parent.is_none()) {
+            skip(r, child_size)?;
+            continue;
+        }
+        let id = decode_id(encoded)?;
+        if let Some(body) = read_unknown(r, ctx, UnknownElements::Skip, id, child_size)? {
+            let element = ElementRef::Unknown(encoded);
+            nodes.push(EbmlNode { element, value: NodeValue::Unknown(body) });
+        }
+    }
+
+    // Absent mandatory elements take their default, if they have one, and fail the read if not.
+    for (child, &count) in allowed.iter().zip(&counts) {
+        let cardinality = child.cardinality();
+        if count > 0 || cardinality.allows(0) {
+            continue;
+        }
+        match default_of(child) {
+            Some(value) => nodes.push(node(child, NodeValue::Value(value))),
+            None => check_count(ctx, child.name, &cardinality, 0)?,
+        }
+    }
+    Ok(Body { children: nodes, remaining, next })
+}
+
+// Reads a child of known size, whose header has been read.
+fn read_element<R: Read>(dtd: &Dtd<'static>, r: &mut R, ctx: &mut ReadContext,
+                         element: &Element<'static>, size: u64)
+    -> Result<EbmlNode, ReadError>
+{
+    let type_ = dtd.resolve_type(element.type_);
+    if type_ == Some(Type::Container) {
+        ctx.enter(element.name);
+        let result = check_limits(ctx, Some(size))
+            .and_then(|()| read_body(dtd, r, ctx, Some(element), Some(size), false));
+        ctx.leave();
+        let body = result.map_err(|err| err.within(element.name))?;
+        return Ok(node(element, NodeValue::Container(body.children)));
+    }
+
+    if let Some(ref sizes) = element.size {
+        check_size(size, sizes).map_err(|err| err.within(element.name))?;
+    }
+    read_value(dtd, r, ctx, element, type_, size)
+        .map(|value| node(element, NodeValue::Value(value)))
+        .map_err(|err| err.within(element.name))
+}
+
+// Reads a container whose size is unknown, and which ends at the first element which can't be
+// inside it, after `limit` bytes if there's a limit, or at the end of the input.
+fn read_unsized<R: Read>(dtd: &Dtd<'static>, r: &mut R, ctx: &mut ReadContext,
+                         element: &Element<'static>, limit: Option<u64>)
+    -> Result<Body, ReadError>
+{
+    ctx.enter(element.name);
+    let result = check_limits(ctx, None)
+        .and_then(|()| read_body(dtd, r, ctx, Some(element), limit, true));
+    ctx.leave();
+    result.map_err(|err| err.within(element.name))
+}
+
+// Reads the value of an element which isn't a container, checking it against its range.
+fn read_value<R: Read>(dtd: &Dtd<'static>, r: &mut R, ctx: &mut ReadContext,
+                       element: &Element<'static>, type_: Option<Type>, size: u64)
+    -> Result<Value, ReadError>
+{
+    let name = element.name;
+    let range = dtd.range_of(element);
+    Ok(match (type_, range) {
+        (Some(Type::Int), Some(Property::IntRange(range))) => {
+            Value::Int(check_range(ctx, name, read_int(r, size)?, &range)?)
+        }
+        (Some(Type::Int), _) => Value::Int(read_int(r, size)?),
+        (Some(Type::Uint), Some(Property::UintRange(range))) => {
+            Value::Uint(check_range(ctx, name, read_uint(r, size)?, &range)?)
+        }
+        (Some(Type::Uint), _) => Value::Uint(read_uint(r, size)?),
+        (Some(Type::Float), Some(Property::FloatRange(range))) => {
+            Value::Float(check_range(ctx, name, read_float(r, size)?, &range)?)
+        }
+        (Some(Type::Float), _) => Value::Float(read_float(r, size)?),
+        (Some(Type::Date), Some(Property::DateRange(range))) => {
+            Value::Date(check_date_range(ctx, name, read_date(r, size)?, &range)?)
+        }
+        (Some(Type::Date), _) => Value::Date(read_date(r, size)?),
+        (Some(Type::String), Some(Property::StringRange(range))) => {
+            Value::String(check_string_range(ctx, name, read_string(r, size)?, &range)?)
+        }
+        (Some(Type::String), _) => Value::String(read_string(r, size)?),
+        (Some(Type::Binary), Some(Property::BinaryRange(range))) => {
+            Value::Binary(check_binary_range(ctx, name, read_binary(r, size)?, &range)?)
+        }
+        // Elements of types the EDTD doesn't define are kept as bytes.
+        _ => Value::Binary(read_binary(r, size)?),
+    })
+}
+
+// The value an absent element takes: its default, if it has one.
+fn default_of(element: &Element) -> Option<Value> {
+    match element.default.as_deref() {
+        Some(&Property::IntDefault(value)) => Some(Value::Int(value)),
+        Some(&Property::UintDefault(value)) => Some(Value::Uint(value)),
+        Some(&Property::FloatDefault(value)) => Some(Value::Float(value)),
+        // Dates too far from the epoch to encode are left without a default.
+        Some(&Property::DateDefault(value)) => date::nanos(&value).map(Value::Date),
+        Some(Property::StringDefault(value)) => Some(Value::String(value.to_string())),
+        Some(Property::BinaryDefault(value)) => Some(Value::Binary(value.to_vec())),
+        _ => None,
+    }
+}
+
+fn node(element: &Element<'static>, value: NodeValue) -> EbmlNode {
+    EbmlNode { element: ElementRef::Known { name: element.name, id: element.id }, value }
+}
+
+// Takes `len` bytes from what's left of a body, failing if there aren't that many.
+fn consume(remaining: Option<u64>, len: u64) -> Result<Option<u64>, ReadError> {
+    match remaining {
+        Some(remaining) => remaining.checked_sub(len)
+            .map(Some)
+            .ok_or_else(|| ReadError::new(ReadErrorKind::Overrun)),
+        None => Ok(None),
+    }
+}
@@ -0,0 +1,292 @@
+use super::*;
+use fixtures::element;
+use runtime::{crc32, RangePolicy, ReadOptions};
+use {parse_dtd, ParseOptions};
+
+const DTD: &str = r#"
+declare header {
+    DocType := "test";
+}
+define types {
+    bool := uint [ range:0..1; ]
+}
+define elements {
+    Segment := 18538067 container [ card:1; unknownsizeallowed:yes; ] {
+        Info := 1549a966 container [ card:1; ] {
+            Title := 7ba9 string [ card:1; ]
+            Flag := 9c bool [ card:1; def:1; ]
+        }
+        Track := ae container [ card:*; ] {
+            Number := d7 uint [ card:1; range:1..; ]
+        }
+    }
+}
+"#;
+
+// An EBML header, and a `Segment` holding `children`.
+fn document(children: &[Vec<u8>]) -> Vec<u8> {
+    let mut bytes = element(&[0x1A, 0x45, 0xDF, 0xA3], &[]);
+    bytes.extend(element(&[0x18, 0x53, 0x80, 0x67], &children.concat()));
+    bytes
+}
+
+fn info(children: &[Vec<u8>]) -> Vec<u8> {
+    element(&[0x15, 0x49, 0xA9, 0x66], &children.concat())
+}
+
+fn title() -> Vec<u8> {
+    element(&[0x7B, 0xA9], b"Hi")
+}
+
+fn track(number: u8) -> Vec<u8> {
+    element(&[0xAE], &element(&[0xD7], &[number]))
+}
+
+// Reads `bytes` with `options`, returning the tree and any warnings.
+fn read(bytes: &[u8], options: ReadOptions)
+    -> Result<(EbmlValueTree, Vec<ReadError>), ReadError>
+{
+    let dtd = parse_dtd(DTD, &ParseOptions::default()).unwrap();
+    let mut ctx = ReadContext::new(options);
+    let tree = dtd.read_document_with(bytes, &mut ctx)?;
+    Ok((tree, ctx.take_warnings()))
+}
+
+#[test]
+fn test_navigate() {
+    let void = element(&[0xEC], &[0; 2]);
+    let bytes = document(&[info(&[title()]), track(1), void, track(2)]);
+    let (tree, warnings) = read(&bytes, ReadOptions::default()).unwrap();
+    assert!(warnings.is_empty());
+
+    // The EBML header isn't in the EDTD, so it's skipped.
+    assert_eq!(tree.roots.len(), 1);
+    let segment = tree.child("Segment", 0).unwrap();
+    assert_eq!(segment.element, ElementRef::Known { name: "Segment", id: 0x1853_8067 });
+    assert_eq!(segment.children().len(), 3);
+
+    let info = segment.child("Info", 0).unwrap();
+    let title = info.child("Title", 0).unwrap();
+    assert_eq!(title.value(), Some(&Value::String("Hi".to_string())));
+    // The absent flag takes its default.
+    assert_eq!(info.child("Flag", 0).unwrap().value(), Some(&Value::Uint(1)));
+
+    assert_eq!(segment.children_named("Track").count(), 2);
+    let number = segment.child("Track", 1).and_then(|track| track.child("Number", 0));
+    assert_eq!(number.and_then(EbmlNode::value), Some(&Value::Uint(2)));
+    assert!(segment.child("Track", 2).is_none());
+    assert!(title.child("Title", 0).is_none());
+    assert_eq!(info.value(), None);
+}
+
+#[test]
+fn test_unknown_elements() {
+    let bytes = document(&[info(&[title(), element(&[0x4D, 0xBB], &[1, 2])])]);
+
+    let (tree, _) = read(&bytes, ReadOptions::default()).unwrap();
+    let info = tree.child("Segment", 0).and_then(|segment| segment.child("Info", 0)).unwrap();
+    assert_eq!(info.children().len(), 2);
+
+    let options = ReadOptions {
+        unknown_elements: Some(UnknownElements::Collect),
+        ..ReadOptions::default()
+    };
+    let (tree, _) = read(&bytes, options).unwrap();
+    let info = tree.child("Segment", 0).and_then(|segment| segment.child("Info", 0)).unwrap();
+    assert_eq!(info.children()[1], EbmlNode {
+        element: ElementRef::Unknown(0x4DBB),
+        value: NodeValue::Unknown(vec![1, 2]),
+    });
+
+    let options = ReadOptions {
+        unknown_elements: Some(UnknownElements::Error),
+        ..ReadOptions::default()
+    };
+    let err = read(&bytes, options).unwrap_err();
+    match *err.kind() {
+        ReadErrorKind::UnknownElement(_) => {}
+        ref kind => panic!("{:?}", kind),
+    }
+    assert_eq!(err.path(), &["Segment", "Info"]);
+}
+
+#[test]
+fn test_checks() {
+    let err = read(&document(&[info(&[])]), ReadOptions::default()).unwrap_err();
+    match *err.kind() {
+        ReadErrorKind::MissingElement("Title") => {}
+        ref kind => panic!("{:?}", kind),
+    }
+    assert_eq!(err.path(), &["Segment", "Info"]);
+
+    let bytes = document(&[info(&[title()]), track(0)]);
+    let err = read(&bytes, ReadOptions::default()).unwrap_err();
+    match *err.kind() {
+        ReadErrorKind::OutOfRange { .. } => {}
+        ref kind => panic!("{:?}", kind),
+    }
+    assert_eq!(err.path(), &["Segment", "Track", "Number"]);
+
+    let options = ReadOptions { lenient_ranges: true, ..ReadOptions::default() };
+    let (tree, warnings) = read(&bytes, options).unwrap();
+    assert_eq!(warnings.len(), 1);
+    let number = tree.child("Segment", 0)
+        .and_then(|segment| segment.child("Track", 0))
+        .and_then(|track| track.child("Number", 0));
+    assert_eq!(number.and_then(EbmlNode::value), Some(&Value::Uint(0)));
+}
+
+#[test]
+fn test_unknown_size() {
+    let mut bytes = element(&[0x1A, 0x45, 0xDF, 0xA3], &[]);
+    bytes.extend(&[0x18, 0x53, 0x80, 0x67, 0xFF]);
+    bytes.extend(info(&[title()]));
+    bytes.extend(track(1));
+    let (tree, _) = read(&bytes, ReadOptions::default()).unwrap();
+    let segment = tree.child("Segment", 0).unwrap();
+    assert_eq!(segment.children_named("Track").count(), 1);
+
+    // Info doesn't allow an unknown size.
+    let mut bytes = document(&[info(&[title()])]);
+    bytes[14] = 0xFF;
+    let err = read(&bytes, ReadOptions::default()).unwrap_err();
+    match *err.kind() {
+        ReadErrorKind::UnknownSize => {}
+        ref kind => panic!("{:?}", kind),
+    }
+}
+
+#[test]
+fn test_crc32() {
+    let rest = title();
+    let crc = element(&[0xBF], &crc32(&rest).to_le_bytes());
+    let bytes = document(&[info(&[crc, rest.clone()])]);
+    let (tree, _) = read(&bytes, ReadOptions::default()).unwrap();
+    let title = tree.child("Segment", 0)
+        .and_then(|segment| segment.child("Info", 0))
+        .and_then(|info| info.child("Title", 0));
+    assert!(title.is_some());
+
+    let bad = element(&[0xBF], &[0; 4]);
+    let bytes = document(&[info(&[bad, rest])]);
+    let err = read(&bytes, ReadOptions::default()).unwrap_err();
+    match *err.kind() {
+        ReadErrorKind::CrcMismatch { .. } => {}
+        ref kind => panic!("{:?}", kind),
+    }
+    let options = ReadOptions { lenient_crc: true, ..ReadOptions::default() };
+    assert!(read(&bytes, options).is_ok());
+}
+
+fn write(tree: &EbmlValueTree, options: &WriteTreeOptions) -> Result<Vec<u8>, WriteTreeError> {
+    let dtd = parse_dtd(DTD, &ParseOptions::default()).unwrap();
+    let mut bytes = Vec::new();
+    let written = dtd.write_document_with(tree, &mut bytes, options)?;
+    assert_eq!(written, bytes.len() as u64);
+    Ok(bytes)
+}
+
+// A node of the element named `name`.
+fn node(name: &str, value: NodeValue) -> EbmlNode {
+    let dtd = parse_dtd(DTD, &ParseOptions::default()).unwrap();
+    EbmlNode { element: dtd.element_ref(name).unwrap(), value }
+}
+
+#[test]
+fn test_round_trip() {
+    let options = ReadOptions {
+        unknown_elements: Some(UnknownElements::Collect),
+        ..ReadOptions::default()
+    };
+    let segment = [info(&[title(), element(&[0x4D, 0xBB], &[1, 2]), element(&[0x9C], &[0])]),
+                   track(1), track(200)].concat();
+    let bytes = element(&[0x18, 0x53, 0x80, 0x67], &segment);
+    let (tree, _) = read(&bytes, options.clone()).unwrap();
+    let written = write(&tree, &WriteTreeOptions::default()).unwrap();
+    assert_eq!(written, bytes);
+
+    // Absent elements are written with the defaults they were read with.
+    let (tree, _) = read(&document(&[info(&[title()])]), options.clone()).unwrap();
+    let written = write(&tree, &WriteTreeOptions::default()).unwrap();
+    assert_eq!(read(&written, options).unwrap().0, tree);
+}
+
+#[test]
+fn test_write_built() {
+    let title_node = node("Title", NodeValue::Value(Value::String("Hi".to_string())));
+    let info_node = node("Info", NodeValue::Container(vec![title_node]));
+    let track_node = node("Track", NodeValue::Container(vec![
+        node("Number", NodeValue::Value(Value::Uint(3))),
+    ]));
+    let tree = EbmlValueTree {
+        roots: vec![node("Segment", NodeValue::Container(vec![info_node, track_node.clone()]))],
+    };
+    let written = write(&tree, &WriteTreeOptions::default()).unwrap();
+    let segment = [info(&[title()]), track(3)].concat();
+    assert_eq!(written, element(&[0x18, 0x53, 0x80, 0x67], &segment));
+
+    // A Segment needs one Info, which needs one Title.
+    let tree = EbmlValueTree {
+        roots: vec![node("Segment", NodeValue::Container(vec![track_node.clone()]))],
+    };
+    let err = write(&tree, &WriteTreeOptions::default()).unwrap_err();
+    match *err.kind() {
+        WriteTreeErrorKind::Cardinality { ref element, count: 0, .. } => {
+            assert_eq!(element, "Info");
+        }
+        ref kind => panic!("{:?}", kind),
+    }
+    assert_eq!(err.path(), &["Segment"]);
+
+    let info_node = node("Info", NodeValue::Container(vec![]));
+    let tree = EbmlValueTree {
+        roots: vec![node("Segment", NodeValue::Container(vec![info_node, track_node]))],
+    };
+    let err = write(&tree, &WriteTreeOptions::default()).unwrap_err();
+    assert_eq!(err.to_string(),
+               "`Title` occurs 0 times, which its `card:` doesn't allow in Segment/Info");
+    let options = WriteTreeOptions { lenient_cardinality: true, ..WriteTreeOptions::default() };
+    assert!(write(&tree, &options).is_ok());
+}
+
+#[test]
+fn test_write_checks() {
+    let segment = |children| EbmlValueTree {
+        roots: vec![node("Segment", NodeValue::Container(vec![
+            node("Info", NodeValue::Container(children)),
+        ]))],
+    };
+    let title = node("Title", NodeValue::Value(Value::String("Hi".to_string())));
+
+    let flag = node("Flag", NodeValue::Value(Value::Uint(2)));
+    let err = write(&segment(vec![title.clone(), flag]), &WriteTreeOptions::default())
+        .unwrap_err();
+    match *err.kind() {
+        WriteTreeErrorKind::OutOfRange(_) => {}
+        ref kind => panic!("{:?}", kind),
+    }
+    assert_eq!(err.path(), &["Segment", "Info", "Flag"]);
+    let options = WriteTreeOptions {
+        ranges: Some(RangePolicy::Clamp),
+        ..WriteTreeOptions::default()
+    };
+    let flag = node("Flag", NodeValue::Value(Value::Uint(2)));
+    let written = write(&segment(vec![title.clone(), flag]), &options).unwrap();
+    assert!(written.ends_with(&element(&[0x9C], &[1])));
+
+    let flag = node("Flag", NodeValue::Value(Value::Int(1)));
+    let err = write(&segment(vec![title.clone(), flag]), &WriteTreeOptions::default())
+        .unwrap_err();
+    match *err.kind() {
+        WriteTreeErrorKind::WrongType => {}
+        ref kind => panic!("{:?}", kind),
+    }
+
+    let number = node("Number", NodeValue::Value(Value::Uint(1)));
+    let err = write(&segment(vec![title, number]), &WriteTreeOptions::default()).unwrap_err();
+    match *err.kind() {
+        WriteTreeErrorKind::MisplacedElement("Number") => {}
+        ref kind => panic!("{:?}", kind),
+    }
+    assert_eq!(err.path(), &["Segment", "Info"]);
+}
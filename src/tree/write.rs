@@ -0,0 +1,356 @@
+use std::error::Error;
+use std::fmt;
+use std::io::{self, Write};
+
+use runtime::{binary_body, binary_in_range, clamp, date_body, date_in_range, float_body,
+              in_range, int_body, string_body, string_in_range, uint_body, write_element,
+              RangeError, RangePolicy, Value};
+use {Cardinality, ClampRangeItem, Dtd, Element, Property, SizeList, Type, UintRangeItem};
+use super::{EbmlNode, EbmlValueTree, ElementRef, NodeValue};
+
+/// Options for `Dtd::write_document_with`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct WriteTreeOptions {
+    /// What to do with values outside their range. If unset, they're refused.
+    pub ranges: Option<RangePolicy>,
+    /// If set, elements may occur any number of times.
+    pub lenient_cardinality: bool,
+    /// If set, children of `ordered:yes` elements may be in any order.
+    pub lenient_order: bool,
+    /// If set, values are written whatever their element's `size:` allows.
+    pub lenient_sizes: bool,
+}
+
+/// An error encountered while writing an `EbmlValueTree` with `Dtd::write_document`.
+#[derive(Debug)]
+pub struct WriteTreeError {
+    kind: WriteTreeErrorKind,
+    // Outermost element first.
+    path: Vec<&'static str>,
+}
+
+/// The ways writing an `EbmlValueTree` can fail.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum WriteTreeErrorKind {
+    /// The underlying writer failed, or a value couldn't be encoded.
+    Io(io::Error),
+    /// A node names an element the EDTD doesn't define, or gives it the wrong id. Holds the id.
+    UnknownElement(u32),
+    /// A node is an element the EDTD defines, somewhere it can't be. Holds its name.
+    MisplacedElement(&'static str),
+    /// A node's value isn't of its element's type.
+    WrongType,
+    /// A value is outside its element's `range:`, or its type's.
+    OutOfRange(RangeError),
+    /// A value can't be encoded in any of the sizes its element's `size:` allows.
+    SizeNotAllowed {
+        /// The size of the value's body.
+        size: u64,
+        /// The sizes it could have had, as written in an EDTD.
+        allowed: String,
+    },
+    /// A container has a child a number of times its `card:` doesn't allow.
+    Cardinality {
+        /// The name of the child.
+        element: String,
+        /// How many times it occurs.
+        count: usize,
+        /// How many times it may.
+        allowed: Cardinality,
+    },
+    /// A child of an `ordered:yes` element comes after a child declared later than it. Holds
+    /// its name.
+    OutOfOrder(&'static str),
+}
+
+impl WriteTreeError {
+    fn new(kind: WriteTreeErrorKind) -> Self {
+        WriteTreeError { kind, path: Vec::new() }
+    }
+
+    /// What went wrong.
+    pub fn kind(&self) -> &WriteTreeErrorKind {
+        &self.kind
+    }
+
+    /// The names of the elements being written when the error happened, outermost first.
+    pub fn path(&self) -> &[&'static str] {
+        &self.path
+    }
+
+    fn within(mut self, element: &'static str) -> Self {
+        self.path.insert(0, element);
+        self
+    }
+}
+
+impl From<io::Error> for WriteTreeError {
+    fn from(err: io::Error) -> Self {
+        WriteTreeError::new(WriteTreeErrorKind::Io(err))
+    }
+}
+
+impl fmt::Display for WriteTreeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.kind {
+            WriteTreeErrorKind::Io(ref err) => write!(f, "{}", err)?,
+            WriteTreeErrorKind::UnknownElement(id) => {
+                write!(f, "element 0x{:X} isn't defined by the EDTD", id)?
+            }
+            WriteTreeErrorKind::MisplacedElement(name) => {
+                write!(f, "`{}` element can't appear here", name)?
+            }
+            WriteTreeErrorKind::WrongType => {
+                f.write_str("value isn't of the type of its element")?
+            }
+            WriteTreeErrorKind::OutOfRange(ref err) => write!(f, "{}", err)?,
+            WriteTreeErrorKind::SizeNotAllowed { size, ref allowed } => {
+                write!(f, "size {} isn't one of the allowed sizes {}", size, allowed)?
+            }
+            WriteTreeErrorKind::Cardinality { ref element, count, .. } => {
+                write!(f, "`{}` occurs {} times, which its `card:` doesn't allow", element,
+                       count)?
+            }
+            WriteTreeErrorKind::OutOfOrder(name) => {
+                write!(f, "`{}` element is out of order", name)?
+            }
+        }
+        if !self.path.is_empty() {
+            write!(f, " in {}", self.path.join("/"))?;
+        }
+        Ok(())
+    }
+}
+
+impl Error for WriteTreeError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self.kind {
+            WriteTreeErrorKind::Io(ref err) => Some(err),
+            WriteTreeErrorKind::OutOfRange(ref err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl<'a> Dtd<'a> {
+    /// Writes `tree` to `writer` as an EBML document, returning the number of bytes written.
+    /// Nothing is written unless the whole tree is valid.
+    ///
+    /// Every node must be an element this EDTD allows where it is, holding a value of its type
+    /// in its `range:` and `size:`. Containers must have each child as many times as its
+    /// `card:` allows, though mandatory children with a default may be left out, and in order
+    /// if they're `ordered:yes`. Nodes of elements the EDTD doesn't define are written as they
+    /// are, as is the tree `Dtd::read_document` reads. Integers and strings are padded to the
+    /// smallest size allowed if they're shorter, and floats are written in 8 bytes unless only
+    /// 4 are allowed.
+    pub fn write_document<W: Write>(&self, tree: &EbmlValueTree, writer: W)
+        -> Result<u64, WriteTreeError>
+    {
+        self.write_document_with(tree, writer, &WriteTreeOptions::default())
+    }
+
+    /// Like `write_document`, checking the tree as `options` say.
+    pub fn write_document_with<W: Write>(&self, tree: &EbmlValueTree, mut writer: W,
+                                         options: &WriteTreeOptions)
+        -> Result<u64, WriteTreeError>
+    {
+        let encoder = Encoder { dtd: self, options };
+        let bytes = encoder.children(None, &tree.roots)?;
+        writer.write_all(&bytes)?;
+        Ok(bytes.len() as u64)
+    }
+}
+
+struct Encoder<'d, 'a: 'd> {
+    dtd: &'d Dtd<'a>,
+    options: &'d WriteTreeOptions,
+}
+
+impl<'d, 'a> Encoder<'d, 'a> {
+    // Encodes `nodes`, the children of `parent` or the top level of the document.
+    fn children(&self, parent: Option<&'d Element<'a>>, nodes: &[EbmlNode])
+        -> Result<Vec<u8>, WriteTreeError>
+    {
+        let allowed: Vec<&Element> = match parent {
+            Some(parent) => self.dtd.children_of(parent),
+            None => self.dtd.elements.iter()
+                .map(|element| &element.node)
+                .filter(|element| element.parent.is_none())
+                .collect(),
+        };
+        let ordered = parent.is_some_and(Element::is_ordered) && !self.options.lenient_order;
+        let mut counts = vec![0; allowed.len()];
+        let mut latest = 0;
+        let mut bytes = Vec::new();
+        for node in nodes {
+            let (name, id) = match node.element {
+                ElementRef::Known { name, id } => (name, id),
+                ElementRef::Unknown(id) => match node.value {
+                    NodeValue::Unknown(ref body) => {
+                        write_element(&mut bytes, id, body)?;
+                        continue;
+                    }
+                    _ => return Err(WriteTreeError::new(WriteTreeErrorKind::WrongType)),
+                },
+            };
+            let position = allowed.iter().position(|child| child.name == name);
+            let index = match position {
+                Some(index) if allowed[index].id == id => index,
+                None if self.dtd.all_elements().iter().any(|element| element.name == name) => {
+                    return Err(WriteTreeError::new(WriteTreeErrorKind::MisplacedElement(name)));
+                }
+                _ => return Err(WriteTreeError::new(WriteTreeErrorKind::UnknownElement(id))),
+            };
+            counts[index] += 1;
+            if ordered && index < latest {
+                return Err(WriteTreeError::new(WriteTreeErrorKind::OutOfOrder(name)));
+            }
+            latest = latest.max(index);
+            let body = self.body(allowed[index], &node.value).map_err(|err| err.within(name))?;
+            write_element(&mut bytes, id, &body)?;
+        }
+
+        if !self.options.lenient_cardinality {
+            for (child, count) in allowed.iter().zip(counts) {
+                let cardinality = child.cardinality();
+                // Readers fill in absent mandatory elements with their default.
+                let defaulted = count == 0 && child.default.is_some();
+                if !cardinality.allows(count) && !defaulted {
+                    let element = child.name.to_string();
+                    let allowed = cardinality;
+                    let kind = WriteTreeErrorKind::Cardinality { element, count, allowed };
+                    return Err(WriteTreeError::new(kind));
+                }
+            }
+        }
+        Ok(bytes)
+    }
+
+    // The body of a node of `element`.
+    fn body(&self, element: &'d Element<'a>, value: &NodeValue)
+        -> Result<Vec<u8>, WriteTreeError>
+    {
+        let type_ = self.dtd.resolve_type(element.type_);
+        let value = match (type_, value) {
+            (Some(Type::Container), &NodeValue::Container(ref children)) => {
+                return self.children(Some(element), children);
+            }
+            (_, &NodeValue::Value(ref value)) => value,
+            _ => return Err(WriteTreeError::new(WriteTreeErrorKind::WrongType)),
+        };
+        let value = self.check_range(element, type_, value)?;
+        let float_size = match element.size {
+            Some(ref sizes) if !sizes.matches(8) && sizes.matches(4) => 4,
+            _ => 8,
+        };
+        let body = match (type_, &value) {
+            (Some(Type::Int), &Value::Int(value)) => int_body(&value)?,
+            (Some(Type::Uint), &Value::Uint(value)) => uint_body(&value)?,
+            (Some(Type::Float), &Value::Float(value)) => float_body(&value, float_size)?,
+            (Some(Type::Date), &Value::Date(value)) => date_body(&value)?,
+            (Some(Type::String), Value::String(value)) => string_body(value)?,
+            (Some(Type::Binary), Value::Binary(value)) | (None, Value::Binary(value)) => {
+                binary_body(value)?
+            }
+            _ => return Err(WriteTreeError::new(WriteTreeErrorKind::WrongType)),
+        };
+        match element.size {
+            Some(ref sizes) if !self.options.lenient_sizes => fit(body, &value, sizes),
+            _ => Ok(body),
+        }
+    }
+
+    // The value to write for `value`, following the range policy.
+    fn check_range(&self, element: &Element<'a>, type_: Option<Type>, value: &Value)
+        -> Result<Value, WriteTreeError>
+    {
+        let policy = self.options.ranges.unwrap_or(RangePolicy::Error);
+        let range = match self.dtd.range_of(element) {
+            Some(range) if policy != RangePolicy::Ignore => range,
+            _ => return Ok(value.clone()),
+        };
+        let clamping = policy == RangePolicy::Clamp;
+        let result = match (type_, value, &range) {
+            (Some(Type::Int), &Value::Int(value), Property::IntRange(range)) => {
+                clamped(value, range, clamping, Value::Int)
+            }
+            (Some(Type::Uint), &Value::Uint(value), Property::UintRange(range)) => {
+                clamped(value, range, clamping, Value::Uint)
+            }
+            (Some(Type::Float), &Value::Float(value), Property::FloatRange(range)) => {
+                clamped(value, range, clamping, Value::Float)
+            }
+            (Some(Type::Date), &Value::Date(value), Property::DateRange(range)) => {
+                date_in_range(value, range).map(|()| Value::Date(value))
+            }
+            (Some(Type::String), Value::String(string), Property::StringRange(range)) => {
+                string_in_range(string, range).map(|()| value.clone())
+            }
+            (Some(Type::Binary), Value::Binary(binary), Property::BinaryRange(range)) => {
+                binary_in_range(binary, range).map(|()| value.clone())
+            }
+            // Values of the wrong type are caught as they're encoded.
+            _ => Ok(value.clone()),
+        };
+        result.map_err(|err| WriteTreeError::new(WriteTreeErrorKind::OutOfRange(err)))
+    }
+}
+
+// `value` if it's in `range`, or else the nearest value which is if `clamping`.
+fn clamped<T, I>(value: T, range: &[I], clamping: bool, wrap: fn(T) -> Value)
+    -> Result<Value, RangeError>
+    where T: Copy + fmt::Display, I: ClampRangeItem<T>
+{
+    match in_range(&value, range) {
+        Ok(()) => Ok(wrap(value)),
+        Err(err) => match clamp(value, range) {
+            Some(nearest) if clamping => Ok(wrap(nearest)),
+            _ => Err(err),
+        },
+    }
+}
+
+// `body`, the encoding of `value`, padded to the smallest size `sizes` allow if it's too short
+// and padding doesn't change what it means. Integers can be padded up to 8 bytes, and strings
+// to any size.
+fn fit(body: Vec<u8>, value: &Value, sizes: &SizeList) -> Result<Vec<u8>, WriteTreeError> {
+    let len = body.len() as u64;
+    if sizes.matches(len) {
+        return Ok(body);
+    }
+    let longest = match *value {
+        Value::Int(_) | Value::Uint(_) => 8,
+        Value::String(_) => u64::MAX,
+        _ => len,
+    };
+    let size = sizes.items().iter()
+        .filter_map(|item| match *item {
+            UintRangeItem::Single(size) => Some(size),
+            UintRangeItem::From { start } => Some(start.max(len)),
+            UintRangeItem::Bounded { start, end } => Some(start.max(len)).filter(|&s| s <= end),
+        })
+        .filter(|&size| size > len && size <= longest)
+        .min();
+    let padding = match size {
+        Some(size) => (size - len) as usize,
+        None => {
+            let kind = WriteTreeErrorKind::SizeNotAllowed { size: len, allowed: sizes.to_string() };
+            return Err(WriteTreeError::new(kind));
+        }
+    };
+    Ok(match *value {
+        // Integers are big-endian, so they're padded at the front, extending their sign.
+        Value::Int(_) | Value::Uint(_) => {
+            let negative = matches!(*value, Value::Int(raw) if raw < 0);
+            let mut padded = vec![if negative { 0xFF } else { 0 }; padding];
+            padded.extend(body);
+            padded
+        }
+        _ => {
+            let mut padded = body;
+            padded.resize(padded.len() + padding, 0);
+            padded
+        }
+    })
+}
@@ -0,0 +1,340 @@
+use std::cmp::Ordering;
+use std::error::Error;
+use std::fmt;
+use std::iter::FromIterator;
+use std::ops::Deref;
+use std::slice;
+use std::vec;
+
+use {render, Dtd, ErrorCode, Span};
+
+mod rules;
+
+// A validation rule inspects a DTD and reports anything wrong with it.
+type Rule = fn(&Dtd, &mut Vec<ValidationDiagnostic>);
+
+// Every rule run by `Dtd::validate`. Adding a rule means writing the function in `rules` and
+// listing it here.
+const RULES: &[Rule] = &[
+    rules::duplicate_type_names,
+    rules::duplicate_element_names,
+    rules::duplicate_ids,
+    rules::unknown_types,
+    rules::unknown_parents,
+    rules::mismatched_properties,
+    rules::shadowed_builtin_types,
+    rules::deprecated_parents,
+    rules::invalid_version_ranges,
+];
+
+impl<'a> Dtd<'a> {
+    /// Runs every validation rule against this EDTD, returning what they found in a deterministic
+    /// order. The elements of each DocType are validated separately, so those of different
+    /// DocTypes may share names and ids.
+    pub fn validate(&self) -> Diagnostics {
+        let mut diagnostics = Vec::new();
+        for rule in RULES {
+            rule(self, &mut diagnostics);
+        }
+        // The header and types are shared, so what's found in them is only reported once.
+        for &(doc_type, _) in &self.doc_types {
+            let dtd = self.select_doc_type(doc_type).expect("labeled DocTypes can be selected");
+            let mut found = Vec::new();
+            for rule in RULES {
+                rule(&dtd, &mut found);
+            }
+            for diagnostic in found {
+                if !diagnostics.contains(&diagnostic) {
+                    diagnostics.push(diagnostic);
+                }
+            }
+        }
+        diagnostics.into_iter().collect()
+    }
+
+    /// Like `validate`, but fails if any `Severity::Error` diagnostic is reported. On success, the
+    /// remaining (less severe) diagnostics are returned.
+    pub fn validate_strict(&self) -> Result<Diagnostics, Diagnostics> {
+        let diagnostics = self.validate();
+        if diagnostics.has_errors() {
+            Err(diagnostics)
+        } else {
+            Ok(diagnostics)
+        }
+    }
+}
+
+/// How serious a validation finding is.
+///
+/// Severities are ordered from least to most severe, so `Severity::Error` compares greatest.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Severity {
+    /// Purely informational; the EDTD is fine as written.
+    Info,
+    /// The EDTD is usable, but probably doesn't mean what its author intended.
+    Warning,
+    /// The EDTD is invalid.
+    Error,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match *self {
+            Severity::Info => "info",
+            Severity::Warning => "warning",
+            Severity::Error => "error",
+        })
+    }
+}
+
+/// A single finding produced by a validation rule.
+///
+/// Every rule reports its findings through this type. The code is stable across releases and is
+/// the thing tools should match on; the message is meant for humans and may change.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ValidationDiagnostic {
+    code: ErrorCode,
+    severity: Severity,
+    subject: String,
+    message: String,
+    span: Option<Span>,
+}
+
+impl ValidationDiagnostic {
+    /// Creates a new diagnostic about the element or type named `subject`.
+    pub fn new<S, M>(code: ErrorCode, severity: Severity, subject: S, message: M) -> Self
+        where S: Into<String>,
+              M: Into<String>
+    {
+        ValidationDiagnostic {
+            code,
+            severity,
+            subject: subject.into(),
+            message: message.into(),
+            span: None,
+        }
+    }
+
+    /// Creates a new `Severity::Error` diagnostic.
+    pub fn error<S, M>(code: ErrorCode, subject: S, message: M) -> Self
+        where S: Into<String>,
+              M: Into<String>
+    {
+        Self::new(code, Severity::Error, subject, message)
+    }
+
+    /// Creates a new `Severity::Warning` diagnostic.
+    pub fn warning<S, M>(code: ErrorCode, subject: S, message: M) -> Self
+        where S: Into<String>,
+              M: Into<String>
+    {
+        Self::new(code, Severity::Warning, subject, message)
+    }
+
+    /// Creates a new `Severity::Info` diagnostic.
+    pub fn info<S, M>(code: ErrorCode, subject: S, message: M) -> Self
+        where S: Into<String>,
+              M: Into<String>
+    {
+        Self::new(code, Severity::Info, subject, message)
+    }
+
+    /// Attaches the location in the source text this diagnostic refers to.
+    pub fn with_span(mut self, span: Span) -> Self {
+        self.span = Some(span);
+        self
+    }
+
+    /// The stable identifier of the rule which produced this diagnostic, e.g.
+    /// `ErrorCode::DuplicateId`.
+    pub fn code(&self) -> ErrorCode {
+        self.code
+    }
+
+    /// How serious this diagnostic is.
+    pub fn severity(&self) -> Severity {
+        self.severity
+    }
+
+    /// The name of the element or type this diagnostic is about.
+    pub fn subject(&self) -> &str {
+        &self.subject
+    }
+
+    /// A human readable description of the problem.
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+
+    /// Where in the source text the problem is, if known.
+    pub fn span(&self) -> Option<Span> {
+        self.span
+    }
+
+    /// True if this diagnostic has `Severity::Error`.
+    pub fn is_error(&self) -> bool {
+        self.severity == Severity::Error
+    }
+
+    /// True if this diagnostic has `Severity::Warning`.
+    pub fn is_warning(&self) -> bool {
+        self.severity == Severity::Warning
+    }
+
+    /// True if this diagnostic has `Severity::Info`.
+    pub fn is_note(&self) -> bool {
+        self.severity == Severity::Info
+    }
+
+    /// Renders the diagnostic like rustc does, with the line of `source` its span starts on and
+    /// the span underlined. Diagnostics without a span are rendered without any source.
+    pub fn render(&self, source: &str) -> String {
+        let header = format!("{}[{}]: {}: {}", self.severity, self.code, self.subject,
+                             self.message);
+        match self.span {
+            Some(span) => render::render(source, &header, span),
+            None => header,
+        }
+    }
+}
+
+impl fmt::Display for ValidationDiagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}[{}]", self.severity, self.code)?;
+        if let Some(span) = self.span {
+            write!(f, " at {}..{}", span.start, span.end)?;
+        }
+        write!(f, ": {}: {}", self.subject, self.message)
+    }
+}
+
+impl Error for ValidationDiagnostic {}
+
+// Diagnostics sort by position in the source first (unpositioned ones last), then most severe
+// first, then by code and subject, so that output is stable no matter which order rules ran in.
+impl Ord for ValidationDiagnostic {
+    fn cmp(&self, other: &Self) -> Ordering {
+        let position = match (self.span, other.span) {
+            (Some(a), Some(b)) => a.cmp(&b),
+            (Some(_), None) => Ordering::Less,
+            (None, Some(_)) => Ordering::Greater,
+            (None, None) => Ordering::Equal,
+        };
+
+        position
+            .then_with(|| other.severity.cmp(&self.severity))
+            .then_with(|| self.code.cmp(&other.code))
+            .then_with(|| self.subject.cmp(&other.subject))
+            .then_with(|| self.message.cmp(&other.message))
+    }
+}
+
+impl PartialOrd for ValidationDiagnostic {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// Everything found while parsing and validating an EDTD, from errors which make it unusable down
+/// to notes, kept in the order `ValidationDiagnostic`s sort in. Dereferences to a slice of them.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct Diagnostics(Vec<ValidationDiagnostic>);
+
+impl Diagnostics {
+    /// Creates an empty collection.
+    pub fn new() -> Self {
+        Diagnostics(Vec::new())
+    }
+
+    /// Adds a diagnostic in its place in the order.
+    pub fn push(&mut self, diagnostic: ValidationDiagnostic) {
+        let idx = match self.0.binary_search(&diagnostic) {
+            Ok(idx) | Err(idx) => idx,
+        };
+        self.0.insert(idx, diagnostic);
+    }
+
+    /// The `Severity::Error` diagnostics.
+    pub fn errors(&self) -> impl Iterator<Item = &ValidationDiagnostic> {
+        self.0.iter().filter(|d| d.is_error())
+    }
+
+    /// The `Severity::Warning` diagnostics.
+    pub fn warnings(&self) -> impl Iterator<Item = &ValidationDiagnostic> {
+        self.0.iter().filter(|d| d.is_warning())
+    }
+
+    /// The `Severity::Info` diagnostics.
+    pub fn notes(&self) -> impl Iterator<Item = &ValidationDiagnostic> {
+        self.0.iter().filter(|d| d.is_note())
+    }
+
+    /// The severity of the most severe diagnostic, or `None` if there are none.
+    pub fn max_severity(&self) -> Option<Severity> {
+        self.0.iter().map(ValidationDiagnostic::severity).max()
+    }
+
+    /// True if any diagnostic has `Severity::Error`.
+    pub fn has_errors(&self) -> bool {
+        self.max_severity() == Some(Severity::Error)
+    }
+
+    /// Makes every warning an error, for builds which shouldn't let them by.
+    pub fn promote_warnings(&mut self) {
+        for diagnostic in &mut self.0 {
+            if diagnostic.severity == Severity::Warning {
+                diagnostic.severity = Severity::Error;
+            }
+        }
+        self.0.sort();
+    }
+
+    /// The diagnostics, in order.
+    pub fn into_vec(self) -> Vec<ValidationDiagnostic> {
+        self.0
+    }
+}
+
+impl Deref for Diagnostics {
+    type Target = [ValidationDiagnostic];
+
+    fn deref(&self) -> &[ValidationDiagnostic] {
+        &self.0
+    }
+}
+
+impl FromIterator<ValidationDiagnostic> for Diagnostics {
+    fn from_iter<I: IntoIterator<Item = ValidationDiagnostic>>(iter: I) -> Self {
+        let mut diagnostics: Vec<_> = iter.into_iter().collect();
+        diagnostics.sort();
+        Diagnostics(diagnostics)
+    }
+}
+
+impl Extend<ValidationDiagnostic> for Diagnostics {
+    fn extend<I: IntoIterator<Item = ValidationDiagnostic>>(&mut self, iter: I) {
+        self.0.extend(iter);
+        self.0.sort();
+    }
+}
+
+impl IntoIterator for Diagnostics {
+    type Item = ValidationDiagnostic;
+    type IntoIter = vec::IntoIter<ValidationDiagnostic>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.into_iter()
+    }
+}
+
+impl<'d> IntoIterator for &'d Diagnostics {
+    type Item = &'d ValidationDiagnostic;
+    type IntoIter = slice::Iter<'d, ValidationDiagnostic>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.iter()
+    }
+}
+
+#[cfg(test)]
+mod tests;
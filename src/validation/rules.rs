@@ -0,0 +1,185 @@
+use std::collections::{HashMap, HashSet};
+
+use suggest::did_you_mean;
+use {BUILTIN_TYPES, Dtd, ErrorCode, Type};
+use super::ValidationDiagnostic;
+
+pub fn duplicate_type_names(dtd: &Dtd, out: &mut Vec<ValidationDiagnostic>) {
+    let mut seen = HashSet::new();
+    for new_type in &dtd.types {
+        if !seen.insert(new_type.name()) {
+            out.push(ValidationDiagnostic::error(
+                ErrorCode::DuplicateTypeName,
+                new_type.name(),
+                "type is defined more than once"
+            ));
+        }
+    }
+}
+
+pub fn duplicate_element_names(dtd: &Dtd, out: &mut Vec<ValidationDiagnostic>) {
+    let mut seen = HashSet::new();
+    for element in dtd.all_elements() {
+        if !seen.insert(element.name) {
+            out.push(ValidationDiagnostic::error(
+                ErrorCode::DuplicateElementName,
+                element.name,
+                "element is defined more than once"
+            ));
+        }
+    }
+}
+
+pub fn duplicate_ids(dtd: &Dtd, out: &mut Vec<ValidationDiagnostic>) {
+    let mut seen = HashMap::new();
+    for element in dtd.all_elements() {
+        if let Some(first) = seen.insert(element.id, element.name) {
+            out.push(ValidationDiagnostic::error(
+                ErrorCode::DuplicateId,
+                element.name,
+                format!("id {:#x} is already used by {}", element.id, first)
+            ));
+        }
+    }
+}
+
+pub fn unknown_types(dtd: &Dtd, out: &mut Vec<ValidationDiagnostic>) {
+    let names = || {
+        let builtins = BUILTIN_TYPES.iter().map(|&(name, _)| name);
+        dtd.types.iter().map(|new_type| new_type.name()).chain(builtins)
+    };
+    for element in dtd.all_elements() {
+        if let Type::Name(name) = element.type_ {
+            if dtd.new_type(name).is_none() {
+                out.push(ValidationDiagnostic::error(
+                    ErrorCode::UnknownType,
+                    element.name,
+                    format!("type {} is not defined{}", name, did_you_mean(name, names()))
+                ));
+            }
+        }
+    }
+}
+
+pub fn unknown_parents(dtd: &Dtd, out: &mut Vec<ValidationDiagnostic>) {
+    let elements = dtd.all_elements();
+    let names: HashSet<_> = elements.iter().map(|e| e.name).collect();
+
+    for element in &elements {
+        for parent in element.parent.iter().flat_map(|p| p.iter()) {
+            if !names.contains(parent) {
+                out.push(ValidationDiagnostic::error(
+                    ErrorCode::UnknownParent,
+                    element.name,
+                    format!("parent {} is not defined{}", parent,
+                            did_you_mean(parent, elements.iter().map(|e| e.name)))
+                ));
+            }
+        }
+    }
+}
+
+pub fn mismatched_properties(dtd: &Dtd, out: &mut Vec<ValidationDiagnostic>) {
+    for element in dtd.all_elements() {
+        // Unknown types are reported elsewhere.
+        let base = match dtd.resolve_type(element.type_) {
+            Some(base) => base,
+            None => continue,
+        };
+
+        let props = element.default.iter().chain(element.range.iter());
+        for prop in props.filter(|p| p.value_type() != Some(base)) {
+            let kind = if prop.is_default() { "default" } else { "range" };
+            out.push(ValidationDiagnostic::error(
+                ErrorCode::MismatchedProperty,
+                element.name,
+                format!("{} is not valid for an element of type {:?}", kind, base)
+            ));
+        }
+    }
+}
+
+pub fn shadowed_builtin_types(dtd: &Dtd, out: &mut Vec<ValidationDiagnostic>) {
+    let is_builtin = |name| Type::builtin(name).is_some();
+
+    for new_type in dtd.types.iter().filter(|t| is_builtin(t.name())) {
+        out.push(ValidationDiagnostic::warning(
+            ErrorCode::ShadowedBuiltinType,
+            new_type.name(),
+            format!(
+                "type has the same name as the built-in type; every use of {} refers to the \
+                 built-in, so this definition is unreachable",
+                new_type.name()
+            )
+        ));
+    }
+
+    for element in dtd.all_elements().into_iter().filter(|e| is_builtin(e.name)) {
+        out.push(ValidationDiagnostic::warning(
+            ErrorCode::ShadowedBuiltinType,
+            element.name,
+            "element has the same name as a built-in type"
+        ));
+    }
+}
+
+// An element which isn't deprecated, but can only appear inside one which is, will go out of use
+// with its parent, whether or not that was meant.
+pub fn deprecated_parents(dtd: &Dtd, out: &mut Vec<ValidationDiagnostic>) {
+    let elements = dtd.all_elements();
+    let mut parents: HashMap<&str, Vec<&str>> = HashMap::new();
+    for container in elements.iter().filter(|e| e.type_ == Type::Container) {
+        for child in dtd.children_of(container) {
+            parents.entry(child.name).or_default().push(container.name);
+        }
+    }
+    let deprecated: HashSet<_> = elements.iter()
+        .filter(|e| e.deprecation().is_some())
+        .map(|e| e.name)
+        .collect();
+
+    for element in elements.iter().filter(|e| !deprecated.contains(e.name)) {
+        let parent = match parents.get(element.name).map(Vec::as_slice) {
+            Some(&[parent]) => parent,
+            _ => continue,
+        };
+        if deprecated.contains(parent) {
+            out.push(ValidationDiagnostic::warning(
+                ErrorCode::DeprecatedParent,
+                element.name,
+                format!("element can only appear inside {}, which is deprecated", parent)
+            ));
+        }
+    }
+}
+
+pub fn invalid_version_ranges(dtd: &Dtd, out: &mut Vec<ValidationDiagnostic>) {
+    let doc_type_version = dtd.header_uint("DocTypeVersion");
+    for element in dtd.all_elements() {
+        if let (Some(minver), Some(maxver)) = (element.minver, element.maxver) {
+            if minver > maxver {
+                out.push(ValidationDiagnostic::error(
+                    ErrorCode::InvalidVersionRange,
+                    element.name,
+                    format!("minver {} is greater than maxver {}", minver, maxver)
+                ));
+            }
+        }
+        let latest = match doc_type_version {
+            Some(latest) => latest,
+            None => continue,
+        };
+        let versions = [("minver", element.minver), ("maxver", element.maxver)];
+        for &(property, version) in &versions {
+            match version {
+                Some(version) if version > latest => out.push(ValidationDiagnostic::error(
+                    ErrorCode::InvalidVersionRange,
+                    element.name,
+                    format!("{} {} is greater than the DocTypeVersion, {}", property, version,
+                            latest)
+                )),
+                _ => {}
+            }
+        }
+    }
+}
@@ -0,0 +1,231 @@
+use super::*;
+
+#[test]
+fn test_display() {
+    let diag =
+        ValidationDiagnostic::error(ErrorCode::DuplicateId, "Segment", "duplicate id 0x18538067");
+    assert_eq!(diag.to_string(), "error[V0011]: Segment: duplicate id 0x18538067");
+
+    let diag = ValidationDiagnostic::warning(ErrorCode::ShadowedBuiltinType, "Foo", "something odd")
+        .with_span(Span { start: 4, end: 10 });
+    assert_eq!(diag.to_string(), "warning[V0001] at 4..10: Foo: something odd");
+}
+
+#[test]
+fn test_accessors() {
+    let diag = ValidationDiagnostic::info(ErrorCode::UnknownType, "Foo", "note")
+        .with_span(Span { start: 1, end: 2 });
+    assert_eq!(diag.code(), ErrorCode::UnknownType);
+    assert_eq!(diag.severity(), Severity::Info);
+    assert_eq!(diag.subject(), "Foo");
+    assert_eq!(diag.message(), "note");
+    assert_eq!(diag.span(), Some(Span { start: 1, end: 2 }));
+    assert!(!diag.is_error());
+}
+
+#[test]
+fn test_ordering() {
+    let mut diags = vec![
+        ValidationDiagnostic::warning(ErrorCode::ShadowedBuiltinType, "B", "unpositioned warning"),
+        ValidationDiagnostic::error(ErrorCode::DuplicateId, "B", "unpositioned error"),
+        ValidationDiagnostic::info(ErrorCode::UnknownType, "A", "late")
+            .with_span(Span { start: 20, end: 21 }),
+        ValidationDiagnostic::warning(ErrorCode::UnknownParent, "A", "early")
+            .with_span(Span { start: 0, end: 5 }),
+        ValidationDiagnostic::error(ErrorCode::DuplicateTypeName, "A", "unpositioned error"),
+    ];
+    diags.sort();
+
+    let codes: Vec<_> = diags.iter().map(|d| d.code()).collect();
+    assert_eq!(codes, vec![
+        ErrorCode::UnknownParent,
+        ErrorCode::UnknownType,
+        ErrorCode::DuplicateTypeName,
+        ErrorCode::DuplicateId,
+        ErrorCode::ShadowedBuiltinType,
+    ]);
+}
+
+#[test]
+fn test_validate() {
+    let dtd = ::parse_dtd(include_bytes!("../../tests/dtd0"), &Default::default()).unwrap();
+    assert!(dtd.validate().is_empty());
+    assert_eq!(dtd.validate_strict(), Ok(Diagnostics::new()));
+
+    let dtd = ::parse_dtd(include_bytes!("../../tests/dtd1"), &Default::default()).unwrap();
+    let codes: Vec<_> = dtd.validate().iter().map(|d| d.code()).collect();
+    assert_eq!(codes, vec![
+        ErrorCode::DuplicateId,
+        ErrorCode::DuplicateElementName,
+        ErrorCode::UnknownType,
+        ErrorCode::UnknownParent,
+    ]);
+    assert!(dtd.validate_strict().is_err());
+}
+
+#[test]
+fn test_suggestions() {
+    let dtd = ::parse_dtd(include_bytes!("../../tests/dtd1"), &Default::default()).unwrap();
+    let messages: Vec<_> = dtd.validate().iter()
+        .filter(|d| d.code() == ErrorCode::UnknownType || d.code() == ErrorCode::UnknownParent)
+        .map(|d| d.message().to_string())
+        .collect();
+    assert_eq!(messages, vec![
+        "type missing_type is not defined",
+        "parent Segmnet is not defined; did you mean `Segment`?",
+    ]);
+
+    let dtd = ::parse_dtd(b"
+        define types { Flag := uint [ range:0..1; ] }
+        define elements {
+            Tracks := 1654ae6b container;
+            TrackUID := 73c5 uint64 [ parent:tracks; ]
+            Enabled := b9 Flga [ parent:TrackUID; ]
+        }
+    ", &Default::default()).unwrap();
+    let messages: Vec<_> = dtd.validate().iter().map(|d| d.message().to_string()).collect();
+    assert_eq!(messages, vec![
+        "type Flga is not defined; did you mean `Flag`?",
+        "type uint64 is not defined; did you mean `uint`?",
+        "parent tracks is not defined; did you mean `Tracks`?",
+    ]);
+}
+
+#[test]
+fn test_parse_and_validate() {
+    let options = ::ParseOptions { validate: Some(Severity::Error), ..Default::default() };
+    assert!(::parse_dtd(include_bytes!("../../tests/dtd0"), &options).is_ok());
+    match ::parse_dtd(include_bytes!("../../tests/dtd1"), &options) {
+        Err(::DtdError::Invalid(diagnostics)) => {
+            assert_eq!(diagnostics.len(), 4);
+            assert!(diagnostics.iter().all(|d| d.span().is_some()));
+            assert!(diagnostics.windows(2).all(|pair| pair[0] <= pair[1]));
+        }
+        other => panic!("{:?}", other),
+    }
+}
+
+#[test]
+fn test_shadowed_builtin_types() {
+    let dtd = ::parse_dtd(include_bytes!("../../tests/dtd3"), &Default::default()).unwrap();
+    let diagnostics = dtd.validate();
+    let subjects: Vec<_> = diagnostics.iter().map(|d| (d.code(), d.subject())).collect();
+    assert_eq!(subjects, vec![
+        (ErrorCode::ShadowedBuiltinType, "float"),
+        (ErrorCode::ShadowedBuiltinType, "int"),
+    ]);
+    assert!(dtd.validate_strict().is_ok());
+
+    let options = ::ParseOptions { validate: Some(Severity::Warning), ..Default::default() };
+    assert!(::parse_dtd(include_bytes!("../../tests/dtd3"), &options).is_err());
+}
+
+#[test]
+fn test_deprecated_parents() {
+    let dtd = ::parse_dtd(b"
+        define elements {
+            // @deprecated
+            Old := 81 container {
+                Child := 82 uint;
+                // @deprecated since 2
+                OldChild := 83 uint;
+            }
+            New := 84 container;
+            Shared := 85 uint [ parent:Old New; ]
+            Orphan := 86 uint [ parent:Old; ]
+        }
+    ", &Default::default()).unwrap();
+    let diagnostics = dtd.validate();
+    let subjects: Vec<_> = diagnostics.iter().map(|d| (d.code(), d.subject())).collect();
+    assert_eq!(subjects, vec![
+        (ErrorCode::DeprecatedParent, "Child"),
+        (ErrorCode::DeprecatedParent, "Orphan"),
+    ]);
+    assert!(diagnostics.iter().all(ValidationDiagnostic::is_warning));
+    assert_eq!(diagnostics[0].message(), "element can only appear inside Old, which is deprecated");
+}
+
+#[test]
+fn test_invalid_version_ranges() {
+    let dtd = ::parse_dtd(b"
+        declare header { DocTypeVersion := 3; }
+        define elements {
+            A := 81 uint [ minver:2; maxver:1; ]
+            B := 82 uint [ minver:1; maxver:3; ]
+            C := 83 uint [ minver:4; ]
+            D := 84 uint [ maxver:5; ]
+        }
+    ", &Default::default()).unwrap();
+    let diagnostics = dtd.validate();
+    let messages: Vec<_> = diagnostics.iter().map(|d| (d.subject(), d.message())).collect();
+    assert_eq!(messages, vec![
+        ("A", "minver 2 is greater than maxver 1"),
+        ("C", "minver 4 is greater than the DocTypeVersion, 3"),
+        ("D", "maxver 5 is greater than the DocTypeVersion, 3"),
+    ]);
+    assert!(diagnostics.iter().all(|d| d.code() == ErrorCode::InvalidVersionRange));
+    assert!(diagnostics.has_errors());
+}
+
+#[test]
+fn test_diagnostics() {
+    let mut diagnostics: Diagnostics = vec![
+        ValidationDiagnostic::info(ErrorCode::UnknownType, "A", "a note"),
+        ValidationDiagnostic::warning(ErrorCode::ShadowedBuiltinType, "B", "a warning"),
+    ].into_iter().collect();
+    assert_eq!(diagnostics.max_severity(), Some(Severity::Warning));
+    assert!(!diagnostics.has_errors());
+
+    diagnostics.push(ValidationDiagnostic::error(ErrorCode::DuplicateId, "C", "an error"));
+    assert_eq!(diagnostics.max_severity(), Some(Severity::Error));
+    let codes: Vec<_> = diagnostics.iter().map(|d| d.code()).collect();
+    assert_eq!(codes, vec![
+        ErrorCode::DuplicateId,
+        ErrorCode::ShadowedBuiltinType,
+        ErrorCode::UnknownType,
+    ]);
+    assert_eq!(diagnostics.errors().map(|d| d.subject()).collect::<Vec<_>>(), vec!["C"]);
+    assert_eq!(diagnostics.warnings().map(|d| d.subject()).collect::<Vec<_>>(), vec!["B"]);
+    assert_eq!(diagnostics.notes().map(|d| d.subject()).collect::<Vec<_>>(), vec!["A"]);
+
+    diagnostics.promote_warnings();
+    assert_eq!(diagnostics.errors().map(|d| d.subject()).collect::<Vec<_>>(), vec!["B", "C"]);
+    assert_eq!(diagnostics.warnings().count(), 0);
+    assert_eq!(diagnostics.notes().count(), 1);
+    assert_eq!(Diagnostics::new().max_severity(), None);
+}
+
+#[test]
+fn test_warnings_as_errors() {
+    let options = ::ParseOptions {
+        validate: Some(Severity::Error),
+        warnings_as_errors: true,
+        ..Default::default()
+    };
+    match ::parse_dtd(include_bytes!("../../tests/dtd3"), &options) {
+        Err(::DtdError::Invalid(diagnostics)) => {
+            assert_eq!(diagnostics.errors().count(), 2);
+            assert_eq!(diagnostics.warnings().count(), 0);
+        }
+        other => panic!("{:?}", other),
+    }
+}
+
+#[test]
+fn test_check_dtd() {
+    let options = ::ParseOptions { validate: Some(Severity::Error), ..Default::default() };
+    let (dtd, diagnostics) = ::check_dtd(include_bytes!("../../tests/dtd3"), &options);
+    assert!(dtd.is_some());
+    assert_eq!(diagnostics.max_severity(), Some(Severity::Warning));
+    assert!(diagnostics.iter().all(|d| d.span().is_some()));
+
+    let (dtd, diagnostics) = ::check_dtd(include_bytes!("../../tests/dtd1"), &options);
+    assert!(dtd.is_none());
+    assert_eq!(diagnostics.errors().count(), 4);
+
+    // Syntax errors are reported in the same way, each in the definition it's in.
+    let (dtd, diagnostics) = ::check_dtd(include_bytes!("../../tests/recovery0"), &options);
+    assert!(dtd.is_none());
+    assert!(diagnostics.len() > 1);
+    assert!(diagnostics.iter().all(|d| d.is_error() && d.code().is_parse_error()));
+}
@@ -0,0 +1,91 @@
+//! Picks out the elements of an EDTD which are part of a particular DocTypeVersion, by their
+//! `minver:` and `maxver:` properties.
+
+use std::collections::HashSet;
+
+use {flatten, Dtd, Element, HeaderStatement, Span, Spanned};
+
+impl<'a> Dtd<'a> {
+    /// The EDTD of one version of its DocType: only the elements which are part of
+    /// DocTypeVersion `version`, with this EDTD's types, and the header's `DocTypeVersion`
+    /// changed to it.
+    ///
+    /// An element is part of the versions from its `minver:` to its `maxver:`, or every version
+    /// if it has neither. When an element is left out, so is everything nested inside it, and
+    /// it's dropped from the `parent:` of elements defined elsewhere; those defined at the top
+    /// with no parent left are left out too, since they could no longer appear anywhere.
+    /// Containers whose children are all left out are kept, empty, as they can still appear in
+    /// documents of that version.
+    pub fn subset_for_version(&self, version: u64) -> Dtd<'a> {
+        let mut dtd = self.clone();
+        let statement = HeaderStatement::Uint { name: "DocTypeVersion", value: version };
+        match dtd.header.iter_mut().find(|statement| statement.name() == "DocTypeVersion") {
+            Some(existing) => existing.node = statement,
+            None => dtd.header.push(Spanned::new(statement, Span { start: 0, end: 0 })),
+        }
+
+        let groups = Some(&mut dtd.elements).into_iter()
+            .chain(dtd.doc_types.iter_mut().map(|group| &mut group.1));
+        for elements in groups {
+            let all = names(elements);
+            retain(elements, &|element| element.is_in_version(version));
+            // An element defined at the top whose parents have all been left out can't appear
+            // anywhere, and leaving it out may do the same to another.
+            loop {
+                let left = names(elements);
+                let removed: HashSet<_> = all.difference(&left).cloned().collect();
+                let before = elements.len();
+                elements.retain(|element| match element.parent {
+                    Some(ref parents) => parents.iter().any(|parent| !removed.contains(parent)),
+                    None => true,
+                });
+                if elements.len() == before {
+                    prune_parents(elements, &removed);
+                    break;
+                }
+            }
+        }
+        dtd.assembled()
+    }
+}
+
+impl<'a> Element<'a> {
+    // Whether the element is part of DocTypeVersion `version`.
+    pub fn is_in_version(&self, version: u64) -> bool {
+        self.minver.map_or(true, |minver| minver <= version) &&
+            self.maxver.map_or(true, |maxver| version <= maxver)
+    }
+}
+
+// Keeps the elements `keep` is true of, and the same among their children, at every depth.
+fn retain<'a, F>(elements: &mut Vec<Spanned<Element<'a>>>, keep: &F)
+    where F: Fn(&Element<'a>) -> bool
+{
+    elements.retain(|element| keep(element));
+    for element in elements {
+        retain(&mut element.node.children, keep);
+    }
+}
+
+// The names of every element in `elements`, at any depth.
+fn names<'a>(elements: &[Spanned<Element<'a>>]) -> HashSet<&'a str> {
+    flatten(elements).iter().map(|element| element.name).collect()
+}
+
+// Drops the names of the `removed` elements from every `parent:`. Elements nested inside others
+// with no parent left can still appear there, so they lose their `parent:`.
+fn prune_parents(elements: &mut [Spanned<Element>], removed: &HashSet<&str>) {
+    for element in elements {
+        let element = &mut element.node;
+        if let Some(ref mut parents) = element.parent {
+            parents.retain(|parent| !removed.contains(parent));
+        }
+        if element.parent.as_ref().is_some_and(Vec::is_empty) {
+            element.parent = None;
+        }
+        prune_parents(&mut element.children, removed);
+    }
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,76 @@
+use super::*;
+use parse_dtd;
+
+const DTD: &str = "
+    declare header { DocType := \"doc\"; DocTypeVersion := 4; }
+    define elements {
+        Segment := 18538067 container {
+            Info := 1549a966 container {
+                Title := 7ba9 string;
+                SegmentName := 7ba8 string [ maxver:2; ]
+            }
+            Tracks := 1654ae6b container [ minver:2; ] {
+                TrackEntry := ae container;
+            }
+            Legacy := 4d80 container [ maxver:1; ] {
+                LegacyFlag := 4d81 uint;
+            }
+            Flags := 4d82 container [ minver:3; maxver:3; ] {
+                Flag := 4d83 uint [ minver:4; ]
+            }
+        }
+        CodecPrivate := 63a2 binary [ parent:TrackEntry; ]
+        Shared := 4d84 uint [ parent:Legacy, Info; ]
+        LegacyOnly := 4d85 container [ parent:Legacy; ]
+        LegacyChild := 4d86 uint [ parent:LegacyOnly; ]
+    }
+";
+
+fn names(dtd: &Dtd) -> Vec<&str> {
+    dtd.all_elements().iter().map(|element| element.name).collect()
+}
+
+#[test]
+fn test_subset_for_version() {
+    let dtd = parse_dtd(DTD, &Default::default()).unwrap();
+
+    let v1 = dtd.subset_for_version(1);
+    assert_eq!(v1.header_uint("DocTypeVersion"), Some(1));
+    assert_eq!(names(&v1), vec![
+        "Segment", "Info", "Title", "SegmentName", "Legacy", "LegacyFlag", "Shared", "LegacyOnly",
+        "LegacyChild",
+    ]);
+    assert_eq!(v1.all_elements()[6].parent, Some(vec!["Legacy", "Info"]));
+
+    // Leaving out a container leaves out what's nested in it, and what can only appear in it.
+    let v2 = dtd.subset_for_version(2);
+    assert_eq!(names(&v2), vec![
+        "Segment", "Info", "Title", "SegmentName", "Tracks", "TrackEntry", "CodecPrivate",
+        "Shared",
+    ]);
+    assert_eq!(v2.all_elements()[7].parent, Some(vec!["Info"]));
+
+    // A container whose children are all left out is kept.
+    let v3 = dtd.subset_for_version(3);
+    assert!(names(&v3).contains(&"Flags"));
+    assert!(!names(&v3).contains(&"Flag"));
+    assert!(v3.all_elements().iter().find(|e| e.name == "Flags").unwrap().children.is_empty());
+
+    let v4 = dtd.subset_for_version(4);
+    assert_eq!(names(&v4), vec![
+        "Segment", "Info", "Title", "Tracks", "TrackEntry", "CodecPrivate", "Shared",
+    ]);
+    assert!(v4.validate().is_empty());
+    assert_eq!(dtd.subset_for_version(4).subset_for_version(4), v4);
+}
+
+#[test]
+fn test_without_versions() {
+    let dtd = parse_dtd("define elements { Void := ec binary [ parent:Missing; ] }",
+                        &Default::default()).unwrap();
+    let subset = dtd.subset_for_version(7);
+    assert_eq!(subset.header_uint("DocTypeVersion"), Some(7));
+    // Parents which were never defined aren't this method's concern.
+    assert_eq!(subset.all_elements()[0].parent, Some(vec!["Missing"]));
+    assert_eq!(subset.elements, dtd.elements);
+}
@@ -0,0 +1,395 @@
+//! Writes a `Dtd` out as an XML schema, the inverse of `Dtd::from_xml_schema`.
+
+use std::fmt::Write;
+
+use super::unsupported;
+use {date, BinaryRangeItem, Cardinality, DateRangeItem, Diagnostics, Dtd, Element,
+     FloatRangeItem, HeaderStatement, IntRangeItem, Level, NewType, Property, SizeList, Spanned,
+     StringRangeItem, Type, UintRangeItem};
+
+impl<'a> Dtd<'a> {
+    /// Writes this EDTD as an EBML schema in the XML format of RFC 8794.
+    ///
+    /// Every element gets an `<element>` whose `path` is worked out from where it's nested, or
+    /// from its first `parent:`; elements with a `level:` become global elements. Types defined in
+    /// the EDTD are written as the built-in types they're based on, and comments become
+    /// `<documentation>`. Anything an XML schema has no equivalent of, like `ordered:` or a range
+    /// with more than one item, is left out and reported in the returned warnings.
+    ///
+    /// Importing the result with `Dtd::from_xml_schema` gives back an equal `Dtd` for EDTDs which
+    /// only use what both formats have.
+    pub fn to_xml_schema(&self) -> (String, Diagnostics) {
+        let mut exporter = Exporter {
+            dtd: self,
+            elements: Vec::new(),
+            paths: Vec::new(),
+            diagnostics: Diagnostics::new(),
+        };
+        exporter.flatten(&self.elements, None);
+        exporter.paths = vec![PathState::Unknown; exporter.elements.len()];
+        for idx in 0..exporter.elements.len() {
+            exporter.path(idx);
+        }
+        for new_type in &self.types {
+            exporter.diagnostics.push(unsupported(new_type.name(), format!(
+                "XML schemas can't define types, so elements of this type are written as `{}`",
+                xml_type(new_type.base_type()).unwrap()
+            ), new_type.span));
+        }
+
+        let mut out = String::from("<?xml version=\"1.0\" encoding=\"utf-8\"?>\n");
+        out.push_str("<EBMLSchema xmlns=\"urn:ietf:rfc:8794\"");
+        exporter.header(&mut out);
+        out.push_str(">\n");
+        for idx in 0..exporter.elements.len() {
+            exporter.element(idx, &mut out);
+        }
+        out.push_str("</EBMLSchema>\n");
+        (out, exporter.diagnostics)
+    }
+}
+
+struct Exporter<'d, 'a: 'd> {
+    dtd: &'d Dtd<'a>,
+    // Every element depth-first, with the index of the one it's nested inside.
+    elements: Vec<(&'d Spanned<Element<'a>>, Option<usize>)>,
+    paths: Vec<PathState>,
+    diagnostics: Diagnostics,
+}
+
+#[derive(Clone)]
+enum PathState {
+    Unknown,
+    // Being worked out, so an element which needs it names its parents in a circle.
+    Pending,
+    Known(String),
+}
+
+impl<'d, 'a> Exporter<'d, 'a> {
+    fn flatten(&mut self, elements: &'d [Spanned<Element<'a>>], parent: Option<usize>) {
+        for element in elements {
+            let idx = self.elements.len();
+            self.elements.push((element, parent));
+            self.flatten(&element.children, Some(idx));
+        }
+    }
+
+    fn warn<M: Into<String>>(&mut self, idx: usize, message: M) {
+        let element = self.elements[idx].0;
+        self.diagnostics.push(unsupported(element.name, message, element.span));
+    }
+
+    // The path of the element at `idx`, like `\Segment\Tags\Tag\+SimpleTag`.
+    fn path(&mut self, idx: usize) -> String {
+        match self.paths[idx] {
+            PathState::Known(ref path) => return path.clone(),
+            PathState::Pending => return String::new(),
+            PathState::Unknown => {}
+        }
+        self.paths[idx] = PathState::Pending;
+
+        let (element, nested_in) = self.elements[idx];
+        // Being its own parent is how an EDTD says an element is recursive.
+        let is_recursive = element.parent.as_ref()
+            .is_some_and(|parents| parents.contains(&element.name));
+        let parents: Vec<&'a str> = element.parent.iter()
+            .flat_map(|parents| parents.iter().cloned())
+            .filter(|&parent| parent != element.name)
+            .collect();
+        let atom = format!("{}{}", if is_recursive { "+" } else { "" }, element.name);
+
+        let path = if let Some(ref level) = element.level {
+            if nested_in.is_some() || !parents.is_empty() {
+                self.warn(idx, "global elements can't be limited to inside particular elements, \
+                                so it may be anywhere at its levels");
+            }
+            match *level {
+                Level::Open { start: 0 } => format!("\\(-\\){}", atom),
+                Level::Open { start } => format!("\\({}-\\){}", start, atom),
+                Level::Bounded { start, end } => format!("\\({}-{}\\){}", start, end, atom),
+            }
+        } else if let Some(parent) = nested_in {
+            if !parents.is_empty() {
+                self.warn(idx, "elements can only have one parent, so `parent:` was left out");
+            }
+            format!("{}\\{}", self.path(parent), atom)
+        } else if let Some(&parent) = parents.first() {
+            if parents.len() > 1 {
+                self.warn(idx, format!("elements can only have one parent, so only `{}` was kept",
+                                       parent));
+            }
+            let found = (0..self.elements.len()).find(|&other| {
+                self.elements[other].0.name == parent
+            });
+            match found.map(|other| self.path(other)) {
+                Some(ref path) if !path.is_empty() => format!("{}\\{}", path, atom),
+                _ => {
+                    self.warn(idx, format!("`{}` has no path, so the element was put at the top",
+                                           parent));
+                    format!("\\{}", atom)
+                }
+            }
+        } else {
+            format!("\\{}", atom)
+        };
+
+        self.paths[idx] = PathState::Known(path.clone());
+        path
+    }
+
+    fn header(&mut self, out: &mut String) {
+        let dtd = self.dtd;
+        for statement in &dtd.header {
+            let attribute = match (statement.name(), dtd.resolve_header(statement)) {
+                ("DocType", Some(HeaderStatement::String { value, .. })) => {
+                    Some(("docType", value.to_string()))
+                }
+                ("DocTypeVersion", Some(&HeaderStatement::Uint { value, .. })) => {
+                    Some(("version", value.to_string()))
+                }
+                ("EBMLVersion", Some(&HeaderStatement::Uint { value, .. })) => {
+                    Some(("ebml", value.to_string()))
+                }
+                _ => None,
+            };
+            match attribute {
+                Some((name, value)) => write_attribute(out, name, &value),
+                None => self.diagnostics.push(unsupported(
+                    statement.name(),
+                    "XML schemas have no equivalent of this header statement",
+                    statement.span,
+                )),
+            }
+        }
+    }
+
+    fn element(&mut self, idx: usize, out: &mut String) {
+        let element = &self.elements[idx].0.node;
+        let type_ = match self.dtd.resolve_type(element.type_).and_then(xml_type) {
+            Some(type_) => type_,
+            None => {
+                self.warn(idx, format!("the type `{}` isn't defined, so the element was left out",
+                                       element.type_.name()));
+                return;
+            }
+        };
+
+        out.push_str("  <element");
+        write_attribute(out, "name", element.name);
+        write_attribute(out, "path", &self.path(idx));
+        write_attribute(out, "id", &format!("0x{:X}", element.id));
+        write_attribute(out, "type", type_);
+        let (min_occurs, max_occurs) = match element.cardinality {
+            None => (None, None),
+            Some(Cardinality::ZeroOrMany) => (Some(0), None),
+            Some(Cardinality::ZeroOrOne) => (None, Some(1)),
+            Some(Cardinality::ExactlyOne) => (Some(1), Some(1)),
+            Some(Cardinality::OneOrMany) => (Some(1), None),
+        };
+        if let Some(min_occurs) = min_occurs {
+            write_attribute(out, "minOccurs", &min_occurs.to_string());
+        }
+        if let Some(max_occurs) = max_occurs {
+            write_attribute(out, "maxOccurs", &max_occurs.to_string());
+        }
+        if let Some(range) = self.dtd.range_of(element) {
+            match range_text(&range) {
+                Some(range) => write_attribute(out, "range", &range),
+                None => self.warn(idx, "XML schemas can only give one item in a range, so the \
+                                        range was left out"),
+            }
+        }
+        if let Some(default) = self.default_of(element) {
+            match default_text(&default) {
+                Some(default) => write_attribute(out, "default", &default),
+                None => self.warn(idx, "the default is too far from 2001 to be written, so it \
+                                        was left out"),
+            }
+        }
+        if let Some(SizeList(ref items)) = element.size {
+            match *items.as_ref() {
+                [ref item] => write_attribute(out, "length", &uint_text(item)),
+                _ => self.warn(idx, "XML schemas can only give one item in a size, so the size \
+                                     was left out"),
+            }
+        }
+        if let Some(allowed) = element.unknown_size_allowed {
+            write_attribute(out, "unknownsizeallowed", if allowed { "1" } else { "0" });
+        }
+        if element.parent.as_ref().is_some_and(|parents| parents.contains(&element.name)) {
+            write_attribute(out, "recursive", "1");
+        }
+        if let Some(minver) = element.minver {
+            write_attribute(out, "minver", &minver.to_string());
+        }
+        if let Some(maxver) = element.maxver {
+            write_attribute(out, "maxver", &maxver.to_string());
+        }
+        if element.ordered.is_some() {
+            self.warn(idx, "XML schemas have no equivalent of `ordered:`, so it was left out");
+        }
+
+        if element.comments.is_empty() && element.variants.is_none() {
+            out.push_str("/>\n");
+            return;
+        }
+        out.push_str(">\n");
+        if !element.comments.is_empty() {
+            let text: Vec<_> = element.comments.iter().map(|comment| comment.trim()).collect();
+            out.push_str("    <documentation lang=\"en\" purpose=\"definition\">");
+            escape(out, &text.join("\n"), false);
+            out.push_str("</documentation>\n");
+        }
+        if let Some(ref variants) = element.variants {
+            out.push_str("    <restriction>\n");
+            for &(label, value) in variants {
+                out.push_str("      <enum");
+                write_attribute(out, "value", &value.to_string());
+                write_attribute(out, "label", label);
+                out.push_str("/>\n");
+            }
+            out.push_str("    </restriction>\n");
+        }
+        out.push_str("  </element>\n");
+    }
+
+    // An element's own default, or if it has none, that of its type.
+    fn default_of(&self, element: &Element<'a>) -> Option<Property<'a>> {
+        if let Some(ref default) = element.default {
+            return Some(default.node.clone());
+        }
+        match (element.type_, self.dtd.new_type(element.type_.name())) {
+            (Type::Name(_), Some(new_type)) => type_default(new_type),
+            _ => None,
+        }
+    }
+}
+
+fn type_default<'a>(new_type: &NewType<'a>) -> Option<Property<'a>> {
+    match *new_type {
+        NewType::Int { default, .. } => default.map(Property::IntDefault),
+        NewType::Uint { default, .. } => default.map(Property::UintDefault),
+        NewType::Float { default, .. } => default.map(Property::FloatDefault),
+        NewType::Date { default, .. } => default.map(Property::DateDefault),
+        NewType::String { ref default, .. } => default.clone().map(Property::StringDefault),
+        NewType::Binary { ref default, .. } => default.clone().map(Property::BinaryDefault),
+    }
+}
+
+fn xml_type(type_: Type) -> Option<&'static str> {
+    match type_ {
+        Type::Int => Some("integer"),
+        Type::Uint => Some("uinteger"),
+        Type::Float => Some("float"),
+        Type::String => Some("utf-8"),
+        Type::Date => Some("date"),
+        Type::Binary => Some("binary"),
+        Type::Container => Some("master"),
+        Type::Name(_) => None,
+    }
+}
+
+// The value of a default in an XML schema. `None` for dates which don't fit in the nanoseconds
+// they're written as.
+fn default_text(default: &Property) -> Option<String> {
+    Some(match *default {
+        Property::IntDefault(value) => value.to_string(),
+        Property::UintDefault(value) => value.to_string(),
+        Property::FloatDefault(value) => value.to_string(),
+        Property::DateDefault(value) => date::nanos(&value)?.to_string(),
+        Property::StringDefault(ref value) => value.to_string(),
+        Property::BinaryDefault(ref value) => {
+            let mut text = String::from("0x");
+            for byte in value.iter() {
+                write!(text, "{:02X}", byte).unwrap();
+            }
+            text
+        }
+        _ => unreachable!(),
+    })
+}
+
+// The value of a range in an XML schema. `None` unless the range has exactly one item, and it
+// can be written.
+fn range_text(range: &Property) -> Option<String> {
+    fn single<T>(items: &[T]) -> Option<&T> {
+        match *items {
+            [ref item] => Some(item),
+            _ => None,
+        }
+    }
+
+    Some(match *range {
+        Property::IntRange(ref items) => match *single(items)? {
+            IntRangeItem::Single(value) => value.to_string(),
+            IntRangeItem::From { start } => format!(">={}", start),
+            IntRangeItem::To { end } => format!("<={}", end),
+            IntRangeItem::Bounded { start, end } => format!("{}-{}", start, end),
+        },
+        Property::UintRange(ref items) => uint_text(single(items)?),
+        Property::FloatRange(ref items) => match *single(items)? {
+            FloatRangeItem::From { start, include_start } => {
+                format!("{}{}", if include_start { ">=" } else { ">" }, start)
+            }
+            FloatRangeItem::To { end, include_end } => {
+                format!("{}{}", if include_end { "<=" } else { "<" }, end)
+            }
+            FloatRangeItem::Bounded { start, include_start: true, end, include_end: true } => {
+                format!("{}-{}", start, end)
+            }
+            FloatRangeItem::Bounded { start, include_start, end, include_end } => {
+                format!("{}{},{}{}", if include_start { ">=" } else { ">" }, start,
+                        if include_end { "<=" } else { "<" }, end)
+            }
+        },
+        Property::DateRange(ref items) => {
+            let nanoseconds = |value| date::nanos(&value);
+            match *single(items)? {
+                DateRangeItem::From { start } => format!(">={}", nanoseconds(start)?),
+                DateRangeItem::To { end } => format!("<={}", nanoseconds(end)?),
+                DateRangeItem::Bounded { start, end } => {
+                    format!("{}-{}", nanoseconds(start)?, nanoseconds(end)?)
+                }
+            }
+        }
+        Property::StringRange(ref items) => match *single(items)? {
+            StringRangeItem::Single(value) => value.to_string(),
+            StringRangeItem::From { start } => format!(">={}", start),
+            StringRangeItem::Bounded { start, end } => format!("{}-{}", start, end),
+        },
+        Property::BinaryRange(ref items) => match *single(items)? {
+            BinaryRangeItem::Single(value) => value.to_string(),
+            BinaryRangeItem::From { start } => format!(">={}", start),
+            BinaryRangeItem::Bounded { start, end } => format!("{}-{}", start, end),
+        },
+        _ => unreachable!(),
+    })
+}
+
+fn uint_text(item: &UintRangeItem) -> String {
+    match *item {
+        UintRangeItem::Single(value) => value.to_string(),
+        UintRangeItem::From { start } => format!(">={}", start),
+        UintRangeItem::Bounded { start, end } => format!("{}-{}", start, end),
+    }
+}
+
+fn write_attribute(out: &mut String, name: &str, value: &str) {
+    write!(out, " {}=\"", name).unwrap();
+    escape(out, value, true);
+    out.push('"');
+}
+
+fn escape(out: &mut String, text: &str, in_attribute: bool) {
+    for c in text.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' if in_attribute => out.push_str("&quot;"),
+            // Line breaks in attributes would be read back as spaces.
+            '\n' if in_attribute => out.push_str("&#10;"),
+            c => out.push(c),
+        }
+    }
+}
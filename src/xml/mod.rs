@@ -0,0 +1,656 @@
+//! Converts between EDTDs and EBML schemas written in the XML format of RFC 8794, which is how
+//! new-style schemas like Matroska's are published.
+
+mod export;
+mod reader;
+#[cfg(test)]
+mod tests;
+
+use std::borrow::Cow;
+use std::convert::TryFrom;
+
+use self::reader::{Attribute, Node};
+use {date, Cardinality, DateRangeItem, Diagnostics, Dtd, Element, ErrorCode, FloatRangeItem,
+     HeaderStatement, IntRangeItem, Level, Property, SizeList, Span, Spanned, Type, UintRangeItem,
+     ValidationDiagnostic};
+
+impl<'a> Dtd<'a> {
+    /// Imports an EBML schema written in the XML format of RFC 8794.
+    ///
+    /// Each `<element>` becomes an element definition, nested inside the one its `path` names as
+    /// its parent; global elements are defined at the top with a `level:` instead. `minOccurs` and
+    /// `maxOccurs` become its cardinality, `length` its size, `minver` and `maxver` its
+    /// `minver:` and `maxver:`, and `range` and `default` are converted to the element's type.
+    /// The schema's `docType`, `version` and `ebml` attributes become the `DocType`,
+    /// `DocTypeVersion` and `EBMLVersion` header statements.
+    ///
+    /// Anything the EDTD has no equivalent of, like `recurring` or `<restriction>`, is left out
+    /// with a warning. Fails if the input isn't well-formed, or if an element is missing a required
+    /// attribute or has an invalid one. Either way, the diagnostics have spans into `input`.
+    pub fn from_xml_schema(input: &'a str) -> Result<(Dtd<'a>, Diagnostics), Diagnostics> {
+        let mut diagnostics = Diagnostics::new();
+        let root = match reader::parse(input) {
+            Ok(root) => root,
+            Err(err) => {
+                let len = input[err.offset..].chars().next().map_or(0, char::len_utf8);
+                let span = Span { start: err.offset, end: err.offset + len };
+                diagnostics.push(ValidationDiagnostic::error(ErrorCode::MalformedXml, "XML",
+                                                             err.message)
+                    .with_span(span));
+                return Err(diagnostics);
+            }
+        };
+
+        let dtd = import(&root, &mut diagnostics);
+        if diagnostics.has_errors() {
+            Err(diagnostics)
+        } else {
+            Ok((dtd, diagnostics))
+        }
+    }
+}
+
+fn import<'a>(root: &Node<'a>, diagnostics: &mut Diagnostics) -> Dtd<'a> {
+    if root.name != "EBMLSchema" {
+        diagnostics.push(invalid(root.name, "the root element should be `<EBMLSchema>`",
+                                 root.span));
+    }
+
+    let mut header = Vec::new();
+    for attribute in &root.attributes {
+        let statement = match attribute.name {
+            "docType" => Some(HeaderStatement::String {
+                name: "DocType",
+                value: attribute.value.clone(),
+            }),
+            "version" | "ebml" => match parse_int(&attribute.value).map(u64::try_from) {
+                Some(Ok(value)) => {
+                    let name = if attribute.name == "version" {
+                        "DocTypeVersion"
+                    } else {
+                        "EBMLVersion"
+                    };
+                    Some(HeaderStatement::Uint { name, value })
+                }
+                _ => {
+                    diagnostics.push(invalid(root.name, "expected an unsigned integer",
+                                             attribute.span));
+                    None
+                }
+            },
+            name if name == "xmlns" || name.starts_with("xmlns:") => None,
+            _ => {
+                diagnostics.push(unsupported_attribute(root.name, attribute));
+                None
+            }
+        };
+        header.extend(statement.map(|statement| Spanned::new(statement, attribute.span)));
+    }
+
+    let mut imported = Vec::new();
+    for node in &root.children {
+        if node.name == "element" {
+            imported.extend(import_element(node, diagnostics));
+        } else {
+            diagnostics.push(unsupported_node(root.name, node));
+        }
+    }
+
+    // Elements whose parent isn't defined can't be nested inside it, so they name it instead.
+    let paths: Vec<String> = imported.iter().map(|imported| imported.path.clone()).collect();
+    for imported in &mut imported {
+        let is_orphan = match imported.parent {
+            Some((ref path, _)) => !paths.contains(path),
+            None => false,
+        };
+        if is_orphan {
+            let (_, name) = imported.parent.take().unwrap();
+            imported.element.node.parent.get_or_insert_with(Vec::new).insert(0, name);
+        }
+    }
+    let mut imported = imported.into_iter().map(Some).collect();
+    Dtd::new(header, Vec::new(), nest(&mut imported, None))
+}
+
+// An `<element>`, and where in the schema's tree it goes.
+struct Imported<'a> {
+    element: Spanned<Element<'a>>,
+    // The names in its path, joined with backslashes and without recursion markers.
+    path: String,
+    // The path and name of its parent. `None` for top-level and global elements.
+    parent: Option<(String, &'a str)>,
+}
+
+// Takes the elements whose parent has the path `parent` out of `imported`, along with their
+// descendants, nested as they go.
+fn nest<'a>(imported: &mut Vec<Option<Imported<'a>>>, parent: Option<&str>)
+    -> Vec<Spanned<Element<'a>>>
+{
+    let mut elements = Vec::new();
+    for idx in 0..imported.len() {
+        let is_child = imported[idx].as_ref().is_some_and(|imported| {
+            imported.parent.as_ref().map(|(path, _)| path.as_str()) == parent
+        });
+        if is_child {
+            let Imported { mut element, path, .. } = imported[idx].take().unwrap();
+            element.node.children = nest(imported, Some(&path));
+            elements.push(element);
+        }
+    }
+    elements
+}
+
+fn import_element<'a>(node: &Node<'a>, diagnostics: &mut Diagnostics) -> Option<Imported<'a>> {
+    let required = |diagnostics: &mut Diagnostics, subject: &str, name: &str| {
+        let attribute = node.attribute(name);
+        if attribute.is_none() {
+            diagnostics.push(invalid(subject, format!("`<element>` has no `{}`", name),
+                                     node.span));
+        }
+        attribute
+    };
+
+    let name_attribute = required(diagnostics, "element", "name")?;
+    let name = match name_attribute.value {
+        Cow::Borrowed(name) if is_name(name) => name,
+        _ => {
+            diagnostics.push(unsupported(&*name_attribute.value,
+                                         "isn't a valid EDTD name, so the element was left out",
+                                         name_attribute.span));
+            return None;
+        }
+    };
+    let id_attribute = required(diagnostics, name, "id");
+    let type_attribute = required(diagnostics, name, "type");
+    let path_attribute = required(diagnostics, name, "path");
+
+    let id = id_attribute.and_then(|attribute| {
+        let id = parse_id(&attribute.value);
+        if id.is_none() {
+            diagnostics.push(invalid(name, "expected an id like `0x1A45DFA3`", attribute.span));
+        }
+        id
+    });
+    let type_ = type_attribute.and_then(|attribute| {
+        let type_ = parse_type(&attribute.value);
+        if type_.is_none() {
+            diagnostics.push(invalid(name, format!("unknown type `{}`", attribute.value),
+                                     attribute.span));
+        }
+        type_
+    });
+    let path = path_attribute.and_then(|attribute| {
+        let path = match attribute.value {
+            Cow::Borrowed(path) => Path::parse(path),
+            Cow::Owned(_) => None,
+        };
+        match path {
+            Some(ref path) if path.name() != name => {
+                diagnostics.push(invalid(name, format!("the path ends in `{}`", path.name()),
+                                         attribute.span));
+                None
+            }
+            Some(path) => Some(path),
+            None => {
+                diagnostics.push(invalid(name, "expected a path like `\\Segment\\Info`",
+                                         attribute.span));
+                None
+            }
+        }
+    });
+    let (id, type_, path) = (id?, type_?, path?);
+
+    let mut element = Element::new(name, id, type_);
+    for attribute in &node.attributes {
+        match attribute.name {
+            "name" | "id" | "type" | "path" | "minOccurs" | "maxOccurs" => {}
+            "default" => match parse_default(type_, &attribute.value) {
+                Ok(default) => element.default = Some(Spanned::new(default, attribute.span)),
+                Err(problem) => diagnostics.push(problem.diagnostic(name, attribute.span)),
+            },
+            "range" => match parse_range(type_, &attribute.value) {
+                Ok(range) => element.range = Some(Spanned::new(range, attribute.span)),
+                Err(problem) => diagnostics.push(problem.diagnostic(name, attribute.span)),
+            },
+            "length" | "size" => match parse_uint_range(&attribute.value) {
+                Ok(size) => element.size = Some(SizeList(Cow::Owned(vec![size]))),
+                Err(problem) => diagnostics.push(problem.diagnostic(name, attribute.span)),
+            },
+            "unknownsizeallowed" | "recursive" => match parse_bool(&attribute.value) {
+                Some(value) if attribute.name == "recursive" => if value {
+                    element.parent = Some(vec![name]);
+                },
+                Some(value) => element.unknown_size_allowed = Some(value),
+                None => diagnostics.push(invalid(name, "expected `0` or `1`", attribute.span)),
+            },
+            "minver" | "maxver" => match parse_int(&attribute.value).map(u64::try_from) {
+                Some(Ok(version)) if attribute.name == "minver" => element.minver = Some(version),
+                Some(Ok(version)) => element.maxver = Some(version),
+                _ => diagnostics.push(invalid(name, "expected an unsigned integer",
+                                              attribute.span)),
+            },
+            _ => diagnostics.push(unsupported_attribute(name, attribute)),
+        }
+    }
+    element.cardinality = import_cardinality(node, name, diagnostics);
+    for child in &node.children {
+        match child.name {
+            "documentation" | "implementation_note" | "extension" => {}
+            _ => diagnostics.push(unsupported_node(name, child)),
+        }
+    }
+
+    let parent = match path.global {
+        Some(global) => {
+            if path.names.len() > 1 {
+                diagnostics.push(unsupported(name, "global elements can't be limited to inside \
+                                                   particular elements, so it may be anywhere \
+                                                   at its levels",
+                                             path_attribute.unwrap().span));
+            }
+            let start = (path.names.len() - 1) as u64 + global.start;
+            element.level = Some(match global.end {
+                Some(end) => Level::Bounded { start, end: (path.names.len() - 1) as u64 + end },
+                None => Level::Open { start },
+            });
+            None
+        }
+        None if path.names.len() > 1 => {
+            let parents = &path.names[..path.names.len() - 1];
+            Some((parents.join("\\"), parents[parents.len() - 1]))
+        }
+        None => None,
+    };
+    Some(Imported {
+        element: Spanned::new(element, node.span),
+        path: path.names.join("\\"),
+        parent,
+    })
+}
+
+fn import_cardinality(node: &Node, name: &str, diagnostics: &mut Diagnostics)
+    -> Option<Cardinality>
+{
+    let mut occurs = |attribute: Option<&Attribute>| match attribute {
+        Some(attribute) => match parse_int(&attribute.value) {
+            Some(value) if value >= 0 => Ok(Some(value)),
+            _ => {
+                diagnostics.push(invalid(name, "expected an unsigned integer", attribute.span));
+                Err(())
+            }
+        },
+        None => Ok(None),
+    };
+    let min_occurs = occurs(node.attribute("minOccurs")).ok()?;
+    let max_occurs = occurs(node.attribute("maxOccurs")).ok()?;
+    if min_occurs.is_none() && max_occurs.is_none() {
+        return None;
+    }
+
+    let (cardinality, symbol) = match (min_occurs.unwrap_or(0), max_occurs) {
+        (0, Some(1)) => (Cardinality::ZeroOrOne, "?"),
+        (0, _) => (Cardinality::ZeroOrMany, "*"),
+        (_, Some(1)) => (Cardinality::ExactlyOne, "1"),
+        _ => (Cardinality::OneOrMany, "+"),
+    };
+    let is_exact = min_occurs.unwrap_or(0) <= 1 && matches!(max_occurs, None | Some(1));
+    if !is_exact {
+        let attribute = node.attribute("maxOccurs").or_else(|| node.attribute("minOccurs"));
+        diagnostics.push(unsupported(name, format!("the number of occurrences can't be given \
+                                                    exactly, so `card:{};` was used",
+                                                   symbol),
+                                     attribute.unwrap().span));
+    }
+    Some(cardinality)
+}
+
+// An element's path, like `\Segment\Tags\Tag\+SimpleTag` or `\(1-\)Void`.
+#[derive(Debug, PartialEq)]
+struct Path<'a> {
+    // The names of the element's ancestors, outermost first, then its own.
+    names: Vec<&'a str>,
+    // For global elements, how many more levels than its named ancestors it may be nested.
+    global: Option<GlobalLevels>,
+}
+
+#[derive(Debug, PartialEq)]
+struct GlobalLevels {
+    start: u64,
+    end: Option<u64>,
+}
+
+impl<'a> Path<'a> {
+    fn parse(path: &'a str) -> Option<Self> {
+        let mut rest = path.strip_prefix('\\')?;
+        let mut names = Vec::new();
+        let mut global = None;
+        loop {
+            // The global placeholder is only allowed just before the element's own name.
+            if rest.starts_with('(') && global.is_none() {
+                let end = rest.find("\\)")?;
+                let (start, end_bound) = rest[1..end].split_once('-')?;
+                global = Some(GlobalLevels {
+                    start: if start.is_empty() { 0 } else { start.parse().ok()? },
+                    end: if end_bound.is_empty() { None } else { Some(end_bound.parse().ok()?) },
+                });
+                rest = &rest[end + 2..];
+                if rest.contains('\\') {
+                    return None;
+                }
+            }
+
+            let end = rest.find('\\').unwrap_or(rest.len());
+            // A `+` marks the element as recursive, which the `recursive` attribute also says.
+            let name = rest[..end].trim_start_matches('+');
+            if name.is_empty() {
+                return None;
+            }
+            names.push(name);
+            if end == rest.len() {
+                return Some(Path { names, global });
+            }
+            rest = &rest[end + 1..];
+        }
+    }
+
+    fn name(&self) -> &'a str {
+        self.names[self.names.len() - 1]
+    }
+}
+
+// What's wrong with the value of an attribute.
+enum Problem {
+    // It isn't valid.
+    Invalid(String),
+    // It's valid, but has no equivalent in an EDTD.
+    Unsupported(String),
+}
+
+impl Problem {
+    fn diagnostic(self, subject: &str, span: Span) -> ValidationDiagnostic {
+        match self {
+            Problem::Invalid(message) => invalid(subject, message, span),
+            Problem::Unsupported(message) => unsupported(subject, message, span),
+        }
+    }
+}
+
+fn parse_default<'a>(type_: Type, value: &str) -> Result<Property<'a>, Problem> {
+    let expected = |what: &str| Problem::Invalid(format!("expected {}", what));
+    match type_ {
+        Type::Int => parse_int(value)
+            .and_then(|value| i64::try_from(value).ok())
+            .map(Property::IntDefault)
+            .ok_or_else(|| expected("a signed integer")),
+        Type::Uint => parse_int(value)
+            .and_then(|value| u64::try_from(value).ok())
+            .map(Property::UintDefault)
+            .ok_or_else(|| expected("an unsigned integer")),
+        Type::Float => parse_float(value)
+            .map(Property::FloatDefault)
+            .ok_or_else(|| expected("a number")),
+        // Dates are given as nanoseconds since the start of the millennium.
+        Type::Date => parse_int(value)
+            .and_then(|value| i64::try_from(value).ok())
+            .map(|value| Property::DateDefault(date::from_nanos(value)))
+            .ok_or_else(|| expected("a number of nanoseconds")),
+        Type::String => Ok(Property::StringDefault(Cow::Owned(value.to_string()))),
+        Type::Binary => parse_hex(value)
+            .map(|value| Property::BinaryDefault(Cow::Owned(value)))
+            .ok_or_else(|| expected("hexadecimal bytes")),
+        Type::Container | Type::Name(_) => {
+            Err(Problem::Unsupported("master elements can't have a default".to_string()))
+        }
+    }
+}
+
+fn parse_range<'a>(type_: Type, value: &str) -> Result<Property<'a>, Problem> {
+    match type_ {
+        Type::Int | Type::Date => {
+            let (start, end) = parse_int_bounds(value)?;
+            let (start, end) = (fit::<i64>(start, "signed")?, fit::<i64>(end, "signed")?);
+            if type_ == Type::Int {
+                Ok(Property::IntRange(vec![match (start, end) {
+                    (Some(start), Some(end)) if start == end => IntRangeItem::Single(start),
+                    (Some(start), Some(end)) => IntRangeItem::Bounded { start, end },
+                    (Some(start), None) => IntRangeItem::From { start },
+                    (None, end) => IntRangeItem::To { end: end.unwrap() },
+                }]))
+            } else {
+                let date = date::from_nanos;
+                Ok(Property::DateRange(vec![match (start.map(date), end.map(date)) {
+                    (Some(start), Some(end)) => DateRangeItem::Bounded { start, end },
+                    (Some(start), None) => DateRangeItem::From { start },
+                    (None, end) => DateRangeItem::To { end: end.unwrap() },
+                }]))
+            }
+        }
+        Type::Uint | Type::String | Type::Binary => {
+            let range = Property::UintRange(vec![parse_uint_range(value)?]);
+            range.coerce(type_).map_err(|_| {
+                Problem::Invalid(format!("expected values which fit in a {}", type_.name()))
+            })
+        }
+        Type::Float => {
+            let bounds = parse_bounds(value, parse_float)
+                .ok_or_else(|| Problem::Invalid("expected a range of numbers".to_string()))?;
+            Ok(Property::FloatRange(vec![match bounds {
+                (Some((start, include_start)), Some((end, include_end))) => {
+                    FloatRangeItem::Bounded { start, include_start, end, include_end }
+                }
+                (Some((start, include_start)), None) => {
+                    FloatRangeItem::From { start, include_start }
+                }
+                (None, end) => {
+                    let (end, include_end) = end.unwrap();
+                    FloatRangeItem::To { end, include_end }
+                }
+            }]))
+        }
+        Type::Container | Type::Name(_) => {
+            Err(Problem::Unsupported("master elements can't have a range".to_string()))
+        }
+    }
+}
+
+fn parse_uint_range(value: &str) -> Result<UintRangeItem, Problem> {
+    // Ranges of unsigned integers are the only ones which can leave out a single value.
+    if value.trim() == "not 0" {
+        return Ok(UintRangeItem::From { start: 1 });
+    }
+    let (start, end) = parse_int_bounds(value)?;
+    let (start, end) = (fit::<u64>(start, "unsigned")?, fit::<u64>(end, "unsigned")?);
+    Ok(match (start.unwrap_or(0), end) {
+        (start, Some(end)) if start == end => UintRangeItem::Single(start),
+        (start, Some(end)) => UintRangeItem::Bounded { start, end },
+        (start, None) => UintRangeItem::From { start },
+    })
+}
+
+// Converts a bound of a range of `kind` integers to their type.
+fn fit<T: TryFrom<i128>>(bound: Option<i128>, kind: &str) -> Result<Option<T>, Problem> {
+    bound.map(T::try_from)
+        .transpose()
+        .map_err(|_| Problem::Invalid(format!("expected {} integers", kind)))
+}
+
+// The inclusive bounds of a range of integers.
+fn parse_int_bounds(value: &str) -> Result<(Option<i128>, Option<i128>), Problem> {
+    if value.trim() == "not 0" {
+        return Err(Problem::Unsupported("only `uinteger` ranges can be `not 0`".to_string()));
+    }
+    let (start, end) = parse_bounds(value, parse_int)
+        .ok_or_else(|| Problem::Invalid("expected a range of integers".to_string()))?;
+    let start = start.map(|(start, inclusive)| if inclusive { start } else { start + 1 });
+    let end = end.map(|(end, inclusive)| if inclusive { end } else { end - 1 });
+    match (start, end) {
+        (Some(start), Some(end)) if start > end => {
+            Err(Problem::Invalid("the range is empty".to_string()))
+        }
+        bounds => Ok(bounds),
+    }
+}
+
+// The lower and upper bounds of a range, if it has them, and whether each is included.
+type Bounds<T> = (Option<(T, bool)>, Option<(T, bool)>);
+
+// A range is a single value, two joined by a hyphen, or a lower or upper bound like `>= 1`, or
+// both separated by a comma.
+fn parse_bounds<T: Copy, F>(value: &str, parse: F) -> Option<Bounds<T>>
+    where F: Fn(&str) -> Option<T>
+{
+    let (mut start, mut end) = (None, None);
+    for part in value.split(',') {
+        let part = part.trim();
+        let (bound, text, inclusive) = if let Some(text) = part.strip_prefix(">=") {
+            (&mut start, text, true)
+        } else if let Some(text) = part.strip_prefix('>') {
+            (&mut start, text, false)
+        } else if let Some(text) = part.strip_prefix("<=") {
+            (&mut end, text, true)
+        } else if let Some(text) = part.strip_prefix('<') {
+            (&mut end, text, false)
+        } else if start.is_none() && end.is_none() && !value.contains(',') {
+            // Either a single value, or two joined by a hyphen. Values may have signs of their
+            // own, so each hyphen is tried in turn.
+            if let Some(value) = parse(part) {
+                return Some((Some((value, true)), Some((value, true))));
+            }
+            return part.match_indices('-').filter_map(|(idx, _)| {
+                Some((Some((parse(&part[..idx])?, true)), Some((parse(&part[idx + 1..])?, true))))
+            }).next();
+        } else {
+            return None;
+        };
+        if bound.is_some() {
+            return None;
+        }
+        *bound = Some((parse(text.trim())?, inclusive));
+    }
+    Some((start, end))
+}
+
+// A decimal or hexadecimal integer.
+fn parse_int(value: &str) -> Option<i128> {
+    let (negative, digits) = split_sign(value.trim());
+    let magnitude = if let Some(digits) = strip_hex_prefix(digits) {
+        if !digits.chars().all(|c| c.is_ascii_hexdigit()) {
+            return None;
+        }
+        i128::from_str_radix(digits, 16).ok()?
+    } else {
+        if !digits.chars().all(|c| c.is_ascii_digit()) {
+            return None;
+        }
+        digits.parse().ok()?
+    };
+    Some(if negative { -magnitude } else { magnitude })
+}
+
+// A decimal number, or a hexadecimal one like `0x1.8p+1` as C writes them.
+fn parse_float(value: &str) -> Option<f64> {
+    let value = value.trim();
+    let (negative, digits) = split_sign(value);
+    let digits = match strip_hex_prefix(digits) {
+        Some(digits) => digits,
+        None => {
+            let is_number = digits.chars().all(|c| c.is_ascii_digit() || "+-.eE".contains(c));
+            return if is_number { value.parse().ok() } else { None };
+        }
+    };
+    let (mantissa, exponent) = match digits.find(['p', 'P']) {
+        Some(idx) => (&digits[..idx], digits[idx + 1..].parse::<i32>().ok()?),
+        None => (digits, 0),
+    };
+    let (whole, fraction) = match mantissa.find('.') {
+        Some(idx) => (&mantissa[..idx], &mantissa[idx + 1..]),
+        None => (mantissa, ""),
+    };
+    if whole.is_empty() && fraction.is_empty() {
+        return None;
+    }
+    let mut value = 0.0;
+    for c in whole.chars().chain(fraction.chars()) {
+        value = value * 16.0 + f64::from(c.to_digit(16)?);
+    }
+    let value = value * 2f64.powi(exponent - 4 * fraction.len() as i32);
+    Some(if negative { -value } else { value })
+}
+
+fn parse_id(value: &str) -> Option<u32> {
+    u32::from_str_radix(strip_hex_prefix(value.trim())?, 16).ok().filter(|&id| id != 0)
+}
+
+fn parse_hex(value: &str) -> Option<Vec<u8>> {
+    let value = value.trim();
+    let digits = strip_hex_prefix(value).unwrap_or(value);
+    if !digits.len().is_multiple_of(2) || !digits.is_ascii() {
+        return None;
+    }
+    (0..digits.len()).step_by(2).map(|idx| u8::from_str_radix(&digits[idx..idx + 2], 16).ok())
+        .collect()
+}
+
+fn split_sign(value: &str) -> (bool, &str) {
+    match value.strip_prefix('-') {
+        Some(rest) => (true, rest),
+        None => (false, value),
+    }
+}
+
+fn strip_hex_prefix(value: &str) -> Option<&str> {
+    value.strip_prefix("0x").or_else(|| value.strip_prefix("0X"))
+}
+
+fn parse_bool(value: &str) -> Option<bool> {
+    match value.trim() {
+        "0" => Some(false),
+        "1" => Some(true),
+        _ => None,
+    }
+}
+
+fn parse_type(value: &str) -> Option<Type<'static>> {
+    match value {
+        "integer" => Some(Type::Int),
+        "uinteger" => Some(Type::Uint),
+        "float" => Some(Type::Float),
+        // EDTD strings are UTF-8, which ASCII strings are too.
+        "string" | "utf-8" => Some(Type::String),
+        "date" => Some(Type::Date),
+        "binary" => Some(Type::Binary),
+        "master" => Some(Type::Container),
+        _ => None,
+    }
+}
+
+// Whether an EDTD could refer to an element by `name`.
+fn is_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars.next().is_some_and(|c| c.is_ascii_alphabetic() || c == '_') &&
+        chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+fn invalid<S, M>(subject: S, message: M, span: Span) -> ValidationDiagnostic
+    where S: Into<String>,
+          M: Into<String>
+{
+    ValidationDiagnostic::error(ErrorCode::InvalidSchemaElement, subject, message).with_span(span)
+}
+
+fn unsupported<S, M>(subject: S, message: M, span: Span) -> ValidationDiagnostic
+    where S: Into<String>,
+          M: Into<String>
+{
+    ValidationDiagnostic::warning(ErrorCode::UnsupportedSchemaFeature, subject, message)
+        .with_span(span)
+}
+
+fn unsupported_attribute(subject: &str, attribute: &Attribute) -> ValidationDiagnostic {
+    unsupported(subject, format!("`{}` isn't supported, and was ignored", attribute.name),
+                attribute.span)
+}
+
+fn unsupported_node(subject: &str, node: &Node) -> ValidationDiagnostic {
+    unsupported(subject, format!("`<{}>` isn't supported, and was ignored", node.name), node.span)
+}
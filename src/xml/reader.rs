@@ -0,0 +1,285 @@
+//! A reader for the subset of XML that schemas are written in: elements and their attributes.
+//! Text, comments, processing instructions, CDATA sections and the document type declaration are
+//! skipped over, and only the five predefined entities and character references are understood.
+
+use std::borrow::Cow;
+use std::char;
+
+use Span;
+
+// An element of the document, and the elements inside it.
+#[derive(Debug)]
+pub struct Node<'a> {
+    pub name: &'a str,
+    pub attributes: Vec<Attribute<'a>>,
+    pub children: Vec<Node<'a>>,
+    // The start tag, from its `<` to its `>`.
+    pub span: Span,
+}
+
+impl<'a> Node<'a> {
+    pub fn attribute(&self, name: &str) -> Option<&Attribute<'a>> {
+        self.attributes.iter().find(|attribute| attribute.name == name)
+    }
+}
+
+#[derive(Debug)]
+pub struct Attribute<'a> {
+    pub name: &'a str,
+    // With references replaced by the characters they stand for. Borrowed if there were none.
+    pub value: Cow<'a, str>,
+    // From the start of the name to the closing quote.
+    pub span: Span,
+}
+
+// Where a document stops being well-formed, and what's wrong there.
+#[derive(Debug)]
+pub struct XmlError {
+    pub offset: usize,
+    pub message: String,
+}
+
+type Result<T> = ::std::result::Result<T, XmlError>;
+
+// Reads the root element of `input`.
+pub fn parse<'a>(input: &'a str) -> Result<Node<'a>> {
+    let mut reader = Reader { input, pos: 0 };
+    reader.skip_misc()?;
+    let root = reader.element()?;
+    reader.skip_misc()?;
+    if reader.pos < input.len() {
+        return Err(reader.error("expected nothing after the root element"));
+    }
+    Ok(root)
+}
+
+struct Reader<'a> {
+    input: &'a str,
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn rest(&self) -> &'a str {
+        &self.input[self.pos..]
+    }
+
+    fn error<M: Into<String>>(&self, message: M) -> XmlError {
+        XmlError { offset: self.pos, message: message.into() }
+    }
+
+    fn eat(&mut self, prefix: &str) -> bool {
+        if self.rest().starts_with(prefix) {
+            self.pos += prefix.len();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, prefix: &str) -> Result<()> {
+        if self.eat(prefix) {
+            Ok(())
+        } else {
+            Err(self.error(format!("expected `{}`", prefix)))
+        }
+    }
+
+    fn skip_whitespace(&mut self) {
+        let rest = self.rest();
+        self.pos += rest.len() - rest.trim_start_matches(is_whitespace).len();
+    }
+
+    // Skips everything up to and including `end`.
+    fn skip_past(&mut self, end: &str) -> Result<()> {
+        match self.rest().find(end) {
+            Some(idx) => {
+                self.pos += idx + end.len();
+                Ok(())
+            }
+            None => Err(self.error(format!("expected `{}` before the end of the input", end))),
+        }
+    }
+
+    // Skips whitespace, comments, processing instructions (including the XML declaration) and
+    // the document type declaration, which may come before or after the root element.
+    fn skip_misc(&mut self) -> Result<()> {
+        loop {
+            self.skip_whitespace();
+            if self.eat("<!--") {
+                self.skip_past("-->")?;
+            } else if self.eat("<?") {
+                self.skip_past("?>")?;
+            } else if self.eat("<!DOCTYPE") {
+                self.skip_doctype()?;
+            } else {
+                return Ok(());
+            }
+        }
+    }
+
+    // The declaration may have an internal subset in square brackets, which can contain `>`.
+    fn skip_doctype(&mut self) -> Result<()> {
+        let mut depth = 0;
+        for (idx, c) in self.rest().char_indices() {
+            match c {
+                '[' => depth += 1,
+                ']' => depth -= 1,
+                '>' if depth == 0 => {
+                    self.pos += idx + 1;
+                    return Ok(());
+                }
+                _ => {}
+            }
+        }
+        Err(self.error("expected `>` before the end of the input"))
+    }
+
+    fn name(&mut self) -> Result<&'a str> {
+        let rest = self.rest();
+        let len = rest.len() - rest.trim_start_matches(is_name_char).len();
+        if len == 0 || rest.starts_with(|c: char| c == '-' || c == '.' || c.is_ascii_digit()) {
+            return Err(self.error("expected a name"));
+        }
+        self.pos += len;
+        Ok(&rest[..len])
+    }
+
+    fn element(&mut self) -> Result<Node<'a>> {
+        let start = self.pos;
+        self.expect("<")?;
+        let name = self.name()?;
+        let mut attributes = Vec::new();
+        loop {
+            let before = self.pos;
+            self.skip_whitespace();
+            if self.eat("/>") {
+                let span = Span { start, end: self.pos };
+                return Ok(Node { name, attributes, children: Vec::new(), span });
+            }
+            if self.eat(">") {
+                break;
+            }
+            if self.pos == before {
+                return Err(self.error("expected whitespace, `>` or `/>`"));
+            }
+            let attribute = self.attribute()?;
+            if attributes.iter().any(|other: &Attribute| other.name == attribute.name) {
+                return Err(XmlError {
+                    offset: attribute.span.start,
+                    message: format!("`{}` has more than one `{}` attribute", name, attribute.name),
+                });
+            }
+            attributes.push(attribute);
+        }
+        let span = Span { start, end: self.pos };
+
+        let mut children = Vec::new();
+        loop {
+            self.skip_past_text()?;
+            if self.eat("<!--") {
+                self.skip_past("-->")?;
+            } else if self.eat("<![CDATA[") {
+                self.skip_past("]]>")?;
+            } else if self.eat("<?") {
+                self.skip_past("?>")?;
+            } else if self.eat("</") {
+                let end_pos = self.pos;
+                if self.name()? != name {
+                    return Err(XmlError {
+                        offset: end_pos,
+                        message: format!("expected the end tag of `{}`", name),
+                    });
+                }
+                self.skip_whitespace();
+                self.expect(">")?;
+                return Ok(Node { name, attributes, children, span });
+            } else {
+                children.push(self.element()?);
+            }
+        }
+    }
+
+    // Skips character data, making sure any references in it are well-formed.
+    fn skip_past_text(&mut self) -> Result<()> {
+        let len = match self.rest().find('<') {
+            Some(len) => len,
+            None => {
+                self.pos = self.input.len();
+                return Err(self.error("expected an end tag before the end of the input"));
+            }
+        };
+        let text = &self.input[self.pos..self.pos + len];
+        unescape(text, self.pos)?;
+        self.pos += len;
+        Ok(())
+    }
+
+    fn attribute(&mut self) -> Result<Attribute<'a>> {
+        let start = self.pos;
+        let name = self.name()?;
+        self.skip_whitespace();
+        self.expect("=")?;
+        self.skip_whitespace();
+        let quote = match self.rest().chars().next() {
+            Some(quote @ '"') | Some(quote @ '\'') => quote,
+            _ => return Err(self.error("expected a quoted value")),
+        };
+        self.pos += 1;
+        let len = match self.rest().find([quote, '<']) {
+            Some(len) if self.rest()[len..].starts_with(quote) => len,
+            Some(len) => {
+                self.pos += len;
+                return Err(self.error("`<` must be written `&lt;` in attribute values"));
+            }
+            None => return Err(self.error(format!("expected `{}`", quote))),
+        };
+        let value = unescape(&self.rest()[..len], self.pos)?;
+        self.pos += len + 1;
+        Ok(Attribute { name, value, span: Span { start, end: self.pos } })
+    }
+}
+
+// Replaces references in `text`, which is at `offset` in the document, with what they refer to.
+fn unescape<'a>(text: &'a str, offset: usize) -> Result<Cow<'a, str>> {
+    if !text.contains('&') {
+        return Ok(Cow::Borrowed(text));
+    }
+
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(idx) = rest.find('&') {
+        out.push_str(&rest[..idx]);
+        let error = || XmlError {
+            offset: offset + (text.len() - rest.len()) + idx,
+            message: "expected an entity or character reference".to_string(),
+        };
+        let end = rest[idx..].find(';').ok_or_else(error)?;
+        let reference = &rest[idx + 1..idx + end];
+        let c = match reference {
+            "lt" => '<',
+            "gt" => '>',
+            "amp" => '&',
+            "quot" => '"',
+            "apos" => '\'',
+            _ if reference.starts_with("#x") => u32::from_str_radix(&reference[2..], 16).ok()
+                .and_then(char::from_u32)
+                .ok_or_else(error)?,
+            _ if reference.starts_with('#') => reference[1..].parse().ok()
+                .and_then(char::from_u32)
+                .ok_or_else(error)?,
+            _ => return Err(error()),
+        };
+        out.push(c);
+        rest = &rest[idx + end + 1..];
+    }
+    out.push_str(rest);
+    Ok(Cow::Owned(out))
+}
+
+fn is_whitespace(c: char) -> bool {
+    c == ' ' || c == '\t' || c == '\n' || c == '\r'
+}
+
+fn is_name_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_' || c == '-' || c == '.' || c == ':'
+}
@@ -0,0 +1,246 @@
+use super::*;
+
+use {Severity, StringRangeItem};
+
+fn find<'d, 'a>(dtd: &'d Dtd<'a>, name: &str) -> &'d Element<'a> {
+    dtd.all_elements().into_iter().find(|element| element.name == name).unwrap()
+}
+
+fn errors(input: &str) -> Vec<(ErrorCode, String, String)> {
+    let diagnostics = Dtd::from_xml_schema(input).unwrap_err();
+    diagnostics.errors()
+        .map(|d| (d.code(), d.subject().to_string(), d.message().to_string()))
+        .collect()
+}
+
+#[test]
+fn test_import() {
+    let input = include_str!("../../tests/xml_schema0");
+    let (dtd, diagnostics) = Dtd::from_xml_schema(input).unwrap();
+
+    assert_eq!(dtd.doc_type(), Some("matroska"));
+    assert_eq!(dtd.header_uint("DocTypeVersion"), Some(4));
+
+    let warnings: Vec<_> = diagnostics.iter()
+        .map(|d| (d.severity(), d.subject(), &input[d.span().unwrap().start..][..8]))
+        .collect();
+    assert_eq!(warnings, vec![
+        (Severity::Warning, "SeekHead", "maxOccur"),
+        (Severity::Warning, "TrackType", "<restric"),
+        (Severity::Warning, "CRC-32", "name=\"CR"),
+    ]);
+    assert!(diagnostics.iter().all(|d| d.code() == ErrorCode::UnsupportedSchemaFeature));
+
+    // Paths become nesting.
+    let names: Vec<_> = dtd.elements.iter().map(|element| element.name).collect();
+    assert_eq!(names, vec!["Segment", "Void"]);
+    let segment = find(&dtd, "Segment");
+    let names: Vec<_> = segment.children.iter().map(|element| element.name).collect();
+    assert_eq!(names, vec!["SeekHead", "Info", "Tracks", "Tags"]);
+    assert_eq!(segment.id, 0x1853_8067);
+    assert_eq!(segment.type_, Type::Container);
+    assert_eq!(segment.cardinality, Some(Cardinality::ExactlyOne));
+    assert_eq!(segment.unknown_size_allowed, Some(true));
+    assert_eq!(&input[dtd.elements[0].span.start..][..17], "<element name=\"Se");
+
+    assert_eq!(find(&dtd, "SeekHead").cardinality, Some(Cardinality::ZeroOrMany));
+    assert_eq!(find(&dtd, "Seek").cardinality, Some(Cardinality::OneOrMany));
+    assert_eq!(find(&dtd, "DateUTC").cardinality, Some(Cardinality::ZeroOrOne));
+    assert_eq!(find(&dtd, "Tags").cardinality, None);
+    assert_eq!(find(&dtd, "SeekID").size,
+               Some(SizeList(Cow::Owned(vec![UintRangeItem::Single(4)]))));
+    assert_eq!(find(&dtd, "Title").type_, Type::String);
+    assert_eq!(find(&dtd, "DateUTC").type_, Type::Date);
+    assert_eq!(find(&dtd, "CodecDelay").minver, Some(4));
+    assert_eq!(find(&dtd, "CodecDelay").maxver, None);
+
+    let scale = find(&dtd, "TimestampScale");
+    assert_eq!(scale.default.as_deref(), Some(&Property::UintDefault(1_000_000)));
+    assert_eq!(scale.range.as_deref(),
+               Some(&Property::UintRange(vec![UintRangeItem::From { start: 1 }])));
+    assert_eq!(find(&dtd, "Duration").range.as_deref(), Some(&Property::FloatRange(vec![
+        FloatRangeItem::From { start: 0.0, include_start: false },
+    ])));
+    assert_eq!(find(&dtd, "TrackType").range.as_deref(), Some(&Property::UintRange(vec![
+        UintRangeItem::Bounded { start: 1, end: 254 },
+    ])));
+    assert_eq!(find(&dtd, "Language").default.as_deref(),
+               Some(&Property::StringDefault("eng".into())));
+
+    // Recursive elements may be inside themselves as well as their parent.
+    let simple_tag = find(&dtd, "SimpleTag");
+    assert_eq!(simple_tag.parent, Some(vec!["SimpleTag"]));
+    assert_eq!(simple_tag.children[0].name, "TagName");
+    assert_eq!(find(&dtd, "Tag").children[0].name, "SimpleTag");
+
+    assert_eq!(find(&dtd, "Void").level, Some(Level::Open { start: 0 }));
+    assert_eq!(find(&dtd, "Void").parent, None);
+
+    assert!(dtd.validate().is_empty());
+}
+
+#[test]
+fn test_orphans() {
+    let input = r#"<EBMLSchema docType="x" version="1">
+        <element name="Info" path="\Segment\Info" id="0x1549A966" type="master"/>
+        <element name="Title" path="\Segment\Info\Title" id="0x7BA9" type="string"/>
+    </EBMLSchema>"#;
+    let (dtd, _) = Dtd::from_xml_schema(input).unwrap();
+    assert_eq!(dtd.elements.len(), 1);
+    assert_eq!(dtd.elements[0].parent, Some(vec!["Segment"]));
+    assert_eq!(dtd.elements[0].children[0].name, "Title");
+}
+
+#[test]
+fn test_errors() {
+    assert_eq!(errors("<EBMLSchema><element></EBMLSchema>"), vec![
+        (ErrorCode::MalformedXml, "XML".to_string(),
+         "expected the end tag of `element`".to_string()),
+    ]);
+    let diagnostics = Dtd::from_xml_schema("<EBMLSchema a='1' a='2'/>").unwrap_err();
+    assert_eq!(diagnostics[0].span(), Some(Span { start: 18, end: 19 }));
+
+    let input = r#"<EBMLSchema docType="x" version="1">
+        <element name="A" path="\A" type="uinteger"/>
+        <element name="B" path="\B" id="0xA1" type="mystery"/>
+        <element name="C" path="\D" id="12" type="uinteger" default="-1"/>
+        <element name="E" path="\E" id="0xA2" type="uinteger" range="5-1" recursive="yes"/>
+    </EBMLSchema>"#;
+    assert_eq!(errors(input), vec![
+        (ErrorCode::InvalidSchemaElement, "A".to_string(), "`<element>` has no `id`".to_string()),
+        (ErrorCode::InvalidSchemaElement, "B".to_string(), "unknown type `mystery`".to_string()),
+        (ErrorCode::InvalidSchemaElement, "C".to_string(), "the path ends in `D`".to_string()),
+        (ErrorCode::InvalidSchemaElement, "C".to_string(),
+         "expected an id like `0x1A45DFA3`".to_string()),
+        (ErrorCode::InvalidSchemaElement, "E".to_string(), "the range is empty".to_string()),
+        (ErrorCode::InvalidSchemaElement, "E".to_string(), "expected `0` or `1`".to_string()),
+    ]);
+}
+
+#[test]
+fn test_paths() {
+    assert_eq!(Path::parse("\\Segment\\Info"), Some(Path {
+        names: vec!["Segment", "Info"],
+        global: None,
+    }));
+    assert_eq!(Path::parse("\\Tag\\+SimpleTag\\+SimpleTag"), Some(Path {
+        names: vec!["Tag", "SimpleTag", "SimpleTag"],
+        global: None,
+    }));
+    assert_eq!(Path::parse("\\(1-\\)CRC"), Some(Path {
+        names: vec!["CRC"],
+        global: Some(GlobalLevels { start: 1, end: None }),
+    }));
+    assert_eq!(Path::parse("\\Segment\\(-2\\)Foo"), Some(Path {
+        names: vec!["Segment", "Foo"],
+        global: Some(GlobalLevels { start: 0, end: Some(2) }),
+    }));
+    assert_eq!(Path::parse("Segment"), None);
+    assert_eq!(Path::parse("\\Segment\\"), None);
+    assert_eq!(Path::parse("\\(1-\\)Segment\\Info"), None);
+    assert_eq!(Path::parse("\\(1\\)Void"), None);
+}
+
+#[test]
+fn test_values() {
+    assert_eq!(parse_float("0x1.8p+1"), Some(3.0));
+    assert_eq!(parse_float("-0x10p-4"), Some(-1.0));
+    assert_eq!(parse_float("1.5e3"), Some(1500.0));
+    assert_eq!(parse_float("0x"), None);
+    assert_eq!(parse_float("inf"), None);
+    assert_eq!(parse_int("0x1F"), Some(31));
+    assert_eq!(parse_int("-12"), Some(-12));
+    assert_eq!(parse_int("1.0"), None);
+
+    assert_eq!(parse_range(Type::Int, "-5--1").ok(), Some(Property::IntRange(vec![
+        IntRangeItem::Bounded { start: -5, end: -1 },
+    ])));
+    assert_eq!(parse_range(Type::Int, "< 0").ok(), Some(Property::IntRange(vec![
+        IntRangeItem::To { end: -1 },
+    ])));
+    assert_eq!(parse_range(Type::Uint, ">=1,<=6").ok(), Some(Property::UintRange(vec![
+        UintRangeItem::Bounded { start: 1, end: 6 },
+    ])));
+    assert_eq!(parse_range(Type::Uint, "<5").ok(), Some(Property::UintRange(vec![
+        UintRangeItem::Bounded { start: 0, end: 4 },
+    ])));
+    assert_eq!(parse_range(Type::Float, "0x0p+0-0x1p+0").ok(), Some(Property::FloatRange(vec![
+        FloatRangeItem::Bounded { start: 0.0, include_start: true, end: 1.0, include_end: true },
+    ])));
+    assert_eq!(parse_range(Type::String, "32-126").ok(), Some(Property::StringRange(vec![
+        StringRangeItem::Bounded { start: 32, end: 126 },
+    ])));
+    assert!(matches!(parse_range(Type::Int, "not 0"), Err(Problem::Unsupported(_))));
+    assert!(matches!(parse_range(Type::Binary, "0-300"), Err(Problem::Invalid(_))));
+    assert!(parse_range(Type::Uint, ">1,>2").is_err());
+    assert!(parse_range(Type::Uint, "1-2,3").is_err());
+
+    assert_eq!(parse_default(Type::Binary, "0x0aFF").ok(),
+               Some(Property::BinaryDefault(vec![0x0A, 0xFF].into())));
+    assert_eq!(parse_default(Type::Date, "0").ok(), Some(Property::DateDefault(date::epoch())));
+}
+
+#[test]
+fn test_round_trip() {
+    let input = include_bytes!("../../tests/dtd8");
+    let dtd = ::parse_dtd(input, &Default::default()).unwrap();
+    let (xml, diagnostics) = dtd.to_xml_schema();
+    assert!(diagnostics.is_empty(), "{:?}", diagnostics);
+    let (imported, diagnostics) = Dtd::from_xml_schema(&xml).unwrap();
+    assert!(diagnostics.is_empty(), "{:?}", diagnostics);
+    assert_eq!(imported, dtd);
+
+    assert!(xml.contains("  <element name=\"Title\" path=\"\\Segment\\Info\\Title\" id=\"0x7BA9\" \
+                          type=\"utf-8\" default=\"Tom &amp; Jerry &lt;3\"/>\n"));
+    assert!(xml.contains(" path=\"\\Segment\\Tags\\Tag\\+SimpleTag\\TagName\""));
+    assert!(xml.contains(" path=\"\\(1-2\\)CRC32\""));
+    assert!(xml.contains(" range=\"&gt;=-4.5,&lt;10.25\""));
+}
+
+#[test]
+fn test_export() {
+    let input = b"declare header { DocType := \"x\"; MaxSize := 8; }
+                  define types { bool := uint [ def:0; range:0..1; ] }
+                  define elements {
+                      // The root.
+                      Root := a1 container [ ordered:yes; ] {
+                          Flag := 81 bool [ variants: Off = 0, On = 1; ]
+                          Both := 82 uint [ parent:Other; range:1,5..; ]
+                      }
+                      Other := a2 container;
+                      Loose := 83 string [ parent:Root, Other; size:1,4; ]
+                  }";
+    let dtd = ::parse_dtd(input, &Default::default()).unwrap();
+    let (xml, diagnostics) = dtd.to_xml_schema();
+    assert_eq!(xml, concat!(
+        "<?xml version=\"1.0\" encoding=\"utf-8\"?>\n",
+        "<EBMLSchema xmlns=\"urn:ietf:rfc:8794\" docType=\"x\">\n",
+        "  <element name=\"Root\" path=\"\\Root\" id=\"0xA1\" type=\"master\">\n",
+        "    <documentation lang=\"en\" purpose=\"definition\">The root.</documentation>\n",
+        "  </element>\n",
+        "  <element name=\"Flag\" path=\"\\Root\\Flag\" id=\"0x81\" type=\"uinteger\" \
+         range=\"0-1\" default=\"0\">\n",
+        "    <restriction>\n",
+        "      <enum value=\"0\" label=\"Off\"/>\n",
+        "      <enum value=\"1\" label=\"On\"/>\n",
+        "    </restriction>\n",
+        "  </element>\n",
+        "  <element name=\"Both\" path=\"\\Root\\Both\" id=\"0x82\" type=\"uinteger\"/>\n",
+        "  <element name=\"Other\" path=\"\\Other\" id=\"0xA2\" type=\"master\"/>\n",
+        "  <element name=\"Loose\" path=\"\\Root\\Loose\" id=\"0x83\" type=\"utf-8\"/>\n",
+        "</EBMLSchema>\n",
+    ));
+
+    let warnings: Vec<_> = diagnostics.iter().map(|d| (d.subject(), d.message())).collect();
+    assert_eq!(warnings, vec![
+        ("MaxSize", "XML schemas have no equivalent of this header statement"),
+        ("bool", "XML schemas can't define types, so elements of this type are written as \
+                  `uinteger`"),
+        ("Root", "XML schemas have no equivalent of `ordered:`, so it was left out"),
+        ("Both", "XML schemas can only give one item in a range, so the range was left out"),
+        ("Both", "elements can only have one parent, so `parent:` was left out"),
+        ("Loose", "XML schemas can only give one item in a size, so the size was left out"),
+        ("Loose", "elements can only have one parent, so only `Root` was kept"),
+    ]);
+    assert!(diagnostics.iter().all(|d| d.is_warning() && d.span().is_some()));
+}
@@ -0,0 +1,64 @@
+//! Runs the `ebml_dtd_check` example, which `cargo test` builds alongside the tests.
+
+use std::env;
+use std::path::PathBuf;
+use std::process::Command;
+
+// Returns the exit code and what was printed.
+fn check(args: &[&str]) -> (i32, String) {
+    // Tests are in `target/<profile>/deps`, and examples in `target/<profile>/examples`.
+    let mut path = env::current_exe().unwrap();
+    path.pop();
+    path.pop();
+    path.push("examples");
+    path.push("ebml_dtd_check");
+
+    let output = Command::new(path)
+        .args(args)
+        .current_dir(PathBuf::from(env!("CARGO_MANIFEST_DIR")))
+        .output()
+        .unwrap();
+    (output.status.code().unwrap(), String::from_utf8(output.stdout).unwrap())
+}
+
+#[test]
+fn test_valid() {
+    let (code, output) = check(&["tests/dtd9"]);
+    assert_eq!(code, 0, "{}", output);
+    assert_eq!(output, "tests/dtd9: 0 errors, 0 warnings\n");
+
+    let (code, output) = check(&["--dump-ast", "tests/dtd0"]);
+    assert_eq!(code, 0);
+    assert!(output.contains("\n{\n  \"header\": [\n"), "{}", output);
+    assert!(output.contains("{\"name\": \"DocType\", \"type\": \"string\""), "{}", output);
+
+    let (code, output) = check(&["--markdown", "tests/dtd9"]);
+    assert_eq!(code, 0);
+    assert!(output.ends_with(include_str!("markdown0")), "{}", output);
+}
+
+#[test]
+fn test_invalid() {
+    let (code, output) = check(&["tests/invalid0"]);
+    assert_eq!(code, 1);
+    let error = "error[V0011]: Name: id 0x7ba9 is already used by Title\n  --> line 10";
+    assert!(output.contains(error), "{}", output);
+    assert!(output.ends_with("tests/invalid0: 1 error, 1 warning\n"), "{}", output);
+
+    // With `--strict`, warnings are errors too.
+    let (code, output) = check(&["--strict", "--json", "tests/invalid0"]);
+    assert_eq!(code, 1);
+    assert!(output.starts_with("[\n  {\"severity\": \"error\", \"code\": \"V0001\", \
+                                \"subject\": \"string\""), "{}", output);
+
+    let (code, output) = check(&["--json", "tests/syntax_error0"]);
+    assert_eq!(code, 1);
+    assert!(output.contains("\"code\": \"P0005\""), "{}", output);
+    assert!(output.contains("\"line\": 6, \"column\": 18}"), "{}", output);
+}
+
+#[test]
+fn test_usage() {
+    assert_eq!(check(&["--bogus"]).0, 2);
+    assert_eq!(check(&["tests/missing"]).0, 2);
+}